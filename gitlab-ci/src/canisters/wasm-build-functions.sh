@@ -16,6 +16,7 @@ wasm_canister_list=(
     ledger-archive-node-canister
     ledger-canister
     ledger-canister_notify-method
+    ledger-index-canister
     lifeline
     mem-utils-test-canister
     memory-test-canister
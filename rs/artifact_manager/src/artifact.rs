@@ -164,3 +164,29 @@ impl ArtifactKind for EcdsaArtifact {
         }
     }
 }
+
+/// The `ArtifactKind` of the evidence of equivocation.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct EquivocationEvidenceArtifact;
+
+/// `EquivocationEvidenceArtifact` implements the `ArtifactKind` trait.
+impl ArtifactKind for EquivocationEvidenceArtifact {
+    const TAG: ArtifactTag = ArtifactTag::EquivocationEvidenceArtifact;
+    type Id = EquivocationEvidenceId;
+    type Message = EquivocationEvidence;
+    type SerializeAs = EquivocationEvidence;
+    type Attribute = EquivocationEvidenceAttribute;
+    type Filter = ();
+
+    /// The function converts an `EquivocationEvidence` into an advert for an
+    /// `EquivocationEvidenceArtifact`.
+    fn message_to_advert(msg: &EquivocationEvidence) -> Advert<EquivocationEvidenceArtifact> {
+        let hash = ic_crypto::crypto_hash(msg);
+        Advert {
+            id: hash.clone(),
+            attribute: EquivocationEvidenceAttribute::from(msg),
+            size: bincode::serialize(msg).unwrap().len(),
+            integrity_hash: hash.get(),
+        }
+    }
+}
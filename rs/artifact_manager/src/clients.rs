@@ -6,13 +6,13 @@ use ic_interfaces::{
     artifact_manager::{AdvertMismatchError, ArtifactAcceptance, ArtifactClient, OnArtifactError},
     artifact_pool::{ArtifactPoolError, ReplicaVersionMismatch, UnvalidatedArtifact},
     certification::{CertificationPool, CertifierGossip},
-    consensus::ConsensusGossip,
+    consensus::{ConsensusGossip, EquivocationEvidenceGossip},
     consensus_pool::{ConsensusPool, ConsensusPoolCache},
     dkg::{DkgGossip, DkgPool},
     ecdsa::{EcdsaGossip, EcdsaPool},
     gossip_pool::{
         CertificationGossipPool, ConsensusGossipPool, DkgGossipPool, EcdsaGossipPool,
-        IngressGossipPool,
+        IngressGossipPool, PeerQuotaChecker,
     },
     ingress_pool::IngressPool,
     time_source::TimeSource,
@@ -34,6 +34,13 @@ use ic_types::{
 use std::convert::{TryFrom, TryInto};
 use std::sync::{Arc, RwLock};
 
+/// The size (in bytes) up to which *Consensus* and certification artifacts are
+/// pushed to peers together with their adverts. This covers shares (e.g.
+/// notarization, finalization, random beacon and certification shares),
+/// which are on the critical path of every round, while larger artifacts like
+/// block proposals are still advertised and requested.
+const SMALL_ARTIFACT_PUSH_SIZE_THRESHOLD: usize = 1024;
+
 /// In order to let the artifact manager manage artifact clients, which can be
 /// parameterized by different artifact types, it has to use trait objects.
 /// Consequently, there has to be a translation between various artifact
@@ -78,6 +85,9 @@ pub(crate) trait ArtifactManagerBackend: Send + Sync {
     /// tag.
     fn get_remaining_quota(&self, tag: artifact::ArtifactTag, peer_id: NodeId) -> Option<usize>;
 
+    /// The method returns the push size threshold for a given artifact tag.
+    fn get_push_size_threshold(&self, tag: artifact::ArtifactTag) -> Option<usize>;
+
     /// The method returns a priority function for a given artifact tag.
     fn get_priority_function(&self, tag: artifact::ArtifactTag) -> Option<ArtifactPriorityFn>;
 
@@ -198,6 +208,15 @@ where
         }
     }
 
+    /// The method returns the client's push size threshold.
+    fn get_push_size_threshold(&self, tag: artifact::ArtifactTag) -> Option<usize> {
+        if tag == Artifact::TAG {
+            self.client.as_ref().get_push_size_threshold()
+        } else {
+            None
+        }
+    }
+
     /// The method returns the priority function.
     fn get_priority_function(&self, tag: artifact::ArtifactTag) -> Option<ArtifactPriorityFn> {
         if tag == Artifact::TAG {
@@ -232,18 +251,23 @@ pub struct ConsensusClient<Pool> {
     /// The *Consensus* pool, protected by a read-write lock and automatic
     /// reference counting.
     consensus_pool: Arc<RwLock<Pool>>,
+    /// Checks the quota of unvalidated artifacts per peer without the lock of
+    /// the *Consensus* pool.
+    peer_quota_checker: Arc<dyn PeerQuotaChecker>,
     /// The `ConsensusGossip` client.
     client: Arc<dyn ConsensusGossip>,
 }
 
-impl<Pool> ConsensusClient<Pool> {
+impl<Pool: ConsensusGossipPool> ConsensusClient<Pool> {
     /// The constructor creates a `ConsensusClient` instance.
     pub fn new<T: ConsensusGossip + 'static>(
         consensus_pool: Arc<RwLock<Pool>>,
         consensus: T,
     ) -> Self {
+        let peer_quota_checker = consensus_pool.read().unwrap().peer_quota_checker();
         Self {
             consensus_pool,
+            peer_quota_checker,
             client: Arc::new(consensus),
         }
     }
@@ -268,16 +292,18 @@ impl<Pool: ConsensusPool + ConsensusGossipPool + Send + Sync> ArtifactClient<Con
     for ConsensusClient<Pool>
 {
     /// The method checks if the protocol version in the *Consensus* message is
-    /// correct.
+    /// correct, and if the peer has not used up its quota of unvalidated
+    /// artifacts.
     ///
-    /// If the version is correct, the message is returned in an
+    /// If both checks pass, the message is returned in an
     /// `ArtifactAcceptance` enum.
     fn check_artifact_acceptance(
         &self,
         msg: ConsensusMessage,
-        _peer_id: &NodeId,
+        peer_id: &NodeId,
     ) -> Result<ArtifactAcceptance<ConsensusMessage>, ArtifactPoolError> {
         check_protocol_version(&msg)?;
+        self.peer_quota_checker.check_quota(peer_id)?;
         Ok(ArtifactAcceptance::AcceptedForProcessing(msg))
     }
 
@@ -314,6 +340,12 @@ impl<Pool: ConsensusPool + ConsensusGossipPool + Send + Sync> ArtifactClient<Con
             .collect()
     }
 
+    /// The method returns the size up to which *Consensus* artifacts are
+    /// pushed to peers.
+    fn get_push_size_threshold(&self) -> Option<usize> {
+        Some(SMALL_ARTIFACT_PUSH_SIZE_THRESHOLD)
+    }
+
     /// The method returns the priority function.
     fn get_priority_function(
         &self,
@@ -541,6 +573,12 @@ impl<PoolCertification: CertificationPool + CertificationGossipPool + Send + Syn
             .collect()
     }
 
+    /// The method returns the size up to which certification messages are
+    /// pushed to peers.
+    fn get_push_size_threshold(&self) -> Option<usize> {
+        Some(SMALL_ARTIFACT_PUSH_SIZE_THRESHOLD)
+    }
+
     /// The method returns the priority function.
     fn get_priority_function(
         &self,
@@ -666,3 +704,77 @@ impl<Pool: EcdsaPool + EcdsaGossipPool + Send + Sync> ArtifactClient<EcdsaArtifa
         Box::new(SingleChunked::Ecdsa)
     }
 }
+
+/// The client of the evidence of equivocation, which is held by the
+/// *Consensus* pool.
+pub struct EquivocationEvidenceClient<Pool> {
+    consensus_pool: Arc<RwLock<Pool>>,
+    gossip: Arc<dyn EquivocationEvidenceGossip>,
+}
+
+impl<Pool> EquivocationEvidenceClient<Pool> {
+    pub fn new<T: EquivocationEvidenceGossip + 'static>(
+        consensus_pool: Arc<RwLock<Pool>>,
+        gossip: T,
+    ) -> Self {
+        Self {
+            consensus_pool,
+            gossip: Arc::new(gossip),
+        }
+    }
+}
+
+impl<Pool: ConsensusPool + Send + Sync> ArtifactClient<EquivocationEvidenceArtifact>
+    for EquivocationEvidenceClient<Pool>
+{
+    fn check_artifact_acceptance(
+        &self,
+        msg: EquivocationEvidence,
+        _peer_id: &NodeId,
+    ) -> Result<ArtifactAcceptance<EquivocationEvidence>, ArtifactPoolError> {
+        Ok(ArtifactAcceptance::AcceptedForProcessing(msg))
+    }
+
+    fn has_artifact(&self, msg_id: &EquivocationEvidenceId) -> bool {
+        self.get_validated_by_identifier(msg_id).is_some()
+    }
+
+    fn get_validated_by_identifier(
+        &self,
+        msg_id: &EquivocationEvidenceId,
+    ) -> Option<EquivocationEvidence> {
+        self.consensus_pool
+            .read()
+            .unwrap()
+            .equivocation_evidence()
+            .into_iter()
+            .find(|evidence| &ic_crypto::crypto_hash(evidence) == msg_id)
+    }
+
+    fn get_priority_function(
+        &self,
+    ) -> Option<PriorityFn<EquivocationEvidenceId, EquivocationEvidenceAttribute>> {
+        let consensus_pool = &*self.consensus_pool.read().unwrap();
+        Some(self.gossip.get_priority_function(consensus_pool))
+    }
+
+    /// The method returns the adverts of all the recorded evidence, so that a
+    /// resuming peer learns about equivocations that happened while it was
+    /// away.
+    fn get_all_validated_by_filter(
+        &self,
+        _filter: &(),
+    ) -> Vec<Advert<EquivocationEvidenceArtifact>> {
+        self.consensus_pool
+            .read()
+            .unwrap()
+            .equivocation_evidence()
+            .iter()
+            .map(EquivocationEvidenceArtifact::message_to_advert)
+            .collect()
+    }
+
+    fn get_chunk_tracker(&self, _id: &EquivocationEvidenceId) -> Box<dyn Chunkable + Send + Sync> {
+        Box::new(SingleChunked::EquivocationEvidence)
+    }
+}
@@ -142,6 +142,16 @@ impl ArtifactManager for ArtifactManagerImpl {
             .and_then(|client| client.get_remaining_quota(tag, peer_id))
     }
 
+    /// The method returns the size up to which artifacts of a specific client
+    /// that is identified by the given artifact tag are pushed to peers.
+    ///
+    /// See `ArtifactClient::get_push_size_threshold` for more details.
+    fn get_push_size_threshold(&self, tag: artifact::ArtifactTag) -> Option<usize> {
+        self.clients
+            .get(&tag)
+            .and_then(|client| client.get_push_size_threshold(tag))
+    }
+
     /// The method returns the priority function for a specific client that is
     /// identified by the given artifact tag.
     ///
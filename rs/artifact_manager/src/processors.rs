@@ -7,10 +7,13 @@ use ic_interfaces::{
     artifact_pool::UnvalidatedArtifact,
     certification,
     certification::{Certifier, CertifierGossip, MutableCertificationPool},
-    consensus::{Consensus, ConsensusGossip},
+    consensus::{
+        Consensus, ConsensusGossip, EquivocationEvidenceGossip, EquivocationEvidenceValidator,
+    },
     consensus_pool::{ChangeAction as ConsensusAction, ConsensusPoolCache, MutableConsensusPool},
     dkg::{ChangeAction as DkgChangeAction, Dkg, DkgGossip, MutableDkgPool},
     ecdsa::{Ecdsa, EcdsaChangeAction, EcdsaGossip, MutableEcdsaPool},
+    gossip_pool::ConsensusGossipPool,
     ingress_manager::IngressHandler,
     ingress_pool::{
         ChangeAction as IngressAction, IngressPoolObject, IngressPoolSelect, MutableIngressPool,
@@ -23,12 +26,15 @@ use ic_metrics::MetricsRegistry;
 use ic_types::consensus::HasRank;
 use ic_types::{
     artifact::*,
-    consensus::{certification::CertificationMessage, dkg, ConsensusMessage},
+    consensus::{
+        certification::CertificationMessage, dkg, equivocation::EquivocationKind, ConsensusMessage,
+    },
     malicious_flags::MaliciousFlags,
     messages::SignedIngress,
     NodeId, Time,
 };
 use prometheus::{histogram_opts, labels, Histogram, IntCounter};
+use std::collections::BTreeSet;
 use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread::{Builder as ThreadBuilder, JoinHandle};
@@ -262,7 +268,7 @@ pub struct ConsensusProcessor<PoolConsensus, PoolIngress> {
 }
 
 impl<
-        PoolConsensus: MutableConsensusPool + Send + Sync + 'static,
+        PoolConsensus: MutableConsensusPool + ConsensusGossipPool + Send + Sync + 'static,
         PoolIngress: IngressPoolSelect + Send + Sync + 'static,
     > ConsensusProcessor<PoolConsensus, PoolIngress>
 {
@@ -388,6 +394,15 @@ impl<
                 ConsensusAction::RemoveFromUnvalidated(_) => {}
                 ConsensusAction::PurgeValidatedBelow(_) => {}
                 ConsensusAction::PurgeUnvalidatedBelow(_) => {}
+                ConsensusAction::RecordEquivocation(evidence) => {
+                    warn!(
+                        self.log,
+                        "Recorded evidence of equivocation by {} as {} at height {}",
+                        evidence.signer(),
+                        evidence.kind(),
+                        evidence.height()
+                    );
+                }
                 ConsensusAction::HandleInvalid(artifact, s) => {
                     self.invalidated_artifacts.inc();
                     warn!(self.log, "Invalid artifact {} {:?}", s, artifact);
@@ -866,3 +881,104 @@ impl<PoolEcdsa: MutableEcdsaPool + Send + Sync + 'static> ArtifactProcessor<Ecds
         (adverts, changed)
     }
 }
+
+/// Equivocation evidence `OnStateChange` client. The evidence is held by the
+/// *Consensus* pool, which records both the evidence detected by *Consensus*
+/// and the valid evidence received from peers.
+pub struct EquivocationEvidenceProcessor<PoolConsensus> {
+    consensus_pool: Arc<RwLock<PoolConsensus>>,
+    client: Box<dyn EquivocationEvidenceValidator>,
+    /// The equivocations whose evidence was advertised already.
+    advertised: Mutex<BTreeSet<(NodeId, EquivocationKind)>>,
+}
+
+impl<PoolConsensus: MutableConsensusPool + Send + Sync + 'static>
+    EquivocationEvidenceProcessor<PoolConsensus>
+{
+    pub fn build<
+        C: EquivocationEvidenceValidator + 'static,
+        G: EquivocationEvidenceGossip + 'static,
+        S: Fn(AdvertSendRequest<EquivocationEvidenceArtifact>) + Send + 'static,
+        F: FnOnce() -> (C, G),
+    >(
+        send_advert: S,
+        setup: F,
+        time_source: Arc<SysTimeSource>,
+        consensus_pool: Arc<RwLock<PoolConsensus>>,
+        metrics_registry: MetricsRegistry,
+    ) -> (
+        clients::EquivocationEvidenceClient<PoolConsensus>,
+        ArtifactProcessorManager<EquivocationEvidenceArtifact>,
+    ) {
+        let (validator, gossip) = setup();
+        let client = Self {
+            consensus_pool: consensus_pool.clone(),
+            client: Box::new(validator),
+            advertised: Mutex::new(BTreeSet::new()),
+        };
+        let manager = ArtifactProcessorManager::new(
+            time_source,
+            metrics_registry,
+            BoxOrArcClient::BoxClient(Box::new(client)),
+            send_advert,
+        );
+        (
+            clients::EquivocationEvidenceClient::new(consensus_pool, gossip),
+            manager,
+        )
+    }
+}
+
+impl<PoolConsensus: MutableConsensusPool + Send + Sync + 'static>
+    ArtifactProcessor<EquivocationEvidenceArtifact>
+    for EquivocationEvidenceProcessor<PoolConsensus>
+{
+    /// The method records the valid evidence received from peers, and
+    /// advertises all the recorded evidence that was not advertised yet,
+    /// including the evidence detected by *Consensus*.
+    fn process_changes(
+        &self,
+        time_source: &dyn TimeSource,
+        artifacts: Vec<UnvalidatedArtifact<EquivocationEvidence>>,
+    ) -> (
+        Vec<AdvertSendRequest<EquivocationEvidenceArtifact>>,
+        ProcessingResult,
+    ) {
+        let change_set = if artifacts.is_empty() {
+            Vec::new()
+        } else {
+            let consensus_pool = self.consensus_pool.read().unwrap();
+            let evidence = artifacts
+                .into_iter()
+                .map(|artifact| artifact.message)
+                .collect();
+            self.client.on_state_change(&*consensus_pool, evidence)
+        };
+        let changed = if !change_set.is_empty() {
+            self.consensus_pool
+                .write()
+                .unwrap()
+                .apply_changes(time_source, change_set);
+            ProcessingResult::StateChanged
+        } else {
+            ProcessingResult::StateUnchanged
+        };
+
+        let mut advertised = self.advertised.lock().unwrap();
+        let adverts = self
+            .consensus_pool
+            .read()
+            .unwrap()
+            .equivocation_evidence()
+            .iter()
+            .filter(|evidence| advertised.insert((evidence.signer(), evidence.kind())))
+            .map(|evidence| {
+                EquivocationEvidenceArtifact::message_to_advert_send_request(
+                    evidence,
+                    AdvertClass::Critical,
+                )
+            })
+            .collect();
+        (adverts, changed)
+    }
+}
@@ -7,7 +7,8 @@ use ic_logger::replica_logger::{no_op_logger, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_test_utilities::{
     consensus::{fake::*, make_genesis, MockConsensus},
-    types::ids::subnet_test_id,
+    registry::{setup_registry, SubnetRecordBuilder},
+    types::ids::{node_test_id, subnet_test_id},
 };
 use std::sync::{Arc, RwLock};
 
@@ -59,6 +60,10 @@ fn init_artifact_pools(
             subnet_test_id(0),
             ic_types::consensus::catchup::CUPWithOriginalProtobuf::from_cup(cup),
             config,
+            setup_registry(
+                subnet_test_id(0),
+                vec![(1, SubnetRecordBuilder::from(&[node_test_id(0)]).build())],
+            ),
             registry,
             log,
         ))),
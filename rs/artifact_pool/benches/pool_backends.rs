@@ -0,0 +1,149 @@
+//! This compares the persistent pool backends (LMDB, RocksDB and the
+//! append-only log) when inserting, purging and loading block proposals.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ic_artifact_pool::consensus_pool::ConsensusPoolImpl;
+use ic_config::artifact_pool::{ArtifactPoolConfig, ArtifactPoolTomlConfig};
+use ic_consensus_message::ConsensusMessageHashable;
+use ic_interfaces::consensus_pool::{ChangeAction, ChangeSet, ConsensusPool, MutableConsensusPool};
+use ic_logger::replica_logger::no_op_logger;
+use ic_test_utilities::FastForwardTimeSource;
+use ic_test_utilities::{
+    consensus::{fake::*, make_genesis},
+    types::ids::{node_test_id, subnet_test_id},
+    types::messages::SignedIngressBuilder,
+};
+use ic_types::{
+    batch::{BatchPayload, IngressPayload, SelfValidatingPayload, XNetPayload},
+    consensus::{dkg, Block, BlockProposal, Payload, Rank},
+    Height,
+};
+use tempfile::Builder;
+
+/// The backends to compare, as named in the replica configuration.
+const BACKENDS: [&str; 3] = ["lmdb", "rocksdb", "append_log"];
+
+/// Heights between two purges when inserting.
+const PURGE_INTERVAL: u64 = 100;
+
+// Helper to run the benchmarks below on a fresh pool using the given backend.
+// The persistent pool directory is removed afterwards.
+fn run_test<T>(backend: &str, test: T)
+where
+    T: FnOnce(&mut ConsensusPoolImpl),
+{
+    let tempdir = Builder::new().prefix("persistent-pool").tempdir().unwrap();
+    let mut toml_config = ArtifactPoolTomlConfig::new(tempdir.path().to_path_buf(), None);
+    toml_config.consensus_pool_backend = Some(backend.to_string());
+    let mut consensus_pool = ConsensusPoolImpl::new_from_cup_without_bytes(
+        subnet_test_id(0),
+        make_genesis(ic_types::consensus::dkg::Summary::fake()),
+        ArtifactPoolConfig::from(toml_config),
+        ic_metrics::MetricsRegistry::new(),
+        no_op_logger(),
+    );
+    test(&mut consensus_pool);
+}
+
+/// Creates a block proposal of the given height and rank, with a single
+/// ingress message of `ingress_size` bytes.
+fn make_proposal(parent: &Block, height: Height, rank: u64, ingress_size: usize) -> BlockProposal {
+    let mut block = Block::from_parent(parent);
+    block.height = height;
+    block.rank = Rank(rank);
+    let ingress = IngressPayload::from(vec![SignedIngressBuilder::new()
+        .method_payload(vec![0; ingress_size])
+        .build()]);
+    block.payload = Payload::new(
+        ic_crypto::crypto_hash,
+        (
+            BatchPayload::new(
+                ingress,
+                XNetPayload::default(),
+                SelfValidatingPayload::default(),
+            ),
+            dkg::Dealings::new_empty(parent.payload.as_ref().dkg_interval_start_height()),
+            None,
+        )
+            .into(),
+    );
+    BlockProposal::fake(block, node_test_id(rank))
+}
+
+fn genesis_block(pool: &ConsensusPoolImpl) -> Block {
+    pool.validated()
+        .catch_up_package()
+        .get_by_height(Height::from(0))
+        .next()
+        .unwrap()
+        .content
+        .block
+        .into_inner()
+}
+
+/// Speed test for inserting block proposals at increasing heights, purging
+/// the pool at a fixed interval.
+fn insert_and_purge(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("insert_and_purge");
+    for backend in BACKENDS.iter() {
+        run_test(backend, |pool: &mut ConsensusPoolImpl| {
+            let parent = genesis_block(pool);
+            let time_source = FastForwardTimeSource::new();
+            let mut height = Height::from(1);
+            group.bench_function(*backend, |bench| {
+                bench.iter(|| {
+                    let proposal = make_proposal(&parent, height, 0, 1024);
+                    let mut changeset: ChangeSet =
+                        vec![ChangeAction::AddToValidated(proposal.into_message())];
+                    if height.get() % PURGE_INTERVAL == 0 {
+                        changeset.push(ChangeAction::PurgeValidatedBelow(Height::from(
+                            height.get() - PURGE_INTERVAL,
+                        )));
+                    }
+                    pool.apply_changes(time_source.as_ref(), changeset);
+                    height = height.increment();
+                })
+            });
+        })
+    }
+}
+
+/// Speed test for loading block proposals and their payloads.
+fn load_blocks(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("load_blocks");
+    for backend in BACKENDS.iter() {
+        run_test(backend, |pool: &mut ConsensusPoolImpl| {
+            let parent = genesis_block(pool);
+            let changeset = (0..20)
+                .map(|rank| {
+                    let proposal = make_proposal(&parent, Height::from(1), rank, 128 * 1024);
+                    ChangeAction::AddToValidated(proposal.into_message())
+                })
+                .collect();
+            let time_source = FastForwardTimeSource::new();
+            pool.apply_changes(time_source.as_ref(), changeset);
+            group.bench_function(*backend, |bench| {
+                bench.iter(|| {
+                    pool.validated()
+                        .block_proposal()
+                        .get_all()
+                        .map(|proposal| {
+                            let block: Block = proposal.into();
+                            block
+                                .payload
+                                .as_ref()
+                                .as_data()
+                                .batch
+                                .ingress
+                                .message_count()
+                        })
+                        .sum::<usize>()
+                })
+            });
+        })
+    }
+}
+
+criterion_group!(benches, insert_and_purge, load_blocks);
+
+criterion_main!(benches);
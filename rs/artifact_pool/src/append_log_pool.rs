@@ -0,0 +1,1232 @@
+use crate::consensus_pool::{InitializablePoolSection, PoolSectionOp, PoolSectionOps};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ic_config::artifact_pool::AppendLogConfig;
+use ic_consensus_message::ConsensusMessageHashable;
+use ic_interfaces::{
+    artifact_pool::ValidatedArtifact,
+    consensus_pool::{
+        HeightIndexedPool, HeightRange, OnlyError, PoolSection, ValidatedConsensusArtifact,
+    },
+    crypto::CryptoHashable,
+};
+use ic_logger::{info, warn, ReplicaLogger};
+use ic_protobuf::types::v1 as pb;
+use ic_types::{
+    artifact::ConsensusMessageId,
+    consensus::{
+        catchup::CUPWithOriginalProtobuf,
+        certification::{Certification, CertificationMessage, CertificationShare},
+        BlockProposal, CatchUpPackage, CatchUpPackageShare, ConsensusMessage, ConsensusMessageHash,
+        Finalization, FinalizationShare, HasHeight, Notarization, NotarizationShare, RandomBeacon,
+        RandomBeaconShare, RandomTape, RandomTapeShare,
+    },
+    Height, Time,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::{TryFrom, TryInto};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, ErrorKind, Read, Write};
+use std::marker::PhantomData;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+// Macro that panics when result is not ok, printing the error,
+// and that unwraps the content otherwise.
+macro_rules! check_ok_uw {
+    ($r:expr) => {
+        $r.expect("Fatal error in persistent artifact pool.")
+    };
+}
+
+// Macro that panics when the option is None, and unwraps it
+// otherwise.
+macro_rules! check_not_none_uw {
+    ($o:expr) => {
+        $o.expect("Fatal error in persistent artifact pool. Unexpected None.")
+    };
+}
+
+/// Name of the file that persists the purge watermark.
+const WATERMARK_FILE: &str = "watermark";
+/// File extension of log segments.
+const SEGMENT_EXTENSION: &str = "log";
+
+/// A persistent implementation of PoolSection on an embedded, append-only
+/// segmented log. It is pure Rust and depends on nothing but the file system.
+///
+/// Artifacts are appended to log segments, each of which covers a fixed range
+/// of `segment_height_span` heights, and is named after the first height of
+/// that range:
+///
+/// ```text
+/// <db_path>/00000000000000000000.log   heights [0, span[
+/// <db_path>/00000000000000000500.log   heights [span, 2 * span[
+/// <db_path>/watermark                  purge height (u64, big endian)
+/// ```
+///
+/// A segment is a sequence of frames, each recording either the insertion or
+/// the removal of an artifact:
+///
+/// ```text
+/// [0-4[   length of the frame from offset 8 on (u32, big endian)
+/// [4-8[   CRC32 of the frame from offset 8 on (u32, big endian)
+/// [8]     operation (insert or remove)
+/// [9]     type key of the artifact
+/// [10-18[ height of the artifact (u64, big endian)
+/// [18-50[ hash of the artifact
+/// [50-..[ bincode serialized artifact (inserts only)
+/// ```
+///
+/// Frames are never modified once written. Removals are appended as
+/// tombstones, and purging below a height deletes all segments that only
+/// cover heights below it. The remaining artifacts below the purge height are
+/// hidden by the persisted watermark until their segment is deleted.
+///
+/// On start-up the segments are replayed to build an in-memory index that maps
+/// each artifact (by type, height and hash) to the position of its bytes.
+/// The index is ordered by type and height, so that iteration sorted by height
+/// and lookups by height are efficient. Replay stops at the first frame that is
+/// incomplete (e.g. torn by a crash) or fails its checksum, and the segment is
+/// truncated there, so that only intact frames are ever read.
+///
+/// Iterators returned by the pool work on a snapshot of the index at the time
+/// of creation and keep the segments they read from open. Since frames are
+/// immutable and open files remain readable after they are deleted, iterators
+/// do not see later updates and can outlive the pool.
+pub struct PersistentHeightIndexedPool<T> {
+    db_path: PathBuf,
+    segment_height_span: u64,
+    read_only: bool,
+    state: RwLock<State>,
+    log: ReplicaLogger,
+    pool_type: PhantomData<T>,
+}
+
+/// The mutable state of the pool, i.e. the index and the open segments.
+struct State {
+    index: BTreeMap<IdKey, Location>,
+    /// Open segments, by the first height they cover.
+    segments: BTreeMap<u64, OpenSegment>,
+    /// Artifacts below this height are purged.
+    watermark: Height,
+}
+
+/// A log segment, shared between the pool and the iterators reading from it.
+pub struct Segment {
+    file: File,
+}
+
+struct OpenSegment {
+    segment: Arc<Segment>,
+    path: PathBuf,
+    /// The current length of the segment, i.e. the offset of the next frame.
+    len: u64,
+}
+
+/// The position of the serialized bytes of an artifact in a segment.
+#[derive(Clone)]
+pub struct Location {
+    segment: Arc<Segment>,
+    offset: u64,
+    len: usize,
+}
+
+impl Location {
+    /// Reads the artifact at this location. Returns an error if it can not be
+    /// read or deserialized.
+    fn read<T: DeserializeOwned>(&self) -> io::Result<T> {
+        let mut bytes = vec![0; self.len];
+        self.segment.file.read_exact_at(&mut bytes, self.offset)?;
+        bincode::deserialize(&bytes).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+/// Identifies a type of artifacts in the log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TypeKey(u8);
+
+const HASH_LEN: usize = 32;
+
+/// Key of an artifact in the index. Keys are ordered by type, height and
+/// hash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct IdKey {
+    type_key: TypeKey,
+    height: u64,
+    hash: [u8; HASH_LEN],
+}
+
+impl IdKey {
+    fn new(type_key: TypeKey, height: Height, hash: &[u8]) -> Self {
+        Self {
+            type_key,
+            height: height.get(),
+            hash: check_ok_uw!(hash.try_into()),
+        }
+    }
+
+    /// The smallest key of the given type and height.
+    fn min(type_key: TypeKey, height: Height) -> Self {
+        Self {
+            type_key,
+            height: height.get(),
+            hash: [0x00; HASH_LEN],
+        }
+    }
+
+    /// The largest key of the given type and height.
+    fn max(type_key: TypeKey, height: Height) -> Self {
+        Self {
+            type_key,
+            height: height.get(),
+            hash: [0xff; HASH_LEN],
+        }
+    }
+}
+
+/// Operations recorded by frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FrameOp {
+    Insert = 0,
+    Remove = 1,
+}
+
+impl TryFrom<u8> for FrameOp {
+    type Error = io::Error;
+
+    fn try_from(op: u8) -> io::Result<Self> {
+        match op {
+            0 => Ok(FrameOp::Insert),
+            1 => Ok(FrameOp::Remove),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Unknown frame operation {}", op),
+            )),
+        }
+    }
+}
+
+/// The length of the length and checksum prefix of a frame.
+const FRAME_PREFIX_LEN: usize = 4 + 4;
+/// The length of the frame header that follows the prefix.
+const FRAME_HEADER_LEN: usize = 1 + 1 + 8 + HASH_LEN;
+
+fn encode_frame(op: FrameOp, key: &IdKey, bytes: &[u8]) -> Vec<u8> {
+    let len = FRAME_HEADER_LEN + bytes.len();
+    let mut frame = Vec::with_capacity(FRAME_PREFIX_LEN + len);
+    frame.extend(&check_ok_uw!(u32::try_from(len)).to_be_bytes());
+    frame.extend(&[0; 4]);
+    frame.push(op as u8);
+    frame.push(key.type_key.0);
+    frame.extend(&key.height.to_be_bytes());
+    frame.extend(&key.hash);
+    frame.extend(bytes);
+    let crc = crc32fast::hash(&frame[FRAME_PREFIX_LEN..]);
+    frame[4..FRAME_PREFIX_LEN].copy_from_slice(&crc.to_be_bytes());
+    frame
+}
+
+/// Reads the next frame from `reader`, which has `remaining` bytes left,
+/// returning the operation, the key and the length of the artifact bytes.
+/// Returns `None` at the end of the segment, and an `InvalidData` error if the
+/// frame is incomplete or corrupted.
+fn read_frame(
+    reader: &mut impl Read,
+    remaining: u64,
+) -> io::Result<Option<(FrameOp, IdKey, usize)>> {
+    let invalid = |msg: String| Err(io::Error::new(ErrorKind::InvalidData, msg));
+    if remaining == 0 {
+        return Ok(None);
+    }
+    if remaining < FRAME_PREFIX_LEN as u64 {
+        return invalid(format!("Incomplete frame prefix of {} bytes", remaining));
+    }
+    let len = reader.read_u32::<BigEndian>()? as usize;
+    let crc = reader.read_u32::<BigEndian>()?;
+    if len < FRAME_HEADER_LEN {
+        return invalid(format!("Frame length {} is too short", len));
+    }
+    if (FRAME_PREFIX_LEN + len) as u64 > remaining {
+        return invalid(format!(
+            "Incomplete frame of {} bytes, with {} bytes left",
+            len,
+            remaining - FRAME_PREFIX_LEN as u64
+        ));
+    }
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame)?;
+    if crc32fast::hash(&frame) != crc {
+        return invalid("Frame checksum mismatch".to_string());
+    }
+    let mut header = &frame[..FRAME_HEADER_LEN];
+    let op = FrameOp::try_from(header.read_u8()?)?;
+    let type_key = TypeKey(header.read_u8()?);
+    let height = header.read_u64::<BigEndian>()?;
+    let mut hash = [0; HASH_LEN];
+    header.read_exact(&mut hash)?;
+    let key = IdKey {
+        type_key,
+        height,
+        hash,
+    };
+    Ok(Some((op, key, len - FRAME_HEADER_LEN)))
+}
+
+impl<T> PersistentHeightIndexedPool<T> {
+    fn new(
+        db_path: PathBuf,
+        segment_height_span: Height,
+        read_only: bool,
+        log: ReplicaLogger,
+    ) -> PersistentHeightIndexedPool<T> {
+        assert!(
+            segment_height_span.get() > 0,
+            "The segment height span must be positive"
+        );
+        if !read_only {
+            check_ok_uw!(fs::create_dir_all(&db_path));
+        }
+        let mut pool = PersistentHeightIndexedPool {
+            db_path,
+            segment_height_span: segment_height_span.get(),
+            read_only,
+            state: RwLock::new(State {
+                index: BTreeMap::new(),
+                segments: BTreeMap::new(),
+                watermark: Height::from(0),
+            }),
+            log,
+            pool_type: PhantomData,
+        };
+        if let Err(err) = pool.replay() {
+            panic!(
+                "Error opening persistent pool at: {:?}. Error: {}",
+                pool.db_path, err
+            );
+        }
+        pool
+    }
+
+    /// Rebuilds the index from the watermark and the segments on disk,
+    /// deleting segments that are entirely below the watermark.
+    fn replay(&mut self) -> io::Result<()> {
+        let watermark = match fs::read(self.db_path.join(WATERMARK_FILE)) {
+            Ok(bytes) => Height::from((&bytes[..]).read_u64::<BigEndian>()?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Height::from(0),
+            Err(err) => return Err(err),
+        };
+        let mut starts = BTreeSet::new();
+        match fs::read_dir(&self.db_path) {
+            Ok(entries) => {
+                for entry in entries {
+                    let path = entry?.path();
+                    if path.extension().and_then(|ext| ext.to_str()) != Some(SEGMENT_EXTENSION) {
+                        continue;
+                    }
+                    match path
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .and_then(|stem| stem.parse::<u64>().ok())
+                    {
+                        Some(start) => {
+                            starts.insert(start);
+                        }
+                        None => warn!(self.log, "Ignoring unexpected file {:?}", path),
+                    }
+                }
+            }
+            // A read-only pool may be opened before anything was written.
+            Err(err) if self.read_only && err.kind() == ErrorKind::NotFound => (),
+            Err(err) => return Err(err),
+        }
+
+        let state = self.state.get_mut().unwrap();
+        state.watermark = watermark;
+        for start in starts {
+            let path = self.db_path.join(segment_file_name(start));
+            if start + self.segment_height_span <= watermark.get() {
+                if !self.read_only {
+                    fs::remove_file(&path)?;
+                }
+                continue;
+            }
+            let file = OpenOptions::new()
+                .read(true)
+                .append(!self.read_only)
+                .open(&path)?;
+            let segment = Arc::new(Segment { file });
+            let (len, err) = replay_segment(&segment, watermark, &mut state.index)?;
+            if let Some(err) = err {
+                warn!(
+                    self.log,
+                    "Truncating segment {:?} at offset {} of {}: {}",
+                    path,
+                    len,
+                    segment.file.metadata()?.len(),
+                    err
+                );
+                if !self.read_only {
+                    segment.file.set_len(len)?;
+                }
+            }
+            state
+                .segments
+                .insert(start, OpenSegment { segment, path, len });
+        }
+        Ok(())
+    }
+
+    /// Returns the first height of the segment that contains `height`.
+    fn segment_start(&self, height: Height) -> u64 {
+        height.get() - height.get() % self.segment_height_span
+    }
+
+    /// Appends a frame to the segment covering the height of `key` and updates
+    /// the index accordingly. Returns the segment the frame was appended to.
+    fn append(&self, state: &mut State, op: FrameOp, key: IdKey, bytes: &[u8]) -> u64 {
+        assert!(
+            !self.read_only,
+            "Attempted to write to a read-only persistent pool"
+        );
+        let start = self.segment_start(Height::from(key.height));
+        if !state.segments.contains_key(&start) {
+            let path = self.db_path.join(segment_file_name(start));
+            let file = check_ok_uw!(OpenOptions::new()
+                .read(true)
+                .append(true)
+                .create(true)
+                .open(&path));
+            let len = check_ok_uw!(file.metadata()).len();
+            let segment = Arc::new(Segment { file });
+            state
+                .segments
+                .insert(start, OpenSegment { segment, path, len });
+        }
+        let open_segment = check_not_none_uw!(state.segments.get_mut(&start));
+        let frame = encode_frame(op, &key, bytes);
+        check_ok_uw!((&open_segment.segment.file).write_all(&frame));
+        let offset = open_segment.len + (FRAME_PREFIX_LEN + FRAME_HEADER_LEN) as u64;
+        open_segment.len += frame.len() as u64;
+        match op {
+            FrameOp::Insert => {
+                state.index.insert(
+                    key,
+                    Location {
+                        segment: open_segment.segment.clone(),
+                        offset,
+                        len: bytes.len(),
+                    },
+                );
+            }
+            FrameOp::Remove => {
+                state.index.remove(&key);
+            }
+        }
+        start
+    }
+
+    /// Inserts the given artifact, unless it is below the watermark.
+    fn insert<S: Serialize>(&self, key: IdKey, value: &S) {
+        let mut state = self.state.write().unwrap();
+        if key.height < state.watermark.get() {
+            return;
+        }
+        let bytes = check_ok_uw!(bincode::serialize(value));
+        let start = self.append(&mut state, FrameOp::Insert, key, &bytes);
+        self.sync(&state, &[start]);
+    }
+
+    /// Flushes the given segments to disk.
+    fn sync(&self, state: &State, starts: &[u64]) {
+        for start in starts {
+            if let Some(open_segment) = state.segments.get(start) {
+                check_ok_uw!(open_segment.segment.file.sync_data());
+            }
+        }
+    }
+
+    /// Purges all artifacts below `height`, deleting the segments that only
+    /// contain such artifacts.
+    pub fn purge_below_height(&self, height: Height) {
+        let mut state = self.state.write().unwrap();
+        if height <= state.watermark {
+            return;
+        }
+        assert!(
+            !self.read_only,
+            "Attempted to purge a read-only persistent pool"
+        );
+        // Persist the watermark first, so that a crash before the segments are
+        // deleted can not resurrect purged artifacts.
+        write_watermark(&self.db_path, height);
+        state.watermark = height;
+        state.index.retain(|key, _| key.height >= height.get());
+
+        let first_kept = self.segment_start(height);
+        let kept = state.segments.split_off(&first_kept);
+        let purged = std::mem::replace(&mut state.segments, kept);
+        for open_segment in purged.values() {
+            // Iterators may still hold the segment open, which keeps it
+            // readable until they are dropped.
+            check_ok_uw!(fs::remove_file(&open_segment.path));
+        }
+        if !purged.is_empty() {
+            info!(
+                self.log,
+                "Purged {} segment(s) below height {}",
+                purged.len(),
+                height
+            );
+        }
+    }
+
+    /// Returns the loaded artifact, logging and skipping it if it could not be
+    /// read.
+    fn loaded<A>(&self, artifact: io::Result<A>) -> Option<A> {
+        artifact
+            .map_err(|err| warn!(self.log, "Skipping unreadable artifact: {}", err))
+            .ok()
+    }
+
+    fn contains_key(&self, key: &IdKey) -> bool {
+        self.state.read().unwrap().index.contains_key(key)
+    }
+
+    fn location(&self, key: &IdKey) -> Option<Location> {
+        self.state.read().unwrap().index.get(key).cloned()
+    }
+
+    /// Returns the locations of all artifacts of the given type with heights
+    /// in `[min, max]`, in increasing order of height.
+    fn locations(&self, type_key: TypeKey, min: Height, max: Height) -> Vec<Location> {
+        if min > max {
+            return Vec::new();
+        }
+        self.state
+            .read()
+            .unwrap()
+            .index
+            .range(IdKey::min(type_key, min)..=IdKey::max(type_key, max))
+            .map(|(_, location)| location.clone())
+            .collect()
+    }
+
+    fn type_min_height(&self, type_key: TypeKey) -> Option<Height> {
+        self.state
+            .read()
+            .unwrap()
+            .index
+            .range(
+                IdKey::min(type_key, Height::from(0))
+                    ..=IdKey::max(type_key, Height::from(u64::MAX)),
+            )
+            .next()
+            .map(|(key, _)| Height::from(key.height))
+    }
+
+    fn type_max_height(&self, type_key: TypeKey) -> Option<Height> {
+        self.state
+            .read()
+            .unwrap()
+            .index
+            .range(
+                IdKey::min(type_key, Height::from(0))
+                    ..=IdKey::max(type_key, Height::from(u64::MAX)),
+            )
+            .next_back()
+            .map(|(key, _)| Height::from(key.height))
+    }
+}
+
+/// Replays the frames of `segment` into `index`, skipping frames below the
+/// watermark. Stops at the first incomplete or corrupted frame, which is
+/// returned together with the offset of the end of the last intact frame.
+fn replay_segment(
+    segment: &Arc<Segment>,
+    watermark: Height,
+    index: &mut BTreeMap<IdKey, Location>,
+) -> io::Result<(u64, Option<io::Error>)> {
+    let file_len = segment.file.metadata()?.len();
+    let mut reader = BufReader::new(&segment.file);
+    let mut offset = 0;
+    loop {
+        let (op, key, len) = match read_frame(&mut reader, file_len - offset) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok((offset, None)),
+            Err(err) if err.kind() == ErrorKind::InvalidData => return Ok((offset, Some(err))),
+            Err(err) => return Err(err),
+        };
+        let bytes_offset = offset + (FRAME_PREFIX_LEN + FRAME_HEADER_LEN) as u64;
+        offset = bytes_offset + len as u64;
+        if key.height < watermark.get() {
+            continue;
+        }
+        match op {
+            FrameOp::Insert => {
+                index.insert(
+                    key,
+                    Location {
+                        segment: segment.clone(),
+                        offset: bytes_offset,
+                        len,
+                    },
+                );
+            }
+            FrameOp::Remove => {
+                index.remove(&key);
+            }
+        }
+    }
+}
+
+fn segment_file_name(start: u64) -> String {
+    format!("{:020}.{}", start, SEGMENT_EXTENSION)
+}
+
+/// Atomically replaces the persisted watermark.
+fn write_watermark(db_path: &Path, height: Height) {
+    let tmp_path = db_path.join(format!("{}.tmp", WATERMARK_FILE));
+    let mut file = check_ok_uw!(File::create(&tmp_path));
+    check_ok_uw!(file.write_u64::<BigEndian>(height.get()));
+    check_ok_uw!(file.sync_all());
+    check_ok_uw!(fs::rename(&tmp_path, db_path.join(WATERMARK_FILE)));
+}
+
+/// Trait allowing to specify the TypeKey per artifact type, e.g. RandomBeacon
+/// or Certification.
+pub trait PerTypeKey {
+    fn type_key() -> TypeKey;
+}
+
+/// Trait allowing a pool to load artifacts of type `Message` from the log.
+pub trait LoadFromLog<Message> {
+    fn load(location: &Location) -> io::Result<Message>;
+}
+
+const RANDOM_BEACON_KEY: TypeKey = TypeKey(0);
+const FINALIZATION_KEY: TypeKey = TypeKey(1);
+const NOTARIZATION_KEY: TypeKey = TypeKey(2);
+const BLOCK_PROPOSAL_KEY: TypeKey = TypeKey(3);
+const RANDOM_BEACON_SHARE_KEY: TypeKey = TypeKey(4);
+const NOTARIZATION_SHARE_KEY: TypeKey = TypeKey(5);
+const FINALIZATION_SHARE_KEY: TypeKey = TypeKey(6);
+const RANDOM_TAPE_KEY: TypeKey = TypeKey(7);
+const RANDOM_TAPE_SHARE_KEY: TypeKey = TypeKey(8);
+const CATCH_UP_PACKAGE_KEY: TypeKey = TypeKey(9);
+const CATCH_UP_PACKAGE_SHARE_KEY: TypeKey = TypeKey(10);
+const CERTIFICATION_KEY: TypeKey = TypeKey(16);
+const CERTIFICATION_SHARE_KEY: TypeKey = TypeKey(17);
+
+macro_rules! impl_per_type_key {
+    ($($message:ty => $key:expr),* $(,)?) => {
+        $(
+            impl PerTypeKey for $message {
+                fn type_key() -> TypeKey {
+                    $key
+                }
+            }
+        )*
+    };
+}
+
+impl_per_type_key!(
+    RandomBeacon => RANDOM_BEACON_KEY,
+    Finalization => FINALIZATION_KEY,
+    Notarization => NOTARIZATION_KEY,
+    BlockProposal => BLOCK_PROPOSAL_KEY,
+    RandomBeaconShare => RANDOM_BEACON_SHARE_KEY,
+    NotarizationShare => NOTARIZATION_SHARE_KEY,
+    FinalizationShare => FINALIZATION_SHARE_KEY,
+    RandomTape => RANDOM_TAPE_KEY,
+    RandomTapeShare => RANDOM_TAPE_SHARE_KEY,
+    CatchUpPackage => CATCH_UP_PACKAGE_KEY,
+    CatchUpPackageShare => CATCH_UP_PACKAGE_SHARE_KEY,
+    Certification => CERTIFICATION_KEY,
+    CertificationShare => CERTIFICATION_SHARE_KEY,
+);
+
+/// Returns the type key for a given 'msg_id' based on its type.
+fn type_key_for_msg_id(msg_id: &ConsensusMessageId) -> TypeKey {
+    match msg_id.hash {
+        ConsensusMessageHash::RandomBeacon(_) => RANDOM_BEACON_KEY,
+        ConsensusMessageHash::Finalization(_) => FINALIZATION_KEY,
+        ConsensusMessageHash::Notarization(_) => NOTARIZATION_KEY,
+        ConsensusMessageHash::BlockProposal(_) => BLOCK_PROPOSAL_KEY,
+        ConsensusMessageHash::RandomBeaconShare(_) => RANDOM_BEACON_SHARE_KEY,
+        ConsensusMessageHash::NotarizationShare(_) => NOTARIZATION_SHARE_KEY,
+        ConsensusMessageHash::FinalizationShare(_) => FINALIZATION_SHARE_KEY,
+        ConsensusMessageHash::RandomTape(_) => RANDOM_TAPE_KEY,
+        ConsensusMessageHash::RandomTapeShare(_) => RANDOM_TAPE_SHARE_KEY,
+        ConsensusMessageHash::CatchUpPackage(_) => CATCH_UP_PACKAGE_KEY,
+        ConsensusMessageHash::CatchUpPackageShare(_) => CATCH_UP_PACKAGE_SHARE_KEY,
+    }
+}
+
+fn id_key_for_msg_id(msg_id: &ConsensusMessageId) -> IdKey {
+    IdKey::new(
+        type_key_for_msg_id(msg_id),
+        msg_id.height,
+        &msg_id.hash.digest().0,
+    )
+}
+
+/// Loads a consensus artifact. Catch-up packages are stored as their original
+/// protobuf, so that `highest_catch_up_package_proto` can return the exact
+/// bytes they were received with.
+fn load_consensus_artifact(
+    type_key: TypeKey,
+    location: &Location,
+) -> io::Result<ValidatedConsensusArtifact> {
+    if type_key == CATCH_UP_PACKAGE_KEY {
+        let artifact: ValidatedArtifact<pb::CatchUpPackage> = location.read()?;
+        Ok(ValidatedConsensusArtifact {
+            msg: ConsensusMessage::CatchUpPackage(
+                CatchUpPackage::try_from(&artifact.msg)
+                    .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?,
+            ),
+            timestamp: artifact.timestamp,
+        })
+    } else {
+        location.read()
+    }
+}
+
+impl<Message: ConsensusMessageHashable + PerTypeKey> LoadFromLog<Message> for ConsensusMessage {
+    fn load(location: &Location) -> io::Result<Message> {
+        let artifact = load_consensus_artifact(Message::type_key(), location)?;
+        Message::assert(&artifact.msg)
+            .cloned()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Unexpected type of artifact"))
+    }
+}
+
+impl<Message: DeserializeOwned + PerTypeKey> LoadFromLog<Message> for CertificationMessage {
+    fn load(location: &Location) -> io::Result<Message> {
+        location.read()
+    }
+}
+
+impl PersistentHeightIndexedPool<ConsensusMessage> {
+    pub fn new_consensus_pool(
+        config: AppendLogConfig,
+        read_only: bool,
+        log: ReplicaLogger,
+    ) -> PersistentHeightIndexedPool<ConsensusMessage> {
+        let mut path = config.persistent_pool_validated_persistent_db_path;
+        path.push("consensus");
+        PersistentHeightIndexedPool::new(
+            path,
+            config.persistent_pool_segment_height_span,
+            read_only,
+            log,
+        )
+    }
+}
+
+impl InitializablePoolSection for PersistentHeightIndexedPool<ConsensusMessage> {
+    /// Insert a cup with the original bytes from which that cup was received.
+    fn insert_cup_with_proto(&self, cup_with_proto: CUPWithOriginalProtobuf) {
+        let key = IdKey::new(
+            CATCH_UP_PACKAGE_KEY,
+            cup_with_proto.cup.height(),
+            &cup_with_proto.cup.get_cm_hash().digest().0,
+        );
+        let artifact = ValidatedArtifact {
+            msg: cup_with_proto.protobuf,
+            timestamp: cup_with_proto.cup.content.block.as_ref().context.time,
+        };
+        self.insert(key, &artifact);
+    }
+}
+
+impl crate::consensus_pool::MutablePoolSection<ValidatedConsensusArtifact>
+    for PersistentHeightIndexedPool<ConsensusMessage>
+{
+    fn mutate(&mut self, ops: PoolSectionOps<ValidatedConsensusArtifact>) {
+        let mut touched = Vec::new();
+        for op in ops.ops {
+            let mut state = self.state.write().unwrap();
+            match op {
+                PoolSectionOp::Insert(artifact) => {
+                    let msg_id = artifact.msg.get_id();
+                    if msg_id.height < state.watermark {
+                        continue;
+                    }
+                    let key = id_key_for_msg_id(&msg_id);
+                    let bytes = match artifact.msg {
+                        ConsensusMessage::CatchUpPackage(cup) => {
+                            check_ok_uw!(bincode::serialize(&ValidatedArtifact {
+                                msg: pb::CatchUpPackage::from(&cup),
+                                timestamp: artifact.timestamp,
+                            }))
+                        }
+                        _ => check_ok_uw!(bincode::serialize(&artifact)),
+                    };
+                    touched.push(self.append(&mut state, FrameOp::Insert, key, &bytes));
+                }
+                PoolSectionOp::Remove(msg_id) => {
+                    let key = id_key_for_msg_id(&msg_id);
+                    if state.index.contains_key(&key) {
+                        touched.push(self.append(&mut state, FrameOp::Remove, key, &[]));
+                    } else {
+                        warn!(
+                            self.log,
+                            "Cannot remove from persistent pool: msg_id not found {:?}", msg_id
+                        );
+                    }
+                }
+                PoolSectionOp::PurgeBelow(height) => {
+                    // Flush pending writes before their segments may be deleted.
+                    self.sync(&state, &touched);
+                    touched.clear();
+                    drop(state);
+                    self.purge_below_height(height);
+                }
+            }
+        }
+        touched.sort_unstable();
+        touched.dedup();
+        self.sync(&self.state.read().unwrap(), &touched);
+    }
+
+    fn pool_section(&self) -> &dyn PoolSection<ValidatedConsensusArtifact> {
+        self
+    }
+}
+
+impl PoolSection<ValidatedConsensusArtifact> for PersistentHeightIndexedPool<ConsensusMessage> {
+    fn contains(&self, msg_id: &ConsensusMessageId) -> bool {
+        self.contains_key(&id_key_for_msg_id(msg_id))
+    }
+
+    fn get(&self, msg_id: &ConsensusMessageId) -> Option<ConsensusMessage> {
+        let type_key = type_key_for_msg_id(msg_id);
+        self.location(&id_key_for_msg_id(msg_id))
+            .and_then(|location| self.loaded(load_consensus_artifact(type_key, &location)))
+            .map(|artifact| artifact.msg)
+    }
+
+    fn get_timestamp(&self, msg_id: &ConsensusMessageId) -> Option<Time> {
+        let type_key = type_key_for_msg_id(msg_id);
+        self.location(&id_key_for_msg_id(msg_id))
+            .and_then(|location| self.loaded(load_consensus_artifact(type_key, &location)))
+            .map(|artifact| artifact.timestamp)
+    }
+
+    fn random_beacon(&self) -> &dyn HeightIndexedPool<RandomBeacon> {
+        self
+    }
+
+    fn block_proposal(&self) -> &dyn HeightIndexedPool<BlockProposal> {
+        self
+    }
+
+    fn notarization(&self) -> &dyn HeightIndexedPool<Notarization> {
+        self
+    }
+
+    fn finalization(&self) -> &dyn HeightIndexedPool<Finalization> {
+        self
+    }
+
+    fn random_beacon_share(&self) -> &dyn HeightIndexedPool<RandomBeaconShare> {
+        self
+    }
+
+    fn notarization_share(&self) -> &dyn HeightIndexedPool<NotarizationShare> {
+        self
+    }
+
+    fn finalization_share(&self) -> &dyn HeightIndexedPool<FinalizationShare> {
+        self
+    }
+
+    fn random_tape(&self) -> &dyn HeightIndexedPool<RandomTape> {
+        self
+    }
+
+    fn random_tape_share(&self) -> &dyn HeightIndexedPool<RandomTapeShare> {
+        self
+    }
+
+    fn catch_up_package(&self) -> &dyn HeightIndexedPool<CatchUpPackage> {
+        self
+    }
+
+    fn catch_up_package_share(&self) -> &dyn HeightIndexedPool<CatchUpPackageShare> {
+        self
+    }
+
+    fn highest_catch_up_package_proto(&self) -> pb::CatchUpPackage {
+        let height = check_not_none_uw!(self.type_max_height(CATCH_UP_PACKAGE_KEY));
+        self.locations(CATCH_UP_PACKAGE_KEY, Height::from(0), height)
+            .into_iter()
+            .rev()
+            .find_map(|location| {
+                self.loaded(location.read::<ValidatedArtifact<pb::CatchUpPackage>>())
+            })
+            .expect("There must be a readable catch up package in the pool")
+            .msg
+    }
+
+    fn size(&self) -> u64 {
+        self.state.read().unwrap().index.len() as u64
+    }
+}
+
+impl<T: LoadFromLog<Message> + 'static, Message: PerTypeKey + 'static> HeightIndexedPool<Message>
+    for PersistentHeightIndexedPool<T>
+{
+    fn height_range(&self) -> Option<HeightRange> {
+        let min_height = self.type_min_height(Message::type_key())?;
+        let max_height = self.type_max_height(Message::type_key())?;
+        Some(HeightRange::new(min_height, max_height))
+    }
+
+    fn max_height(&self) -> Option<Height> {
+        self.type_max_height(Message::type_key())
+    }
+
+    fn get_all(&self) -> Box<dyn Iterator<Item = Message>> {
+        self.get_by_height_range(HeightRange::new(Height::from(0), Height::from(u64::MAX)))
+    }
+
+    fn get_by_height(&self, h: Height) -> Box<dyn Iterator<Item = Message>> {
+        self.get_by_height_range(HeightRange::new(h, h))
+    }
+
+    fn get_only_by_height(&self, h: Height) -> Result<Message, OnlyError> {
+        let mut as_vec: Vec<Message> = self.get_by_height(h).collect();
+        match as_vec.len() {
+            0 => Err(OnlyError::NoneAvailable),
+            1 => Ok(as_vec.remove(0)),
+            _ => Err(OnlyError::MultipleValues),
+        }
+    }
+
+    fn get_by_height_range(&self, range: HeightRange) -> Box<dyn Iterator<Item = Message>> {
+        let locations = self.locations(Message::type_key(), range.min, range.max);
+        let log = self.log.clone();
+        Box::new(
+            locations
+                .into_iter()
+                .filter_map(move |location| match T::load(&location) {
+                    Ok(artifact) => Some(artifact),
+                    Err(err) => {
+                        warn!(log, "Skipping unreadable artifact: {}", err);
+                        None
+                    }
+                }),
+        )
+    }
+
+    fn get_highest_iter(&self) -> Box<dyn Iterator<Item = Message>> {
+        match self.type_max_height(Message::type_key()) {
+            Some(height) => self.get_by_height(height),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn get_highest(&self) -> Result<Message, OnlyError> {
+        let mut as_vec: Vec<Message> = self.get_highest_iter().collect();
+        match as_vec.len() {
+            0 => Err(OnlyError::NoneAvailable),
+            1 => Ok(as_vec.remove(0)),
+            _ => Err(OnlyError::MultipleValues),
+        }
+    }
+}
+
+impl PersistentHeightIndexedPool<CertificationMessage> {
+    pub fn new_certification_pool(
+        config: AppendLogConfig,
+        read_only: bool,
+        log: ReplicaLogger,
+    ) -> PersistentHeightIndexedPool<CertificationMessage> {
+        let mut path = config.persistent_pool_validated_persistent_db_path;
+        path.push("certification");
+        PersistentHeightIndexedPool::new(
+            path,
+            config.persistent_pool_segment_height_span,
+            read_only,
+            log,
+        )
+    }
+
+    fn insert_message<T: Serialize + PerTypeKey + CryptoHashable + HasHeight>(&self, value: &T) {
+        let key = IdKey::new(
+            T::type_key(),
+            value.height(),
+            &ic_crypto::crypto_hash(value).get_ref().0,
+        );
+        self.insert(key, value);
+    }
+}
+
+impl crate::certification_pool::MutablePoolSection
+    for PersistentHeightIndexedPool<CertificationMessage>
+{
+    fn insert(&self, message: CertificationMessage) {
+        match message {
+            CertificationMessage::Certification(value) => self.insert_message(&value),
+            CertificationMessage::CertificationShare(value) => self.insert_message(&value),
+        }
+    }
+
+    fn certifications(&self) -> &dyn HeightIndexedPool<Certification> {
+        self
+    }
+
+    fn certification_shares(&self) -> &dyn HeightIndexedPool<CertificationShare> {
+        self
+    }
+
+    fn purge_below(&self, height: Height) {
+        self.purge_below_height(height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus_pool::MutablePoolSection;
+    use crate::test_utils::{random_beacon_ops, PoolTestHelper};
+    use ic_test_utilities::with_test_replica_logger;
+    use std::panic;
+
+    // Helper to run the persistence tests below.
+    // It creates the config and logger that is passed to the instances and then
+    // makes sure that the the directories are removed after the test.
+    fn run_persistent_pool_test<T>(_test_name: &str, test: T)
+    where
+        T: FnOnce(AppendLogConfig, ReplicaLogger) + panic::UnwindSafe,
+    {
+        with_test_replica_logger(|log| {
+            ic_test_utilities::artifact_pool_config::with_test_append_log_pool_config(|config| {
+                let result = panic::catch_unwind(|| test(config.clone(), log));
+                assert!(result.is_ok());
+            })
+        })
+    }
+
+    impl PoolTestHelper for AppendLogConfig {
+        type PersistentHeightIndexedPool = PersistentHeightIndexedPool<ConsensusMessage>;
+
+        fn run_persistent_pool_test<T, R>(_test_name: &str, test: T) -> R
+        where
+            T: FnOnce(AppendLogConfig, ReplicaLogger) -> R + panic::UnwindSafe,
+        {
+            with_test_replica_logger(|log| {
+                ic_test_utilities::artifact_pool_config::with_test_append_log_pool_config(
+                    |config| {
+                        let result = panic::catch_unwind(|| test(config.clone(), log));
+                        assert!(result.is_ok());
+                        result.unwrap()
+                    },
+                )
+            })
+        }
+
+        fn new_consensus_pool(self, log: ReplicaLogger) -> Self::PersistentHeightIndexedPool {
+            PersistentHeightIndexedPool::new_consensus_pool(self, false, log)
+        }
+
+        fn persistent_pool_validated_persistent_db_path(&self) -> &PathBuf {
+            &self.persistent_pool_validated_persistent_db_path
+        }
+    }
+
+    fn segment_starts(config: &AppendLogConfig) -> Vec<u64> {
+        let path = config
+            .persistent_pool_validated_persistent_db_path
+            .join("consensus");
+        let mut starts: Vec<u64> = fs::read_dir(path)
+            .unwrap()
+            .filter_map(|entry| {
+                let path = entry.unwrap().path();
+                if path.extension().and_then(|ext| ext.to_str()) == Some(SEGMENT_EXTENSION) {
+                    path.file_stem()?.to_str()?.parse().ok()
+                } else {
+                    None
+                }
+            })
+            .collect();
+        starts.sort_unstable();
+        starts
+    }
+
+    #[test]
+    fn test_as_pool_section() {
+        crate::test_utils::test_as_pool_section::<AppendLogConfig>()
+    }
+
+    #[test]
+    fn test_as_height_indexed_pool() {
+        crate::test_utils::test_as_height_indexed_pool::<AppendLogConfig>()
+    }
+
+    #[test]
+    fn test_block_proposal_and_payload_correspondence() {
+        crate::test_utils::test_block_proposal_and_payload_correspondence::<AppendLogConfig>()
+    }
+
+    #[test]
+    fn test_iterating_while_inserting_doesnt_see_new_updates() {
+        crate::test_utils::test_iterating_while_inserting_doesnt_see_new_updates::<AppendLogConfig>(
+        )
+    }
+
+    #[test]
+    fn test_iterator_can_outlive_the_pool() {
+        crate::test_utils::test_iterator_can_outlive_the_pool::<AppendLogConfig>()
+    }
+
+    #[test]
+    fn test_persistent_pool_path_is_cleanedup_after_tests() {
+        crate::test_utils::test_persistent_pool_path_is_cleanedup_after_tests::<AppendLogConfig>()
+    }
+
+    #[test]
+    fn test_timestamp_survives_reboot() {
+        crate::test_utils::test_timestamp_survives_reboot::<AppendLogConfig>()
+    }
+
+    #[test]
+    fn test_purge_survives_reboot() {
+        run_persistent_pool_test("test_purge_survives_reboot", |config, log| {
+            // create a pool and purge at height 10
+            {
+                let mut pool = PersistentHeightIndexedPool::new_consensus_pool(
+                    config.clone(),
+                    false,
+                    log.clone(),
+                );
+                // insert a few things
+                let rb_ops = random_beacon_ops();
+                pool.mutate(rb_ops.clone());
+                assert_eq!(pool.random_beacon().get_all().count(), rb_ops.ops.len());
+                // purge at height 10
+                let mut purge_ops = PoolSectionOps::new();
+                purge_ops.purge_below(Height::from(10));
+                pool.mutate(purge_ops);
+                assert_eq!(
+                    pool.random_beacon().height_range().map(|r| r.min),
+                    Some(Height::from(10))
+                );
+            }
+            // create the same pool again, check if purge was persisted
+            {
+                let pool = PersistentHeightIndexedPool::new_consensus_pool(config, false, log);
+                assert_eq!(
+                    pool.random_beacon().height_range().map(|r| r.min),
+                    Some(Height::from(10))
+                );
+            }
+        });
+    }
+
+    // Tests that purging deletes exactly the segments below the purge height,
+    // and that iterators created before the purge can still read from them.
+    #[test]
+    fn test_purge_deletes_whole_segments() {
+        run_persistent_pool_test("test_purge_deletes_whole_segments", |mut config, log| {
+            // random beacons are at heights [3, 19[, i.e. in segments 0, 5, 10, 15
+            config.persistent_pool_segment_height_span = Height::from(5);
+            let mut pool =
+                PersistentHeightIndexedPool::new_consensus_pool(config.clone(), false, log);
+            let rb_ops = random_beacon_ops();
+            pool.mutate(rb_ops.clone());
+            assert_eq!(segment_starts(&config), vec![0, 5, 10, 15]);
+
+            let iter = pool.random_beacon().get_all();
+            let mut purge_ops = PoolSectionOps::new();
+            purge_ops.purge_below(Height::from(12));
+            pool.mutate(purge_ops);
+            assert_eq!(segment_starts(&config), vec![10, 15]);
+            let range = pool.random_beacon().height_range().unwrap();
+            assert_eq!((range.min, range.max), (Height::from(12), Height::from(18)));
+            assert_eq!(iter.count(), rb_ops.ops.len());
+        });
+    }
+
+    // Tests that a torn frame at the end of a segment is truncated on start-up
+    // and that appending afterwards works.
+    #[test]
+    fn test_torn_frame_is_truncated() {
+        run_persistent_pool_test("test_torn_frame_is_truncated", |config, log| {
+            let rb_ops = random_beacon_ops();
+            {
+                let mut pool = PersistentHeightIndexedPool::new_consensus_pool(
+                    config.clone(),
+                    false,
+                    log.clone(),
+                );
+                pool.mutate(rb_ops.clone());
+            }
+            let path = config
+                .persistent_pool_validated_persistent_db_path
+                .join("consensus")
+                .join(segment_file_name(0));
+            let len = fs::metadata(&path).unwrap().len();
+            let file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.set_len(len - 3).unwrap();
+
+            let mut pool = PersistentHeightIndexedPool::new_consensus_pool(config, false, log);
+            assert_eq!(pool.random_beacon().get_all().count(), rb_ops.ops.len() - 1);
+            pool.mutate(rb_ops.clone());
+            assert_eq!(pool.random_beacon().get_all().count(), rb_ops.ops.len());
+        });
+    }
+
+    // Tests that a segment is truncated at the first frame that fails its
+    // checksum or has a bogus length, and that the pool can be opened and
+    // appended to afterwards.
+    #[test]
+    fn test_corrupted_frame_is_truncated() {
+        run_persistent_pool_test("test_corrupted_frame_is_truncated", |config, log| {
+            let rb_ops = random_beacon_ops();
+            {
+                let mut pool = PersistentHeightIndexedPool::new_consensus_pool(
+                    config.clone(),
+                    false,
+                    log.clone(),
+                );
+                pool.mutate(rb_ops.clone());
+            }
+            let path = config
+                .persistent_pool_validated_persistent_db_path
+                .join("consensus")
+                .join(segment_file_name(0));
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .unwrap();
+
+            // Flip a bit in the artifact of the last frame.
+            let len = fs::metadata(&path).unwrap().len();
+            let mut byte = [0];
+            file.read_exact_at(&mut byte, len - 1).unwrap();
+            file.write_all_at(&[byte[0] ^ 1], len - 1).unwrap();
+            {
+                let pool = PersistentHeightIndexedPool::new_consensus_pool(
+                    config.clone(),
+                    false,
+                    log.clone(),
+                );
+                assert_eq!(pool.random_beacon().get_all().count(), rb_ops.ops.len() - 1);
+            }
+            assert!(fs::metadata(&path).unwrap().len() < len);
+
+            // Overwrite the length of the first frame.
+            file.write_all_at(&u32::MAX.to_be_bytes(), 0).unwrap();
+            let mut pool = PersistentHeightIndexedPool::new_consensus_pool(config, false, log);
+            assert_eq!(pool.random_beacon().get_all().count(), 0);
+            assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+            pool.mutate(rb_ops.clone());
+            assert_eq!(pool.random_beacon().get_all().count(), rb_ops.ops.len());
+        });
+    }
+}
@@ -13,6 +13,11 @@
 //! and since we backup all artifacts instantly after the pool update, there is
 //! no possibility to inject purging (or any other deletion) of artifacts
 //! between the pool update and the backup.
+//!
+//! Heights are stored in group directories. The group size and the purging
+//! interval can be set per subnet in the registry (see
+//! `ConsensusParameters`), and default to `BACKUP_GROUP_SIZE` and the local
+//! backup configuration otherwise.
 
 use ic_config::artifact_pool::BACKUP_GROUP_SIZE;
 use ic_interfaces::{
@@ -21,7 +26,7 @@ use ic_interfaces::{
 };
 use ic_logger::{error, info, warn, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
-use ic_protobuf::types::v1 as pb;
+use ic_protobuf::{registry::subnet::v1::ConsensusParameters, types::v1 as pb};
 use ic_types::{
     consensus::{
         BlockProposal, CatchUpPackage, ConsensusMessage, Finalization, HasHeight, Notarization,
@@ -34,6 +39,7 @@ use ic_types::{
 use prometheus::IntCounter;
 use prost::Message;
 use std::{
+    collections::BTreeMap,
     fs,
     io::{self, Write},
     path::{Path, PathBuf},
@@ -77,7 +83,8 @@ impl Metrics {
 const QUEUE_LENGTH: usize = 0;
 
 enum BackupRequest {
-    Backup(Vec<ConsensusMessage>),
+    // The artifacts to store and the size of newly created groups.
+    Backup(Vec<ConsensusMessage>, u64),
     Await(SyncSender<()>),
     Shutdown,
 }
@@ -86,14 +93,16 @@ struct BackupThread {
     // Path pointing to <backup_dir>/<subnet_id>/<replica_version>. It contains all artifacts
     // backed up by the current replica version.
     version_path: PathBuf,
+    groups: Groups,
     metrics: Metrics,
     log: ReplicaLogger,
 }
 
 impl BackupThread {
-    fn new(version_path: PathBuf, metrics: Metrics, log: ReplicaLogger) -> Self {
+    fn new(version_path: PathBuf, groups: Groups, metrics: Metrics, log: ReplicaLogger) -> Self {
         BackupThread {
             version_path,
+            groups,
             metrics,
             log,
         }
@@ -111,8 +120,10 @@ impl BackupThread {
     fn run(&mut self, rx: Receiver<BackupRequest>) {
         loop {
             match rx.recv() {
-                Ok(BackupRequest::Backup(artifacts)) => {
-                    if let Err(err) = store_artifacts(artifacts, &self.version_path) {
+                Ok(BackupRequest::Backup(artifacts, group_size)) => {
+                    if let Err(err) =
+                        store_artifacts(artifacts, &self.version_path, &mut self.groups, group_size)
+                    {
                         error!(self.log, "Backup storing failed: {:?}", err);
                         self.metrics.io_errors.inc();
                     }
@@ -210,6 +221,11 @@ pub(super) struct Backup {
     purging_thread: Option<thread::JoinHandle<()>>,
     // Time interval between purges.
     purge_interval_secs: Duration,
+    // The purge interval from the local configuration, used if the registry
+    // does not specify one.
+    default_purge_interval_secs: Duration,
+    // The size of newly created group directories.
+    group_size: u64,
     metrics: Metrics,
     log: ReplicaLogger,
 }
@@ -221,12 +237,19 @@ impl Backup {
         version_path: PathBuf,
         age_threshold_secs: Duration,
         purge_interval_secs: Duration,
+        consensus_parameters: Option<&ConsensusParameters>,
         metrics_registry: MetricsRegistry,
         log: ReplicaLogger,
     ) -> Self {
         let metrics = Metrics::new(&metrics_registry);
-        let (backup_queue, backup_thread) =
-            BackupThread::new(version_path.clone(), metrics.clone(), log.clone()).start();
+        let mut groups = Groups::load(&version_path).unwrap_or_else(|err| {
+            error!(log, "Loading the backup groups failed: {:?}", err);
+            metrics.io_errors.inc();
+            Groups::default()
+        });
+        let default_purge_interval_secs = purge_interval_secs;
+        let (purge_interval_secs, group_size) =
+            backup_parameters(consensus_parameters, default_purge_interval_secs);
         let (purging_queue, purging_thread) = PurgingThread::new(
             backup_path,
             age_threshold_secs,
@@ -234,16 +257,6 @@ impl Backup {
             log.clone(),
         )
         .start();
-        let backup = Self {
-            time_of_last_purge: RwLock::new(UNIX_EPOCH),
-            backup_queue,
-            backup_thread: Some(backup_thread),
-            purging_queue,
-            purging_thread: Some(purging_thread),
-            purge_interval_secs,
-            metrics,
-            log,
-        };
 
         // Due to the fact that the backup is synced to the disk completely
         // independently of the consensus pool and always after the consensus pool was
@@ -253,11 +266,25 @@ impl Backup {
         // component, we need to synchronize the backup with the pool in a blocking
         // manner.
         let artifacts = get_all_persisted_artifacts(pool);
-        if let Err(err) = store_artifacts(artifacts, &version_path) {
-            error!(backup.log, "Backup storing failed: {:?}", err);
-            backup.metrics.io_errors.inc();
+        if let Err(err) = store_artifacts(artifacts, &version_path, &mut groups, group_size) {
+            error!(log, "Backup storing failed: {:?}", err);
+            metrics.io_errors.inc();
+        }
+
+        let (backup_queue, backup_thread) =
+            BackupThread::new(version_path, groups, metrics.clone(), log.clone()).start();
+        Self {
+            time_of_last_purge: RwLock::new(UNIX_EPOCH),
+            backup_queue,
+            backup_thread: Some(backup_thread),
+            purging_queue,
+            purging_thread: Some(purging_thread),
+            purge_interval_secs,
+            default_purge_interval_secs,
+            group_size,
+            metrics,
+            log,
         }
-        backup
     }
 
     // Filters the new artifacts and asynchronously writes the relevant artifacts
@@ -266,7 +293,7 @@ impl Backup {
         // If the queue is full, we will block here.
         if self
             .backup_queue
-            .send(BackupRequest::Backup(artifacts))
+            .send(BackupRequest::Backup(artifacts, self.group_size))
             .is_err()
         {
             error!(
@@ -295,6 +322,15 @@ impl Backup {
         }
     }
 
+    /// Applies the consensus parameters from the registry. Without parameters,
+    /// the defaults are restored.
+    pub fn set_consensus_parameters(&mut self, consensus_parameters: Option<&ConsensusParameters>) {
+        let (purge_interval_secs, group_size) =
+            backup_parameters(consensus_parameters, self.default_purge_interval_secs);
+        self.purge_interval_secs = purge_interval_secs;
+        self.group_size = group_size;
+    }
+
     /// Blocks the current thread until all artifacts have been written to disk.
     ///
     /// Mainly useful for testing.
@@ -325,9 +361,88 @@ impl Backup {
     }
 }
 
+// Returns the purge interval and the group size to use, given the consensus
+// parameters from the registry and the locally configured purge interval.
+fn backup_parameters(
+    consensus_parameters: Option<&ConsensusParameters>,
+    purge_interval_secs: Duration,
+) -> (Duration, u64) {
+    match consensus_parameters {
+        Some(parameters) => (
+            Duration::from_secs(parameters.backup_purging_interval_secs),
+            parameters.backup_group_size.max(1),
+        ),
+        None => (purge_interval_secs, BACKUP_GROUP_SIZE),
+    }
+}
+
+/// The group directories of a backup version directory. A height belongs to
+/// the group with the largest key not above it. New groups start at a multiple
+/// of the group size, but always above the heights stored in the previous
+/// group, so that changing the group size never moves a stored height to a
+/// different group.
+#[derive(Default)]
+pub(crate) struct Groups {
+    // Maps the key of each group to the highest height stored in it.
+    highest_heights: BTreeMap<u64, u64>,
+}
+
+impl Groups {
+    // Reads the existing groups from disk.
+    fn load(version_path: &Path) -> Result<Self, io::Error> {
+        let mut highest_heights = BTreeMap::new();
+        if !version_path.exists() {
+            return Ok(Self { highest_heights });
+        }
+        for group in fs::read_dir(version_path)? {
+            let group = group?.path();
+            let key = match parse_number(&group) {
+                Some(key) if group.is_dir() => key,
+                _ => continue,
+            };
+            let mut highest = key;
+            for dir in fs::read_dir(&group)? {
+                if let Some(height) = parse_number(&dir?.path()) {
+                    highest = highest.max(height);
+                }
+            }
+            highest_heights.insert(key, highest);
+        }
+        Ok(Self { highest_heights })
+    }
+
+    // Returns the key of the group that `height` is stored in, creating a new
+    // group of `group_size` heights if necessary.
+    fn key(&mut self, height: Height, group_size: u64) -> u64 {
+        let height = height.get();
+        let lowest_new_key = match self.highest_heights.range_mut(..=height).next_back() {
+            Some((key, highest)) if height <= *highest || height - key < group_size => {
+                *highest = height.max(*highest);
+                return *key;
+            }
+            Some((_, highest)) => *highest + 1,
+            None => 0,
+        };
+        let key = (height / group_size * group_size).max(lowest_new_key);
+        self.highest_heights.insert(key, height);
+        key
+    }
+}
+
+fn parse_number(path: &Path) -> Option<u64> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.parse::<u64>().ok())
+}
+
 // Write all backup files to the disk. For the sake of simplicity, we write all
 // artifacts sequentially.
-fn store_artifacts(artifacts: Vec<ConsensusMessage>, path: &Path) -> Result<(), io::Error> {
+pub(crate) fn store_artifacts(
+    artifacts: Vec<ConsensusMessage>,
+    path: &Path,
+    groups: &mut Groups,
+    group_size: u64,
+) -> Result<(), io::Error> {
     use ConsensusMessage::*;
     artifacts
         .into_iter()
@@ -346,7 +461,7 @@ fn store_artifacts(artifacts: Vec<ConsensusMessage>, path: &Path) -> Result<(),
             | RandomTapeShare(_)
             | CatchUpPackageShare(_) => None,
         })
-        .try_for_each(|artifact| artifact.write_to_disk(path))
+        .try_for_each(|artifact| artifact.write_to_disk(path, groups, group_size))
 }
 
 // Traverses the whole backup directory and finds all leaf directories
@@ -500,8 +615,13 @@ impl Drop for Backup {
 impl BackupArtifact {
     // Writes the protobuf serialization of the artifact into a file in the given
     // directory.
-    fn write_to_disk(&self, path: &Path) -> Result<(), std::io::Error> {
-        let (file_directory, file_name) = self.file_location(path);
+    fn write_to_disk(
+        &self,
+        path: &Path,
+        groups: &mut Groups,
+        group_size: u64,
+    ) -> Result<(), std::io::Error> {
+        let (file_directory, file_name) = self.file_location(path, groups, group_size);
         // Create the path if necessary.
         fs::create_dir_all(&file_directory)?;
         let full_path = file_directory.join(file_name);
@@ -539,7 +659,12 @@ impl BackupArtifact {
     // notarizations and finalizations, these artifacts can be created in different
     // ways on different replicas, so we need to put their hashes into the artifact
     // name.
-    fn file_location(&self, path: &Path) -> (PathBuf, String) {
+    fn file_location(
+        &self,
+        path: &Path,
+        groups: &mut Groups,
+        group_size: u64,
+    ) -> (PathBuf, String) {
         // Create a subdir for the height
         use BackupArtifact::*;
         let (height, file_name) = match self {
@@ -573,8 +698,8 @@ impl BackupArtifact {
         };
         // We group heights by directories to avoid running into any kind of unexpected
         // FS inode limitations. Each group directory will contain at most
-        // `BACKUP_GROUP_SIZE` heights.
-        let group_key = (height.get() / BACKUP_GROUP_SIZE) * BACKUP_GROUP_SIZE;
+        // `group_size` heights.
+        let group_key = groups.key(height, group_size);
         let path_with_height = path.join(group_key.to_string()).join(height.to_string());
         (path_with_height, file_name)
     }
@@ -604,6 +729,25 @@ mod tests {
     };
     use std::convert::TryFrom;
 
+    #[test]
+    fn test_group_keys() {
+        let mut groups = Groups::default();
+        let key =
+            |groups: &mut Groups, height, group_size| groups.key(Height::from(height), group_size);
+        assert_eq!(key(&mut groups, 5, 10), 0);
+        assert_eq!(key(&mut groups, 12, 10), 10);
+        // A smaller group size starts new groups at its own multiples.
+        assert_eq!(key(&mut groups, 21, 4), 20);
+        assert_eq!(key(&mut groups, 25, 4), 24);
+        // Heights stored in a group stay in it after the size changes.
+        assert_eq!(key(&mut groups, 25, 100), 24);
+        assert_eq!(key(&mut groups, 99, 100), 24);
+        // A new group never starts at or below a stored height.
+        assert_eq!(key(&mut groups, 210, 100), 200);
+        assert_eq!(key(&mut groups, 205, 4), 200);
+        assert_eq!(key(&mut groups, 215, 7), 211);
+    }
+
     #[test]
     fn test_random_tape_conversion() {
         let artifact = RandomTape::fake(RandomTapeContent::new(Height::from(22)));
@@ -0,0 +1,1105 @@
+//! Offline verification of the consensus backups written by the `backup`
+//! module.
+//!
+//! A backup version directory (`<backup_dir>/<subnet_id>/<replica_version>`)
+//! contains one directory per height, grouped into group directories. A
+//! height belongs to the group with the largest key not above it. The
+//! verifier reconstructs the finalized chain by starting at the highest
+//! finalization and following the parent hashes of the stored block proposals
+//! downwards. It then walks the heights upwards and checks the signatures of
+//! all finalizations, notarizations and catch-up packages against the registry
+//! version of the DKG interval they belong to. Like the consensus validator,
+//! it requires the signers of finalizations and notarizations to reach the
+//! notarization threshold and to be members of the notarization committee,
+//! which is sampled from the subnet nodes with the random beacon of the
+//! previous height. Heights for which the chain
+//! cannot be reconstructed are reported as gaps, everything else that fails is
+//! reported as an issue.
+//!
+//! The verified part of the chain can be exported as a gzipped tar archive,
+//! which has the same directory layout as the backup itself and can therefore
+//! be used as a backup directory again (e.g. by the replay tool).
+
+use crate::backup::bytes_to_hex_str;
+use flate2::{write::GzEncoder, Compression};
+use ic_consensus_message::ConsensusMessageHashable;
+use ic_crypto::prng::{Csprng, RandomnessPurpose};
+use ic_interfaces::{
+    crypto::{MultiSigVerifier, Signable, ThresholdSigVerifierByPublicKey},
+    registry::RegistryClient,
+};
+use ic_protobuf::types::v1 as pb;
+use ic_registry_client::helper::subnet::SubnetRegistry;
+use ic_types::{
+    consensus::{
+        get_committee_size, get_faults_tolerated, Block, BlockProposal,
+        CatchUpContentProtobufBytes, CatchUpPackage, Finalization, FinalizationContent,
+        MultiSignature, Notarization, NotarizationContent, RandomBeacon, RandomTape,
+    },
+    crypto::{CombinedThresholdSig, CombinedThresholdSigOf, CryptoHashOf, Signed},
+    Height, NodeId, RegistryVersion, SubnetId,
+};
+use prost::Message;
+use rand::seq::SliceRandom;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+const CATCH_UP_PACKAGE_FILE: &str = "catch_up_package.bin";
+const RANDOM_BEACON_FILE: &str = "random_beacon.bin";
+const RANDOM_TAPE_FILE: &str = "random_tape.bin";
+const BLOCK_PROPOSAL_PREFIX: &str = "block_proposal_";
+const FINALIZATION_PREFIX: &str = "finalization_";
+const NOTARIZATION_PREFIX: &str = "notarization_";
+
+/// The signature verification required to check a backup.
+pub trait BackupVerifierCrypto:
+    MultiSigVerifier<FinalizationContent>
+    + MultiSigVerifier<NotarizationContent>
+    + ThresholdSigVerifierByPublicKey<CatchUpContentProtobufBytes>
+{
+}
+
+// Blanket implementation of `BackupVerifierCrypto` for all types that fulfill
+// the requirements.
+impl<T> BackupVerifierCrypto for T where
+    T: MultiSigVerifier<FinalizationContent>
+        + MultiSigVerifier<NotarizationContent>
+        + ThresholdSigVerifierByPublicKey<CatchUpContentProtobufBytes>
+{
+}
+
+/// A problem found at a specific height of the backup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackupIssue {
+    /// An artifact file could not be read or decoded.
+    Unreadable {
+        height: Height,
+        path: PathBuf,
+        error: String,
+    },
+    /// The hash of a block does not match its content.
+    IntegrityViolation { height: Height, path: PathBuf },
+    /// No block with the hash expected by the finalized chain is stored.
+    BrokenChain {
+        height: Height,
+        expected: CryptoHashOf<Block>,
+    },
+    /// Finalizations at this height finalize different blocks.
+    ConflictingFinalizations { height: Height },
+    /// The block of the catch-up package is not the finalized block.
+    ConflictingCatchUpPackage { height: Height, path: PathBuf },
+    /// The signature of an artifact could not be verified.
+    InvalidSignature {
+        height: Height,
+        path: PathBuf,
+        error: String,
+    },
+    /// No summary block was found at or below this height, so the registry
+    /// version to verify signatures against is unknown.
+    UnknownRegistryVersion { height: Height },
+    /// The notarization committee of this height could not be determined,
+    /// e.g. because the random beacon of the previous height is missing.
+    UnknownNotaryCommittee { height: Height, error: String },
+}
+
+impl BackupIssue {
+    /// Returns the height at which the issue was found.
+    pub fn height(&self) -> Height {
+        use BackupIssue::*;
+        match self {
+            Unreadable { height, .. }
+            | IntegrityViolation { height, .. }
+            | BrokenChain { height, .. }
+            | ConflictingFinalizations { height }
+            | ConflictingCatchUpPackage { height, .. }
+            | InvalidSignature { height, .. }
+            | UnknownRegistryVersion { height }
+            | UnknownNotaryCommittee { height, .. } => *height,
+        }
+    }
+}
+
+impl fmt::Display for BackupIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use BackupIssue::*;
+        match self {
+            Unreadable {
+                height,
+                path,
+                error,
+            } => write!(f, "{}: cannot read {}: {}", height, path.display(), error),
+            IntegrityViolation { height, path } => {
+                write!(f, "{}: hash mismatch in {}", height, path.display())
+            }
+            BrokenChain { height, expected } => write!(
+                f,
+                "{}: no block with hash {} found",
+                height,
+                bytes_to_hex_str(expected)
+            ),
+            ConflictingFinalizations { height } => {
+                write!(f, "{}: finalizations of different blocks", height)
+            }
+            ConflictingCatchUpPackage { height, path } => write!(
+                f,
+                "{}: {} does not contain the finalized block",
+                height,
+                path.display()
+            ),
+            InvalidSignature {
+                height,
+                path,
+                error,
+            } => write!(
+                f,
+                "{}: invalid signature in {}: {}",
+                height,
+                path.display(),
+                error
+            ),
+            UnknownRegistryVersion { height } => {
+                write!(f, "{}: registry version is unknown", height)
+            }
+            UnknownNotaryCommittee { height, error } => {
+                write!(
+                    f,
+                    "{}: notarization committee is unknown: {}",
+                    height, error
+                )
+            }
+        }
+    }
+}
+
+// A block of the finalized chain together with the backup files belonging to
+// it.
+struct ChainEntry {
+    hash: CryptoHashOf<Block>,
+    // The start height of the DKG interval the block belongs to.
+    interval_start: Height,
+    // Set if the block is a summary block.
+    summary_registry_version: Option<RegistryVersion>,
+    files: Vec<PathBuf>,
+}
+
+/// The result of verifying a backup.
+#[derive(Default)]
+pub struct BackupVerificationReport {
+    /// The lowest and highest height stored in the backup within the
+    /// requested range.
+    pub backup_range: Option<(Height, Height)>,
+    /// The height of the highest finalized block.
+    pub finalized_tip: Option<Height>,
+    /// Ranges of heights below the finalized tip for which the chain could
+    /// not be reconstructed.
+    pub gaps: Vec<(Height, Height)>,
+    /// All issues found, ordered by height.
+    pub issues: Vec<BackupIssue>,
+    /// The longest range of the finalized chain, starting at its lowest
+    /// height, without any gaps or issues.
+    pub verified_range: Option<(Height, Height)>,
+    chain: BTreeMap<Height, ChainEntry>,
+}
+
+impl BackupVerificationReport {
+    /// Returns true if the backup has no gaps and no issues.
+    pub fn is_ok(&self) -> bool {
+        self.gaps.is_empty() && self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for BackupVerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = |range: &Option<(Height, Height)>| match range {
+            Some((from, to)) => format!("{}..={}", from, to),
+            None => "none".to_string(),
+        };
+        writeln!(f, "Backup heights: {}", range(&self.backup_range))?;
+        match self.finalized_tip {
+            Some(height) => writeln!(f, "Finalized tip: {}", height)?,
+            None => writeln!(f, "Finalized tip: none")?,
+        }
+        writeln!(f, "Verified range: {}", range(&self.verified_range))?;
+        writeln!(f, "Gaps: {}", self.gaps.len())?;
+        for gap in &self.gaps {
+            writeln!(f, "  {}", range(&Some(*gap)))?;
+        }
+        writeln!(f, "Issues: {}", self.issues.len())?;
+        for issue in &self.issues {
+            writeln!(f, "  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Verifies the backup stored in `backup_path`, which is expected to point to
+/// the directory of a single replica version of a subnet. Only heights in the
+/// given (inclusive) range are considered. The registry client is used to
+/// look up the nodes of the subnet at the registry versions of the backup.
+///
+/// Returns an error only if the backup directory itself cannot be traversed;
+/// problems with individual artifacts are part of the report.
+pub fn verify_backup<C: BackupVerifierCrypto>(
+    crypto: &C,
+    registry: &dyn RegistryClient,
+    subnet_id: SubnetId,
+    backup_path: &Path,
+    from: Option<Height>,
+    to: Option<Height>,
+) -> io::Result<BackupVerificationReport> {
+    let all_dirs = height_dirs(backup_path)?;
+    let dirs: BTreeMap<Height, PathBuf> = all_dirs
+        .iter()
+        .filter(|(height, _)| {
+            from.map_or(true, |from| **height >= from) && to.map_or(true, |to| **height <= to)
+        })
+        .map(|(height, path)| (*height, path.clone()))
+        .collect();
+    let mut report = BackupVerificationReport::default();
+    let (low, high) = match (dirs.keys().next(), dirs.keys().last()) {
+        (Some(low), Some(high)) => (*low, *high),
+        _ => return Ok(report),
+    };
+    report.backup_range = Some((low, high));
+
+    let mut issues = Vec::new();
+    let mut chain = BTreeMap::new();
+    let bottom_parent = follow_chain(
+        &dirs,
+        low,
+        high,
+        None,
+        &mut chain,
+        &mut issues,
+        &mut report.gaps,
+    );
+
+    // If the lowest block of the chain is not a summary block, we need to
+    // follow the chain below the requested range to find the registry version
+    // of its DKG interval.
+    let mut registry_version = None;
+    if let Some((height, entry)) = chain.iter().next() {
+        if entry.summary_registry_version.is_none() && entry.interval_start < *height {
+            let mut prefix = BTreeMap::new();
+            follow_chain(
+                &all_dirs,
+                entry.interval_start,
+                height.decrement(),
+                bottom_parent,
+                &mut prefix,
+                &mut Vec::new(),
+                &mut Vec::new(),
+            );
+            registry_version = prefix
+                .get(&entry.interval_start)
+                .and_then(|entry: &ChainEntry| entry.summary_registry_version);
+        }
+    }
+
+    for (height, dir) in dirs.iter() {
+        let entry = chain.get_mut(height);
+        if let Some(version) = entry.as_ref().and_then(|e| e.summary_registry_version) {
+            registry_version = Some(version);
+        }
+        let previous_dir = all_dirs.get(&height.decrement()).map(PathBuf::as_path);
+        verify_height(
+            crypto,
+            registry,
+            subnet_id,
+            *height,
+            dir,
+            previous_dir,
+            registry_version,
+            entry,
+            &mut issues,
+        );
+    }
+
+    issues.sort_by_key(|issue| issue.height());
+    report.gaps.sort();
+    report.finalized_tip = chain.keys().last().cloned();
+    report.verified_range = chain.keys().next().and_then(|bottom| {
+        let bad_heights: BTreeSet<Height> = issues.iter().map(|issue| issue.height()).collect();
+        let mut top = None;
+        let mut height = *bottom;
+        while chain.contains_key(&height) && !bad_heights.contains(&height) {
+            top = Some(height);
+            height = height.increment();
+        }
+        top.map(|top| (*bottom, top))
+    });
+    report.issues = issues;
+    report.chain = chain;
+    Ok(report)
+}
+
+/// Writes all backup files of the verified range of the report into a gzipped
+/// tar archive at `archive_path`. Only the finalized blocks and the artifacts
+/// referring to them are included. File paths in the archive are relative to
+/// `backup_path`.
+pub fn write_archive(
+    report: &BackupVerificationReport,
+    backup_path: &Path,
+    archive_path: &Path,
+) -> io::Result<()> {
+    let file = fs::File::create(archive_path)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    if let Some((from, to)) = report.verified_range {
+        for entry in report.chain.range(from..=to).map(|(_, entry)| entry) {
+            for path in &entry.files {
+                let name = path
+                    .strip_prefix(backup_path)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+                builder.append_path_with_name(path, name)?;
+            }
+        }
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+// Returns the directories of all heights stored in the backup version
+// directory. Entries which are not named by a height (e.g. `lost+found`) are
+// ignored.
+fn height_dirs(backup_path: &Path) -> io::Result<BTreeMap<Height, PathBuf>> {
+    let mut dirs = BTreeMap::new();
+    for group in fs::read_dir(backup_path)? {
+        let group = group?.path();
+        let group_key = match parse_height(&group) {
+            Some(group_key) if group.is_dir() => group_key,
+            _ => continue,
+        };
+        for dir in fs::read_dir(&group)? {
+            let dir = dir?.path();
+            match parse_height(&dir) {
+                Some(height) if dir.is_dir() && group_key <= height => {
+                    dirs.insert(height, dir);
+                }
+                _ => (),
+            }
+        }
+    }
+    Ok(dirs)
+}
+
+fn parse_height(path: &Path) -> Option<Height> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.parse::<u64>().ok())
+        .map(Height::from)
+}
+
+// Follows the finalized chain downwards from `high` to `low` (both inclusive)
+// and adds all found blocks to `chain`. If no block is `expected` at `high`,
+// the chain starts at the highest finalization. Whenever the chain breaks, it
+// resumes at the next lower finalization and the skipped heights are recorded
+// as a gap. Returns the parent hash of the lowest block found.
+fn follow_chain(
+    dirs: &BTreeMap<Height, PathBuf>,
+    low: Height,
+    high: Height,
+    mut expected: Option<CryptoHashOf<Block>>,
+    chain: &mut BTreeMap<Height, ChainEntry>,
+    issues: &mut Vec<BackupIssue>,
+    gaps: &mut Vec<(Height, Height)>,
+) -> Option<CryptoHashOf<Block>> {
+    let mut gap_top = None;
+    for height in (low.get()..=high.get()).rev().map(Height::from) {
+        let dir = dirs.get(&height);
+        let finalized: BTreeSet<CryptoHashOf<Block>> = dir
+            .map(|dir| read_artifacts::<pb::Finalization, Finalization>(dir, height, issues))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, finalization)| finalization.content.block)
+            .collect();
+        let conflicting = match &expected {
+            Some(hash) => finalized.iter().any(|finalized| finalized != hash),
+            None => finalized.len() > 1,
+        };
+        if conflicting {
+            issues.push(BackupIssue::ConflictingFinalizations { height });
+        }
+        let target = match expected.take().or_else(|| finalized.into_iter().next()) {
+            Some(target) => target,
+            None => continue,
+        };
+        if let Some(top) = gap_top.take() {
+            gaps.push((height.increment(), top));
+        }
+        match dir.and_then(|dir| find_block(dir, height, &target, issues)) {
+            Some((block, path)) => {
+                expected = Some(block.parent.clone());
+                let payload = block.payload.as_ref();
+                chain.insert(
+                    height,
+                    ChainEntry {
+                        hash: target,
+                        interval_start: payload.dkg_interval_start_height(),
+                        summary_registry_version: if payload.is_summary() {
+                            Some(payload.as_summary().dkg.registry_version)
+                        } else {
+                            None
+                        },
+                        files: path.into_iter().collect(),
+                    },
+                );
+            }
+            None => {
+                issues.push(BackupIssue::BrokenChain {
+                    height,
+                    expected: target,
+                });
+                gap_top = Some(height);
+            }
+        }
+    }
+    if let Some(top) = gap_top {
+        gaps.push((low, top));
+    }
+    expected
+}
+
+// Looks up the block with the given hash among the block proposals and the
+// catch-up package stored at the given height. Returns the block and the path
+// of the proposal it was found in, if any.
+fn find_block(
+    dir: &Path,
+    height: Height,
+    hash: &CryptoHashOf<Block>,
+    issues: &mut Vec<BackupIssue>,
+) -> Option<(Block, Option<PathBuf>)> {
+    let prefix = format!("{}{}_", BLOCK_PROPOSAL_PREFIX, bytes_to_hex_str(hash));
+    let paths = files_with_prefix(dir, &prefix).unwrap_or_else(|err| {
+        issues.push(unreadable(height, dir, err));
+        Vec::new()
+    });
+    for path in paths {
+        match decode::<pb::BlockProposal, BlockProposal>(&path) {
+            Ok(proposal) if proposal.content.get_hash() == hash => {
+                if proposal.check_integrity() {
+                    return Some((proposal.content.into_inner(), Some(path)));
+                }
+                issues.push(BackupIssue::IntegrityViolation { height, path });
+            }
+            Ok(_) => (),
+            Err(error) => issues.push(BackupIssue::Unreadable {
+                height,
+                path,
+                error,
+            }),
+        }
+    }
+    // The genesis block and blocks which were already purged from the pool
+    // when the backup started are only available in the catch-up package.
+    let path = dir.join(CATCH_UP_PACKAGE_FILE);
+    if !path.exists() {
+        return None;
+    }
+    match read_cup(&path) {
+        Ok((_, cup)) if cup.content.block.get_hash() == hash => {
+            if cup.check_integrity() {
+                return Some((cup.content.block.into_inner(), None));
+            }
+            issues.push(BackupIssue::IntegrityViolation { height, path });
+        }
+        Ok(_) => (),
+        Err(error) => issues.push(BackupIssue::Unreadable {
+            height,
+            path,
+            error,
+        }),
+    }
+    None
+}
+
+// Verifies the signatures of all finalizations, notarizations and the
+// catch-up package at the given height, and collects the files belonging to
+// the finalized block into its chain entry. The directory of the previous
+// height is needed to determine the notarization committee.
+#[allow(clippy::too_many_arguments)]
+fn verify_height<C: BackupVerifierCrypto>(
+    crypto: &C,
+    registry: &dyn RegistryClient,
+    subnet_id: SubnetId,
+    height: Height,
+    dir: &Path,
+    previous_dir: Option<&Path>,
+    registry_version: Option<RegistryVersion>,
+    mut entry: Option<&mut ChainEntry>,
+    issues: &mut Vec<BackupIssue>,
+) {
+    let finalizations = read_artifacts::<pb::Finalization, Finalization>(dir, height, issues);
+    let notarizations = read_artifacts::<pb::Notarization, Notarization>(dir, height, issues);
+    let committee = match registry_version {
+        Some(registry_version) if !finalizations.is_empty() || !notarizations.is_empty() => {
+            read_previous_beacon(height, previous_dir)
+                .and_then(|beacon| {
+                    NotaryCommittee::new(registry, subnet_id, registry_version, &beacon)
+                })
+                .map_err(|error| issues.push(BackupIssue::UnknownNotaryCommittee { height, error }))
+                .ok()
+        }
+        _ => None,
+    };
+    match (registry_version, committee) {
+        (Some(registry_version), Some(committee)) => {
+            for (path, finalization) in finalizations {
+                let result = verify_multi_sig(crypto, &committee, &finalization, registry_version);
+                check_signed(
+                    height,
+                    path,
+                    result,
+                    &finalization.content.block,
+                    &mut entry,
+                    issues,
+                );
+            }
+            for (path, notarization) in notarizations {
+                let result = verify_multi_sig(crypto, &committee, &notarization, registry_version);
+                check_signed(
+                    height,
+                    path,
+                    result,
+                    &notarization.content.block,
+                    &mut entry,
+                    issues,
+                );
+            }
+        }
+        (None, _) if !finalizations.is_empty() || !notarizations.is_empty() => {
+            issues.push(BackupIssue::UnknownRegistryVersion { height })
+        }
+        _ => (),
+    }
+
+    let path = dir.join(CATCH_UP_PACKAGE_FILE);
+    if path.exists() {
+        match read_cup(&path) {
+            Ok((protobuf, cup)) => {
+                // The genesis catch-up package is not signed.
+                let result = if height.get() > 0 {
+                    crypto
+                        .verify_combined_threshold_sig_by_public_key(
+                            &CombinedThresholdSigOf::new(CombinedThresholdSig(protobuf.signature)),
+                            &CatchUpContentProtobufBytes(protobuf.content),
+                            subnet_id,
+                            cup.content.block.get_value().context.registry_version,
+                        )
+                        .map_err(|err| err.to_string())
+                } else {
+                    Ok(())
+                };
+                check_signed(
+                    height,
+                    path,
+                    result,
+                    cup.content.block.get_hash(),
+                    &mut entry,
+                    issues,
+                );
+                if let Some(entry) = entry.as_ref() {
+                    if cup.content.block.get_hash() != &entry.hash {
+                        issues.push(BackupIssue::ConflictingCatchUpPackage {
+                            height,
+                            path: dir.join(CATCH_UP_PACKAGE_FILE),
+                        });
+                    }
+                }
+            }
+            Err(error) => issues.push(BackupIssue::Unreadable {
+                height,
+                path,
+                error,
+            }),
+        }
+    }
+
+    if let Some(entry) = entry {
+        let path = dir.join(RANDOM_BEACON_FILE);
+        if path.exists() {
+            match decode::<pb::RandomBeacon, RandomBeacon>(&path) {
+                Ok(_) => entry.files.push(path),
+                Err(error) => issues.push(BackupIssue::Unreadable {
+                    height,
+                    path,
+                    error,
+                }),
+            }
+        }
+        let path = dir.join(RANDOM_TAPE_FILE);
+        if path.exists() {
+            match decode::<pb::RandomTape, RandomTape>(&path) {
+                Ok(_) => entry.files.push(path),
+                Err(error) => issues.push(BackupIssue::Unreadable {
+                    height,
+                    path,
+                    error,
+                }),
+            }
+        }
+    }
+}
+
+// Records the result of a signature verification. Files of artifacts with a
+// valid signature on the finalized block are added to the chain entry.
+fn check_signed(
+    height: Height,
+    path: PathBuf,
+    result: Result<(), String>,
+    block: &CryptoHashOf<Block>,
+    entry: &mut Option<&mut ChainEntry>,
+    issues: &mut Vec<BackupIssue>,
+) {
+    match (result, entry) {
+        (Err(error), _) => issues.push(BackupIssue::InvalidSignature {
+            height,
+            path,
+            error,
+        }),
+        (Ok(()), Some(entry)) if &entry.hash == block => entry.files.push(path),
+        (Ok(()), _) => (),
+    }
+}
+
+// The notarization committee of a height, which signs its notarizations and
+// finalizations.
+struct NotaryCommittee {
+    members: BTreeSet<NodeId>,
+    threshold: usize,
+}
+
+impl NotaryCommittee {
+    // Samples the committee from the subnet nodes at the given registry
+    // version with the random beacon of the previous height. This has to
+    // match the sampling of `Membership` in the consensus crate.
+    fn new(
+        registry: &dyn RegistryClient,
+        subnet_id: SubnetId,
+        registry_version: RegistryVersion,
+        previous_beacon: &RandomBeacon,
+    ) -> Result<Self, String> {
+        let mut nodes = registry
+            .get_node_ids_on_subnet(subnet_id, registry_version)
+            .map_err(|err| format!("{:?}", err))?
+            .ok_or_else(|| {
+                format!(
+                    "subnet {} not found at registry version {}",
+                    subnet_id, registry_version
+                )
+            })?;
+        nodes.sort_unstable();
+        let mut rng = Csprng::from_random_beacon_and_purpose(
+            previous_beacon,
+            &RandomnessPurpose::CommitteeSampling,
+        );
+        nodes.shuffle(&mut rng);
+        let committee_size = get_committee_size(nodes.len());
+        Ok(Self {
+            members: nodes.into_iter().take(committee_size).collect(),
+            threshold: committee_size - get_faults_tolerated(committee_size),
+        })
+    }
+}
+
+// Reads the random beacon of the height below the given one, which is either
+// stored on its own or as part of a catch-up package.
+fn read_previous_beacon(
+    height: Height,
+    previous_dir: Option<&Path>,
+) -> Result<RandomBeacon, String> {
+    let previous_height = height.decrement();
+    let dir = match previous_dir {
+        Some(dir) if height.get() > 0 => dir,
+        _ => return Err(format!("no random beacon at height {}", previous_height)),
+    };
+    let path = dir.join(RANDOM_BEACON_FILE);
+    if path.exists() {
+        return decode::<pb::RandomBeacon, RandomBeacon>(&path);
+    }
+    let path = dir.join(CATCH_UP_PACKAGE_FILE);
+    if path.exists() {
+        return read_cup(&path).map(|(_, cup)| cup.content.random_beacon.into_inner());
+    }
+    Err(format!("no random beacon at height {}", previous_height))
+}
+
+fn verify_multi_sig<C, T>(
+    crypto: &C,
+    committee: &NotaryCommittee,
+    artifact: &Signed<T, MultiSignature<T>>,
+    registry_version: RegistryVersion,
+) -> Result<(), String>
+where
+    C: MultiSigVerifier<T>,
+    T: Signable,
+{
+    let signers: BTreeSet<_> = artifact.signature.signers.iter().cloned().collect();
+    if signers.len() != artifact.signature.signers.len() {
+        return Err("repeated signers".to_string());
+    }
+    if signers.len() < committee.threshold {
+        return Err(format!(
+            "{} signers are below the threshold of {}",
+            signers.len(),
+            committee.threshold
+        ));
+    }
+    if let Some(signer) = signers.difference(&committee.members).next() {
+        return Err(format!(
+            "signer {} is not in the notarization committee",
+            signer
+        ));
+    }
+    crypto
+        .verify_multi_sig_combined(
+            &artifact.signature.signature,
+            &artifact.content,
+            signers,
+            registry_version,
+        )
+        .map_err(|err| err.to_string())
+}
+
+// Reads all artifacts of the given type at the given height. Unreadable files
+// are reported as issues and skipped.
+fn read_artifacts<P, T>(
+    dir: &Path,
+    height: Height,
+    issues: &mut Vec<BackupIssue>,
+) -> Vec<(PathBuf, T)>
+where
+    P: Message + Default,
+    T: TryFrom<P> + ArtifactFile,
+    T::Error: fmt::Debug,
+{
+    let paths = files_with_prefix(dir, T::PREFIX).unwrap_or_else(|err| {
+        issues.push(unreadable(height, dir, err));
+        Vec::new()
+    });
+    paths
+        .into_iter()
+        .filter_map(|path| match decode::<P, T>(&path) {
+            Ok(artifact) => Some((path, artifact)),
+            Err(error) => {
+                issues.push(BackupIssue::Unreadable {
+                    height,
+                    path,
+                    error,
+                });
+                None
+            }
+        })
+        .collect()
+}
+
+// Artifacts of which several can be stored per height.
+trait ArtifactFile {
+    const PREFIX: &'static str;
+}
+
+impl ArtifactFile for Finalization {
+    const PREFIX: &'static str = FINALIZATION_PREFIX;
+}
+
+impl ArtifactFile for Notarization {
+    const PREFIX: &'static str = NOTARIZATION_PREFIX;
+}
+
+fn files_with_prefix(dir: &Path, prefix: &str) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let matches = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| name.starts_with(prefix));
+        if matches {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn decode<P, T>(path: &Path) -> Result<T, String>
+where
+    P: Message + Default,
+    T: TryFrom<P>,
+    T::Error: fmt::Debug,
+{
+    let bytes = fs::read(path).map_err(|err| err.to_string())?;
+    let protobuf = P::decode(bytes.as_slice()).map_err(|err| err.to_string())?;
+    T::try_from(protobuf).map_err(|err| format!("{:?}", err))
+}
+
+fn read_cup(path: &Path) -> Result<(pb::CatchUpPackage, CatchUpPackage), String> {
+    let bytes = fs::read(path).map_err(|err| err.to_string())?;
+    let protobuf = pb::CatchUpPackage::decode(bytes.as_slice()).map_err(|err| err.to_string())?;
+    let cup = CatchUpPackage::try_from(&protobuf).map_err(|err| format!("{:?}", err))?;
+    Ok((protobuf, cup))
+}
+
+fn unreadable(height: Height, path: &Path, err: io::Error) -> BackupIssue {
+    BackupIssue::Unreadable {
+        height,
+        path: path.to_path_buf(),
+        error: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{store_artifacts, Groups};
+    use ic_config::artifact_pool::BACKUP_GROUP_SIZE;
+    use ic_test_utilities::{
+        consensus::{fake::*, make_genesis},
+        crypto::CryptoReturningOk,
+        registry::{setup_registry, SubnetRecordBuilder},
+        types::ids::{node_test_id, subnet_test_id},
+    };
+    use ic_types::consensus::{dkg::Summary, ConsensusMessage, RandomTapeContent};
+    use std::io::Read;
+    use std::sync::Arc;
+
+    // Returns a registry in which the fake genesis summary's subnet consists
+    // of the single node signing all artifacts of `store_chain`.
+    fn registry() -> Arc<dyn RegistryClient> {
+        setup_registry(
+            subnet_test_id(0),
+            vec![(1, SubnetRecordBuilder::from(&[node_test_id(0)]).build())],
+        )
+    }
+
+    fn signed<T>(content: T) -> Signed<T, MultiSignature<T>> {
+        let mut artifact = Signed::<T, MultiSignature<T>>::fake(content);
+        artifact.signature.signers = vec![node_test_id(0)];
+        artifact
+    }
+
+    // Stores a finalized chain of the given length on top of the genesis
+    // catch-up package, and returns the hashes of all blocks.
+    fn store_chain(path: &Path, length: u64) -> Vec<CryptoHashOf<Block>> {
+        let cup = make_genesis(Summary::fake());
+        let mut parent = cup.content.block.clone().into_inner();
+        let mut beacon = cup.content.random_beacon.clone().into_inner();
+        let mut hashes = vec![cup.content.block.get_hash().clone()];
+        let mut artifacts = vec![ConsensusMessage::CatchUpPackage(cup)];
+        for _ in 0..length {
+            let block = Block::from_parent(&parent);
+            let proposal = BlockProposal::fake(block.clone(), node_test_id(0));
+            let hash = proposal.content.get_hash().clone();
+            beacon = RandomBeacon::from_parent(&beacon);
+            artifacts.push(ConsensusMessage::Notarization(signed(
+                NotarizationContent::new(block.height, hash.clone()),
+            )));
+            artifacts.push(ConsensusMessage::Finalization(signed(
+                FinalizationContent::new(block.height, hash.clone()),
+            )));
+            artifacts.push(ConsensusMessage::RandomBeacon(beacon.clone()));
+            artifacts.push(ConsensusMessage::RandomTape(RandomTape::fake(
+                RandomTapeContent::new(block.height),
+            )));
+            artifacts.push(ConsensusMessage::BlockProposal(proposal));
+            hashes.push(hash);
+            parent = block;
+        }
+        store_artifacts(artifacts, path, &mut Groups::default(), BACKUP_GROUP_SIZE).unwrap();
+        hashes
+    }
+
+    fn proposal_dir(path: &Path, height: u64) -> PathBuf {
+        path.join((height / BACKUP_GROUP_SIZE * BACKUP_GROUP_SIZE).to_string())
+            .join(height.to_string())
+    }
+
+    #[test]
+    fn test_verify_linked_chain() {
+        let tmp = tempfile::tempdir().unwrap();
+        store_chain(tmp.path(), 10);
+        let report = verify_backup(
+            &CryptoReturningOk::default(),
+            registry().as_ref(),
+            subnet_test_id(0),
+            tmp.path(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.finalized_tip, Some(Height::from(10)));
+        assert_eq!(
+            report.verified_range,
+            Some((Height::from(0), Height::from(10)))
+        );
+    }
+
+    #[test]
+    fn test_missing_proposal_is_reported_as_gap() {
+        let tmp = tempfile::tempdir().unwrap();
+        let hashes = store_chain(tmp.path(), 10);
+        for path in files_with_prefix(&proposal_dir(tmp.path(), 5), BLOCK_PROPOSAL_PREFIX).unwrap()
+        {
+            fs::remove_file(path).unwrap();
+        }
+        let report = verify_backup(
+            &CryptoReturningOk::default(),
+            registry().as_ref(),
+            subnet_test_id(0),
+            tmp.path(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(report.gaps, vec![(Height::from(5), Height::from(5))]);
+        assert_eq!(
+            report.issues,
+            vec![BackupIssue::BrokenChain {
+                height: Height::from(5),
+                expected: hashes[5].clone(),
+            }]
+        );
+        assert_eq!(
+            report.verified_range,
+            Some((Height::from(0), Height::from(4)))
+        );
+    }
+
+    #[test]
+    fn test_signers_outside_notary_committee_are_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let hashes = store_chain(tmp.path(), 3);
+        let height = Height::from(2);
+        let dir = proposal_dir(tmp.path(), 2);
+        let path = |prefix: &str| files_with_prefix(&dir, prefix).unwrap().remove(0);
+
+        // A notarization without signers is below the threshold.
+        let mut notarization = signed(NotarizationContent::new(height, hashes[2].clone()));
+        notarization.signature.signers.clear();
+        let notarization_path = path(NOTARIZATION_PREFIX);
+        fs::write(
+            &notarization_path,
+            pb::Notarization::from(&notarization).encode_to_vec(),
+        )
+        .unwrap();
+        // A finalization signed by a node of another subnet.
+        let mut finalization = signed(FinalizationContent::new(height, hashes[2].clone()));
+        finalization.signature.signers = vec![node_test_id(1)];
+        let finalization_path = path(FINALIZATION_PREFIX);
+        fs::write(
+            &finalization_path,
+            pb::Finalization::from(&finalization).encode_to_vec(),
+        )
+        .unwrap();
+
+        let report = verify_backup(
+            &CryptoReturningOk::default(),
+            registry().as_ref(),
+            subnet_test_id(0),
+            tmp.path(),
+            None,
+            None,
+        )
+        .unwrap();
+        let invalid: BTreeSet<PathBuf> = report
+            .issues
+            .iter()
+            .map(|issue| match issue {
+                BackupIssue::InvalidSignature { path, .. } => path.clone(),
+                other => panic!("Unexpected issue {}", other),
+            })
+            .collect();
+        assert_eq!(
+            invalid,
+            vec![notarization_path, finalization_path]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            report.verified_range,
+            Some((Height::from(0), Height::from(1)))
+        );
+    }
+
+    #[test]
+    fn test_missing_random_beacon_is_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        store_chain(tmp.path(), 3);
+        fs::remove_file(proposal_dir(tmp.path(), 1).join(RANDOM_BEACON_FILE)).unwrap();
+        let report = verify_backup(
+            &CryptoReturningOk::default(),
+            registry().as_ref(),
+            subnet_test_id(0),
+            tmp.path(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            report.issues.as_slice(),
+            [BackupIssue::UnknownNotaryCommittee { height, .. }] if *height == Height::from(2)
+        ));
+        assert_eq!(
+            report.verified_range,
+            Some((Height::from(0), Height::from(1)))
+        );
+    }
+
+    #[test]
+    fn test_range_starting_inside_dkg_interval() {
+        let tmp = tempfile::tempdir().unwrap();
+        store_chain(tmp.path(), 10);
+        let report = verify_backup(
+            &CryptoReturningOk::default(),
+            registry().as_ref(),
+            subnet_test_id(0),
+            tmp.path(),
+            Some(Height::from(3)),
+            Some(Height::from(7)),
+        )
+        .unwrap();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(
+            report.verified_range,
+            Some((Height::from(3), Height::from(7)))
+        );
+    }
+
+    #[test]
+    fn test_archive_contains_verified_range() {
+        let tmp = tempfile::tempdir().unwrap();
+        store_chain(tmp.path(), 3);
+        let report = verify_backup(
+            &CryptoReturningOk::default(),
+            registry().as_ref(),
+            subnet_test_id(0),
+            tmp.path(),
+            None,
+            None,
+        )
+        .unwrap();
+        let archive_path = tmp.path().join("backup.tar.gz");
+        write_archive(&report, tmp.path(), &archive_path).unwrap();
+
+        let mut bytes = Vec::new();
+        fs::File::open(&archive_path)
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(bytes.as_slice()));
+        let names: BTreeSet<String> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect();
+        // The genesis CUP, and a proposal, notarization, finalization, random
+        // beacon and random tape at each of the 3 heights above it.
+        assert_eq!(names.len(), 1 + 3 * 5);
+        assert!(names.contains("0/0/catch_up_package.bin"));
+        assert!(names.contains("0/3/random_tape.bin"));
+    }
+}
@@ -1,16 +1,19 @@
 use clap::{App, Arg, SubCommand};
 use ic_artifact_pool::{
+    backup_verifier::{verify_backup, write_archive},
     certification_pool::CertificationPoolImpl,
     consensus_pool::{PoolSectionOps, UncachedConsensusPoolImpl},
 };
-use ic_config::artifact_pool::ArtifactPoolConfig;
+use ic_config::{artifact_pool::ArtifactPoolConfig, registry_client::DataProviderConfig};
 use ic_consensus_message::ConsensusMessageHashable;
 use ic_interfaces::consensus_pool::*;
 use ic_logger::{LoggerImpl, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
+use ic_registry_client::client::{create_data_provider, RegistryClientImpl};
 use ic_types::{
     consensus::{certification::CertificationMessage, CatchUpPackage},
     time::current_time,
+    Height, PrincipalId, SubnetId,
 };
 use prost::Message;
 use serde::{Deserialize, Serialize};
@@ -19,7 +22,9 @@ use serde_json::{Deserializer, Serializer};
 use std::convert::TryFrom;
 use std::io::BufRead;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
 
 fn main() {
     let app = App::new("ic-consensus-pool-util")
@@ -52,7 +57,54 @@ fn main() {
                         .takes_value(true),
                 ),
         )
-        .args_from_usage("<PATH>       'PATH to the consensus pool directory'");
+        .subcommand(
+            SubCommand::with_name("verify-backup")
+                .about(
+                    "Verify the finalized chain and the signatures of a backup directory, \
+                     given as PATH",
+                )
+                .arg(
+                    Arg::with_name("registry-local-store")
+                        .long("registry-local-store")
+                        .value_name("DIR")
+                        .help("Path to the registry local store")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("subnet-id")
+                        .long("subnet-id")
+                        .value_name("ID")
+                        .help("Id of the subnet the backup belongs to")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("HEIGHT")
+                        .help("Lowest height to verify")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("HEIGHT")
+                        .help("Highest height to verify")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("archive")
+                        .long("archive")
+                        .value_name("FILE")
+                        .help("Write the verified range into a .tar.gz archive")
+                        .takes_value(true),
+                ),
+        )
+        .args_from_usage(
+            "<PATH>       'PATH to the consensus pool directory, or to the backup directory of a \
+             replica version for verify-backup'",
+        );
     let mut help = Vec::new();
     app.write_help(&mut help)
         .expect("Unable to output help message");
@@ -66,6 +118,8 @@ fn main() {
         import(path)
     } else if let Some(matches) = matches.subcommand_matches("export-cup-proto") {
         export_cup_proto(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("verify-backup") {
+        verify_backup_dir(path, matches)
     } else {
         eprintln!(
             "{}",
@@ -274,3 +328,54 @@ fn export_cup_proto(path: &str, matches: &clap::ArgMatches) {
     file.write_all(&buf)
         .unwrap_or_else(|err| panic!("Cannot write to file {}: {:?}", filename, err));
 }
+
+fn parse_height(matches: &clap::ArgMatches, name: &str) -> Option<Height> {
+    matches.value_of(name).map(|height| {
+        Height::from(
+            height
+                .parse::<u64>()
+                .unwrap_or_else(|err| panic!("Invalid height {}: {:?}", height, err)),
+        )
+    })
+}
+
+fn verify_backup_dir(path: &str, matches: &clap::ArgMatches) {
+    let local_store = matches
+        .value_of("registry-local-store")
+        .expect("Expect a registry local store");
+    let subnet_id = matches.value_of("subnet-id").expect("Expect a subnet id");
+    let subnet_id = SubnetId::from(
+        PrincipalId::from_str(subnet_id)
+            .unwrap_or_else(|err| panic!("Invalid subnet id {}: {:?}", subnet_id, err)),
+    );
+
+    let data_provider = create_data_provider(
+        &DataProviderConfig::LocalStore(PathBuf::from(local_store)),
+        None,
+    );
+    let registry = Arc::new(RegistryClientImpl::new(data_provider, None));
+    registry
+        .poll_once()
+        .expect("Couldn't poll the registry data provider");
+    let crypto = ic_crypto::CryptoComponentFatClient::new_for_verification_only(registry.clone());
+
+    let report = verify_backup(
+        &crypto,
+        registry.as_ref(),
+        subnet_id,
+        Path::new(path),
+        parse_height(matches, "from"),
+        parse_height(matches, "to"),
+    )
+    .unwrap_or_else(|err| panic!("Cannot read backup directory {}: {:?}", path, err));
+    print!("{}", report);
+
+    if let Some(filename) = matches.value_of("archive") {
+        write_archive(&report, Path::new(path), Path::new(filename))
+            .unwrap_or_else(|err| panic!("Cannot write archive {}: {:?}", filename, err));
+        println!("Archive written to {}", filename);
+    }
+    if !report.is_ok() {
+        std::process::exit(1);
+    }
+}
@@ -56,6 +56,13 @@ impl CertificationPoolImpl {
                     config, log,
                 ),
             ) as Box<_>,
+            PersistentPoolBackend::AppendLog(append_log_config) => Box::new(
+                crate::append_log_pool::PersistentHeightIndexedPool::new_certification_pool(
+                    append_log_config,
+                    config.persistent_pool_read_only,
+                    log,
+                ),
+            ) as Box<_>,
             #[allow(unreachable_patterns)]
             cfg => {
                 unimplemented!("Configuration {:?} is not supported", cfg)
@@ -4,28 +4,37 @@ use crate::{
         get_highest_catch_up_package, get_highest_finalized_block, update_summary_block,
         ConsensusBlockChainImpl, ConsensusCacheImpl,
     },
+    equivocation_evidence::EquivocationEvidenceStore,
     inmemory_pool::InMemoryPoolSection,
     metrics::{LABEL_POOL_TYPE, POOL_TYPE_UNVALIDATED, POOL_TYPE_VALIDATED},
+    peer_index::PeerIndex,
+    pool_snapshot::read_pool_snapshot,
 };
 use ic_config::artifact_pool::{ArtifactPoolConfig, PersistentPoolBackend};
 use ic_consensus_message::ConsensusMessageHashable;
 use ic_interfaces::{
+    artifact_pool::ArtifactPoolError,
     consensus_pool::{
         ChangeAction, ChangeSet, ConsensusBlockCache, ConsensusBlockChain, ConsensusPool,
         ConsensusPoolCache, HeightIndexedPool, HeightRange, MutableConsensusPool, PoolSection,
         UnvalidatedConsensusArtifact, ValidatedConsensusArtifact,
     },
-    gossip_pool::{ConsensusGossipPool, GossipPool},
+    gossip_pool::{ConsensusGossipPool, GossipPool, PeerQuotaChecker},
+    registry::RegistryClient,
     time_source::TimeSource,
 };
-use ic_logger::ReplicaLogger;
+use ic_logger::{warn, ReplicaLogger};
+use ic_protobuf::{registry::subnet::v1::ConsensusParameters, types::v1 as pb};
+use ic_registry_client::helper::subnet::SubnetRegistry;
 use ic_types::{
-    artifact::ConsensusMessageId, consensus::catchup::CUPWithOriginalProtobuf, consensus::*,
-    Height, SubnetId, Time,
+    artifact::ConsensusMessageId,
+    consensus::{catchup::CUPWithOriginalProtobuf, equivocation::EquivocationEvidence, *},
+    Height, NodeId, RegistryVersion, SubnetId, Time,
 };
 use prometheus::{labels, opts, IntGauge};
+use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 #[derive(Debug, Clone)]
@@ -194,16 +203,87 @@ pub struct ConsensusPoolImpl {
     unvalidated_metrics: PoolMetrics,
     cache: Arc<ConsensusCacheImpl>,
     backup: Option<Backup>,
+    equivocation_evidence: EquivocationEvidenceStore,
+    consensus_parameters: Option<ConsensusParametersReader>,
+    // Counts the artifacts of each peer in the unvalidated section.
+    peer_index: PeerQuota,
+    // The peer that sent each artifact in the unvalidated section.
+    unvalidated_peers: HashMap<ConsensusMessageId, NodeId>,
+}
+
+// The counts of unvalidated artifacts per peer. They are shared with the
+// artifact manager, which checks the quota of every incoming artifact, so
+// that it does not need to take the lock of the pool for it.
+#[derive(Clone)]
+struct PeerQuota(Arc<RwLock<PeerIndex>>);
+
+impl PeerQuotaChecker for PeerQuota {
+    fn check_quota(&self, peer_id: &NodeId) -> Result<(), ArtifactPoolError> {
+        if self.0.read().unwrap().get_remaining_quota(peer_id) == 0 {
+            return Err(ArtifactPoolError::InsufficientQuotaError);
+        }
+        Ok(())
+    }
+}
+
+// Reads the `ConsensusParameters` from the subnet record at the registry
+// version of the current DKG interval.
+struct ConsensusParametersReader {
+    subnet_id: SubnetId,
+    registry_client: Arc<dyn RegistryClient>,
+    // The registry version the parameters were read at.
+    registry_version: Option<RegistryVersion>,
+    parameters: Option<ConsensusParameters>,
+    log: ReplicaLogger,
+}
+
+impl ConsensusParametersReader {
+    // Reads the parameters if the DKG interval of `summary_block` uses a new
+    // registry version. Returns true if they were read.
+    fn update(&mut self, summary_block: &Block) -> bool {
+        let registry_version = summary_block
+            .payload
+            .as_ref()
+            .as_summary()
+            .dkg
+            .registry_version;
+        if self.registry_version == Some(registry_version) {
+            return false;
+        }
+        match self
+            .registry_client
+            .get_consensus_parameters(self.subnet_id, registry_version)
+        {
+            Ok(parameters) => {
+                self.registry_version = Some(registry_version);
+                self.parameters = parameters.flatten();
+                true
+            }
+            Err(err) => {
+                warn!(
+                    self.log,
+                    "Could not retrieve consensus parameters from the registry: {:?}", err
+                );
+                false
+            }
+        }
+    }
 }
 
 // A temporary pool implementation used for genesis initialization.
 pub struct UncachedConsensusPoolImpl {
     pub validated: Box<dyn InitializablePoolSection + Send + Sync>,
     unvalidated: Box<dyn MutablePoolSection<UnvalidatedConsensusArtifact> + Send + Sync>,
+    equivocation_evidence: EquivocationEvidenceStore,
 }
 
 impl UncachedConsensusPoolImpl {
     pub fn new(config: ArtifactPoolConfig, log: ReplicaLogger) -> UncachedConsensusPoolImpl {
+        let equivocation_evidence = EquivocationEvidenceStore::new(
+            &config.persistent_pool_db_path(),
+            config.persistent_pool_read_only,
+            log.clone(),
+        );
         let validated = match config.persistent_pool_backend {
             PersistentPoolBackend::Lmdb(lmdb_config) => Box::new(
                 crate::lmdb_pool::PersistentHeightIndexedPool::new_consensus_pool(
@@ -219,6 +299,13 @@ impl UncachedConsensusPoolImpl {
                     log.clone(),
                 ),
             ) as Box<_>,
+            PersistentPoolBackend::AppendLog(append_log_config) => Box::new(
+                crate::append_log_pool::PersistentHeightIndexedPool::new_consensus_pool(
+                    append_log_config,
+                    config.persistent_pool_read_only,
+                    log.clone(),
+                ),
+            ) as Box<_>,
             #[allow(unreachable_patterns)]
             cfg => {
                 unimplemented!("Configuration {:?} is not supported", cfg)
@@ -228,6 +315,7 @@ impl UncachedConsensusPoolImpl {
         UncachedConsensusPoolImpl {
             validated,
             unvalidated: Box::new(InMemoryPoolSection::new(log)),
+            equivocation_evidence,
         }
     }
 }
@@ -290,6 +378,10 @@ impl ConsensusPool for UncachedConsensusPoolImpl {
     fn as_block_cache(&self) -> &dyn ConsensusBlockCache {
         self
     }
+
+    fn equivocation_evidence(&self) -> Vec<EquivocationEvidence> {
+        self.equivocation_evidence.get_all()
+    }
 }
 
 impl ConsensusPoolImpl {
@@ -297,18 +389,53 @@ impl ConsensusPoolImpl {
     /// the given `catch_up_package`. If a catch-up package already exists in
     /// the validated pool, the one that is greater (with respect to
     /// height and registry version) will be used.
+    ///
+    /// The `ConsensusParameters` of the subnet are read from `registry_client`
+    /// at the start of each DKG interval.
     pub fn new(
         subnet_id: SubnetId,
         catch_up_package: CUPWithOriginalProtobuf,
         config: ArtifactPoolConfig,
+        registry_client: Arc<dyn RegistryClient>,
+        registry: ic_metrics::MetricsRegistry,
+        log: ReplicaLogger,
+    ) -> ConsensusPoolImpl {
+        Self::new_with_optional_registry_client(
+            subnet_id,
+            catch_up_package,
+            config,
+            Some(registry_client),
+            registry,
+            log,
+        )
+    }
+
+    fn new_with_optional_registry_client(
+        subnet_id: SubnetId,
+        catch_up_package: CUPWithOriginalProtobuf,
+        config: ArtifactPoolConfig,
+        registry_client: Option<Arc<dyn RegistryClient>>,
         registry: ic_metrics::MetricsRegistry,
         log: ReplicaLogger,
     ) -> ConsensusPoolImpl {
         let mut pool = UncachedConsensusPoolImpl::new(config.clone(), log.clone());
         Self::init_genesis(catch_up_package, pool.validated.as_mut());
         let mut pool = Self::from_uncached(pool, registry.clone());
+        pool.consensus_parameters =
+            registry_client.map(|registry_client| ConsensusParametersReader {
+                subnet_id,
+                registry_client,
+                registry_version: None,
+                parameters: None,
+                log: log.clone(),
+            });
+        pool.update_consensus_parameters();
         // If the back up directory is set, instantiate the backup component
         // and create a subdirectory with the subnet id as directory name.
+        let consensus_parameters = pool
+            .consensus_parameters
+            .as_ref()
+            .and_then(|reader| reader.parameters.clone());
         pool.backup = config.backup_config.map(|config| {
             Backup::new(
                 &pool,
@@ -319,6 +446,7 @@ impl ConsensusPoolImpl {
                     .join(ic_types::ReplicaVersion::default().to_string()),
                 Duration::from_secs(config.retention_time_secs),
                 Duration::from_secs(config.purging_interval_secs),
+                consensus_parameters.as_ref(),
                 registry,
                 log,
             )
@@ -364,13 +492,19 @@ impl ConsensusPoolImpl {
         ConsensusPoolImpl {
             validated: uncached.validated,
             unvalidated: uncached.unvalidated,
+            equivocation_evidence: uncached.equivocation_evidence,
             validated_metrics: PoolMetrics::new(registry.clone(), POOL_TYPE_VALIDATED),
             unvalidated_metrics: PoolMetrics::new(registry, POOL_TYPE_UNVALIDATED),
             cache,
             backup: None,
+            consensus_parameters: None,
+            peer_index: PeerQuota(Arc::new(RwLock::new(PeerIndex::new(usize::MAX)))),
+            unvalidated_peers: HashMap::new(),
         }
     }
 
+    /// Creates a pool without a registry client, which always uses the default
+    /// consensus parameters.
     pub fn new_from_cup_without_bytes(
         subnet_id: SubnetId,
         catch_up_package: CatchUpPackage,
@@ -378,15 +512,52 @@ impl ConsensusPoolImpl {
         registry: ic_metrics::MetricsRegistry,
         log: ReplicaLogger,
     ) -> ConsensusPoolImpl {
-        Self::new(
+        Self::new_with_optional_registry_client(
             subnet_id,
             CUPWithOriginalProtobuf::from_cup(catch_up_package),
             config,
+            None,
             registry,
             log,
         )
     }
 
+    /// Inserts the artifacts of a snapshot served by a peer into the
+    /// unvalidated section, where they are validated like any artifact
+    /// received through gossip (see `pool_snapshot`). The artifacts are
+    /// attributed to `node_id`, which should be the id of this node, so that
+    /// they don't count towards the quota of any peer.
+    ///
+    /// Returns the number of inserted artifacts, or an error if the snapshot
+    /// cannot be decoded or was taken above a different catch-up package than
+    /// the one of this pool.
+    pub fn insert_snapshot(
+        &mut self,
+        snapshot: pb::ConsensusPoolSnapshot,
+        node_id: NodeId,
+        timestamp: Time,
+    ) -> Result<usize, String> {
+        let snapshot_cup_content = snapshot
+            .catch_up_package
+            .as_ref()
+            .map(|cup| cup.content.clone());
+        if snapshot_cup_content != Some(self.cache.cup_with_protobuf().protobuf.content) {
+            return Err("The snapshot was taken above a different catch-up package".to_string());
+        }
+        let (_, artifacts) = read_pool_snapshot(snapshot)?;
+        let count = artifacts.len();
+        let mut ops = PoolSectionOps::new();
+        for message in artifacts {
+            ops.insert(UnvalidatedConsensusArtifact {
+                message,
+                peer_id: node_id,
+                timestamp,
+            });
+        }
+        self.apply_changes_unvalidated(ops);
+        Ok(count)
+    }
+
     /// Get a copy of ConsensusPoolCache.
     pub fn get_cache(&self) -> Arc<dyn ConsensusPoolCache> {
         Arc::clone(&self.cache) as Arc<_>
@@ -404,8 +575,64 @@ impl ConsensusPoolImpl {
         }
     }
 
+    // Applies the consensus parameters from the registry, if the DKG interval
+    // uses a new registry version. Without parameters, there is no limit on
+    // the unvalidated artifacts per peer.
+    fn update_consensus_parameters(&mut self) {
+        let summary_block = self.cache.summary_block();
+        let reader = match &mut self.consensus_parameters {
+            Some(reader) => reader,
+            None => return,
+        };
+        if !reader.update(&summary_block) {
+            return;
+        }
+        let parameters = reader.parameters.as_ref();
+        self.peer_index.0.write().unwrap().set_max_quota_per_peer(
+            parameters.map_or(usize::MAX, |parameters| {
+                parameters.max_unvalidated_artifacts_per_peer as usize
+            }),
+        );
+        if let Some(backup) = &mut self.backup {
+            backup.set_consensus_parameters(parameters);
+        }
+    }
+
+    // Keeps track of the peers that sent the artifacts in the unvalidated
+    // section.
+    fn update_unvalidated_peers(&mut self, ops: &PoolSectionOps<UnvalidatedConsensusArtifact>) {
+        let mut peer_index = self.peer_index.0.write().unwrap();
+        for op in &ops.ops {
+            match op {
+                PoolSectionOp::Insert(artifact) => {
+                    let id = artifact.message.get_id();
+                    if !self.unvalidated_peers.contains_key(&id) {
+                        peer_index.insert(artifact.peer_id, 1);
+                        self.unvalidated_peers.insert(id, artifact.peer_id);
+                    }
+                }
+                PoolSectionOp::Remove(id) => {
+                    if let Some(peer_id) = self.unvalidated_peers.remove(id) {
+                        peer_index.remove(peer_id, 1);
+                    }
+                }
+                PoolSectionOp::PurgeBelow(height) => {
+                    self.unvalidated_peers.retain(|id, peer_id| {
+                        if id.height < *height {
+                            peer_index.remove(*peer_id, 1);
+                            false
+                        } else {
+                            true
+                        }
+                    });
+                }
+            }
+        }
+    }
+
     fn apply_changes_unvalidated(&mut self, ops: PoolSectionOps<UnvalidatedConsensusArtifact>) {
         if !ops.ops.is_empty() {
+            self.update_unvalidated_peers(&ops);
             self.unvalidated.mutate(ops);
             self.unvalidated_metrics
                 .update(self.unvalidated.pool_section());
@@ -429,6 +656,10 @@ impl ConsensusPool for ConsensusPoolImpl {
     fn as_block_cache(&self) -> &dyn ConsensusBlockCache {
         self.cache.as_ref()
     }
+
+    fn equivocation_evidence(&self) -> Vec<EquivocationEvidence> {
+        self.equivocation_evidence.get_all()
+    }
 }
 
 impl MutableConsensusPool for ConsensusPoolImpl {
@@ -480,6 +711,9 @@ impl MutableConsensusPool for ConsensusPoolImpl {
                 ChangeAction::HandleInvalid(to_remove, _) => {
                     unvalidated_ops.remove(to_remove.get_id());
                 }
+                ChangeAction::RecordEquivocation(evidence) => {
+                    self.equivocation_evidence.record(evidence);
+                }
             }
         }
 
@@ -498,6 +732,7 @@ impl MutableConsensusPool for ConsensusPoolImpl {
         }
         if !updates.is_empty() {
             self.cache.update(self, updates);
+            self.update_consensus_parameters();
         }
     }
 }
@@ -506,6 +741,14 @@ impl GossipPool<ConsensusMessage, ChangeSet> for ConsensusPoolImpl {
     type MessageId = ConsensusMessageId;
     type Filter = Height;
 
+    fn check_quota(
+        &self,
+        _msg: &ConsensusMessage,
+        peer_id: &NodeId,
+    ) -> Result<(), ArtifactPoolError> {
+        self.peer_index.check_quota(peer_id)
+    }
+
     fn contains(&self, id: &ConsensusMessageId) -> bool {
         self.unvalidated.contains(id) || self.validated.contains(id)
     }
@@ -695,7 +938,11 @@ impl GossipPool<ConsensusMessage, ChangeSet> for ConsensusPoolImpl {
     }
 }
 
-impl ConsensusGossipPool for ConsensusPoolImpl {}
+impl ConsensusGossipPool for ConsensusPoolImpl {
+    fn peer_quota_checker(&self) -> Arc<dyn PeerQuotaChecker> {
+        Arc::new(self.peer_index.clone())
+    }
+}
 
 /// Returns the block chain cache between the given start/end
 /// blocks (inclusive)
@@ -729,6 +976,201 @@ mod tests {
     use prost::Message;
     use std::{convert::TryFrom, fs, io::Read, time::Instant};
 
+    #[test]
+    fn test_unvalidated_quota_per_peer() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let subnet_id = subnet_test_id(0);
+            let registry_client = ic_test_utilities::registry::setup_registry(
+                subnet_id,
+                vec![(
+                    1,
+                    ic_test_utilities::registry::SubnetRecordBuilder::from(&[node_test_id(0)])
+                        .with_consensus_parameters(ConsensusParameters {
+                            catch_up_package_retention_heights: 50,
+                            max_unvalidated_artifacts_per_peer: 2,
+                            backup_group_size: 10000,
+                            backup_purging_interval_secs: 3600,
+                            catch_up_package_interval_length: 0,
+                        })
+                        .build(),
+                )],
+            );
+            let time_source = FastForwardTimeSource::new();
+            let mut pool = ConsensusPoolImpl::new(
+                subnet_id,
+                CUPWithOriginalProtobuf::from_cup(make_genesis(
+                    ic_types::consensus::dkg::Summary::fake(),
+                )),
+                pool_config,
+                registry_client,
+                MetricsRegistry::new(),
+                no_op_logger(),
+            );
+            let random_beacon = |height: u64| {
+                RandomBeacon::fake(RandomBeaconContent::new(
+                    Height::from(height),
+                    CryptoHashOf::from(CryptoHash(Vec::new())),
+                ))
+                .into_message()
+            };
+            let insert = |pool: &mut ConsensusPoolImpl, height: u64, peer_id: NodeId| {
+                pool.insert(UnvalidatedArtifact {
+                    message: random_beacon(height),
+                    peer_id,
+                    timestamp: time_source.get_relative_time(),
+                })
+            };
+
+            insert(&mut pool, 1, node_test_id(1));
+            assert!(pool
+                .check_quota(&random_beacon(2), &node_test_id(1))
+                .is_ok());
+            // Inserting the same artifact again does not count twice.
+            insert(&mut pool, 1, node_test_id(1));
+            assert!(pool
+                .check_quota(&random_beacon(2), &node_test_id(1))
+                .is_ok());
+            insert(&mut pool, 2, node_test_id(1));
+            assert!(pool
+                .check_quota(&random_beacon(3), &node_test_id(1))
+                .is_err());
+            assert!(pool
+                .check_quota(&random_beacon(3), &node_test_id(2))
+                .is_ok());
+            // The quota can be checked without access to the pool.
+            let checker = pool.peer_quota_checker();
+            assert!(checker.check_quota(&node_test_id(1)).is_err());
+            assert!(checker.check_quota(&node_test_id(2)).is_ok());
+
+            // Removed and purged artifacts free up the quota.
+            pool.apply_changes(
+                time_source.as_ref(),
+                vec![ChangeAction::RemoveFromUnvalidated(random_beacon(2))],
+            );
+            assert!(pool
+                .check_quota(&random_beacon(3), &node_test_id(1))
+                .is_ok());
+            assert!(checker.check_quota(&node_test_id(1)).is_ok());
+            insert(&mut pool, 3, node_test_id(1));
+            assert!(pool
+                .check_quota(&random_beacon(4), &node_test_id(1))
+                .is_err());
+            pool.apply_changes(
+                time_source.as_ref(),
+                vec![ChangeAction::PurgeUnvalidatedBelow(Height::from(3))],
+            );
+            assert!(pool
+                .check_quota(&random_beacon(4), &node_test_id(1))
+                .is_ok());
+        })
+    }
+
+    #[test]
+    fn test_pool_snapshot() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let time_source = FastForwardTimeSource::new();
+            let genesis = make_genesis(ic_types::consensus::dkg::Summary::fake());
+            let make_pool = |pool_config| {
+                ConsensusPoolImpl::new_from_cup_without_bytes(
+                    subnet_test_id(0),
+                    genesis.clone(),
+                    pool_config,
+                    MetricsRegistry::new(),
+                    no_op_logger(),
+                )
+            };
+            let mut pool = make_pool(pool_config);
+
+            // Build a chain of two notarized blocks, of which the second one
+            // is finalized.
+            let mut parent = genesis.content.block.get_hash().clone();
+            let mut changeset = Vec::new();
+            for height in 1..=2 {
+                let proposal = BlockProposal::fake(
+                    Block::new(
+                        parent,
+                        Payload::new(
+                            ic_crypto::crypto_hash,
+                            (ic_types::consensus::dkg::Summary::fake(), None).into(),
+                        ),
+                        Height::from(height),
+                        Rank(0),
+                        ValidationContext {
+                            registry_version: RegistryVersion::from(1),
+                            certified_height: Height::from(0),
+                            time: mock_time(),
+                        },
+                    ),
+                    node_test_id(1),
+                );
+                parent = proposal.content.get_hash().clone();
+                changeset.push(
+                    Notarization::fake(NotarizationContent::new(
+                        Height::from(height),
+                        parent.clone(),
+                    ))
+                    .into_message(),
+                );
+                changeset.push(proposal.into_message());
+            }
+            changeset.push(
+                Finalization::fake(FinalizationContent::new(Height::from(2), parent))
+                    .into_message(),
+            );
+            pool.apply_changes(
+                time_source.as_ref(),
+                changeset
+                    .into_iter()
+                    .map(ChangeAction::AddToValidated)
+                    .collect(),
+            );
+
+            let pool = std::sync::RwLock::new(pool);
+            let snapshot = crate::pool_snapshot::make_pool_snapshot(&pool);
+            assert_eq!(snapshot.block_proposals.len(), 2);
+            assert_eq!(snapshot.notarizations.len(), 2);
+            assert_eq!(snapshot.finalizations.len(), 1);
+
+            // A capped snapshot keeps the heights right above the CUP.
+            let capped_snapshot = crate::pool_snapshot::make_capped_pool_snapshot(
+                &pool,
+                prost::Message::encoded_len(&snapshot) - 1,
+            );
+            assert_eq!(
+                capped_snapshot.block_proposals,
+                snapshot.block_proposals[..1]
+            );
+            assert_eq!(capped_snapshot.notarizations.len(), 1);
+            assert_eq!(capped_snapshot.finalizations.len(), 0);
+
+            // A node starting from the same CUP validates the snapshot.
+            ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+                let mut new_pool = make_pool(pool_config);
+                assert_eq!(
+                    new_pool.insert_snapshot(
+                        snapshot.clone(),
+                        node_test_id(2),
+                        time_source.get_relative_time()
+                    ),
+                    Ok(5)
+                );
+                assert_eq!(new_pool.unvalidated().block_proposal().size(), 2);
+                assert_eq!(new_pool.unvalidated().finalization().size(), 1);
+
+                // A snapshot taken above another CUP is rejected.
+                let mut other_snapshot = snapshot;
+                other_snapshot.catch_up_package.as_mut().unwrap().content = Vec::new();
+                assert!(new_pool
+                    .insert_snapshot(
+                        other_snapshot,
+                        node_test_id(2),
+                        time_source.get_relative_time()
+                    )
+                    .is_err());
+            });
+        })
+    }
+
     #[test]
     fn test_timestamp() {
         ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
@@ -818,6 +1260,7 @@ mod tests {
                 Duration::from_millis(100),
                 // We purge every 5 milliseconds.
                 purging_interval,
+                None,
                 MetricsRegistry::new(),
                 no_op_logger(),
             ));
@@ -1089,6 +1532,7 @@ mod tests {
                 // Artifact retention time
                 Duration::from_millis(2700),
                 purging_interval,
+                None,
                 MetricsRegistry::new(),
                 no_op_logger(),
             ));
@@ -0,0 +1,167 @@
+//! Persistent store of the evidence of equivocation recorded by consensus.
+//!
+//! The evidence is kept next to the validated pool section, but is neither
+//! purged with it nor removed when the replica version changes: it is stored
+//! in the version independent protobuf format, one file per piece of evidence.
+//!
+//! A single pair of conflicting artifacts proves that a node misbehaved, so
+//! only the first evidence per node and kind of equivocation is kept. This
+//! bounds the size of the store by twice the number of nodes that ever were
+//! in the subnet.
+use ic_logger::{warn, ReplicaLogger};
+use ic_protobuf::types::v1 as pb;
+use ic_types::{
+    consensus::equivocation::{EquivocationEvidence, EquivocationKind},
+    NodeId,
+};
+use ic_utils::fs::write_protobuf_using_tmp_file;
+use prost::Message;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// The name of the directory within the persistent pool directory that holds
+/// the evidence.
+pub(crate) const EQUIVOCATION_EVIDENCE_DIR: &str = "equivocation_evidence";
+
+const EVIDENCE_EXTENSION: &str = "pb";
+
+pub(crate) struct EquivocationEvidenceStore {
+    path: PathBuf,
+    read_only: bool,
+    evidence: BTreeMap<(NodeId, EquivocationKind), EquivocationEvidence>,
+    log: ReplicaLogger,
+}
+
+impl EquivocationEvidenceStore {
+    /// Opens the store in the given persistent pool directory, loading the
+    /// evidence recorded so far. A read-only store keeps newly recorded
+    /// evidence in memory only.
+    pub(crate) fn new(pool_path: &Path, read_only: bool, log: ReplicaLogger) -> Self {
+        let mut store = Self {
+            path: pool_path.join(EQUIVOCATION_EVIDENCE_DIR),
+            read_only,
+            evidence: BTreeMap::new(),
+            log,
+        };
+        store.load();
+        store
+    }
+
+    fn load(&mut self) {
+        let entries = match fs::read_dir(&self.path) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return,
+            Err(err) => {
+                warn!(self.log, "Failed to read {:?}: {:?}", self.path, err);
+                return;
+            }
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.extension().and_then(|ext| ext.to_str()) != Some(EVIDENCE_EXTENSION) {
+                continue;
+            }
+            let evidence = fs::read(&path)
+                .map_err(|err| format!("{:?}", err))
+                .and_then(|bytes| {
+                    pb::EquivocationEvidence::decode(&bytes[..]).map_err(|err| format!("{:?}", err))
+                })
+                .and_then(EquivocationEvidence::try_from);
+            match evidence {
+                Ok(evidence) => {
+                    self.evidence
+                        .entry((evidence.signer(), evidence.kind()))
+                        .or_insert(evidence);
+                }
+                Err(err) => warn!(
+                    self.log,
+                    "Ignoring unreadable equivocation evidence {:?}: {}", path, err
+                ),
+            }
+        }
+    }
+
+    /// Records the given evidence, unless there already is evidence of the
+    /// same kind of equivocation by the same node.
+    pub(crate) fn record(&mut self, evidence: EquivocationEvidence) {
+        let key = (evidence.signer(), evidence.kind());
+        if self.evidence.contains_key(&key) {
+            return;
+        }
+        if !self.read_only {
+            let file = self.path.join(format!(
+                "{}_{}.{}",
+                evidence.kind(),
+                evidence.signer(),
+                EVIDENCE_EXTENSION
+            ));
+            let written = fs::create_dir_all(&self.path).and_then(|()| {
+                write_protobuf_using_tmp_file(&file, &pb::EquivocationEvidence::from(&evidence))
+            });
+            if let Err(err) = written {
+                warn!(
+                    self.log,
+                    "Failed to persist equivocation evidence to {:?}: {:?}", file, err
+                );
+            }
+        }
+        self.evidence.insert(key, evidence);
+    }
+
+    /// Returns the recorded evidence, in ascending order of height.
+    pub(crate) fn get_all(&self) -> Vec<EquivocationEvidence> {
+        let mut evidence: Vec<_> = self.evidence.values().cloned().collect();
+        evidence.sort_by_key(|evidence| evidence.height());
+        evidence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_test_utilities::{
+        consensus::{fake::*, make_genesis},
+        types::ids::node_test_id,
+    };
+    use ic_types::consensus::{dkg::Summary, Block, BlockProposal};
+    use std::time::Duration;
+
+    fn proposal(node: u64, time: u64) -> BlockProposal {
+        let parent = make_genesis(Summary::fake()).content.block;
+        let mut block = Block::from_parent(parent.as_ref());
+        block.context.time += Duration::from_nanos(time);
+        BlockProposal::fake(block, node_test_id(node))
+    }
+
+    fn evidence(node: u64, time: u64) -> EquivocationEvidence {
+        EquivocationEvidence::BlockMaker {
+            first: proposal(node, 0),
+            second: proposal(node, time),
+        }
+    }
+
+    #[test]
+    fn test_evidence_survives_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = EquivocationEvidenceStore::new(dir.path(), false, no_op_logger());
+        store.record(evidence(1, 1));
+        // Only the first evidence of a node is kept.
+        store.record(evidence(1, 2));
+        store.record(evidence(2, 1));
+        assert_eq!(store.get_all(), vec![evidence(1, 1), evidence(2, 1)]);
+
+        let store = EquivocationEvidenceStore::new(dir.path(), false, no_op_logger());
+        assert_eq!(store.get_all(), vec![evidence(1, 1), evidence(2, 1)]);
+
+        // A read-only store does not persist new evidence.
+        let mut store = EquivocationEvidenceStore::new(dir.path(), true, no_op_logger());
+        store.record(evidence(3, 1));
+        assert_eq!(store.get_all().len(), 3);
+        let store = EquivocationEvidenceStore::new(dir.path(), false, no_op_logger());
+        assert_eq!(store.get_all().len(), 2);
+    }
+}
@@ -1,16 +1,20 @@
+pub mod backup_verifier;
 pub mod certification_pool;
 pub mod consensus_pool;
 mod consensus_pool_cache;
 pub mod dkg_pool;
 pub mod ecdsa_pool;
+mod equivocation_evidence;
 mod height_index;
 pub mod ingress_pool;
 mod inmemory_pool;
 mod metrics;
 mod peer_index;
+pub mod pool_snapshot;
 #[cfg(test)]
 mod test_utils;
 
+mod append_log_pool;
 mod backup;
 mod lmdb_iterator;
 mod lmdb_pool;
@@ -37,7 +41,8 @@ pub fn set_replica_version<P: AsRef<Path>>(filepath: P, replica_version: &Replic
 
 /// Check that the replica version of the pool matches that of this process. If
 /// it does not, delete the contents of the old pool directory and create a new
-/// one.
+/// one. The evidence of equivocation is kept, as its format does not depend on
+/// the replica version.
 pub fn ensure_persistent_pool_replica_version_compatibility(pool_path: PathBuf) {
     let mut replica_version_file_path = pool_path.clone();
     replica_version_file_path.push("replica_version");
@@ -45,6 +50,9 @@ pub fn ensure_persistent_pool_replica_version_compatibility(pool_path: PathBuf)
         if pool_path.exists() {
             for entry in fs::read_dir(&pool_path).expect("Couldn't read the directory") {
                 let path = entry.expect("Couldn't read the metadata").path();
+                if path.ends_with(equivocation_evidence::EQUIVOCATION_EVIDENCE_DIR) {
+                    continue;
+                }
                 if path.is_dir() {
                     fs::remove_dir_all(path).expect("Couldn't remove the directory");
                 } else {
@@ -78,6 +86,10 @@ mod tests {
             let mut random_file_path = config.persistent_pool_db_path();
             random_file_path.push("random_file");
             std::fs::write(&random_file_path, "stuff").unwrap();
+            let evidence_path = config
+                .persistent_pool_db_path()
+                .join(equivocation_evidence::EQUIVOCATION_EVIDENCE_DIR);
+            std::fs::create_dir_all(&evidence_path).unwrap();
 
             ensure_persistent_pool_replica_version_compatibility(config.persistent_pool_db_path());
 
@@ -94,6 +106,8 @@ mod tests {
             // Now that the folder has a different replica version it should
             // have been deleted and created with the new replica version.
             assert!(std::fs::metadata(&random_file_path).is_err());
+            // The evidence of equivocation is kept.
+            assert!(evidence_path.is_dir());
             random_file_path.pop();
             random_file_path.pop();
 
@@ -56,6 +56,10 @@ impl PeerIndex {
         }
     }
 
+    pub(crate) fn set_max_quota_per_peer(&mut self, max_quota_per_peer: usize) {
+        self.max_quota_per_peer = max_quota_per_peer;
+    }
+
     pub(crate) fn get_remaining_quota(&self, peer_id: &NodeId) -> usize {
         match self.peer_map.get(peer_id) {
             Some(bucket) => {
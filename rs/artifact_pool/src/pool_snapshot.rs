@@ -0,0 +1,185 @@
+//! Snapshots of the finalized chain in the consensus pool.
+//!
+//! A node that starts from a catch-up package, e.g. because it just joined
+//! the subnet, would otherwise have to wait for its peers to gossip the chain
+//! above it. Instead, the replicas of a subnet serve a snapshot of their
+//! finalized chain above their latest catch-up package: the block proposals
+//! together with their notarizations, finalizations, random beacons and random
+//! tapes. The DKG dealings needed to validate the chain are part of the block
+//! payloads.
+//!
+//! The receiving node inserts the artifacts into its unvalidated pool, so
+//! that they go through the same validation as artifacts received through
+//! gossip. Hence a snapshot served by a faulty peer can delay a node, but not
+//! make it accept invalid artifacts.
+//!
+//! Snapshots are only served to the nodes of the subnet, and are capped at
+//! `MAX_POOL_SNAPSHOT_SIZE_BYTES`, keeping the heights right above the
+//! catch-up package: those are the ones a node needs first.
+use ic_interfaces::consensus_pool::ConsensusPool;
+use ic_protobuf::types::v1 as pb;
+use ic_types::consensus::{
+    BlockProposal, CatchUpPackage, ConsensusMessage, Finalization, HasHeight, Notarization,
+    RandomBeacon, RandomTape,
+};
+use ic_types::Height;
+use prost::Message;
+use std::convert::TryFrom;
+use std::sync::RwLock;
+
+/// The maximum size of an encoded snapshot.
+pub const MAX_POOL_SNAPSHOT_SIZE_BYTES: usize = 64 * 1024 * 1024;
+
+/// The artifacts of a single height of the finalized chain.
+struct ChainHeight {
+    proposal: BlockProposal,
+    notarization: Notarization,
+    finalization: Option<Finalization>,
+    random_beacon: Option<RandomBeacon>,
+    random_tape: Option<RandomTape>,
+}
+
+/// Returns a snapshot of the finalized chain above the latest catch-up
+/// package in the given pool.
+///
+/// The pool is only locked while the artifacts of a single height are copied
+/// out, so that consensus is not blocked while a snapshot is taken. If the
+/// pool is purged meanwhile, the snapshot ends where the chain is broken.
+pub fn make_pool_snapshot<P: ConsensusPool + ?Sized>(
+    pool: &RwLock<P>,
+) -> pb::ConsensusPoolSnapshot {
+    make_capped_pool_snapshot(pool, MAX_POOL_SNAPSHOT_SIZE_BYTES)
+}
+
+/// Like `make_pool_snapshot`, but with the size of the encoded snapshot
+/// capped at `max_size` bytes.
+pub(crate) fn make_capped_pool_snapshot<P: ConsensusPool + ?Sized>(
+    pool: &RwLock<P>,
+    max_size: usize,
+) -> pb::ConsensusPoolSnapshot {
+    let (cup, finalized_tip) = {
+        let pool = pool.read().unwrap();
+        let cup = pool.as_cache().cup_with_protobuf();
+        let finalized_height = pool.as_cache().finalized_block().height();
+        let finalized_tip = pool
+            .validated()
+            .finalization()
+            .get_by_height(finalized_height)
+            .next()
+            .filter(|_| finalized_height > cup.cup.height())
+            .map(|finalization| (finalized_height, finalization.content.block));
+        (cup, finalized_tip)
+    };
+    let cup_height = cup.cup.height();
+    let mut snapshot = pb::ConsensusPoolSnapshot {
+        catch_up_package: Some(cup.protobuf),
+        ..Default::default()
+    };
+    let (finalized_height, mut block_hash) = match finalized_tip {
+        Some(tip) => tip,
+        None => return snapshot,
+    };
+
+    // The chain is traced back from the finalized tip, since not every height
+    // necessarily has a finalization.
+    let mut chain = Vec::new();
+    for height in (cup_height.get() + 1..=finalized_height.get()).rev() {
+        let height = Height::from(height);
+        let pool_guard = pool.read().unwrap();
+        let validated = pool_guard.validated();
+        let proposal = validated
+            .block_proposal()
+            .get_by_height(height)
+            .find(|proposal| proposal.content.get_hash() == &block_hash);
+        let notarization = validated
+            .notarization()
+            .get_by_height(height)
+            .find(|notarization| notarization.content.block == block_hash);
+        let (proposal, notarization) = match (proposal, notarization) {
+            (Some(proposal), Some(notarization)) => (proposal, notarization),
+            // The chain can only be broken if the pool was purged meanwhile.
+            _ => break,
+        };
+        block_hash = proposal.content.as_ref().parent.clone();
+        chain.push(ChainHeight {
+            finalization: validated
+                .finalization()
+                .get_by_height(height)
+                .find(|finalization| finalization.content.block == notarization.content.block),
+            random_beacon: validated.random_beacon().get_by_height(height).next(),
+            random_tape: validated.random_tape().get_by_height(height).next(),
+            proposal,
+            notarization,
+        });
+    }
+    // A chain that was broken by purging no longer reaches the CUP, and is
+    // of no use to a node starting from it.
+    if chain.last().map_or(true, |lowest| {
+        lowest.proposal.height() != cup_height.increment()
+    }) {
+        return snapshot;
+    }
+
+    let mut size = snapshot.encoded_len();
+    for height in chain.into_iter().rev() {
+        let mut part = pb::ConsensusPoolSnapshot {
+            block_proposals: vec![pb::BlockProposal::from(&height.proposal)],
+            notarizations: vec![pb::Notarization::from(&height.notarization)],
+            finalizations: height
+                .finalization
+                .iter()
+                .map(pb::Finalization::from)
+                .collect(),
+            random_beacons: height
+                .random_beacon
+                .iter()
+                .map(pb::RandomBeacon::from)
+                .collect(),
+            random_tapes: height
+                .random_tape
+                .iter()
+                .map(pb::RandomTape::from)
+                .collect(),
+            ..Default::default()
+        };
+        size += part.encoded_len();
+        if size > max_size {
+            break;
+        }
+        snapshot.block_proposals.append(&mut part.block_proposals);
+        snapshot.notarizations.append(&mut part.notarizations);
+        snapshot.finalizations.append(&mut part.finalizations);
+        snapshot.random_beacons.append(&mut part.random_beacons);
+        snapshot.random_tapes.append(&mut part.random_tapes);
+    }
+    snapshot
+}
+
+/// Decodes the catch-up package and the artifacts of the given snapshot.
+pub fn read_pool_snapshot(
+    snapshot: pb::ConsensusPoolSnapshot,
+) -> Result<(CatchUpPackage, Vec<ConsensusMessage>), String> {
+    let cup = CatchUpPackage::try_from(
+        snapshot
+            .catch_up_package
+            .as_ref()
+            .ok_or_else(|| "The snapshot contains no catch-up package".to_string())?,
+    )?;
+    let mut artifacts = Vec::new();
+    for proposal in snapshot.block_proposals {
+        artifacts.push(BlockProposal::try_from(proposal).map(ConsensusMessage::BlockProposal)?);
+    }
+    for notarization in snapshot.notarizations {
+        artifacts.push(Notarization::try_from(notarization).map(ConsensusMessage::Notarization)?);
+    }
+    for finalization in snapshot.finalizations {
+        artifacts.push(Finalization::try_from(finalization).map(ConsensusMessage::Finalization)?);
+    }
+    for beacon in snapshot.random_beacons {
+        artifacts.push(RandomBeacon::try_from(beacon).map(ConsensusMessage::RandomBeacon)?);
+    }
+    for tape in snapshot.random_tapes {
+        artifacts.push(RandomTape::try_from(tape).map(ConsensusMessage::RandomTape)?);
+    }
+    Ok((cup, artifacts))
+}
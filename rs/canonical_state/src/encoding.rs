@@ -12,7 +12,7 @@
 
 use ic_protobuf::proxy::ProxyDecodeError;
 use ic_replicated_state::metadata_state::SystemMetadata;
-use ic_types::{messages::RequestOrResponse, xnet::StreamHeader, PrincipalId};
+use ic_types::{messages::RequestOrResponse, xnet::StreamHeader, NumBytes, PrincipalId};
 use serde::Serialize;
 use std::collections::BTreeSet;
 use std::convert::TryInto;
@@ -119,3 +119,16 @@ pub fn encode_controllers(controllers: &BTreeSet<PrincipalId>) -> Vec<u8> {
     controllers.serialize(&mut serializer).unwrap();
     serializer.into_inner()
 }
+
+/// Encodes the metrics of a subnet as a self-describing CBOR map with the
+/// fields `num_canisters` and `canister_state_bytes`.
+pub fn encode_subnet_metrics(num_canisters: u64, canister_state_bytes: NumBytes) -> Vec<u8> {
+    let metrics = types::SubnetMetrics {
+        num_canisters,
+        canister_state_bytes: canister_state_bytes.get(),
+    };
+    let mut serializer = serde_cbor::ser::Serializer::new(vec![]);
+    serializer.self_describe().unwrap();
+    metrics.serialize(&mut serializer).unwrap();
+    serializer.into_inner()
+}
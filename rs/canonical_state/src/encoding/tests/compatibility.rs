@@ -21,7 +21,7 @@ use ic_types::{
     messages::{CallbackId, Payload, RejectContext, Request, RequestOrResponse, Response},
     user_error::RejectCode,
     xnet::StreamHeader,
-    CryptoHashOfPartialState, Cycles, Funds,
+    CryptoHashOfPartialState, Cycles, Funds, NumBytes,
 };
 use serde::{Deserialize, Serialize};
 use serde_cbor::value::Value;
@@ -419,6 +419,29 @@ fn canonical_encoding_system_metadata() {
     assert_eq!("A2 00 0E 01 81 0F", as_hex(&encode_metadata(&metadata)));
 }
 
+/// Canonical CBOR encoding of subnet metrics with 3 canisters taking up 4096
+/// bytes.
+///
+/// Expected:
+///
+/// ```text
+/// D9 D9F7                                         # tag(55799)
+///    A2                                           # map(2)
+///       6D                                        # text(13)
+///          6E756D5F63616E697374657273             # "num_canisters"
+///       03                                        # unsigned(3)
+///       74                                        # text(20)
+///          63616E69737465725F73746174655F6279746573 # "canister_state_bytes"
+///       19 1000                                   # unsigned(4096)
+/// ```
+#[test]
+fn canonical_encoding_subnet_metrics() {
+    assert_eq!(
+        "D9 D9 F7 A2 6D 6E 75 6D 5F 63 61 6E 69 73 74 65 72 73 03 74 63 61 6E 69 73 74 65 72 5F 73 74 61 74 65 5F 62 79 74 65 73 19 10 00",
+        as_hex(&encode_subnet_metrics(3, NumBytes::from(4096)))
+    );
+}
+
 //
 // `RequestOrResponse` decoding
 //
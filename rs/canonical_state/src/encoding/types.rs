@@ -117,6 +117,16 @@ pub struct SystemMetadata {
     pub prev_state_hash: Option<Vec<u8>>,
 }
 
+/// Canonical representation of the subnet metrics leaf.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubnetMetrics {
+    /// The number of canisters hosted by the subnet.
+    pub num_canisters: u64,
+    /// The total memory taken by the canisters of the subnet, in bytes.
+    pub canister_state_bytes: u64,
+}
+
 impl From<(&ic_types::xnet::StreamHeader, u32)> for StreamHeader {
     fn from((header, _certification_version): (&ic_types::xnet::StreamHeader, u32)) -> Self {
         Self {
@@ -80,6 +80,25 @@ pub fn num<'a>(n: u64) -> LazyTree<'a> {
     }))
 }
 
+/// A helper function that construct a leaf from a 128-bit number (e.g. a
+/// cycles amount), encoded as unsigned LEB128.
+pub fn num_u128<'a>(n: u128) -> LazyTree<'a> {
+    LazyTree::<'a>::LazyBlob(Arc::new(move || {
+        let mut buf = Vec::with_capacity(19);
+        let mut n = n;
+        loop {
+            let byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                buf.push(byte);
+                break;
+            }
+            buf.push(byte | 0x80);
+        }
+        buf
+    }))
+}
+
 /// A function that extracts a value from the lazy tree by the specified path.
 pub fn follow_path<'a>(t: &LazyTree<'a>, path: &[&[u8]]) -> Option<LazyTree<'a>> {
     if path.is_empty() {
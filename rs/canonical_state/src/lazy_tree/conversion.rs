@@ -1,10 +1,10 @@
 //! Conversion from `ReplicatedState` to `LazyTree`.
 
-use super::{blob, fork, num, string, Lazy, LazyFork, LazyTree};
+use super::{blob, fork, num, num_u128, string, Lazy, LazyFork, LazyTree};
 use crate::{
     encoding::{
         encode_controllers, encode_message, encode_metadata, encode_stream_header,
-        encode_subnet_canister_ranges,
+        encode_subnet_canister_ranges, encode_subnet_metrics,
     },
     MAX_SUPPORTED_CERTIFICATION_VERSION,
 };
@@ -12,7 +12,9 @@ use ic_crypto_tree_hash::Label;
 use ic_registry_routing_table::RoutingTable;
 use ic_replicated_state::{
     canister_state::CanisterState,
-    metadata_state::{IngressHistoryState, StreamMap, SubnetTopology, SystemMetadata},
+    metadata_state::{
+        IngressHistoryState, NodeTopology, StreamMap, SubnetTopology, SystemMetadata,
+    },
     replicated_state::ReplicatedStateMessageRouting,
     ExecutionState, ReplicatedState,
 };
@@ -21,7 +23,7 @@ use ic_types::{
     messages::{MessageId, EXPECTED_MESSAGE_ID_LENGTH},
     user_error::RejectCode,
     xnet::{StreamHeader, StreamIndex, StreamIndexedQueue},
-    CanisterId, PrincipalId, SubnetId,
+    CanisterId, NodeId, PrincipalId, SubnetId,
 };
 use std::collections::BTreeMap;
 use std::convert::{AsRef, TryInto};
@@ -239,11 +241,7 @@ fn state_as_tree(state: &ReplicatedState) -> LazyTree<'_> {
                 let inverted_routing_table = Arc::new(invert_routing_table(
                     &state.metadata.network_topology.routing_table,
                 ));
-                subnets_as_tree(
-                    &state.metadata.network_topology.subnets,
-                    inverted_routing_table,
-                    certification_version,
-                )
+                subnets_as_tree(state, inverted_routing_table, certification_version)
             })
             .with_tree(
                 "time",
@@ -357,6 +355,11 @@ fn canisters_as_tree(
                         certification_version > 5,
                         "metadata",
                         canister_metadata_as_tree(execution_state, certification_version),
+                    )
+                    .with_tree_if(
+                        certification_version > 6,
+                        "cycles_balance",
+                        num_u128(canister.system_state.cycles_balance.get()),
                     ),
             ),
             None => fork(
@@ -370,6 +373,11 @@ fn canisters_as_tree(
                         certification_version > 1,
                         "controllers",
                         blob(move || encode_controllers(&canister.system_state.controllers)),
+                    )
+                    .with_tree_if(
+                        certification_version > 6,
+                        "cycles_balance",
+                        num_u128(canister.system_state.cycles_balance.get()),
                     ),
             ),
         },
@@ -377,12 +385,13 @@ fn canisters_as_tree(
 }
 
 fn subnets_as_tree(
-    subnets: &BTreeMap<SubnetId, SubnetTopology>,
+    state: &ReplicatedState,
     inverted_routing_table: Arc<BTreeMap<SubnetId, Vec<(PrincipalId, PrincipalId)>>>,
     certification_version: u32,
 ) -> LazyTree<'_> {
+    let own_subnet_id = state.metadata.own_subnet_id;
     fork(MapTransformFork {
-        map: subnets,
+        map: &state.metadata.network_topology.subnets,
         certification_version,
         mk_tree: move |subnet_id, subnet_topology, certification_version| {
             fork(
@@ -399,12 +408,40 @@ fn subnets_as_tree(
                                 )
                             }
                         }),
+                    )
+                    .with_tree_if(
+                        certification_version > 6,
+                        "node",
+                        nodes_as_tree(&subnet_topology.nodes, certification_version),
+                    )
+                    .with_tree_if(
+                        certification_version > 6 && subnet_id == own_subnet_id,
+                        "metrics",
+                        blob(move || {
+                            encode_subnet_metrics(
+                                state.num_canisters() as u64,
+                                state.total_memory_taken(),
+                            )
+                        }),
                     ),
             )
         },
     })
 }
 
+fn nodes_as_tree(
+    nodes: &BTreeMap<NodeId, NodeTopology>,
+    certification_version: u32,
+) -> LazyTree<'_> {
+    fork(MapTransformFork {
+        map: nodes,
+        certification_version,
+        mk_tree: |_node_id, node_topology, _version| {
+            fork(FiniteMap::default().with_tree("public_key", Blob(&node_topology.public_key[..])))
+        },
+    })
+}
+
 fn canister_metadata_as_tree(
     execution_state: &ExecutionState,
     certification_version: u32,
@@ -10,11 +10,13 @@
 //!      fields that are not yet populated.
 //!   5. Added support for custom canister metadata sections.
 //!   6. Encoding of canister metadata sections.
+//!   7. Added node public keys, canister cycles balances and subnet metrics.
 
 pub mod encoding;
 pub mod hash_tree;
 pub mod lazy_tree;
 pub mod size_limit_visitor;
+pub mod state_diff;
 pub mod subtree_visitor;
 mod traversal;
 pub mod visitor;
@@ -37,7 +39,7 @@ pub use visitor::{Control, Visitor};
 /// For virtually all certification version changes must be bumped at least one
 /// release before bumping `CURRENT_CERTIFICATION_VERSION` in order to ensure
 /// forwards compatibility in the case of a replica downgrade.
-pub const MAX_SUPPORTED_CERTIFICATION_VERSION: u32 = 6;
+pub const MAX_SUPPORTED_CERTIFICATION_VERSION: u32 = 7;
 
 /// The Canonical State certification version that should be used for newly
 /// computed states.
@@ -0,0 +1,355 @@
+//! Typed diff of the parts of the replicated state that are exposed in the
+//! Canonical State.
+//!
+//! Unlike `ic_state_manager::tree_diff`, which reports changed labels and leaf
+//! hashes, this module reports changes in terms of the entities they affect
+//! (canisters, streams and ingress messages), so that they can be consumed by
+//! humans and indexers alike.
+
+use ic_replicated_state::{CanisterState, ReplicatedState};
+use ic_types::{messages::MessageId, xnet::StreamHeader, CanisterId, PrincipalId, SubnetId};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests;
+
+/// A single semantic change between two states.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateChange {
+    /// A canister that did not exist in the old state exists in the new one.
+    CanisterCreated { canister_id: CanisterId },
+    /// A canister that existed in the old state no longer exists.
+    CanisterDeleted { canister_id: CanisterId },
+    /// The set of controllers of a canister changed.
+    ControllersChanged {
+        canister_id: CanisterId,
+        old: BTreeSet<PrincipalId>,
+        new: BTreeSet<PrincipalId>,
+    },
+    /// The SHA-256 hash of the Wasm module of a canister changed. `None`
+    /// means that no module is installed.
+    ModuleHashChanged {
+        canister_id: CanisterId,
+        old: Option<[u8; 32]>,
+        new: Option<[u8; 32]>,
+    },
+    /// The header (bounds or signals end) of the stream to a remote subnet
+    /// changed. `None` means that there is no stream to that subnet.
+    StreamHeaderChanged {
+        subnet_id: SubnetId,
+        old: Option<StreamHeader>,
+        new: Option<StreamHeader>,
+    },
+    /// The status of an ingress message changed (e.g. from "received" to
+    /// "processing"). `None` means that the message is not in the ingress
+    /// history.
+    IngressStatusChanged {
+        message_id: MessageId,
+        old: Option<&'static str>,
+        new: Option<&'static str>,
+    },
+}
+
+impl fmt::Display for StateChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn opt<T: fmt::Display>(v: &Option<T>) -> String {
+            v.as_ref()
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "∅".to_string())
+        }
+        fn principals(set: &BTreeSet<PrincipalId>) -> String {
+            let ids: Vec<_> = set.iter().map(|p| p.to_string()).collect();
+            format!("[{}]", ids.join(", "))
+        }
+        fn hash(h: &Option<[u8; 32]>) -> String {
+            opt(&h.map(|h| h.iter().map(|b| format!("{:02x}", b)).collect::<String>()))
+        }
+        fn header(h: &Option<StreamHeader>) -> String {
+            opt(&h
+                .as_ref()
+                .map(|h| format!("[{}, {}) signals_end {}", h.begin, h.end, h.signals_end)))
+        }
+
+        match self {
+            Self::CanisterCreated { canister_id } => write!(f, "canister {} created", canister_id),
+            Self::CanisterDeleted { canister_id } => write!(f, "canister {} deleted", canister_id),
+            Self::ControllersChanged {
+                canister_id,
+                old,
+                new,
+            } => write!(
+                f,
+                "canister {} controllers {} → {}",
+                canister_id,
+                principals(old),
+                principals(new)
+            ),
+            Self::ModuleHashChanged {
+                canister_id,
+                old,
+                new,
+            } => write!(
+                f,
+                "canister {} module hash {} → {}",
+                canister_id,
+                hash(old),
+                hash(new)
+            ),
+            Self::StreamHeaderChanged {
+                subnet_id,
+                old,
+                new,
+            } => write!(
+                f,
+                "stream to {} {} → {}",
+                subnet_id,
+                header(old),
+                header(new)
+            ),
+            Self::IngressStatusChanged {
+                message_id,
+                old,
+                new,
+            } => write!(f, "request {} {} → {}", message_id, opt(old), opt(new)),
+        }
+    }
+}
+
+/// Serializes changes as maps with a `type` field naming the kind of change and
+/// human-readable (textual) IDs, hashes and stream headers, suitable for
+/// machine-readable JSON output.
+impl Serialize for StateChange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        fn principals(set: &BTreeSet<PrincipalId>) -> Vec<String> {
+            set.iter().map(|p| p.to_string()).collect()
+        }
+        fn hash(h: &Option<[u8; 32]>) -> Option<String> {
+            h.map(|h| h.iter().map(|b| format!("{:02x}", b)).collect())
+        }
+        fn header(h: &Option<StreamHeader>) -> Option<BTreeMap<&'static str, u64>> {
+            h.as_ref().map(|h| {
+                vec![
+                    ("begin", h.begin.get()),
+                    ("end", h.end.get()),
+                    ("signals_end", h.signals_end.get()),
+                ]
+                .into_iter()
+                .collect()
+            })
+        }
+
+        match self {
+            Self::CanisterCreated { canister_id } => {
+                let mut s = serializer.serialize_struct("StateChange", 2)?;
+                s.serialize_field("type", "canister_created")?;
+                s.serialize_field("canister_id", &canister_id.to_string())?;
+                s.end()
+            }
+            Self::CanisterDeleted { canister_id } => {
+                let mut s = serializer.serialize_struct("StateChange", 2)?;
+                s.serialize_field("type", "canister_deleted")?;
+                s.serialize_field("canister_id", &canister_id.to_string())?;
+                s.end()
+            }
+            Self::ControllersChanged {
+                canister_id,
+                old,
+                new,
+            } => {
+                let mut s = serializer.serialize_struct("StateChange", 4)?;
+                s.serialize_field("type", "controllers_changed")?;
+                s.serialize_field("canister_id", &canister_id.to_string())?;
+                s.serialize_field("old", &principals(old))?;
+                s.serialize_field("new", &principals(new))?;
+                s.end()
+            }
+            Self::ModuleHashChanged {
+                canister_id,
+                old,
+                new,
+            } => {
+                let mut s = serializer.serialize_struct("StateChange", 4)?;
+                s.serialize_field("type", "module_hash_changed")?;
+                s.serialize_field("canister_id", &canister_id.to_string())?;
+                s.serialize_field("old", &hash(old))?;
+                s.serialize_field("new", &hash(new))?;
+                s.end()
+            }
+            Self::StreamHeaderChanged {
+                subnet_id,
+                old,
+                new,
+            } => {
+                let mut s = serializer.serialize_struct("StateChange", 4)?;
+                s.serialize_field("type", "stream_header_changed")?;
+                s.serialize_field("subnet_id", &subnet_id.to_string())?;
+                s.serialize_field("old", &header(old))?;
+                s.serialize_field("new", &header(new))?;
+                s.end()
+            }
+            Self::IngressStatusChanged {
+                message_id,
+                old,
+                new,
+            } => {
+                let mut s = serializer.serialize_struct("StateChange", 4)?;
+                s.serialize_field("type", "ingress_status_changed")?;
+                s.serialize_field("message_id", &message_id.to_string())?;
+                s.serialize_field("old", old)?;
+                s.serialize_field("new", new)?;
+                s.end()
+            }
+        }
+    }
+}
+
+/// Computes the semantic changes between states `a` and `b`.
+///
+/// Changes are reported in a deterministic order: canister changes (by
+/// canister ID), then stream changes (by remote subnet ID), then ingress
+/// status changes (by message ID).
+///
+/// # Complexity
+///
+/// `O(C + S + I)` where `C`, `S` and `I` are the number of canisters, streams
+/// and ingress history entries in both states. Consecutive states share their
+/// Wasm modules and ingress statuses unless they were changed, so only the
+/// changed ones are compared; unchanged entries cost a pointer comparison.
+pub fn diff_states(a: &ReplicatedState, b: &ReplicatedState) -> Vec<StateChange> {
+    let mut changes = Vec::new();
+    diff_canisters(&a.canister_states, &b.canister_states, &mut changes);
+    diff_streams(a, b, &mut changes);
+    diff_ingress_history(a, b, &mut changes);
+    changes
+}
+
+/// Walks the entries of two iterators sorted by key in lockstep and calls `f`
+/// with the values of every key present in either of them, in increasing key
+/// order.
+fn merge_join<'a, K: Ord + 'a, V>(
+    a: impl Iterator<Item = (&'a K, V)>,
+    b: impl Iterator<Item = (&'a K, V)>,
+    mut f: impl FnMut(&'a K, Option<V>, Option<V>),
+) {
+    let (mut a, mut b) = (a.peekable(), b.peekable());
+    loop {
+        let order = match (a.peek(), b.peek()) {
+            (Some((a_key, _)), Some((b_key, _))) => a_key.cmp(b_key),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return,
+        };
+        match order {
+            Ordering::Less => {
+                let (key, old) = a.next().unwrap();
+                f(key, Some(old), None);
+            }
+            Ordering::Greater => {
+                let (key, new) = b.next().unwrap();
+                f(key, None, Some(new));
+            }
+            Ordering::Equal => {
+                let (key, old) = a.next().unwrap();
+                let (_, new) = b.next().unwrap();
+                f(key, Some(old), Some(new));
+            }
+        }
+    }
+}
+
+/// Returns the SHA-256 hash of the canister's Wasm module, which is computed
+/// once when the module is loaded and cached alongside it.
+fn module_hash(canister: &CanisterState) -> Option<[u8; 32]> {
+    canister
+        .execution_state
+        .as_ref()
+        .map(|execution_state| execution_state.wasm_binary.binary.hash_sha256())
+}
+
+/// Returns true if both canisters run the very same (shared) Wasm module, or
+/// both have none installed.
+fn same_module(a: &CanisterState, b: &CanisterState) -> bool {
+    match (&a.execution_state, &b.execution_state) {
+        (Some(a), Some(b)) => Arc::ptr_eq(&a.wasm_binary, &b.wasm_binary),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn diff_canisters(
+    a: &BTreeMap<CanisterId, CanisterState>,
+    b: &BTreeMap<CanisterId, CanisterState>,
+    changes: &mut Vec<StateChange>,
+) {
+    merge_join(a.iter(), b.iter(), |canister_id, old, new| {
+        let canister_id = *canister_id;
+        match (old, new) {
+            (Some(_), None) => changes.push(StateChange::CanisterDeleted { canister_id }),
+            (None, Some(_)) => changes.push(StateChange::CanisterCreated { canister_id }),
+            (Some(old), Some(new)) => {
+                if old.system_state.controllers != new.system_state.controllers {
+                    changes.push(StateChange::ControllersChanged {
+                        canister_id,
+                        old: old.system_state.controllers.clone(),
+                        new: new.system_state.controllers.clone(),
+                    });
+                }
+                if same_module(old, new) {
+                    return;
+                }
+                let (old_hash, new_hash) = (module_hash(old), module_hash(new));
+                if old_hash != new_hash {
+                    changes.push(StateChange::ModuleHashChanged {
+                        canister_id,
+                        old: old_hash,
+                        new: new_hash,
+                    });
+                }
+            }
+            (None, None) => unreachable!("canister {} is in neither state", canister_id),
+        }
+    });
+}
+
+fn diff_streams(a: &ReplicatedState, b: &ReplicatedState, changes: &mut Vec<StateChange>) {
+    merge_join(
+        a.streams().iter(),
+        b.streams().iter(),
+        |subnet_id, old, new| {
+            let old = old.map(|stream| stream.header());
+            let new = new.map(|stream| stream.header());
+            if old != new {
+                changes.push(StateChange::StreamHeaderChanged {
+                    subnet_id: *subnet_id,
+                    old,
+                    new,
+                });
+            }
+        },
+    );
+}
+
+fn diff_ingress_history(a: &ReplicatedState, b: &ReplicatedState, changes: &mut Vec<StateChange>) {
+    let (a, b) = (&a.metadata.ingress_history, &b.metadata.ingress_history);
+    merge_join(a.statuses(), b.statuses(), |message_id, old, new| {
+        // Statuses that were not updated in between are shared by both states.
+        if let (Some(old), Some(new)) = (old, new) {
+            if std::ptr::eq(old, new) {
+                return;
+            }
+        }
+        let old = old.map(|status| status.as_str());
+        let new = new.map(|status| status.as_str());
+        if old != new {
+            changes.push(StateChange::IngressStatusChanged {
+                message_id: message_id.clone(),
+                old,
+                new,
+            });
+        }
+    });
+}
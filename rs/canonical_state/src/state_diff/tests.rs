@@ -0,0 +1,244 @@
+use super::*;
+use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::{
+    canister_state::execution_state::WasmBinary, metadata_state::Stream,
+    testing::ReplicatedStateTesting,
+};
+use ic_test_utilities::{
+    mock_time,
+    state::CanisterStateBuilder,
+    types::{
+        ids::{canister_test_id, message_test_id, subnet_test_id, user_test_id},
+        messages::RequestBuilder,
+    },
+};
+use ic_types::{
+    ingress::IngressStatus,
+    messages::RequestOrResponse,
+    xnet::{StreamIndex, StreamIndexedQueue},
+};
+use ic_wasm_types::BinaryEncodedWasm;
+use maplit::btreeset;
+
+fn empty_state() -> ReplicatedState {
+    ReplicatedState::new_rooted_at(
+        subnet_test_id(1),
+        SubnetType::Application,
+        "NOT_USED".into(),
+    )
+}
+
+fn received(canister_id: CanisterId) -> IngressStatus {
+    IngressStatus::Received {
+        receiver: canister_id.get(),
+        user_id: user_test_id(1),
+        time: mock_time(),
+    }
+}
+
+fn processing(canister_id: CanisterId) -> IngressStatus {
+    IngressStatus::Processing {
+        receiver: canister_id.get(),
+        user_id: user_test_id(1),
+        time: mock_time(),
+    }
+}
+
+#[test]
+fn identical_states_have_no_changes() {
+    let mut state = empty_state();
+    state.put_canister_state(
+        CanisterStateBuilder::new()
+            .with_canister_id(canister_test_id(1))
+            .with_wasm(vec![1, 2, 3])
+            .build(),
+    );
+    state.set_ingress_status(message_test_id(1), received(canister_test_id(1)));
+
+    assert_eq!(diff_states(&state, &state.clone()), vec![]);
+}
+
+#[test]
+fn diff_reports_canister_changes() {
+    let mut a = empty_state();
+    a.put_canister_state(
+        CanisterStateBuilder::new()
+            .with_canister_id(canister_test_id(1))
+            .build(),
+    );
+    a.put_canister_state(
+        CanisterStateBuilder::new()
+            .with_canister_id(canister_test_id(2))
+            .with_controller(user_test_id(1).get())
+            .build(),
+    );
+
+    let mut b = empty_state();
+    b.put_canister_state(
+        CanisterStateBuilder::new()
+            .with_canister_id(canister_test_id(2))
+            .with_controller(user_test_id(2).get())
+            .with_wasm(vec![1, 2, 3])
+            .build(),
+    );
+    b.put_canister_state(
+        CanisterStateBuilder::new()
+            .with_canister_id(canister_test_id(3))
+            .build(),
+    );
+    let new_hash = module_hash(b.canister_state(&canister_test_id(2)).unwrap());
+    assert!(new_hash.is_some());
+
+    assert_eq!(
+        diff_states(&a, &b),
+        vec![
+            StateChange::CanisterDeleted {
+                canister_id: canister_test_id(1)
+            },
+            StateChange::ControllersChanged {
+                canister_id: canister_test_id(2),
+                old: btreeset! {user_test_id(1).get()},
+                new: btreeset! {user_test_id(2).get()},
+            },
+            StateChange::ModuleHashChanged {
+                canister_id: canister_test_id(2),
+                old: None,
+                new: new_hash,
+            },
+            StateChange::CanisterCreated {
+                canister_id: canister_test_id(3)
+            },
+        ]
+    );
+}
+
+#[test]
+fn diff_reports_replaced_modules_with_a_different_hash() {
+    let mut a = empty_state();
+    a.put_canister_state(
+        CanisterStateBuilder::new()
+            .with_canister_id(canister_test_id(1))
+            .with_wasm(vec![1, 2, 3])
+            .build(),
+    );
+    let old_hash = module_hash(a.canister_state(&canister_test_id(1)).unwrap());
+
+    let replace_module = |state: &ReplicatedState, wasm: Vec<u8>| {
+        let mut state = state.clone();
+        let canister = state.canister_state_mut(&canister_test_id(1)).unwrap();
+        canister.execution_state.as_mut().unwrap().wasm_binary =
+            WasmBinary::new(BinaryEncodedWasm::new(wasm));
+        state
+    };
+
+    // Reinstalling the same module is not a change.
+    assert_eq!(diff_states(&a, &replace_module(&a, vec![1, 2, 3])), vec![]);
+
+    let b = replace_module(&a, vec![4, 5, 6]);
+    let new_hash = module_hash(b.canister_state(&canister_test_id(1)).unwrap());
+    assert_ne!(old_hash, new_hash);
+    assert_eq!(
+        diff_states(&a, &b),
+        vec![StateChange::ModuleHashChanged {
+            canister_id: canister_test_id(1),
+            old: old_hash,
+            new: new_hash,
+        }]
+    );
+}
+
+#[test]
+fn diff_reports_stream_header_changes() {
+    let a = empty_state();
+    let mut b = empty_state();
+    let mut stream = Stream::new(
+        StreamIndexedQueue::with_begin(StreamIndex::from(4)),
+        StreamIndex::new(10),
+    );
+    stream.push(RequestOrResponse::Request(RequestBuilder::new().build()));
+    let header = stream.header();
+    b.modify_streams(|streams| {
+        streams.insert(subnet_test_id(5), stream);
+    });
+
+    assert_eq!(
+        diff_states(&a, &b),
+        vec![StateChange::StreamHeaderChanged {
+            subnet_id: subnet_test_id(5),
+            old: None,
+            new: Some(header.clone()),
+        }]
+    );
+    assert_eq!(
+        diff_states(&b, &a),
+        vec![StateChange::StreamHeaderChanged {
+            subnet_id: subnet_test_id(5),
+            old: Some(header),
+            new: None,
+        }]
+    );
+}
+
+#[test]
+fn diff_reports_ingress_status_transitions() {
+    let mut a = empty_state();
+    a.set_ingress_status(message_test_id(1), received(canister_test_id(1)));
+    a.set_ingress_status(message_test_id(2), received(canister_test_id(1)));
+
+    let mut b = a.clone();
+    b.set_ingress_status(message_test_id(1), processing(canister_test_id(1)));
+    b.set_ingress_status(message_test_id(3), received(canister_test_id(1)));
+
+    assert_eq!(
+        diff_states(&a, &b),
+        vec![
+            StateChange::IngressStatusChanged {
+                message_id: message_test_id(1),
+                old: Some("received"),
+                new: Some("processing"),
+            },
+            StateChange::IngressStatusChanged {
+                message_id: message_test_id(3),
+                old: None,
+                new: Some("received"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn changes_serialize_to_readable_json() {
+    let change = StateChange::StreamHeaderChanged {
+        subnet_id: subnet_test_id(5),
+        old: None,
+        new: Some(StreamHeader {
+            begin: StreamIndex::new(4),
+            end: StreamIndex::new(5),
+            signals_end: StreamIndex::new(10),
+        }),
+    };
+    assert_eq!(
+        serde_json::to_value(&change).unwrap(),
+        serde_json::json!({
+            "type": "stream_header_changed",
+            "subnet_id": subnet_test_id(5).to_string(),
+            "old": null,
+            "new": {"begin": 4, "end": 5, "signals_end": 10},
+        })
+    );
+
+    let change = StateChange::IngressStatusChanged {
+        message_id: message_test_id(1),
+        old: Some("received"),
+        new: None,
+    };
+    assert_eq!(
+        serde_json::to_value(&change).unwrap(),
+        serde_json::json!({
+            "type": "ingress_status_changed",
+            "message_id": message_test_id(1).to_string(),
+            "old": "received",
+            "new": null,
+        })
+    );
+}
@@ -55,7 +55,7 @@ mod tests {
             execution_state::{CustomSection, CustomSectionType, WasmBinary, WasmMetadata},
             ExecutionState, ExportedFunctions, Global, NumWasmPages,
         },
-        metadata_state::SubnetTopology,
+        metadata_state::{NodeTopology, SubnetTopology},
         page_map::PageMap,
         testing::ReplicatedStateTesting,
         Memory,
@@ -63,7 +63,7 @@ mod tests {
     use ic_test_utilities::{
         mock_time,
         state::new_canister_state,
-        types::ids::{canister_test_id, subnet_test_id, user_test_id},
+        types::ids::{canister_test_id, node_test_id, subnet_test_id, user_test_id},
     };
     use ic_types::{CanisterId, Cycles, ExecutionRound};
     use ic_wasm_types::BinaryEncodedWasm;
@@ -749,4 +749,125 @@ mod tests {
             traverse(&state, visitor).0
         );
     }
+
+    #[test]
+    fn test_traverse_node_public_keys_cycles_balance_and_subnet_metrics() {
+        let canister_id = canister_test_id(2);
+        let controller = user_test_id(24);
+        let controllers_cbor = {
+            let mut cbor = vec![217, 217, 247, 129, 74];
+            cbor.extend(controller.get().to_vec());
+            cbor
+        };
+        let tmpdir = tempfile::Builder::new().prefix("test").tempdir().unwrap();
+        let mut state = ReplicatedState::new_rooted_at(
+            subnet_test_id(1),
+            SubnetType::Application,
+            tmpdir.path().into(),
+        );
+        state.put_canister_state(new_canister_state(
+            canister_id,
+            controller.get(),
+            INITIAL_CYCLES,
+            NumSeconds::from(100_000),
+        ));
+        state.metadata.network_topology.subnets = btreemap! {
+            subnet_test_id(0) => SubnetTopology {
+                public_key: vec![1, 2, 3, 4],
+                nodes: btreemap! {
+                    node_test_id(1) => NodeTopology {
+                        public_key: vec![9, 9],
+                        ..Default::default()
+                    },
+                },
+                subnet_type: SubnetType::Application,
+            },
+            subnet_test_id(1) => SubnetTopology {
+                public_key: vec![5, 6, 7, 8],
+                nodes: btreemap! {
+                    node_test_id(2) => NodeTopology {
+                        public_key: vec![7, 7],
+                        ..Default::default()
+                    },
+                },
+                subnet_type: SubnetType::Application,
+            }
+        };
+        state.metadata.certification_version = 7;
+        let metrics_cbor = crate::encoding::encode_subnet_metrics(1, state.total_memory_taken());
+
+        let pattern = Pattern::match_only("canister", Pattern::all());
+        let visitor = SubtreeVisitor::new(&pattern, TracingVisitor::new(NoopVisitor));
+        assert_eq!(
+            vec![
+                E::StartSubtree, // global
+                edge("canister"),
+                E::StartSubtree,
+                E::EnterEdge(canister_id.get().into_vec()),
+                E::StartSubtree,
+                edge("controller"),
+                E::VisitBlob(controller.get().to_vec()),
+                edge("controllers"),
+                E::VisitBlob(controllers_cbor),
+                edge("cycles_balance"),
+                // 2^36 as unsigned LEB128.
+                E::VisitBlob(vec![128, 128, 128, 128, 128, 2]),
+                E::EndSubtree, // canister
+                E::EndSubtree, // canisters
+                E::EndSubtree, // global
+            ],
+            traverse(&state, visitor).0
+        );
+
+        let pattern = Pattern::match_only(
+            "subnet",
+            Pattern::any(Pattern::match_any(
+                vec![
+                    ("metrics", Pattern::all()),
+                    ("node", Pattern::all()),
+                    ("public_key", Pattern::all()),
+                ]
+                .into_iter(),
+            )),
+        );
+        let visitor = SubtreeVisitor::new(&pattern, TracingVisitor::new(NoopVisitor));
+        assert_eq!(
+            vec![
+                E::StartSubtree, // global
+                edge("subnet"),
+                E::StartSubtree,
+                E::EnterEdge(subnet_test_id(0).get().into_vec()),
+                E::StartSubtree,
+                edge("node"),
+                E::StartSubtree,
+                E::EnterEdge(node_test_id(1).get().into_vec()),
+                E::StartSubtree,
+                edge("public_key"),
+                E::VisitBlob(vec![9, 9]),
+                E::EndSubtree, // node
+                E::EndSubtree, // nodes
+                edge("public_key"),
+                E::VisitBlob(vec![1, 2, 3, 4]),
+                E::EndSubtree, // subnet
+                E::EnterEdge(subnet_test_id(1).get().into_vec()),
+                E::StartSubtree,
+                edge("metrics"),
+                E::VisitBlob(metrics_cbor),
+                edge("node"),
+                E::StartSubtree,
+                E::EnterEdge(node_test_id(2).get().into_vec()),
+                E::StartSubtree,
+                edge("public_key"),
+                E::VisitBlob(vec![7, 7]),
+                E::EndSubtree, // node
+                E::EndSubtree, // nodes
+                edge("public_key"),
+                E::VisitBlob(vec![5, 6, 7, 8]),
+                E::EndSubtree, // subnet
+                E::EndSubtree, // subnets
+                E::EndSubtree, // global
+            ],
+            traverse(&state, visitor).0
+        );
+    }
 }
@@ -8,10 +8,11 @@ const MAX_INGRESS_POOL_UNVALIDATED_CAPACITY_PER_PEER: usize = 100_000_000;
 const MAX_CONSENSUS_POOL_VALIDATED_CAPACITY: usize = 2048;
 const MAX_CONSENSUS_POOL_UNVALIDATED_CAPACITY_PER_PEER: usize = 2048;
 const PERSISTENT_POOL_VALIDATED_PURGE_INTERVAL: u64 = 5000;
+const PERSISTENT_POOL_SEGMENT_HEIGHT_SPAN: u64 = 500;
 
 /// The number of height folders we store grouped inside a single "shard" folder
 /// (to avoid running into inode limits on potentially misconfigured file
-/// systems).
+/// systems), unless the subnet record specifies a different group size.
 pub const BACKUP_GROUP_SIZE: u64 = 10000;
 
 /// External configuration for artifact pools meant to be used by replica's
@@ -57,7 +58,8 @@ pub struct BackupConfig {
     pub spool_path: PathBuf,
     /// The maximum age backup artifacts can reach before purging.
     pub retention_time_secs: u64,
-    /// Time interval between purges.
+    /// Time interval between purges, unless the subnet record specifies a
+    /// different one.
     pub purging_interval_secs: u64,
 }
 
@@ -88,11 +90,13 @@ pub struct ArtifactPoolConfig {
     pub backup_config: Option<BackupConfig>,
 }
 
-/// Choice of persistent pool database is either LMDB or RocksDB.
+/// Choice of persistent pool database is either LMDB, RocksDB or an embedded
+/// append-only log.
 #[derive(Clone, Debug)]
 pub enum PersistentPoolBackend {
     Lmdb(LMDBConfig),
     RocksDB(RocksDBConfig),
+    AppendLog(AppendLogConfig),
 }
 
 /// LMDB specific configuration
@@ -119,6 +123,18 @@ pub struct RocksDBConfig {
     pub persistent_pool_validated_purge_interval: Height,
 }
 
+/// Append-only log specific configuration
+#[derive(Clone, Debug)]
+pub struct AppendLogConfig {
+    /// The path at which the validated section of the persistent pool is
+    /// stored.
+    pub persistent_pool_validated_persistent_db_path: PathBuf,
+    /// The number of heights covered by a single log segment. Purging deletes
+    /// whole segments, so artifacts may outlive the purge height on disk by up
+    /// to this many heights.
+    pub persistent_pool_segment_height_span: Height,
+}
+
 impl From<ArtifactPoolTomlConfig> for ArtifactPoolConfig {
     fn from(toml_config: ArtifactPoolTomlConfig) -> ArtifactPoolConfig {
         let backend = toml_config
@@ -135,8 +151,14 @@ impl From<ArtifactPoolTomlConfig> for ArtifactPoolConfig {
                     PERSISTENT_POOL_VALIDATED_PURGE_INTERVAL,
                 ),
             }),
+            "append_log" => PersistentPoolBackend::AppendLog(AppendLogConfig {
+                persistent_pool_validated_persistent_db_path: toml_config.consensus_pool_path,
+                persistent_pool_segment_height_span: Height::from(
+                    PERSISTENT_POOL_SEGMENT_HEIGHT_SPAN,
+                ),
+            }),
             _ => {
-                panic!("Unsupported persistent_pool_backend: {}, must be either \"lmdb\" or \"rocksdb\".", backend);
+                panic!("Unsupported persistent_pool_backend: {}, must be one of \"lmdb\", \"rocksdb\" or \"append_log\".", backend);
             }
         };
         ArtifactPoolConfig {
@@ -167,6 +189,9 @@ impl ArtifactPoolConfig {
             PersistentPoolBackend::RocksDB(config) => {
                 config.persistent_pool_validated_persistent_db_path.clone()
             }
+            PersistentPoolBackend::AppendLog(config) => {
+                config.persistent_pool_validated_persistent_db_path.clone()
+            }
         }
     }
 }
@@ -169,7 +169,12 @@ pub const SAMPLE_CONFIG: &str = r#"
     // ====================================
     http_handler: {
         // The address to listen on.
-        listen_addr: "127.0.0.1:8080"
+        listen_addr: "127.0.0.1:8080",
+        // The loopback address to serve the operator endpoints on.
+        admin_listen_addr: "127.0.0.1:8081",
+        // The address to serve pool snapshots to the other nodes of the
+        // subnet on.
+        pool_snapshot_listen_addr: "127.0.0.1:8082"
     },
     // ==================================================
     // Configuration of the metrics collection subsystem.
@@ -68,6 +68,32 @@ pub struct ExternalConfig {
     /// ```
     pub listen_addr: Option<SocketAddr>,
 
+    /// Loopback IP address and port on which the endpoints meant for the
+    /// operators of this node, like `/_/consensus`, are served over plain
+    /// HTTP. They are not served at all if this is not set.
+    ///
+    /// ```json5
+    /// {
+    ///   http_handler: {
+    ///     admin_listen_addr: "[::1]:8081"
+    ///   }
+    /// }
+    /// ```
+    pub admin_listen_addr: Option<SocketAddr>,
+
+    /// IP address and port on which `/_/pool_snapshot` is served to the other
+    /// nodes of the subnet, over TLS with client authentication. Snapshots
+    /// are not served at all if this is not set.
+    ///
+    /// ```json5
+    /// {
+    ///   http_handler: {
+    ///     pool_snapshot_listen_addr: "[::]:8082"
+    ///   }
+    /// }
+    /// ```
+    pub pool_snapshot_listen_addr: Option<SocketAddr>,
+
     /// An escape hatch to allow API traffic over IPv6 if absolutely
     /// necessary.
     pub allow_ipv6_my_users_have_no_privacy: Option<bool>,
@@ -91,6 +117,8 @@ impl Default for ExternalConfig {
     fn default() -> Self {
         Self {
             listen_addr: None,
+            admin_listen_addr: None,
+            pool_snapshot_listen_addr: None,
             allow_ipv6_my_users_have_no_privacy: None,
             port: None,
             show_root_key_in_status: true,
@@ -106,6 +134,10 @@ impl Default for ExternalConfig {
 pub struct Config {
     /// IP address and port to listen on
     pub listen_addr: SocketAddr,
+    /// Loopback IP address and port to serve the operator endpoints on, if any
+    pub admin_listen_addr: Option<SocketAddr>,
+    /// IP address and port to serve pool snapshots to the subnet on, if any
+    pub pool_snapshot_listen_addr: Option<SocketAddr>,
     /// The path to write the listening port to
     pub port_file_path: Option<PathBuf>,
     /// True if the replica public key is returned from the `/status` endpoint
@@ -119,6 +151,8 @@ impl Default for Config {
                 DEFAULT_IP_ADDR.parse().expect("can't fail"),
                 DEFAULT_PORT,
             ),
+            admin_listen_addr: None,
+            pool_snapshot_listen_addr: None,
             port_file_path: None,
             show_root_key_in_status: true,
         }
@@ -154,6 +188,14 @@ impl TryFrom<ExternalConfig> for Config {
             }
         }?;
 
+        config.admin_listen_addr = match ec.admin_listen_addr {
+            Some(addr) if !addr.ip().is_loopback() => {
+                Err("admin_listen_addr must be a loopback address")
+            }
+            addr => Ok(addr),
+        }?;
+        config.pool_snapshot_listen_addr = ec.pool_snapshot_listen_addr;
+
         config.show_root_key_in_status = ec.show_root_key_in_status;
         Ok(config)
     }
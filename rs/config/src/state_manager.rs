@@ -4,14 +4,31 @@ use std::path::PathBuf;
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Config {
     state_root: PathBuf,
+    /// If set, the state manager recomputes the manifests of all checkpoints
+    /// on startup and marks the checkpoints that don't match their persisted
+    /// manifests as diverged instead of loading them.
+    #[serde(default)]
+    verify_checkpoints_on_startup: bool,
 }
 
 impl Config {
     pub fn new(state_root: PathBuf) -> Self {
-        Self { state_root }
+        Self {
+            state_root,
+            verify_checkpoints_on_startup: false,
+        }
     }
 
     pub fn state_root(&self) -> PathBuf {
         self.state_root.clone()
     }
+
+    pub fn verify_checkpoints_on_startup(&self) -> bool {
+        self.verify_checkpoints_on_startup
+    }
+
+    pub fn with_checkpoint_verification_on_startup(mut self, enabled: bool) -> Self {
+        self.verify_checkpoints_on_startup = enabled;
+        self
+    }
 }
@@ -3,9 +3,11 @@
 
 pub mod batch_delivery;
 mod block_maker;
+pub mod block_rate;
 mod catchup_package_maker;
 pub(crate) mod crypto;
 pub mod dkg_key_manager;
+pub mod equivocation;
 mod finalizer;
 mod malicious_consensus;
 pub(crate) mod membership;
@@ -206,6 +208,7 @@ impl ConsensusImpl {
             ),
             catch_up_package_maker: CatchUpPackageMaker::new(
                 replica_config.clone(),
+                Arc::clone(&registry_client),
                 membership.clone(),
                 crypto.clone(),
                 state_manager.clone(),
@@ -246,6 +249,8 @@ impl ConsensusImpl {
                 logger.clone(),
             ),
             purger: Purger::new(
+                replica_config.clone(),
+                Arc::clone(&registry_client),
                 state_manager.clone(),
                 message_routing,
                 logger.clone(),
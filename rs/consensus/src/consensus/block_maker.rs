@@ -1,6 +1,7 @@
 #![deny(missing_docs)]
 use crate::{
     consensus::{
+        block_rate::get_finalization_lag,
         membership::Membership,
         metrics::{BlockMakerMetrics, EcdsaPayloadMetrics},
         payload_builder::PayloadBuilder,
@@ -437,6 +438,7 @@ impl BlockMaker {
                 &past_payloads,
                 context,
                 subnet_records,
+                get_finalization_lag(pool, parent),
             );
 
             self.metrics
@@ -793,7 +795,7 @@ mod tests {
 
             payload_builder
                 .expect_get_payload()
-                .withf(move |_, _, payloads, context, _| {
+                .withf(move |_, _, payloads, context, _, _| {
                     matches_expected_payloads(payloads) && context == &expected_context
                 })
                 .return_const(BatchPayload::default());
@@ -0,0 +1,246 @@
+//! Adaptive block rate
+//!
+//! If the subnet record contains an `AdaptiveBlockRateConfig`, the initial
+//! notary delay is adjusted to the round time observed on the finalized chain,
+//! and block makers shrink the payloads they propose while finalization lags
+//! behind notarization.
+//!
+//! The round time is the average difference between the timestamps of the
+//! most recent finalized blocks. Block timestamps are part of the chain, so
+//! all replicas that agree on the finalized chain use the same notary delay.
+//!
+//! The finalization lag is the number of notarized heights below a new block
+//! that are not finalized yet. It is a local observation of the block maker,
+//! so it only limits the payloads a replica builds: validators keep checking
+//! proposals against the `max_block_payload_size` of the subnet record, which
+//! all replicas agree on, and therefore accept the same blocks.
+use crate::consensus::{pool_reader::PoolReader, prelude::*};
+use ic_registry_client::helper::subnet::AdaptiveBlockRateSettings;
+use std::time::Duration;
+
+/// Returns the average round time over the last `window` rounds of the chain
+/// ending at `block`, or `None` if there are fewer than two blocks to compare.
+///
+/// The chain is never traced below the summary block of the DKG interval of
+/// `block`. All replicas validating a child of `block` hold these blocks, since
+/// nothing at or above the summary block is purged before the next catch-up
+/// package, so they all observe the same round time.
+pub fn get_observed_round_time(
+    pool: &PoolReader<'_>,
+    block: &Block,
+    window: u64,
+) -> Option<Duration> {
+    let interval_start = block.payload.as_ref().dkg_interval_start_height();
+    let times: Vec<Time> = pool
+        .chain_iterator(block.clone())
+        .take_while(|block| block.height >= interval_start)
+        .take(window.saturating_add(1) as usize)
+        .map(|block| block.context.time)
+        .collect();
+    average_round_time(&times)
+}
+
+// Returns the average difference between consecutive timestamps, which are
+// expected in decreasing order.
+fn average_round_time(times: &[Time]) -> Option<Duration> {
+    let (newest, oldest) = (*times.first()?, *times.last()?);
+    let rounds = times.len() as u32 - 1;
+    if rounds == 0 || newest < oldest {
+        return None;
+    }
+    Some((newest - oldest) / rounds)
+}
+
+/// Returns the initial notary delay adjusted to the observed round time.
+///
+/// Half of the time by which rounds exceed the target is added to the
+/// configured delay, bounded by `max_initial_notary_delay`. A longer notary
+/// delay itself lengthens rounds; only adding half of the excess keeps the
+/// adjustment from feeding on itself.
+pub fn adjusted_initial_notary_delay(
+    settings: &AdaptiveBlockRateSettings,
+    initial_notary_delay: Duration,
+    round_time: Duration,
+) -> Duration {
+    let excess = round_time
+        .checked_sub(settings.target_round_time)
+        .unwrap_or_default();
+    (initial_notary_delay + excess / 2)
+        .min(settings.max_initial_notary_delay.max(initial_notary_delay))
+}
+
+/// Returns the number of notarized heights up to `parent` that are not
+/// finalized yet.
+pub fn get_finalization_lag(pool: &PoolReader<'_>, parent: &Block) -> u64 {
+    parent
+        .height
+        .get()
+        .saturating_sub(pool.get_finalized_height().get())
+}
+
+/// Returns the maximum size of the payload a block maker builds, given the
+/// finalization lag below the new block.
+///
+/// While the parent is finalized, or only its own finalization is pending,
+/// the configured size is used. Every further height of lag halves the size,
+/// just like the notary delay grows exponentially with the gap between the
+/// notarized and the finalized height, but never below
+/// `min_block_payload_size`.
+pub fn adjusted_max_block_payload_size(
+    settings: &AdaptiveBlockRateSettings,
+    max_block_payload_size: NumBytes,
+    finalization_lag: u64,
+) -> NumBytes {
+    let halvings = finalization_lag.saturating_sub(1).min(63);
+    NumBytes::new(
+        (max_block_payload_size.get() >> halvings)
+            .max(settings.min_block_payload_size)
+            .min(max_block_payload_size.get()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::mocks::{dependencies, Dependencies};
+    use ic_test_utilities::mock_time;
+
+    fn settings() -> AdaptiveBlockRateSettings {
+        AdaptiveBlockRateSettings {
+            window: 5,
+            target_round_time: Duration::from_secs(1),
+            max_initial_notary_delay: Duration::from_secs(3),
+            min_block_payload_size: 1000,
+        }
+    }
+
+    #[test]
+    fn test_average_round_time() {
+        let at = |secs| mock_time() + Duration::from_secs(secs);
+        assert_eq!(average_round_time(&[]), None);
+        assert_eq!(average_round_time(&[at(3)]), None);
+        assert_eq!(
+            average_round_time(&[at(9), at(5), at(3)]),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(average_round_time(&[at(3), at(5)]), None);
+    }
+
+    #[test]
+    fn test_adjusted_initial_notary_delay() {
+        let initial = Duration::from_millis(500);
+        // Rounds within the target keep the configured delay.
+        assert_eq!(
+            adjusted_initial_notary_delay(&settings(), initial, Duration::from_millis(800)),
+            initial
+        );
+        // Half of the excess is added.
+        assert_eq!(
+            adjusted_initial_notary_delay(&settings(), initial, Duration::from_secs(2)),
+            Duration::from_secs(1)
+        );
+        // The delay is bounded.
+        assert_eq!(
+            adjusted_initial_notary_delay(&settings(), initial, Duration::from_secs(60)),
+            Duration::from_secs(3)
+        );
+        // A bound below the configured delay never lowers it.
+        assert_eq!(
+            adjusted_initial_notary_delay(
+                &settings(),
+                Duration::from_secs(5),
+                Duration::from_secs(60)
+            ),
+            Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn test_adjusted_max_block_payload_size() {
+        let max = NumBytes::new(10_000);
+        assert_eq!(adjusted_max_block_payload_size(&settings(), max, 0), max);
+        assert_eq!(adjusted_max_block_payload_size(&settings(), max, 1), max);
+        assert_eq!(
+            adjusted_max_block_payload_size(&settings(), max, 3),
+            NumBytes::new(2_500)
+        );
+        assert_eq!(
+            adjusted_max_block_payload_size(&settings(), max, 1000),
+            NumBytes::new(1_000)
+        );
+        // The lower bound never exceeds the configured size.
+        assert_eq!(
+            adjusted_max_block_payload_size(&settings(), NumBytes::new(500), 3),
+            NumBytes::new(500)
+        );
+    }
+
+    // Tests that the block rate adapts to a subnet whose finalization stalls:
+    // the payload budget shrinks with every round that is only notarized, and
+    // the notary delay grows once the slow rounds are finalized.
+    #[test]
+    fn test_block_rate_adapts_to_finalization() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let Dependencies { mut pool, .. } = dependencies(pool_config, 1);
+            let max = NumBytes::new(8_000);
+            let initial_notary_delay = Duration::from_millis(500);
+            pool.advance_round_normal_operation_n(5);
+            let reader = PoolReader::new(&pool);
+            let tip = reader.get_finalized_tip();
+            assert_eq!(get_finalization_lag(&reader, &tip), 0);
+            let round_time = get_observed_round_time(&reader, &tip, settings().window).unwrap();
+            assert_eq!(
+                adjusted_initial_notary_delay(&settings(), initial_notary_delay, round_time),
+                initial_notary_delay
+            );
+
+            // Rounds take 3 seconds and are only notarized.
+            let mut budgets = Vec::new();
+            let mut slow_blocks = Vec::new();
+            for _ in 0..4 {
+                let mut block = pool.make_next_block();
+                block.content.as_mut().context.time += Duration::from_secs(3);
+                block.update_content();
+                pool.insert_validated(block.clone());
+                pool.insert_validated(pool.make_next_beacon());
+                pool.notarize(&block);
+                let lag = get_finalization_lag(&PoolReader::new(&pool), block.as_ref());
+                budgets.push(adjusted_max_block_payload_size(&settings(), max, lag).get());
+                slow_blocks.push(block);
+            }
+            assert_eq!(budgets, vec![8_000, 4_000, 2_000, 1_000]);
+
+            // Once the slow rounds are finalized, the notary delay grows.
+            let block = slow_blocks.pop().unwrap();
+            pool.finalize(&block);
+            let reader = PoolReader::new(&pool);
+            let tip = reader.get_finalized_tip();
+            assert_eq!(tip.height, block.height());
+            assert_eq!(get_finalization_lag(&reader, &tip), 0);
+            let round_time = get_observed_round_time(&reader, &tip, settings().window).unwrap();
+            assert!(round_time >= Duration::from_secs(2), "{:?}", round_time);
+            assert!(
+                adjusted_initial_notary_delay(&settings(), initial_notary_delay, round_time)
+                    > initial_notary_delay
+            );
+        })
+    }
+
+    #[test]
+    fn test_observed_round_time_stays_in_dkg_interval() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let Dependencies { mut pool, .. } = dependencies(pool_config, 1);
+            let start = PoolReader::new(&pool).get_highest_catch_up_package();
+            let start_time = start.content.block.as_ref().context.time;
+            pool.advance_round_normal_operation_n(4);
+            let reader = PoolReader::new(&pool);
+            let tip = reader.get_finalized_tip();
+            let rounds = tip.height.get() - start.height().get();
+            let expected = (tip.context.time - start_time) / rounds as u32;
+            // The window reaches down to the summary block.
+            assert_eq!(get_observed_round_time(&reader, &tip, 100), Some(expected));
+            assert!(get_observed_round_time(&reader, &tip, 1).is_some());
+            assert_eq!(get_observed_round_time(&reader, &tip, 0), None);
+        })
+    }
+}
@@ -11,16 +11,25 @@
 //! state is known.
 //!
 //! At the moment, we will start to make a CatchUpPackage once a DKG summary
-//! block is considered finalized.
+//! block is considered finalized. The `catch_up_package_interval_length` of
+//! the subnet's `ConsensusParameters` can make CatchUpPackages less frequent:
+//! they are then only made at the first summary block that reaches the next
+//! multiple of that length. The CatchUpPackages of replica upgrades are always
+//! made.
 use crate::consensus::{
-    membership::Membership, pool_reader::PoolReader, prelude::*,
-    utils::active_high_threshold_transcript, ConsensusCrypto,
+    membership::Membership,
+    pool_reader::PoolReader,
+    prelude::*,
+    utils::{active_high_threshold_transcript, lookup_replica_version},
+    ConsensusCrypto,
 };
 use ic_interfaces::messaging::MessageRouting;
+use ic_interfaces::registry::RegistryClient;
 use ic_interfaces::state_manager::{
     PermanentStateHashError::*, StateHashError, StateManager, TransientStateHashError::*,
 };
-use ic_logger::{debug, error, trace, ReplicaLogger};
+use ic_logger::{debug, error, trace, warn, ReplicaLogger};
+use ic_registry_client::helper::subnet::SubnetRegistry;
 use ic_replicated_state::ReplicatedState;
 use ic_types::replica_config::ReplicaConfig;
 use std::sync::Arc;
@@ -28,6 +37,7 @@ use std::sync::Arc;
 /// CatchUpPackage maker is responsible for creating beacon shares
 pub struct CatchUpPackageMaker {
     replica_config: ReplicaConfig,
+    registry_client: Arc<dyn RegistryClient>,
     membership: Arc<Membership>,
     crypto: Arc<dyn ConsensusCrypto>,
     state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
@@ -39,6 +49,7 @@ impl<'a> CatchUpPackageMaker {
     /// Instantiate a new CatchUpPackage maker and save a copy of the config.
     pub fn new(
         replica_config: ReplicaConfig,
+        registry_client: Arc<dyn RegistryClient>,
         membership: Arc<Membership>,
         crypto: Arc<dyn ConsensusCrypto>,
         state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
@@ -47,6 +58,7 @@ impl<'a> CatchUpPackageMaker {
     ) -> Self {
         Self {
             replica_config,
+            registry_client,
             membership,
             crypto,
             state_manager,
@@ -135,6 +147,57 @@ impl<'a> CatchUpPackageMaker {
         None
     }
 
+    /// Returns true if a CatchUpPackage should be made for the given summary
+    /// block, according to the consensus parameters at the registry version
+    /// of its DKG summary. The decision only depends on the finalized chain
+    /// and the registry, so all honest nodes agree on it.
+    fn is_catch_up_package_height(&self, pool: &PoolReader<'_>, summary_block: &Block) -> bool {
+        let height = summary_block.height();
+        let registry_version = summary_block
+            .payload
+            .as_ref()
+            .as_summary()
+            .dkg
+            .registry_version;
+        let subnet_id = self.replica_config.subnet_id;
+
+        // A replica upgrade happens at a CatchUpPackage, so it is always made.
+        if lookup_replica_version(
+            self.registry_client.as_ref(),
+            subnet_id,
+            &self.log,
+            registry_version,
+        ) != Some(ReplicaVersion::default())
+        {
+            return true;
+        }
+
+        let interval_length = match self
+            .registry_client
+            .get_consensus_parameters(subnet_id, registry_version)
+        {
+            Ok(parameters) => parameters
+                .flatten()
+                .map_or(0, |parameters| parameters.catch_up_package_interval_length),
+            Err(err) => {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "Could not retrieve consensus parameters from the registry: {:?}", err
+                );
+                0
+            }
+        };
+        if interval_length == 0 {
+            return true;
+        }
+        let previous_summary_height = match pool.get_finalized_block(height.decrement()) {
+            Some(block) => block.payload.as_ref().dkg_interval_start_height(),
+            None => return true,
+        };
+        height.get() / interval_length > previous_summary_height.get() / interval_length
+    }
+
     /// Consider the provided block for the creation of a catch up package.
     fn consider_block(
         &self,
@@ -143,6 +206,11 @@ impl<'a> CatchUpPackageMaker {
     ) -> Option<CatchUpPackageShare> {
         let height = start_block.height();
 
+        // Skip if no CUP is made at this summary block
+        if !self.is_catch_up_package_height(pool, &start_block) {
+            return None;
+        }
+
         // Skip if this node is not in the committee to make CUP shares
         let my_node_id = self.replica_config.node_id;
         if self.membership.node_belongs_to_threshold_committee(
@@ -242,6 +310,7 @@ mod tests {
     use super::*;
     use crate::consensus::mocks::{dependencies_with_subnet_params, Dependencies};
     use ic_logger::replica_logger::no_op_logger;
+    use ic_protobuf::registry::subnet::v1::ConsensusParameters;
     use ic_test_utilities::{
         message_routing::FakeMessageRouting,
         registry::SubnetRecordBuilder,
@@ -256,6 +325,7 @@ mod tests {
             let committee: Vec<_> = (0..4).map(node_test_id).collect();
             let Dependencies {
                 mut pool,
+                registry,
                 membership,
                 replica_config,
                 crypto,
@@ -283,6 +353,7 @@ mod tests {
 
             let cup_maker = CatchUpPackageMaker::new(
                 replica_config,
+                registry,
                 membership,
                 crypto,
                 state_manager,
@@ -324,6 +395,7 @@ mod tests {
             let committee: Vec<_> = (0..5).map(node_test_id).collect();
             let Dependencies {
                 mut pool,
+                registry,
                 membership,
                 replica_config,
                 crypto,
@@ -363,6 +435,7 @@ mod tests {
 
             let cup_maker = CatchUpPackageMaker::new(
                 replica_config,
+                registry,
                 membership,
                 crypto,
                 state_manager,
@@ -383,6 +456,7 @@ mod tests {
             let committee: Vec<_> = (0..5).map(node_test_id).collect();
             let Dependencies {
                 mut pool,
+                registry,
                 membership,
                 replica_config,
                 crypto,
@@ -407,6 +481,7 @@ mod tests {
             let message_routing = Arc::new(FakeMessageRouting::new());
             let cup_maker = CatchUpPackageMaker::new(
                 replica_config,
+                registry,
                 membership,
                 crypto,
                 state_manager.clone(),
@@ -456,4 +531,61 @@ mod tests {
             cup_maker.on_state_change(&PoolReader::new(&pool));
         })
     }
+
+    #[test]
+    fn test_catch_up_package_interval_length() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let interval_length = 3;
+            let committee: Vec<_> = (0..4).map(node_test_id).collect();
+            let Dependencies {
+                mut pool,
+                registry,
+                membership,
+                replica_config,
+                crypto,
+                state_manager,
+                ..
+            } = dependencies_with_subnet_params(
+                pool_config,
+                subnet_test_id(0),
+                vec![(
+                    1,
+                    SubnetRecordBuilder::from(&committee)
+                        .with_dkg_interval_length(interval_length)
+                        .with_consensus_parameters(ConsensusParameters {
+                            catch_up_package_retention_heights: 0,
+                            max_unvalidated_artifacts_per_peer: 2048,
+                            backup_group_size: 10000,
+                            backup_purging_interval_secs: 3600,
+                            catch_up_package_interval_length: 8,
+                        })
+                        .build(),
+                )],
+            );
+            let cup_maker = CatchUpPackageMaker::new(
+                replica_config,
+                registry,
+                membership,
+                crypto,
+                state_manager,
+                Arc::new(FakeMessageRouting::new()),
+                no_op_logger(),
+            );
+
+            // Summary blocks are at heights 4, 8 and 12, but only the one at
+            // height 8 reaches the next multiple of 8.
+            pool.advance_round_normal_operation_n(12);
+            let pool_reader = PoolReader::new(&pool);
+            let is_catch_up_package_height = |height: u64| {
+                let block = pool_reader
+                    .get_finalized_block(Height::from(height))
+                    .unwrap();
+                assert!(block.payload.as_ref().is_summary());
+                cup_maker.is_catch_up_package_height(&pool_reader, &block)
+            };
+            assert!(!is_catch_up_package_height(4));
+            assert!(is_catch_up_package_height(8));
+            assert!(!is_catch_up_package_height(12));
+        })
+    }
 }
@@ -0,0 +1,499 @@
+//! Equivocation detection
+//!
+//! A block maker equivocates if it proposes more than one block at the same
+//! height. Since the rank of a proposal determines its block maker, two
+//! validated proposals with the same height and rank but different hashes are
+//! evidence of an equivocation.
+//!
+//! Notaries are expected to sign all valid proposals of the lowest rank,
+//! including the equivocating ones (see `notary`), so two notarization shares
+//! of the same node at the same height are not evidence of misbehavior. A
+//! notary must however not create a finalization share for a block if it
+//! notarized another block at the same height (see `finalizer`), so such a
+//! pair of shares is evidence of an equivocation.
+//!
+//! The validator detects both kinds of equivocation when it validates the
+//! second artifact of a pair, and records the pair as `EquivocationEvidence`
+//! with a `ChangeAction::RecordEquivocation`. The consensus pool persists the
+//! evidence separately from the pool sections, so that it is not purged.
+//!
+//! The recorded evidence is gossiped as an artifact type of its own, so that
+//! it reaches every replica of the subnet, also after the conflicting
+//! artifacts were purged. The `EquivocationEvidenceValidatorImpl` records the
+//! evidence received from peers once it verified that the two artifacts
+//! conflict and are both signed by the equivocating node.
+//!
+//! `make_equivocation_report` turns the recorded evidence into a report that
+//! names the node operator of every equivocating node in the registry, so that
+//! the node providers can be held accountable through NNS proposals.
+use crate::consensus::{metrics::EquivocationEvidenceMetrics, pool_reader::PoolReader, prelude::*};
+use ic_interfaces::{
+    consensus::{EquivocationEvidenceGossip, EquivocationEvidenceValidator},
+    consensus_pool::ConsensusPool,
+    registry::RegistryClient,
+};
+use ic_logger::{warn, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
+use ic_protobuf::types::v1 as pb;
+use ic_registry_client::helper::node::NodeRegistry;
+use ic_types::{
+    artifact::{EquivocationEvidenceAttribute, EquivocationEvidenceId, Priority, PriorityFn},
+    consensus::equivocation::{EquivocationEvidence, EquivocationKind},
+};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// Returns true if the two proposals are evidence of an equivocation.
+pub fn is_block_maker_equivocation(first: &BlockProposal, second: &BlockProposal) -> bool {
+    first.height() == second.height()
+        && first.rank() == second.rank()
+        && first.signature.signer == second.signature.signer
+        && first.content.get_hash() != second.content.get_hash()
+}
+
+/// Returns true if the two shares are evidence of an equivocation.
+pub fn is_notary_equivocation(
+    finalization_share: &FinalizationShare,
+    notarization_share: &NotarizationShare,
+) -> bool {
+    finalization_share.height() == notarization_share.height()
+        && finalization_share.signature.signer == notarization_share.signature.signer
+        && finalization_share.content.block != notarization_share.content.block
+}
+
+/// Returns the evidence of an equivocation between the given proposal and a
+/// validated proposal, if there is one.
+pub fn find_block_maker_equivocation(
+    pool: &PoolReader<'_>,
+    proposal: &BlockProposal,
+) -> Option<EquivocationEvidence> {
+    pool.pool()
+        .validated()
+        .block_proposal()
+        .get_by_height(proposal.height())
+        .find(|other| is_block_maker_equivocation(other, proposal))
+        .map(|other| EquivocationEvidence::BlockMaker {
+            first: other,
+            second: proposal.clone(),
+        })
+}
+
+/// Returns the evidence of an equivocation between the given finalization
+/// share and a validated notarization share, if there is one.
+pub fn find_equivocating_notarization_share(
+    pool: &PoolReader<'_>,
+    finalization_share: &FinalizationShare,
+) -> Option<EquivocationEvidence> {
+    pool.pool()
+        .validated()
+        .notarization_share()
+        .get_by_height(finalization_share.height())
+        .find(|share| is_notary_equivocation(finalization_share, share))
+        .map(|notarization_share| EquivocationEvidence::Notary {
+            finalization_share: finalization_share.clone(),
+            notarization_share,
+        })
+}
+
+/// Returns the evidence of an equivocation between the given notarization
+/// share and a validated finalization share, if there is one.
+pub fn find_equivocating_finalization_share(
+    pool: &PoolReader<'_>,
+    notarization_share: &NotarizationShare,
+) -> Option<EquivocationEvidence> {
+    pool.pool()
+        .validated()
+        .finalization_share()
+        .get_by_height(notarization_share.height())
+        .find(|share| is_notary_equivocation(share, notarization_share))
+        .map(|finalization_share| EquivocationEvidence::Notary {
+            finalization_share,
+            notarization_share: notarization_share.clone(),
+        })
+}
+
+/// Returns the nodes and kinds of equivocation the pool holds evidence for.
+fn recorded_equivocations(pool: &dyn ConsensusPool) -> BTreeSet<(NodeId, EquivocationKind)> {
+    pool.equivocation_evidence()
+        .iter()
+        .map(|evidence| (evidence.signer(), evidence.kind()))
+        .collect()
+}
+
+/// Validates the evidence of equivocation gossiped by other replicas.
+pub struct EquivocationEvidenceValidatorImpl {
+    crypto: Arc<dyn ConsensusCrypto>,
+    metrics: EquivocationEvidenceMetrics,
+    log: ReplicaLogger,
+}
+
+impl EquivocationEvidenceValidatorImpl {
+    pub fn new(
+        crypto: Arc<dyn ConsensusCrypto>,
+        metrics_registry: MetricsRegistry,
+        log: ReplicaLogger,
+    ) -> Self {
+        Self {
+            crypto,
+            metrics: EquivocationEvidenceMetrics::new(metrics_registry),
+            log,
+        }
+    }
+
+    /// Checks that the two artifacts of the evidence conflict, and that both
+    /// are signed by the equivocating node with its key at the given registry
+    /// version. The signature of a proposal covers the block rather than its
+    /// hash, so the blocks themselves must differ.
+    fn validate(
+        &self,
+        evidence: &EquivocationEvidence,
+        registry_version: RegistryVersion,
+    ) -> Result<(), String> {
+        match evidence {
+            EquivocationEvidence::BlockMaker { first, second } => {
+                if !is_block_maker_equivocation(first, second)
+                    || first.content.as_ref() == second.content.as_ref()
+                {
+                    return Err("the proposals do not conflict".to_string());
+                }
+                self.crypto
+                    .verify(first, registry_version)
+                    .and_then(|()| self.crypto.verify(second, registry_version))
+            }
+            EquivocationEvidence::Notary {
+                finalization_share,
+                notarization_share,
+            } => {
+                if !is_notary_equivocation(finalization_share, notarization_share) {
+                    return Err("the shares do not conflict".to_string());
+                }
+                self.crypto
+                    .verify(finalization_share, registry_version)
+                    .and_then(|()| self.crypto.verify(notarization_share, registry_version))
+            }
+        }
+        .map_err(|err| format!("invalid signature: {:?}", err))
+    }
+}
+
+impl EquivocationEvidenceValidator for EquivocationEvidenceValidatorImpl {
+    /// Records the valid pieces of the given evidence, unless the pool already
+    /// holds evidence of the same kind of equivocation by the same node.
+    /// Evidence at a height whose registry version is not known to the pool
+    /// cannot be verified, and is ignored.
+    fn on_state_change(
+        &self,
+        consensus_pool: &dyn ConsensusPool,
+        evidence: Vec<EquivocationEvidence>,
+    ) -> ChangeSet {
+        let pool_reader = PoolReader::new(consensus_pool);
+        let mut recorded = recorded_equivocations(consensus_pool);
+        let mut change_set = ChangeSet::new();
+        for evidence in evidence {
+            let key = (evidence.signer(), evidence.kind());
+            if recorded.contains(&key) {
+                continue;
+            }
+            let outcome = match pool_reader.registry_version(evidence.height()) {
+                None => "unverifiable",
+                Some(registry_version) => match self.validate(&evidence, registry_version) {
+                    Ok(()) => "valid",
+                    Err(err) => {
+                        warn!(
+                            self.log,
+                            "Ignoring invalid equivocation evidence, {}: {:?}", err, evidence
+                        );
+                        "invalid"
+                    }
+                },
+            };
+            self.metrics
+                .received_evidence
+                .with_label_values(&[evidence.kind().as_str(), outcome])
+                .inc();
+            if outcome == "valid" {
+                warn!(
+                    self.log,
+                    "Node {} equivocated as {} at height {}, as a peer reported",
+                    evidence.signer(),
+                    evidence.kind(),
+                    evidence.height()
+                );
+                recorded.insert(key);
+                change_set.push(ChangeAction::RecordEquivocation(evidence));
+            }
+        }
+        change_set
+    }
+}
+
+/// Implementation of the equivocation evidence gossip interface.
+pub struct EquivocationEvidenceGossipImpl;
+
+impl EquivocationEvidenceGossip for EquivocationEvidenceGossipImpl {
+    /// Fetches the evidence of the equivocations the pool holds no evidence
+    /// of yet. Evidence below the catch-up package is dropped, as the registry
+    /// version to verify it at is no longer known.
+    fn get_priority_function(
+        &self,
+        consensus_pool: &dyn ConsensusPool,
+    ) -> PriorityFn<EquivocationEvidenceId, EquivocationEvidenceAttribute> {
+        let catch_up_height = PoolReader::new(consensus_pool).get_catch_up_height();
+        let recorded = recorded_equivocations(consensus_pool);
+        Box::new(move |_, attribute: &'_ EquivocationEvidenceAttribute| {
+            if attribute.height < catch_up_height
+                || recorded.contains(&(attribute.signer, attribute.kind))
+            {
+                Priority::Drop
+            } else {
+                Priority::Fetch
+            }
+        })
+    }
+}
+
+/// Returns the report of the evidence recorded in the pool. The report names
+/// the node operator of each equivocating node at the given registry version,
+/// and the registry version to verify the signatures of the evidence against,
+/// if the pool still knows it.
+pub fn make_equivocation_report(
+    subnet_id: SubnetId,
+    pool: &PoolReader<'_>,
+    registry_client: &dyn RegistryClient,
+    registry_version: RegistryVersion,
+) -> pb::EquivocationReport {
+    let entries = pool
+        .pool()
+        .equivocation_evidence()
+        .iter()
+        .map(|evidence| pb::EquivocationReportEntry {
+            node_id: Some(node_id_into_protobuf(evidence.signer())),
+            node_operator_id: registry_client
+                .get_transport_info(evidence.signer(), registry_version)
+                .ok()
+                .flatten()
+                .map(|node_record| node_record.node_operator_id)
+                .unwrap_or_default(),
+            signing_registry_version: pool
+                .registry_version(evidence.height())
+                .map(|version| version.get())
+                .unwrap_or_default(),
+            evidence: Some(evidence.into()),
+        })
+        .collect();
+    pb::EquivocationReport {
+        subnet_id: Some(subnet_id_into_protobuf(subnet_id)),
+        registry_version: registry_version.get(),
+        entries,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::mocks::{dependencies, Dependencies};
+    use ic_interfaces::consensus_pool::MutableConsensusPool;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_protobuf::registry::node::v1::NodeRecord;
+    use ic_registry_keys::make_node_record_key;
+    use ic_test_utilities::{
+        consensus::fake::*,
+        types::ids::{node_test_id, subnet_test_id},
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_find_block_maker_equivocation() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let Dependencies { mut pool, .. } = dependencies(pool_config, 1);
+            let mut block = pool.make_next_block();
+            block.content.as_mut().rank = Rank(1);
+            block.update_content();
+            pool.insert_validated(block.clone());
+
+            // A proposal of another rank is no evidence.
+            let mut other_rank = block.clone();
+            other_rank.content.as_mut().rank = Rank(2);
+            other_rank.update_content();
+            assert!(!is_block_maker_equivocation(&block, &other_rank));
+            assert!(find_block_maker_equivocation(&PoolReader::new(&pool), &other_rank).is_none());
+
+            let mut equivocating = block.clone();
+            equivocating.content.as_mut().context.time += Duration::from_millis(1);
+            equivocating.update_content();
+            assert!(is_block_maker_equivocation(&block, &equivocating));
+            assert_eq!(
+                find_block_maker_equivocation(&PoolReader::new(&pool), &equivocating),
+                Some(EquivocationEvidence::BlockMaker {
+                    first: block,
+                    second: equivocating,
+                })
+            );
+        })
+    }
+
+    #[test]
+    fn test_find_notary_equivocation() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let Dependencies { mut pool, .. } = dependencies(pool_config, 1);
+            let block = pool.make_next_block();
+            let mut other_block = block.clone();
+            other_block.content.as_mut().context.time += Duration::from_millis(1);
+            other_block.update_content();
+            let notary = block.signature.signer;
+
+            let notarization_share = NotarizationShare::fake(block.as_ref(), notary);
+            let other_notarization_share = NotarizationShare::fake(other_block.as_ref(), notary);
+            let finalization_share = FinalizationShare::fake(block.as_ref(), notary);
+            pool.insert_validated(notarization_share.clone());
+
+            // Notarizing and finalizing the same block is no evidence.
+            assert!(!is_notary_equivocation(
+                &finalization_share,
+                &notarization_share
+            ));
+            assert!(find_equivocating_notarization_share(
+                &PoolReader::new(&pool),
+                &finalization_share
+            )
+            .is_none());
+
+            pool.insert_validated(finalization_share.clone());
+            let evidence = EquivocationEvidence::Notary {
+                finalization_share: finalization_share.clone(),
+                notarization_share: other_notarization_share.clone(),
+            };
+            assert_eq!(
+                find_equivocating_finalization_share(
+                    &PoolReader::new(&pool),
+                    &other_notarization_share
+                ),
+                Some(evidence.clone())
+            );
+            assert_eq!(evidence.signer(), notary);
+            assert_eq!(evidence.height(), block.height());
+        })
+    }
+
+    #[test]
+    fn test_gossiped_equivocation_evidence_is_validated_and_recorded() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let Dependencies {
+                mut pool,
+                crypto,
+                time_source,
+                ..
+            } = dependencies(pool_config, 1);
+            let validator = EquivocationEvidenceValidatorImpl::new(
+                crypto,
+                MetricsRegistry::new(),
+                no_op_logger(),
+            );
+            let block = pool.make_next_block();
+            let mut equivocating = block.clone();
+            equivocating.content.as_mut().context.time += Duration::from_millis(1);
+            equivocating.update_content();
+            let mut other_rank = block.clone();
+            other_rank.content.as_mut().rank = Rank(1);
+            other_rank.update_content();
+
+            let evidence = EquivocationEvidence::BlockMaker {
+                first: block.clone(),
+                second: equivocating,
+            };
+            let attribute = EquivocationEvidenceAttribute::from(&evidence);
+            let id = ic_crypto::crypto_hash(&evidence);
+            let priority = EquivocationEvidenceGossipImpl.get_priority_function(&pool);
+            assert_eq!(priority(&id, &attribute), Priority::Fetch);
+
+            // Proposals of different ranks, or twice the same proposal, are no
+            // evidence.
+            let no_evidence = vec![
+                EquivocationEvidence::BlockMaker {
+                    first: block.clone(),
+                    second: other_rank,
+                },
+                EquivocationEvidence::BlockMaker {
+                    first: block.clone(),
+                    second: block,
+                },
+            ];
+            assert!(validator.on_state_change(&pool, no_evidence).is_empty());
+
+            // Only the first evidence of an equivocation is recorded.
+            let change_set =
+                validator.on_state_change(&pool, vec![evidence.clone(), evidence.clone()]);
+            assert_eq!(
+                change_set,
+                vec![ChangeAction::RecordEquivocation(evidence.clone())]
+            );
+            pool.apply_changes(time_source.as_ref(), change_set);
+            assert_eq!(pool.equivocation_evidence(), vec![evidence.clone()]);
+            assert!(validator
+                .on_state_change(&pool, vec![evidence.clone()])
+                .is_empty());
+            let priority = EquivocationEvidenceGossipImpl.get_priority_function(&pool);
+            assert_eq!(priority(&id, &attribute), Priority::Drop);
+        })
+    }
+
+    #[test]
+    fn test_equivocation_report_names_the_node_operator() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let Dependencies {
+                mut pool,
+                registry,
+                registry_data_provider,
+                time_source,
+                ..
+            } = dependencies(pool_config, 1);
+            let block = pool.make_next_block();
+            let mut other_block = block.clone();
+            other_block.content.as_mut().context.time += Duration::from_millis(1);
+            other_block.update_content();
+            let notary = block.signature.signer;
+            let evidence = EquivocationEvidence::Notary {
+                finalization_share: FinalizationShare::fake(block.as_ref(), notary),
+                notarization_share: NotarizationShare::fake(other_block.as_ref(), notary),
+            };
+            pool.apply_changes(
+                time_source.as_ref(),
+                vec![ChangeAction::RecordEquivocation(evidence.clone())],
+            );
+
+            let node_operator_id = node_test_id(42).get().into_vec();
+            let registry_version = registry.get_latest_version().increment();
+            registry_data_provider
+                .add(
+                    &make_node_record_key(notary),
+                    registry_version,
+                    Some(NodeRecord {
+                        node_operator_id: node_operator_id.clone(),
+                        ..Default::default()
+                    }),
+                )
+                .unwrap();
+            registry.update_to_latest_version();
+
+            let report = make_equivocation_report(
+                subnet_test_id(0),
+                &PoolReader::new(&pool),
+                registry.as_ref(),
+                registry_version,
+            );
+            assert_eq!(report.registry_version, registry_version.get());
+            assert_eq!(
+                report.entries,
+                vec![pb::EquivocationReportEntry {
+                    node_id: Some(node_id_into_protobuf(notary)),
+                    node_operator_id,
+                    signing_registry_version: PoolReader::new(&pool)
+                        .registry_version(block.height())
+                        .unwrap()
+                        .get(),
+                    evidence: Some((&evidence).into()),
+                }]
+            );
+        })
+    }
+}
@@ -224,6 +224,7 @@ impl PayloadBuilderMetrics {
 pub struct ValidatorMetrics {
     pub time_to_receive_block: HistogramVec,
     pub duplicate_artifact: IntCounterVec,
+    pub equivocations: IntCounterVec,
     pub validation_duration: HistogramVec,
     pub dkg_validator: IntCounterVec,
     // Used to sum the values within a single validator run
@@ -247,6 +248,11 @@ impl ValidatorMetrics {
                 "The number of duplicate notarizations and finalizations Consensus has received",
                 &["artifact"],
             ),
+            equivocations: metrics_registry.int_counter_vec(
+                "consensus_equivocations",
+                "The number of equivocations Consensus has detected, labelled by the artifact type",
+                &["artifact"],
+            ),
             validation_duration: metrics_registry.histogram_vec(
                 "consensus_validation_duration_seconds",
                 "Time to validate by subcomponent, in seconds",
@@ -322,6 +328,22 @@ impl PurgerMetrics {
     }
 }
 
+pub struct EquivocationEvidenceMetrics {
+    pub received_evidence: IntCounterVec,
+}
+
+impl EquivocationEvidenceMetrics {
+    pub fn new(metrics_registry: MetricsRegistry) -> Self {
+        Self {
+            received_evidence: metrics_registry.int_counter_vec(
+                "consensus_received_equivocation_evidence",
+                "The number of pieces of equivocation evidence received from peers, labelled by the kind of equivocation and the validation outcome",
+                &["kind", "outcome"],
+            ),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EcdsaClientMetrics {
     pub on_state_change_duration: HistogramVec,
@@ -41,6 +41,7 @@ mock! {
             past_payloads: &[(Height, Time, Payload)],
             context: &ValidationContext,
             subnet_records: &SubnetRecords,
+            finalization_lag: u64,
         ) -> BatchPayload;
 
         fn validate_payload(
@@ -1,6 +1,8 @@
 //! Payload creation/validation subcomponent
 
-use crate::consensus::metrics::PayloadBuilderMetrics;
+use crate::consensus::{
+    block_rate::adjusted_max_block_payload_size, metrics::PayloadBuilderMetrics,
+};
 use ic_interfaces::{
     consensus::{PayloadPermanentError, PayloadTransientError, PayloadValidationError},
     ingress_manager::{IngressSelector, IngressSetQuery},
@@ -13,7 +15,7 @@ use ic_interfaces::{
 use ic_logger::{warn, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_protobuf::registry::subnet::v1::SubnetRecord;
-use ic_registry_client::helper::subnet::SubnetRegistry;
+use ic_registry_client::helper::subnet::{AdaptiveBlockRateSettings, SubnetRegistry};
 use ic_types::{
     artifact::IngressMessageId,
     batch::{BatchPayload, SelfValidatingPayload, ValidationContext, XNetPayload},
@@ -35,6 +37,10 @@ pub trait PayloadBuilder: Send + Sync {
     /// `past_payloads` contains the `Payloads` from all blocks above the
     /// certified height provided in `context`, in descending block height
     /// order.
+    ///
+    /// `finalization_lag` is the number of notarized heights below `height`
+    /// that are not finalized yet. If the subnet uses an adaptive block rate,
+    /// a lagging finalization reduces the size of the payload.
     fn get_payload(
         &self,
         height: Height,
@@ -42,6 +48,7 @@ pub trait PayloadBuilder: Send + Sync {
         past_payloads: &[(Height, Time, Payload)],
         context: &ValidationContext,
         subnet_records: &SubnetRecords,
+        finalization_lag: u64,
     ) -> BatchPayload;
 
     /// Checks whether the provided `payload` is valid given `past_payloads` and
@@ -135,6 +142,7 @@ impl PayloadBuilder for PayloadBuilderImpl {
         past_payloads: &[(Height, Time, Payload)],
         context: &ValidationContext,
         subnet_records: &SubnetRecords,
+        finalization_lag: u64,
     ) -> BatchPayload {
         let _timer = self.metrics.get_payload_duration.start_timer();
         self.metrics
@@ -162,7 +170,8 @@ impl PayloadBuilder for PayloadBuilderImpl {
         // On a block with even height, we fill up the block with xnet messages.
         // If there is space left, we fill it is ingress messages.
         // On odd blocks, we prioritize ingress over xnet.
-        let max_block_payload_size = self.get_max_block_payload_size_bytes(&subnet_records.stable);
+        let max_block_payload_size =
+            self.get_max_block_payload_size_bytes(&subnet_records.stable, finalization_lag);
         let get_ingress_payload = |byte_limit| {
             self.ingress_selector.get_ingress_payload(
                 ingress_pool,
@@ -249,7 +258,7 @@ impl PayloadBuilder for PayloadBuilderImpl {
                     PayloadTransientError::SubnetNotFound(self.subnet_id),
                 ));
             }
-            Ok(Some(subnet_record)) => self.get_max_block_payload_size_bytes(&subnet_record),
+            Ok(Some(subnet_record)) => self.get_max_block_payload_size_bytes(&subnet_record, 0),
         };
 
         // If ingress valiation is not valid, return it early.
@@ -294,12 +303,33 @@ impl PayloadBuilderImpl {
     /// Returns the valid maximum block payload length from the registry and
     /// checks the invariants. Emits a warning in case the invariants are not
     /// met.
-    fn get_max_block_payload_size_bytes(&self, subnet_record: &SubnetRecord) -> NumBytes {
+    ///
+    /// If the subnet uses an adaptive block rate, the length is reduced
+    /// according to the `finalization_lag`. Payloads are always validated
+    /// without a lag, i.e. against the length of the subnet record, as the
+    /// lag observed by the block maker is not known to the validators.
+    fn get_max_block_payload_size_bytes(
+        &self,
+        subnet_record: &SubnetRecord,
+        finalization_lag: u64,
+    ) -> NumBytes {
         let required_min_size = MAX_XNET_PAYLOAD_IN_BYTES
             .get()
             .max(subnet_record.max_ingress_bytes_per_message);
 
         let mut max_block_payload_size = subnet_record.max_block_payload_size;
+        if let Some(settings) = subnet_record
+            .adaptive_block_rate_config
+            .as_ref()
+            .map(AdaptiveBlockRateSettings::from)
+        {
+            max_block_payload_size = adjusted_max_block_payload_size(
+                &settings,
+                NumBytes::new(max_block_payload_size),
+                finalization_lag,
+            )
+            .get();
+        }
         // In any case, ensure the value is bigger than inter canister payload and
         // message size
         if max_block_payload_size < required_min_size {
@@ -388,6 +418,7 @@ mod test {
     use crate::consensus::mocks::{dependencies, dependencies_with_subnet_params, Dependencies};
     use ic_interfaces::self_validating_payload::NoOpSelfValidatingPayloadBuilder;
     use ic_logger::replica_logger::no_op_logger;
+    use ic_protobuf::registry::subnet::v1::AdaptiveBlockRateConfig;
     use ic_test_artifact_pool::ingress_pool::TestIngressPool;
     use ic_test_utilities::{
         consensus::fake::Fake,
@@ -504,6 +535,7 @@ mod test {
                     &prev_payloads,
                     &context,
                     &subnet_records,
+                    0,
                 )
                 .into_messages()
                 .unwrap();
@@ -622,6 +654,7 @@ mod test {
                 &[],
                 &context,
                 &subnet_records,
+                0,
             );
             let wrapped_payload0 = batch_payload_to_payload(0, payload0);
             payload_builder
@@ -636,6 +669,7 @@ mod test {
                 &past_payload0,
                 &context,
                 &subnet_records,
+                0,
             );
             let wrapped_payload1 = batch_payload_to_payload(0, payload1);
             payload_builder
@@ -651,6 +685,7 @@ mod test {
                 &past_payload1,
                 &context,
                 &subnet_records,
+                0,
             );
 
             let pb_result = payload_builder.validate_payload(
@@ -669,4 +704,42 @@ mod test {
             }
         });
     }
+
+    // Tests that the maximum block payload size shrinks with the finalization
+    // lag, if the subnet uses an adaptive block rate, while payloads are still
+    // validated against the size of the subnet record.
+    #[test]
+    fn test_adaptive_max_block_payload_size() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let max_size = 4 * MAX_XNET_PAYLOAD_IN_BYTES.get();
+            let mut subnet_record = SubnetRecordBuilder::from(&[node_test_id(0)])
+                .with_adaptive_block_rate_config(AdaptiveBlockRateConfig {
+                    window: 10,
+                    target_round_time_millis: 1000,
+                    max_initial_notary_delay_millis: 3000,
+                    min_block_payload_size: 0,
+                })
+                .build();
+            subnet_record.max_block_payload_size = max_size;
+            subnet_record.max_ingress_bytes_per_message = 1024;
+
+            let Dependencies { registry, .. } = dependencies_with_subnet_params(
+                pool_config,
+                subnet_test_id(0),
+                vec![(1, subnet_record.clone())],
+            );
+            let payload_builder = make_test_payload_impl(registry, vec![], vec![]);
+            let max_payload_size = |finalization_lag| {
+                payload_builder
+                    .get_max_block_payload_size_bytes(&subnet_record, finalization_lag)
+                    .get()
+            };
+
+            assert_eq!(max_payload_size(0), max_size);
+            assert_eq!(max_payload_size(1), max_size);
+            assert_eq!(max_payload_size(2), max_size / 2);
+            // The size never drops below the required minimum.
+            assert_eq!(max_payload_size(8), MAX_XNET_PAYLOAD_IN_BYTES.get());
+        });
+    }
 }
@@ -11,17 +11,21 @@
 //!
 //! 2. Validated artifacts below the latest CatchUpPackage height can be purged.
 //! But we also want to keep a minimum chain length that is older than the
-//! CatchUpPackage to help peers catch up.
+//! CatchUpPackage to help peers catch up. The length can be tuned per subnet
+//! through the `ConsensusParameters` in the subnet record.
 //!
 //! 3. Replicated states below the certified height recorded in the block
 //! in the latest CatchUpPackage can be purged.
 use crate::consensus::{metrics::PurgerMetrics, pool_reader::PoolReader, prelude::*};
 use ic_interfaces::{
-    consensus_pool::HeightRange, messaging::MessageRouting, state_manager::StateManager,
+    consensus_pool::HeightRange, messaging::MessageRouting, registry::RegistryClient,
+    state_manager::StateManager,
 };
 use ic_logger::{trace, warn, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
+use ic_registry_client::helper::subnet::SubnetRegistry;
 use ic_replicated_state::ReplicatedState;
+use ic_types::replica_config::ReplicaConfig;
 use std::cell::RefCell;
 use std::sync::Arc;
 
@@ -29,6 +33,10 @@ use std::sync::Arc;
 pub struct Purger {
     prev_expected_batch_height: RefCell<Height>,
     prev_finalized_certified_height: RefCell<Height>,
+    // The minimum chain length and the registry version it was read at.
+    minimum_chain_length: RefCell<Option<(RegistryVersion, Height)>>,
+    replica_config: ReplicaConfig,
+    registry_client: Arc<dyn RegistryClient>,
     state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
     message_routing: Arc<dyn MessageRouting>,
     log: ReplicaLogger,
@@ -37,6 +45,8 @@ pub struct Purger {
 
 impl Purger {
     pub fn new(
+        replica_config: ReplicaConfig,
+        registry_client: Arc<dyn RegistryClient>,
         state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
         message_routing: Arc<dyn MessageRouting>,
         log: ReplicaLogger,
@@ -46,6 +56,9 @@ impl Purger {
             // expected_batch_height starts from 1
             prev_expected_batch_height: RefCell::new(Height::from(1)),
             prev_finalized_certified_height: RefCell::new(Height::from(1)),
+            minimum_chain_length: RefCell::new(None),
+            replica_config,
+            registry_client,
             state_manager,
             message_routing,
             log,
@@ -149,7 +162,8 @@ impl Purger {
         pool_reader: &PoolReader<'_>,
         changeset: &mut ChangeSet,
     ) -> bool {
-        if let Some(purge_height) = get_purge_height(pool_reader) {
+        let min_length = self.get_minimum_chain_length(pool_reader);
+        if let Some(purge_height) = get_purge_height(pool_reader, min_length) {
             if purge_height < self.state_manager.latest_state_height() {
                 changeset.push(ChangeAction::PurgeValidatedBelow(purge_height));
                 trace!(self.log, "Purge validated pool below {:?}", purge_height);
@@ -173,6 +187,49 @@ impl Purger {
         }
     }
 
+    /// Returns the chain length to keep below the latest CatchUpPackage. It is
+    /// read from the subnet record at the registry version of the current DKG
+    /// interval, and defaults to `MINIMUM_CHAIN_LENGTH`. The registry is only
+    /// consulted when the registry version changes.
+    fn get_minimum_chain_length(&self, pool_reader: &PoolReader<'_>) -> Height {
+        let registry_version =
+            match pool_reader.registry_version(pool_reader.get_finalized_height()) {
+                Some(registry_version) => registry_version,
+                None => return Height::from(MINIMUM_CHAIN_LENGTH),
+            };
+        if let Some((cached_version, min_length)) = *self.minimum_chain_length.borrow() {
+            if cached_version == registry_version {
+                return min_length;
+            }
+        }
+        let min_length = match self
+            .registry_client
+            .get_consensus_parameters(self.replica_config.subnet_id, registry_version)
+        {
+            Ok(parameters) => {
+                let min_length = Height::from(
+                    parameters
+                        .flatten()
+                        .map_or(MINIMUM_CHAIN_LENGTH, |parameters| {
+                            parameters.catch_up_package_retention_heights
+                        }),
+                );
+                self.minimum_chain_length
+                    .replace(Some((registry_version, min_length)));
+                min_length
+            }
+            Err(err) => {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "Could not retrieve consensus parameters from the registry: {:?}", err
+                );
+                Height::from(MINIMUM_CHAIN_LENGTH)
+            }
+        };
+        min_length
+    }
+
     /// Ask state manager to purge all states below the certified height
     /// recorded in the block in the latest finalized block.
     fn purge_replicated_state_by_finalized_certified_height(&self, pool_reader: &PoolReader<'_>) {
@@ -196,18 +253,19 @@ impl Purger {
     }
 }
 
-/// We always keep a minimum chain length below catch-up height.
+/// The chain length we keep below catch-up height, unless the subnet record
+/// specifies a different one.
 const MINIMUM_CHAIN_LENGTH: u64 = 50;
 
 /// Compute the purge height by looking at available CatchUpPackage(s) in the
-/// validated pool. Usually things with height less than a min_length below the
+/// validated pool. Usually things with height less than `min_length` below the
 /// latest catch up height can be purged, but if there is nothing to purge,
 /// this function will return None.
 ///
 /// Note that for actual purging, we must also consider execution state
 /// so that we don't purge below latest known state height. Otherwise
 /// we cannot replay past blocks to catch up state during a replica restart.
-pub fn get_purge_height(pool_reader: &PoolReader<'_>) -> Option<Height> {
+pub fn get_purge_height(pool_reader: &PoolReader<'_>, min_length: Height) -> Option<Height> {
     pool_reader
         .pool()
         .validated()
@@ -217,7 +275,6 @@ pub fn get_purge_height(pool_reader: &PoolReader<'_>) -> Option<Height> {
             // The condition check below uses range.min because the existence of a range.min
             // that is different than range.max approximately indicates that a purge should
             // take place.
-            let min_length = Height::from(MINIMUM_CHAIN_LENGTH);
             if range.max > range.min + min_length {
                 Some(range.max - min_length)
             } else {
@@ -230,13 +287,18 @@ pub fn get_purge_height(pool_reader: &PoolReader<'_>) -> Option<Height> {
 mod tests {
     use super::*;
     use crate::consensus::{
-        mocks::{dependencies, Dependencies},
+        mocks::{dependencies, dependencies_with_subnet_params, Dependencies},
         pool_reader::PoolReader,
     };
     use ic_interfaces::consensus_pool::MutableConsensusPool;
     use ic_logger::replica_logger::no_op_logger;
     use ic_metrics::MetricsRegistry;
-    use ic_test_utilities::message_routing::MockMessageRouting;
+    use ic_protobuf::registry::subnet::v1::ConsensusParameters;
+    use ic_test_utilities::{
+        message_routing::MockMessageRouting,
+        registry::SubnetRecordBuilder,
+        types::ids::{node_test_id, subnet_test_id},
+    };
     use std::sync::{Arc, RwLock};
 
     #[test]
@@ -246,6 +308,8 @@ mod tests {
                 mut pool,
                 time_source,
                 state_manager,
+                replica_config,
+                registry,
                 ..
             } = dependencies(pool_config, 1);
 
@@ -278,6 +342,8 @@ mod tests {
                 .returning(move || *expected_batch_height_clone.read().unwrap());
 
             let purger = Purger::new(
+                replica_config,
+                registry,
                 state_manager,
                 Arc::new(message_routing),
                 no_op_logger(),
@@ -311,7 +377,8 @@ mod tests {
 
             // Both unvalidated and validated pools are purged
             let pool_reader = PoolReader::new(&pool);
-            assert!(get_purge_height(&pool_reader).is_some());
+            let min_length = Height::from(MINIMUM_CHAIN_LENGTH);
+            assert!(get_purge_height(&pool_reader, min_length).is_some());
             // Make sure state manager is purged at purge_height too
             *state_purge_height.write().unwrap() = pool_reader
                 .get_highest_catch_up_package()
@@ -331,7 +398,9 @@ mod tests {
             );
             assert_eq!(
                 changeset[1],
-                ChangeAction::PurgeValidatedBelow(get_purge_height(&pool_reader).unwrap())
+                ChangeAction::PurgeValidatedBelow(
+                    get_purge_height(&pool_reader, min_length).unwrap()
+                )
             );
 
             // No more purge action when called again
@@ -347,23 +416,65 @@ mod tests {
         ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
             let Dependencies { mut pool, .. } = dependencies(pool_config, 1);
 
+            let min_length = Height::from(MINIMUM_CHAIN_LENGTH);
             // Initial purge height is None.
-            assert_eq!(get_purge_height(&PoolReader::new(&pool)), None);
+            assert_eq!(get_purge_height(&PoolReader::new(&pool), min_length), None);
 
             // Put some stuff in the pool
             pool.advance_round_normal_operation_n(9);
             // Purge height is still None.
-            assert_eq!(get_purge_height(&PoolReader::new(&pool)), None);
+            assert_eq!(get_purge_height(&PoolReader::new(&pool), min_length), None);
 
             // Put more stuff in the pool above catch_up_package threshold.
             pool.advance_round_normal_operation_n(59);
             let pool_reader = PoolReader::new(&pool);
             let catch_up_height = pool_reader.get_catch_up_height();
-            assert!(catch_up_height > Height::from(MINIMUM_CHAIN_LENGTH));
-            // Purge height is MINIMUM_CHAIN_LENGTH below catch_up_height.
+            assert!(catch_up_height > min_length);
+            // Purge height is min_length below catch_up_height.
+            assert_eq!(
+                get_purge_height(&pool_reader, min_length),
+                Some(catch_up_height - min_length)
+            );
+        })
+    }
+
+    #[test]
+    fn test_minimum_chain_length_from_registry() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let committee = vec![node_test_id(0)];
+            let Dependencies {
+                pool,
+                state_manager,
+                replica_config,
+                registry,
+                ..
+            } = dependencies_with_subnet_params(
+                pool_config,
+                subnet_test_id(0),
+                vec![(
+                    1,
+                    SubnetRecordBuilder::from(&committee)
+                        .with_consensus_parameters(ConsensusParameters {
+                            catch_up_package_retention_heights: 20,
+                            max_unvalidated_artifacts_per_peer: 2048,
+                            backup_group_size: 10000,
+                            backup_purging_interval_secs: 3600,
+                            catch_up_package_interval_length: 0,
+                        })
+                        .build(),
+                )],
+            );
+            let purger = Purger::new(
+                replica_config,
+                registry,
+                state_manager,
+                Arc::new(MockMessageRouting::new()),
+                no_op_logger(),
+                MetricsRegistry::new(),
+            );
             assert_eq!(
-                get_purge_height(&pool_reader),
-                Some(catch_up_height - Height::from(MINIMUM_CHAIN_LENGTH))
+                purger.get_minimum_chain_length(&PoolReader::new(&pool)),
+                Height::from(20)
             );
         })
     }
@@ -1,5 +1,10 @@
 //! Consensus utility functions
-use crate::consensus::{membership::Membership, pool_reader::PoolReader, prelude::*};
+use crate::consensus::{
+    block_rate::{adjusted_initial_notary_delay, get_observed_round_time},
+    membership::Membership,
+    pool_reader::PoolReader,
+    prelude::*,
+};
 use ic_interfaces::{
     consensus_pool::ConsensusPoolCache, crypto::CryptoHashable, registry::RegistryClient,
     state_manager::StateManager, time_source::TimeSource,
@@ -148,7 +153,8 @@ pub fn get_adjusted_notary_delay(
 /// Calculate the required delay for notary based on the rank of block to
 /// notarize, adjusted by a multiplier depending the gap between finalized and
 /// notarized heights, and adjusted by how far the certified height lags behind
-/// the finalized height.
+/// the finalized height. With an adaptive block rate, the initial delay is
+/// first adjusted to the round time observed on the finalized chain.
 pub fn get_adjusted_notary_delay_from_settings(
     settings: NotarizationDelaySettings,
     pool: &PoolReader<'_>,
@@ -157,9 +163,17 @@ pub fn get_adjusted_notary_delay_from_settings(
 ) -> Duration {
     let NotarizationDelaySettings {
         unit_delay,
-        initial_notary_delay,
-        ..
+        mut initial_notary_delay,
+        adaptive_block_rate,
     } = settings;
+    if let Some(adaptive) = adaptive_block_rate {
+        if let Some(round_time) =
+            get_observed_round_time(pool, &pool.get_finalized_tip(), adaptive.window)
+        {
+            initial_notary_delay =
+                adjusted_initial_notary_delay(&adaptive, initial_notary_delay, round_time);
+        }
+    }
     // We adjust regular delay based on the gap between finalization and
     // notarization to make it exponentially longer to keep the gap from growing too
     // big. This is because increasing delay leads to higher chance of notarizing
@@ -530,6 +544,7 @@ mod tests {
             let settings = NotarizationDelaySettings {
                 unit_delay: Duration::from_secs(1),
                 initial_notary_delay: Duration::from_secs(0),
+                adaptive_block_rate: None,
             };
             let crate::consensus::mocks::Dependencies {
                 mut pool,
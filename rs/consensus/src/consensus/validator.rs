@@ -2,6 +2,10 @@
 
 use crate::{
     consensus::{
+        equivocation::{
+            find_block_maker_equivocation, find_equivocating_finalization_share,
+            find_equivocating_notarization_share, is_block_maker_equivocation,
+        },
         membership::{Membership, MembershipError},
         metrics::ValidatorMetrics,
         payload_builder::PayloadBuilder,
@@ -29,6 +33,7 @@ use ic_interfaces::{
 use ic_logger::{trace, warn, ReplicaLogger};
 use ic_replicated_state::ReplicatedState;
 use ic_types::{
+    consensus::equivocation::EquivocationEvidence,
     crypto::{threshold_sig::ni_dkg::NiDkgId, CryptoError},
     registry::RegistryClientError,
     replica_config::ReplicaConfig,
@@ -648,9 +653,11 @@ impl Validator {
             .finalization_share()
             .get_by_height_range(range);
 
-        finalization_shares
+        let mut change_set: ChangeSet = finalization_shares
             .filter_map(|share| self.validate_notary_issued(pool_reader, share))
-            .collect()
+            .collect();
+        self.report_notary_equivocations(pool_reader, &mut change_set);
+        change_set
     }
 
     /// Return a `ChangeSet` of `Notarization`s. See
@@ -694,9 +701,11 @@ impl Validator {
             .notarization_share()
             .get_by_height_range(range);
 
-        notarization_shares
+        let mut change_set: ChangeSet = notarization_shares
             .filter_map(|share| self.validate_notary_issued(pool_reader, share))
-            .collect()
+            .collect();
+        self.report_notary_equivocations(pool_reader, &mut change_set);
+        change_set
     }
 
     /// Validate a single `Signed`, `NotaryIssued` value. This involves checking
@@ -786,6 +795,7 @@ impl Validator {
                     } else if verification.is_ok() {
                         if get_notarized_parent(pool_reader, &proposal).is_ok() {
                             self.metrics.observe_block(pool_reader, &proposal);
+                            self.report_equivocation(pool_reader, &proposal, &mut change_set);
                             known_ranks.insert(proposal.height(), Some(proposal.rank()));
                             change_set.push(ChangeAction::MoveToValidated(proposal.into_message()));
                             change_set
@@ -840,6 +850,7 @@ impl Validator {
                 match self.check_block_validity(pool_reader, &proposal, dkg_pool) {
                     Ok(()) => {
                         self.metrics.observe_block(pool_reader, &proposal);
+                        self.report_equivocation(pool_reader, &proposal, &mut change_set);
                         known_ranks.insert(proposal.height(), Some(proposal.rank()));
                         change_set.push(ChangeAction::MoveToValidated(proposal.into_message()))
                     }
@@ -875,6 +886,94 @@ impl Validator {
         change_set
     }
 
+    /// Report an equivocation if the block maker of the given proposal, which
+    /// is about to be validated, already proposed a different block at the
+    /// same height and rank, either in the validated pool or in `change_set`.
+    /// Equivocating proposals are still validated, so that they can be
+    /// notarized and consensus makes progress.
+    fn report_equivocation(
+        &self,
+        pool_reader: &PoolReader<'_>,
+        proposal: &BlockProposal,
+        change_set: &mut ChangeSet,
+    ) {
+        let evidence = find_block_maker_equivocation(pool_reader, proposal).or_else(|| {
+            change_set.iter().find_map(|action| match action {
+                ChangeAction::MoveToValidated(ConsensusMessage::BlockProposal(other))
+                    if is_block_maker_equivocation(other, proposal) =>
+                {
+                    Some(EquivocationEvidence::BlockMaker {
+                        first: other.clone(),
+                        second: proposal.clone(),
+                    })
+                }
+                _ => None,
+            })
+        });
+        if let Some(evidence) = evidence {
+            self.record_equivocation("block_proposal", evidence, change_set);
+        }
+    }
+
+    /// Report the equivocations of notaries that signed both a finalization
+    /// share and a notarization share for different blocks at the same
+    /// height, among the shares that `change_set` moves to the validated pool.
+    /// Finalization and notarization shares are validated in separate rounds,
+    /// so the conflicting share is always in the validated pool already. Like
+    /// equivocating proposals, the shares are still validated.
+    fn report_notary_equivocations(
+        &self,
+        pool_reader: &PoolReader<'_>,
+        change_set: &mut ChangeSet,
+    ) {
+        let validated_shares: Vec<_> = change_set
+            .iter()
+            .filter_map(|action| match action {
+                ChangeAction::MoveToValidated(message) => Some(message.clone()),
+                _ => None,
+            })
+            .collect();
+        for message in validated_shares {
+            let (artifact, evidence) = match &message {
+                ConsensusMessage::FinalizationShare(share) => (
+                    "finalization_share",
+                    find_equivocating_notarization_share(pool_reader, share),
+                ),
+                ConsensusMessage::NotarizationShare(share) => (
+                    "notarization_share",
+                    find_equivocating_finalization_share(pool_reader, share),
+                ),
+                _ => continue,
+            };
+            if let Some(evidence) = evidence {
+                self.record_equivocation(artifact, evidence, change_set);
+            }
+        }
+    }
+
+    /// Count, log and record the given evidence of an equivocation, detected
+    /// when validating an artifact of the given type.
+    fn record_equivocation(
+        &self,
+        artifact: &str,
+        evidence: EquivocationEvidence,
+        change_set: &mut ChangeSet,
+    ) {
+        self.metrics
+            .equivocations
+            .with_label_values(&[artifact])
+            .inc();
+        warn!(
+            self.log,
+            "Node {} equivocated as {} at height {}: {:?}",
+            evidence.signer(),
+            evidence.kind(),
+            evidence.height(),
+            evidence
+        );
+        change_set.push(ChangeAction::RecordEquivocation(evidence));
+    }
+
     /// Check whether or not the provided `BlockProposal` can be moved into the
     /// validated pool. A `ValidatiorError::TransientError` value is returned
     /// when any of the following conditions are met:
@@ -2590,6 +2689,69 @@ pub mod test {
         })
     }
 
+    #[test]
+    fn test_notary_equivocation_is_recorded() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let (
+                payload_builder,
+                membership,
+                state_manager,
+                message_routing,
+                crypto,
+                _data_provider,
+                registry_client,
+                mut pool,
+                dkg_pool,
+                time_source,
+                replica_config,
+            ) = setup_dependencies(pool_config, &(0..4).map(node_test_id).collect::<Vec<_>>());
+
+            let block = pool.make_next_block();
+            pool.insert_validated(block.clone());
+            pool.notarize(&block);
+            let mut other_block = block.clone();
+            other_block.content.as_mut().context.time += Duration::from_millis(1);
+            other_block.update_content();
+            pool.insert_validated(other_block.clone());
+
+            // Node 1 notarized `other_block`, but finalizes `block`.
+            let notary = node_test_id(1);
+            let notarization_share = NotarizationShare::fake(other_block.as_ref(), notary);
+            let finalization_share = FinalizationShare::fake(block.as_ref(), notary);
+            pool.insert_validated(notarization_share.clone());
+            pool.insert_unvalidated(finalization_share.clone());
+
+            let validator = Validator::new(
+                replica_config,
+                membership,
+                registry_client,
+                crypto,
+                payload_builder,
+                state_manager,
+                message_routing,
+                dkg_pool,
+                no_op_logger(),
+                ValidatorMetrics::new(MetricsRegistry::new()),
+                Arc::clone(&time_source) as Arc<_>,
+            );
+
+            // The share is still validated, and the evidence recorded.
+            let changeset = validator.validate_finalization_shares(&PoolReader::new(&pool));
+            assert_eq!(
+                changeset,
+                vec![
+                    ChangeAction::MoveToValidated(finalization_share.clone().into_message()),
+                    ChangeAction::RecordEquivocation(EquivocationEvidence::Notary {
+                        finalization_share,
+                        notarization_share,
+                    }),
+                ]
+            );
+            pool.apply_changes(time_source.as_ref(), changeset);
+            assert_eq!(pool.equivocation_evidence().len(), 1);
+        })
+    }
+
     #[test]
     fn test_validate_catch_up_package() {
         ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
@@ -118,6 +118,26 @@ impl<'a> ConsensusRunner<'a> {
         fake_crypto: Arc<CryptoReturningOk>,
         deps: &'a ConsensusDependencies,
         pool_config: ArtifactPoolConfig,
+    ) {
+        self.add_instance_with_malicious_flags(
+            membership,
+            fake_crypto,
+            deps,
+            pool_config,
+            MaliciousFlags::default(),
+        )
+    }
+
+    /// Add a new consensus instance like `add_instance`, whose consensus
+    /// behaves according to the given `MaliciousFlags`. The flags only take
+    /// effect if the `malicious_code` feature is enabled.
+    pub fn add_instance_with_malicious_flags(
+        &mut self,
+        membership: Arc<Membership>,
+        fake_crypto: Arc<CryptoReturningOk>,
+        deps: &'a ConsensusDependencies,
+        pool_config: ArtifactPoolConfig,
+        malicious_flags: MaliciousFlags,
     ) {
         let node_id = deps.replica_config.node_id;
 
@@ -148,7 +168,7 @@ impl<'a> ConsensusRunner<'a> {
             deps.state_manager.clone(),
             Arc::clone(&self.time) as Arc<_>,
             Duration::from_secs(0),
-            MaliciousFlags::default(),
+            malicious_flags,
             deps.metrics_registry.clone(),
             replica_logger.clone(),
             None,
@@ -2,8 +2,9 @@ mod framework;
 use crate::framework::{
     ConsensusDependencies, ConsensusInstance, ConsensusRunner, ConsensusRunnerConfig,
 };
-use ic_consensus::consensus::Membership;
+use ic_consensus::consensus::{pool_reader::PoolReader, Membership};
 use ic_interfaces::{consensus_pool::ConsensusPool, registry::RegistryClient};
+use ic_protobuf::registry::subnet::v1::{AdaptiveBlockRateConfig, SubnetRecord};
 use ic_test_utilities::{
     consensus::make_catch_up_package_with_empty_transcript,
     crypto::CryptoReturningOk,
@@ -11,10 +12,14 @@ use ic_test_utilities::{
     types::ids::{node_test_id, subnet_test_id},
     FastForwardTimeSource,
 };
-use ic_types::{crypto::CryptoHash, replica_config::ReplicaConfig, Height, RegistryVersion};
+use ic_types::{
+    crypto::CryptoHash, malicious_flags::MaliciousFlags, replica_config::ReplicaConfig, Height,
+    NodeId, RegistryVersion, Time,
+};
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[test]
 fn multiple_nodes_are_live() -> Result<(), String> {
@@ -49,7 +54,87 @@ fn multiple_nodes_are_deterministic() {
     assert_eq!(run(), run());
 }
 
+#[test]
+fn multiple_nodes_with_adaptive_block_rate_are_live_and_deterministic() {
+    let config = || ConsensusRunnerConfig {
+        num_nodes: 4,
+        num_rounds: 20,
+        ..Default::default()
+    };
+    // One node proposes empty blocks, if built with the `malicious_code`
+    // feature.
+    let malicious_flags = |index| MaliciousFlags {
+        maliciously_propose_empty_blocks: index == 0,
+        ..MaliciousFlags::default()
+    };
+    let target_round_time = Duration::from_millis(100);
+    let max_initial_notary_delay = Duration::from_millis(2000);
+    let adaptive_run = || {
+        let subnet_record = |node_ids: &[NodeId]| {
+            SubnetRecordBuilder::from(node_ids)
+                .with_adaptive_block_rate_config(AdaptiveBlockRateConfig {
+                    window: 5,
+                    target_round_time_millis: target_round_time.as_millis() as u64,
+                    max_initial_notary_delay_millis: max_initial_notary_delay.as_millis() as u64,
+                    min_block_payload_size: 1024,
+                })
+                .build()
+        };
+        run_n_rounds_and_collect_hashes_with(config(), subnet_record, malicious_flags)
+    };
+    let (hashes, adaptive_block_times) = adaptive_run();
+    assert_eq!((hashes, adaptive_block_times.clone()), adaptive_run());
+
+    let (_, static_block_times) = run_n_rounds_and_collect_hashes_with(
+        config(),
+        |node_ids| SubnetRecordBuilder::from(node_ids).build(),
+        malicious_flags,
+    );
+
+    // With the static configuration, rounds take at least the initial notary
+    // delay of the subnet record, far longer than the target round time. The
+    // adaptive block rate therefore raises the notary delay up to
+    // `max_initial_notary_delay`, which lengthens the rounds.
+    let static_round_time = recent_round_time(&static_block_times);
+    let adaptive_round_time = recent_round_time(&adaptive_block_times);
+    assert!(static_round_time > target_round_time);
+    assert!(
+        adaptive_round_time > static_round_time,
+        "adaptive round time {:?} is not longer than the static round time {:?}",
+        adaptive_round_time,
+        static_round_time
+    );
+    assert!(adaptive_round_time >= max_initial_notary_delay);
+}
+
+// Returns the average round time over the last 10 of the given block times,
+// which are in ascending order of height.
+fn recent_round_time(block_times: &[Time]) -> Duration {
+    let rounds = 10;
+    assert!(block_times.len() > rounds, "not enough finalized blocks");
+    let recent = &block_times[block_times.len() - rounds - 1..];
+    (recent[rounds] - recent[0]) / rounds as u32
+}
+
 fn run_n_rounds_and_collect_hashes(config: ConsensusRunnerConfig) -> Rc<RefCell<Vec<CryptoHash>>> {
+    run_n_rounds_and_collect_hashes_with(
+        config,
+        |node_ids| SubnetRecordBuilder::from(node_ids).build(),
+        |_| MaliciousFlags::default(),
+    )
+    .0
+}
+
+/// Runs `config.num_rounds` rounds on a subnet with the record returned by
+/// `subnet_record`, where the node with index `i` behaves according to
+/// `malicious_flags(i)`, and returns the hashes of all notarizations together
+/// with the timestamps of the blocks on the finalized chain of the first node,
+/// in ascending order of height.
+fn run_n_rounds_and_collect_hashes_with(
+    config: ConsensusRunnerConfig,
+    subnet_record: impl Fn(&[NodeId]) -> SubnetRecord,
+    malicious_flags: impl Fn(usize) -> MaliciousFlags,
+) -> (Rc<RefCell<Vec<CryptoHash>>>, Vec<Time>) {
     let nodes = config.num_nodes;
     ic_test_utilities::artifact_pool_config::with_test_pool_configs(nodes, |pool_configs| {
         let rounds = config.num_rounds;
@@ -82,13 +167,8 @@ fn run_n_rounds_and_collect_hashes(config: ConsensusRunnerConfig) -> Rc<RefCell<
             .collect();
         let crypto = Arc::new(CryptoReturningOk::default());
         let initial_version = 1;
-        let (data_provider, registry_client) = setup_registry_non_final(
-            subnet_id,
-            vec![(
-                initial_version,
-                SubnetRecordBuilder::from(&node_ids).build(),
-            )],
-        );
+        let (data_provider, registry_client) =
+            setup_registry_non_final(subnet_id, vec![(initial_version, subnet_record(&node_ids))]);
         // This is required by the XNet payload builder.
         for node in node_ids.iter() {
             data_provider
@@ -116,21 +196,30 @@ fn run_n_rounds_and_collect_hashes(config: ConsensusRunnerConfig) -> Rc<RefCell<
 
         let mut framework = ConsensusRunner::new_with_config(config, time_source);
 
-        for (pool_config, deps) in pool_configs.iter().zip(inst_deps.iter()) {
+        for (index, (pool_config, deps)) in pool_configs.iter().zip(inst_deps.iter()).enumerate() {
             let membership = Membership::new(
                 deps.consensus_pool.read().unwrap().get_cache(),
                 Arc::clone(&registry_client) as Arc<dyn RegistryClient>,
                 subnet_id,
             );
             let membership = Arc::new(membership);
-            framework.add_instance(
+            framework.add_instance_with_malicious_flags(
                 membership.clone(),
                 crypto.clone(),
                 deps,
                 pool_config.clone(),
+                malicious_flags(index),
             );
         }
         assert!(framework.run_until(&reach_n_rounds));
-        hashes
+
+        let pool = inst_deps[0].consensus_pool.read().unwrap();
+        let pool_reader = PoolReader::new(&*pool);
+        let mut block_times: Vec<_> = pool_reader
+            .chain_iterator(pool_reader.get_finalized_tip())
+            .map(|block| block.context.time)
+            .collect();
+        block_times.reverse();
+        (hashes, block_times)
     })
 }
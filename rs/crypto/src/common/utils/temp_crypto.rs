@@ -11,8 +11,8 @@ use ic_crypto_internal_csp::secret_key_store::volatile_store::VolatileSecretKeyS
 use ic_crypto_internal_csp::{public_key_store, CryptoServiceProvider, Csp};
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
 use ic_crypto_tls_interfaces::{
-    AllowedClients, AuthenticatedPeer, TlsClientHandshakeError, TlsHandshake,
-    TlsServerHandshakeError, TlsStream,
+    AllowedClients, AuthenticatedPeer, TlsClientConfig, TlsClientHandshakeError, TlsHandshake,
+    TlsServerConfig, TlsServerHandshakeError, TlsStream,
 };
 use ic_interfaces::crypto::{
     BasicSigVerifier, BasicSigVerifierByPublicKey, CanisterSigVerifier, IDkgProtocol,
@@ -583,6 +583,24 @@ impl<C: CryptoServiceProvider + Send + Sync> TlsHandshake for TempCryptoComponen
             .perform_tls_client_handshake_with_rustls(tcp_stream, server, registry_version)
             .await
     }
+
+    fn quic_server_config(
+        &self,
+        allowed_clients: AllowedClients,
+        registry_version: RegistryVersion,
+    ) -> Result<TlsServerConfig, TlsServerHandshakeError> {
+        self.crypto_component
+            .quic_server_config(allowed_clients, registry_version)
+    }
+
+    fn quic_client_config(
+        &self,
+        server: NodeId,
+        registry_version: RegistryVersion,
+    ) -> Result<TlsClientConfig, TlsClientHandshakeError> {
+        self.crypto_component
+            .quic_client_config(server, registry_version)
+    }
 }
 
 impl<C: CryptoServiceProvider, T: Signable> BasicSigVerifier<T> for TempCryptoComponentGeneric<C> {
@@ -26,6 +26,7 @@ pub use sign::utils::{
     KeyBytesContentType,
 };
 pub use sign::{derive_tecdsa_public_key, get_tecdsa_master_public_key};
+pub use tls_stub::node_id_from_cert_subject_common_name;
 
 use crate::common::utils::{derive_node_id, TempCryptoComponent};
 use crate::sign::ThresholdSigDataStoreImpl;
@@ -46,7 +47,7 @@ use ic_logger::{new_logger, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
 use ic_types::consensus::{
-    Block, CatchUpContent, CatchUpContentProtobufBytes, FinalizationContent,
+    Block, CatchUpContent, CatchUpContentProtobufBytes, FinalizationContent, NotarizationContent,
 };
 use ic_types::crypto::{CryptoError, CryptoResult, KeyPurpose};
 use ic_types::messages::MessageId;
@@ -105,6 +106,7 @@ impl<T> CryptoComponentForNonReplicaProcess for T where
 /// do not require secret keys.
 pub trait CryptoComponentForVerificationOnly:
     MultiSigVerifier<FinalizationContent>
+    + MultiSigVerifier<NotarizationContent>
     + BasicSigVerifier<Block>
     + BasicSigVerifierByPublicKey<MessageId>
     + ThresholdSigVerifier<CatchUpContent>
@@ -119,6 +121,7 @@ pub trait CryptoComponentForVerificationOnly:
 // that fulfill the requirements.
 impl<T> CryptoComponentForVerificationOnly for T where
     T: MultiSigVerifier<FinalizationContent>
+        + MultiSigVerifier<NotarizationContent>
         + BasicSigVerifier<Block>
         + BasicSigVerifierByPublicKey<MessageId>
         + ThresholdSigVerifier<CatchUpContent>
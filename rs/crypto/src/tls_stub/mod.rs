@@ -2,8 +2,8 @@ use super::*;
 use async_trait::async_trait;
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
 use ic_crypto_tls_interfaces::{
-    AllowedClients, AuthenticatedPeer, MalformedPeerCertificateError, TlsClientHandshakeError,
-    TlsHandshake, TlsServerHandshakeError, TlsStream,
+    AllowedClients, AuthenticatedPeer, MalformedPeerCertificateError, TlsClientConfig,
+    TlsClientHandshakeError, TlsHandshake, TlsServerConfig, TlsServerHandshakeError, TlsStream,
 };
 use ic_logger::{debug, new_logger};
 use ic_types::registry::RegistryClientError;
@@ -198,9 +198,71 @@ where
         );
         result
     }
+
+    fn quic_server_config(
+        &self,
+        allowed_clients: AllowedClients,
+        registry_version: RegistryVersion,
+    ) -> Result<TlsServerConfig, TlsServerHandshakeError> {
+        let logger = new_logger!(&self.logger;
+            crypto.trait_name => "TlsHandshake",
+            crypto.method_name => "quic_server_config",
+            crypto.registry_version => registry_version.get(),
+            crypto.allowed_tls_clients => format!("{:?}", allowed_clients),
+        );
+        debug!(logger; crypto.description => "start",);
+        let result = rustls::server_handshake::server_config_with_client_auth(
+            &self.csp,
+            self.node_id,
+            &self.registry_client,
+            allowed_clients,
+            registry_version,
+        )
+        .map(TlsServerConfig::new_rustls);
+        debug!(logger;
+            crypto.description => "end",
+            crypto.is_ok => result.is_ok(),
+            crypto.error => log_err(result.as_ref().err()),
+        );
+        result
+    }
+
+    fn quic_client_config(
+        &self,
+        server: NodeId,
+        registry_version: RegistryVersion,
+    ) -> Result<TlsClientConfig, TlsClientHandshakeError> {
+        let logger = new_logger!(&self.logger;
+            crypto.trait_name => "TlsHandshake",
+            crypto.method_name => "quic_client_config",
+            crypto.registry_version => registry_version.get(),
+            crypto.tls_server => format!("{}", server),
+        );
+        debug!(logger; crypto.description => "start",);
+        let result = rustls::client_handshake::client_config(
+            &self.csp,
+            self.node_id,
+            &self.registry_client,
+            server,
+            registry_version,
+        )
+        .map(TlsClientConfig::new_rustls);
+        debug!(logger;
+            crypto.description => "end",
+            crypto.is_ok => result.is_ok(),
+            crypto.error => log_err(result.as_ref().err()),
+        );
+        result
+    }
 }
 
-fn node_id_from_cert_subject_common_name(
+/// Determines the node ID from the subject common name of a node's TLS
+/// certificate.
+///
+/// Note that this does not authenticate the node: callers must ensure that the
+/// certificate was presented in a successful TLS handshake that verified it
+/// against the registry.
+pub fn node_id_from_cert_subject_common_name(
     cert: &TlsPublicKeyCert,
 ) -> Result<NodeId, MalformedPeerCertificateError> {
     let common_name_entry = ensure_exactly_one_subject_common_name_entry(cert)?;
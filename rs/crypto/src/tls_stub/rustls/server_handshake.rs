@@ -29,17 +29,13 @@ pub async fn perform_tls_server_handshake<P: CspTlsHandshakeSignerProvider>(
     allowed_clients: AllowedClients,
     registry_version: RegistryVersion,
 ) -> Result<(TlsStream, AuthenticatedPeer), TlsServerHandshakeError> {
-    let self_tls_cert = tls_cert_from_registry(registry_client, self_node_id, registry_version)?;
-    let client_cert_verifier = NodeClientCertVerifier::new_with_mandatory_client_auth(
-        allowed_clients.nodes().clone(),
-        Arc::clone(registry_client),
-        registry_version,
-    );
-    let config = server_config_with_tls13_and_aes_ciphersuites_and_ed25519_signing_key(
-        Arc::new(client_cert_verifier),
-        self_tls_cert,
+    let config = server_config_with_client_auth(
         signer_provider,
-    );
+        self_node_id,
+        registry_client,
+        allowed_clients,
+        registry_version,
+    )?;
 
     let rustls_stream = accept_connection(tcp_stream, config).await?;
 
@@ -50,6 +46,30 @@ pub async fn perform_tls_server_handshake<P: CspTlsHandshakeSignerProvider>(
     Ok((tls_stream, AuthenticatedPeer::Node(authenticated_peer)))
 }
 
+/// Returns the server configuration used for handshakes with mandatory client
+/// authentication, where clients are authenticated against `allowed_clients`.
+pub fn server_config_with_client_auth<P: CspTlsHandshakeSignerProvider>(
+    signer_provider: &P,
+    self_node_id: NodeId,
+    registry_client: &Arc<dyn RegistryClient>,
+    allowed_clients: AllowedClients,
+    registry_version: RegistryVersion,
+) -> Result<ServerConfig, TlsServerHandshakeError> {
+    let self_tls_cert = tls_cert_from_registry(registry_client, self_node_id, registry_version)?;
+    let client_cert_verifier = NodeClientCertVerifier::new_with_mandatory_client_auth(
+        allowed_clients.nodes().clone(),
+        Arc::clone(registry_client),
+        registry_version,
+    );
+    Ok(
+        server_config_with_tls13_and_aes_ciphersuites_and_ed25519_signing_key(
+            Arc::new(client_cert_verifier),
+            self_tls_cert,
+            signer_provider,
+        ),
+    )
+}
+
 pub async fn perform_tls_server_handshake_without_client_auth<P: CspTlsHandshakeSignerProvider>(
     signer_provider: &P,
     self_node_id: NodeId,
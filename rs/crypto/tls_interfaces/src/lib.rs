@@ -317,6 +317,38 @@ impl TlsStream {
     }
 }
 
+/// A TLS server configuration for transports that perform the TLS handshake
+/// themselves, see `TlsHandshake::quic_server_config`.
+pub struct TlsServerConfig(tokio_rustls::rustls::ServerConfig);
+
+impl TlsServerConfig {
+    pub fn new_rustls(rustls_config: tokio_rustls::rustls::ServerConfig) -> Self {
+        Self(rustls_config)
+    }
+
+    /// Returns the underlying rustls configuration. Transport implementations
+    /// consuming it must use the same rustls version as this crate.
+    pub fn into_rustls(self) -> tokio_rustls::rustls::ServerConfig {
+        self.0
+    }
+}
+
+/// A TLS client configuration for transports that perform the TLS handshake
+/// themselves, see `TlsHandshake::quic_client_config`.
+pub struct TlsClientConfig(tokio_rustls::rustls::ClientConfig);
+
+impl TlsClientConfig {
+    pub fn new_rustls(rustls_config: tokio_rustls::rustls::ClientConfig) -> Self {
+        Self(rustls_config)
+    }
+
+    /// Returns the underlying rustls configuration. Transport implementations
+    /// consuming it must use the same rustls version as this crate.
+    pub fn into_rustls(self) -> tokio_rustls::rustls::ClientConfig {
+        self.0
+    }
+}
+
 impl AsyncRead for TlsStream {
     fn poll_read(
         mut self: Pin<&mut Self>,
@@ -628,6 +660,54 @@ pub trait TlsHandshake {
         server: NodeId,
         registry_version: RegistryVersion,
     ) -> Result<TlsStream, TlsClientHandshakeError>;
+
+    /// Returns a TLS server configuration for transports that perform the
+    /// TLS handshake themselves (e.g. QUIC, where TLS is part of the transport
+    /// protocol and cannot be layered on top of a TCP stream).
+    ///
+    /// The configuration is the same as the one used by
+    /// `perform_tls_server_handshake`, i.e. clients are authenticated against
+    /// `allowed_clients` as described there. Since the caller drives the
+    /// handshake, it is responsible for determining the authenticated peer
+    /// from the certificate that the client presented.
+    ///
+    /// Note that, unlike the TLS streams returned by the other methods, the
+    /// caller gets access to the TLS session (but not to the node's private
+    /// key, which stays in the secret key store).
+    ///
+    /// # Errors
+    /// * TlsServerHandshakeError::RegistryError if the registry cannot be
+    ///   accessed.
+    /// * TlsServerHandshakeError::CertificateNotInRegistry if the node's own
+    ///   certificate is not found in the registry.
+    /// * TlsServerHandshakeError::MalformedSelfCertificate if the node's own
+    ///   server certificate is malformed.
+    fn quic_server_config(
+        &self,
+        allowed_clients: AllowedClients,
+        registry_version: RegistryVersion,
+    ) -> Result<TlsServerConfig, TlsServerHandshakeError>;
+
+    /// Returns a TLS client configuration for transports that perform the
+    /// TLS handshake themselves (e.g. QUIC).
+    ///
+    /// The configuration is the same as the one used by
+    /// `perform_tls_client_handshake`, i.e. the handshake only succeeds if the
+    /// peer authenticates as `server`. Please refer to
+    /// `quic_server_config` regarding access to the TLS session.
+    ///
+    /// # Errors
+    /// * TlsClientHandshakeError::RegistryError if the registry cannot be
+    ///   accessed.
+    /// * TlsClientHandshakeError::CertificateNotInRegistry if the node's own
+    ///   certificate is not found in the registry.
+    /// * TlsClientHandshakeError::MalformedSelfCertificate if the node's own
+    ///   client certificate is malformed.
+    fn quic_client_config(
+        &self,
+        server: NodeId,
+        registry_version: RegistryVersion,
+    ) -> Result<TlsClientConfig, TlsClientHandshakeError>;
 }
 
 #[derive(Clone, Debug)]
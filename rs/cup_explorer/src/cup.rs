@@ -0,0 +1,333 @@
+//! Loading, verifying and comparing catch-up packages.
+//!
+//! CUPs can be fetched from the nodes of a subnet, read from individual files,
+//! or read from a consensus backup directory. The latter contains a directory
+//! per group of heights, named by the lowest height of the group, which in turn
+//! contains a directory per height, e.g. `<group>/<height>/catch_up_package.bin`.
+use ic_interfaces::{crypto::ThresholdSigVerifierByPublicKey, registry::RegistryClient};
+use ic_protobuf::types::v1 as pb;
+use ic_types::{
+    consensus::{CatchUpContentProtobufBytes, CatchUpPackage, HasHeight},
+    crypto::{CombinedThresholdSig, CombinedThresholdSigOf},
+    Height, RegistryVersion, SubnetId,
+};
+use prost::Message;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const CUP_FILE_NAME: &str = "catch_up_package.bin";
+
+/// A decoded CUP together with the protobuf it was decoded from, which holds
+/// the exact bytes the signature was computed on.
+pub struct LoadedCup {
+    /// Where the CUP was loaded from, e.g. a node id or a file path.
+    pub source: String,
+    pub protobuf: pb::CatchUpPackage,
+    pub cup: CatchUpPackage,
+}
+
+impl LoadedCup {
+    pub fn new(source: String, protobuf: pb::CatchUpPackage) -> Result<Self, String> {
+        let cup = CatchUpPackage::try_from(&protobuf)
+            .map_err(|e| format!("failed to deserialize cup from {}: {}", source, e))?;
+        Ok(Self {
+            source,
+            protobuf,
+            cup,
+        })
+    }
+
+    /// The registry version the CUP pins, i.e. the registry version of the
+    /// block it contains.
+    pub fn registry_version(&self) -> RegistryVersion {
+        self.cup.content.block.get_value().context.registry_version
+    }
+
+    pub fn state_hash(&self) -> String {
+        hex::encode(&self.cup.content.state_hash.get_ref().0)
+    }
+}
+
+/// Reads a protobuf-encoded CUP from the given file.
+pub fn read_cup_file(path: &Path) -> Result<LoadedCup, String> {
+    let bytes = fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let protobuf = pb::CatchUpPackage::decode(bytes.as_slice())
+        .map_err(|e| format!("failed to decode {}: {}", path.display(), e))?;
+    LoadedCup::new(path.display().to_string(), protobuf)
+}
+
+/// Reads all CUPs at or above `from_height` from the given backup directory,
+/// in ascending order of height.
+pub fn read_backup_cups(backup_dir: &Path, from_height: Height) -> Result<Vec<LoadedCup>, String> {
+    let mut paths = BTreeMap::new();
+    for group_dir in read_height_dirs(backup_dir)?.into_values() {
+        for (height, height_dir) in read_height_dirs(&group_dir)? {
+            let path = height_dir.join(CUP_FILE_NAME);
+            if height >= from_height && path.exists() {
+                paths.insert(height, path);
+            }
+        }
+    }
+    paths.values().map(|path| read_cup_file(path)).collect()
+}
+
+// Returns the subdirectories of `dir` whose names are heights, indexed by
+// height. Other entries are ignored.
+fn read_height_dirs(dir: &Path) -> Result<BTreeMap<Height, PathBuf>, String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("failed to read {}: {}", dir.display(), e))?;
+    let mut dirs = BTreeMap::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("failed to read {}: {}", dir.display(), e))?
+            .path();
+        let height = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<u64>().ok());
+        if let (Some(height), true) = (height, path.is_dir()) {
+            dirs.insert(Height::from(height), path);
+        }
+    }
+    Ok(dirs)
+}
+
+/// Verifies the threshold signatures of the given CUPs against the subnet's
+/// public key in the registry, returning the result for each CUP in order.
+pub fn verify_cups(
+    registry: Arc<dyn RegistryClient>,
+    subnet_id: SubnetId,
+    cups: &[LoadedCup],
+) -> Vec<Result<(), String>> {
+    let latest_version = registry.get_latest_version();
+    let crypto = ic_crypto::CryptoComponentFatClient::new_for_verification_only(registry);
+    cups.iter()
+        .map(|loaded| {
+            // The genesis CUP and CUPs created from the registry during a
+            // subnet recovery are not signed by the subnet.
+            if loaded.protobuf.signature.is_empty() {
+                return Err("the CUP is not signed".to_string());
+            }
+            let registry_version = loaded.registry_version();
+            if registry_version > latest_version {
+                return Err(format!(
+                    "the local store only holds registry versions up to {}",
+                    latest_version
+                ));
+            }
+            crypto
+                .verify_combined_threshold_sig_by_public_key(
+                    &CombinedThresholdSigOf::new(CombinedThresholdSig(
+                        loaded.protobuf.signature.clone(),
+                    )),
+                    &CatchUpContentProtobufBytes(loaded.protobuf.content.clone()),
+                    subnet_id,
+                    registry_version,
+                )
+                .map_err(|e| format!("invalid signature: {}", e))
+        })
+        .collect()
+}
+
+/// Prints the contents of the given CUP, including its DKG summary.
+pub fn print_cup(loaded: &LoadedCup) {
+    let cup = &loaded.cup;
+    let block = cup.content.block.get_value();
+    let summary = &block.payload.as_ref().as_summary().dkg;
+    println!("{:>24}: {}", "SOURCE", loaded.source);
+    println!("{:>24}: {}", "HEIGHT", cup.height());
+    println!("{:>24}: {}", "STATE HASH", loaded.state_hash());
+    println!("{:>24}: {}", "REPLICA VERSION", cup.content.version());
+    println!("{:>24}: {}", "REGISTRY VERSION", loaded.registry_version());
+    println!("{:>24}: {}", "SIGNER", cup.signature.signer);
+    println!(
+        "{:>24}: {}",
+        "DKG REGISTRY VERSION", summary.registry_version
+    );
+    println!(
+        "{:>24}: {} (length {})",
+        "DKG INTERVAL START", summary.height, summary.interval_length
+    );
+    println!(
+        "{:>24}: {} (length {})",
+        "NEXT DKG INTERVAL START",
+        summary.get_next_start_height(),
+        summary.next_interval_length
+    );
+    for (label, transcripts) in &[
+        ("CURRENT TRANSCRIPT", summary.current_transcripts()),
+        ("NEXT TRANSCRIPT", summary.next_transcripts()),
+    ] {
+        for (tag, transcript) in transcripts.iter() {
+            println!(
+                "{:>24}: {:?} (threshold {}, {} receivers, registry version {})",
+                label,
+                tag,
+                transcript.threshold.get().get(),
+                transcript.committee.get().len(),
+                transcript.registry_version
+            );
+        }
+    }
+    println!("{:>24}: {}", "DKG CONFIGS", summary.configs.len());
+}
+
+/// Compares the CUPs loaded from different sources and returns a description
+/// of each disagreement. CUPs at the same height must have the same content;
+/// sources at a lower height than the highest one are reported as behind.
+pub fn compare_cups(cups: &[LoadedCup]) -> Vec<String> {
+    let mut by_height: BTreeMap<Height, Vec<&LoadedCup>> = BTreeMap::new();
+    for loaded in cups {
+        by_height
+            .entry(loaded.cup.height())
+            .or_default()
+            .push(loaded);
+    }
+    let max_height = match by_height.keys().last() {
+        Some(height) => *height,
+        None => return Vec::new(),
+    };
+    let mut problems = Vec::new();
+    for (height, cups) in &by_height {
+        let first = cups[0];
+        for other in &cups[1..] {
+            if other.state_hash() != first.state_hash() {
+                problems.push(format!(
+                    "state hash mismatch at height {}: {} reports {}, {} reports {}",
+                    height,
+                    first.source,
+                    first.state_hash(),
+                    other.source,
+                    other.state_hash()
+                ));
+            } else if other.protobuf.content != first.protobuf.content {
+                problems.push(format!(
+                    "content mismatch at height {} between {} and {}",
+                    height, first.source, other.source
+                ));
+            }
+        }
+        if *height < max_height {
+            for loaded in cups {
+                problems.push(format!(
+                    "{} holds a CUP at height {}, below the highest height {}",
+                    loaded.source, height, max_height
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Checks that the given CUPs, in ascending order of height, form a chain
+/// without gaps: each CUP must be at the start of the DKG interval following
+/// that of the previous CUP. Returns a description of each gap.
+pub fn check_chain(cups: &[LoadedCup]) -> Vec<String> {
+    cups.windows(2)
+        .filter_map(|pair| {
+            let expected = pair[0]
+                .cup
+                .content
+                .block
+                .get_value()
+                .payload
+                .as_ref()
+                .as_summary()
+                .dkg
+                .get_next_start_height();
+            let height = pair[1].cup.height();
+            (height != expected).then(|| {
+                format!(
+                    "the CUP following height {} is at height {}, expected {}",
+                    pair[0].cup.height(),
+                    height,
+                    expected
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::consensus::{fake::*, make_genesis};
+    use ic_types::{
+        consensus::dkg::Summary,
+        crypto::{CryptoHash, CryptoHashOf},
+    };
+
+    // The length of the DKG intervals of the CUPs made by `cup`, so that
+    // consecutive CUPs are 10 heights apart.
+    const INTERVAL_LENGTH: u64 = 9;
+
+    fn cup(source: &str, height: u64, state_hash: u8) -> LoadedCup {
+        let mut summary = Summary::fake();
+        summary.height = Height::from(height);
+        summary.interval_length = Height::from(INTERVAL_LENGTH);
+        let mut cup = make_genesis(summary);
+        cup.content.state_hash = CryptoHashOf::from(CryptoHash(vec![state_hash]));
+        LoadedCup::new(source.to_string(), pb::CatchUpPackage::from(&cup)).unwrap()
+    }
+
+    fn heights(cups: &[LoadedCup]) -> Vec<u64> {
+        cups.iter()
+            .map(|loaded| loaded.cup.height().get())
+            .collect()
+    }
+
+    #[test]
+    fn test_compare_cups() {
+        assert!(compare_cups(&[]).is_empty());
+        assert!(compare_cups(&[cup("a", 10, 1), cup("b", 10, 1)]).is_empty());
+
+        let problems = compare_cups(&[cup("a", 10, 1), cup("b", 10, 2), cup("c", 0, 1)]);
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems
+            .contains(&"c holds a CUP at height 0, below the highest height 10".to_string()));
+        assert!(problems
+            .contains(&"state hash mismatch at height 10: a reports 01, b reports 02".to_string()));
+    }
+
+    #[test]
+    fn test_check_chain() {
+        assert!(check_chain(&[cup("a", 0, 1), cup("b", 10, 1), cup("c", 20, 1)]).is_empty());
+        assert_eq!(
+            check_chain(&[cup("a", 0, 1), cup("b", 20, 1)]),
+            vec!["the CUP following height 0 is at height 20, expected 10".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_read_backup_cups() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = |group: u64, loaded: &LoadedCup| {
+            let dir = tmp
+                .path()
+                .join(group.to_string())
+                .join(loaded.cup.height().to_string());
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(CUP_FILE_NAME), loaded.protobuf.encode_to_vec()).unwrap();
+        };
+        store(0, &cup("a", 10, 1));
+        store(20, &cup("a", 30, 1));
+        store(20, &cup("a", 20, 1));
+        // Heights without a CUP and entries that are no heights are ignored.
+        fs::create_dir_all(tmp.path().join("20").join("21")).unwrap();
+        fs::create_dir_all(tmp.path().join("tmp")).unwrap();
+        fs::write(tmp.path().join("20").join("notes"), "").unwrap();
+
+        let cups = read_backup_cups(tmp.path(), Height::from(0)).unwrap();
+        assert_eq!(heights(&cups), vec![10, 20, 30]);
+        assert!(check_chain(&cups).is_empty());
+        assert_eq!(cups[0].cup, cup("a", 10, 1).cup);
+
+        let cups = read_backup_cups(tmp.path(), Height::from(15)).unwrap();
+        assert_eq!(heights(&cups), vec![20, 30]);
+
+        assert!(read_backup_cups(&tmp.path().join("missing"), Height::from(0)).is_err());
+    }
+}
@@ -1,16 +1,27 @@
+//! Explores the catch-up packages (CUPs) of a subnet.
+//!
+//! CUPs can be fetched from the nodes of a subnet, or loaded from files or a
+//! consensus backup directory. Given a registry local store, the threshold
+//! signature of each CUP is verified offline. CUPs loaded from different
+//! sources are compared, so that the tool can confirm that all nodes hold the
+//! same CUP before a recovery.
+mod cup;
+
+use clap::Clap;
+use cup::LoadedCup;
 use ic_canister_client::{Agent, Sender};
-use ic_protobuf::{
-    registry::{node::v1::connection_endpoint, node::v1::NodeRecord, subnet::v1::SubnetRecord},
-    types::v1::CatchUpContent,
+use ic_protobuf::registry::{
+    node::v1::connection_endpoint, node::v1::NodeRecord, subnet::v1::SubnetRecord,
 };
-use ic_registry_common::registry::RegistryCanister;
+use ic_registry_client::client::RegistryClientImpl;
+use ic_registry_common::{local_store::LocalStoreImpl, registry::RegistryCanister};
 use ic_registry_keys::{make_node_record_key, make_subnet_record_key};
-use ic_types::{NodeId, PrincipalId, SubnetId};
+use ic_types::{consensus::HasHeight, Height, NodeId, PrincipalId, SubnetId};
 use prost::Message;
 use reqwest::Url;
 use std::convert::TryFrom;
 use std::net::SocketAddr;
-use std::str::FromStr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::task;
 
@@ -77,39 +88,20 @@ fn http_url(n: &NodeRecord) -> Url {
     .unwrap()
 }
 
-/// Fetches the contents of a CatchUp package, if it's present.
-async fn get_catchup_content(agent: &Agent) -> Result<Option<CatchUpContent>, String> {
+/// Fetches the CatchUp package of a node, if it's present.
+async fn get_catchup_package(node_id: NodeId, agent: &Agent) -> Result<Option<LoadedCup>, String> {
     let maybe_cup = agent
         .query_cup_endpoint(None)
         .await
         .map_err(|e| format!("failed to get catch up package: {}", e))?;
-    match maybe_cup {
-        Some(cup) => {
-            // TODO(roman): verify signatures?
-            let content = CatchUpContent::decode(&cup.content[..])
-                .map_err(|e| format!("failed to deserialize cup: {}", e))?;
-            Ok(Some(content))
-        }
-        None => Ok(None),
-    }
+    maybe_cup
+        .map(|cup| LoadedCup::new(node_id.to_string(), cup))
+        .transpose()
 }
 
-#[tokio::main]
-async fn main() {
-    let args: Vec<_> = std::env::args().collect();
-    if args.len() != 3 {
-        eprintln!("Usage: {} [REGISTRY_URL] [SUBNET_ID]", args[0]);
-        std::process::exit(1);
-    }
-
-    let registry_url = Url::parse(&args[1][..])
-        .unwrap_or_else(|e| panic!("failed to parse registry url {}: {}", args[1], e));
-
-    let subnet_id = SubnetId::from(
-        PrincipalId::from_str(&args[2][..])
-            .unwrap_or_else(|e| panic!("failed to parse subnet id {}: {}", args[2], e)),
-    );
-
+/// Fetches the latest CUP from each node of the subnet. Nodes that fail to
+/// respond or don't have a CUP yet are reported and skipped.
+async fn fetch_cups(registry_url: Url, subnet_id: SubnetId) -> Vec<LoadedCup> {
     let registry_canister = Arc::new(RegistryCanister::new(vec![registry_url]));
 
     println!("Fetching the list of nodes on subnet {}...", subnet_id);
@@ -120,45 +112,178 @@ async fn main() {
         println!("  {:2}. {} ({})", i + 1, id, http_url(record));
     }
 
-    println!("\nDetecting the latest CUP...");
+    println!("\nFetching the CUPs...");
 
-    let tasks = node_records
+    let tasks: Vec<_> = node_records
         .into_iter()
         .map(|(node_id, node)| (node_id, Agent::new(http_url(&node), Sender::Anonymous)))
         .map(|(node_id, agent)| {
-            task::spawn(async move { (node_id, get_catchup_content(&agent).await) })
-        });
-
-    let mut latest_height = 0;
-    let mut latest = None;
+            task::spawn(async move { (node_id, get_catchup_package(node_id, &agent).await) })
+        })
+        .collect();
 
+    let mut cups = Vec::new();
     for t in tasks {
-        let (node_id, content) = t.await.unwrap();
-        match content {
+        let (node_id, cup) = t.await.unwrap();
+        match cup {
             Err(err) => {
                 println!(" ✘ [{}]: {}", node_id, err);
             }
             Ok(None) => {
                 println!(" ? [{}]: no cup yet", node_id);
             }
-            Ok(Some(content)) => {
-                let h = content.block.unwrap().height;
-                let s = hex::encode(&content.state_hash[..]);
-
-                println!(" ✔ [{}]: height = {}, state_hash: {}", node_id, h, s);
-                if h > latest_height {
-                    latest_height = h;
-                    latest = Some((node_id, s));
-                }
+            Ok(Some(cup)) => {
+                println!(
+                    " ✔ [{}]: height = {}, state_hash: {}",
+                    node_id,
+                    cup.cup.height(),
+                    cup.state_hash()
+                );
+                cups.push(cup);
             }
         }
     }
+    cups
+}
+
+/// Explores the CUPs of a subnet.
+#[derive(Clap)]
+#[clap(version = "1.0")]
+struct Opts {
+    /// The id of the subnet the CUPs belong to.
+    #[clap(long)]
+    subnet_id: PrincipalId,
+
+    /// The path of a registry local store. If present, the threshold
+    /// signatures of the CUPs are verified against the registry versions
+    /// found in it.
+    #[clap(long)]
+    local_store: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    subcmd: SubCommand,
+}
+
+#[derive(Clap)]
+enum SubCommand {
+    /// Fetches the latest CUP from each node of the subnet.
+    Fetch(FetchCmd),
+    /// Loads protobuf-encoded CUPs from files.
+    Files(FilesCmd),
+    /// Loads the CUPs from a consensus backup directory and checks that they
+    /// form a chain.
+    Backup(BackupCmd),
+}
+
+#[derive(Clap)]
+struct FetchCmd {
+    /// The URL of an NNS entry point.
+    registry_url: Url,
+}
+
+#[derive(Clap)]
+struct FilesCmd {
+    /// The files to load, e.g. copies of `cups/cup.types.v1.CatchUpPackage.pb`
+    /// taken from different nodes.
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Clap)]
+struct BackupCmd {
+    /// The backup directory of a replica version, containing one directory per
+    /// group of heights.
+    backup_dir: PathBuf,
+
+    /// Ignore the CUPs below this height.
+    #[clap(long, default_value = "0")]
+    from_height: u64,
+}
+
+#[tokio::main]
+async fn main() {
+    let opts = Opts::parse();
+    let subnet_id = SubnetId::from(opts.subnet_id);
+    // CUPs from a backup are at different heights by design, so they are
+    // checked for gaps instead of being compared.
+    let compare = !matches!(opts.subcmd, SubCommand::Backup(_));
+
+    let (cups, chain_problems) = match opts.subcmd {
+        SubCommand::Fetch(cmd) => (fetch_cups(cmd.registry_url, subnet_id).await, Vec::new()),
+        SubCommand::Files(cmd) => {
+            let cups = cmd
+                .files
+                .iter()
+                .map(|path| cup::read_cup_file(path))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap_or_else(|e| panic!("{}", e));
+            (cups, Vec::new())
+        }
+        SubCommand::Backup(cmd) => {
+            let cups = cup::read_backup_cups(&cmd.backup_dir, Height::from(cmd.from_height))
+                .unwrap_or_else(|e| panic!("{}", e));
+            let chain_problems = cup::check_chain(&cups);
+            (cups, chain_problems)
+        }
+    };
+
+    if cups.is_empty() {
+        println!("No CUPs found");
+        std::process::exit(1);
+    }
+
+    let verifications = opts.local_store.map(|path| {
+        let registry = Arc::new(RegistryClientImpl::new(
+            Arc::new(LocalStoreImpl::new(path)),
+            None,
+        ));
+        registry
+            .poll_once()
+            .expect("failed to read the registry local store");
+        cup::verify_cups(registry, subnet_id, &cups)
+    });
 
-    if let Some((node, hash)) = latest {
+    let mut problems = Vec::new();
+    for (i, loaded) in cups.iter().enumerate() {
         println!();
-        println!("Latest state:");
-        println!("{:>10}: {}", "HEIGHT", latest_height);
-        println!("{:>10}: {}", "HASH", hash);
-        println!("{:>10}: {}", "NODE", node);
+        cup::print_cup(loaded);
+        if let Some(verifications) = &verifications {
+            match &verifications[i] {
+                Ok(()) => println!("{:>24}: ✔ valid", "SIGNATURE"),
+                Err(err) => {
+                    println!("{:>24}: ✘ {}", "SIGNATURE", err);
+                    problems.push(format!(
+                        "failed to verify the CUP from {}: {}",
+                        loaded.source, err
+                    ));
+                }
+            }
+        }
+    }
+
+    // Scripts rely on this summary of the latest CUP, e.g. to compute the
+    // recovery height and state hash of a subnet.
+    let latest = cups
+        .iter()
+        .max_by_key(|loaded| loaded.cup.height())
+        .expect("there is at least one CUP");
+    println!("\nLatest state:");
+    println!("{:>10}: {}", "HEIGHT", latest.cup.height());
+    println!("{:>10}: {}", "HASH", latest.state_hash());
+    println!("{:>10}: {}", "SOURCE", latest.source);
+
+    problems.extend(chain_problems);
+    if compare {
+        problems.extend(cup::compare_cups(&cups));
+    }
+
+    println!();
+    if problems.is_empty() {
+        println!("All {} CUP(s) are consistent", cups.len());
+    } else {
+        for problem in &problems {
+            println!(" ✘ {}", problem);
+        }
+        std::process::exit(1);
     }
 }
@@ -35,4 +35,36 @@ struct Dashboard<'a> {{
         .as_bytes(),
     )
     .unwrap();
+
+    println!("cargo:rerun-if-changed=templates/consensus_pool.html");
+    let mut f =
+        File::create(PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("consensus_pool.rs"))
+            .unwrap();
+    f.write_all(
+        format!(
+            r#"
+#[derive(Template)]
+#[template(escape = "html", source = {:?}, ext = "html")]
+struct ConsensusPoolPage<'a> {{
+    finalized_height: Height,
+    finalized_hash: String,
+    notarized_height: Height,
+    random_beacon_height: Height,
+    catch_up_height: Height,
+    cup_heights: Vec<Height>,
+    dkg_interval_start: Height,
+    next_dkg_interval_start: Height,
+    artifact_types: &'a [&'static str],
+    validated: &'a [PoolRow],
+    unvalidated: &'a [PoolRow],
+    advert_types: Vec<ic_types::artifact::ArtifactTag>,
+    peers: &'a [PeerRow],
+    equivocations: &'a [EquivocationRow],
+}}
+    "#,
+            std::fs::read_to_string("templates/consensus_pool.html").unwrap()
+        )
+        .as_bytes(),
+    )
+    .unwrap();
 }
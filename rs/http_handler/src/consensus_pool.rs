@@ -0,0 +1,436 @@
+//! Module that serves the `/_/consensus` page, which shows the live contents
+//! of the consensus pool, the artifacts exchanged with each peer, and the
+//! evidence of equivocation recorded by consensus. It is meant for debugging
+//! stalls, and only served on the admin listener, which is bound to the
+//! local host.
+//!
+//! The admin listener also serves the `/_/equivocation_report`, the recorded
+//! evidence as a protobuf `EquivocationReport` naming the node operators of
+//! the equivocating nodes in the registry.
+
+use crate::common::{make_response, protobuf_response, CONTENT_TYPE_HTML};
+use askama::Template;
+use hyper::{Body, Response, StatusCode};
+use ic_consensus::consensus::{equivocation::make_equivocation_report, pool_reader::PoolReader};
+use ic_interfaces::{
+    consensus_pool::{ConsensusPool, ConsensusPoolCache, HeightRange},
+    p2p::PeerArtifactCountsReader,
+    registry::RegistryClient,
+};
+use ic_types::{
+    canonical_error::internal_error,
+    consensus::{equivocation::EquivocationEvidence, HasHeight},
+    Height, SubnetId,
+};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use tower::{BoxError, Service};
+
+// See build.rs
+include!(concat!(env!("OUT_DIR"), "/consensus_pool.rs"));
+
+/// The maximum number of heights shown per pool section, counting down from
+/// the highest height in the section.
+const MAX_HEIGHTS_PER_SECTION: u64 = 100;
+
+/// The artifact types in the order of the columns of a `PoolRow`.
+const ARTIFACT_TYPES: [&str; 11] = [
+    "BlockProposal",
+    "Notarization",
+    "NotarizationShare",
+    "Finalization",
+    "FinalizationShare",
+    "RandomBeacon",
+    "RandomBeaconShare",
+    "RandomTape",
+    "RandomTapeShare",
+    "CatchUpPackage",
+    "CatchUpPackageShare",
+];
+
+/// The number of artifacts of each type at a single height of a pool section.
+struct PoolRow {
+    height: Height,
+    counts: Vec<usize>,
+}
+
+/// The artifacts tracked for a single peer.
+struct PeerRow {
+    node_id: String,
+    adverts: Vec<usize>,
+    requested_chunks: usize,
+}
+
+/// A piece of evidence of equivocation: the node, the role it equivocated in,
+/// and the hashes of the two blocks it signed conflicting artifacts for. The
+/// rank is only shown for block makers.
+struct EquivocationRow {
+    signer: String,
+    kind: &'static str,
+    height: Height,
+    rank: String,
+    first_hash: String,
+    second_hash: String,
+}
+
+impl From<&EquivocationEvidence> for EquivocationRow {
+    fn from(evidence: &EquivocationEvidence) -> Self {
+        let (first_hash, second_hash) = match evidence {
+            EquivocationEvidence::BlockMaker { first, second } => (
+                first.content.get_hash().get_ref(),
+                second.content.get_hash().get_ref(),
+            ),
+            EquivocationEvidence::Notary {
+                finalization_share,
+                notarization_share,
+            } => (
+                finalization_share.content.block.get_ref(),
+                notarization_share.content.block.get_ref(),
+            ),
+        };
+        Self {
+            signer: evidence.signer().to_string(),
+            kind: evidence.kind().as_str(),
+            height: evidence.height(),
+            rank: evidence
+                .rank()
+                .map(|rank| rank.0.to_string())
+                .unwrap_or_default(),
+            first_hash: hex::encode(&first_hash.0),
+            second_hash: hex::encode(&second_hash.0),
+        }
+    }
+}
+
+/// Counts the artifacts of one type in the given section of `$pool` between
+/// `$min` and `$max`, holding the lock for a single range scan.
+macro_rules! count_by_height {
+    ($pool:expr, $section:ident, $artifact:ident, $min:expr, $max:expr) => {{
+        let pool = $pool.read().unwrap();
+        let mut counts = BTreeMap::new();
+        for artifact in pool
+            .$section()
+            .$artifact()
+            .get_by_height_range(HeightRange::new($min, $max))
+        {
+            *counts.entry(artifact.height()).or_insert(0) += 1;
+        }
+        counts
+    }};
+}
+
+/// Returns the `PoolRow`s of the given section of `$pool`. The lock is taken
+/// once to find the heights to show, and then once per artifact type to count
+/// the artifacts of that type in a single range scan.
+macro_rules! pool_rows {
+    ($pool:expr, $section:ident) => {{
+        let range = {
+            let pool = $pool.read().unwrap();
+            let section = pool.$section();
+            shown_heights(&[
+                section.block_proposal().height_range(),
+                section.notarization().height_range(),
+                section.notarization_share().height_range(),
+                section.finalization().height_range(),
+                section.finalization_share().height_range(),
+                section.random_beacon().height_range(),
+                section.random_beacon_share().height_range(),
+                section.random_tape().height_range(),
+                section.random_tape_share().height_range(),
+                section.catch_up_package().height_range(),
+                section.catch_up_package_share().height_range(),
+            ])
+        };
+        match range {
+            Some((min, max)) => make_rows(
+                min,
+                max,
+                &[
+                    count_by_height!($pool, $section, block_proposal, min, max),
+                    count_by_height!($pool, $section, notarization, min, max),
+                    count_by_height!($pool, $section, notarization_share, min, max),
+                    count_by_height!($pool, $section, finalization, min, max),
+                    count_by_height!($pool, $section, finalization_share, min, max),
+                    count_by_height!($pool, $section, random_beacon, min, max),
+                    count_by_height!($pool, $section, random_beacon_share, min, max),
+                    count_by_height!($pool, $section, random_tape, min, max),
+                    count_by_height!($pool, $section, random_tape_share, min, max),
+                    count_by_height!($pool, $section, catch_up_package, min, max),
+                    count_by_height!($pool, $section, catch_up_package_share, min, max),
+                ],
+            ),
+            None => Vec::new(),
+        }
+    }};
+}
+
+pub(crate) struct ConsensusPoolService {
+    consensus_pool_cache: Arc<dyn ConsensusPoolCache>,
+    consensus_pool: Arc<RwLock<dyn ConsensusPool + Send + Sync>>,
+    peer_artifact_counts_reader: Arc<dyn PeerArtifactCountsReader>,
+}
+
+impl ConsensusPoolService {
+    pub(crate) fn new(
+        consensus_pool_cache: Arc<dyn ConsensusPoolCache>,
+        consensus_pool: Arc<RwLock<dyn ConsensusPool + Send + Sync>>,
+        peer_artifact_counts_reader: Arc<dyn PeerArtifactCountsReader>,
+    ) -> Self {
+        Self {
+            consensus_pool_cache,
+            consensus_pool,
+            peer_artifact_counts_reader,
+        }
+    }
+
+    fn render(&self) -> askama::Result<String> {
+        let finalized_tip = self.consensus_pool_cache.finalized_block();
+        let summary = self.consensus_pool_cache.summary_block();
+        let dkg_summary = &summary.payload.as_ref().as_summary().dkg;
+
+        // The pool lock is only held for short scans that copy out what is
+        // shown, and never while the page is rendered, so that consensus is
+        // not blocked by a slow or repeated request.
+        let (notarized_height, random_beacon_height, cup_heights, finalized_hash) = {
+            let pool = self.consensus_pool.read().unwrap();
+            let reader = PoolReader::new(&*pool);
+            let finalized_hash = pool
+                .validated()
+                .finalization()
+                .get_by_height(finalized_tip.height)
+                .next()
+                .map(|finalization| hex::encode(&finalization.content.block.get_ref().0))
+                .unwrap_or_default();
+            let mut cup_heights: Vec<Height> = pool
+                .validated()
+                .catch_up_package()
+                .get_all()
+                .map(|cup| cup.height())
+                .collect();
+            cup_heights.sort();
+            (
+                reader.get_notarized_height(),
+                reader.get_random_beacon_height(),
+                cup_heights,
+                finalized_hash,
+            )
+        };
+        let equivocations: Vec<EquivocationRow> = self
+            .consensus_pool
+            .read()
+            .unwrap()
+            .equivocation_evidence()
+            .iter()
+            .map(EquivocationRow::from)
+            .collect();
+        let validated = pool_rows!(self.consensus_pool, validated);
+        let unvalidated = pool_rows!(self.consensus_pool, unvalidated);
+
+        let peer_artifact_counts = self.peer_artifact_counts_reader.get_peer_artifact_counts();
+        let advert_types = peer_artifact_counts
+            .values()
+            .next()
+            .map(|counts| counts.adverts.iter().map(|(tag, _)| *tag).collect())
+            .unwrap_or_default();
+        let peers: Vec<PeerRow> = peer_artifact_counts
+            .into_iter()
+            .map(|(node_id, counts)| PeerRow {
+                node_id: node_id.to_string(),
+                adverts: counts.adverts.into_iter().map(|(_, count)| count).collect(),
+                requested_chunks: counts.requested_chunks,
+            })
+            .collect();
+
+        ConsensusPoolPage {
+            finalized_height: finalized_tip.height,
+            finalized_hash,
+            notarized_height,
+            random_beacon_height,
+            catch_up_height: self.consensus_pool_cache.catch_up_package().height(),
+            cup_heights,
+            dkg_interval_start: dkg_summary.height,
+            next_dkg_interval_start: dkg_summary.get_next_start_height(),
+            artifact_types: &ARTIFACT_TYPES,
+            validated: &validated,
+            unvalidated: &unvalidated,
+            advert_types,
+            peers: &peers,
+            equivocations: &equivocations,
+        }
+        .render()
+    }
+}
+
+/// Returns the response to a request for the report of the evidence of
+/// equivocation, with the node operators at the latest registry version.
+pub(crate) fn equivocation_report_response(
+    subnet_id: SubnetId,
+    consensus_pool: &RwLock<dyn ConsensusPool + Send + Sync>,
+    registry_client: &dyn RegistryClient,
+) -> Response<Body> {
+    let pool = consensus_pool.read().unwrap();
+    protobuf_response(&make_equivocation_report(
+        subnet_id,
+        &PoolReader::new(&*pool),
+        registry_client,
+        registry_client.get_latest_version(),
+    ))
+}
+
+/// Returns the heights shown for a section with the given per-type height
+/// ranges: up to `MAX_HEIGHTS_PER_SECTION` heights ending at the highest one.
+fn shown_heights(ranges: &[Option<HeightRange>]) -> Option<(Height, Height)> {
+    let min = ranges.iter().flatten().map(|range| range.min).min()?;
+    let max = ranges.iter().flatten().map(|range| range.max).max()?;
+    let min = min.max(Height::from(
+        max.get().saturating_sub(MAX_HEIGHTS_PER_SECTION - 1),
+    ));
+    Some((min, max))
+}
+
+/// Builds one `PoolRow` per height from `max` down to `min`, out of the
+/// per-type artifact counts indexed by height.
+fn make_rows(min: Height, max: Height, columns: &[BTreeMap<Height, usize>]) -> Vec<PoolRow> {
+    (min.get()..=max.get())
+        .rev()
+        .map(Height::from)
+        .map(|height| PoolRow {
+            height,
+            counts: columns
+                .iter()
+                .map(|column| column.get(&height).copied().unwrap_or(0))
+                .collect(),
+        })
+        .collect()
+}
+
+impl Service<Body> for ConsensusPoolService {
+    type Response = Response<Body>;
+    type Error = BoxError;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + Sync>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _unused: Body) -> Self::Future {
+        use hyper::header;
+        let res = match self.render() {
+            Ok(content) => {
+                let mut response = Response::new(Body::from(content));
+                *response.status_mut() = StatusCode::OK;
+                response.headers_mut().insert(
+                    header::CONTENT_TYPE,
+                    header::HeaderValue::from_static(CONTENT_TYPE_HTML),
+                );
+                response
+            }
+            // If there was an internal error, the error description is text, not HTML, and
+            // therefore we don't attach the header
+            Err(e) => make_response(internal_error(format!("Internal error: {}", e))),
+        };
+        Box::pin(async move { Ok(res) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shown_heights() {
+        let range =
+            |min: u64, max: u64| Some(HeightRange::new(Height::from(min), Height::from(max)));
+        assert_eq!(shown_heights(&[None, None]), None);
+        assert_eq!(
+            shown_heights(&[range(3, 5), None, range(1, 4)]),
+            Some((Height::from(1), Height::from(5)))
+        );
+        assert_eq!(
+            shown_heights(&[range(0, 1000), range(10, 500)]),
+            Some((
+                Height::from(1000 - MAX_HEIGHTS_PER_SECTION + 1),
+                Height::from(1000)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_make_rows() {
+        let column = |counts: &[(u64, usize)]| -> BTreeMap<Height, usize> {
+            counts
+                .iter()
+                .map(|(height, count)| (Height::from(*height), *count))
+                .collect()
+        };
+        let rows = make_rows(
+            Height::from(2),
+            Height::from(4),
+            &[column(&[(2, 1), (4, 3)]), column(&[(3, 2), (7, 9)])],
+        );
+        let rows: Vec<(u64, Vec<usize>)> = rows
+            .into_iter()
+            .map(|row| (row.height.get(), row.counts))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![(4, vec![3, 0]), (3, vec![0, 2]), (2, vec![1, 0])]
+        );
+    }
+
+    #[test]
+    fn test_render_consensus_pool_page() {
+        let validated = make_rows(
+            Height::from(7),
+            Height::from(8),
+            &[vec![(Height::from(8), 4)].into_iter().collect()],
+        );
+        let peers = vec![PeerRow {
+            node_id: "peer-1".to_string(),
+            adverts: vec![5],
+            requested_chunks: 6,
+        }];
+        let equivocations = vec![EquivocationRow {
+            signer: "maker-1".to_string(),
+            kind: "block_maker",
+            height: Height::from(8),
+            rank: "0".to_string(),
+            first_hash: "aaaa".to_string(),
+            second_hash: "<bbbb>".to_string(),
+        }];
+        let page = ConsensusPoolPage {
+            finalized_height: Height::from(6),
+            finalized_hash: "cafe".to_string(),
+            notarized_height: Height::from(8),
+            random_beacon_height: Height::from(8),
+            catch_up_height: Height::from(0),
+            cup_heights: vec![Height::from(0)],
+            dkg_interval_start: Height::from(0),
+            next_dkg_interval_start: Height::from(100),
+            artifact_types: &ARTIFACT_TYPES[..1],
+            validated: &validated,
+            unvalidated: &[],
+            advert_types: vec![ic_types::artifact::ArtifactTag::ConsensusArtifact],
+            peers: &peers,
+            equivocations: &equivocations,
+        }
+        .render()
+        .unwrap();
+
+        assert!(page.contains("6 (cafe)"));
+        assert!(page.contains("<th class=\"number\">BlockProposal</th>"));
+        assert!(page.contains("<td class=\"number\">4</td>"));
+        assert!(page.contains("<td class=\"number\">7</td>"));
+        assert!(page.contains("<td class=\"text\">peer-1</td>"));
+        assert!(page.contains("<td class=\"number\">6</td>"));
+        assert!(page.contains("maker-1"));
+        assert!(page.contains("<td class=\"text\">block_maker</td>"));
+        // Values are escaped.
+        assert!(page.contains("&lt;bbbb&gt;"));
+        assert!(!page.contains("<bbbb>"));
+    }
+}
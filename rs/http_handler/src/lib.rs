@@ -8,11 +8,14 @@
 mod body;
 mod catch_up_package;
 mod common;
+mod consensus_pool;
 mod dashboard;
 mod metrics;
+mod pool_snapshot;
 mod pprof;
 mod query;
 mod read_state;
+mod state_changes;
 mod status;
 mod submit;
 mod types;
@@ -21,12 +24,15 @@ use crate::{
     body::BodyReceiverLayer,
     catch_up_package::CatchUpPackageService,
     common::{get_cors_headers, map_box_error_to_response},
+    consensus_pool::ConsensusPoolService,
     dashboard::DashboardService,
     metrics::{
         LABEL_REQUEST_TYPE, LABEL_STATUS, LABEL_TYPE, REQUESTS_LABEL_NAMES, REQUESTS_NUM_LABELS,
     },
+    pool_snapshot::PoolSnapshotService,
     query::QueryService,
     read_state::ReadStateService,
+    state_changes::StateChanges,
     status::StatusService,
     submit::CallService,
     types::*,
@@ -34,18 +40,19 @@ use crate::{
 use hyper::{server::conn::Http, Body, Request, Response, StatusCode};
 use ic_base_thread::ObservableCountingSemaphore;
 use ic_config::http_handler::Config;
-use ic_crypto_tls_interfaces::TlsHandshake;
+use ic_crypto_tls_interfaces::{AllowedClients, TlsHandshake};
 use ic_crypto_tree_hash::{lookup_path, LabeledTree, Path};
 use ic_interfaces::{
-    consensus_pool::ConsensusPoolCache,
+    consensus_pool::{ConsensusPool, ConsensusPoolCache},
     crypto::IngressSigVerifier,
     execution_environment::{IngressFilterService, QueryExecutionService},
-    p2p::IngressIngestionService,
+    p2p::{IngressIngestionService, PeerArtifactCountsReader},
     registry::RegistryClient,
     state_manager::StateReader,
 };
 use ic_logger::{debug, error, fatal, info, warn, ReplicaLogger};
 use ic_metrics::{histogram_vec_timer::HistogramVecTimer, MetricsRegistry};
+use ic_registry_client::helper::subnet::SubnetRegistry;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{NodeTopology, ReplicatedState};
 use ic_types::{
@@ -61,6 +68,7 @@ use ic_types::{
 use metrics::HttpHandlerMetrics;
 use rand::Rng;
 use std::convert::TryFrom;
+use std::future::Future;
 use std::io::{Error, ErrorKind, Write};
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -69,6 +77,7 @@ use std::time::Duration;
 use tempfile::NamedTempFile;
 use tokio::{
     net::{TcpListener, TcpStream},
+    sync::Semaphore,
     time::{timeout, Instant},
 };
 use tower::{
@@ -124,6 +133,10 @@ pub(crate) const MAX_REQUEST_RECEIVE_DURATION: Duration = Duration::from_secs(30
 const MAX_FETCH_DELEGATION_ATTEMPTS: u8 = 10;
 
 const HTTP_DASHBOARD_URL_PATH: &str = "/_/dashboard";
+const HTTP_CONSENSUS_URL_PATH: &str = "/_/consensus";
+const HTTP_EQUIVOCATION_REPORT_URL_PATH: &str = "/_/equivocation_report";
+const HTTP_STATE_CHANGES_URL_PATH: &str = "/_/state_changes";
+const HTTP_POOL_SNAPSHOT_URL_PATH: &str = "/_/pool_snapshot";
 const CONTENT_TYPE_CBOR: &str = "application/cbor";
 
 // Placeholder used when we can't determine the approriate prometheus label.
@@ -149,6 +162,8 @@ struct HttpHandler {
     ingress_filter: IngressFilterService,
 
     consensus_pool_cache: Arc<dyn ConsensusPoolCache>,
+    consensus_pool: Arc<RwLock<dyn ConsensusPool + Send + Sync>>,
+    peer_artifact_counts_reader: Arc<dyn PeerArtifactCountsReader>,
     #[allow(dead_code)]
     backup_spool_path: Option<PathBuf>,
     malicious_flags: MaliciousFlags,
@@ -259,6 +274,8 @@ pub async fn start_server(
     nns_subnet_id: SubnetId,
     log: ReplicaLogger,
     consensus_pool_cache: Arc<dyn ConsensusPoolCache>,
+    consensus_pool: Arc<RwLock<dyn ConsensusPool + Send + Sync>>,
+    peer_artifact_counts_reader: Arc<dyn PeerArtifactCountsReader>,
     backup_spool_path: Option<PathBuf>,
     subnet_type: SubnetType,
     malicious_flags: MaliciousFlags,
@@ -281,6 +298,8 @@ pub async fn start_server(
         ingress_sender,
         ingress_filter,
         consensus_pool_cache,
+        consensus_pool,
+        peer_artifact_counts_reader,
         backup_spool_path,
         malicious_flags,
         delegation_from_nns: Arc::new(RwLock::new(None)),
@@ -305,6 +324,35 @@ pub async fn start_server(
         create_port_file(path, local_addr.port());
     }
 
+    if let Some(admin_addr) = http_handler.config.admin_listen_addr {
+        info!(log, "Binding admin HTTP server to address {}", admin_addr);
+        let admin_listener = TcpListener::bind(admin_addr).await?;
+        let state_changes =
+            state_changes::start_state_change_feed(Arc::clone(&http_handler.state_reader));
+        tokio::task::spawn(serve_admin_connections(
+            admin_listener,
+            metrics.clone(),
+            http_handler.clone(),
+            state_changes,
+            log.clone(),
+        ));
+    }
+
+    if let Some(pool_snapshot_addr) = http_handler.config.pool_snapshot_listen_addr {
+        info!(
+            log,
+            "Binding pool snapshot server to address {}", pool_snapshot_addr
+        );
+        let pool_snapshot_listener = TcpListener::bind(pool_snapshot_addr).await?;
+        tokio::task::spawn(serve_pool_snapshot_connections(
+            pool_snapshot_listener,
+            metrics.clone(),
+            http_handler.clone(),
+            Arc::clone(&tls_handshake),
+            log.clone(),
+        ));
+    }
+
     start_server_initialization(
         Arc::clone(&http_handler.state_reader),
         http_handler.subnet_id,
@@ -410,11 +458,56 @@ fn create_main_service(
     http_handler: HttpHandler,
     app_layer: AppLayer,
 ) -> BoxService<Request<Body>, Response<Body>, CanonicalError> {
-    let metrics_for_map_request = metrics.clone();
+    let metrics_for_router = metrics.clone();
+    create_timed_service(metrics, move |req| {
+        make_router(
+            metrics_for_router.clone(),
+            http_handler.clone(),
+            app_layer,
+            req,
+        )
+    })
+}
+
+fn create_pool_snapshot_service(
+    metrics: HttpHandlerMetrics,
+    http_handler: HttpHandler,
+    in_progress: Arc<Semaphore>,
+) -> BoxService<Request<Body>, Response<Body>, CanonicalError> {
+    let metrics_for_router = metrics.clone();
+    create_timed_service(metrics, move |req| {
+        make_pool_snapshot_router(
+            metrics_for_router.clone(),
+            http_handler.clone(),
+            Arc::clone(&in_progress),
+            req,
+        )
+    })
+}
+
+fn create_admin_service(
+    metrics: HttpHandlerMetrics,
+    http_handler: HttpHandler,
+    state_changes: Arc<RwLock<StateChanges>>,
+) -> BoxService<Request<Body>, Response<Body>, CanonicalError> {
+    create_timed_service(metrics, move |req| {
+        make_admin_router(http_handler.clone(), Arc::clone(&state_changes), req)
+    })
+}
+
+/// Wraps `router` into a service that records the duration and the labels of
+/// each request it handles.
+fn create_timed_service<F, Fut>(
+    metrics: HttpHandlerMetrics,
+    router: F,
+) -> BoxService<Request<Body>, Response<Body>, CanonicalError>
+where
+    F: Fn(RequestWithTimer) -> Fut + Send + 'static,
+    Fut: Future<Output = ResponseWithTimer> + Send + 'static,
+{
     let route_service = service_fn(move |req: RequestWithTimer| {
-        let metrics = metrics.clone();
-        let http_handler = http_handler.clone();
-        async move { Ok::<_, BoxError>(make_router(metrics, http_handler, app_layer, req).await) }
+        let response = router(req);
+        async move { Ok::<_, BoxError>(response.await) }
     });
     BoxService::new(
         ServiceBuilder::new()
@@ -422,7 +515,7 @@ fn create_main_service(
             .map_request(move |request| {
                 // Start recording request duration.
                 let request_timer = HistogramVecTimer::start_timer(
-                    metrics_for_map_request.requests.clone(),
+                    metrics.requests.clone(),
                     &REQUESTS_LABEL_NAMES,
                     [UNKNOWN_LABEL, UNKNOWN_LABEL, UNKNOWN_LABEL],
                 );
@@ -724,6 +817,233 @@ async fn make_router(
     )
 }
 
+/// Routes the requests to the endpoints meant for the operators of this node.
+/// These expose internal state of the replica, so they are only served by the
+/// admin listener, which `Config` restricts to loopback addresses.
+async fn make_admin_router(
+    http_handler: HttpHandler,
+    state_changes: Arc<RwLock<StateChanges>>,
+    (req, mut timer): RequestWithTimer,
+) -> ResponseWithTimer {
+    use http::method::Method;
+
+    let svc = match (req.method(), req.uri().path()) {
+        (&Method::GET, HTTP_CONSENSUS_URL_PATH) => {
+            set_timer_labels(&mut timer, RequestType::Consensus, ApiReqType::Consensus);
+            BoxService::new(ConsensusPoolService::new(
+                http_handler.consensus_pool_cache,
+                http_handler.consensus_pool,
+                http_handler.peer_artifact_counts_reader,
+            ))
+        }
+        (&Method::GET, HTTP_EQUIVOCATION_REPORT_URL_PATH) => {
+            set_timer_labels(
+                &mut timer,
+                RequestType::EquivocationReport,
+                ApiReqType::EquivocationReport,
+            );
+            return (
+                consensus_pool::equivocation_report_response(
+                    http_handler.subnet_id,
+                    &http_handler.consensus_pool,
+                    http_handler.registry_client.as_ref(),
+                ),
+                timer,
+            );
+        }
+        (&Method::GET, HTTP_STATE_CHANGES_URL_PATH) => {
+            set_timer_labels(
+                &mut timer,
+                RequestType::StateChanges,
+                ApiReqType::StateChanges,
+            );
+            return (
+                state_changes::response(&state_changes, req.uri().query()),
+                timer,
+            );
+        }
+        _ => {
+            set_timer_labels(
+                &mut timer,
+                RequestType::InvalidArgument,
+                ApiReqType::InvalidArgument,
+            );
+            return (
+                common::make_response(invalid_argument_error(format!(""))),
+                timer,
+            );
+        }
+    };
+    (
+        LoadShed::new(svc)
+            .ready()
+            .await
+            .expect("The load shedder must always be ready.")
+            .call(req.into_body())
+            .await
+            .unwrap_or_else(|err| map_box_error_to_response(err)),
+        timer,
+    )
+}
+
+/// Routes the requests of the other nodes of the subnet, which only fetch pool
+/// snapshots.
+async fn make_pool_snapshot_router(
+    metrics: HttpHandlerMetrics,
+    http_handler: HttpHandler,
+    in_progress: Arc<Semaphore>,
+    (req, mut timer): RequestWithTimer,
+) -> ResponseWithTimer {
+    use http::method::Method;
+
+    if req.method() != Method::POST || req.uri().path() != HTTP_POOL_SNAPSHOT_URL_PATH {
+        set_timer_labels(
+            &mut timer,
+            RequestType::InvalidArgument,
+            ApiReqType::InvalidArgument,
+        );
+        return (
+            common::make_response(invalid_argument_error(format!(""))),
+            timer,
+        );
+    }
+    set_timer_labels(
+        &mut timer,
+        RequestType::PoolSnapshot,
+        ApiReqType::PoolSnapshot,
+    );
+    let svc = BoxService::new(
+        ServiceBuilder::new()
+            .layer(BodyReceiverLayer::default())
+            .service(PoolSnapshotService::new(
+                metrics,
+                http_handler.consensus_pool,
+                in_progress,
+            )),
+    );
+    (
+        LoadShed::new(svc)
+            .ready()
+            .await
+            .expect("The load shedder must always be ready.")
+            .call(req.into_body())
+            .await
+            .unwrap_or_else(|err| map_box_error_to_response(err)),
+        timer,
+    )
+}
+
+/// Serves the connections accepted by the pool snapshot listener, forever.
+/// Only the nodes of this subnet, authenticated by their TLS client
+/// certificates, are served.
+async fn serve_pool_snapshot_connections(
+    tcp_listener: TcpListener,
+    metrics: HttpHandlerMetrics,
+    http_handler: HttpHandler,
+    tls_handshake: Arc<dyn TlsHandshake + Send + Sync>,
+    log: ReplicaLogger,
+) {
+    let http = Http::new();
+    let in_progress = Arc::new(Semaphore::new(1));
+    loop {
+        let (tcp_stream, _) = match tcp_listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                warn!(log, "Pool snapshot connection error (can't accept) {}", err);
+                continue;
+            }
+        };
+        let registry_version = http_handler.registry_client.get_latest_version();
+        let allowed_clients = match http_handler
+            .registry_client
+            .get_node_ids_on_subnet(http_handler.subnet_id, registry_version)
+        {
+            Ok(Some(node_ids)) => AllowedClients::new_with_nodes(node_ids.into_iter().collect()),
+            Ok(None) | Err(_) => {
+                warn!(
+                    log,
+                    "No nodes found for subnet {} at registry version {}",
+                    http_handler.subnet_id,
+                    registry_version
+                );
+                continue;
+            }
+        };
+        let allowed_clients = match allowed_clients {
+            Ok(allowed_clients) => allowed_clients,
+            Err(err) => {
+                warn!(log, "Invalid pool snapshot clients: {:?}", err);
+                continue;
+            }
+        };
+        let service = create_pool_snapshot_service(
+            metrics.clone(),
+            http_handler.clone(),
+            Arc::clone(&in_progress),
+        );
+        let tls_handshake = Arc::clone(&tls_handshake);
+        let http = http.clone();
+        let log = log.clone();
+        tokio::task::spawn(async move {
+            let tls_stream = match tls_handshake
+                .perform_tls_server_handshake(tcp_stream, allowed_clients, registry_version)
+                .await
+            {
+                Ok((tls_stream, _peer)) => tls_stream,
+                Err(err) => {
+                    warn!(
+                        log,
+                        "Pool snapshot connection error (TLS handshake): {}", err
+                    );
+                    return;
+                }
+            };
+            if let Err(err) = http.serve_connection(tls_stream, service).await {
+                warn!(
+                    log,
+                    "Pool snapshot connection error (can't serve HTTPS connection): {}", err
+                );
+            }
+        });
+    }
+}
+
+/// Serves the connections accepted by the admin listener over plain HTTP,
+/// forever.
+async fn serve_admin_connections(
+    tcp_listener: TcpListener,
+    metrics: HttpHandlerMetrics,
+    http_handler: HttpHandler,
+    state_changes: Arc<RwLock<StateChanges>>,
+    log: ReplicaLogger,
+) {
+    let http = Http::new();
+    loop {
+        match tcp_listener.accept().await {
+            Ok((tcp_stream, _)) => {
+                let service = create_admin_service(
+                    metrics.clone(),
+                    http_handler.clone(),
+                    Arc::clone(&state_changes),
+                );
+                let http = http.clone();
+                let log = log.clone();
+                tokio::task::spawn(async move {
+                    if let Err(err) = http.serve_connection(tcp_stream, service).await {
+                        warn!(
+                            log,
+                            "Admin connection error (can't serve HTTP connection): {}", err
+                        );
+                    }
+                });
+            }
+            Err(err) => {
+                warn!(log, "Admin connection error (can't accept) {}", err);
+            }
+        }
+    }
+}
+
 // Fetches a delegation from the NNS subnet to allow this subnet to issue
 // certificates on its behalf. On the NNS subnet this method is a no-op.
 fn load_root_delegation(
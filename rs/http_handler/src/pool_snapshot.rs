@@ -0,0 +1,89 @@
+//! Module that deals with requests to /_/pool_snapshot
+
+use crate::{
+    common,
+    types::{ApiReqType, RequestType},
+    HttpHandlerMetrics, UNKNOWN_LABEL,
+};
+use hyper::{Body, Response};
+use ic_artifact_pool::pool_snapshot::make_pool_snapshot;
+use ic_interfaces::consensus_pool::ConsensusPool;
+use ic_types::canonical_error::{internal_error, unavailable_error};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use tokio::sync::Semaphore;
+use tower::{BoxError, Service};
+
+/// Serves the finalized chain above the latest CUP to nodes that start from
+/// that CUP. See `ic_artifact_pool::pool_snapshot`.
+#[derive(Clone)]
+pub(crate) struct PoolSnapshotService {
+    metrics: HttpHandlerMetrics,
+    consensus_pool: Arc<RwLock<dyn ConsensusPool + Send + Sync>>,
+    /// Snapshots are taken one at a time, concurrent requests are rejected.
+    in_progress: Arc<Semaphore>,
+}
+
+impl PoolSnapshotService {
+    pub(crate) fn new(
+        metrics: HttpHandlerMetrics,
+        consensus_pool: Arc<RwLock<dyn ConsensusPool + Send + Sync>>,
+        in_progress: Arc<Semaphore>,
+    ) -> Self {
+        Self {
+            metrics,
+            consensus_pool,
+            in_progress,
+        }
+    }
+}
+
+impl Service<Vec<u8>> for PoolSnapshotService {
+    type Response = Response<Body>;
+    type Error = BoxError;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, body: Vec<u8>) -> Self::Future {
+        self.metrics
+            .requests_body_size_bytes
+            .with_label_values(&[
+                RequestType::PoolSnapshot.as_str(),
+                ApiReqType::PoolSnapshot.as_str(),
+                UNKNOWN_LABEL,
+            ])
+            .observe(body.len() as f64);
+
+        let permit = match Arc::clone(&self.in_progress).try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                let res = Ok(common::make_response(unavailable_error(
+                    "Another pool snapshot is being taken, retry later.".to_string(),
+                )));
+                return Box::pin(async move { res });
+            }
+        };
+        let consensus_pool = Arc::clone(&self.consensus_pool);
+        Box::pin(async move {
+            // Taking the snapshot locks the pool repeatedly, which must not
+            // block the async runtime.
+            let res = tokio::task::spawn_blocking(move || {
+                let _permit = permit;
+                common::protobuf_response(&make_pool_snapshot(&*consensus_pool))
+            })
+            .await;
+            Ok(res.unwrap_or_else(|err| {
+                common::make_response(internal_error(format!(
+                    "Failed to take a pool snapshot: {}",
+                    err
+                )))
+            }))
+        })
+    }
+}
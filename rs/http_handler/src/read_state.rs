@@ -36,6 +36,13 @@ use tower::{BoxError, Service};
 
 const MAX_READ_STATE_REQUEST_IDS: u8 = 100;
 
+/// The certification version from which on the cycles balances of canisters,
+/// the public keys of nodes and the subnet metrics are part of the certified
+/// state. Until the subnet certifies its states with (at least) this version,
+/// requests for these paths are rejected instead of being answered with a
+/// proof of absence.
+const CERTIFIED_CYCLES_BALANCES_AND_NODE_KEYS_VERSION: u32 = 7;
+
 #[derive(Clone)]
 pub(crate) struct ReadStateService {
     log: ReplicaLogger,
@@ -202,6 +209,20 @@ fn verify_paths(
             [b"canister", _canister_id, b"controller"] => {}
             [b"canister", _canister_id, b"controllers"] => {}
             [b"canister", _canister_id, b"module_hash"] => {}
+            [b"canister", canister_id, b"cycles_balance"] => {
+                check_path_is_certified(&state)?;
+                match CanisterId::try_from(*canister_id) {
+                    Ok(canister_id) => {
+                        can_read_canister_cycles_balance(user, &canister_id, &state)?
+                    }
+                    Err(err) => {
+                        return Err(invalid_argument_error(format!(
+                            "Could not parse Canister ID: {}.",
+                            err
+                        )))
+                    }
+                }
+            }
             [b"canister", canister_id, b"metadata", name] => {
                 let name = String::from_utf8(Vec::from(*name)).map_err(|err| {
                     invalid_argument_error(format!(
@@ -224,6 +245,10 @@ fn verify_paths(
             }
             [b"subnet", _subnet_id, b"public_key"] => {}
             [b"subnet", _subnet_id, b"canister_ranges"] => {}
+            [b"subnet", _subnet_id, b"metrics"]
+            | [b"subnet", _subnet_id, b"node", _node_id, b"public_key"] => {
+                check_path_is_certified(&state)?
+            }
             [b"request_status", request_id] | [b"request_status", request_id, ..] => {
                 num_request_ids += 1;
 
@@ -298,10 +323,46 @@ fn can_read_canister_metadata(
     Ok(())
 }
 
+// Rejects paths that were added in certification version
+// `CERTIFIED_CYCLES_BALANCES_AND_NODE_KEYS_VERSION` if `state` is certified
+// with an older version, i.e. if the paths are not part of the certified state.
+fn check_path_is_certified(state: &ReplicatedState) -> Result<(), CanonicalError> {
+    if state.metadata.certification_version < CERTIFIED_CYCLES_BALANCES_AND_NODE_KEYS_VERSION {
+        return Err(not_found_error(format!(
+            "The requested path is not certified before certification version {}.",
+            CERTIFIED_CYCLES_BALANCES_AND_NODE_KEYS_VERSION
+        )));
+    }
+    Ok(())
+}
+
+fn can_read_canister_cycles_balance(
+    user: &UserId,
+    canister_id: &CanisterId,
+    state: &ReplicatedState,
+) -> Result<(), CanonicalError> {
+    let canister = state
+        .canister_states
+        .get(canister_id)
+        .ok_or_else(|| not_found_error("Invalid path requested.".to_string()))?;
+
+    // Only the controllers can request the cycles balance.
+    if !canister.system_state.controllers.contains(&user.get()) {
+        return Err(permission_denied_error(
+            "The cycles balance can only be requested by the controllers of the canister."
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use crate::common::test::{array, assert_cbor_ser_equal, bytes, int};
-    use crate::read_state::can_read_canister_metadata;
+    use crate::read_state::{
+        can_read_canister_cycles_balance, can_read_canister_metadata, check_path_is_certified,
+        CERTIFIED_CYCLES_BALANCES_AND_NODE_KEYS_VERSION,
+    };
     use ic_crypto_tree_hash::{Digest, Label, MixedHashTree};
     use ic_registry_subnet_type::SubnetType;
     use ic_replicated_state::ReplicatedState;
@@ -423,4 +484,54 @@ mod test {
             Err(not_found_error("Invalid path requested.".to_string()))
         );
     }
+
+    #[test]
+    fn only_controllers_can_read_canister_cycles_balance() {
+        let canister_id = canister_test_id(100);
+        let controller = user_test_id(24);
+        let non_controller = user_test_id(20);
+
+        let mut state = ReplicatedState::new_rooted_at(
+            subnet_test_id(1),
+            SubnetType::Application,
+            "Initial".into(),
+        );
+        insert_dummy_canister(&mut state, canister_id, controller.get());
+
+        assert!(can_read_canister_cycles_balance(&controller, &canister_id, &state).is_ok());
+        assert_eq!(
+            can_read_canister_cycles_balance(&non_controller, &canister_id, &state),
+            Err(permission_denied_error(
+                "The cycles balance can only be requested by the controllers of the canister."
+                    .to_string()
+            ))
+        );
+
+        // Non existent canister.
+        assert_eq!(
+            can_read_canister_cycles_balance(&controller, &canister_test_id(101), &state),
+            Err(not_found_error("Invalid path requested.".to_string()))
+        );
+    }
+
+    #[test]
+    fn new_paths_are_rejected_before_they_are_certified() {
+        let mut state = ReplicatedState::new_rooted_at(
+            subnet_test_id(1),
+            SubnetType::Application,
+            "Initial".into(),
+        );
+
+        state.metadata.certification_version = CERTIFIED_CYCLES_BALANCES_AND_NODE_KEYS_VERSION - 1;
+        assert_eq!(
+            check_path_is_certified(&state),
+            Err(not_found_error(format!(
+                "The requested path is not certified before certification version {}.",
+                CERTIFIED_CYCLES_BALANCES_AND_NODE_KEYS_VERSION
+            )))
+        );
+
+        state.metadata.certification_version = CERTIFIED_CYCLES_BALANCES_AND_NODE_KEYS_VERSION;
+        assert_eq!(check_path_is_certified(&state), Ok(()));
+    }
 }
@@ -0,0 +1,229 @@
+//! Module that serves `/_/state_changes`, a JSON feed of the typed changes
+//! (see `ic_canonical_state::state_diff`) of every certified height, meant for
+//! local consumers like indexers. It is only served on the admin listener.
+//!
+//! A background task follows the certified states with a `StateChangeFeed`
+//! and buffers the changes of the most recent heights. Consumers page through
+//! the buffer with the `since` query argument, passing the last height they
+//! have seen.
+
+use crate::common::{get_cors_headers, make_response};
+use hyper::{header, Body, Response, StatusCode};
+use ic_canonical_state::state_diff::StateChange;
+use ic_interfaces::state_manager::StateReader;
+use ic_replicated_state::ReplicatedState;
+use ic_state_manager::state_change_feed::StateChangeFeed;
+use ic_types::{
+    canonical_error::{invalid_argument_error, out_of_range_error, CanonicalError},
+    Height,
+};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const CONTENT_TYPE_JSON: &str = "application/json";
+
+/// The maximum number of heights whose changes are buffered. Consumers that
+/// fall further behind have to resynchronize from the certified state.
+const MAX_BUFFERED_HEIGHTS: usize = 1000;
+
+/// The maximum number of heights returned by a single request.
+const MAX_HEIGHTS_PER_RESPONSE: usize = 100;
+
+/// How often the feed is polled for newly certified heights.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The changes of the most recently certified heights.
+pub(crate) struct StateChanges {
+    /// The height after which the changes of every height are buffered.
+    covered_since: Height,
+    /// The changes per height, in increasing height order.
+    heights: VecDeque<(Height, Vec<StateChange>)>,
+}
+
+#[derive(Serialize)]
+struct HeightChanges<'a> {
+    height: u64,
+    changes: &'a [StateChange],
+}
+
+impl StateChanges {
+    fn new(covered_since: Height) -> Self {
+        Self {
+            covered_since,
+            heights: VecDeque::new(),
+        }
+    }
+
+    /// Appends the changes of newly certified heights, evicting the oldest
+    /// heights beyond `MAX_BUFFERED_HEIGHTS`.
+    fn push(&mut self, changes: Vec<(Height, Vec<StateChange>)>) {
+        self.heights.extend(changes);
+        while self.heights.len() > MAX_BUFFERED_HEIGHTS {
+            if let Some((height, _)) = self.heights.pop_front() {
+                self.covered_since = height;
+            }
+        }
+    }
+
+    /// Returns the changes of up to `MAX_HEIGHTS_PER_RESPONSE` heights above
+    /// `since` (by default, the oldest buffered ones), or an error if some of
+    /// them were already evicted.
+    fn since(&self, since: Option<Height>) -> Result<Vec<HeightChanges<'_>>, CanonicalError> {
+        let since = since.unwrap_or(self.covered_since);
+        if since < self.covered_since {
+            return Err(out_of_range_error(format!(
+                "The changes after height {} are no longer available, only those after height {}.",
+                since, self.covered_since
+            )));
+        }
+        Ok(self
+            .heights
+            .iter()
+            .filter(|(height, _)| *height > since)
+            .take(MAX_HEIGHTS_PER_RESPONSE)
+            .map(|(height, changes)| HeightChanges {
+                height: height.get(),
+                changes,
+            })
+            .collect())
+    }
+}
+
+/// Starts following the certified states of `state_reader` in a background
+/// task, and returns the buffer the changes are collected into.
+pub(crate) fn start_state_change_feed(
+    state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+) -> Arc<RwLock<StateChanges>> {
+    let mut feed = StateChangeFeed::new(state_reader);
+    let state_changes = Arc::new(RwLock::new(StateChanges::new(feed.height())));
+    let buffer = Arc::clone(&state_changes);
+    tokio::task::spawn_blocking(move || loop {
+        // Diffing happens outside of the lock, so requests are not blocked.
+        let changes = feed.poll();
+        if !changes.is_empty() {
+            buffer.write().unwrap().push(changes);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    });
+    state_changes
+}
+
+/// Returns the buffered changes after the height given by the `since` query
+/// argument, as a JSON array of `{"height": .., "changes": [..]}` objects.
+pub(crate) fn response(
+    state_changes: &RwLock<StateChanges>,
+    query: Option<&str>,
+) -> Response<Body> {
+    let since = match parse_since(query) {
+        Ok(since) => since,
+        Err(err) => return make_response(err),
+    };
+    let body = {
+        let state_changes = state_changes.read().unwrap();
+        match state_changes.since(since) {
+            Ok(heights) => serde_json::to_vec(&heights).expect("Failed to serialize state changes"),
+            Err(err) => return make_response(err),
+        }
+    };
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = StatusCode::OK;
+    *response.headers_mut() = get_cors_headers();
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static(CONTENT_TYPE_JSON),
+    );
+    response
+}
+
+fn parse_since(query: Option<&str>) -> Result<Option<Height>, CanonicalError> {
+    let query_pairs: HashMap<_, _> = match query {
+        Some(query) => url::form_urlencoded::parse(query.as_bytes()).collect(),
+        None => Default::default(),
+    };
+    match query_pairs.get("since") {
+        Some(val) => val
+            .parse::<u64>()
+            .map(|since| Some(Height::from(since)))
+            .map_err(|err| invalid_argument_error(err.to_string())),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::types::ids::canister_test_id;
+
+    fn created(canister: u64) -> Vec<StateChange> {
+        vec![StateChange::CanisterCreated {
+            canister_id: canister_test_id(canister),
+        }]
+    }
+
+    fn heights(changes: &[HeightChanges<'_>]) -> Vec<u64> {
+        changes.iter().map(|changes| changes.height).collect()
+    }
+
+    #[test]
+    fn test_since_pages_through_the_buffered_heights() {
+        let mut state_changes = StateChanges::new(Height::from(10));
+        state_changes.push(vec![
+            (Height::from(11), created(1)),
+            (Height::from(13), created(2)),
+        ]);
+        state_changes.push(vec![(Height::from(14), vec![])]);
+
+        assert_eq!(
+            heights(&state_changes.since(None).unwrap()),
+            vec![11, 13, 14]
+        );
+        assert_eq!(
+            heights(&state_changes.since(Some(Height::from(11))).unwrap()),
+            vec![13, 14]
+        );
+        assert_eq!(
+            heights(&state_changes.since(Some(Height::from(14))).unwrap()),
+            Vec::<u64>::new()
+        );
+        assert!(state_changes.since(Some(Height::from(9))).is_err());
+
+        let json =
+            serde_json::to_string(&state_changes.since(Some(Height::from(12))).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                r#"[{{"height":13,"changes":[{{"type":"canister_created","canister_id":"{}"}}]}},{{"height":14,"changes":[]}}]"#,
+                canister_test_id(2)
+            )
+        );
+    }
+
+    #[test]
+    fn test_evicted_heights_are_reported_as_out_of_range() {
+        let mut state_changes = StateChanges::new(Height::from(0));
+        state_changes.push(
+            (1..=MAX_BUFFERED_HEIGHTS as u64 + 2)
+                .map(|h| (Height::from(h), vec![]))
+                .collect(),
+        );
+
+        assert!(state_changes.since(Some(Height::from(1))).is_err());
+        let changes = state_changes.since(Some(Height::from(2))).unwrap();
+        assert_eq!(changes.len(), MAX_HEIGHTS_PER_RESPONSE);
+        assert_eq!(changes[0].height, 3);
+        assert_eq!(heights(&state_changes.since(None).unwrap())[0], 3);
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since(None).unwrap(), None);
+        assert_eq!(parse_since(Some("foo=1")).unwrap(), None);
+        assert_eq!(
+            parse_since(Some("since=42")).unwrap(),
+            Some(Height::from(42))
+        );
+        assert!(parse_since(Some("since=-1")).is_err());
+    }
+}
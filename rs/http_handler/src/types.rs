@@ -13,9 +13,13 @@ pub(crate) enum ApiReqType {
     ReadState,
     /// In case an error occurred and the request type is unknown.
     CatchUpPackage,
+    PoolSnapshot,
     Status,
     Dashboard,
     RedirectToDashboard,
+    Consensus,
+    EquivocationReport,
+    StateChanges,
     Options,
     PprofHome,
     PprofProfile,
@@ -32,9 +36,13 @@ impl ApiReqType {
             ReadState => "read_state",
             Status => "status",
             CatchUpPackage => "catch_up_package",
+            PoolSnapshot => "pool_snapshot",
             Options => "options",
             Dashboard => "dashboard",
             RedirectToDashboard => "redirect_to_dashboard",
+            Consensus => "consensus",
+            EquivocationReport => "equivocation_report",
+            StateChanges => "state_changes",
             InvalidArgument => "invalid_argument",
             PprofHome => "pprof_home",
             PprofProfile => "pprof_profile",
@@ -76,8 +84,16 @@ pub(crate) enum RequestType {
     RedirectToDashboard,
     /// A direct request for the dashboard
     Dashboard,
+    /// A request for the consensus pool page
+    Consensus,
+    /// A request for the report of the evidence of equivocation
+    EquivocationReport,
+    /// A request for the typed changes of the latest certified heights
+    StateChanges,
     /// A request for the latest Catch-Up Package (CUP)
     CatchUpPackage,
+    /// A request for the finalized chain above the latest CUP
+    PoolSnapshot,
     InvalidArgument,
     PprofHome,
     PprofProfile,
@@ -95,7 +111,11 @@ impl RequestType {
             Options => "options",
             RedirectToDashboard => "redirect_to_dashboard",
             Dashboard => "dashboard",
+            Consensus => "consensus",
+            EquivocationReport => "equivocation-report",
+            StateChanges => "state-changes",
             CatchUpPackage => "catch-up-package",
+            PoolSnapshot => "pool-snapshot",
             InvalidArgument => "invalid_argument",
             PprofHome => "pprof_home",
             PprofProfile => "pprof_profile",
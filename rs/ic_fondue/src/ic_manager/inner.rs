@@ -16,7 +16,7 @@ use ic_prep_lib::subnet_configuration::SubnetConfig;
 use ic_types::ReplicaVersion;
 use ic_types::{
     malicious_behaviour::MaliciousBehaviour,
-    transport::{TransportConfig, TransportFlowConfig},
+    transport::{TransportBackend, TransportConfig, TransportFlowConfig},
     SubnetId,
 };
 use ic_utils::command::find_file_on_path;
@@ -490,6 +490,7 @@ impl IcManager {
                 server_port: p2p_port,
                 queue_size: 256,
             }],
+            transport_backend: TransportBackend::Tcp,
         });
         replica_config.state_manager = Some(StateManagerConfig::new(state_manager_root));
         replica_config.http_handler = Some(http_handler::ExternalConfig {
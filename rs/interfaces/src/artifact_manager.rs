@@ -121,6 +121,18 @@ pub trait ArtifactClient<Artifact: artifact::ArtifactKind>: Send + Sync {
         usize::max_value()
     }
 
+    /// Return the size (in bytes) up to which artifacts of this client are
+    /// pushed to peers together with their adverts, instead of being
+    /// advertised and requested by each peer.
+    ///
+    /// Pushing saves a network round trip per artifact at the cost of
+    /// sending the artifact to peers that may already have it, so it is
+    /// only worthwhile for small artifacts. The default implementation
+    /// disables pushing.
+    fn get_push_size_threshold(&self) -> Option<usize> {
+        None
+    }
+
     /// Return the priority function used by this client.
     #[allow(clippy::type_complexity)]
     fn get_priority_function(&self) -> Option<PriorityFn<Artifact::Id, Artifact::Attribute>>;
@@ -220,6 +232,12 @@ pub trait ArtifactManager: Send + Sync {
     /// See `ArtifactClient::get_remaining_quota` for more details.
     fn get_remaining_quota(&self, tag: artifact::ArtifactTag, peer_id: NodeId) -> Option<usize>;
 
+    /// Gets the size up to which artifacts of a specific client that is
+    /// identified by the given artifact tag are pushed to peers.
+    ///
+    /// See `ArtifactClient::get_push_size_threshold` for more details.
+    fn get_push_size_threshold(&self, tag: artifact::ArtifactTag) -> Option<usize>;
+
     /// Return the priority function for a specific client that is identified by
     /// the given artifact tag.
     ///
@@ -14,7 +14,11 @@ use crate::{
 };
 use ic_base_types::{NumBytes, SubnetId};
 use ic_types::{
-    artifact::{ConsensusMessageAttribute, ConsensusMessageFilter, ConsensusMessageId, PriorityFn},
+    artifact::{
+        ConsensusMessageAttribute, ConsensusMessageFilter, ConsensusMessageId,
+        EquivocationEvidenceAttribute, EquivocationEvidenceId, PriorityFn,
+    },
+    consensus::equivocation::EquivocationEvidence,
     registry::RegistryClientError,
 };
 
@@ -53,6 +57,27 @@ pub trait ConsensusGossip: Send + Sync {
     fn get_filter(&self) -> ConsensusMessageFilter;
 }
 
+/// Validation of the evidence of equivocation gossiped by other replicas.
+pub trait EquivocationEvidenceValidator: Send {
+    /// Validate the given evidence received from peers against the input
+    /// [ConsensusPool], and return a [ChangeSet] recording the valid pieces
+    /// of evidence.
+    fn on_state_change(
+        &self,
+        consensus_pool: &dyn ConsensusPool,
+        evidence: Vec<EquivocationEvidence>,
+    ) -> ChangeSet;
+}
+
+/// Equivocation evidence to gossip interface.
+pub trait EquivocationEvidenceGossip: Send + Sync {
+    /// Return a priority function that matches the given consensus pool.
+    fn get_priority_function(
+        &self,
+        consensus_pool: &dyn ConsensusPool,
+    ) -> PriorityFn<EquivocationEvidenceId, EquivocationEvidenceAttribute>;
+}
+
 #[derive(Debug)]
 pub enum PayloadPermanentError {
     XNetPayloadValidationError(InvalidXNetPayload),
@@ -9,10 +9,10 @@ use ic_protobuf::types::v1 as pb;
 use ic_types::{
     artifact::ConsensusMessageId,
     consensus::{
-        catchup::CUPWithOriginalProtobuf, Block, BlockProposal, CatchUpPackage,
-        CatchUpPackageShare, ConsensusMessage, ContentEq, Finalization, FinalizationShare,
-        HasHeight, HashedBlock, Notarization, NotarizationShare, RandomBeacon, RandomBeaconShare,
-        RandomTape, RandomTapeShare,
+        catchup::CUPWithOriginalProtobuf, equivocation::EquivocationEvidence, Block, BlockProposal,
+        CatchUpPackage, CatchUpPackageShare, ConsensusMessage, ContentEq, Finalization,
+        FinalizationShare, HasHeight, HashedBlock, Notarization, NotarizationShare, RandomBeacon,
+        RandomBeaconShare, RandomTape, RandomTapeShare,
     },
     time::Time,
     Height,
@@ -34,6 +34,7 @@ pub enum ChangeAction {
     HandleInvalid(ConsensusMessage, String),
     PurgeValidatedBelow(Height),
     PurgeUnvalidatedBelow(Height),
+    RecordEquivocation(EquivocationEvidence),
 }
 // end::change_set[]
 
@@ -90,6 +91,7 @@ impl ContentEq for ChangeAction {
             (ChangeAction::AddToValidated(x), ChangeAction::MoveToValidated(y)) => x.content_eq(y),
             (ChangeAction::MoveToValidated(x), ChangeAction::AddToValidated(y)) => x.content_eq(y),
             (ChangeAction::PurgeValidatedBelow(x), ChangeAction::PurgeValidatedBelow(y)) => x == y,
+            (ChangeAction::RecordEquivocation(x), ChangeAction::RecordEquivocation(y)) => x == y,
             // Default to false when comparing actions of different type
             _ => false,
         }
@@ -210,6 +212,10 @@ pub trait ConsensusPool {
 
     /// Return a reference to the consensus block cache (ConsensusBlockCache).
     fn as_block_cache(&self) -> &dyn ConsensusBlockCache;
+
+    /// Return the recorded evidence of equivocation. Unlike the artifacts in
+    /// the pool sections, the evidence is not purged.
+    fn equivocation_evidence(&self) -> Vec<EquivocationEvidence>;
 }
 
 /// Mutation operations on top of ConsensusPool.
@@ -4,6 +4,7 @@ use ic_types::consensus::dkg as consensus_dkg;
 use ic_types::consensus::{
     certification::{Certification, CertificationContent, CertificationShare},
     ecdsa::{EcdsaDealing, EcdsaMessage, EcdsaSigShare, EcdsaTranscript},
+    equivocation::EquivocationEvidence,
     BasicSignature, Block, BlockPayload, CatchUpContent, CatchUpContentProtobufBytes,
     CatchUpShareContent, ConsensusMessage, FinalizationContent, HashedBlock, MultiSignature,
     MultiSignatureShare, NotarizationContent, RandomBeaconContent, RandomTapeContent,
@@ -70,6 +71,8 @@ const DOMAIN_ECDSA_VERIFIED_DEALING: &str = "ic-threshold-ecdsa-verified-dealing
 const DOMAIN_ECDSA_TRANSCRIPT: &str = "ic-idkg-transcript-domain";
 const DOMAIN_ECDSA_SIG_SHARE: &str = "ic-threshold-ecdsa-sig-share-domain";
 
+const DOMAIN_EQUIVOCATION_EVIDENCE: &str = "equivocation_evidence_domain";
+
 /// A cryptographically hashable type.
 pub trait CryptoHashable: CryptoHashDomain + Hash {}
 impl<T> CryptoHashable for T where T: CryptoHashDomain + Hash {}
@@ -155,6 +158,8 @@ mod private {
     impl CryptoHashDomainSeal for EcdsaTranscript {}
     impl CryptoHashDomainSeal for EcdsaSigShare {}
 
+    impl CryptoHashDomainSeal for EquivocationEvidence {}
+
     impl CryptoHashDomainSeal for CryptoHashableTestDummy {}
 }
 
@@ -388,6 +393,12 @@ impl CryptoHashDomain for EcdsaSigShare {
     }
 }
 
+impl CryptoHashDomain for EquivocationEvidence {
+    fn domain(&self) -> String {
+        DOMAIN_EQUIVOCATION_EVIDENCE.to_string()
+    }
+}
+
 impl CryptoHashDomain for CryptoHashableTestDummy {
     fn domain(&self) -> String {
         "test_struct_domain".to_string()
@@ -12,6 +12,7 @@ use ic_types::{
     messages::SignedIngress,
     Height, NodeId, Time,
 };
+use std::sync::Arc;
 
 /// GossipPool trait is the generic interface used by ArtifactManager
 /// to interact with the Pools internally and allow GossipProtocol to
@@ -51,10 +52,20 @@ pub trait GossipPool<T, S> {
         -> Box<dyn Iterator<Item = T> + '_>;
 }
 
+/// Checks the quota of unvalidated artifacts of a peer like
+/// `GossipPool::check_quota`, but without holding the lock of the pool.
+pub trait PeerQuotaChecker: Send + Sync {
+    /// Returns `InsufficientQuotaError` if the peer used up its quota.
+    fn check_quota(&self, peer_id: &NodeId) -> Result<(), ArtifactPoolError>;
+}
+
 /// GossipPool trait for ConsensusPool
 pub trait ConsensusGossipPool:
     GossipPool<ConsensusMessage, ConsensusChangeSet, MessageId = ConsensusMessageId, Filter = Height>
 {
+    /// Returns a checker of the quota of unvalidated artifacts per peer, which
+    /// stays up to date with the pool.
+    fn peer_quota_checker(&self) -> Arc<dyn PeerQuotaChecker>;
 }
 
 /// GossipPool trait for IngressPool
@@ -1,5 +1,8 @@
 //! The P2P public interface.
-use ic_types::{canonical_error::CanonicalError, messages::SignedIngress};
+use ic_types::{
+    artifact::ArtifactTag, canonical_error::CanonicalError, messages::SignedIngress, NodeId,
+};
+use std::collections::BTreeMap;
 use std::convert::Infallible;
 use tower::{buffer::Buffer, util::BoxService};
 
@@ -21,3 +24,20 @@ pub trait P2PRunner: Send {
     /// The method starts the execution of the `P2PRunner`.
     fn run(&mut self);
 }
+
+/// The artifacts *Gossip* currently tracks for a single peer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PeerArtifactCounts {
+    /// The number of artifacts advertised by the peer that have not been
+    /// downloaded yet, by artifact type.
+    pub adverts: Vec<(ArtifactTag, usize)>,
+    /// The number of chunks currently requested from the peer.
+    pub requested_chunks: usize,
+}
+
+/// Gives a read-only view on the per-peer state of *Gossip*. It is mainly to be
+/// used by the HTTP handler for debugging.
+pub trait PeerArtifactCountsReader: Send + Sync {
+    /// Returns the artifact counts of all current peers.
+    fn get_peer_artifact_counts(&self) -> BTreeMap<NodeId, PeerArtifactCounts>;
+}
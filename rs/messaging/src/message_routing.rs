@@ -31,6 +31,7 @@ use ic_replicated_state::{
 };
 use ic_types::{
     batch::Batch,
+    crypto::KeyPurpose,
     ingress::IngressStatus,
     messages::MessageId,
     registry::RegistryClientError,
@@ -451,11 +452,22 @@ impl BatchProcessorImpl {
                     }
                 };
 
+                let public_key =
+                    get_node_public_key(Arc::clone(&self.registry), node_id, registry_version)?
+                        .unwrap_or_else(|| {
+                            debug!(
+                                self.log,
+                                "No valid signing public key found for node {}.", node_id
+                            );
+                            vec![]
+                        });
+
                 nodes.insert(
                     node_id,
                     NodeTopology {
                         ip_address: http_info.ip_addr,
                         http_port,
+                        public_key,
                     },
                 );
             }
@@ -570,6 +582,19 @@ fn get_subnet_public_key(
         .expect("Initial DKG transcripts not found."))
 }
 
+/// Returns the DER-encoded node signing public key of `node_id`, if a valid
+/// one is registered.
+fn get_node_public_key(
+    registry: Arc<dyn RegistryClient>,
+    node_id: NodeId,
+    registry_version: RegistryVersion,
+) -> Result<Option<Vec<u8>>, RegistryClientError> {
+    use ic_crypto::ed25519_public_key_to_der;
+    Ok(registry
+        .get_crypto_key_for_node(node_id, KeyPurpose::NodeSigning, registry_version)?
+        .and_then(|pk| ed25519_public_key_to_der(pk.key_value).ok()))
+}
+
 impl BatchProcessor for BatchProcessorImpl {
     fn process_batch(&self, batch: Batch) {
         let timer = Timer::start();
@@ -0,0 +1,163 @@
+use cycles_minting_canister::{IcpXdrConversionRate, IcpXdrRateQuote};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How often the exchange-rate canister is asked for quotes.
+pub const FETCH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Quotes older than this are ignored.
+pub const MAX_QUOTE_AGE_SECONDS: u64 = 10 * 60;
+
+/// The minimum number of sources whose quotes have to agree for the rate to
+/// be updated.
+pub const MIN_SOURCES: usize = 3;
+
+/// Quotes that deviate from the median quote by more than this, in 1/10,000ths,
+/// are rejected as outliers.
+pub const MAX_QUOTE_DEVIATION_PERMYRIAD: u64 = 500;
+
+/// The maximum change of the rate in a single update, in 1/10,000ths of the
+/// current rate. Larger moves are spread over several updates.
+pub const MAX_RATE_CHANGE_PERMYRIAD: u64 = 1_000;
+
+/// If the rate wasn't updated from the exchange-rate canister for this long,
+/// the source is considered stale and the rate can be set by proposals
+/// again.
+pub const MAX_SOURCE_STALENESS_SECONDS: u64 = 30 * 60;
+
+/// Computes the rate from the quotes of an exchange-rate canister at time
+/// `now_seconds`. Only the most recent fresh quote of every source is
+/// considered, and quotes too far from the median are rejected. The result is
+/// the median of the remaining quotes, with the timestamp of the most recent
+/// one.
+pub fn aggregate_quotes(
+    quotes: &[IcpXdrRateQuote],
+    now_seconds: u64,
+) -> Result<IcpXdrConversionRate, String> {
+    let mut latest_by_source: BTreeMap<&str, &IcpXdrRateQuote> = BTreeMap::new();
+    for quote in quotes.iter().filter(|quote| {
+        quote.xdr_permyriad_per_icp > 0
+            && quote.timestamp_seconds <= now_seconds
+            && now_seconds - quote.timestamp_seconds <= MAX_QUOTE_AGE_SECONDS
+    }) {
+        let latest = latest_by_source
+            .entry(quote.source.as_str())
+            .or_insert(quote);
+        if latest.timestamp_seconds < quote.timestamp_seconds {
+            *latest = quote;
+        }
+    }
+    if latest_by_source.len() < MIN_SOURCES {
+        return Err(format!(
+            "Got fresh quotes from {} sources, at least {} are required",
+            latest_by_source.len(),
+            MIN_SOURCES
+        ));
+    }
+
+    let mut rates: Vec<u64> = latest_by_source
+        .values()
+        .map(|quote| quote.xdr_permyriad_per_icp)
+        .collect();
+    rates.sort_unstable();
+    let median = rates[rates.len() / 2];
+
+    let agreeing: Vec<&IcpXdrRateQuote> = latest_by_source
+        .values()
+        .copied()
+        .filter(|quote| {
+            let deviation = (quote.xdr_permyriad_per_icp as i128 - median as i128).abs();
+            deviation * 10_000 <= median as i128 * MAX_QUOTE_DEVIATION_PERMYRIAD as i128
+        })
+        .collect();
+    if agreeing.len() < MIN_SOURCES {
+        return Err(format!(
+            "Only {} of {} sources agree on the rate, at least {} are required",
+            agreeing.len(),
+            latest_by_source.len(),
+            MIN_SOURCES
+        ));
+    }
+
+    let mut rates: Vec<u64> = agreeing
+        .iter()
+        .map(|quote| quote.xdr_permyriad_per_icp)
+        .collect();
+    rates.sort_unstable();
+    Ok(IcpXdrConversionRate {
+        timestamp_seconds: agreeing
+            .iter()
+            .map(|quote| quote.timestamp_seconds)
+            .max()
+            .unwrap(),
+        xdr_permyriad_per_icp: rates[rates.len() / 2],
+    })
+}
+
+/// Returns the rate closest to `new_rate` that differs from `current_rate` by
+/// at most `MAX_RATE_CHANGE_PERMYRIAD`.
+pub fn bound_rate_change(current_rate: u64, new_rate: u64) -> u64 {
+    let max_change = (current_rate as u128 * MAX_RATE_CHANGE_PERMYRIAD as u128 / 10_000) as u64;
+    new_rate.clamp(
+        current_rate.saturating_sub(max_change),
+        current_rate.saturating_add(max_change),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(source: &str, timestamp_seconds: u64, xdr_permyriad_per_icp: u64) -> IcpXdrRateQuote {
+        IcpXdrRateQuote {
+            source: source.to_string(),
+            timestamp_seconds,
+            xdr_permyriad_per_icp,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_quotes_rejects_outliers_and_stale_quotes() {
+        let now = 1_000_000;
+        let quotes = vec![
+            quote("a", now - 60, 100_000),
+            quote("b", now - 30, 101_000),
+            quote("c", now - 10, 99_500),
+            // An outlier.
+            quote("d", now - 10, 150_000),
+            // Superseded by a more recent quote of the same source.
+            quote("c", now - 100, 120_000),
+            // Too old.
+            quote("e", now - MAX_QUOTE_AGE_SECONDS - 1, 100_000),
+            // From the future.
+            quote("f", now + 1, 100_000),
+        ];
+        assert_eq!(
+            aggregate_quotes(&quotes, now),
+            Ok(IcpXdrConversionRate {
+                timestamp_seconds: now - 10,
+                xdr_permyriad_per_icp: 100_000,
+            })
+        );
+
+        // Without source "b", the remaining sources don't suffice.
+        let quotes: Vec<_> = quotes.into_iter().filter(|q| q.source != "b").collect();
+        assert!(aggregate_quotes(&quotes, now).is_err());
+
+        // Sources that don't agree don't update the rate.
+        let quotes = vec![
+            quote("a", now, 100_000),
+            quote("b", now, 200_000),
+            quote("c", now, 300_000),
+        ];
+        assert!(aggregate_quotes(&quotes, now).is_err());
+    }
+
+    #[test]
+    fn test_bound_rate_change() {
+        assert_eq!(bound_rate_change(100_000, 105_000), 105_000);
+        assert_eq!(bound_rate_change(100_000, 200_000), 110_000);
+        assert_eq!(bound_rate_change(100_000, 1), 90_000);
+        assert_eq!(bound_rate_change(u64::MAX, u64::MAX), u64::MAX);
+    }
+}
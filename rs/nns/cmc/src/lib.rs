@@ -16,6 +16,34 @@ pub struct CyclesCanisterInitPayload {
     pub ledger_canister_id: CanisterId,
     pub governance_canister_id: CanisterId,
     pub minting_account_id: Option<AccountIdentifier>,
+    /// The canister the ICP/XDR conversion rate is fetched from. If not set,
+    /// the rate is only updated by proposals.
+    pub exchange_rate_canister_id: Option<CanisterId>,
+}
+
+/// Optional argument of upgrades of the cycles minting canister. Upgrades
+/// without an argument keep the current configuration.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct CyclesCanisterUpgradePayload {
+    /// The canister the ICP/XDR conversion rate is fetched from. `None`
+    /// disables fetching the rate.
+    pub exchange_rate_canister_id: Option<CanisterId>,
+}
+
+/// The method of an exchange-rate canister that returns its current ICP/XDR
+/// quotes. It takes no argument and returns a `Vec<IcpXdrRateQuote>`.
+pub const GET_ICP_XDR_RATE_QUOTES_METHOD: &str = "get_icp_xdr_rate_quotes";
+
+/// A quote of the ICP/XDR conversion rate from one of the sources of an
+/// exchange-rate canister.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct IcpXdrRateQuote {
+    /// The exchange or API the quote comes from.
+    pub source: String,
+    /// The time of the quote, expressed in UNIX epoch time in seconds.
+    pub timestamp_seconds: u64,
+    /// The number of 10,000ths of XDR that 1 ICP is worth.
+    pub xdr_permyriad_per_icp: u64,
 }
 
 pub const MEMO_CREATE_CANISTER: Memo = Memo(0x41455243); // == 'CREA'
@@ -65,6 +93,51 @@ pub type CreateCanisterResult = Result<CanisterId, (String, Option<BlockHeight>)
 /// an error, contains the index of the refund block.
 pub type TopUpCanisterResult = Result<(), (String, Option<BlockHeight>)>;
 
+/// Argument taken by the notify_top_up endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct NotifyTopUp {
+    /// The index of the ledger block in which ICP were sent to the
+    /// subaccount of `canister_id` with memo `MEMO_TOP_UP_CANISTER`.
+    pub block_index: BlockHeight,
+    pub canister_id: CanisterId,
+}
+
+/// Argument taken by the notify_create_canister endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct NotifyCreateCanister {
+    /// The index of the ledger block in which ICP were sent to the
+    /// subaccount of `controller` with memo `MEMO_CREATE_CANISTER`.
+    pub block_index: BlockHeight,
+    pub controller: PrincipalId,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum NotifyError {
+    /// Creating or topping up the canister failed and the payment was
+    /// refunded, minus the fees, in block `block_index`. No block is
+    /// created if the payment didn't cover the fees.
+    Refunded {
+        reason: String,
+        block_index: Option<BlockHeight>,
+    },
+    /// The block doesn't contain a payment for the requested action.
+    InvalidTransaction(String),
+    /// Payments in blocks below the given index can't be notified with this
+    /// endpoint.
+    TransactionTooOld(BlockHeight),
+    /// Another call is processing the payment.
+    Processing,
+    /// The payment couldn't be processed. The call can be retried.
+    Other { error_message: String },
+}
+
+/// The result of notify_top_up: the number of cycles the canister was
+/// topped up with.
+pub type NotifyTopUpResult = Result<Cycles, NotifyError>;
+
+/// The result of notify_create_canister: the id of the new canister.
+pub type NotifyCreateCanisterResult = Result<CanisterId, NotifyError>;
+
 pub struct TokensToCycles {
     /// Number of 1/10,000ths of XDR that 1 ICP is worth.
     pub xdr_permyriad_per_icp: u64,
@@ -1,14 +1,14 @@
 use std::collections::BTreeMap;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::sync::RwLock;
-use std::time::{Duration, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use candid::{CandidType, Encode};
 use cycles_minting_canister::*;
 use dfn_candid::{candid_one, CandidOne};
 use dfn_core::{
     api::{caller, set_certified_data},
-    over, over_init, stable, BytesS,
+    over, over_async, over_init, stable, BytesS,
 };
 use dfn_protobuf::protobuf;
 use ic_crypto_tree_hash::{
@@ -19,8 +19,9 @@ use ic_nns_constants::{GOVERNANCE_CANISTER_ID, REGISTRY_CANISTER_ID};
 use ic_types::ic00::{CanisterIdRecord, CanisterSettingsArgs, CreateCanisterArgs, Method, IC_00};
 use ic_types::{CanisterId, Cycles, PrincipalId, SubnetId};
 use ledger_canister::{
-    AccountIdentifier, BlockHeight, CyclesResponse, Memo, SendArgs, Tokens,
-    TransactionNotification, DEFAULT_TRANSFER_FEE,
+    AccountIdentifier, Block, BlockHeight, CyclesResponse, GetBlocksArgs, GetBlocksResult, Memo,
+    Operation, QueryBlocksResponse, SendArgs, Subaccount, Tokens, TransactionNotification,
+    DEFAULT_TRANSFER_FEE,
 };
 use on_wire::{FromWire, IntoWire, NewType};
 
@@ -29,6 +30,7 @@ use lazy_static::lazy_static;
 use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use serde::{Deserialize, Serialize};
 
+mod exchange_rate;
 mod limiter;
 
 /// The past 30 days are used for the average ICP/XDR rate.
@@ -36,6 +38,12 @@ const NUM_DAYS_FOR_ICP_XDR_AVERAGE: usize = 30;
 pub const LABEL_ICP_XDR_CONVERSION_RATE: &[u8] = b"ICP_XDR_CONVERSION_RATE";
 pub const LABEL_AVERAGE_ICP_XDR_CONVERSION_RATE: &[u8] = b"AVERAGE_ICP_XDR_CONVERSION_RATE";
 
+/// The maximum number of notifications kept in `blocks_notified`.
+const MAX_NOTIFY_HISTORY: usize = 1_000_000;
+/// The number of the oldest notifications removed from `blocks_notified`
+/// when it grows beyond `MAX_NOTIFY_HISTORY`.
+const MAX_NOTIFY_PURGE: usize = 100_000;
+
 #[derive(Serialize, Deserialize, Clone, CandidType, Eq, PartialEq, Debug)]
 struct State {
     ledger_canister_id: CanisterId,
@@ -71,6 +79,25 @@ struct State {
     limiter: limiter::Limiter,
 
     total_cycles_minted: Cycles,
+
+    /// The status of the payments this canister was notified about, by the
+    /// index of the ledger block holding the payment. Ensures that every
+    /// payment is used at most once.
+    blocks_notified: Option<BTreeMap<BlockHeight, NotificationStatus>>,
+
+    /// Payments in blocks below this index can only be notified by the
+    /// ledger, unless they are in `blocks_notified`. It is 0 for a new
+    /// canister, and the length of the ledger's chain right after the
+    /// upgrade for a canister that didn't record its notifications before.
+    /// It is raised above the notifications purged from `blocks_notified`.
+    first_notifiable_block: Option<BlockHeight>,
+
+    /// The canister the ICP/XDR conversion rate is fetched from.
+    exchange_rate_canister_id: Option<CanisterId>,
+
+    /// When the rate was last updated from the exchange-rate canister,
+    /// expressed in UNIX epoch time in seconds.
+    last_exchange_rate_update_seconds: Option<u64>,
 }
 
 impl State {
@@ -94,6 +121,47 @@ impl State {
             cycles_limit: 50_000_000_000_000_000u128.into(), // == 50 Pcycles/hour
             limiter: limiter::Limiter::new(resolution, max_age),
             total_cycles_minted: 0.into(),
+            blocks_notified: Some(BTreeMap::new()),
+            first_notifiable_block: None,
+            exchange_rate_canister_id: None,
+            last_exchange_rate_update_seconds: None,
+        }
+    }
+
+    /// Whether the rate is currently being updated from the exchange-rate
+    /// canister, in which case proposals can't set it.
+    fn is_exchange_rate_source_active(&self, now_seconds: u64) -> bool {
+        self.exchange_rate_canister_id.is_some()
+            && self
+                .last_exchange_rate_update_seconds
+                .map_or(false, |last_update| {
+                    now_seconds.saturating_sub(last_update)
+                        <= exchange_rate::MAX_SOURCE_STALENESS_SECONDS
+                })
+    }
+
+    /// Removes the `num_purged` oldest notifications that aren't being
+    /// processed once there are more than `max_history`, so that the state
+    /// stays bounded, and makes their blocks too old to be notified.
+    fn purge_old_notifications(&mut self, max_history: usize, num_purged: usize) {
+        let blocks_notified = self.blocks_notified.get_or_insert_with(BTreeMap::new);
+        if blocks_notified.len() <= max_history {
+            return;
+        }
+
+        let purged: Vec<BlockHeight> = blocks_notified
+            .iter()
+            .filter(|(_, status)| **status != NotificationStatus::Processing)
+            .map(|(block_index, _)| *block_index)
+            .take(num_purged)
+            .collect();
+        for block_index in &purged {
+            blocks_notified.remove(block_index);
+        }
+
+        if let Some(last_purged) = purged.last() {
+            let first_notifiable_block = self.first_notifiable_block.unwrap_or_default();
+            self.first_notifiable_block = Some(first_notifiable_block.max(last_purged + 1));
         }
     }
 
@@ -107,8 +175,79 @@ impl State {
     }
 }
 
+/// The status of the processing of a payment.
+#[derive(Serialize, Deserialize, Clone, CandidType, Eq, PartialEq, Debug)]
+enum NotificationStatus {
+    /// A call is processing the payment.
+    Processing,
+    /// The payment was used to top up a canister, or refunded.
+    NotifiedTopUp(NotifyTopUpResult),
+    /// The payment was used to create a canister, or refunded.
+    NotifiedCreateCanister(NotifyCreateCanisterResult),
+}
+
+impl NotificationStatus {
+    /// The response to a notification from the ledger about the payment.
+    fn to_cycles_response(&self) -> Result<CyclesResponse, String> {
+        match self {
+            NotificationStatus::Processing => {
+                Err("The payment is already being processed.".to_string())
+            }
+            NotificationStatus::NotifiedTopUp(Ok(_)) => Ok(CyclesResponse::ToppedUp(())),
+            NotificationStatus::NotifiedCreateCanister(Ok(canister_id)) => {
+                Ok(CyclesResponse::CanisterCreated(*canister_id))
+            }
+            NotificationStatus::NotifiedTopUp(Err(NotifyError::Refunded {
+                reason,
+                block_index,
+            }))
+            | NotificationStatus::NotifiedCreateCanister(Err(NotifyError::Refunded {
+                reason,
+                block_index,
+            })) => Ok(CyclesResponse::Refunded(reason.clone(), *block_index)),
+            NotificationStatus::NotifiedTopUp(Err(err))
+            | NotificationStatus::NotifiedCreateCanister(Err(err)) => Err(format!("{:?}", err)),
+        }
+    }
+}
+
+/// An ICP payment to a subaccount of this canister for creating or topping
+/// up a canister.
+struct Payment {
+    /// The account the payment is refunded to.
+    from: AccountIdentifier,
+    to_subaccount: Option<Subaccount>,
+    amount: Tokens,
+}
+
+impl Payment {
+    fn from_notification(tn: &TransactionNotification, to_subaccount: Subaccount) -> Self {
+        Self {
+            from: AccountIdentifier::new(tn.from, tn.from_subaccount),
+            to_subaccount: Some(to_subaccount),
+            amount: tn.amount,
+        }
+    }
+}
+
 lazy_static! {
     static ref STATE: RwLock<State> = RwLock::new(State::default());
+    // When the exchange-rate canister was last asked for quotes, and whether
+    // a request is in flight.
+    static ref LAST_EXCHANGE_RATE_FETCH: RwLock<Option<SystemTime>> = RwLock::new(None);
+    static ref IS_FETCHING_EXCHANGE_RATE: RwLock<bool> = RwLock::new(false);
+    // Whether the ledger is being asked for the first notifiable block.
+    static ref IS_FETCHING_FIRST_NOTIFIABLE_BLOCK: RwLock<bool> = RwLock::new(false);
+}
+
+/// Clears a flag when dropped, which also happens when a callback of the
+/// future holding it traps.
+struct ClearOnDrop(&'static RwLock<bool>);
+
+impl Drop for ClearOnDrop {
+    fn drop(&mut self) {
+        *self.0.write().unwrap() = false;
+    }
 }
 
 // Helper to print messages in yellow
@@ -139,6 +278,9 @@ fn init(args: CyclesCanisterInitPayload) {
     state.ledger_canister_id = args.ledger_canister_id;
     state.governance_canister_id = args.governance_canister_id;
     state.minting_account_id = args.minting_account_id;
+    state.exchange_rate_canister_id = args.exchange_rate_canister_id;
+    // A new canister wasn't notified about any payment yet.
+    state.first_notifiable_block = Some(0);
 }
 
 #[export_name = "canister_update set_authorized_subnetwork_list"]
@@ -281,6 +423,14 @@ fn set_icp_xdr_conversion_rate_() {
     over(
         candid_one,
         |proposed_conversion_rate: UpdateIcpXdrConversionRatePayload| -> Result<(), String> {
+            if state.is_exchange_rate_source_active(now_seconds()) {
+                return Err(format!(
+                    "The conversion rate is fetched from the exchange-rate canister {}. \
+                     Proposals can only set it when no rate was obtained from it for {} seconds.",
+                    state.exchange_rate_canister_id.unwrap(),
+                    exchange_rate::MAX_SOURCE_STALENESS_SECONDS
+                ));
+            }
             let rate: IcpXdrConversionRate = proposed_conversion_rate.into();
             update_recent_icp_xdr_rates(&rate, &mut state);
             set_icp_xdr_conversion_rate(rate, &mut state)
@@ -288,6 +438,123 @@ fn set_icp_xdr_conversion_rate_() {
     );
 }
 
+#[export_name = "canister_heartbeat"]
+fn canister_heartbeat() {
+    fetch_first_notifiable_block();
+    fetch_icp_xdr_rate();
+}
+
+/// Gets the first notifiable block from the ledger right after an upgrade
+/// from a version that didn't record its notifications, so that only the
+/// payments made before the upgrade can't be notified with `notify_top_up`
+/// and `notify_create_canister`.
+fn fetch_first_notifiable_block() {
+    let ledger_canister_id = {
+        let state = STATE.read().unwrap();
+        if state.first_notifiable_block.is_some() {
+            return;
+        }
+        state.ledger_canister_id
+    };
+
+    {
+        let mut is_fetching = IS_FETCHING_FIRST_NOTIFIABLE_BLOCK.write().unwrap();
+        if *is_fetching {
+            return;
+        }
+        *is_fetching = true;
+    }
+
+    dfn_core::api::futures::spawn(async move {
+        let _is_fetching = ClearOnDrop(&IS_FETCHING_FIRST_NOTIFIABLE_BLOCK);
+        if let Err(err) = get_first_notifiable_block(ledger_canister_id).await {
+            print(format!(
+                "[cycles] failed to get the first notifiable block: {:?}",
+                err
+            ));
+        }
+    });
+}
+
+fn fetch_icp_xdr_rate() {
+    let exchange_rate_canister_id = match STATE.read().unwrap().exchange_rate_canister_id {
+        Some(canister_id) => canister_id,
+        None => return,
+    };
+
+    let now = dfn_core::api::now();
+    {
+        let mut is_fetching = IS_FETCHING_EXCHANGE_RATE.write().unwrap();
+        let mut last_fetch = LAST_EXCHANGE_RATE_FETCH.write().unwrap();
+        if *is_fetching
+            || last_fetch.map_or(false, |last_fetch| {
+                last_fetch + exchange_rate::FETCH_INTERVAL > now
+            })
+        {
+            return;
+        }
+        *is_fetching = true;
+        *last_fetch = Some(now);
+    }
+
+    // canister_heartbeat must be synchronous, so we cannot .await the future
+    dfn_core::api::futures::spawn(async move {
+        let _is_fetching = ClearOnDrop(&IS_FETCHING_EXCHANGE_RATE);
+        if let Err(err) =
+            update_icp_xdr_rate_from_exchange_rate_canister(exchange_rate_canister_id).await
+        {
+            print(format!(
+                "[cycles] failed to update the conversion rate from {}: {}",
+                exchange_rate_canister_id, err
+            ));
+        }
+    });
+}
+
+/// Sets the conversion rate to the rate agreed on by the sources of the
+/// exchange-rate canister, moving it at most by
+/// `exchange_rate::MAX_RATE_CHANGE_PERMYRIAD`.
+async fn update_icp_xdr_rate_from_exchange_rate_canister(
+    exchange_rate_canister_id: CanisterId,
+) -> Result<(), String> {
+    let quotes: Vec<IcpXdrRateQuote> = dfn_core::api::call_with_cleanup(
+        exchange_rate_canister_id,
+        GET_ICP_XDR_RATE_QUOTES_METHOD,
+        candid_one,
+        (),
+    )
+    .await
+    .map_err(|(code, msg)| {
+        format!(
+            "Getting quotes failed with code {}: {:?}",
+            code.unwrap_or_default(),
+            msg
+        )
+    })?;
+
+    let now_seconds = now_seconds();
+    let mut rate = exchange_rate::aggregate_quotes(&quotes, now_seconds)?;
+
+    let mut state = STATE.write().unwrap();
+    if let Some(current_rate) = state.icp_xdr_conversion_rate.as_ref() {
+        rate.xdr_permyriad_per_icp = exchange_rate::bound_rate_change(
+            current_rate.xdr_permyriad_per_icp,
+            rate.xdr_permyriad_per_icp,
+        );
+    }
+    set_icp_xdr_conversion_rate(rate.clone(), &mut state)?;
+    update_recent_icp_xdr_rates(&rate, &mut state);
+    state.last_exchange_rate_update_seconds = Some(now_seconds);
+    Ok(())
+}
+
+fn now_seconds() -> u64 {
+    dfn_core::api::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("now is before the UNIX epoch")
+        .as_secs()
+}
+
 #[export_name = "canister_query get_average_icp_xdr_conversion_rate"]
 fn get_average_icp_xdr_conversion_rate_() {
     let state = STATE.read().unwrap();
@@ -473,92 +740,60 @@ async fn transaction_notification(tn: TransactionNotification) -> Result<CyclesR
         ));
     }
 
-    // Cloning is required here because of the asynchronous function call in the
-    // 'else' branch below.
-    let conversion_rate_option = STATE.read().unwrap().icp_xdr_conversion_rate.clone();
-
-    // Get the conversion rate from the registry if no value is set locally. If
-    // retrieval from the Registry fails then refund the transaction and log.
-    let xdr_permyriad_per_icp = if let Some(rate) = conversion_rate_option {
-        rate.xdr_permyriad_per_icp
-    } else {
-        match ic_nns_common::registry::get_icp_xdr_conversion_rate_record().await {
-            None => {
-                print(format!(
-                    "[cycles] No conversion rate found in CMC or Registry, transaction {:?} by {} refunded",
-                    tn, caller
-                ));
-                let refund_block = refund(&tn, &ledger_canister_id, Tokens::ZERO).await?;
-                return Ok(CyclesResponse::Refunded(
-                    "No conversion rate found in CMC or Registry, amount refunded".to_string(),
-                    refund_block,
-                ));
-            }
-            Some((rate_record, _)) => rate_record.xdr_permyriad_per_icp,
-        }
-    };
-
-    let cycles = TokensToCycles {
-        xdr_permyriad_per_icp,
-        cycles_per_xdr: STATE.read().unwrap().cycles_per_xdr,
-    }
-    .to_cycles(tn.amount);
-
     if tn.memo == MEMO_CREATE_CANISTER {
-        let controller = (&tn
+        let subaccount = tn
             .to_subaccount
-            .ok_or_else(|| "Reserving requires a principal.".to_string())?)
+            .ok_or_else(|| "Reserving requires a principal.".to_string())?;
+        let controller = (&subaccount)
             .try_into()
             .map_err(|err| format!("Cannot parse subaccount: {}", err))?;
 
-        print(format!(
-            "Creating canister with controller {} in block {} with {} cycles.",
-            controller, tn.block_height, cycles,
-        ));
+        let xdr_permyriad_per_icp = get_xdr_permyriad_per_icp().await;
+        let mut processing = match start_processing(tn.block_height) {
+            Ok(processing) => processing,
+            Err(status) => return status.to_cycles_response(),
+        };
 
         // Create the canister. If this fails, refund. Either way,
         // return a TransactionNotificationResult so that the
         // notification cannot be retried.
-        let res = create_canister(controller, cycles).await;
-
-        let refund_block = burn_or_refund(
-            res.is_ok(),
-            CREATE_CANISTER_REFUND_FEE,
-            &tn,
-            &ledger_canister_id,
-        )
-        .await?;
+        let payment = Payment::from_notification(&tn, subaccount);
+        let result =
+            create_canister_or_refund(controller, &payment, xdr_permyriad_per_icp, &mut processing)
+                .await;
+        processing.finish(
+            result
+                .clone()
+                .ok()
+                .map(NotificationStatus::NotifiedCreateCanister),
+        );
 
-        Ok(match res {
-            Ok(cid) => CyclesResponse::CanisterCreated(cid),
-            Err(err) => CyclesResponse::Refunded(err, refund_block),
-        })
+        NotificationStatus::NotifiedCreateCanister(result?).to_cycles_response()
     } else if tn.memo == MEMO_TOP_UP_CANISTER {
-        let canister_id = (&tn
+        let subaccount = tn
             .to_subaccount
-            .ok_or_else(|| "Topping up requires a subaccount.".to_string())?)
+            .ok_or_else(|| "Topping up requires a subaccount.".to_string())?;
+        let canister_id = (&subaccount)
             .try_into()
             .map_err(|err| format!("Cannot parse subaccount: {}", err))?;
 
-        print(format!(
-            "Topping up canister {} by {} cycles.",
-            canister_id, cycles
-        ));
-
-        let res = deposit_cycles(canister_id, cycles).await;
+        let xdr_permyriad_per_icp = get_xdr_permyriad_per_icp().await;
+        let mut processing = match start_processing(tn.block_height) {
+            Ok(processing) => processing,
+            Err(status) => return status.to_cycles_response(),
+        };
 
-        let refund_block = burn_or_refund(
-            res.is_ok(),
-            TOP_UP_CANISTER_REFUND_FEE,
-            &tn,
-            &ledger_canister_id,
+        let payment = Payment::from_notification(&tn, subaccount);
+        let result = top_up_or_refund(
+            canister_id,
+            &payment,
+            xdr_permyriad_per_icp,
+            &mut processing,
         )
-        .await?;
+        .await;
+        processing.finish(result.clone().ok().map(NotificationStatus::NotifiedTopUp));
 
-        Ok(match res {
-            Ok(()) => CyclesResponse::ToppedUp(()),
-            Err(err) => CyclesResponse::Refunded(err, refund_block),
-        })
+        NotificationStatus::NotifiedTopUp(result?).to_cycles_response()
     } else {
         Err(format!(
             "Don't know what to do with transaction with memo {}.",
@@ -567,56 +802,475 @@ async fn transaction_notification(tn: TransactionNotification) -> Result<CyclesR
     }
 }
 
+#[export_name = "canister_update notify_top_up"]
+fn notify_top_up_() {
+    over_async(candid_one, notify_top_up)
+}
+
+/// Tops up a canister with the cycles bought by the payment in the given
+/// block, which must send ICP to the subaccount of the canister with memo
+/// `MEMO_TOP_UP_CANISTER`. Unlike `transaction_notification`, anyone can
+/// call this, and calling it again for the same block returns the outcome of
+/// the first call.
+async fn notify_top_up(
+    NotifyTopUp {
+        block_index,
+        canister_id,
+    }: NotifyTopUp,
+) -> NotifyTopUpResult {
+    let payment =
+        fetch_payment_for_notification(block_index, MEMO_TOP_UP_CANISTER, (&canister_id).into())
+            .await?;
+
+    let xdr_permyriad_per_icp = get_xdr_permyriad_per_icp().await;
+    let mut processing = match start_processing(block_index) {
+        Ok(processing) => processing,
+        Err(NotificationStatus::NotifiedTopUp(result)) => return result,
+        Err(NotificationStatus::NotifiedCreateCanister(_)) => {
+            return Err(NotifyError::InvalidTransaction(format!(
+                "The payment in block {} was used to create a canister.",
+                block_index
+            )))
+        }
+        Err(NotificationStatus::Processing) => return Err(NotifyError::Processing),
+    };
+
+    print(format!(
+        "[cycles] notified about top-up of canister {} in block {} by {}",
+        canister_id,
+        block_index,
+        caller()
+    ));
+
+    let result = top_up_or_refund(
+        canister_id,
+        &payment,
+        xdr_permyriad_per_icp,
+        &mut processing,
+    )
+    .await;
+    processing.finish(result.clone().ok().map(NotificationStatus::NotifiedTopUp));
+
+    result.map_err(|error_message| NotifyError::Other { error_message })?
+}
+
+#[export_name = "canister_update notify_create_canister"]
+fn notify_create_canister_() {
+    over_async(candid_one, notify_create_canister)
+}
+
+/// Creates a canister with the cycles bought by the payment in the given
+/// block, which must send ICP to the subaccount of the controller with memo
+/// `MEMO_CREATE_CANISTER`. Calling it again for the same block returns the
+/// outcome of the first call.
+async fn notify_create_canister(
+    NotifyCreateCanister {
+        block_index,
+        controller,
+    }: NotifyCreateCanister,
+) -> NotifyCreateCanisterResult {
+    let payment =
+        fetch_payment_for_notification(block_index, MEMO_CREATE_CANISTER, (&controller).into())
+            .await?;
+
+    let xdr_permyriad_per_icp = get_xdr_permyriad_per_icp().await;
+    let mut processing = match start_processing(block_index) {
+        Ok(processing) => processing,
+        Err(NotificationStatus::NotifiedCreateCanister(result)) => return result,
+        Err(NotificationStatus::NotifiedTopUp(_)) => {
+            return Err(NotifyError::InvalidTransaction(format!(
+                "The payment in block {} was used to top up a canister.",
+                block_index
+            )))
+        }
+        Err(NotificationStatus::Processing) => return Err(NotifyError::Processing),
+    };
+
+    print(format!(
+        "[cycles] notified about creation of a canister controlled by {} in block {} by {}",
+        controller,
+        block_index,
+        caller()
+    ));
+
+    let result =
+        create_canister_or_refund(controller, &payment, xdr_permyriad_per_icp, &mut processing)
+            .await;
+    processing.finish(
+        result
+            .clone()
+            .ok()
+            .map(NotificationStatus::NotifiedCreateCanister),
+    );
+
+    result.map_err(|error_message| NotifyError::Other { error_message })?
+}
+
+/// Marks the payment in the given block as being processed, unless it was
+/// notified before, in which case its status is returned.
+fn start_processing(block_index: BlockHeight) -> Result<Processing, NotificationStatus> {
+    let mut state = STATE.write().unwrap();
+    let blocks_notified = state.blocks_notified.get_or_insert_with(BTreeMap::new);
+    if let Some(status) = blocks_notified.get(&block_index) {
+        return Err(status.clone());
+    }
+    blocks_notified.insert(block_index, NotificationStatus::Processing);
+    Ok(Processing {
+        block_index,
+        finished: false,
+    })
+}
+
+/// The processing of the payment in a block. If it is dropped before its
+/// outcome is recorded, because a callback trapped, the payment can be
+/// notified again. The outcome is therefore recorded as soon as the cycles
+/// bought by the payment were sent, and refunds are the last step.
+struct Processing {
+    block_index: BlockHeight,
+    finished: bool,
+}
+
+impl Processing {
+    /// Records the outcome of processing the payment. If there is none, the
+    /// processing failed without any effect and the payment can be notified
+    /// again.
+    fn finish(&mut self, status: Option<NotificationStatus>) {
+        let mut state = STATE.write().unwrap();
+        let blocks_notified = state.blocks_notified.get_or_insert_with(BTreeMap::new);
+        match status {
+            Some(status) => blocks_notified.insert(self.block_index, status),
+            None => blocks_notified.remove(&self.block_index),
+        };
+        state.purge_old_notifications(MAX_NOTIFY_HISTORY, MAX_NOTIFY_PURGE);
+        self.finished = true;
+    }
+}
+
+impl Drop for Processing {
+    fn drop(&mut self) {
+        if !self.finished {
+            print(format!(
+                "[cycles] processing the payment in block {} was interrupted",
+                self.block_index
+            ));
+            self.finish(None);
+        }
+    }
+}
+
+/// Fetches the block with the given index from the ledger and checks that
+/// it holds a payment to the given subaccount of this canister with the
+/// given memo.
+async fn fetch_payment_for_notification(
+    block_index: BlockHeight,
+    memo: Memo,
+    to_subaccount: Subaccount,
+) -> Result<Payment, NotifyError> {
+    let ledger_canister_id = STATE.read().unwrap().ledger_canister_id;
+
+    // This canister doesn't know which payments it was notified about by the
+    // ledger before it started recording notifications, so these can only be
+    // notified through the ledger.
+    let first_notifiable_block = get_first_notifiable_block(ledger_canister_id).await?;
+    let already_notified = STATE
+        .read()
+        .unwrap()
+        .blocks_notified
+        .as_ref()
+        .map_or(false, |blocks| blocks.contains_key(&block_index));
+    if block_index < first_notifiable_block && !already_notified {
+        return Err(NotifyError::TransactionTooOld(first_notifiable_block));
+    }
+
+    let block = fetch_block(ledger_canister_id, block_index).await?;
+    let (from, to, amount) = match block.transaction.operation {
+        Operation::Transfer {
+            from, to, amount, ..
+        } => (from, to, amount),
+        _ => {
+            return Err(NotifyError::InvalidTransaction(format!(
+                "Block {} doesn't contain a transfer.",
+                block_index
+            )))
+        }
+    };
+    if block.transaction.memo != memo {
+        return Err(NotifyError::InvalidTransaction(format!(
+            "The transfer in block {} has memo {} instead of {}.",
+            block_index, block.transaction.memo.0, memo.0
+        )));
+    }
+    let expected_to = AccountIdentifier::new(dfn_core::api::id().get(), Some(to_subaccount));
+    if to != expected_to {
+        return Err(NotifyError::InvalidTransaction(format!(
+            "The transfer in block {} is to {} instead of {}.",
+            block_index, to, expected_to
+        )));
+    }
+
+    Ok(Payment {
+        from,
+        to_subaccount: Some(to_subaccount),
+        amount,
+    })
+}
+
+/// Returns the index of the first block whose payment can be notified with
+/// `notify_top_up` or `notify_create_canister`. It is the length of the
+/// ledger's chain when the first such call was made.
+async fn get_first_notifiable_block(
+    ledger_canister_id: CanisterId,
+) -> Result<BlockHeight, NotifyError> {
+    if let Some(block_index) = STATE.read().unwrap().first_notifiable_block {
+        return Ok(block_index);
+    }
+
+    let response: QueryBlocksResponse = dfn_core::api::call_with_cleanup(
+        ledger_canister_id,
+        "query_blocks",
+        candid_one,
+        GetBlocksArgs {
+            start: 0,
+            length: 0,
+        },
+    )
+    .await
+    .map_err(|(code, msg)| NotifyError::Other {
+        error_message: format!(
+            "Getting the chain length from the ledger failed with code {}: {:?}",
+            code.unwrap_or_default(),
+            msg
+        ),
+    })?;
+
+    // Another call may have set it in the meantime.
+    Ok(*STATE
+        .write()
+        .unwrap()
+        .first_notifiable_block
+        .get_or_insert(response.chain_length))
+}
+
+/// Fetches the block with the given index from the ledger, or from the
+/// archive node that holds it.
+async fn fetch_block(
+    ledger_canister_id: CanisterId,
+    block_index: BlockHeight,
+) -> Result<Block, NotifyError> {
+    let fetch_error = |(code, msg): (Option<i32>, String)| NotifyError::Other {
+        error_message: format!(
+            "Fetching block {} failed with code {}: {:?}",
+            block_index,
+            code.unwrap_or_default(),
+            msg
+        ),
+    };
+
+    let response: QueryBlocksResponse = dfn_core::api::call_with_cleanup(
+        ledger_canister_id,
+        "query_blocks",
+        candid_one,
+        GetBlocksArgs {
+            start: block_index,
+            length: 1,
+        },
+    )
+    .await
+    .map_err(fetch_error)?;
+
+    let block = if response.first_block_index == block_index && !response.blocks.is_empty() {
+        response.blocks.into_iter().next()
+    } else if let Some(range) = response
+        .archived_blocks
+        .into_iter()
+        .find(|range| range.start <= block_index && block_index < range.start + range.length)
+    {
+        let result: GetBlocksResult = dfn_core::api::call_with_cleanup(
+            range.callback.canister_id,
+            &range.callback.method,
+            candid_one,
+            GetBlocksArgs {
+                start: block_index,
+                length: 1,
+            },
+        )
+        .await
+        .map_err(fetch_error)?;
+        result
+            .map_err(|err| NotifyError::Other {
+                error_message: format!("Fetching archived block {} failed: {:?}", block_index, err),
+            })?
+            .blocks
+            .into_iter()
+            .next()
+    } else {
+        None
+    };
+
+    let block = block.ok_or_else(|| {
+        NotifyError::InvalidTransaction(format!("Block {} not found.", block_index))
+    })?;
+    Block::try_from(block).map_err(|err| NotifyError::Other {
+        error_message: format!("Cannot decode block {}: {}", block_index, err),
+    })
+}
+
+/// Returns the number of 1/10,000ths of XDR that 1 ICP is worth, getting the
+/// conversion rate from the registry if no value is set locally.
+async fn get_xdr_permyriad_per_icp() -> Option<u64> {
+    // Cloning is required here because of the asynchronous function call in
+    // the 'else' branch below.
+    let conversion_rate_option = STATE.read().unwrap().icp_xdr_conversion_rate.clone();
+
+    if let Some(rate) = conversion_rate_option {
+        Some(rate.xdr_permyriad_per_icp)
+    } else {
+        ic_nns_common::registry::get_icp_xdr_conversion_rate_record()
+            .await
+            .map(|(rate_record, _)| rate_record.xdr_permyriad_per_icp)
+    }
+}
+
+/// Converts the payment to cycles. If there is no conversion rate, the
+/// payment is refunded and the refund is returned as the error.
+async fn payment_to_cycles(
+    payment: &Payment,
+    xdr_permyriad_per_icp: Option<u64>,
+) -> Result<Result<Cycles, NotifyError>, String> {
+    let xdr_permyriad_per_icp = match xdr_permyriad_per_icp {
+        Some(rate) => rate,
+        None => {
+            print(format!(
+                "[cycles] No conversion rate found in CMC or Registry, payment from {} refunded",
+                payment.from
+            ));
+            let refund_block = refund(payment, Tokens::ZERO).await?;
+            return Ok(Err(NotifyError::Refunded {
+                reason: "No conversion rate found in CMC or Registry, amount refunded".to_string(),
+                block_index: refund_block,
+            }));
+        }
+    };
+
+    Ok(Ok(TokensToCycles {
+        xdr_permyriad_per_icp,
+        cycles_per_xdr: STATE.read().unwrap().cycles_per_xdr,
+    }
+    .to_cycles(payment.amount)))
+}
+
+/// Creates a canister with the cycles bought by the payment, burning the
+/// payment if that succeeds and refunding it otherwise. Only fails if the
+/// refund fails.
+async fn create_canister_or_refund(
+    controller: PrincipalId,
+    payment: &Payment,
+    xdr_permyriad_per_icp: Option<u64>,
+    processing: &mut Processing,
+) -> Result<NotifyCreateCanisterResult, String> {
+    let cycles = match payment_to_cycles(payment, xdr_permyriad_per_icp).await? {
+        Ok(cycles) => cycles,
+        Err(err) => return Ok(Err(err)),
+    };
+
+    print(format!(
+        "Creating canister with controller {} with {} cycles.",
+        controller, cycles,
+    ));
+
+    let res = create_canister(controller, cycles).await;
+    if let Ok(canister_id) = res {
+        processing.finish(Some(NotificationStatus::NotifiedCreateCanister(Ok(
+            canister_id,
+        ))));
+    }
+
+    let refund_block = burn_or_refund(res.is_ok(), CREATE_CANISTER_REFUND_FEE, payment).await?;
+
+    Ok(res.map_err(|reason| NotifyError::Refunded {
+        reason,
+        block_index: refund_block,
+    }))
+}
+
+/// Tops up a canister with the cycles bought by the payment, burning the
+/// payment if that succeeds and refunding it otherwise. Only fails if the
+/// refund fails.
+async fn top_up_or_refund(
+    canister_id: CanisterId,
+    payment: &Payment,
+    xdr_permyriad_per_icp: Option<u64>,
+    processing: &mut Processing,
+) -> Result<NotifyTopUpResult, String> {
+    let cycles = match payment_to_cycles(payment, xdr_permyriad_per_icp).await? {
+        Ok(cycles) => cycles,
+        Err(err) => return Ok(Err(err)),
+    };
+
+    print(format!(
+        "Topping up canister {} by {} cycles.",
+        canister_id, cycles
+    ));
+
+    let res = deposit_cycles(canister_id, cycles).await;
+    if res.is_ok() {
+        processing.finish(Some(NotificationStatus::NotifiedTopUp(Ok(cycles))));
+    }
+
+    let refund_block = burn_or_refund(res.is_ok(), TOP_UP_CANISTER_REFUND_FEE, payment).await?;
+
+    Ok(res
+        .map(|()| cycles)
+        .map_err(|reason| NotifyError::Refunded {
+            reason,
+            block_index: refund_block,
+        }))
+}
+
 async fn burn_or_refund(
     is_ok: bool,
     extra_fee: Tokens,
-    tn: &TransactionNotification,
-    ledger_canister_id: &CanisterId,
+    payment: &Payment,
 ) -> Result<Option<BlockHeight>, String> {
     if is_ok {
-        if let Ok(amount) = tn.amount - DEFAULT_TRANSFER_FEE {
-            burn_and_log(tn, amount, ledger_canister_id).await;
+        if let Ok(amount) = payment.amount - DEFAULT_TRANSFER_FEE {
+            burn_and_log(payment, amount).await;
         }
         Ok(None)
     } else {
-        refund(tn, ledger_canister_id, extra_fee).await
+        refund(payment, extra_fee).await
     }
 }
 
 /// Burn funds and log but ignore any errors. When canister creation /
 /// topping up succeeded, we don't want to reject the transaction
 /// notification because then it could be retried.
-async fn burn_and_log(
-    tn: &TransactionNotification,
-    amount: Tokens,
-    ledger_canister_id: &CanisterId,
-) {
-    if let Err(err) = burn(tn, amount, ledger_canister_id).await {
-        print(format!("Burning {} ICPTs failed: {}", tn.amount, err));
+async fn burn_and_log(payment: &Payment, amount: Tokens) {
+    if let Err(err) = burn(payment, amount).await {
+        print(format!("Burning {} ICPTs failed: {}", payment.amount, err));
     }
 }
 
 /// Burn the funds for canister creation or top up to prevent
 /// accumulating a lot of dead accounts on the ledger.
-async fn burn(
-    tn: &TransactionNotification,
-    amount: Tokens,
-    ledger_canister_id: &CanisterId,
-) -> Result<(), String> {
-    let minting_account_id = STATE.read().unwrap().minting_account_id;
+async fn burn(payment: &Payment, amount: Tokens) -> Result<(), String> {
+    let (ledger_canister_id, minting_account_id) = {
+        let state = STATE.read().unwrap();
+        (state.ledger_canister_id, state.minting_account_id)
+    };
 
     if let Some(minting_account_id) = minting_account_id {
         let send_args = SendArgs {
             memo: Memo::default(),
             amount,
             fee: Tokens::ZERO,
-            from_subaccount: tn.to_subaccount,
+            from_subaccount: payment.to_subaccount,
             to: minting_account_id,
             created_at_time: None,
         };
 
         let res: Result<BlockHeight, (Option<i32>, String)> = dfn_core::api::call_with_cleanup(
-            *ledger_canister_id,
+            ledger_canister_id,
             "send_pb",
             protobuf,
             send_args.clone(),
@@ -627,7 +1281,7 @@ async fn burn(
             format!(
                 "Burning of {} ICPTs from {} failed with code {}: {:?}",
                 send_args.amount,
-                tn.from,
+                payment.from,
                 code.unwrap_or_default(),
                 msg
             )
@@ -635,7 +1289,7 @@ async fn burn(
 
         print(format!(
             "Burning of {} ICPTs from {} done in block {}.",
-            send_args.amount, tn.from, block
+            send_args.amount, payment.from, block
         ));
     }
 
@@ -646,15 +1300,11 @@ async fn burn(
 /// minus the transaction fee (which is gone) and the fee for the
 /// action (which is burned). Returns the index of the block in which
 /// the refund was done.
-async fn refund(
-    tn: &TransactionNotification,
-    ledger_canister_id: &CanisterId,
-    extra_fee: Tokens,
-) -> Result<Option<BlockHeight>, String> {
+async fn refund(payment: &Payment, extra_fee: Tokens) -> Result<Option<BlockHeight>, String> {
     let mut refund_block_index = None;
 
     // Don't refund a negative amount.
-    let amount_minus_fee = if let Ok(amount) = tn.amount - DEFAULT_TRANSFER_FEE {
+    let amount_minus_fee = if let Ok(amount) = payment.amount - DEFAULT_TRANSFER_FEE {
         amount
     } else {
         return Ok(None);
@@ -668,18 +1318,26 @@ async fn refund(
 
     assert_eq!(Ok(amount_minus_fee), refunded + burned);
 
+    // Burn first, so that the payment is still there if burning is
+    // interrupted and the payment is processed again.
+    if burned != Tokens::ZERO {
+        burn_and_log(payment, burned).await;
+    }
+
     if refunded != Tokens::ZERO {
+        let ledger_canister_id = STATE.read().unwrap().ledger_canister_id;
+
         let send_args = SendArgs {
             memo: Memo::default(),
             amount: refunded,
             fee: DEFAULT_TRANSFER_FEE,
-            from_subaccount: tn.to_subaccount,
-            to: AccountIdentifier::new(tn.from, tn.from_subaccount),
+            from_subaccount: payment.to_subaccount,
+            to: payment.from,
             created_at_time: None,
         };
 
         let res: Result<BlockHeight, (Option<i32>, String)> = dfn_core::api::call_with_cleanup(
-            *ledger_canister_id,
+            ledger_canister_id,
             "send_pb",
             protobuf,
             send_args.clone(),
@@ -703,10 +1361,6 @@ async fn refund(
         refund_block_index = Some(block);
     }
 
-    if burned != Tokens::ZERO {
-        burn_and_log(tn, burned, ledger_canister_id).await;
-    }
-
     Ok(refund_block_index)
 }
 
@@ -880,14 +1534,26 @@ fn pre_upgrade() {
 
 #[export_name = "canister_post_upgrade"]
 fn post_upgrade() {
-    over_init(|_: BytesS| {
+    over_init(|BytesS(arg)| {
         let bytes = stable::get();
         print(format!(
             "[cycles] deserializing state after upgrade ({} bytes)",
             bytes.len(),
         ));
 
-        *STATE.write().unwrap() = State::decode(&bytes).unwrap();
+        let mut state = State::decode(&bytes).unwrap();
+
+        if !arg.is_empty() {
+            let payload: CyclesCanisterUpgradePayload = candid::decode_one(&arg)
+                .unwrap_or_else(|err| panic!("Decoding the upgrade argument failed: {}", err));
+            print(format!(
+                "[cycles] setting exchange-rate canister to {:?}",
+                payload.exchange_rate_canister_id
+            ));
+            state.exchange_rate_canister_id = payload.exchange_rate_canister_id;
+        }
+
+        *STATE.write().unwrap() = state;
     })
 }
 
@@ -909,6 +1575,19 @@ mod tests {
         );
         state.default_subnets = vec![SubnetId::from(PrincipalId::new_subnet_test_id(123))];
         state.total_cycles_minted = 1234.into();
+        let blocks_notified = state.blocks_notified.as_mut().unwrap();
+        blocks_notified.insert(7, NotificationStatus::Processing);
+        blocks_notified.insert(8, NotificationStatus::NotifiedTopUp(Ok(1_000.into())));
+        blocks_notified.insert(
+            9,
+            NotificationStatus::NotifiedCreateCanister(Err(NotifyError::Refunded {
+                reason: "No subnets in which to create a canister.".to_string(),
+                block_index: Some(10),
+            })),
+        );
+        state.first_notifiable_block = Some(5);
+        state.exchange_rate_canister_id = Some(CanisterId::from_u64(11));
+        state.last_exchange_rate_update_seconds = Some(1_632_700_800);
 
         let bytes = state.encode();
 
@@ -917,6 +1596,76 @@ mod tests {
         assert_eq!(state, state2);
     }
 
+    #[test]
+    fn test_proposals_set_the_rate_when_the_exchange_rate_source_is_stale() {
+        let mut state = State::default();
+        let now = 1_632_700_800;
+        assert!(!state.is_exchange_rate_source_active(now));
+
+        state.exchange_rate_canister_id = Some(CanisterId::from_u64(11));
+        assert!(!state.is_exchange_rate_source_active(now));
+
+        state.last_exchange_rate_update_seconds = Some(now - 60);
+        assert!(state.is_exchange_rate_source_active(now));
+        assert!(!state
+            .is_exchange_rate_source_active(now + exchange_rate::MAX_SOURCE_STALENESS_SECONDS));
+
+        state.exchange_rate_canister_id = None;
+        assert!(!state.is_exchange_rate_source_active(now));
+    }
+
+    #[test]
+    fn test_purge_old_notifications() {
+        let mut state = State::default();
+        state.first_notifiable_block = Some(2);
+        let blocks_notified = state.blocks_notified.as_mut().unwrap();
+        blocks_notified.insert(3, NotificationStatus::Processing);
+        for block_index in 4..10 {
+            blocks_notified.insert(block_index, NotificationStatus::NotifiedTopUp(Ok(1.into())));
+        }
+
+        state.purge_old_notifications(7, 3);
+        assert_eq!(state.blocks_notified.as_ref().unwrap().len(), 7);
+        assert_eq!(state.first_notifiable_block, Some(2));
+
+        state.purge_old_notifications(5, 3);
+        // Payments being processed are kept.
+        assert_eq!(
+            state
+                .blocks_notified
+                .as_ref()
+                .unwrap()
+                .keys()
+                .copied()
+                .collect::<Vec<_>>(),
+            vec![3, 7, 8, 9]
+        );
+        assert_eq!(state.first_notifiable_block, Some(7));
+    }
+
+    #[test]
+    fn test_notified_payments_are_reported_to_the_ledger() {
+        let canister_id = CanisterId::from_u64(42);
+        assert!(matches!(
+            NotificationStatus::NotifiedCreateCanister(Ok(canister_id)).to_cycles_response(),
+            Ok(CyclesResponse::CanisterCreated(id)) if id == canister_id
+        ));
+        assert!(matches!(
+            NotificationStatus::NotifiedTopUp(Ok(1_000.into())).to_cycles_response(),
+            Ok(CyclesResponse::ToppedUp(()))
+        ));
+        assert!(matches!(
+            NotificationStatus::NotifiedTopUp(Err(NotifyError::Refunded {
+                reason: "failed".to_string(),
+                block_index: Some(3),
+            }))
+            .to_cycles_response(),
+            Ok(CyclesResponse::Refunded(reason, Some(3))) if reason == "failed"
+        ));
+        // The ledger can retry the notification once the payment is processed.
+        assert!(NotificationStatus::Processing.to_cycles_response().is_err());
+    }
+
     #[test]
     // The function tests if the average ICP/XDR price is computed correctly.
     fn test_average_icp_xdr_price() {
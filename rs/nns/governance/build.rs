@@ -605,6 +605,26 @@ fn main() {
         "ic_nns_governance.pb.v1.ListKnownNeuronsResponse",
         "#[derive(candid::CandidType, candid::Deserialize)]",
     );
+    config.type_attribute(
+        "ic_nns_governance.pb.v1.FollowingReport",
+        "#[derive(candid::CandidType, candid::Deserialize)]",
+    );
+    config.type_attribute(
+        "ic_nns_governance.pb.v1.TopicFollowingReport",
+        "#[derive(candid::CandidType, candid::Deserialize)]",
+    );
+    config.type_attribute(
+        "ic_nns_governance.pb.v1.FolloweeReport",
+        "#[derive(candid::CandidType, candid::Deserialize)]",
+    );
+    config.type_attribute(
+        "ic_nns_governance.pb.v1.FollowCycle",
+        "#[derive(candid::CandidType, candid::Deserialize)]",
+    );
+    config.type_attribute(
+        "ic_nns_governance.pb.v1.VotingPath",
+        "#[derive(candid::CandidType, candid::Deserialize)]",
+    );
     config.type_attribute(
         "ic_nns_governance.pb.v1.Governance",
         [
@@ -48,10 +48,10 @@ use ic_nns_governance::{
             ClaimOrRefresh, Command, RegisterVote,
         },
         manage_neuron_response, ClaimOrRefreshNeuronFromAccount,
-        ClaimOrRefreshNeuronFromAccountResponse, ExecuteNnsFunction, Governance as GovernanceProto,
-        GovernanceError, ListKnownNeuronsResponse, ListNeurons, ListNeuronsResponse,
-        ListProposalInfo, ListProposalInfoResponse, ManageNeuron, ManageNeuronResponse, Neuron,
-        NeuronInfo, NnsFunction, Proposal, ProposalInfo, Vote,
+        ClaimOrRefreshNeuronFromAccountResponse, ExecuteNnsFunction, FollowingReport,
+        Governance as GovernanceProto, GovernanceError, ListKnownNeuronsResponse, ListNeurons,
+        ListNeuronsResponse, ListProposalInfo, ListProposalInfoResponse, ManageNeuron,
+        ManageNeuronResponse, Neuron, NeuronInfo, NnsFunction, Proposal, ProposalInfo, Vote,
     },
 };
 
@@ -610,6 +610,19 @@ fn get_full_neuron_(neuron_id: NeuronId) -> Result<Neuron, GovernanceError> {
     governance().get_full_neuron(&NeuronIdProto::from(neuron_id), &caller())
 }
 
+/// Returns a report on how the neuron with the given id follows other
+/// neurons.
+#[export_name = "canister_query get_following_report"]
+fn get_following_report() {
+    println!("{}get_following_report", LOG_PREFIX);
+    over(candid_one, get_following_report_)
+}
+
+#[candid_method(query, rename = "get_following_report")]
+fn get_following_report_(neuron_id: NeuronId) -> Result<FollowingReport, GovernanceError> {
+    governance().get_following_report(&NeuronIdProto::from(neuron_id), &caller())
+}
+
 /// Returns the public neuron info corresponding to the neuron id.
 #[export_name = "canister_query get_neuron_info"]
 fn get_neuron_info() {
@@ -5,6 +5,7 @@ use std::convert::TryInto;
 use std::fmt;
 use std::string::ToString;
 
+use crate::nns_function_payload::NnsFunctionPayload;
 use crate::pb::v1::{
     add_or_remove_node_provider::Change,
     governance::neuron_in_flight_command::Command as InFlightCommand,
@@ -20,26 +21,24 @@ use crate::pb::v1::{
     neuron::Followees,
     proposal,
     reward_node_provider::RewardMode,
-    Ballot, BallotInfo, ExecuteNnsFunction, Governance as GovernanceProto, GovernanceError,
-    KnownNeuron, ListKnownNeuronsResponse, ListNeurons, ListNeuronsResponse, ListProposalInfo,
-    ListProposalInfoResponse, ManageNeuron, ManageNeuronResponse, NetworkEconomics, Neuron,
-    NeuronInfo, NeuronState, NnsFunction, NodeProvider, Proposal, ProposalData, ProposalInfo,
-    ProposalRewardStatus, ProposalStatus, RewardEvent, RewardNodeProvider, RewardNodeProviders,
-    Tally, Topic, UpdateNodeProvider, Vote,
+    Ballot, BallotInfo, ExecuteNnsFunction, FollowCycle, FolloweeReport, FollowingReport,
+    Governance as GovernanceProto, GovernanceError, KnownNeuron, ListKnownNeuronsResponse,
+    ListNeurons, ListNeuronsResponse, ListProposalInfo, ListProposalInfoResponse, ManageNeuron,
+    ManageNeuronResponse, NetworkEconomics, Neuron, NeuronInfo, NeuronState, NnsFunction,
+    NodeProvider, Proposal, ProposalData, ProposalInfo, ProposalRewardStatus, ProposalStatus,
+    RewardEvent, RewardNodeProvider, RewardNodeProviders, Tally, Topic, TopicFollowingReport,
+    UpdateNodeProvider, Vote, VotingPath,
 };
-use candid::Decode;
 use dfn_protobuf::ToProto;
 use ic_base_types::{CanisterId, PrincipalId};
 use ic_crypto_sha::Sha256;
 use ic_nns_common::pb::v1::{NeuronId, ProposalId};
-use ic_nns_common::types::UpdateIcpXdrConversionRatePayload;
 use ic_nns_constants::{
     CYCLES_MINTING_CANISTER_ID, GENESIS_TOKEN_CANISTER_ID, GOVERNANCE_CANISTER_ID,
     LIFELINE_CANISTER_ID, REGISTRY_CANISTER_ID, ROOT_CANISTER_ID,
 };
-use ic_protobuf::registry::dc::v1::AddOrRemoveDataCentersProposalPayload;
 use ledger_canister::{AccountIdentifier, Subaccount, DEFAULT_TRANSFER_FEE};
-use registry_canister::mutations::do_add_node_operator::AddNodeOperatorPayload;
+use strum::IntoEnumIterator;
 
 use async_trait::async_trait;
 
@@ -121,6 +120,10 @@ pub const MAX_NUMBER_OF_NEURONS: usize = 200_000;
 /// The maximum number results returned by the method `list_proposals`.
 pub const MAX_LIST_PROPOSAL_RESULTS: u32 = 100;
 
+/// The maximum number of voting paths, per topic, in the report returned by
+/// the method `get_following_report`.
+pub const MAX_FOLLOWING_REPORT_VOTING_PATHS_PER_TOPIC: usize = 10;
+
 /// The number of e8s per ICPT;
 const E8S_PER_ICPT: u64 = TOKEN_SUBDIVIDABLE_BY;
 
@@ -565,16 +568,7 @@ impl Neuron {
     /// neuron follows on this topic (or on the default topic if this
     /// neuron doesn't specify any followees for `topic`).
     fn would_follow_ballots(&self, topic: Topic, ballots: &HashMap<u64, Ballot>) -> Vote {
-        // Compute the list of followees for this topic. If no
-        // following is specified for the topic, use the followees
-        // from the 'Unspecified' topic.
-        if let Some(followees) = self
-            .followees
-            .get(&(topic as i32))
-            .or_else(|| self.followees.get(&(Topic::Unspecified as i32)))
-            // extract plain vector from 'Followees' proto
-            .map(|x| &x.followees)
-        {
+        if let Some((followees, _)) = self.followees_for_topic(topic) {
             // If, for some reason, a list of followees is specified
             // but empty (this is not normal), don't vote 'no', as
             // would be the natural result of the algorithm below, but
@@ -604,6 +598,19 @@ impl Neuron {
         Vote::Unspecified
     }
 
+    /// Returns the list of followees of this neuron for `topic`. If no
+    /// following is specified for the topic, the followees from the
+    /// 'Unspecified' topic are returned, and the returned flag is true.
+    fn followees_for_topic(&self, topic: Topic) -> Option<(&Vec<NeuronId>, bool)> {
+        match self.followees.get(&(topic as i32)) {
+            Some(followees) => Some((&followees.followees, false)),
+            None => self
+                .followees
+                .get(&(Topic::Unspecified as i32))
+                .map(|followees| (&followees.followees, true)),
+        }
+    }
+
     /// Returns the list of followees on the manage neuron topic for
     /// this neuron.
     fn neuron_managers(&self) -> Option<&Vec<NeuronId>> {
@@ -3714,6 +3721,172 @@ impl Governance {
         )
     }
 
+    /// Returns a report on how the neuron with the given `id` follows
+    /// other neurons, after checking that the `caller` is authorized to
+    /// get the full neuron. See `FollowingReport`.
+    ///
+    /// The report covers the topics on which proposals are decided by
+    /// voting, i.e., all topics but `Unspecified` and `NeuronManagement`.
+    pub fn get_following_report(
+        &self,
+        id: &NeuronId,
+        caller: &PrincipalId,
+    ) -> Result<FollowingReport, GovernanceError> {
+        let neuron = self.get_full_neuron(id, caller)?;
+        let now = self.env.now();
+        let mut topics = Vec::new();
+        for topic in Topic::iter() {
+            if topic == Topic::Unspecified || topic == Topic::NeuronManagement {
+                continue;
+            }
+            let (followees, catch_all) = match neuron.followees_for_topic(topic) {
+                Some((followees, catch_all)) if !followees.is_empty() => (followees, catch_all),
+                _ => continue,
+            };
+            let voting_period_seconds = self.voting_period_seconds()(topic);
+            // The proposals of this topic that still have ballots, most
+            // recent first.
+            let proposals: Vec<&ProposalData> = self
+                .proto
+                .proposals
+                .values()
+                .rev()
+                .filter(|p| p.topic() == topic && !p.ballots.is_empty())
+                .collect();
+
+            let followees = followees
+                .iter()
+                .map(|followee| {
+                    let followee_neuron = self.proto.neurons.get(&followee.id);
+                    let ineligible_proposals = proposals
+                        .iter()
+                        .filter(|p| !p.ballots.contains_key(&followee.id))
+                        .count();
+                    let missed_votes = proposals
+                        .iter()
+                        .filter(|p| {
+                            !p.accepts_vote(now, voting_period_seconds)
+                                && p.ballots
+                                    .get(&followee.id)
+                                    .map_or(false, |ballot| ballot.vote == Vote::Unspecified as i32)
+                        })
+                        .count();
+                    FolloweeReport {
+                        followee: Some(followee.clone()),
+                        not_found: followee_neuron.is_none(),
+                        dissolved: followee_neuron
+                            .map_or(false, |n| n.state(now) == NeuronState::Dissolved),
+                        ineligible_proposals: ineligible_proposals as u32,
+                        missed_votes: missed_votes as u32,
+                    }
+                })
+                .collect();
+
+            let voting_paths = proposals
+                .iter()
+                .filter(|p| p.ballots.contains_key(&id.id))
+                .take(MAX_FOLLOWING_REPORT_VOTING_PATHS_PER_TOPIC)
+                .map(|p| self.voting_path(id, topic, p))
+                .collect();
+
+            topics.push(TopicFollowingReport {
+                topic: topic as i32,
+                catch_all,
+                followees,
+                cycles: self.follow_cycles(id, topic),
+                voting_paths,
+            });
+        }
+        Ok(FollowingReport { topics })
+    }
+
+    /// Returns the cycles of the follow graph of `topic` that are
+    /// reachable from the neuron with the given `id`, one per back edge
+    /// found by a depth-first search of the graph.
+    fn follow_cycles(&self, id: &NeuronId, topic: Topic) -> Vec<FollowCycle> {
+        let followees_of = |id: u64| -> std::vec::IntoIter<u64> {
+            self.proto
+                .neurons
+                .get(&id)
+                .and_then(|n| n.followees_for_topic(topic))
+                .map_or_else(Vec::new, |(followees, _)| {
+                    followees.iter().map(|f| f.id).collect()
+                })
+                .into_iter()
+        };
+        let mut cycles = Vec::new();
+        // The neurons whose followees have all been visited.
+        let mut visited = HashSet::new();
+        // The path from `id` to the neuron being visited, along with the
+        // followees of each neuron on the path that remain to be visited.
+        let mut path = vec![(id.id, followees_of(id.id))];
+        while let Some((_, followees)) = path.last_mut() {
+            match followees.next() {
+                Some(followee) => {
+                    if let Some(start) = path.iter().position(|(n, _)| *n == followee) {
+                        cycles.push(FollowCycle {
+                            neuron_ids: path[start..]
+                                .iter()
+                                .map(|(n, _)| NeuronId { id: *n })
+                                .collect(),
+                        });
+                    } else if !visited.contains(&followee) {
+                        path.push((followee, followees_of(followee)));
+                    }
+                }
+                None => {
+                    let (n, _) = path.pop().unwrap();
+                    visited.insert(n);
+                }
+            }
+        }
+        cycles
+    }
+
+    /// Returns how the vote of the neuron with the given `id` on
+    /// `proposal` came about, as inferred from the ballots of the
+    /// proposal. See `VotingPath`.
+    fn voting_path(&self, id: &NeuronId, topic: Topic, proposal: &ProposalData) -> VotingPath {
+        let vote = proposal
+            .ballots
+            .get(&id.id)
+            .map_or(Vote::Unspecified as i32, |ballot| ballot.vote);
+        let mut neuron_ids = vec![id.clone()];
+        if vote != Vote::Unspecified as i32 {
+            let mut current = id.id;
+            while let Some(neuron) = self.proto.neurons.get(&current) {
+                // If the followees don't explain the vote, the neuron
+                // voted directly.
+                if neuron.would_follow_ballots(topic, &proposal.ballots) as i32 != vote {
+                    break;
+                }
+                let next = neuron
+                    .followees_for_topic(topic)
+                    .and_then(|(followees, _)| {
+                        followees.iter().find(|f| {
+                            !neuron_ids.contains(f)
+                                && proposal
+                                    .ballots
+                                    .get(&f.id)
+                                    .map_or(false, |ballot| ballot.vote == vote)
+                        })
+                    });
+                match next {
+                    Some(followee) => {
+                        current = followee.id;
+                        neuron_ids.push(followee.clone());
+                    }
+                    None => break,
+                }
+            }
+        }
+        VotingPath {
+            proposal_id: proposal.id.clone(),
+            vote,
+            neuron_ids,
+        }
+    }
+
     // Returns the set of currently registered node providers.
     pub fn get_node_providers(&self) -> &[NodeProvider] {
         &self.proto.node_providers
@@ -3816,6 +3989,22 @@ impl Governance {
             }
         }
 
+        // Rendering the payload requires decoding it, which is only done when
+        // a single proposal is queried.
+        let payload_text_rendering = if multi_query {
+            None
+        } else {
+            match data.proposal.as_ref().and_then(|p| p.action.as_ref()) {
+                Some(proposal::Action::ExecuteNnsFunction(update)) => {
+                    Some(match NnsFunctionPayload::decode(update) {
+                        Ok(payload) => payload.render(),
+                        Err(e) => format!("Payload could not be rendered: {}", e),
+                    })
+                }
+                _ => None,
+            }
+        };
+
         /// Remove all ballots except the ballots belonging to a neuron present
         /// in `except_from`.
         fn remove_ballots_not_cast_by(
@@ -3850,6 +4039,7 @@ impl Governance {
             deadline_timestamp_seconds: Some(
                 data.get_deadline_timestamp_seconds(voting_period_seconds),
             ),
+            payload_text_rendering,
         }
     }
 
@@ -4767,9 +4957,10 @@ impl Governance {
                     "The maximum NNS function payload size in a proposal action is {} bytes, this payload is: {} bytes",
                     PROPOSAL_EXECUTE_NNS_FUNCTION_PAYLOAD_BYTES_MAX,
                     update.payload.len())
-            } else if update.nns_function == NnsFunction::IcpXdrConversionRate as i32 {
-                match Decode!(&update.payload, UpdateIcpXdrConversionRatePayload) {
-                    Ok(payload) => {
+            } else {
+                match NnsFunctionPayload::decode(update) {
+                    Err(e) => e,
+                    Ok(NnsFunctionPayload::IcpXdrConversionRate(payload)) => {
                         if payload.xdr_permyriad_per_icp
                             < self
                                 .proto
@@ -4789,52 +4980,39 @@ impl Governance {
                             return Ok(());
                         }
                     }
-                    Err(e) => format!(
-                        "The payload could not be decoded into a UpdateIcpXdrConversionRatePayload: {}",
-                        e
-                    ),
-                }
-            } else if update.nns_function == NnsFunction::AssignNoid as i32 {
-                match Decode!(&update.payload, AddNodeOperatorPayload) {
-                    Ok(payload) => match payload.node_provider_principal_id {
-                        Some(id) => {
-                            let is_registered = self
-                                .get_node_providers()
-                                .iter()
-                                .any(|np| np.id.unwrap() == id);
-                            if !is_registered {
-                                "The node provider specified in the payload is not registered"
-                                    .to_string()
-                            } else {
-                                return Ok(());
+                    Ok(NnsFunctionPayload::AssignNoid(payload)) => {
+                        match payload.node_provider_principal_id {
+                            Some(id) => {
+                                let is_registered = self
+                                    .get_node_providers()
+                                    .iter()
+                                    .any(|np| np.id.unwrap() == id);
+                                if !is_registered {
+                                    "The node provider specified in the payload is not registered"
+                                        .to_string()
+                                } else {
+                                    return Ok(());
+                                }
                             }
+                            None => "The payload's node_provider_principal_id field was None"
+                                .to_string(),
                         }
-                        None => {
-                            "The payload's node_provider_principal_id field was None".to_string()
-                        }
-                    },
-                    Err(e) => format!(
-                        "The payload could not be decoded into a AddNodeOperatorPayload: {}",
-                        e
-                    ),
-                }
-            } else if update.nns_function == NnsFunction::AddOrRemoveDataCenters as i32 {
-                match Decode!(&update.payload, AddOrRemoveDataCentersProposalPayload) {
-                    Ok(payload) => match payload.validate() {
-                        Ok(_) => {
-                            return Ok(());
-                        }
-                        Err(e) => {
-                            format!("The given AddOrRemoveDataCentersProposalPayload is invalid: {}", e)
+                    }
+                    Ok(NnsFunctionPayload::AddOrRemoveDataCenters(payload)) => {
+                        match payload.validate() {
+                            Ok(_) => {
+                                return Ok(());
+                            }
+                            Err(e) => format!(
+                                "The given AddOrRemoveDataCentersProposalPayload is invalid: {}",
+                                e
+                            ),
                         }
-                    },
-                    Err(e) => format!(
-                        "The payload could not be decoded into a AddOrRemoveDataCentersProposalPayload: {}",
-                        e
-                    ),
+                    }
+                    Ok(_) => {
+                        return Ok(());
+                    }
                 }
-            } else {
-                return Ok(());
             }
         } else if let Some(proposal::Action::Motion(motion)) = &proposal.action {
             if motion.motion_text.len() > PROPOSAL_MOTION_TEXT_BYTES_MAX {
@@ -0,0 +1,392 @@
+//! Typed views of the payloads of `ExecuteNnsFunction` proposals.
+//!
+//! The payload of an `ExecuteNnsFunction` proposal is the candid-encoded
+//! argument of the method that the proposal calls when it is executed. This
+//! module decodes it into the type expected by that method, so that malformed
+//! payloads are rejected when the proposal is made rather than when it is
+//! executed, and so that voters can be shown what the proposal actually does.
+//!
+//! Most types are the ones of the registry mutations. The payloads of the
+//! proposals that call the root and lifeline canisters, and of
+//! `UninstallCode`, are mirrored here, as governance can't depend on the
+//! canisters that define them.
+
+use crate::pb::v1::{ExecuteNnsFunction, NnsFunction};
+use candid::{CandidType, Decode, Deserialize};
+use cycles_minting_canister::SetAuthorizedSubnetworkListArgs;
+use ic_base_types::{CanisterId, CanisterInstallMode};
+use ic_crypto_sha::Sha256;
+use ic_nns_common::types::{MethodAuthzChange, UpdateIcpXdrConversionRatePayload};
+use ic_nns_constants::memory_allocation_of;
+use ic_protobuf::registry::{
+    dc::v1::AddOrRemoveDataCentersProposalPayload, node_operator::v1::RemoveNodeOperatorsPayload,
+    node_rewards::v2::UpdateNodeRewardsTableProposalPayload,
+};
+use registry_canister::mutations::{
+    do_add_node_operator::AddNodeOperatorPayload, do_add_nodes_to_subnet::AddNodesToSubnetPayload,
+    do_bless_replica_version::BlessReplicaVersionPayload, do_create_subnet::CreateSubnetPayload,
+    do_recover_subnet::RecoverSubnetPayload, do_remove_nodes::RemoveNodesPayload,
+    do_remove_nodes_from_subnet::RemoveNodesFromSubnetPayload,
+    do_set_firewall_config::SetFirewallConfigPayload,
+    do_update_node_operator_config::UpdateNodeOperatorConfigPayload,
+    do_update_subnet::UpdateSubnetPayload,
+    do_update_subnet_replica::UpdateSubnetReplicaVersionPayload,
+    do_update_unassigned_nodes_config::UpdateUnassignedNodesConfigPayload,
+    reroute_canister_range::RerouteCanisterRangePayload,
+};
+use std::fmt;
+
+/// The payload of an `NnsFunction::NnsCanisterUpgrade` proposal.
+///
+/// Mirrors `ChangeNnsCanisterProposalPayload` of the root canister.
+#[derive(CandidType, Deserialize, Clone)]
+pub struct ChangeNnsCanisterProposalPayload {
+    pub stop_before_installing: bool,
+    pub mode: CanisterInstallMode,
+    pub canister_id: CanisterId,
+    pub wasm_module: Vec<u8>,
+    pub arg: Vec<u8>,
+    pub compute_allocation: Option<candid::Nat>,
+    pub memory_allocation: Option<candid::Nat>,
+    pub query_allocation: Option<candid::Nat>,
+    pub authz_changes: Vec<MethodAuthzChange>,
+}
+
+impl fmt::Debug for ChangeNnsCanisterProposalPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeNnsCanisterProposalPayload")
+            .field("stop_before_installing", &self.stop_before_installing)
+            .field("mode", &self.mode)
+            .field("canister_id", &self.canister_id)
+            .field("wasm_module_sha256", &sha256_hex(&self.wasm_module))
+            .field("wasm_module_len", &self.wasm_module.len())
+            .field("arg_sha256", &sha256_hex(&self.arg))
+            .field("arg_len", &self.arg.len())
+            .field("compute_allocation", &self.compute_allocation)
+            .field("memory_allocation", &self.memory_allocation)
+            .field("query_allocation", &self.query_allocation)
+            .field("authz_changes", &self.authz_changes)
+            .finish()
+    }
+}
+
+impl ChangeNnsCanisterProposalPayload {
+    pub fn new(
+        stop_before_installing: bool,
+        mode: CanisterInstallMode,
+        canister_id: CanisterId,
+    ) -> Self {
+        Self {
+            stop_before_installing,
+            mode,
+            canister_id,
+            wasm_module: Vec::new(),
+            arg: Vec::new(),
+            compute_allocation: None,
+            memory_allocation: Some(candid::Nat::from(memory_allocation_of(canister_id))),
+            query_allocation: None,
+            authz_changes: Vec::new(),
+        }
+    }
+}
+
+/// The payload of an `NnsFunction::NnsCanisterInstall` proposal.
+///
+/// Mirrors `AddNnsCanisterProposalPayload` of the root canister.
+#[derive(CandidType, Deserialize, Clone)]
+pub struct AddNnsCanisterProposalPayload {
+    pub name: String,
+    pub wasm_module: Vec<u8>,
+    pub arg: Vec<u8>,
+    pub compute_allocation: Option<candid::Nat>,
+    pub memory_allocation: Option<candid::Nat>,
+    pub query_allocation: Option<candid::Nat>,
+    pub initial_cycles: u64,
+    pub authz_changes: Vec<MethodAuthzChange>,
+}
+
+impl fmt::Debug for AddNnsCanisterProposalPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AddNnsCanisterProposalPayload")
+            .field("name", &self.name)
+            .field("wasm_module_sha256", &sha256_hex(&self.wasm_module))
+            .field("wasm_module_len", &self.wasm_module.len())
+            .field("arg_sha256", &sha256_hex(&self.arg))
+            .field("arg_len", &self.arg.len())
+            .field("compute_allocation", &self.compute_allocation)
+            .field("memory_allocation", &self.memory_allocation)
+            .field("query_allocation", &self.query_allocation)
+            .field("initial_cycles", &self.initial_cycles)
+            .field("authz_changes", &self.authz_changes)
+            .finish()
+    }
+}
+
+/// The payload of an `NnsFunction::NnsRootUpgrade` proposal, i.e., the
+/// argument of the `upgrade_root` method of the lifeline canister.
+#[derive(CandidType, Deserialize, Clone)]
+pub struct UpgradeRootProposalPayload {
+    pub wasm_module: Vec<u8>,
+    pub module_arg: Vec<u8>,
+    pub stop_upgrade_start: bool,
+}
+
+impl fmt::Debug for UpgradeRootProposalPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UpgradeRootProposalPayload")
+            .field("wasm_module_sha256", &sha256_hex(&self.wasm_module))
+            .field("wasm_module_len", &self.wasm_module.len())
+            .field("module_arg_sha256", &sha256_hex(&self.module_arg))
+            .field("module_arg_len", &self.module_arg.len())
+            .field("stop_upgrade_start", &self.stop_upgrade_start)
+            .finish()
+    }
+}
+
+/// Mirrors `CanisterAction` of the root canister.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum CanisterAction {
+    Stop,
+    Start,
+}
+
+/// The payload of an `NnsFunction::StopOrStartNnsCanister` proposal.
+///
+/// Mirrors `StopOrStartNnsCanisterProposalPayload` of the root canister.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StopOrStartNnsCanisterProposalPayload {
+    pub canister_id: CanisterId,
+    pub action: CanisterAction,
+}
+
+/// The payload of an `NnsFunction::UninstallCode` proposal, i.e., the argument
+/// of the `uninstall_code` method of the management canister.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CanisterIdRecord {
+    pub canister_id: CanisterId,
+}
+
+/// The decoded payload of an `ExecuteNnsFunction` proposal, one variant per
+/// `NnsFunction`.
+#[derive(Debug)]
+pub enum NnsFunctionPayload {
+    AssignNoid(AddNodeOperatorPayload),
+    CreateSubnet(CreateSubnetPayload),
+    AddNodeToSubnet(AddNodesToSubnetPayload),
+    RemoveNodesFromSubnet(RemoveNodesFromSubnetPayload),
+    NnsCanisterInstall(AddNnsCanisterProposalPayload),
+    NnsCanisterUpgrade(ChangeNnsCanisterProposalPayload),
+    NnsRootUpgrade(UpgradeRootProposalPayload),
+    RecoverSubnet(RecoverSubnetPayload),
+    BlessReplicaVersion(BlessReplicaVersionPayload),
+    UpdateNodeOperatorConfig(UpdateNodeOperatorConfigPayload),
+    UpdateSubnetReplicaVersion(UpdateSubnetReplicaVersionPayload),
+    UpdateConfigOfSubnet(UpdateSubnetPayload),
+    IcpXdrConversionRate(UpdateIcpXdrConversionRatePayload),
+    /// The registry ignores the argument of `clear_provisional_whitelist`, so
+    /// the payload is not inspected.
+    ClearProvisionalWhitelist,
+    SetAuthorizedSubnetworks(SetAuthorizedSubnetworkListArgs),
+    SetFirewallConfig(SetFirewallConfigPayload),
+    StopOrStartNnsCanister(StopOrStartNnsCanisterProposalPayload),
+    RemoveNodes(RemoveNodesPayload),
+    UninstallCode(CanisterIdRecord),
+    UpdateNodeRewardsTable(UpdateNodeRewardsTableProposalPayload),
+    AddOrRemoveDataCenters(AddOrRemoveDataCentersProposalPayload),
+    UpdateUnassignedNodesConfig(UpdateUnassignedNodesConfigPayload),
+    RemoveNodeOperators(RemoveNodeOperatorsPayload),
+    RerouteCanisterRange(RerouteCanisterRangePayload),
+}
+
+impl NnsFunctionPayload {
+    /// Decodes the payload of the given `ExecuteNnsFunction` into the type
+    /// expected by the method that `nns_function` calls.
+    pub fn decode(update: &ExecuteNnsFunction) -> Result<Self, String> {
+        let nns_function = NnsFunction::from_i32(update.nns_function)
+            .ok_or_else(|| format!("Unknown NNS function: {}", update.nns_function))?;
+        let payload = &update.payload;
+        Ok(match nns_function {
+            NnsFunction::Unspecified => {
+                return Err("The NNS function is not specified".to_string());
+            }
+            NnsFunction::AssignNoid => Self::AssignNoid(decode(payload)?),
+            NnsFunction::CreateSubnet => Self::CreateSubnet(decode(payload)?),
+            NnsFunction::AddNodeToSubnet => Self::AddNodeToSubnet(decode(payload)?),
+            NnsFunction::RemoveNodesFromSubnet => Self::RemoveNodesFromSubnet(decode(payload)?),
+            NnsFunction::NnsCanisterInstall => Self::NnsCanisterInstall(decode(payload)?),
+            NnsFunction::NnsCanisterUpgrade => Self::NnsCanisterUpgrade(decode(payload)?),
+            NnsFunction::NnsRootUpgrade => Self::NnsRootUpgrade(decode(payload)?),
+            NnsFunction::RecoverSubnet => Self::RecoverSubnet(decode(payload)?),
+            NnsFunction::BlessReplicaVersion => Self::BlessReplicaVersion(decode(payload)?),
+            NnsFunction::UpdateNodeOperatorConfig => {
+                Self::UpdateNodeOperatorConfig(decode(payload)?)
+            }
+            NnsFunction::UpdateSubnetReplicaVersion => {
+                Self::UpdateSubnetReplicaVersion(decode(payload)?)
+            }
+            NnsFunction::UpdateConfigOfSubnet => Self::UpdateConfigOfSubnet(decode(payload)?),
+            NnsFunction::IcpXdrConversionRate => Self::IcpXdrConversionRate(decode(payload)?),
+            NnsFunction::ClearProvisionalWhitelist => Self::ClearProvisionalWhitelist,
+            NnsFunction::SetAuthorizedSubnetworks => {
+                Self::SetAuthorizedSubnetworks(decode(payload)?)
+            }
+            NnsFunction::SetFirewallConfig => Self::SetFirewallConfig(decode(payload)?),
+            NnsFunction::StopOrStartNnsCanister => Self::StopOrStartNnsCanister(decode(payload)?),
+            NnsFunction::RemoveNodes => Self::RemoveNodes(decode(payload)?),
+            NnsFunction::UninstallCode => Self::UninstallCode(decode(payload)?),
+            NnsFunction::UpdateNodeRewardsTable => Self::UpdateNodeRewardsTable(decode(payload)?),
+            NnsFunction::AddOrRemoveDataCenters => Self::AddOrRemoveDataCenters(decode(payload)?),
+            NnsFunction::UpdateUnassignedNodesConfig => {
+                Self::UpdateUnassignedNodesConfig(decode(payload)?)
+            }
+            NnsFunction::RemoveNodeOperators => Self::RemoveNodeOperators(decode(payload)?),
+            NnsFunction::RerouteCanisterRange => Self::RerouteCanisterRange(decode(payload)?),
+        })
+    }
+
+    /// Returns a human-readable rendering of the payload. Wasm modules and
+    /// canister arguments are shown by their SHA-256 hash and length.
+    pub fn render(&self) -> String {
+        match self {
+            Self::AssignNoid(payload) => format!("{:#?}", payload),
+            Self::CreateSubnet(payload) => format!("{:#?}", payload),
+            Self::AddNodeToSubnet(payload) => format!("{:#?}", payload),
+            Self::RemoveNodesFromSubnet(payload) => format!("{:#?}", payload),
+            Self::NnsCanisterInstall(payload) => format!("{:#?}", payload),
+            Self::NnsCanisterUpgrade(payload) => format!("{:#?}", payload),
+            Self::NnsRootUpgrade(payload) => format!("{:#?}", payload),
+            Self::RecoverSubnet(payload) => format!("{:#?}", payload),
+            Self::BlessReplicaVersion(payload) => format!("{:#?}", payload),
+            Self::UpdateNodeOperatorConfig(payload) => format!("{:#?}", payload),
+            Self::UpdateSubnetReplicaVersion(payload) => format!("{:#?}", payload),
+            Self::UpdateConfigOfSubnet(payload) => format!("{:#?}", payload),
+            Self::IcpXdrConversionRate(payload) => format!("{:#?}", payload),
+            Self::ClearProvisionalWhitelist => "ClearProvisionalWhitelist".to_string(),
+            Self::SetAuthorizedSubnetworks(payload) => format!("{:#?}", payload),
+            Self::SetFirewallConfig(payload) => format!("{:#?}", payload),
+            Self::StopOrStartNnsCanister(payload) => format!("{:#?}", payload),
+            Self::RemoveNodes(payload) => format!("{:#?}", payload),
+            Self::UninstallCode(payload) => format!("{:#?}", payload),
+            Self::UpdateNodeRewardsTable(payload) => format!("{:#?}", payload),
+            Self::AddOrRemoveDataCenters(payload) => format!("{:#?}", payload),
+            Self::UpdateUnassignedNodesConfig(payload) => format!("{:#?}", payload),
+            Self::RemoveNodeOperators(payload) => format!("{:#?}", payload),
+            Self::RerouteCanisterRange(payload) => format!("{:#?}", payload),
+        }
+    }
+}
+
+fn decode<T>(payload: &[u8]) -> Result<T, String>
+where
+    T: CandidType + for<'de> Deserialize<'de>,
+{
+    Decode!(payload, T).map_err(|e| {
+        let type_name = std::any::type_name::<T>();
+        format!(
+            "The payload could not be decoded into a {}: {}",
+            type_name.rsplit("::").next().unwrap_or(type_name),
+            e
+        )
+    })
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::hash(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Encode;
+    use ic_base_types::{NodeId, PrincipalId};
+
+    fn execute_nns_function(nns_function: NnsFunction, payload: Vec<u8>) -> ExecuteNnsFunction {
+        ExecuteNnsFunction {
+            nns_function: nns_function as i32,
+            payload,
+        }
+    }
+
+    #[test]
+    fn test_decode_and_render_canister_upgrade() {
+        let payload = ChangeNnsCanisterProposalPayload {
+            wasm_module: vec![0, 97, 115, 109],
+            ..ChangeNnsCanisterProposalPayload::new(
+                true,
+                CanisterInstallMode::Upgrade,
+                CanisterId::from_u64(3),
+            )
+        };
+        let update =
+            execute_nns_function(NnsFunction::NnsCanisterUpgrade, Encode!(&payload).unwrap());
+
+        let decoded = NnsFunctionPayload::decode(&update).unwrap();
+        let rendering = decoded.render();
+        assert!(rendering.contains(&format!(
+            "wasm_module_sha256: \"{}\"",
+            sha256_hex(&payload.wasm_module)
+        )));
+        assert!(rendering.contains("wasm_module_len: 4"));
+        assert!(rendering.contains("stop_before_installing: true"));
+    }
+
+    #[test]
+    fn test_decode_and_render_registry_payload() {
+        let payload = RemoveNodesPayload {
+            node_ids: vec![NodeId::from(PrincipalId::new_node_test_id(1))],
+        };
+        let update = execute_nns_function(NnsFunction::RemoveNodes, Encode!(&payload).unwrap());
+
+        match NnsFunctionPayload::decode(&update) {
+            Ok(NnsFunctionPayload::RemoveNodes(decoded)) => assert_eq!(decoded, payload),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_malformed_payloads_are_rejected() {
+        // Not candid.
+        let update = execute_nns_function(NnsFunction::UpdateConfigOfSubnet, vec![1, 2, 3]);
+        let err = NnsFunctionPayload::decode(&update).unwrap_err();
+        assert!(
+            err.starts_with("The payload could not be decoded into a UpdateSubnetPayload"),
+            "{}",
+            err
+        );
+
+        // Valid candid, but of the wrong type.
+        let update = execute_nns_function(
+            NnsFunction::RerouteCanisterRange,
+            Encode!(&CanisterIdRecord {
+                canister_id: CanisterId::from_u64(1)
+            })
+            .unwrap(),
+        );
+        assert!(NnsFunctionPayload::decode(&update).is_err());
+
+        // No payload.
+        let update = execute_nns_function(NnsFunction::NnsCanisterUpgrade, vec![]);
+        assert!(NnsFunctionPayload::decode(&update).is_err());
+
+        // Unknown and unspecified functions.
+        let update = ExecuteNnsFunction {
+            nns_function: 10_000,
+            payload: Encode!(&()).unwrap(),
+        };
+        assert!(NnsFunctionPayload::decode(&update).is_err());
+        let update = execute_nns_function(NnsFunction::Unspecified, Encode!(&()).unwrap());
+        assert!(NnsFunctionPayload::decode(&update).is_err());
+    }
+
+    #[test]
+    fn test_clear_provisional_whitelist_payload_is_not_inspected() {
+        let update = execute_nns_function(NnsFunction::ClearProvisionalWhitelist, vec![]);
+        assert!(matches!(
+            NnsFunctionPayload::decode(&update),
+            Ok(NnsFunctionPayload::ClearProvisionalWhitelist)
+        ));
+    }
+}
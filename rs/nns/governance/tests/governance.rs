@@ -8,12 +8,12 @@ use candid::Encode;
 #[cfg(feature = "test")]
 use comparable::{Changed, I32Change, MapChange, OptionChange, StringChange, U64Change, VecChange};
 use futures::future::FutureExt;
-use ic_base_types::PrincipalId;
+use ic_base_types::{CanisterInstallMode, PrincipalId};
 use ic_crypto_sha::Sha256;
 use ic_nns_common::pb::v1::{NeuronId, ProposalId};
 use ic_nns_common::types::UpdateIcpXdrConversionRatePayload;
 use ic_nns_constants::ids::{TEST_NEURON_1_OWNER_PRINCIPAL, TEST_NEURON_2_OWNER_PRINCIPAL};
-use ic_nns_constants::GOVERNANCE_CANISTER_ID;
+use ic_nns_constants::{GOVERNANCE_CANISTER_ID, REGISTRY_CANISTER_ID};
 #[cfg(feature = "test")]
 use ic_nns_governance::governance::governance_minting_account;
 #[cfg(feature = "test")]
@@ -60,11 +60,12 @@ use ic_nns_governance::{
         neuron::Followees,
         proposal,
         reward_node_provider::{RewardMode, RewardToAccount, RewardToNeuron},
-        AddOrRemoveNodeProvider, Ballot, BallotInfo, Empty, ExecuteNnsFunction,
-        Governance as GovernanceProto, GovernanceError, KnownNeuron, KnownNeuronData, ListNeurons,
-        ListNeuronsResponse, ListProposalInfo, ManageNeuron, Motion, NetworkEconomics, Neuron,
-        NeuronState, NnsFunction, NodeProvider, Proposal, ProposalData, ProposalStatus,
-        RewardEvent, RewardNodeProvider, SetDefaultFollowees, Tally, Topic, Vote,
+        AddOrRemoveNodeProvider, Ballot, BallotInfo, Empty, ExecuteNnsFunction, FollowCycle,
+        FolloweeReport, Governance as GovernanceProto, GovernanceError, KnownNeuron,
+        KnownNeuronData, ListNeurons, ListNeuronsResponse, ListProposalInfo, ManageNeuron, Motion,
+        NetworkEconomics, Neuron, NeuronState, NnsFunction, NodeProvider, Proposal, ProposalData,
+        ProposalStatus, RewardEvent, RewardNodeProvider, SetDefaultFollowees, Tally, Topic, Vote,
+        VotingPath,
     },
 };
 use ledger_canister::{AccountIdentifier, Memo, Tokens};
@@ -86,6 +87,9 @@ use ic_nns_governance::governance::{
     MAX_DISSOLVE_DELAY_SECONDS, MAX_NEURON_AGE_FOR_AGE_BONUS, MAX_NUMBER_OF_PROPOSALS_WITH_BALLOTS,
     ONE_DAY_SECONDS, ONE_YEAR_SECONDS,
 };
+use ic_nns_governance::nns_function_payload::{
+    CanisterAction, ChangeNnsCanisterProposalPayload, StopOrStartNnsCanisterProposalPayload,
+};
 use ic_nns_governance::pb::v1::governance::GovernanceCachedMetrics;
 use ic_nns_governance::pb::v1::governance_error::ErrorType::{NotFound, ResourceExhausted};
 use ic_nns_governance::pb::v1::manage_neuron::MergeMaturity;
@@ -1267,6 +1271,149 @@ fn test_cascade_following() {
     assert_eq!(gov.proto.neurons.get(&1).unwrap().neuron_fees_e8s, 0);
 }
 
+/// *Test scenario*
+///
+/// - Neuron 4 follows neuron 2 on the Governance topic, and neuron 2 follows
+///   neuron 4 back. Neuron 7, which neuron 3 follows, doesn't exist.
+///
+/// - Neuron 1 makes a proposal (Motion of topic Governance) and implicitly
+///   votes yes, neuron 5 votes yes and neuron 2 votes no.
+///
+/// - The following reports show the cycle between neurons 2 and 4, that
+///   neuron 4 voted by following neuron 2, that neuron 7 doesn't exist and,
+///   once the voting period has ended, that neurons 3 and 6 missed the vote.
+#[test]
+fn test_following_report() {
+    let mut driver = fake::FakeDriver::default();
+    let mut proto = fixture_for_following();
+    for id in [2, 3, 4] {
+        proto.neurons.get_mut(&id).unwrap().controller = Some(principal(id));
+    }
+    proto.neurons.get_mut(&4).unwrap().followees.insert(
+        Topic::Governance as i32,
+        neuron::Followees {
+            followees: vec![NeuronId { id: 2 }],
+        },
+    );
+    proto.neurons.remove(&7);
+    let mut gov = Governance::new(proto, driver.get_fake_env(), driver.get_fake_ledger());
+    gov.make_proposal(
+        &NeuronId { id: 1 },
+        // Must match neuron 1's serialized_id.
+        &principal(1),
+        &Proposal {
+            title: Some("A Reasonable Title".to_string()),
+            summary: "test".to_string(),
+            action: Some(proposal::Action::Motion(Motion {
+                motion_text: "dummy text".to_string(),
+            })),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    fake::register_vote_assert_success(
+        &mut gov,
+        principal(5),
+        NeuronId { id: 5 },
+        ProposalId { id: 1 },
+        Vote::Yes,
+    );
+    fake::register_vote_assert_success(
+        &mut gov,
+        principal(2),
+        NeuronId { id: 2 },
+        ProposalId { id: 1 },
+        Vote::No,
+    );
+
+    // Only the controller and hot keys of a neuron can get its report.
+    assert_eq!(
+        gov.get_following_report(&NeuronId { id: 4 }, &principal(5))
+            .unwrap_err()
+            .error_type,
+        NotAuthorized as i32
+    );
+
+    let report = gov
+        .get_following_report(&NeuronId { id: 4 }, &principal(4))
+        .unwrap();
+    assert_eq!(report.topics.len(), 1);
+    let topic_report = &report.topics[0];
+    assert_eq!(topic_report.topic, Topic::Governance as i32);
+    assert!(!topic_report.catch_all);
+    assert_eq!(
+        topic_report.cycles,
+        vec![FollowCycle {
+            neuron_ids: vec![NeuronId { id: 4 }, NeuronId { id: 2 }],
+        }]
+    );
+    assert_eq!(
+        topic_report.voting_paths,
+        vec![VotingPath {
+            proposal_id: Some(ProposalId { id: 1 }),
+            vote: Vote::No as i32,
+            neuron_ids: vec![NeuronId { id: 4 }, NeuronId { id: 2 }],
+        }]
+    );
+
+    // The votes are only missed once the voting period has ended.
+    let report = gov
+        .get_following_report(&NeuronId { id: 3 }, &principal(3))
+        .unwrap();
+    let topic_report = report
+        .topics
+        .iter()
+        .find(|t| t.topic == Topic::Governance as i32)
+        .unwrap();
+    assert!(topic_report.catch_all);
+    assert!(topic_report.followees.iter().all(|f| f.missed_votes == 0));
+    assert_eq!(
+        topic_report.voting_paths,
+        vec![VotingPath {
+            proposal_id: Some(ProposalId { id: 1 }),
+            vote: Vote::Unspecified as i32,
+            neuron_ids: vec![NeuronId { id: 3 }],
+        }]
+    );
+
+    // Past the latest possible wait-for-quiet deadline.
+    driver.advance_time_by(2 * WAIT_FOR_QUIET_DEADLINE_INCREASE_SECONDS + ONE_DAY_SECONDS);
+    let report = gov
+        .get_following_report(&NeuronId { id: 3 }, &principal(3))
+        .unwrap();
+    let topic_report = report
+        .topics
+        .iter()
+        .find(|t| t.topic == Topic::Governance as i32)
+        .unwrap();
+    assert_eq!(
+        topic_report.followees,
+        vec![
+            FolloweeReport {
+                followee: Some(NeuronId { id: 5 }),
+                not_found: false,
+                dissolved: false,
+                ineligible_proposals: 0,
+                missed_votes: 0,
+            },
+            FolloweeReport {
+                followee: Some(NeuronId { id: 6 }),
+                not_found: false,
+                dissolved: false,
+                ineligible_proposals: 0,
+                missed_votes: 1,
+            },
+            FolloweeReport {
+                followee: Some(NeuronId { id: 7 }),
+                not_found: true,
+                dissolved: false,
+                ineligible_proposals: 1,
+                missed_votes: 0,
+            },
+        ]
+    );
+}
+
 /// In this scenario, we simply test that you cannot make a proposal
 /// to set the conversion rate below the minimum allowable rate.
 #[test]
@@ -1385,6 +1532,81 @@ fn test_node_provider_must_be_registered() {
     .unwrap();
 }
 
+/// Tests that the payload of an ExecuteNnsFunction proposal must decode into
+/// the type expected by the NNS function, and that `get_proposal_info` renders
+/// it.
+#[test]
+fn test_execute_nns_function_payload_must_decode() {
+    let driver = fake::FakeDriver::default();
+    let mut gov = Governance::new(
+        fixture_for_following(),
+        driver.get_fake_env(),
+        driver.get_fake_ledger(),
+    );
+    let proposal = |nns_function: NnsFunction, payload: Vec<u8>| Proposal {
+        title: Some("A Reasonable Title".to_string()),
+        summary: "test".to_string(),
+        action: Some(proposal::Action::ExecuteNnsFunction(ExecuteNnsFunction {
+            nns_function: nns_function as i32,
+            payload,
+        })),
+        ..Default::default()
+    };
+    let payload = StopOrStartNnsCanisterProposalPayload {
+        canister_id: REGISTRY_CANISTER_ID,
+        action: CanisterAction::Stop,
+    };
+
+    // This should fail: the payload is not a RerouteCanisterRangePayload.
+    let err = gov
+        .make_proposal(
+            &NeuronId { id: 1 },
+            // Must match neuron 1's serialized_id.
+            &PrincipalId::try_from(b"SID1".to_vec()).unwrap(),
+            &proposal(
+                NnsFunction::RerouteCanisterRange,
+                Encode!(&payload).unwrap(),
+            ),
+        )
+        .unwrap_err();
+    assert_eq!(err.error_type, ErrorType::InvalidProposal as i32);
+    assert!(
+        err.error_message
+            .contains("could not be decoded into a RerouteCanisterRangePayload"),
+        "{}",
+        err.error_message
+    );
+
+    // This should succeed
+    let pid = gov
+        .make_proposal(
+            &NeuronId { id: 1 },
+            // Must match neuron 1's serialized_id.
+            &PrincipalId::try_from(b"SID1".to_vec()).unwrap(),
+            &proposal(
+                NnsFunction::StopOrStartNnsCanister,
+                Encode!(&payload).unwrap(),
+            ),
+        )
+        .unwrap();
+    let rendering = gov
+        .get_proposal_info(&PrincipalId::new_anonymous(), pid)
+        .unwrap()
+        .payload_text_rendering
+        .unwrap();
+    assert!(rendering.contains("action: Stop"), "{}", rendering);
+
+    // Multi-queries don't render payloads.
+    let results = gov.list_proposals(
+        &PrincipalId::new_anonymous(),
+        &ListProposalInfo {
+            limit: 10,
+            ..Default::default()
+        },
+    );
+    assert_eq!(results.proposal_info[0].payload_text_rendering, None);
+}
+
 /// In this scenario, we simply test that you cannot make a proposal
 /// if you have insufficient stake (less than the reject fee).
 #[test]
@@ -7302,7 +7524,12 @@ fn test_max_number_of_proposals_with_ballots() {
                 summary: "NnsCanisterUpgrade should go through despite the limit".to_string(),
                 action: Some(proposal::Action::ExecuteNnsFunction(ExecuteNnsFunction {
                     nns_function: NnsFunction::NnsCanisterUpgrade as i32,
-                    payload: Vec::new(),
+                    payload: Encode!(&ChangeNnsCanisterProposalPayload::new(
+                        true,
+                        CanisterInstallMode::Upgrade,
+                        GOVERNANCE_CANISTER_ID,
+                    ))
+                    .unwrap(),
                 })),
                 ..Default::default()
             },
@@ -1,6 +1,9 @@
-use canister_test::Canister;
+use candid::Encode;
+use canister_test::{Canister, Project};
 use cycles_minting_canister::{
-    IcpXdrConversionRateCertifiedResponse, MEMO_CREATE_CANISTER, MEMO_TOP_UP_CANISTER,
+    CyclesCanisterUpgradePayload, IcpXdrConversionRateCertifiedResponse, IcpXdrRateQuote,
+    NotifyCreateCanister, NotifyCreateCanisterResult, NotifyError, NotifyTopUp, NotifyTopUpResult,
+    CREATE_CANISTER_REFUND_FEE, MEMO_CREATE_CANISTER, MEMO_TOP_UP_CANISTER,
 };
 use dfn_candid::candid_one;
 use dfn_protobuf::protobuf;
@@ -15,9 +18,13 @@ use ic_nns_test_utils::governance::submit_external_update_proposal;
 use ic_nns_test_utils::{
     governance::wait_for_final_state,
     ids::TEST_NEURON_1_ID,
-    itest_helpers::{local_test_on_nns_subnet, NnsCanisters, NnsInitPayloadsBuilder},
+    itest_helpers::{
+        append_inert, install_rust_canister, local_test_on_nns_subnet,
+        upgrade_nns_canister_with_arg_by_proposal, NnsCanisters, NnsInitPayloadsBuilder,
+    },
 };
 use ic_protobuf::registry::conversion_rate::v1::IcpXdrConversionRateRecord;
+use ic_types::Cycles;
 use ledger_canister::{
     AccountBalanceArgs, AccountIdentifier, BlockHeight, CyclesResponse, Memo, NotifyCanisterArgs,
     SendArgs, Subaccount, Tokens, DEFAULT_TRANSFER_FEE,
@@ -85,6 +92,97 @@ async fn set_icp_xdr_conversion_rate(
     );
 }
 
+/// Test that the CMC updates the ICP/XDR conversion rate from the quotes of an
+/// exchange-rate canister, ignoring outliers, once it is configured to do so
+/// by an upgrade.
+#[test]
+fn test_cmc_fetches_icp_xdr_conversion_rate_from_exchange_rate_canister() {
+    local_test_on_nns_subnet(|runtime| async move {
+        let nns_init_payload = NnsInitPayloadsBuilder::new()
+            .with_initial_invariant_compliant_mutations()
+            .with_test_neurons()
+            .build();
+        let nns_canisters = NnsCanisters::set_up(&runtime, nns_init_payload).await;
+
+        let mut exchange_rate_canister = runtime.create_canister_with_max_cycles().await.unwrap();
+        install_rust_canister(
+            &mut exchange_rate_canister,
+            "nns/integration_tests",
+            "mock-exchange-rate-canister",
+            &[],
+            None,
+        )
+        .await;
+
+        let now_seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let quotes: Vec<IcpXdrRateQuote> = [
+            ("a", 99_000),
+            ("b", 100_000),
+            ("c", 101_000),
+            ("outlier", 300_000),
+        ]
+        .iter()
+        .map(|(source, rate)| IcpXdrRateQuote {
+            source: source.to_string(),
+            timestamp_seconds: now_seconds,
+            xdr_permyriad_per_icp: *rate,
+        })
+        .collect();
+        let () = exchange_rate_canister
+            .update_("set_icp_xdr_rate_quotes", candid_one, quotes)
+            .await
+            .unwrap();
+
+        upgrade_nns_canister_with_arg_by_proposal(
+            &nns_canisters.cycles_minting,
+            &nns_canisters.governance,
+            &nns_canisters.root,
+            append_inert(Some(&Project::cargo_bin_maybe_use_path_relative_to_rs(
+                "nns/cmc",
+                "cycles-minting-canister",
+                &[],
+            ))),
+            Encode!(&CyclesCanisterUpgradePayload {
+                exchange_rate_canister_id: Some(exchange_rate_canister.canister_id()),
+            })
+            .unwrap(),
+        )
+        .await;
+
+        // The rate is set on one of the next heartbeats.
+        let mut attempts = 0;
+        let response = loop {
+            let response: Result<IcpXdrConversionRateCertifiedResponse, String> = nns_canisters
+                .cycles_minting
+                .query_("get_icp_xdr_conversion_rate", candid_one, ())
+                .await;
+            if let Ok(response) = response {
+                break response;
+            }
+            attempts += 1;
+            assert!(
+                attempts < 100,
+                "the CMC didn't fetch the rate from the exchange-rate canister"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        };
+        assert_eq!(response.data.timestamp_seconds, now_seconds);
+        assert_eq!(response.data.xdr_permyriad_per_icp, 100_000);
+
+        let average: IcpXdrConversionRateCertifiedResponse = nns_canisters
+            .cycles_minting
+            .query_("get_average_icp_xdr_conversion_rate", candid_one, ())
+            .await
+            .unwrap();
+        assert_eq!(average.data.xdr_permyriad_per_icp, 100_000);
+
+        Ok(())
+    });
+}
+
 /// Attempt to transfer ICP to create a "cycles wallet" (a minimal canister) and
 /// top-up an existing canister with cycles, but assert that both of these
 /// actions fail due to missing the ICP-to-XDR conversion rate, and assert any
@@ -334,6 +432,255 @@ fn test_cmc_mints_cycles_when_cmc_has_exchange_rate() {
     });
 }
 
+/// Test that a canister can be topped up by notifying the CMC about the
+/// payment with `notify_top_up`, and that the payment is used only once, also
+/// when the ledger notifies the CMC about it afterwards.
+#[test]
+fn test_cmc_notify_top_up() {
+    local_test_on_nns_subnet(|runtime| async move {
+        let account = AccountIdentifier::new(*TEST_USER1_PRINCIPAL, None);
+        let icpts = Tokens::new(100, 0).unwrap();
+        let rate = IcpXdrConversionRateRecord {
+            timestamp_seconds: 0,
+            xdr_permyriad_per_icp: 10_000,
+        };
+
+        let nns_init_payload = NnsInitPayloadsBuilder::new()
+            .with_initial_invariant_compliant_mutations()
+            .with_test_neurons()
+            .with_ledger_account(account, icpts)
+            .with_registry_icp_xdr_conversion_rate(&rate)
+            .build();
+
+        let nns_canisters = NnsCanisters::set_up(&runtime, nns_init_payload).await;
+
+        let total_cycles_minted_initial: u64 = nns_canisters
+            .cycles_minting
+            .query_("total_cycles_minted", protobuf, ())
+            .await
+            .unwrap();
+
+        // Top up the Governance canister.
+        let subaccount: Subaccount = GOVERNANCE_CANISTER_ID.get_ref().into();
+        let send_args = send_args_to_cmc(MEMO_TOP_UP_CANISTER, &subaccount);
+        let block_index = send_to_cmc(&nns_canisters.ledger, &send_args).await;
+
+        let notify_top_up = || NotifyTopUp {
+            block_index,
+            canister_id: GOVERNANCE_CANISTER_ID,
+        };
+        let expected_cycles = Cycles::from(10_000_000_000_000u64);
+        for _ in 0..2 {
+            let result: NotifyTopUpResult = nns_canisters
+                .cycles_minting
+                .update_("notify_top_up", candid_one, notify_top_up())
+                .await
+                .unwrap();
+            assert_eq!(result, Ok(expected_cycles));
+        }
+
+        // The payment can't be used for something else.
+        let result: NotifyCreateCanisterResult = nns_canisters
+            .cycles_minting
+            .update_(
+                "notify_create_canister",
+                candid_one,
+                NotifyCreateCanister {
+                    block_index,
+                    controller: *TEST_USER1_PRINCIPAL,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(matches!(result, Err(NotifyError::InvalidTransaction(_))));
+
+        // The ledger gets the outcome of the first notification.
+        let notify_args = NotifyCanisterArgs::new_from_send(
+            &send_args,
+            block_index,
+            CYCLES_MINTING_CANISTER_ID,
+            Some(subaccount),
+        )
+        .unwrap();
+        let cycles_response: CyclesResponse = nns_canisters
+            .ledger
+            .update_from_sender(
+                "notify_dfx",
+                candid_one,
+                notify_args,
+                &Sender::from_keypair(&TEST_USER1_KEYPAIR),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(cycles_response, CyclesResponse::ToppedUp(())));
+
+        let total_cycles_minted_final: u64 = nns_canisters
+            .cycles_minting
+            .query_("total_cycles_minted", protobuf, ())
+            .await
+            .unwrap();
+        assert_eq!(
+            u128::from(total_cycles_minted_final - total_cycles_minted_initial),
+            expected_cycles.get()
+        );
+
+        // Sending and notifying through the ledger each cost a transfer fee.
+        let expected_final_balance = Tokens::from_e8s(
+            icpts.get_e8s()
+                - Tokens::new(10, 0).unwrap().get_e8s()
+                - 2 * DEFAULT_TRANSFER_FEE.get_e8s(),
+        );
+        assert_eq!(
+            balance(&nns_canisters.ledger, account).await,
+            expected_final_balance
+        );
+
+        Ok(())
+    });
+}
+
+/// Test that a payment notified with `notify_create_canister` is refunded,
+/// minus the fees, when no canister can be created, and that notifying the
+/// CMC again returns the same refund.
+#[test]
+fn test_cmc_notify_create_canister_refunds_on_failure() {
+    local_test_on_nns_subnet(|runtime| async move {
+        let account = AccountIdentifier::new(*TEST_USER1_PRINCIPAL, None);
+        let icpts = Tokens::new(100, 0).unwrap();
+        let rate = IcpXdrConversionRateRecord {
+            timestamp_seconds: 0,
+            xdr_permyriad_per_icp: 10_000,
+        };
+
+        let nns_init_payload = NnsInitPayloadsBuilder::new()
+            .with_initial_invariant_compliant_mutations()
+            .with_test_neurons()
+            .with_ledger_account(account, icpts)
+            .with_registry_icp_xdr_conversion_rate(&rate)
+            .build();
+
+        let nns_canisters = NnsCanisters::set_up(&runtime, nns_init_payload).await;
+
+        let total_cycles_minted_initial: u64 = nns_canisters
+            .cycles_minting
+            .query_("total_cycles_minted", protobuf, ())
+            .await
+            .unwrap();
+
+        // There are no subnets in which the CMC can create canisters.
+        let controller = *TEST_USER1_PRINCIPAL;
+        let send_args = send_args_to_cmc(MEMO_CREATE_CANISTER, &(&controller).into());
+        let block_index = send_to_cmc(&nns_canisters.ledger, &send_args).await;
+
+        let notify_create_canister = || NotifyCreateCanister {
+            block_index,
+            controller,
+        };
+        let result: NotifyCreateCanisterResult = nns_canisters
+            .cycles_minting
+            .update_(
+                "notify_create_canister",
+                candid_one,
+                notify_create_canister(),
+            )
+            .await
+            .unwrap();
+        let refund_block = match result {
+            Err(NotifyError::Refunded {
+                block_index: Some(refund_block),
+                ..
+            }) => refund_block,
+            _ => panic!("Failed to be refunded: {:?}", result),
+        };
+        assert!(refund_block > block_index);
+
+        let result_again: NotifyCreateCanisterResult = nns_canisters
+            .cycles_minting
+            .update_(
+                "notify_create_canister",
+                candid_one,
+                notify_create_canister(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result_again, result);
+
+        // The refund pays the transfer fee and the refund fee.
+        let expected_final_balance = Tokens::from_e8s(
+            icpts.get_e8s()
+                - 2 * DEFAULT_TRANSFER_FEE.get_e8s()
+                - CREATE_CANISTER_REFUND_FEE.get_e8s(),
+        );
+        assert_eq!(
+            balance(&nns_canisters.ledger, account).await,
+            expected_final_balance
+        );
+
+        let total_cycles_minted_final: u64 = nns_canisters
+            .cycles_minting
+            .query_("total_cycles_minted", protobuf, ())
+            .await
+            .unwrap();
+        assert_eq!(total_cycles_minted_initial, total_cycles_minted_final);
+
+        // A block without a payment to the CMC can't be notified.
+        let result: NotifyCreateCanisterResult = nns_canisters
+            .cycles_minting
+            .update_(
+                "notify_create_canister",
+                candid_one,
+                NotifyCreateCanister {
+                    block_index: refund_block,
+                    controller,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(matches!(result, Err(NotifyError::InvalidTransaction(_))));
+
+        Ok(())
+    });
+}
+
+/// The arguments to send 10 ICP from `TEST_USER1_PRINCIPAL`s Ledger account
+/// to the given subaccount of the CMC.
+fn send_args_to_cmc(memo: Memo, subaccount: &Subaccount) -> SendArgs {
+    SendArgs {
+        memo,
+        amount: Tokens::new(10, 0).unwrap(),
+        fee: DEFAULT_TRANSFER_FEE,
+        from_subaccount: None,
+        to: AccountIdentifier::new(CYCLES_MINTING_CANISTER_ID.get(), Some(*subaccount)),
+        created_at_time: None,
+    }
+}
+
+/// Sends ICP from `TEST_USER1_PRINCIPAL`s Ledger account without notifying
+/// the CMC, and returns the index of the block.
+async fn send_to_cmc(ledger: &Canister<'_>, send_args: &SendArgs) -> BlockHeight {
+    ledger
+        .update_from_sender(
+            "send_dfx",
+            candid_one,
+            send_args.clone(),
+            &Sender::from_keypair(&TEST_USER1_KEYPAIR),
+        )
+        .await
+        .unwrap()
+}
+
+async fn balance(ledger: &Canister<'_>, account: AccountIdentifier) -> Tokens {
+    ledger
+        .query_from_sender(
+            "account_balance_pb",
+            protobuf,
+            AccountBalanceArgs { account },
+            &Sender::from_keypair(&TEST_USER1_KEYPAIR),
+        )
+        .await
+        .unwrap()
+}
+
 /// Sends 10 ICP from `TEST_USER1_PRINCIPAL`s Ledger account to the given
 /// subaccount of the CMC, which then, depending on `memo`, either tries to
 /// create a canister (aka a "cycles wallet") or top-up the canister whose
@@ -61,6 +61,8 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                adaptive_block_rate_config: None,
+                consensus_parameters: None,
             };
 
             let key = make_subnet_record_key(subnet_id);
@@ -108,6 +110,8 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 max_instructions_per_install_code: None,
                 features: None,
                 ecdsa_config: None,
+                adaptive_block_rate_config: None,
+                consensus_parameters: None,
                 max_number_of_canisters: Some(200),
                 ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
                 ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
@@ -180,6 +184,8 @@ fn test_submit_and_accept_update_subnet_proposal() {
                     ssh_readonly_access: vec!["pub_key_0".to_string()],
                     ssh_backup_access: vec!["pub_key_1".to_string()],
                     ecdsa_config: None,
+                    adaptive_block_rate_config: None,
+                    consensus_parameters: None,
                 }
             );
             Ok(())
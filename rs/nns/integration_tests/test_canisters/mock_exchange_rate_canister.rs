@@ -0,0 +1,26 @@
+//! A mock of an exchange-rate canister for testing that the cycles minting
+//! canister fetches the ICP/XDR conversion rate. It serves whatever quotes it
+//! was last given.
+use cycles_minting_canister::IcpXdrRateQuote;
+use dfn_candid::candid_one;
+use dfn_core::over;
+use lazy_static::lazy_static;
+use std::sync::RwLock;
+
+lazy_static! {
+    static ref QUOTES: RwLock<Vec<IcpXdrRateQuote>> = RwLock::new(vec![]);
+}
+
+#[export_name = "canister_update set_icp_xdr_rate_quotes"]
+fn set_icp_xdr_rate_quotes() {
+    over(candid_one, |quotes: Vec<IcpXdrRateQuote>| {
+        *QUOTES.write().unwrap() = quotes;
+    })
+}
+
+#[export_name = "canister_query get_icp_xdr_rate_quotes"]
+fn get_icp_xdr_rate_quotes() {
+    over(candid_one, |()| QUOTES.read().unwrap().clone())
+}
+
+fn main() {}
@@ -1,9 +1,11 @@
 use crate::error::{OrchestratorError, OrchestratorResult};
 use crate::registry_helper::RegistryHelper;
+use hyper::{body::HttpBody, Body, Request};
 use ic_canister_client::Sender;
 use ic_canister_client::{Agent, HttpClient};
 use ic_crypto::CryptoComponentForNonReplicaProcess;
 use ic_logger::{info, warn, ReplicaLogger};
+use ic_protobuf::registry::node::v1::NodeRecord;
 use ic_protobuf::types::v1 as pb;
 use ic_types::{
     consensus::catchup::{
@@ -11,14 +13,28 @@ use ic_types::{
     },
     consensus::HasHeight,
     crypto::*,
-    RegistryVersion, SubnetId,
+    NodeId, RegistryVersion, SubnetId,
 };
 use ic_utils::fs::write_protobuf_using_tmp_file;
+use prost::Message;
 use std::convert::TryFrom;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 use std::{fs::File, path::PathBuf};
+use tokio::net::TcpStream;
 use url::Url;
 
+const POOL_SNAPSHOT_PATH: &str = "/_/pool_snapshot";
+
+/// The maximum time to download a pool snapshot in.
+const POOL_SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Peers cap the snapshots they serve at 64 MiB
+/// (`ic_artifact_pool::pool_snapshot::MAX_POOL_SNAPSHOT_SIZE_BYTES`), so
+/// larger responses are rejected.
+const MAX_POOL_SNAPSHOT_SIZE_BYTES: usize = 64 * 1024 * 1024;
+
 /// Fetches catch-up packages from peers and local storage.
 ///
 /// CUPs are used to determine which version of the IC peers are running
@@ -29,6 +45,7 @@ pub(crate) struct CatchUpPackageProvider {
     cup_dir: PathBuf,
     client: HttpClient,
     crypto: Arc<dyn CryptoComponentForNonReplicaProcess + Send + Sync>,
+    pool_snapshot_port: Option<u16>,
     logger: ReplicaLogger,
 }
 
@@ -38,6 +55,7 @@ impl CatchUpPackageProvider {
         registry: Arc<RegistryHelper>,
         cup_dir: PathBuf,
         crypto: Arc<dyn CryptoComponentForNonReplicaProcess + Send + Sync>,
+        pool_snapshot_port: Option<u16>,
         logger: ReplicaLogger,
     ) -> Self {
         Self {
@@ -45,58 +63,70 @@ impl CatchUpPackageProvider {
             cup_dir,
             client: HttpClient::new(),
             crypto,
+            pool_snapshot_port,
             logger,
         }
     }
 
-    // Randomly selects a peer from the subnet and pulls its CUP. If this CUP is
-    // newer than the currently available one and it could be verified, then this CUP
-    // is returned. Note that it is acceptable to use a single peer, because CUPs are validated.
-    // If all `f` nodes serve unusable CUPs, we have a probability of 2/3 to hit
-    // a non-faulty node, so roughly on 4th attempt we should obtain the correct
-    // peer CUP.
-    async fn get_peer_cup(
+    // Randomly selects a peer from the subnet and returns its node id and
+    // record.
+    fn get_random_peer(
         &self,
         subnet_id: SubnetId,
         registry_version: RegistryVersion,
-        current_cup: Option<CUPWithOriginalProtobuf>,
-    ) -> Option<CUPWithOriginalProtobuf> {
-        use ic_protobuf::registry::node::v1::NodeRecord;
+    ) -> Option<(NodeId, NodeRecord)> {
         use ic_registry_client::helper::subnet::SubnetTransportRegistry;
-        use ic_types::NodeId;
         use rand::seq::SliceRandom;
 
-        let mut nodes: Vec<(NodeId, NodeRecord)> = self
+        let nodes: Vec<(NodeId, NodeRecord)> = self
             .registry
             .registry_client
             .get_subnet_transport_infos(subnet_id, registry_version)
             .ok()
             .flatten()
             .unwrap_or_else(Vec::new);
-        // Randomize the order of peer_urls
-        nodes.shuffle(&mut rand::thread_rng());
+        let peer = nodes.choose(&mut rand::thread_rng()).cloned();
+        if peer.is_none() {
+            warn!(
+                self.logger,
+                "Empty peer list for subnet {} at version {}", subnet_id, registry_version
+            );
+        }
+        peer
+    }
 
-        let peer_url = match &nodes.as_slice() {
-            [] => {
+    // Randomly selects a peer from the subnet and returns its URL.
+    fn get_random_peer_url(
+        &self,
+        subnet_id: SubnetId,
+        registry_version: RegistryVersion,
+    ) -> Option<Url> {
+        let (_, node_record) = self.get_random_peer(subnet_id, registry_version)?;
+        let http = node_record.http?;
+        let url = format!("http://[{}]:{}", http.ip_addr, http.port);
+        Url::parse(&url)
+            .map_err(|err| {
                 warn!(
                     self.logger,
-                    "Empty peer list for subnet {} at version {}", subnet_id, registry_version
+                    "Unable to parse the peer url {}: {:?}", url, err
                 );
-                return None;
-            }
-            [(_, node_record), ..] => {
-                let http = node_record.clone().http?;
-                let url = format!("http://[{}]:{}", http.ip_addr, http.port);
-                Url::parse(&url)
-                    .map_err(|err| {
-                        warn!(
-                            self.logger,
-                            "Unable to parse the peer url {}: {:?}", url, err
-                        );
-                    })
-                    .ok()?
-            }
-        };
+            })
+            .ok()
+    }
+
+    // Randomly selects a peer from the subnet and pulls its CUP. If this CUP is
+    // newer than the currently available one and it could be verified, then this CUP
+    // is returned. Note that it is acceptable to use a single peer, because CUPs are validated.
+    // If all `f` nodes serve unusable CUPs, we have a probability of 2/3 to hit
+    // a non-faulty node, so roughly on 4th attempt we should obtain the correct
+    // peer CUP.
+    async fn get_peer_cup(
+        &self,
+        subnet_id: SubnetId,
+        registry_version: RegistryVersion,
+        current_cup: Option<CUPWithOriginalProtobuf>,
+    ) -> Option<CUPWithOriginalProtobuf> {
+        let peer_url = self.get_random_peer_url(subnet_id, registry_version)?;
 
         let param = current_cup.as_ref().map(CatchUpPackageParam::from);
         let peer_cup = self
@@ -196,6 +226,154 @@ impl CatchUpPackageProvider {
         Ok(cup_file_path)
     }
 
+    /// Downloads a snapshot of the finalized chain above the given CUP from a
+    /// random peer and persists it, so that the replica can validate the chain
+    /// right after starting instead of waiting for it to be gossiped. The
+    /// artifacts of the snapshot are validated by the replica, so it is
+    /// acceptable to use a single peer.
+    ///
+    /// The snapshot is optional: if it cannot be downloaded or was taken above
+    /// a different CUP, no snapshot is persisted and the replica starts
+    /// without one.
+    pub(crate) async fn download_pool_snapshot(
+        &self,
+        subnet_id: SubnetId,
+        cup: &CUPWithOriginalProtobuf,
+    ) {
+        let path = self.get_pool_snapshot_path();
+        if !self.remove_pool_snapshot() {
+            return;
+        }
+        let snapshot = match tokio::time::timeout(
+            POOL_SNAPSHOT_TIMEOUT,
+            self.fetch_pool_snapshot(subnet_id, self.registry.get_latest_version()),
+        )
+        .await
+        {
+            Ok(Ok(snapshot)) => snapshot,
+            Ok(Err(e)) => {
+                warn!(self.logger, "Failed to fetch a pool snapshot: {}", e);
+                return;
+            }
+            Err(_) => {
+                warn!(
+                    self.logger,
+                    "Fetching a pool snapshot timed out after {:?}", POOL_SNAPSHOT_TIMEOUT
+                );
+                return;
+            }
+        };
+        if snapshot
+            .catch_up_package
+            .as_ref()
+            .map(|snapshot_cup| &snapshot_cup.content)
+            != Some(&cup.protobuf.content)
+        {
+            info!(
+                self.logger,
+                "Ignoring the pool snapshot, which was taken above a different CUP"
+            );
+            return;
+        }
+        match write_protobuf_using_tmp_file(&path, &snapshot) {
+            Ok(()) => info!(
+                self.logger,
+                "Persisted a pool snapshot with {} blocks above the CUP at height {}",
+                snapshot.block_proposals.len(),
+                cup.cup.height()
+            ),
+            Err(e) => warn!(
+                self.logger,
+                "Failed to persist the pool snapshot to {:?}: {:?}", path, e
+            ),
+        }
+    }
+
+    /// Removes a previously downloaded pool snapshot, so that the replica
+    /// does not load a stale one. Returns false if the snapshot exists but
+    /// could not be removed.
+    pub(crate) fn remove_pool_snapshot(&self) -> bool {
+        let path = self.get_pool_snapshot_path();
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!(self.logger, "Failed to remove {:?}: {:?}", path, e);
+                return false;
+            }
+        }
+        true
+    }
+
+    // Fetches a pool snapshot from a random peer. Peers only serve snapshots
+    // to the nodes of their subnet, so this node authenticates itself with
+    // its TLS certificate, and checks the one of the peer.
+    async fn fetch_pool_snapshot(
+        &self,
+        subnet_id: SubnetId,
+        registry_version: RegistryVersion,
+    ) -> Result<pb::ConsensusPoolSnapshot, String> {
+        let port = self
+            .pool_snapshot_port
+            .ok_or_else(|| "No pool snapshot port is configured".to_string())?;
+        let (node_id, node_record) = self
+            .get_random_peer(subnet_id, registry_version)
+            .ok_or_else(|| format!("No peers found in subnet {}", subnet_id))?;
+        let ip_addr: IpAddr = node_record
+            .http
+            .ok_or_else(|| format!("Node {} has no HTTP endpoint", node_id))?
+            .ip_addr
+            .parse()
+            .map_err(|e| format!("Invalid IP address of node {}: {:?}", node_id, e))?;
+
+        let tcp_stream = TcpStream::connect(SocketAddr::new(ip_addr, port))
+            .await
+            .map_err(|e| format!("Failed to connect to node {}: {}", node_id, e))?;
+        let tls_stream = self
+            .crypto
+            .perform_tls_client_handshake(tcp_stream, node_id, registry_version)
+            .await
+            .map_err(|e| format!("TLS handshake with node {} failed: {}", node_id, e))?;
+        let (mut sender, connection) = hyper::client::conn::handshake(tls_stream)
+            .await
+            .map_err(|e| format!("HTTP handshake with node {} failed: {}", node_id, e))?;
+        tokio::spawn(connection);
+
+        let request = Request::post(POOL_SNAPSHOT_PATH)
+            .body(Body::empty())
+            .map_err(|e| format!("Failed to build the request: {}", e))?;
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|e| format!("Request to node {} failed: {}", node_id, e))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Node {} responded with status {}",
+                node_id,
+                response.status()
+            ));
+        }
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(|e| format!("Failed to read the response: {}", e))?;
+            if bytes.len() + chunk.len() > MAX_POOL_SNAPSHOT_SIZE_BYTES {
+                return Err(format!(
+                    "The pool snapshot of node {} exceeds {} bytes",
+                    node_id, MAX_POOL_SNAPSHOT_SIZE_BYTES
+                ));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        pb::ConsensusPoolSnapshot::decode(&bytes[..])
+            .map_err(|e| format!("Failed to deserialize pool snapshot from protobuf: {:?}", e))
+    }
+
+    /// The path that should be used to save the pool snapshot downloaded
+    /// along with the CUP.
+    pub fn get_pool_snapshot_path(&self) -> PathBuf {
+        self.cup_dir
+            .join("pool_snapshot.types.v1.ConsensusPoolSnapshot.pb")
+    }
+
     /// The path that should be used to save the CUP for the assigned subnet.
     /// Includes the specific type encoded in the file for future-proofing and
     /// ease of debugging.
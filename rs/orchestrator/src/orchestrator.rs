@@ -151,6 +151,10 @@ impl Orchestrator {
             Arc::clone(&registry),
             args.cup_dir.clone(),
             crypto.clone(),
+            config
+                .http_handler
+                .pool_snapshot_listen_addr
+                .map(|addr| addr.port()),
             logger.clone(),
         ));
 
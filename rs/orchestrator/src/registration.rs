@@ -325,7 +325,7 @@ fn protobuf_to_vec<M: Message>(entry: M) -> Vec<u8> {
 mod tests {
     use super::*;
     use ic_test_utilities::with_test_replica_logger;
-    use ic_types::transport::TransportFlowConfig;
+    use ic_types::transport::{TransportBackend, TransportFlowConfig};
 
     #[test]
     fn default_http_config_endpoint_succeeds() {
@@ -352,6 +352,7 @@ mod tests {
                     queue_size: 1,
                 },
             ],
+            transport_backend: TransportBackend::Tcp,
         };
 
         with_test_replica_logger(|log| {
@@ -98,8 +98,11 @@ impl ReleasePackage {
         };
 
         let cup = self.cup_provider.get_latest_cup(subnet_id).await?;
-        // If the latest CUP is newer than the local one, persist it.
-        if Some(cup.cup.content.height()) > local_cup.map(|cup| cup.content.height()) {
+        // If the latest CUP is newer than the local one, persist it. The
+        // replica then has to catch up from this CUP.
+        let catching_up =
+            Some(cup.cup.content.height()) > local_cup.map(|cup| cup.content.height());
+        if catching_up {
             self.cup_provider.persist_cup(&cup)?;
         }
 
@@ -127,6 +130,15 @@ impl ReleasePackage {
 
         // Now we know the subnet_id and we're assigned; start the replica if necessary.
         if !self.replica_process.lock().unwrap().is_running() {
+            // A pool snapshot only helps a replica that starts from a new CUP,
+            // otherwise its own pool already holds the chain above it.
+            if catching_up {
+                self.cup_provider
+                    .download_pool_snapshot(subnet_id, &cup)
+                    .await;
+            } else {
+                self.cup_provider.remove_pool_snapshot();
+            }
             return self.start_replica(&self.replica_version, subnet_id);
         }
 
@@ -308,7 +320,7 @@ impl ReleasePackage {
             .as_path()
             .display()
             .to_string();
-        let cmd = vec![
+        let mut cmd = vec![
             format!("--replica-version={}", replica_version.as_ref()),
             format!(
                 "--config-file={}",
@@ -320,6 +332,13 @@ impl ReleasePackage {
             ),
             format!("--force-subnet={}", subnet_id),
         ];
+        let pool_snapshot_path = self.cup_provider.get_pool_snapshot_path();
+        if pool_snapshot_path.exists() {
+            cmd.push(format!(
+                "--pool-snapshot={}",
+                pool_snapshot_path.as_path().display().to_string()
+            ));
+        }
 
         self.replica_process
             .lock()
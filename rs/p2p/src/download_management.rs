@@ -70,8 +70,8 @@ use ic_metrics::MetricsRegistry;
 use ic_protobuf::p2p::v1 as pb;
 use ic_protobuf::proxy::ProtoProxy;
 use ic_types::{
-    artifact::{Artifact, ArtifactId},
-    chunkable::{ArtifactErrorCode, ChunkId},
+    artifact::{Artifact, ArtifactId, ArtifactTag, Priority},
+    chunkable::{ArtifactChunkData, ArtifactErrorCode, ChunkId, CHUNKID_UNIT_CHUNK},
     crypto::CryptoHash,
     p2p::GossipAdvert,
     transport::{FlowTag, TransportPayload},
@@ -87,7 +87,7 @@ use crate::{
     event_handler::P2PEventHandlerControl,
     gossip_protocol::{
         GossipAdvertAction, GossipAdvertSendRequest, GossipChunk, GossipChunkRequest,
-        GossipMessage, GossipRetransmissionRequest, Percentage,
+        GossipMessage, GossipPushedArtifact, GossipRetransmissionRequest, Percentage,
     },
     metrics::{DownloadManagementMetrics, DownloadPrioritizerMetrics},
     utils::FlowMapper,
@@ -109,6 +109,7 @@ use std::{
 use ic_interfaces::artifact_manager::OnArtifactError::ArtifactPoolError;
 use ic_interfaces::artifact_pool::ArtifactPoolError::ArtifactReplicaVersionError;
 use ic_interfaces::consensus_pool::ConsensusPoolCache;
+use ic_interfaces::p2p::PeerArtifactCounts;
 use ic_logger::replica_logger::ReplicaLogger;
 use ic_logger::{info, trace, warn};
 use ic_protobuf::registry::node::v1::NodeRecord;
@@ -139,6 +140,10 @@ pub(crate) trait DownloadManager {
     /// ID.
     fn on_chunk(&self, gossip_chunk: GossipChunk, peer_id: NodeId);
 
+    /// The method reacts to an artifact pushed by the peer with the given node
+    /// ID together with its advert.
+    fn on_pushed_artifact(&self, pushed_artifact: GossipPushedArtifact, peer_id: NodeId);
+
     /// The method reacts to a disconnect event event for the peer with the
     /// given node ID.
     fn peer_connection_down(&self, peer_id: NodeId);
@@ -171,6 +176,10 @@ pub(crate) trait DownloadManager {
     /// b) Check for chunk download timeouts.</br>
     /// c) Poll the registry for subnet membership changes.
     fn on_timer(&self, event_handler: Arc<dyn P2PEventHandlerControl>);
+
+    /// The method returns the number of adverts and requested chunks tracked
+    /// for each current peer.
+    fn get_peer_artifact_counts(&self) -> BTreeMap<NodeId, PeerArtifactCounts>;
 }
 
 /// The peer manager manages the list of current peers.
@@ -234,6 +243,10 @@ pub struct PeerContext {
     disconnect_time: Option<SystemTime>,
     /// The time of the last processed retransmission request from this peer.
     last_retransmission_request_processed_time: Instant,
+    /// Whether the peer accepts pushed artifacts, as announced in its last
+    /// retransmission request. Peers running a version that cannot decode
+    /// pushed artifacts do not announce it, and only receive adverts.
+    accepts_pushed_artifacts: bool,
 }
 
 /// A `NodeId` can be converted into a `PeerContext`.
@@ -246,6 +259,7 @@ impl From<NodeId> for PeerContext {
             requested: HashMap::new(),
             disconnect_time: None,
             last_retransmission_request_processed_time: Instant::now(),
+            accepts_pushed_artifacts: false,
         }
     }
 }
@@ -327,7 +341,25 @@ impl DownloadManager for DownloadManagerImpl {
             .adverts_by_action
             .with_label_values(&[label])
             .inc_by(peers.len() as u64);
-        self.send_advert_to_peer_list(advert_request.advert, peers);
+        // The advert is always sent, also to the peers the artifact is pushed
+        // to, so that they can still download the artifact if they drop the
+        // push, e.g., because it exceeds their quota for this node.
+        let pushed_artifact = self.get_pushed_artifact(&advert_request.advert);
+        self.send_advert_to_peer_list(advert_request.advert, peers.clone());
+        if let Some(pushed_artifact) = pushed_artifact {
+            let push_peers = {
+                let current_peers = self.current_peers.lock().unwrap();
+                peers
+                    .into_iter()
+                    .filter(|peer_id| {
+                        current_peers
+                            .get(peer_id)
+                            .map_or(false, |peer_context| peer_context.accepts_pushed_artifacts)
+                    })
+                    .collect()
+            };
+            self.send_pushed_artifact_to_peer_list(pushed_artifact, push_peers);
+        }
     }
 
     /// The method downloads chunks for adverts with the highest priority from
@@ -434,18 +466,9 @@ impl DownloadManager for DownloadManagerImpl {
                 integrity_hash: gossip_chunk.integrity_hash.clone(),
                 chunk_id: gossip_chunk.chunk_id,
             }) {
-                let artifact_type = match &gossip_chunk.artifact_id {
-                    ArtifactId::ConsensusMessage(_) => "consensus",
-                    ArtifactId::IngressMessage(_) => "ingress",
-                    ArtifactId::CertificationMessage(_) => "certification",
-                    ArtifactId::DkgMessage(_) => "dkg",
-                    ArtifactId::EcdsaMessage(_) => "ecdsa",
-                    ArtifactId::FileTreeSync(_) => "file_tree_sync",
-                    ArtifactId::StateSync(_) => "state_sync",
-                };
                 self.metrics
                     .chunk_delivery_time
-                    .with_label_values(&[artifact_type])
+                    .with_label_values(&[artifact_type(&gossip_chunk.artifact_id)])
                     .observe(tracker.requested_instant.elapsed().as_millis() as f64);
             } else {
                 trace!(
@@ -576,19 +599,7 @@ impl DownloadManager for DownloadManagerImpl {
             }
         };
         // Check if the artifact's integrity hash matches the advertised hash
-        // This construction to compute the integrity hash over all variants of an enum
-        // may be updated in the future.
-        let expected_ih = match &completed_artifact {
-            Artifact::ConsensusMessage(msg) => ic_crypto::crypto_hash(msg).get(),
-            Artifact::IngressMessage(msg) => ic_crypto::crypto_hash(msg).get(),
-            Artifact::CertificationMessage(msg) => ic_crypto::crypto_hash(msg).get(),
-            Artifact::DkgMessage(msg) => ic_crypto::crypto_hash(msg).get(),
-            Artifact::EcdsaMessage(msg) => ic_crypto::crypto_hash(msg).get(),
-            // FileTreeSync is not of ArtifactKind kind, and it's used only for testing.
-            // Thus, we make up the integrity_hash.
-            Artifact::FileTreeSync(_msg) => CryptoHash(vec![]),
-            Artifact::StateSync(msg) => ic_crypto::crypto_hash(msg).get(),
-        };
+        let expected_ih = compute_integrity_hash(&completed_artifact);
 
         if expected_ih != advert.integrity_hash {
             warn!(
@@ -621,6 +632,18 @@ impl DownloadManager for DownloadManagerImpl {
             .unwrap()
             .put(advert.integrity_hash.clone(), ());
 
+        // Record the time since the first advert for the artifact was received.
+        if let Ok(advert_tracker) = self
+            .prioritizer
+            .get_advert_tracker(&gossip_chunk.artifact_id, &gossip_chunk.integrity_hash)
+        {
+            let received_instant = advert_tracker.read().unwrap().received_instant;
+            self.metrics
+                .artifact_delivery_time
+                .with_label_values(&[artifact_type(&gossip_chunk.artifact_id), "pull"])
+                .observe(received_instant.elapsed().as_millis() as f64);
+        }
+
         // The artifact is complete and the integrity hash is okay.
         // Clean up the adverts for all peers:
         let _ = self.prioritizer.delete_advert(
@@ -642,24 +665,134 @@ impl DownloadManager for DownloadManagerImpl {
             peer_id,
             gossip_chunk.artifact_id
         );
+        self.deliver_artifact(completed_artifact, advert, peer_id);
+    }
+
+    /// The method reacts to an artifact pushed by the peer with the given node
+    /// ID together with its advert.
+    ///
+    /// The artifact is dropped if it is already in the artifact pool or has
+    /// been received recently (e.g., pushed by another peer). A pushed artifact
+    /// is subject to the same priority function and quota as a downloaded one:
+    /// if its priority is `Drop`, it is dropped; if it is stashed, exceeds the
+    /// remaining quota of the peer or does not fit in a single chunk, only its
+    /// advert is processed, so that the artifact can be downloaded later.
+    /// Otherwise, any pending adverts and downloads for it are cleaned up and
+    /// it is handed over to the artifact manager.
+    fn on_pushed_artifact(&self, pushed_artifact: GossipPushedArtifact, peer_id: NodeId) {
+        let received_instant = Instant::now();
+        let GossipPushedArtifact {
+            advert,
+            artifact_chunk,
+        } = pushed_artifact;
+        self.metrics.pushed_artifacts_received.inc();
+
+        if self.artifact_manager.has_artifact(&advert.artifact_id)
+            || self
+                .receive_check_caches
+                .read()
+                .unwrap()
+                .values()
+                .any(|cache| cache.contains(&advert.integrity_hash))
+        {
+            self.metrics.pushed_artifacts_duplicate.inc();
+            return;
+        }
+
+        match self.prioritizer.peek_priority(&advert) {
+            Ok(Priority::Drop) => {
+                self.reject_pushed_artifact("priority_drop");
+                return;
+            }
+            Ok(Priority::Stash) => {
+                self.reject_pushed_artifact("priority_stash");
+                self.on_advert(advert, peer_id);
+                return;
+            }
+            _ => (),
+        }
+
         match self
             .artifact_manager
-            .on_artifact(completed_artifact, advert, &peer_id)
+            .get_remaining_quota(ArtifactTag::from(&advert.artifact_id), peer_id)
         {
-            Ok(_) => (),
-            // If this Replica is running an unexpected version, it will log
-            // an unhelpfully large volume of `ArtifactReplicaVersionError`s.
-            // Here we set the log rate at a more appropriate level.
-            Err(ArtifactPoolError(ArtifactReplicaVersionError(err))) => warn!(
-                every_n_seconds => 5,
-                self.log,
-                "Artifact is not processed successfully by Artifact Manager: {:?}", err
-            ),
-            Err(err) => warn!(
+            Some(quota) if quota >= advert.size => (),
+            _ => {
+                self.reject_pushed_artifact("quota");
+                self.on_advert(advert, peer_id);
+                return;
+            }
+        }
+
+        // Only single-chunked artifacts can be pushed.
+        let completed_artifact = match artifact_chunk.artifact_chunk_data {
+            ArtifactChunkData::UnitChunkData(artifact) => artifact,
+            ArtifactChunkData::SemiStructuredChunkData(_) => {
+                self.reject_pushed_artifact("multi_chunk");
+                self.on_advert(advert, peer_id);
+                return;
+            }
+        };
+
+        let expected_ih = compute_integrity_hash(&completed_artifact);
+        if expected_ih != advert.integrity_hash {
+            warn!(
                 self.log,
-                "Artifact is not processed successfully by Artifact Manager: {:?}", err
-            ),
+                "The integrity hash for {:?} pushed by peer {:?} does not match. Expected {:?}, got {:?}.",
+                advert.artifact_id,
+                peer_id.get(),
+                expected_ih,
+                advert.integrity_hash;
+            );
+            self.metrics.integrity_hash_check_failed.inc();
+            return;
         }
+
+        // Add the artifact hash to the receive check set of the peer.
+        match self.receive_check_caches.write().unwrap().get_mut(&peer_id) {
+            Some(cache) => {
+                cache.put(advert.integrity_hash.clone(), ());
+            }
+            None => {
+                warn!(every_n_seconds => 30, self.log, "Dropping pushed artifact from unknown node {:?}", peer_id);
+                return;
+            }
+        }
+
+        // As for downloaded artifacts, the delivery time is measured from the
+        // first advert for the artifact, which is sent alongside the push and
+        // may have arrived before it.
+        let first_advert_instant = self
+            .prioritizer
+            .get_advert_tracker(&advert.artifact_id, &advert.integrity_hash)
+            .map(|advert_tracker| advert_tracker.read().unwrap().received_instant)
+            .unwrap_or(received_instant)
+            .min(received_instant);
+
+        // Adverts and downloads of the artifact are no longer needed.
+        self.artifacts_under_construction
+            .write()
+            .unwrap()
+            .remove_tracker(&advert.integrity_hash);
+        let _ = self.prioritizer.delete_advert(
+            &advert.artifact_id,
+            &advert.integrity_hash,
+            AdvertTrackerFinalAction::Success,
+        );
+
+        self.metrics.artifacts_received.inc();
+        self.metrics
+            .artifact_delivery_time
+            .with_label_values(&[artifact_type(&advert.artifact_id), "push"])
+            .observe(first_advert_instant.elapsed().as_millis() as f64);
+        trace!(
+            self.log,
+            "Node-{:?} received pushed artifact from Node-{:?} ->{:?}",
+            self.node_id,
+            peer_id,
+            advert.artifact_id
+        );
+        self.deliver_artifact(completed_artifact, advert, peer_id);
     }
 
     /// The method reacts to a disconnect event event for the peer with the
@@ -670,6 +803,9 @@ impl DownloadManager for DownloadManagerImpl {
         let mut current_peers = self.current_peers.lock().unwrap();
         if let Some(peer_context) = current_peers.get_mut(&peer_id) {
             peer_context.disconnect_time = Some(now);
+            // The peer may come back with another version, and then announces
+            // again whether it accepts pushed artifacts.
+            peer_context.accepts_pushed_artifacts = false;
             trace!(
                 self.log,
                 "Gossip On Disconnect event with peer: {:?} at time {:?}",
@@ -743,6 +879,7 @@ impl DownloadManager for DownloadManagerImpl {
                 }
             })
             .map_or_else(Err, |peer_context| {
+                peer_context.accepts_pushed_artifacts = gossip_re_request.accepts_pushed_artifacts;
                 let elapsed_ms = peer_context
                     .last_retransmission_request_processed_time
                     .elapsed()
@@ -773,7 +910,10 @@ impl DownloadManager for DownloadManagerImpl {
     /// node ID.
     fn send_retransmission_request(&self, peer_id: NodeId) {
         let filter = self.artifact_manager.get_filter();
-        let message = GossipMessage::RetransmissionRequest(GossipRetransmissionRequest { filter });
+        let message = GossipMessage::RetransmissionRequest(GossipRetransmissionRequest {
+            filter,
+            accepts_pushed_artifacts: true,
+        });
         let flow_tag = self.flow_mapper.map(&message);
         let start_time = Instant::now();
         self.transport_send(message, peer_id, flow_tag)
@@ -843,6 +983,31 @@ impl DownloadManager for DownloadManagerImpl {
             let _ = self.download_next(peer_id);
         }
     }
+
+    /// The method returns the number of adverts and requested chunks tracked
+    /// for each current peer.
+    fn get_peer_artifact_counts(&self) -> BTreeMap<NodeId, PeerArtifactCounts> {
+        // The peer context lock is acquired before the prioritizer lock, as
+        // required by the locking hierarchy.
+        let current_peers = self.current_peers.lock().unwrap();
+        current_peers
+            .iter()
+            .map(|(peer_id, peer_context)| {
+                let adverts = self
+                    .prioritizer
+                    .get_peer_priority_queues(*peer_id)
+                    .peer_advert_map_ref
+                    .read()
+                    .unwrap()
+                    .count_by_artifact_tag();
+                let counts = PeerArtifactCounts {
+                    adverts,
+                    requested_chunks: peer_context.requested.len(),
+                };
+                (*peer_id, counts)
+            })
+            .collect()
+    }
 }
 
 impl DownloadManagerImpl {
@@ -1052,6 +1217,84 @@ impl DownloadManagerImpl {
             })
     }
 
+    /// The method counts a pushed artifact that was not accepted for the given
+    /// reason.
+    fn reject_pushed_artifact(&self, reason: &str) {
+        self.metrics
+            .pushed_artifacts_rejected
+            .with_label_values(&[reason])
+            .inc();
+    }
+
+    /// The method hands the given artifact received from the given peer over
+    /// to the artifact manager.
+    fn deliver_artifact(&self, artifact: Artifact, advert: GossipAdvert, peer_id: NodeId) {
+        match self
+            .artifact_manager
+            .on_artifact(artifact, advert, &peer_id)
+        {
+            Ok(_) => (),
+            // If this Replica is running an unexpected version, it will log
+            // an unhelpfully large volume of `ArtifactReplicaVersionError`s.
+            // Here we set the log rate at a more appropriate level.
+            Err(ArtifactPoolError(ArtifactReplicaVersionError(err))) => warn!(
+                every_n_seconds => 5,
+                self.log,
+                "Artifact is not processed successfully by Artifact Manager: {:?}", err
+            ),
+            Err(err) => warn!(
+                self.log,
+                "Artifact is not processed successfully by Artifact Manager: {:?}", err
+            ),
+        }
+    }
+
+    /// The method returns the given advert together with the artifact if the
+    /// artifact is small enough to be pushed to peers, according to the push
+    /// size threshold of its artifact client.
+    fn get_pushed_artifact(&self, advert: &GossipAdvert) -> Option<GossipPushedArtifact> {
+        let threshold = self
+            .artifact_manager
+            .get_push_size_threshold(ArtifactTag::from(&advert.artifact_id))?;
+        if advert.size > threshold {
+            return None;
+        }
+        let artifact_chunk = self
+            .artifact_manager
+            .get_validated_by_identifier(&advert.artifact_id)?
+            .get_chunk(ChunkId::from(CHUNKID_UNIT_CHUNK))?;
+        Some(GossipPushedArtifact {
+            advert: advert.clone(),
+            artifact_chunk,
+        })
+    }
+
+    /// The method pushes the given artifact to the given list of peers.
+    fn send_pushed_artifact_to_peer_list(
+        &self,
+        pushed_artifact: GossipPushedArtifact,
+        peer_ids: Vec<NodeId>,
+    ) {
+        let artifact_id = pushed_artifact.advert.artifact_id.clone();
+        let message = GossipMessage::PushedArtifact(pushed_artifact);
+        let flow_tag = self.flow_mapper.map(&message);
+        for peer_id in peer_ids {
+            self.transport_send(message.clone(), peer_id, flow_tag)
+                .map(|_| self.metrics.pushed_artifacts_sent.inc())
+                .unwrap_or_else(|_e| {
+                    // Ignore push failures, like advert send failures.
+                    self.metrics.pushed_artifacts_send_failed.inc();
+                });
+            trace!(
+                self.log,
+                "Node-{:?} pushed artifact ->{:?} {:?}",
+                self.node_id,
+                peer_id,
+                artifact_id
+            );
+        }
+    }
+
     /// The method sends the given advert to the given list of peers.
     fn send_advert_to_peer_list(&self, gossip_advert: GossipAdvert, peer_ids: Vec<NodeId>) {
         let message = GossipMessage::Advert(gossip_advert.clone());
@@ -1401,6 +1644,41 @@ impl DownloadManagerImpl {
     }
 }
 
+/// The function returns the label of the given artifact's type used in
+/// metrics.
+fn artifact_type(artifact_id: &ArtifactId) -> &'static str {
+    match artifact_id {
+        ArtifactId::ConsensusMessage(_) => "consensus",
+        ArtifactId::IngressMessage(_) => "ingress",
+        ArtifactId::CertificationMessage(_) => "certification",
+        ArtifactId::DkgMessage(_) => "dkg",
+        ArtifactId::EcdsaMessage(_) => "ecdsa",
+        ArtifactId::FileTreeSync(_) => "file_tree_sync",
+        ArtifactId::StateSync(_) => "state_sync",
+        ArtifactId::EquivocationEvidence(_) => "equivocation_evidence",
+    }
+}
+
+/// The function computes the integrity hash of the given artifact, which is
+/// compared against the advertised integrity hash.
+///
+/// This construction to compute the integrity hash over all variants of an enum
+/// may be updated in the future.
+fn compute_integrity_hash(artifact: &Artifact) -> CryptoHash {
+    match artifact {
+        Artifact::ConsensusMessage(msg) => ic_crypto::crypto_hash(msg).get(),
+        Artifact::IngressMessage(msg) => ic_crypto::crypto_hash(msg).get(),
+        Artifact::CertificationMessage(msg) => ic_crypto::crypto_hash(msg).get(),
+        Artifact::DkgMessage(msg) => ic_crypto::crypto_hash(msg).get(),
+        Artifact::EcdsaMessage(msg) => ic_crypto::crypto_hash(msg).get(),
+        // FileTreeSync is not of ArtifactKind kind, and it's used only for testing.
+        // Thus, we make up the integrity_hash.
+        Artifact::FileTreeSync(_msg) => CryptoHash(vec![]),
+        Artifact::StateSync(msg) => ic_crypto::crypto_hash(msg).get(),
+        Artifact::EquivocationEvidence(msg) => ic_crypto::crypto_hash(msg).get(),
+    }
+}
+
 impl PeerManagerImpl {
     fn new(
         node_id: NodeId,
@@ -1648,10 +1926,10 @@ pub mod tests {
             Ok(())
         }
 
-        /// The method to test if an artifact is available is not implemented as
-        /// it is not used.
+        /// The method always returns "false" as the test artifact manager does
+        /// not store artifacts.
         fn has_artifact(&self, _message_id: &artifact::ArtifactId) -> bool {
-            unimplemented!()
+            false
         }
 
         /// The method to return a validated artifact is not implemented as
@@ -1686,6 +1964,11 @@ pub mod tests {
             Some(self.quota)
         }
 
+        /// The method returns `None` as artifacts are never pushed.
+        fn get_push_size_threshold(&self, _tag: artifact::ArtifactTag) -> Option<usize> {
+            None
+        }
+
         /// The method returns the priority function that always uses
         /// Priority::FetchAll.
         fn get_priority_function(&self, _: artifact::ArtifactTag) -> Option<ArtifactPriorityFn> {
@@ -2397,6 +2680,135 @@ pub mod tests {
         );
     }
 
+    /// The function returns the artifact with the given number pushed together
+    /// with the given advert.
+    fn pushed_artifact_test_create_pushed_artifact(
+        advert: GossipAdvert,
+        number: u32,
+    ) -> GossipPushedArtifact {
+        let payload = Artifact::DkgMessage(receive_check_test_create_message(number));
+        GossipPushedArtifact {
+            advert,
+            artifact_chunk: ArtifactChunk {
+                chunk_id: ChunkId::from(CHUNKID_UNIT_CHUNK),
+                witness: Vec::with_capacity(0),
+                artifact_chunk_data: ArtifactChunkData::UnitChunkData(payload),
+            },
+        }
+    }
+
+    /// This test verifies that a pushed artifact is delivered once, replaces
+    /// pending downloads of the same artifact and that duplicates as well as
+    /// artifacts with incorrect integrity hashes are dropped.
+    #[tokio::test]
+    async fn pushed_artifact_test() {
+        // Initialize the logger and download manager for the test.
+        let logger = p2p_test_setup_logger();
+        let download_manager =
+            new_test_download_manager(3, &logger, tokio::runtime::Handle::current());
+        let adverts = receive_check_test_create_adverts(0..2);
+
+        // Node 1 advertises the artifact, which is then pushed by node 2.
+        download_manager.on_advert(adverts[0].clone(), node_test_id(1));
+        download_manager.on_pushed_artifact(
+            pushed_artifact_test_create_pushed_artifact(adverts[0].clone(), 0),
+            node_test_id(2),
+        );
+        assert_eq!(download_manager.metrics.artifacts_received.get(), 1);
+        let receive_check_caches = download_manager.receive_check_caches.read().unwrap();
+        let cache = &receive_check_caches.get(&node_test_id(2)).unwrap();
+        assert!(cache.contains(&adverts[0].integrity_hash));
+        std::mem::drop(receive_check_caches);
+
+        // The artifact is no longer downloaded from node 1.
+        let chunks_to_be_downloaded = download_manager
+            .download_next_compute_work(node_test_id(1))
+            .unwrap();
+        assert!(chunks_to_be_downloaded.is_empty());
+
+        // The same artifact pushed by node 1 is dropped.
+        download_manager.on_pushed_artifact(
+            pushed_artifact_test_create_pushed_artifact(adverts[0].clone(), 0),
+            node_test_id(1),
+        );
+        assert_eq!(download_manager.metrics.pushed_artifacts_duplicate.get(), 1);
+        assert_eq!(download_manager.metrics.artifacts_received.get(), 1);
+
+        // An artifact that does not match the integrity hash of its advert is
+        // dropped.
+        download_manager.on_pushed_artifact(
+            pushed_artifact_test_create_pushed_artifact(adverts[1].clone(), 2),
+            node_test_id(1),
+        );
+        assert_eq!(
+            download_manager.metrics.integrity_hash_check_failed.get(),
+            1
+        );
+        assert_eq!(download_manager.metrics.artifacts_received.get(), 1);
+        assert_eq!(download_manager.metrics.pushed_artifacts_received.get(), 3);
+    }
+
+    /// This test verifies that a pushed artifact that exceeds the remaining
+    /// quota of the peer or does not fit in a single chunk is not delivered,
+    /// but its advert is processed, so that the artifact can be downloaded.
+    #[tokio::test]
+    async fn pushed_artifact_rejection_test() {
+        // Initialize the logger and download manager for the test.
+        let logger = p2p_test_setup_logger();
+        let mut download_manager =
+            new_test_download_manager(3, &logger, tokio::runtime::Handle::current());
+        download_manager.artifact_manager = Arc::new(TestArtifactManager {
+            quota: 0,
+            num_chunks: 1,
+        });
+        let mut adverts = receive_check_test_create_adverts(0..2);
+        adverts[0].size = 1;
+
+        // The first artifact exceeds the quota of node 1.
+        download_manager.on_pushed_artifact(
+            pushed_artifact_test_create_pushed_artifact(adverts[0].clone(), 0),
+            node_test_id(1),
+        );
+        assert_eq!(
+            download_manager
+                .metrics
+                .pushed_artifacts_rejected
+                .with_label_values(&["quota"])
+                .get(),
+            1
+        );
+
+        // The second artifact does not fit in a single chunk.
+        let mut pushed_artifact =
+            pushed_artifact_test_create_pushed_artifact(adverts[1].clone(), 1);
+        pushed_artifact.artifact_chunk.artifact_chunk_data =
+            ArtifactChunkData::SemiStructuredChunkData(vec![1, 2, 3]);
+        download_manager.on_pushed_artifact(pushed_artifact, node_test_id(1));
+        assert_eq!(
+            download_manager
+                .metrics
+                .pushed_artifacts_rejected
+                .with_label_values(&["multi_chunk"])
+                .get(),
+            1
+        );
+        assert_eq!(download_manager.metrics.chunks_verification_failed.get(), 0);
+
+        // Neither artifact is delivered, but both are advertised by node 1.
+        assert_eq!(download_manager.metrics.artifacts_received.get(), 0);
+        for advert in &adverts {
+            assert!(download_manager
+                .prioritizer
+                .get_advert_from_peer(
+                    &advert.artifact_id,
+                    &advert.integrity_hash,
+                    &node_test_id(1)
+                )
+                .unwrap()
+                .is_some());
+        }
+    }
+
     proptest! {
         /// The function verifies that setting the same set of peer IDs does not change the
         /// set of current peers.
@@ -200,6 +200,8 @@ pub(crate) struct AdvertTracker {
     download_attempt_map: DownloadAttemptMap,
     /// Priority as computed by the last priority function
     priority: Priority,
+    /// Instant when the first advert for this artifact was received
+    pub received_instant: Instant,
 }
 
 /// Chunk download attempt tracker
@@ -374,6 +376,7 @@ struct ClientAdvertMap {
     ecdsa: ClientAdvertMapInt,
     file_tree_sync: ClientAdvertMapInt,
     state: ClientAdvertMapInt,
+    equivocation_evidence: ClientAdvertMapInt,
 }
 
 /// A single client advert tracking data structure
@@ -404,6 +407,7 @@ impl Index<&ArtifactId> for ClientAdvertMap {
             ArtifactId::EcdsaMessage(_) => &self.ecdsa,
             ArtifactId::FileTreeSync(_) => &self.file_tree_sync,
             ArtifactId::StateSync(_) => &self.state,
+            ArtifactId::EquivocationEvidence(_) => &self.equivocation_evidence,
         }
     }
 }
@@ -418,6 +422,7 @@ impl IndexMut<&ArtifactId> for ClientAdvertMap {
             ArtifactId::EcdsaMessage(_) => &mut self.ecdsa,
             ArtifactId::FileTreeSync(_) => &mut self.file_tree_sync,
             ArtifactId::StateSync(_) => &mut self.state,
+            ArtifactId::EquivocationEvidence(_) => &mut self.equivocation_evidence,
         }
     }
 }
@@ -433,6 +438,7 @@ impl Index<ArtifactTag> for ClientAdvertMap {
             ArtifactTag::EcdsaArtifact => &self.ecdsa,
             ArtifactTag::FileTreeSyncArtifact => &self.file_tree_sync,
             ArtifactTag::StateSyncArtifact => &self.state,
+            ArtifactTag::EquivocationEvidenceArtifact => &self.equivocation_evidence,
         }
     }
 }
@@ -447,6 +453,7 @@ impl IndexMut<ArtifactTag> for ClientAdvertMap {
             ArtifactTag::EcdsaArtifact => &mut self.ecdsa,
             ArtifactTag::FileTreeSyncArtifact => &mut self.file_tree_sync,
             ArtifactTag::StateSyncArtifact => &mut self.state,
+            ArtifactTag::EquivocationEvidenceArtifact => &mut self.equivocation_evidence,
         }
     }
 }
@@ -510,6 +517,20 @@ impl PeerAdvertMapInt {
             .chain(self[Priority::Fetch].iter())
             .chain(self[Priority::Later].iter())
     }
+
+    /// Returns the number of adverts in all priority classes, including
+    /// stashed adverts, by artifact type.
+    pub fn count_by_artifact_tag(&self) -> Vec<(ArtifactTag, usize)> {
+        let mut counts: Vec<(ArtifactTag, usize)> =
+            ArtifactTag::iter().map(|tag| (tag, 0)).collect();
+        for (_, tracker) in self.iter().chain(self[Priority::Stash].iter()) {
+            let tag = ArtifactTag::from(&tracker.read().unwrap().advert.artifact_id);
+            if let Some((_, count)) = counts.iter_mut().find(|(t, _)| *t == tag) {
+                *count += 1;
+            }
+        }
+        counts
+    }
 }
 
 /// The Advert Prioritization Manager struct.
@@ -609,6 +630,7 @@ impl DownloadPrioritizer for DownloadPrioritizerImpl {
                 priority,
                 peers: Default::default(),
                 download_attempt_map: Default::default(),
+                received_instant: Instant::now(),
             }))
         });
 
@@ -70,7 +70,8 @@
 use crate::{
     advert_utils::AdvertRequestBuilder,
     gossip_protocol::{
-        Gossip, GossipChunk, GossipChunkRequest, GossipMessage, GossipRetransmissionRequest,
+        Gossip, GossipChunk, GossipChunkRequest, GossipMessage, GossipPushedArtifact,
+        GossipRetransmissionRequest,
     },
     metrics::EventHandlerMetrics,
 };
@@ -156,6 +157,8 @@ enum FlowType {
     Chunk,
     /// Retransmission request variant.
     Retransmission,
+    /// Pushed artifact variant.
+    PushedArtifact,
     /// *Transport* state change variant.
     Transport,
 }
@@ -271,6 +274,8 @@ struct PeerFlows {
     chunk: PeerFlowQueueMap<GossipChunk>,
     /// The current flows of retransmission requests.
     retransmission: PeerFlowQueueMap<GossipRetransmissionRequest>,
+    /// The current flows of received pushed artifacts.
+    pushed_artifact: PeerFlowQueueMap<GossipPushedArtifact>,
     /// The current flows of transport notifications.
     transport: PeerFlowQueueMap<TransportNotification>,
 }
@@ -282,6 +287,7 @@ impl PeerFlows {
             request: PeerFlowQueueMap::<GossipChunkRequest>::new(),
             chunk: PeerFlowQueueMap::<GossipChunk>::new(),
             retransmission: PeerFlowQueueMap::<GossipRetransmissionRequest>::new(),
+            pushed_artifact: PeerFlowQueueMap::<GossipPushedArtifact>::new(),
             transport: PeerFlowQueueMap::<TransportNotification>::new(),
         }
     }
@@ -312,6 +318,11 @@ impl PeerFlows {
                         c_gossip.on_retransmission_request(item, peer_id);
                     });
                 }
+                FlowType::PushedArtifact => {
+                    self.pushed_artifact.start(move |item, peer_id| {
+                        c_gossip.on_pushed_artifact(item, peer_id);
+                    });
+                }
                 FlowType::Transport => {
                     self.transport.start(move |item, _peer_id| match item {
                         TransportNotification::TransportStateChange(state_change) => {
@@ -339,6 +350,9 @@ impl PeerFlows {
                 FlowType::Retransmission => self
                     .retransmission
                     .add_node(node_id, channel_config.map[flow_type]),
+                FlowType::PushedArtifact => self
+                    .pushed_artifact
+                    .add_node(node_id, channel_config.map[flow_type]),
                 FlowType::Transport => self
                     .transport
                     .add_node(node_id, channel_config.map[flow_type]),
@@ -371,6 +385,8 @@ pub(crate) const MAX_ADVERT_BUFFER: usize = 100_000;
 pub(crate) const MAX_TRANSPORT_BUFFER: usize = 1000;
 /// The maximum number of buffered retransmission requests.
 pub(crate) const MAX_RETRANSMISSION_BUFFER: usize = 1000;
+/// The maximum number of buffered pushed artifacts.
+pub(crate) const MAX_PUSHED_ARTIFACT_BUFFER: usize = 10_000;
 
 /// The channel configuration, containing the maximum number of messages for
 /// each flow type.
@@ -395,6 +411,7 @@ impl From<GossipConfig> for ChannelConfig {
                     FlowType::Request => (flow_type, max_outstanding_buffer),
                     FlowType::Chunk => (flow_type, max_outstanding_buffer),
                     FlowType::Retransmission => (flow_type, MAX_RETRANSMISSION_BUFFER),
+                    FlowType::PushedArtifact => (flow_type, MAX_PUSHED_ARTIFACT_BUFFER),
                     FlowType::Transport => (flow_type, MAX_TRANSPORT_BUFFER),
                 })
                 .collect(),
@@ -522,6 +539,16 @@ impl AsyncTransportEventHandler for P2PEventHandlerImpl {
                         .await,
                 )
             }
+            GossipMessage::PushedArtifact(msg) => {
+                let sem_map = &self.peer_flows.pushed_artifact.sem_map;
+                let sender = self.peer_flows.pushed_artifact.sender.clone();
+
+                (
+                    "PushedArtifact",
+                    self.send_gossip_message(sem_map, sender, flow.peer_id, msg)
+                        .await,
+                )
+            }
         };
         self.metrics
             .send_message_duration_ms
@@ -730,6 +757,8 @@ pub mod tests {
         num_chunks: ItemCountCollector,
         /// The item count collector, counting the number of chunk requests.
         num_reqs: ItemCountCollector,
+        /// The item count collector, counting the number of pushed artifacts.
+        num_pushed_artifacts: ItemCountCollector,
         /// The item count collector, counting the number of ingress messages.
         num_ingress: ItemCountCollector,
         /// The item count collector, counting the number of *Transport* state
@@ -748,6 +777,7 @@ pub mod tests {
                 num_adverts: Default::default(),
                 num_chunks: Default::default(),
                 num_reqs: Default::default(),
+                num_pushed_artifacts: Default::default(),
                 num_ingress: Default::default(),
                 num_changes: Default::default(),
                 num_advert_bcasts: Default::default(),
@@ -793,6 +823,11 @@ pub mod tests {
             TestGossip::increment_or_set(&self.num_chunks, peer_id);
         }
 
+        /// The method is called when a pushed artifact is received.
+        fn on_pushed_artifact(&self, _pushed_artifact: GossipPushedArtifact, peer_id: NodeId) {
+            TestGossip::increment_or_set(&self.num_pushed_artifacts, peer_id);
+        }
+
         /// The method is called when a user ingress message is received.
         fn on_user_ingress(
             &self,
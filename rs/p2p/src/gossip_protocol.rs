@@ -27,6 +27,11 @@
 //!
 //! c) artifact chunks.
 //!
+//! Artifacts that are small enough (as determined by the push size
+//! threshold of their artifact client) skip the request step: they are
+//! pushed, alongside their advert, to the peers that announced in their
+//! retransmission request that they accept pushed artifacts.
+//!
 //! When serialized, the objects above should conform to the IC
 //! on-wire protocol specification.  Internally, an implementation may
 //! choose to have augmented structures that describe
@@ -61,6 +66,7 @@ use crate::{
 };
 use ic_artifact_manager::artifact::IngressArtifact;
 use ic_interfaces::artifact_manager::ArtifactManager;
+use ic_interfaces::p2p::{PeerArtifactCounts, PeerArtifactCountsReader};
 use ic_interfaces::registry::RegistryClient;
 use ic_interfaces::transport::Transport;
 use ic_logger::{info, replica_logger::ReplicaLogger, warn};
@@ -72,7 +78,7 @@ use ic_protobuf::proxy::{try_from_option_field, ProxyDecodeError, ProxyDecodeErr
 use ic_types::{
     artifact::{Artifact, ArtifactFilter, ArtifactId, ArtifactKind},
     canonical_error::{unavailable_error, CanonicalError},
-    chunkable::{ArtifactChunk, ArtifactChunkData, ChunkId},
+    chunkable::{ArtifactChunk, ArtifactChunkData, ChunkId, CHUNKID_UNIT_CHUNK},
     crypto::CryptoHash,
     malicious_flags::MaliciousFlags,
     messages::SignedIngress,
@@ -84,6 +90,7 @@ use ic_types::{
 use bincode::{deserialize, serialize};
 use ic_interfaces::consensus_pool::ConsensusPoolCache;
 use phantom_newtype::AmountOf;
+use std::collections::BTreeMap;
 use std::convert::{TryFrom, TryInto};
 use std::sync::Arc;
 
@@ -117,6 +124,12 @@ pub trait Gossip {
     /// the artifact manager.
     fn on_chunk(&self, gossip_chunk: Self::GossipChunk, peer_id: Self::NodeId);
 
+    /// The method handles the given artifact pushed by the peer with the
+    /// given node ID together with its advert.
+    ///
+    /// Artifacts that have been received before are dropped.
+    fn on_pushed_artifact(&self, pushed_artifact: GossipPushedArtifact, peer_id: NodeId);
+
     /// The method handles the received user ingress message.
     fn on_user_ingress(
         &self,
@@ -166,6 +179,9 @@ pub struct GossipChunkRequest {
 pub struct GossipRetransmissionRequest {
     /// The artifact filter used to restrict the set of returned adverts.
     pub(crate) filter: ArtifactFilter,
+    /// Whether the sender accepts pushed artifacts. Older versions, which
+    /// cannot decode pushed artifacts, leave this unset.
+    pub(crate) accepts_pushed_artifacts: bool,
 }
 
 /// A *Gossip* chunk, identified by its artifact ID and chunk ID.
@@ -182,6 +198,16 @@ pub struct GossipChunk {
     pub(crate) artifact_chunk: P2PResult<ArtifactChunk>,
 }
 
+/// A small artifact pushed to peers together with its advert, so that the
+/// peers do not need to request it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GossipPushedArtifact {
+    /// The advert of the artifact.
+    pub(crate) advert: GossipAdvert,
+    /// The (only) chunk of the artifact.
+    pub(crate) artifact_chunk: ArtifactChunk,
+}
+
 /// This is the message exchanged on the wire with other peers.  This
 /// enum is private to the gossip layer because lower layers like
 /// *Transport* do not need to interpret the content.
@@ -196,6 +222,8 @@ pub(crate) enum GossipMessage {
     Chunk(GossipChunk),
     /// The retransmission request variant.
     RetransmissionRequest(GossipRetransmissionRequest),
+    /// The pushed artifact variant.
+    PushedArtifact(GossipPushedArtifact),
 }
 
 /// Request from artifact manager to send adverts for newly added validated
@@ -443,6 +471,13 @@ impl Gossip for GossipImpl {
         let _ = self.download_manager.download_next(peer_id);
     }
 
+    /// The method hands the given pushed artifact over to the download
+    /// manager, which drops it if it has been received before.
+    fn on_pushed_artifact(&self, pushed_artifact: GossipPushedArtifact, peer_id: NodeId) {
+        self.download_manager
+            .on_pushed_artifact(pushed_artifact, peer_id);
+    }
+
     /// The method handles the received user ingress message.
     fn on_user_ingress(
         &self,
@@ -528,6 +563,13 @@ impl Gossip for GossipImpl {
     }
 }
 
+/// *Gossip* exposes the artifacts it tracks per peer for debugging.
+impl PeerArtifactCountsReader for GossipImpl {
+    fn get_peer_artifact_counts(&self) -> BTreeMap<NodeId, PeerArtifactCounts> {
+        self.download_manager.get_peer_artifact_counts()
+    }
+}
+
 /// A *Gossip* message can be converted into a
 /// `pb::GossipMessage`.
 impl From<GossipMessage> for pb::GossipMessage {
@@ -547,6 +589,9 @@ impl From<GossipMessage> for pb::GossipMessage {
             GossipMessage::RetransmissionRequest(r) => Self {
                 body: Some(Body::RetransmissionRequest(r.into())),
             },
+            GossipMessage::PushedArtifact(p) => Self {
+                body: Some(Body::PushedArtifact(p.into())),
+            },
         }
     }
 }
@@ -563,6 +608,7 @@ impl TryFrom<pb::GossipMessage> for GossipMessage {
             Body::ChunkRequest(r) => Self::ChunkRequest(r.try_into()?),
             Body::Chunk(c) => Self::Chunk(c.try_into()?),
             Body::RetransmissionRequest(r) => Self::RetransmissionRequest(r.try_into()?),
+            Body::PushedArtifact(p) => Self::PushedArtifact(p.try_into()?),
         };
         Ok(message)
     }
@@ -638,6 +684,33 @@ impl TryFrom<pb::GossipChunk> for GossipChunk {
     }
 }
 
+/// A pushed artifact can be converted into a `pb::GossipPushedArtifact`.
+impl From<GossipPushedArtifact> for pb::GossipPushedArtifact {
+    /// The function converts the given pushed artifact into the Protobuf
+    /// equivalent.
+    fn from(pushed_artifact: GossipPushedArtifact) -> Self {
+        Self {
+            advert: Some(pushed_artifact.advert.into()),
+            chunk: Some(pushed_artifact.artifact_chunk.into()),
+        }
+    }
+}
+
+/// A `pb::GossipPushedArtifact` can be converted into a pushed artifact.
+impl TryFrom<pb::GossipPushedArtifact> for GossipPushedArtifact {
+    type Error = ProxyDecodeError;
+    /// The function attempts to convert a Protobuf pushed artifact into a
+    /// GossipPushedArtifact.
+    fn try_from(pushed_artifact: pb::GossipPushedArtifact) -> Result<Self, Self::Error> {
+        let artifact_chunk: ArtifactChunk =
+            try_from_option_field(pushed_artifact.chunk, "GossipPushedArtifact.chunk")?;
+        Ok(Self {
+            advert: try_from_option_field(pushed_artifact.advert, "GossipPushedArtifact.advert")?,
+            artifact_chunk: add_chunk_id(artifact_chunk, ChunkId::from(CHUNKID_UNIT_CHUNK)),
+        })
+    }
+}
+
 /// The function returns a new artifact chunk with the given chunk ID
 /// and the same chunk data as the given artifact chunk.
 fn add_chunk_id(artifact_chunk: ArtifactChunk, chunk_id: ChunkId) -> ArtifactChunk {
@@ -656,6 +729,7 @@ impl From<GossipRetransmissionRequest> for pb::GossipRetransmissionRequest {
     fn from(gossip_request: GossipRetransmissionRequest) -> Self {
         Self {
             filter: Some(gossip_request.filter.into()),
+            accepts_pushed_artifacts: gossip_request.accepts_pushed_artifacts,
         }
     }
 }
@@ -674,6 +748,7 @@ impl TryFrom<pb::GossipRetransmissionRequest> for GossipRetransmissionRequest {
                 gossip_retransmission_request.filter,
                 "GossipRetransmissionRequest.filter",
             )?,
+            accepts_pushed_artifacts: gossip_retransmission_request.accepts_pushed_artifacts,
         })
     }
 }
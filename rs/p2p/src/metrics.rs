@@ -50,6 +50,25 @@ pub struct DownloadManagementMetrics {
     pub received_artifact_size: IntGauge,
     /// The number of failed integrity hash checks.
     pub integrity_hash_check_failed: IntCounter,
+    /// The times from receiving the first advert for an artifact (sent alone or
+    /// alongside the pushed artifact) until handing it over to the artifact
+    /// manager, by delivery path.
+    pub artifact_delivery_time: HistogramVec,
+
+    // Push fields.
+    /// The number of artifacts pushed to peers together with their adverts.
+    pub pushed_artifacts_sent: IntCounter,
+    /// The number of failures to push artifacts.
+    pub pushed_artifacts_send_failed: IntCounter,
+    /// The number of artifacts pushed by peers.
+    pub pushed_artifacts_received: IntCounter,
+    /// The number of pushed artifacts that were dropped because they had been
+    /// received before.
+    pub pushed_artifacts_duplicate: IntCounter,
+    /// The number of pushed artifacts that were not accepted, by reason. Their
+    /// adverts are processed as if sent alone, unless the priority function
+    /// drops them.
+    pub pushed_artifacts_rejected: IntCounterVec,
 
     // Chunking fields.
     /// The number of requested chunks.
@@ -151,6 +170,39 @@ impl DownloadManagementMetrics {
                 "integrity_hash_check_failed",
                 "Number of times the integrity check failed for artifacts",
             ),
+            artifact_delivery_time: metrics_registry.histogram_vec(
+                "gossip_artifact_delivery_time",
+                "Time from receiving the first advert for an artifact until it is handed over to the artifact manager, by delivery path (in milliseconds)",
+                vec![
+                    1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 300.0, 400.0, 500.0, 600.0,
+                    700.0, 800.0, 900.0, 1000.0, 1200.0, 1400.0, 1600.0, 1800.0, 2000.0, 2500.0,
+                    3000.0, 4000.0, 5000.0, 7000.0, 10000.0, 20000.0,
+                ],
+                &["artifact_type", "path"],
+            ),
+
+            // Push fields.
+            pushed_artifacts_sent: metrics_registry.int_counter(
+                "gossip_pushed_artifacts_sent",
+                "Number of artifacts pushed to peers together with their adverts",
+            ),
+            pushed_artifacts_send_failed: metrics_registry.int_counter(
+                "gossip_pushed_artifacts_send_failed",
+                "Number of pushed artifact send failures",
+            ),
+            pushed_artifacts_received: metrics_registry.int_counter(
+                "gossip_pushed_artifacts_received",
+                "Number of artifacts pushed by peers",
+            ),
+            pushed_artifacts_duplicate: metrics_registry.int_counter(
+                "gossip_pushed_artifacts_duplicate",
+                "Number of pushed artifacts that were dropped because they had been received before",
+            ),
+            pushed_artifacts_rejected: metrics_registry.int_counter_vec(
+                "gossip_pushed_artifacts_rejected",
+                "Number of pushed artifacts that were not accepted, by reason",
+                &["reason"],
+            ),
 
             // Chunking fields.
             chunks_requested: metrics_registry.int_counter(
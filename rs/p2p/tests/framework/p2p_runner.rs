@@ -83,7 +83,7 @@ fn execute_test(
             subnet_config.cycles_account_manager_config,
         ));
 
-        let (_, p2p_runner, _) = create_networking_stack(
+        let (_, p2p_runner, _, _, _) = create_networking_stack(
             metrics_registry.clone(),
             log.clone(),
             rt_handle,
@@ -109,6 +109,7 @@ fn execute_test(
             CUPWithOriginalProtobuf::from_cup(make_catch_up_package_with_empty_transcript(
                 registry, subnet_id,
             )),
+            None,
             cycles_account_manager,
             None,
             0,
@@ -237,7 +238,7 @@ fn execute_test_chunking_pool(
             subnet_config.cycles_account_manager_config,
         ));
 
-        let (_a, p2p_runner, _) = create_networking_stack(
+        let (_a, p2p_runner, _, _, _) = create_networking_stack(
             metrics_registry.clone(),
             log.clone(),
             rt_handle,
@@ -263,6 +264,7 @@ fn execute_test_chunking_pool(
             CUPWithOriginalProtobuf::from_cup(make_catch_up_package_with_empty_transcript(
                 registry, subnet_id,
             )),
+            None,
             cycles_account_manager,
             None,
             0,
@@ -27,6 +27,21 @@ pub mod v1 {
         }
     }
 
+    impl ConsensusPoolSnapshot {
+        /// Read and deserialize a protobuf ConsensusPoolSnapshot from the
+        /// provided file.
+        pub fn read_from_file<P: AsRef<Path> + std::fmt::Debug>(
+            filepath: P,
+        ) -> Result<Self, String> {
+            let mut buf = Vec::new();
+            File::open(&filepath)
+                .map_err(|e| format!("open failed: {:?}: {:?}", filepath, e))?
+                .read_to_end(&mut buf)
+                .map_err(|e| format!("read failed: {:?}", e))?;
+            Self::decode(&buf[..]).map_err(|e| format!("protobuf decode failed: {:?}", e))
+        }
+    }
+
     impl CatchUpContent {
         pub fn as_protobuf_vec(&self) -> Vec<u8> {
             let mut buf = Vec::<u8>::new();
@@ -63,7 +63,10 @@ use ic_protobuf::registry::{
     provisional_whitelist::v1::ProvisionalWhitelist as ProvisionalWhitelistProto,
     replica_version::v1::{BlessedReplicaVersions, ReplicaVersionRecord},
     routing_table::v1::RoutingTable,
-    subnet::v1::{EcdsaConfig, SubnetListRecord, SubnetRecord as SubnetRecordProto},
+    subnet::v1::{
+        AdaptiveBlockRateConfig, ConsensusParameters, EcdsaConfig, SubnetListRecord,
+        SubnetRecord as SubnetRecordProto,
+    },
     unassigned_nodes_config::v1::UnassignedNodesConfigRecord,
 };
 use ic_protobuf::registry::{
@@ -1251,6 +1254,50 @@ struct ProposeToUpdateSubnetCmd {
     #[clap(long)]
     pub ecdsa_quadruples_to_create_in_advance: Option<u32>,
 
+    /// If set, enables the adaptive block rate. The round time is averaged
+    /// over this many recent blocks. All other `adaptive_block_rate_*`
+    /// arguments must be set as well.
+    #[clap(long)]
+    pub adaptive_block_rate_window: Option<u64>,
+
+    /// The round time (in milliseconds) the adaptive block rate aims for.
+    #[clap(long)]
+    pub adaptive_block_rate_target_round_time_millis: Option<u64>,
+
+    /// The upper bound (in milliseconds) for the adjusted initial notary
+    /// delay.
+    #[clap(long)]
+    pub adaptive_block_rate_max_initial_notary_delay_millis: Option<u64>,
+
+    /// The lower bound (in bytes) for the adjusted maximum block payload size.
+    #[clap(long)]
+    pub adaptive_block_rate_min_block_payload_size: Option<u64>,
+
+    /// If set, updates the consensus parameters of the subnet. This is the
+    /// number of heights of validated artifacts kept below the latest CUP.
+    /// All other `consensus_*` arguments must be set as well.
+    #[clap(long)]
+    pub consensus_catch_up_package_retention_heights: Option<u64>,
+
+    /// The maximum number of artifacts a single peer may have in the
+    /// unvalidated consensus pool.
+    #[clap(long)]
+    pub consensus_max_unvalidated_artifacts_per_peer: Option<u64>,
+
+    /// The number of heights grouped into one directory of the consensus
+    /// pool backup.
+    #[clap(long)]
+    pub consensus_backup_group_size: Option<u64>,
+
+    /// The interval (in seconds) at which the consensus pool backup is purged.
+    #[clap(long)]
+    pub consensus_backup_purging_interval_secs: Option<u64>,
+
+    /// The minimum number of heights between two CUPs. A length up to the
+    /// DKG interval length makes a CUP at every DKG summary block.
+    #[clap(long)]
+    pub consensus_catch_up_package_interval_length: Option<u64>,
+
     /// The features that are enabled and disabled on the subnet.
     #[clap(long)]
     pub features: Option<SubnetFeatures>,
@@ -1271,6 +1318,74 @@ struct ProposeToUpdateSubnetCmd {
     pub max_number_of_canisters: Option<u64>,
 }
 
+impl ProposeToUpdateSubnetCmd {
+    /// Checks that arguments which only make sense together are either all
+    /// set or all unset.
+    fn check_arguments(&self) -> Result<(), String> {
+        self.adaptive_block_rate_config()?;
+        self.consensus_parameters()?;
+        Ok(())
+    }
+
+    fn adaptive_block_rate_config(&self) -> Result<Option<AdaptiveBlockRateConfig>, String> {
+        match (
+            self.adaptive_block_rate_window,
+            self.adaptive_block_rate_target_round_time_millis,
+            self.adaptive_block_rate_max_initial_notary_delay_millis,
+            self.adaptive_block_rate_min_block_payload_size,
+        ) {
+            (
+                Some(window),
+                Some(target_round_time_millis),
+                Some(max_initial_notary_delay_millis),
+                Some(min_block_payload_size),
+            ) => Ok(Some(AdaptiveBlockRateConfig {
+                window,
+                target_round_time_millis,
+                max_initial_notary_delay_millis,
+                min_block_payload_size,
+            })),
+            (None, None, None, None) => Ok(None),
+            _ => Err("--adaptive-block-rate-window, \
+                --adaptive-block-rate-target-round-time-millis, \
+                --adaptive-block-rate-max-initial-notary-delay-millis and \
+                --adaptive-block-rate-min-block-payload-size must be set together"
+                .to_string()),
+        }
+    }
+
+    fn consensus_parameters(&self) -> Result<Option<ConsensusParameters>, String> {
+        match (
+            self.consensus_catch_up_package_retention_heights,
+            self.consensus_max_unvalidated_artifacts_per_peer,
+            self.consensus_backup_group_size,
+            self.consensus_backup_purging_interval_secs,
+            self.consensus_catch_up_package_interval_length,
+        ) {
+            (
+                Some(catch_up_package_retention_heights),
+                Some(max_unvalidated_artifacts_per_peer),
+                Some(backup_group_size),
+                Some(backup_purging_interval_secs),
+                Some(catch_up_package_interval_length),
+            ) => Ok(Some(ConsensusParameters {
+                catch_up_package_retention_heights,
+                max_unvalidated_artifacts_per_peer,
+                backup_group_size,
+                backup_purging_interval_secs,
+                catch_up_package_interval_length,
+            })),
+            (None, None, None, None, None) => Ok(None),
+            _ => Err("--consensus-catch-up-package-retention-heights, \
+                --consensus-max-unvalidated-artifacts-per-peer, \
+                --consensus-backup-group-size, \
+                --consensus-backup-purging-interval-secs and \
+                --consensus-catch-up-package-interval-length must be set together"
+                .to_string()),
+        }
+    }
+}
+
 #[async_trait]
 impl ProposalTitleAndPayload<UpdateSubnetPayload> for ProposeToUpdateSubnetCmd {
     fn title(&self) -> String {
@@ -1319,6 +1434,12 @@ impl ProposalTitleAndPayload<UpdateSubnetPayload> for ProposeToUpdateSubnetCmd {
                 .map(|val| EcdsaConfig {
                     quadruples_to_create_in_advance: val,
                 }),
+            adaptive_block_rate_config: self
+                .adaptive_block_rate_config()
+                .expect("The arguments are checked before proposing."),
+            consensus_parameters: self
+                .consensus_parameters()
+                .expect("The arguments are checked before proposing."),
             ssh_readonly_access: self.ssh_readonly_access.clone(),
             ssh_backup_access: self.ssh_backup_access.clone(),
             max_number_of_canisters: self.max_number_of_canisters,
@@ -2516,6 +2637,10 @@ async fn main() {
             .await;
         }
         SubCommand::ProposeToUpdateSubnet(cmd) => {
+            if let Err(err) = cmd.check_arguments() {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
             propose_external_proposal_from_command(
                 cmd,
                 NnsFunction::UpdateConfigOfSubnet,
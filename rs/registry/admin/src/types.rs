@@ -2,7 +2,10 @@
 
 use ic_protobuf::registry::{
     provisional_whitelist::v1::ProvisionalWhitelist as ProvisionalWhitelistProto,
-    subnet::v1::{GossipConfig as GossipConfigProto, SubnetRecord as SubnetRecordProto},
+    subnet::v1::{
+        AdaptiveBlockRateConfig, ConsensusParameters, GossipConfig as GossipConfigProto,
+        SubnetRecord as SubnetRecordProto,
+    },
 };
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_features::SubnetFeatures;
@@ -67,6 +70,8 @@ pub(crate) struct SubnetRecord {
     pub max_number_of_canisters: u64,
     pub ssh_readonly_access: Vec<String>,
     pub ssh_backup_access: Vec<String>,
+    pub adaptive_block_rate_config: Option<AdaptiveBlockRateConfig>,
+    pub consensus_parameters: Option<ConsensusParameters>,
 }
 
 impl From<&SubnetRecordProto> for SubnetRecord {
@@ -103,6 +108,8 @@ impl From<&SubnetRecordProto> for SubnetRecord {
             max_number_of_canisters: value.max_number_of_canisters,
             ssh_readonly_access: value.ssh_readonly_access.clone(),
             ssh_backup_access: value.ssh_backup_access.clone(),
+            adaptive_block_rate_config: value.adaptive_block_rate_config.clone(),
+            consensus_parameters: value.consensus_parameters.clone(),
         }
     }
 }
@@ -10,7 +10,9 @@ use crate::invariants::common::{
 use ic_base_types::SubnetId;
 use ic_base_types::{NodeId, PrincipalId};
 use ic_nns_common::registry::{decode_or_panic, MAX_NUM_SSH_KEYS};
-use ic_protobuf::registry::subnet::v1::{SubnetRecord, SubnetType};
+use ic_protobuf::registry::subnet::v1::{
+    AdaptiveBlockRateConfig, ConsensusParameters, SubnetRecord, SubnetType,
+};
 use ic_registry_keys::{make_node_record_key, make_subnet_record_key, SUBNET_RECORD_KEY_PREFIX};
 
 /// Subnet invariants hold iff:
@@ -20,6 +22,8 @@ use ic_registry_keys::{make_node_record_key, make_subnet_record_key, SUBNET_RECO
 ///    * Each subnet contains at least one node
 ///    * There is at least one system subnet
 ///    * Each subnet in the registry occurs in the subnet list and vice versa
+///    * The consensus parameters, if set, are within their bounds
+///    * The adaptive block rate config, if set, is within its bounds
 pub(crate) fn check_subnet_invariants(
     snapshot: &RegistrySnapshot,
 ) -> Result<(), InvariantCheckError> {
@@ -103,6 +107,18 @@ pub(crate) fn check_subnet_invariants(
                 the round instruction limit."
         );
 
+        if let Some(consensus_parameters) = &subnet_record.consensus_parameters {
+            check_consensus_parameters_invariants(
+                subnet_id,
+                consensus_parameters,
+                subnet_record.dkg_interval_length,
+            )?;
+        }
+
+        if let Some(config) = &subnet_record.adaptive_block_rate_config {
+            check_adaptive_block_rate_config_invariants(subnet_id, config)?;
+        }
+
         check_gossip_config_invariants(subnet_id, subnet_record);
     }
     // There is at least one system subnet
@@ -217,3 +233,182 @@ fn check_gossip_config_invariants(subnet_id: SubnetId, subnet_record: SubnetReco
         None => panic!("No gossip config defined in subnet record {:}.", subnet_id),
     }
 }
+
+/// The smallest allowed limit for unvalidated artifacts per peer. An honest
+/// peer sends about ten artifacts per height, so this allows peers to be
+/// roughly a hundred heights ahead of us.
+const MIN_UNVALIDATED_ARTIFACTS_PER_PEER: u64 = 1000;
+
+/// The largest allowed number of DKG intervals between two catch-up packages.
+/// Peers that fall behind can only catch up with a catch-up package, and the
+/// artifacts above the latest one are not purged.
+const MAX_DKG_INTERVALS_PER_CATCH_UP_PACKAGE: u64 = 10;
+
+/// Consensus parameters invariants hold iff:
+///    * catch-up package retention heights <= DKG interval length + 1
+///    * max unvalidated artifacts per peer >= 1000
+///    * backup group size > 0
+///    * backup purging interval > 0 seconds
+///    * catch-up package interval length <= 10 DKG intervals
+fn check_consensus_parameters_invariants(
+    subnet_id: SubnetId,
+    parameters: &ConsensusParameters,
+    dkg_interval_length: u64,
+) -> Result<(), InvariantCheckError> {
+    let error = |msg: String| {
+        Err(InvariantCheckError {
+            msg: format!(
+                "Consensus parameters of subnet {:} are invalid: {}",
+                subnet_id, msg
+            ),
+            source: None,
+        })
+    };
+    if parameters.catch_up_package_retention_heights > dkg_interval_length + 1 {
+        return error(format!(
+            "catch_up_package_retention_heights is {} but it must not exceed one DKG \
+            interval ({} heights), so that the validated pool stays bounded.",
+            parameters.catch_up_package_retention_heights,
+            dkg_interval_length + 1
+        ));
+    }
+    if parameters.max_unvalidated_artifacts_per_peer < MIN_UNVALIDATED_ARTIFACTS_PER_PEER {
+        return error(format!(
+            "max_unvalidated_artifacts_per_peer is {} but it must be at least {}, so that \
+            artifacts of honest peers are not dropped.",
+            parameters.max_unvalidated_artifacts_per_peer, MIN_UNVALIDATED_ARTIFACTS_PER_PEER
+        ));
+    }
+    if parameters.backup_group_size < 1 {
+        return error("backup_group_size must be at least 1.".to_string());
+    }
+    if parameters.backup_purging_interval_secs < 1 {
+        return error("backup_purging_interval_secs must be at least 1.".to_string());
+    }
+    let max_catch_up_package_interval_length =
+        MAX_DKG_INTERVALS_PER_CATCH_UP_PACKAGE * (dkg_interval_length + 1);
+    if parameters.catch_up_package_interval_length > max_catch_up_package_interval_length {
+        return error(format!(
+            "catch_up_package_interval_length is {} but it must not exceed {} DKG \
+            intervals ({} heights), so that peers can catch up.",
+            parameters.catch_up_package_interval_length,
+            MAX_DKG_INTERVALS_PER_CATCH_UP_PACKAGE,
+            max_catch_up_package_interval_length
+        ));
+    }
+    Ok(())
+}
+
+/// Adaptive block rate config invariants hold iff:
+///    * window > 0
+///    * target round time > 0 milliseconds
+fn check_adaptive_block_rate_config_invariants(
+    subnet_id: SubnetId,
+    config: &AdaptiveBlockRateConfig,
+) -> Result<(), InvariantCheckError> {
+    let error = |msg: &str| {
+        Err(InvariantCheckError {
+            msg: format!(
+                "Adaptive block rate config of subnet {:} is invalid: {}",
+                subnet_id, msg
+            ),
+            source: None,
+        })
+    };
+    if config.window < 1 {
+        return error("window must be at least 1, to observe any round time.");
+    }
+    if config.target_round_time_millis < 1 {
+        return error("target_round_time_millis must be at least 1.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters() -> ConsensusParameters {
+        ConsensusParameters {
+            catch_up_package_retention_heights: 50,
+            max_unvalidated_artifacts_per_peer: 2048,
+            backup_group_size: 10000,
+            backup_purging_interval_secs: 3600,
+            catch_up_package_interval_length: 0,
+        }
+    }
+
+    #[test]
+    fn test_consensus_parameters_invariants() {
+        let check = |parameters: ConsensusParameters| {
+            check_consensus_parameters_invariants(
+                SubnetId::from(PrincipalId::new_subnet_test_id(1)),
+                &parameters,
+                99,
+            )
+        };
+        assert!(check(parameters()).is_ok());
+        assert!(check(ConsensusParameters {
+            catch_up_package_retention_heights: 100,
+            ..parameters()
+        })
+        .is_ok());
+        assert!(check(ConsensusParameters {
+            catch_up_package_retention_heights: 101,
+            ..parameters()
+        })
+        .is_err());
+        assert!(check(ConsensusParameters {
+            max_unvalidated_artifacts_per_peer: 999,
+            ..parameters()
+        })
+        .is_err());
+        assert!(check(ConsensusParameters {
+            backup_group_size: 0,
+            ..parameters()
+        })
+        .is_err());
+        assert!(check(ConsensusParameters {
+            backup_purging_interval_secs: 0,
+            ..parameters()
+        })
+        .is_err());
+        assert!(check(ConsensusParameters {
+            catch_up_package_interval_length: 1000,
+            ..parameters()
+        })
+        .is_ok());
+        assert!(check(ConsensusParameters {
+            catch_up_package_interval_length: 1001,
+            ..parameters()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_adaptive_block_rate_config_invariants() {
+        let config = AdaptiveBlockRateConfig {
+            window: 10,
+            target_round_time_millis: 1000,
+            max_initial_notary_delay_millis: 3000,
+            min_block_payload_size: 1024,
+        };
+        let check = |config: AdaptiveBlockRateConfig| {
+            check_adaptive_block_rate_config_invariants(
+                SubnetId::from(PrincipalId::new_subnet_test_id(1)),
+                &config,
+            )
+        };
+        assert!(check(config.clone()).is_ok());
+        assert!(check(AdaptiveBlockRateConfig {
+            window: 0,
+            ..config.clone()
+        })
+        .is_err());
+        assert!(check(AdaptiveBlockRateConfig {
+            target_round_time_millis: 0,
+            ..config
+        })
+        .is_err());
+    }
+}
@@ -282,6 +282,8 @@ impl From<CreateSubnetPayload> for SubnetRecord {
             ssh_readonly_access: val.ssh_readonly_access,
             ssh_backup_access: val.ssh_backup_access,
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
         }
     }
 }
@@ -5,7 +5,9 @@ use dfn_core::println;
 use serde::Serialize;
 
 use ic_base_types::SubnetId;
-use ic_protobuf::registry::subnet::v1::{EcdsaConfig, GossipAdvertConfig, SubnetRecord};
+use ic_protobuf::registry::subnet::v1::{
+    AdaptiveBlockRateConfig, ConsensusParameters, EcdsaConfig, GossipAdvertConfig, SubnetRecord,
+};
 use ic_registry_keys::make_subnet_record_key;
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
@@ -84,6 +86,10 @@ pub struct UpdateSubnetPayload {
 
     pub ecdsa_config: Option<EcdsaConfig>,
 
+    pub adaptive_block_rate_config: Option<AdaptiveBlockRateConfig>,
+
+    pub consensus_parameters: Option<ConsensusParameters>,
+
     pub max_number_of_canisters: Option<u64>,
 
     pub ssh_readonly_access: Option<Vec<String>>,
@@ -170,6 +176,8 @@ fn merge_subnet_record(
         max_instructions_per_install_code,
         features,
         ecdsa_config,
+        adaptive_block_rate_config,
+        consensus_parameters,
         max_number_of_canisters,
         ssh_readonly_access,
         ssh_backup_access,
@@ -218,6 +226,8 @@ fn merge_subnet_record(
 
     maybe_set_option!(subnet_record, features);
     maybe_set_option!(subnet_record, ecdsa_config);
+    maybe_set_option!(subnet_record, adaptive_block_rate_config);
+    maybe_set_option!(subnet_record, consensus_parameters);
 
     maybe_set!(subnet_record, max_number_of_canisters);
 
@@ -272,6 +282,8 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -311,6 +323,19 @@ mod tests {
             ecdsa_config: Some(EcdsaConfig {
                 quadruples_to_create_in_advance: 10,
             }),
+            adaptive_block_rate_config: Some(AdaptiveBlockRateConfig {
+                window: 10,
+                target_round_time_millis: 1000,
+                max_initial_notary_delay_millis: 2000,
+                min_block_payload_size: 100,
+            }),
+            consensus_parameters: Some(ConsensusParameters {
+                catch_up_package_retention_heights: 100,
+                max_unvalidated_artifacts_per_peer: 5000,
+                backup_group_size: 1000,
+                backup_purging_interval_secs: 600,
+                catch_up_package_interval_length: 1000,
+            }),
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
@@ -358,6 +383,19 @@ mod tests {
                 ecdsa_config: Some(EcdsaConfig {
                     quadruples_to_create_in_advance: 10,
                 }),
+                adaptive_block_rate_config: Some(AdaptiveBlockRateConfig {
+                    window: 10,
+                    target_round_time_millis: 1000,
+                    max_initial_notary_delay_millis: 2000,
+                    min_block_payload_size: 100,
+                }),
+                consensus_parameters: Some(ConsensusParameters {
+                    catch_up_package_retention_heights: 100,
+                    max_unvalidated_artifacts_per_peer: 5000,
+                    backup_group_size: 1000,
+                    backup_purging_interval_secs: 600,
+                    catch_up_package_interval_length: 1000,
+                }),
                 max_number_of_canisters: 10,
                 ssh_readonly_access: vec!["pub_key_0".to_string()],
                 ssh_backup_access: vec!["pub_key_1".to_string()],
@@ -401,6 +439,8 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -434,6 +474,8 @@ mod tests {
             max_instructions_per_install_code: None,
             features: None,
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
             max_number_of_canisters: Some(50),
             ssh_readonly_access: None,
             ssh_backup_access: None,
@@ -473,6 +515,8 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                adaptive_block_rate_config: None,
+                consensus_parameters: None,
             }
         );
     }
@@ -505,6 +549,8 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -538,6 +584,8 @@ mod tests {
             max_instructions_per_install_code: None,
             features: None,
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
@@ -570,6 +618,8 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -603,6 +653,8 @@ mod tests {
             max_instructions_per_install_code: None,
             features: None,
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
@@ -644,6 +696,8 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                adaptive_block_rate_config: None,
+                consensus_parameters: None,
             }
         );
     }
@@ -684,6 +738,8 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -717,6 +773,8 @@ mod tests {
             max_instructions_per_install_code: None,
             features: None,
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
@@ -758,6 +816,8 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                adaptive_block_rate_config: None,
+                consensus_parameters: None,
             }
         );
     }
@@ -160,6 +160,7 @@ fn test_subnet_is_only_deleted_when_appropriate() {
                 ledger_canister_id: LEDGER_CANISTER_ID,
                 governance_canister_id: GOVERNANCE_CANISTER_ID,
                 minting_account_id: Some(GOVERNANCE_CANISTER_ID.get().into()),
+                exchange_rate_canister_id: None,
             },
         )
         .await;
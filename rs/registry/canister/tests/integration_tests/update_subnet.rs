@@ -72,6 +72,8 @@ fn test_the_anonymous_user_cannot_update_a_subnets_configuration() {
             max_instructions_per_install_code: Some(300_000_000_000),
             features: None,
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
@@ -140,6 +142,8 @@ fn test_a_canister_other_than_the_proposals_canister_cannot_update_a_subnets_con
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
         };
 
         // An attacker got a canister that is trying to pass for the proposals
@@ -193,6 +197,8 @@ fn test_a_canister_other_than_the_proposals_canister_cannot_update_a_subnets_con
             max_instructions_per_install_code: Some(300_000_000_000),
             features: None,
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
             max_number_of_canisters: Some(100),
             ssh_readonly_access: None,
             ssh_backup_access: None,
@@ -262,6 +268,8 @@ fn test_the_proposals_canister_can_update_a_subnets_configuration() {
                             ssh_readonly_access: vec![],
                             ssh_backup_access: vec![],
                             ecdsa_config: None,
+                            adaptive_block_rate_config: None,
+                            consensus_parameters: None,
                         }),
                     )],
                     preconditions: vec![],
@@ -306,6 +314,8 @@ fn test_the_proposals_canister_can_update_a_subnets_configuration() {
             max_instructions_per_install_code: Some(300_000_000_000),
             features: None,
             ecdsa_config: None,
+            adaptive_block_rate_config: None,
+            consensus_parameters: None,
             max_number_of_canisters: Some(42),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
@@ -361,6 +371,8 @@ fn test_the_proposals_canister_can_update_a_subnets_configuration() {
                 ssh_readonly_access: vec!["pub_key_0".to_string()],
                 ssh_backup_access: vec!["pub_key_1".to_string()],
                 ecdsa_config: None,
+                adaptive_block_rate_config: None,
+                consensus_parameters: None,
             }
         );
 
@@ -5,7 +5,8 @@ use ic_protobuf::registry::{
     node::v1::NodeRecord,
     replica_version::v1::ReplicaVersionRecord,
     subnet::v1::{
-        CatchUpPackageContents, EcdsaConfig, GossipConfig, SubnetListRecord, SubnetRecord,
+        AdaptiveBlockRateConfig, CatchUpPackageContents, ConsensusParameters, EcdsaConfig,
+        GossipConfig, SubnetListRecord, SubnetRecord,
     },
 };
 use ic_protobuf::types::v1::SubnetId as SubnetIdProto;
@@ -23,6 +24,32 @@ use std::time::Duration;
 pub struct NotarizationDelaySettings {
     pub unit_delay: Duration,
     pub initial_notary_delay: Duration,
+    /// Set if the adaptive block rate is enabled for the subnet.
+    pub adaptive_block_rate: Option<AdaptiveBlockRateSettings>,
+}
+
+/// Bounds for the adaptive block rate, as configured in the subnet record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdaptiveBlockRateSettings {
+    /// The number of most recent blocks over which the round time is averaged.
+    pub window: u64,
+    /// The round time the subnet aims for.
+    pub target_round_time: Duration,
+    /// Upper bound for the adjusted initial notary delay.
+    pub max_initial_notary_delay: Duration,
+    /// Lower bound for the adjusted maximum block payload size in bytes.
+    pub min_block_payload_size: u64,
+}
+
+impl From<&AdaptiveBlockRateConfig> for AdaptiveBlockRateSettings {
+    fn from(config: &AdaptiveBlockRateConfig) -> Self {
+        Self {
+            window: config.window,
+            target_round_time: Duration::from_millis(config.target_round_time_millis),
+            max_initial_notary_delay: Duration::from_millis(config.max_initial_notary_delay_millis),
+            min_block_payload_size: config.min_block_payload_size,
+        }
+    }
 }
 
 pub struct IngressMessageSettings {
@@ -85,10 +112,19 @@ pub trait SubnetRegistry {
         version: RegistryVersion,
     ) -> RegistryClientResult<Option<EcdsaConfig>>;
 
+    /// Returns the consensus tuning parameters, if they are set for the
+    /// subnet.
+    fn get_consensus_parameters(
+        &self,
+        subnet_id: SubnetId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<Option<ConsensusParameters>>;
+
     /// Returns notarization delay settings:
     /// - the unit delay for blockmaker;
     /// - the initial delay for notary, to give time to rank-0 block
-    /// propagation.
+    /// propagation;
+    /// - the bounds for the adaptive block rate, if enabled.
     fn get_notarization_delay_settings(
         &self,
         subnet_id: SubnetId,
@@ -258,6 +294,16 @@ impl<T: RegistryClient + ?Sized> SubnetRegistry for T {
         Ok(subnet.map(|subnet| subnet.ecdsa_config))
     }
 
+    fn get_consensus_parameters(
+        &self,
+        subnet_id: SubnetId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<Option<ConsensusParameters>> {
+        let bytes = self.get_value(&make_subnet_record_key(subnet_id), version);
+        let subnet = deserialize_registry_value::<SubnetRecord>(bytes)?;
+        Ok(subnet.map(|subnet| subnet.consensus_parameters))
+    }
+
     fn get_notarization_delay_settings(
         &self,
         subnet_id: SubnetId,
@@ -269,6 +315,10 @@ impl<T: RegistryClient + ?Sized> SubnetRegistry for T {
                 NotarizationDelaySettings {
                     unit_delay: Duration::from_millis(subnet.unit_delay_millis),
                     initial_notary_delay: Duration::from_millis(subnet.initial_notary_delay_millis),
+                    adaptive_block_rate: subnet
+                        .adaptive_block_rate_config
+                        .as_ref()
+                        .map(AdaptiveBlockRateSettings::from),
                 }
             }),
         )
@@ -1,5 +1,4 @@
 use ic_artifact_pool::consensus_pool::ConsensusPoolImpl;
-use ic_config::artifact_pool::BACKUP_GROUP_SIZE;
 use ic_consensus::consensus::{pool_reader::PoolReader, utils::lookup_replica_version};
 use ic_consensus_message::ConsensusMessageHashable;
 use ic_interfaces::{
@@ -92,10 +91,8 @@ pub(crate) fn read_cup_at_height(
     backup_dir: &Path,
     height: Height,
 ) -> CatchUpPackage {
-    let group_key = (height.get() / BACKUP_GROUP_SIZE) * BACKUP_GROUP_SIZE;
     let buffer = read_file(
-        &backup_dir
-            .join(group_key.to_string())
+        &group_dir(backup_dir, height)
             .join(height.to_string())
             .join("catch_up_package.bin"),
     );
@@ -123,6 +120,19 @@ pub(crate) fn read_cup_at_height(
     cup
 }
 
+// Returns the group directory containing the given height, which is the one
+// with the largest key not above the height. The group size can be changed in
+// the registry, so the key cannot be computed from the height alone.
+fn group_dir(backup_dir: &Path, height: Height) -> PathBuf {
+    let group_key = fs::read_dir(backup_dir)
+        .expect("Couldn't read the backup directory")
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u64>().ok())
+        .filter(|key| *key <= height.get())
+        .max()
+        .unwrap_or_else(|| panic!("No backup group found for height {}", height));
+    backup_dir.join(group_key.to_string())
+}
+
 /// Read all files from the backup folder starting from the `start_height` and
 /// convert them into batches.
 pub(super) fn heights_to_artifacts_metadata(
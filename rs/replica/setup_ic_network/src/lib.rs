@@ -12,7 +12,7 @@ use ic_artifact_pool::{
 use ic_config::{artifact_pool::ArtifactPoolConfig, consensus::ConsensusConfig};
 use ic_consensus::{
     certification,
-    consensus::{ConsensusCrypto, Membership},
+    consensus::{equivocation, ConsensusCrypto, Membership},
     dkg, ecdsa,
 };
 use ic_crypto_tls_interfaces::TlsHandshake;
@@ -21,18 +21,18 @@ use ic_ingress_manager::IngressManager;
 use ic_interfaces::registry::LocalStoreCertifiedTimeReader;
 use ic_interfaces::{
     artifact_manager::{ArtifactClient, ArtifactManager, ArtifactProcessor},
-    consensus_pool::ConsensusPoolCache,
+    consensus_pool::{ConsensusPool, ConsensusPoolCache},
     crypto::{Crypto, IngressSigVerifier},
     execution_environment::IngressHistoryReader,
     messaging::{MessageRouting, XNetPayloadBuilder},
-    p2p::{IngressIngestionService, P2PRunner},
+    p2p::{IngressIngestionService, P2PRunner, PeerArtifactCountsReader},
     registry::RegistryClient,
     self_validating_payload::SelfValidatingPayloadBuilder,
     state_manager::StateManager,
-    time_source::SysTimeSource,
+    time_source::{SysTimeSource, TimeSource},
     transport::Transport,
 };
-use ic_logger::{debug, info, replica_logger::ReplicaLogger};
+use ic_logger::{debug, info, replica_logger::ReplicaLogger, warn};
 use ic_metrics::MetricsRegistry;
 use ic_p2p::{
     event_handler::{
@@ -41,6 +41,7 @@ use ic_p2p::{
     },
     gossip_protocol::GossipImpl,
 };
+use ic_protobuf::types::v1 as pb;
 use ic_registry_client::helper::subnet::SubnetRegistry;
 use ic_replicated_state::ReplicatedState;
 use ic_state_manager::StateManagerImpl;
@@ -145,6 +146,7 @@ pub fn create_networking_stack(
     registry_client: Arc<dyn RegistryClient>,
     ingress_history_reader: Box<dyn IngressHistoryReader>,
     catch_up_package: CUPWithOriginalProtobuf,
+    pool_snapshot: Option<pb::ConsensusPoolSnapshot>,
     cycles_account_manager: Arc<CyclesAccountManager>,
     local_store_time_reader: Option<Arc<dyn LocalStoreCertifiedTimeReader>>,
     registry_poll_delay_duration_ms: u64,
@@ -153,6 +155,8 @@ pub fn create_networking_stack(
         IngressIngestionService,
         Box<dyn P2PRunner>,
         Arc<dyn ConsensusPoolCache>,
+        Arc<RwLock<dyn ConsensusPool + Send + Sync>>,
+        Arc<dyn PeerArtifactCountsReader>,
     ),
     String,
 > {
@@ -189,32 +193,34 @@ pub fn create_networking_stack(
     );
 
     // Now we setup the Artifact Pools and the manager.
-    let (artifact_manager, consensus_pool_cache, ingress_throttle) = setup_artifact_manager(
-        node_id,
-        Arc::clone(&crypto) as Arc<_>,
-        Arc::clone(&consensus_crypto) as Arc<_>,
-        Arc::clone(&certifier_crypto) as Arc<_>,
-        Arc::clone(&ingress_sig_crypto) as Arc<_>,
-        subnet_id,
-        artifact_pool_config,
-        consensus_config,
-        log.clone(),
-        metrics_registry.clone(),
-        Arc::clone(&registry_client),
-        state_manager,
-        state_sync_client,
-        xnet_payload_builder,
-        self_validating_payload_builder,
-        message_router,
-        ingress_history_reader,
-        catch_up_package,
-        malicious_flags.clone(),
-        cycles_account_manager,
-        local_store_time_reader,
-        registry_poll_delay_duration_ms,
-        advert_subscriber.clone(),
-    )
-    .unwrap();
+    let (artifact_manager, consensus_pool_cache, consensus_pool, ingress_throttle) =
+        setup_artifact_manager(
+            node_id,
+            Arc::clone(&crypto) as Arc<_>,
+            Arc::clone(&consensus_crypto) as Arc<_>,
+            Arc::clone(&certifier_crypto) as Arc<_>,
+            Arc::clone(&ingress_sig_crypto) as Arc<_>,
+            subnet_id,
+            artifact_pool_config,
+            consensus_config,
+            log.clone(),
+            metrics_registry.clone(),
+            Arc::clone(&registry_client),
+            state_manager,
+            state_sync_client,
+            xnet_payload_builder,
+            self_validating_payload_builder,
+            message_router,
+            ingress_history_reader,
+            catch_up_package,
+            pool_snapshot,
+            malicious_flags.clone(),
+            cycles_account_manager,
+            local_store_time_reader,
+            registry_poll_delay_duration_ms,
+            advert_subscriber.clone(),
+        )
+        .unwrap();
 
     let gossip = Arc::new(GossipImpl::new(
         node_id,
@@ -231,6 +237,7 @@ pub fn create_networking_stack(
     ));
     event_handler.start(gossip.clone());
     advert_subscriber.start(gossip.clone());
+    let peer_artifact_counts_reader = gossip.clone() as Arc<dyn PeerArtifactCountsReader>;
 
     let p2p = P2P {
         log,
@@ -256,6 +263,8 @@ pub fn create_networking_stack(
         ingress_ingestion_service,
         Box::new(p2p),
         consensus_pool_cache,
+        consensus_pool,
+        peer_artifact_counts_reader,
     ))
 }
 
@@ -317,6 +326,7 @@ fn setup_artifact_manager(
     message_router: Arc<dyn MessageRouting>,
     ingress_history_reader: Box<dyn IngressHistoryReader>,
     catch_up_package: CUPWithOriginalProtobuf,
+    pool_snapshot: Option<pb::ConsensusPoolSnapshot>,
     malicious_flags: MaliciousFlags,
     cycles_account_manager: Arc<CyclesAccountManager>,
     local_store_time_reader: Option<Arc<dyn LocalStoreCertifiedTimeReader>>,
@@ -325,6 +335,7 @@ fn setup_artifact_manager(
 ) -> std::io::Result<(
     Arc<dyn ArtifactManager>,
     Arc<dyn ConsensusPoolCache>,
+    Arc<RwLock<dyn ConsensusPool + Send + Sync>>,
     IngressThrottler,
 )> {
     // Initialize the time source.
@@ -339,11 +350,31 @@ fn setup_artifact_manager(
     let (ingress_pool, consensus_pool, cert_pool, dkg_pool, ecdsa_pool) = init_artifact_pools(
         subnet_id,
         artifact_pool_config,
+        Arc::clone(&registry_client),
         metrics_registry.clone(),
         replica_logger.clone(),
         catch_up_package,
     );
 
+    // A snapshot of the chain above the CUP, downloaded from a peer by the
+    // orchestrator, saves waiting for the chain to be gossiped.
+    if let Some(snapshot) = pool_snapshot {
+        match consensus_pool.write().unwrap().insert_snapshot(
+            snapshot,
+            node_id,
+            time_source.get_relative_time(),
+        ) {
+            Ok(count) => info!(
+                replica_logger,
+                "Inserted {} artifacts from the consensus pool snapshot", count
+            ),
+            Err(err) => warn!(
+                replica_logger,
+                "Ignoring the consensus pool snapshot: {}", err
+            ),
+        }
+    }
+
     let consensus_cache = consensus_pool.read().unwrap().get_cache();
     let consensus_block_cache = consensus_pool.read().unwrap().get_block_cache();
 
@@ -360,6 +391,7 @@ fn setup_artifact_manager(
         return Ok((
             artifact_manager_maker.finish(),
             consensus_cache,
+            consensus_pool as Arc<_>,
             ingress_pool as Arc<_>,
         ));
     }
@@ -507,6 +539,29 @@ fn setup_artifact_manager(
         artifact_manager_maker.add_client(dkg_client, actor);
     }
 
+    {
+        // Create the equivocation evidence client.
+        let event_handler = event_handler.clone();
+        let (equivocation_evidence_client, actor) =
+            processors::EquivocationEvidenceProcessor::build(
+                move |req| event_handler.broadcast_advert(req.advert.into(), req.advert_class),
+                || {
+                    (
+                        equivocation::EquivocationEvidenceValidatorImpl::new(
+                            Arc::clone(&consensus_crypto),
+                            metrics_registry.clone(),
+                            replica_logger.clone(),
+                        ),
+                        equivocation::EquivocationEvidenceGossipImpl,
+                    )
+                },
+                Arc::clone(&time_source) as Arc<_>,
+                Arc::clone(&consensus_pool),
+                metrics_registry.clone(),
+            );
+        artifact_manager_maker.add_client(equivocation_evidence_client, actor);
+    }
+
     {
         // Create the ECDSA client if enabled by the config
         if registry_client
@@ -545,6 +600,7 @@ fn setup_artifact_manager(
     Ok((
         artifact_manager_maker.finish(),
         consensus_cache,
+        consensus_pool as Arc<_>,
         ingress_pool as Arc<_>,
     ))
 }
@@ -554,6 +610,7 @@ fn setup_artifact_manager(
 pub(crate) fn init_artifact_pools(
     subnet_id: SubnetId,
     config: ArtifactPoolConfig,
+    registry_client: Arc<dyn RegistryClient>,
     registry: MetricsRegistry,
     log: ReplicaLogger,
     catch_up_package: CUPWithOriginalProtobuf,
@@ -574,6 +631,7 @@ pub(crate) fn init_artifact_pools(
             subnet_id,
             catch_up_package,
             config.clone(),
+            registry_client,
             registry.clone(),
             log.clone(),
         ))),
@@ -26,6 +26,11 @@ pub struct ReplicaArgs {
     #[structopt(long, parse(from_os_str))]
     pub catch_up_package: Option<PathBuf>,
 
+    /// A path to a protobuf-encoded snapshot of the finalized chain above the
+    /// catch-up package, downloaded from a peer
+    #[structopt(long, parse(from_os_str))]
+    pub pool_snapshot: Option<PathBuf>,
+
     /// The version of the Replica being run
     #[structopt(long, parse(try_from_str = ReplicaVersion::try_from))]
     pub replica_version: ReplicaVersion,
@@ -166,6 +166,7 @@ async fn run() -> io::Result<()> {
 
     let node_id = crypto.get_node_id();
     let cup_with_proto = setup::get_catch_up_package(&replica_args, &logger);
+    let pool_snapshot = setup::get_pool_snapshot(&replica_args, &logger);
 
     // Set the replica verison and report as metric
     setup::set_replica_version(&replica_args, &logger);
@@ -287,6 +288,8 @@ async fn run() -> io::Result<()> {
         mut p2p_runner,
         p2p_event_handler,
         consensus_pool_cache,
+        consensus_pool,
+        peer_artifact_counts_reader,
         ingress_message_filter,
         _xnet_endpoint,
     ) = ic_replica::setup_p2p::construct_ic_stack(
@@ -300,6 +303,7 @@ async fn run() -> io::Result<()> {
         crypto,
         metrics_registry.clone(),
         cup_with_proto,
+        pool_snapshot,
         registry_certified_time_reader,
     )?;
 
@@ -321,6 +325,8 @@ async fn run() -> io::Result<()> {
         root_subnet_id,
         logger.clone(),
         consensus_pool_cache,
+        consensus_pool,
+        peer_artifact_counts_reader,
         config.artifact_pool.backup.map(|config| config.spool_path),
         subnet_type,
         malicious_behaviour.malicious_flags.clone(),
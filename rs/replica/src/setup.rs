@@ -79,6 +79,19 @@ pub fn get_catch_up_package(
     }
 }
 
+/// Parse the consensus pool snapshot given via command-line args (if one was
+/// given). The snapshot is optional, so a snapshot that cannot be read is
+/// ignored.
+pub fn get_pool_snapshot(
+    replica_args: &Result<ReplicaArgs, clap::Error>,
+    logger: &ReplicaLogger,
+) -> Option<pb::ConsensusPoolSnapshot> {
+    let path = replica_args.as_ref().ok()?.pool_snapshot.clone()?;
+    pb::ConsensusPoolSnapshot::read_from_file(&path)
+        .map_err(|e| warn!(logger, "Failed to load the consensus pool snapshot: {}", e))
+        .ok()
+}
+
 /// Return the subnet ID of the given node
 ///
 /// First attempts to look up the node's subnet ID in the registry.
@@ -5,21 +5,22 @@ use ic_cycles_account_manager::CyclesAccountManager;
 use ic_execution_environment::setup_execution;
 use ic_interfaces::{
     certified_stream_store::CertifiedStreamStore,
-    consensus_pool::ConsensusPoolCache,
+    consensus_pool::{ConsensusPool, ConsensusPoolCache},
     execution_environment::{IngressFilterService, QueryExecutionService, QueryHandler},
     p2p::IngressIngestionService,
-    p2p::P2PRunner,
+    p2p::{P2PRunner, PeerArtifactCountsReader},
     registry::{LocalStoreCertifiedTimeReader, RegistryClient},
     self_validating_payload::NoOpSelfValidatingPayloadBuilder,
 };
 use ic_logger::{info, ReplicaLogger};
 use ic_messaging::{MessageRoutingImpl, XNetEndpoint, XNetEndpointConfig, XNetPayloadBuilderImpl};
+use ic_protobuf::types::v1 as pb;
 use ic_registry_subnet_type::SubnetType;
 use ic_replica_setup_ic_network::{create_networking_stack, P2PStateSyncClient};
 use ic_replicated_state::ReplicatedState;
 use ic_state_manager::StateManagerImpl;
 use ic_types::{consensus::catchup::CUPWithOriginalProtobuf, NodeId, SubnetId};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 #[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn construct_ic_stack(
@@ -33,6 +34,7 @@ pub fn construct_ic_stack(
     crypto: Arc<CryptoComponent>,
     metrics_registry: ic_metrics::MetricsRegistry,
     catch_up_package: Option<CUPWithOriginalProtobuf>,
+    pool_snapshot: Option<pb::ConsensusPoolSnapshot>,
     local_store_time_reader: Option<Arc<dyn LocalStoreCertifiedTimeReader>>,
 ) -> std::io::Result<(
     // TODO(SCL-213): When Rust traits support it, simplify and pass a single
@@ -44,6 +46,8 @@ pub fn construct_ic_stack(
     Box<dyn P2PRunner>,
     IngressIngestionService,
     Arc<dyn ConsensusPoolCache>,
+    Arc<RwLock<dyn ConsensusPool + Send + Sync>>,
+    Arc<dyn PeerArtifactCountsReader>,
     IngressFilterService,
     XNetEndpoint,
 )> {
@@ -212,7 +216,13 @@ pub fn construct_ic_stack(
         }
     };
 
-    let (p2p_event_handler, p2p_runner, consensus_pool_cache) = create_networking_stack(
+    let (
+        p2p_event_handler,
+        p2p_runner,
+        consensus_pool_cache,
+        consensus_pool,
+        peer_artifact_counts_reader,
+    ) = create_networking_stack(
         metrics_registry,
         replica_logger,
         tokio::runtime::Handle::current(),
@@ -237,6 +247,7 @@ pub fn construct_ic_stack(
         registry,
         ingress_history_reader,
         catch_up_package,
+        pool_snapshot,
         cycles_account_manager,
         local_store_time_reader,
         config.nns_registry_replicator.poll_delay_duration_ms,
@@ -251,6 +262,8 @@ pub fn construct_ic_stack(
         p2p_runner,
         p2p_event_handler,
         consensus_pool_cache,
+        consensus_pool,
+        peer_artifact_counts_reader,
         ingress_filter,
         xnet_endpoint,
     ))
@@ -377,7 +377,7 @@ where
             queue_size: 0,
         }];
         let temp_node = node_id;
-        let (_, state_manager, query_handler, _, mut p2p, p2p_event_handler, _, _, _) =
+        let (_, state_manager, query_handler, _, mut p2p, p2p_event_handler, _, _, _, _, _) =
             ic_replica::setup_p2p::construct_ic_stack(
                 logger,
                 config.clone(),
@@ -390,6 +390,7 @@ where
                 metrics_registry,
                 None,
                 None,
+                None,
             )
             .expect("Failed to setup p2p");
 
@@ -239,6 +239,8 @@ impl TryFrom<pb_metadata::SubnetTopology> for SubnetTopology {
 pub struct NodeTopology {
     pub ip_address: String,
     pub http_port: u16,
+    /// The DER-encoded Ed25519 node signing public key.
+    pub public_key: Vec<u8>,
 }
 
 impl From<&NodeTopology> for pb_metadata::NodeTopology {
@@ -246,6 +248,7 @@ impl From<&NodeTopology> for pb_metadata::NodeTopology {
         Self {
             ip_address: item.ip_address.clone(),
             http_port: item.http_port as u32,
+            public_key: item.public_key.clone(),
         }
     }
 }
@@ -256,6 +259,7 @@ impl TryFrom<pb_metadata::NodeTopology> for NodeTopology {
         Ok(Self {
             ip_address: item.ip_address,
             http_port: item.http_port as u16,
+            public_key: item.public_key,
         })
     }
 }
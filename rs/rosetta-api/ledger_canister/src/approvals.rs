@@ -0,0 +1,210 @@
+use crate::{AccountIdentifier, Operation, TimeStamp, Tokens};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The amount a spender may transfer from an account on behalf of its owner.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct Allowance {
+    pub allowance: Tokens,
+    /// The allowance can't be used at or after this time. If not set, the
+    /// allowance doesn't expire.
+    pub expires_at: Option<TimeStamp>,
+}
+
+impl Default for Allowance {
+    fn default() -> Self {
+        Self {
+            allowance: Tokens::ZERO,
+            expires_at: None,
+        }
+    }
+}
+
+/// Keeps track of the allowances granted through `Approve` operations and
+/// spent through `TransferFrom` operations.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Approvals {
+    /// Allowances indexed by (owner, spender). Only non-zero allowances are
+    /// stored.
+    allowances: BTreeMap<(AccountIdentifier, AccountIdentifier), Allowance>,
+    /// The allowances that have an expiration time, sorted by it, so that
+    /// expired allowances can be removed without scanning all of them.
+    expiration_queue: BTreeSet<(TimeStamp, AccountIdentifier, AccountIdentifier)>,
+}
+
+impl Approvals {
+    /// Returns the allowance that `spender` may transfer from `owner` at time
+    /// `now`. Expired allowances are zero.
+    pub fn allowance(
+        &self,
+        owner: &AccountIdentifier,
+        spender: &AccountIdentifier,
+        now: TimeStamp,
+    ) -> Allowance {
+        match self.allowances.get(&(*owner, *spender)) {
+            Some(allowance) if allowance.expires_at.map_or(true, |t| now < t) => allowance.clone(),
+            _ => Allowance::default(),
+        }
+    }
+
+    /// Records the effect of an operation that has been applied to the
+    /// balances: an `Approve` replaces the allowance of the spender, and a
+    /// `TransferFrom` spends the amount and the fee from it. Other operations
+    /// don't affect allowances.
+    pub fn apply(&mut self, operation: &Operation, now: TimeStamp) {
+        match operation {
+            Operation::Approve {
+                from,
+                spender,
+                allowance,
+                expires_at,
+                ..
+            } => self.set(*from, *spender, *allowance, *expires_at),
+            Operation::TransferFrom {
+                from,
+                spender,
+                amount,
+                fee,
+                ..
+            } => {
+                let current = self.allowance(from, spender, now);
+                let spent = (*amount + *fee).unwrap_or(Tokens::MAX);
+                let remaining = (current.allowance - spent).unwrap_or(Tokens::ZERO);
+                self.set(*from, *spender, remaining, current.expires_at);
+            }
+            Operation::Burn { .. } | Operation::Mint { .. } | Operation::Transfer { .. } => {}
+        }
+    }
+
+    fn set(
+        &mut self,
+        owner: AccountIdentifier,
+        spender: AccountIdentifier,
+        allowance: Tokens,
+        expires_at: Option<TimeStamp>,
+    ) {
+        let key = (owner, spender);
+        if let Some(Allowance {
+            expires_at: Some(t),
+            ..
+        }) = self.allowances.remove(&key)
+        {
+            self.expiration_queue.remove(&(t, owner, spender));
+        }
+        if allowance == Tokens::ZERO {
+            return;
+        }
+        if let Some(t) = expires_at {
+            self.expiration_queue.insert((t, owner, spender));
+        }
+        self.allowances.insert(
+            key,
+            Allowance {
+                allowance,
+                expires_at,
+            },
+        );
+    }
+
+    /// Removes at most `limit` allowances that expired at or before `now` and
+    /// returns the number of removed allowances.
+    pub fn purge_expired(&mut self, now: TimeStamp, limit: usize) -> usize {
+        let mut cnt = 0usize;
+        while cnt < limit {
+            let (t, owner, spender) = match self.expiration_queue.iter().next() {
+                Some(entry) if entry.0 <= now => *entry,
+                _ => break,
+            };
+            self.expiration_queue.remove(&(t, owner, spender));
+            self.allowances.remove(&(owner, spender));
+            cnt += 1;
+        }
+        cnt
+    }
+
+    /// The number of stored allowances, including expired allowances that
+    /// haven't been purged yet.
+    pub fn len(&self) -> usize {
+        self.allowances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allowances.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_types::PrincipalId;
+    use std::time::Duration;
+
+    fn approve(
+        from: AccountIdentifier,
+        spender: AccountIdentifier,
+        allowance: u64,
+        expires_at: Option<TimeStamp>,
+    ) -> Operation {
+        Operation::Approve {
+            from,
+            spender,
+            allowance: Tokens::from_e8s(allowance),
+            expires_at,
+            fee: Tokens::from_e8s(10),
+        }
+    }
+
+    #[test]
+    fn allowances_are_replaced_spent_and_expire() {
+        let owner = PrincipalId::new_user_test_id(1).into();
+        let spender = PrincipalId::new_user_test_id(2).into();
+        let now = TimeStamp::from_nanos_since_unix_epoch(1_000);
+        let later = now + Duration::from_secs(60);
+        let mut approvals = Approvals::default();
+
+        approvals.apply(&approve(owner, spender, 500, None), now);
+        approvals.apply(&approve(owner, spender, 300, Some(later)), now);
+        assert_eq!(
+            approvals.allowance(&owner, &spender, now),
+            Allowance {
+                allowance: Tokens::from_e8s(300),
+                expires_at: Some(later)
+            }
+        );
+        // Allowances are not symmetric.
+        assert_eq!(
+            approvals.allowance(&spender, &owner, now),
+            Allowance::default()
+        );
+
+        approvals.apply(
+            &Operation::TransferFrom {
+                from: owner,
+                to: spender,
+                spender,
+                amount: Tokens::from_e8s(90),
+                fee: Tokens::from_e8s(10),
+            },
+            now,
+        );
+        assert_eq!(
+            approvals.allowance(&owner, &spender, now).allowance,
+            Tokens::from_e8s(200)
+        );
+        assert_eq!(
+            approvals.allowance(&owner, &spender, later),
+            Allowance::default()
+        );
+
+        assert_eq!(approvals.purge_expired(now, 10), 0);
+        assert_eq!(approvals.purge_expired(later, 10), 1);
+        assert!(approvals.is_empty());
+
+        // Approving a zero allowance revokes the previous one.
+        approvals.apply(&approve(owner, spender, 500, Some(later)), now);
+        approvals.apply(&approve(owner, spender, 0, None), now);
+        assert!(approvals.is_empty());
+        assert_eq!(approvals.purge_expired(later, 10), 0);
+    }
+}
@@ -1,6 +1,7 @@
 use ledger_canister::{
-    metrics_encoder::MetricsEncoder, BlockHeight, BlockRange, BlockRes, CandidBlock, EncodedBlock,
-    GetBlocksArgs, GetBlocksError, GetBlocksResult, IterBlocksArgs, MAX_BLOCKS_PER_REQUEST,
+    metrics_encoder::MetricsEncoder, Block, BlockHeight, BlockRange, BlockRes, CandidBlock,
+    CandidTransaction, EncodedBlock, GetBlocksArgs, GetBlocksError, GetBlocksResult,
+    GetTransactionsResult, IterBlocksArgs, TransactionRange, MAX_BLOCKS_PER_REQUEST,
 };
 
 use candid::candid_method;
@@ -188,8 +189,11 @@ fn get_blocks_() {
     });
 }
 
-#[candid_method(query, rename = "get_blocks")]
-fn get_blocks(GetBlocksArgs { start, length }: GetBlocksArgs) -> GetBlocksResult {
+/// Decodes the requested blocks that this node stores, at most
+/// `MAX_BLOCKS_PER_REQUEST` of them.
+fn decode_blocks(
+    GetBlocksArgs { start, length }: GetBlocksArgs,
+) -> Result<Vec<Block>, GetBlocksError> {
     use ledger_canister::range_utils;
 
     let archive_state = ARCHIVE_STATE.read().unwrap();
@@ -210,18 +214,22 @@ fn get_blocks(GetBlocksArgs { start, length }: GetBlocksArgs) -> GetBlocksResult
         &range_utils::head(&requested_range, MAX_BLOCKS_PER_REQUEST),
     );
 
-    let mut candid_blocks: Vec<CandidBlock> =
-        Vec::with_capacity(range_utils::range_len(&effective_range) as usize);
-
-    for i in effective_range {
-        let encoded_block = &blocks[(i - block_range.start) as usize];
-        let candid_block =
-            CandidBlock::from(encoded_block.decode().expect("failed to decode a block"));
-        candid_blocks.push(candid_block);
-    }
+    Ok(effective_range
+        .map(|i| {
+            blocks[(i - block_range.start) as usize]
+                .decode()
+                .expect("failed to decode a block")
+        })
+        .collect())
+}
 
+#[candid_method(query, rename = "get_blocks")]
+fn get_blocks(args: GetBlocksArgs) -> GetBlocksResult {
     Ok(BlockRange {
-        blocks: candid_blocks,
+        blocks: decode_blocks(args)?
+            .into_iter()
+            .map(CandidBlock::from)
+            .collect(),
     })
 }
 
@@ -232,6 +240,24 @@ fn get_blocks_candid_() {
     dfn_core::over(candid_one, get_blocks);
 }
 
+/// Returns the decoded transactions of the requested blocks. Unlike
+/// `get_blocks`, the response doesn't contain the parent hashes and timestamps
+/// needed to verify the blocks against the chain.
+#[candid_method(query, rename = "get_transactions")]
+fn get_transactions(args: GetBlocksArgs) -> GetTransactionsResult {
+    Ok(TransactionRange {
+        transactions: decode_blocks(args)?
+            .into_iter()
+            .map(|block| CandidTransaction::from(block.transaction))
+            .collect(),
+    })
+}
+
+#[export_name = "canister_query get_transactions"]
+fn get_transactions_() {
+    dfn_core::over(candid_one, get_transactions);
+}
+
 #[export_name = "canister_post_upgrade"]
 fn post_upgrade() {
     over_init(|_: BytesS| {
@@ -0,0 +1,264 @@
+//! A standard fungible-token interface for the ledger.
+//!
+//! Accounts are identified by the principal that owns them and an optional
+//! subaccount, instead of by the hash of both that `AccountIdentifier` uses.
+//! This allows wallets to tell who owns an account and to validate an account
+//! offline. Every `Account` maps onto exactly one `AccountIdentifier`, so both
+//! interfaces operate on the same balances.
+use crate::{AccountIdentifier, BlockHeight, Subaccount, Tokens};
+use candid::{CandidType, Int, Nat};
+use ic_types::PrincipalId;
+use num_traits::cast::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use std::fmt;
+use std::str::FromStr;
+
+/// An account of the ledger: a principal and one of its subaccounts. The
+/// default subaccount is all zeros, so `None` and `Some([0; 32])` denote the
+/// same account.
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Debug)]
+pub struct Account {
+    pub owner: PrincipalId,
+    pub subaccount: Option<Subaccount>,
+}
+
+impl Account {
+    pub fn new(owner: PrincipalId, subaccount: Option<Subaccount>) -> Self {
+        Self { owner, subaccount }
+    }
+
+    /// Returns the subaccount, substituting the default subaccount for `None`.
+    pub fn effective_subaccount(&self) -> Subaccount {
+        self.subaccount
+            .unwrap_or(crate::account_identifier::SUB_ACCOUNT_ZERO)
+    }
+
+    // The CRC-32 of the owner and the subaccount, encoded in lowercase base32
+    // without padding.
+    fn checksum(&self) -> String {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(self.owner.as_slice());
+        hasher.update(&self.effective_subaccount().0);
+        base32::encode(
+            base32::Alphabet::RFC4648 { padding: false },
+            &hasher.finalize().to_be_bytes(),
+        )
+        .to_lowercase()
+    }
+}
+
+impl PartialEq for Account {
+    fn eq(&self, other: &Self) -> bool {
+        self.owner == other.owner && self.effective_subaccount() == other.effective_subaccount()
+    }
+}
+
+impl Eq for Account {}
+
+impl std::hash::Hash for Account {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.owner.hash(state);
+        self.effective_subaccount().hash(state);
+    }
+}
+
+impl From<PrincipalId> for Account {
+    fn from(owner: PrincipalId) -> Self {
+        Self::new(owner, None)
+    }
+}
+
+impl From<Account> for AccountIdentifier {
+    fn from(account: Account) -> Self {
+        AccountIdentifier::new(account.owner, account.subaccount)
+    }
+}
+
+/// The textual encoding of an account is the textual encoding of its owner
+/// for the default subaccount. Otherwise it is
+/// `<owner>-<checksum>.<subaccount>`, where the subaccount is hex encoded
+/// without leading zeros and the checksum protects against typos.
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subaccount = self.effective_subaccount();
+        if subaccount == crate::account_identifier::SUB_ACCOUNT_ZERO {
+            return write!(f, "{}", self.owner);
+        }
+        let hex = hex::encode(subaccount.0);
+        write!(
+            f,
+            "{}-{}.{}",
+            self.owner,
+            self.checksum(),
+            hex.trim_start_matches('0')
+        )
+    }
+}
+
+/// An error for reporting invalid textual encodings of accounts.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AccountParseError {
+    InvalidOwner(String),
+    InvalidSubaccount(String),
+    BadChecksum,
+    /// The default subaccount must be encoded without a checksum.
+    NotCanonical,
+}
+
+impl fmt::Display for AccountParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidOwner(e) => write!(f, "invalid owner: {}", e),
+            Self::InvalidSubaccount(e) => write!(f, "invalid subaccount: {}", e),
+            Self::BadChecksum => write!(f, "the checksum doesn't match the account"),
+            Self::NotCanonical => write!(f, "the default subaccount must be omitted"),
+        }
+    }
+}
+
+impl FromStr for Account {
+    type Err = AccountParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (owner_and_checksum, subaccount) = match s.split_once('.') {
+            None => {
+                let owner = PrincipalId::from_str(s)
+                    .map_err(|e| AccountParseError::InvalidOwner(e.to_string()))?;
+                return Ok(Self::new(owner, None));
+            }
+            Some(parts) => parts,
+        };
+        let (owner, checksum) = owner_and_checksum
+            .rsplit_once('-')
+            .ok_or(AccountParseError::BadChecksum)?;
+        let owner = PrincipalId::from_str(owner)
+            .map_err(|e| AccountParseError::InvalidOwner(e.to_string()))?;
+        if subaccount.is_empty() || subaccount.starts_with('0') || subaccount.len() > 64 {
+            return Err(AccountParseError::NotCanonical);
+        }
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(format!("{:0>64}", subaccount), &mut bytes as &mut [u8])
+            .map_err(|e| AccountParseError::InvalidSubaccount(e.to_string()))?;
+        let account = Self::new(owner, Some(Subaccount(bytes)));
+        if account.checksum() != checksum {
+            return Err(AccountParseError::BadChecksum);
+        }
+        Ok(account)
+    }
+}
+
+/// Argument taken by the icrc1_transfer endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct TransferArg {
+    pub from_subaccount: Option<Subaccount>,
+    pub to: Account,
+    pub amount: Nat,
+    /// The fee the caller agrees to pay. If not set, the current fee is paid.
+    pub fee: Option<Nat>,
+    pub memo: Option<u64>,
+    /// Nanoseconds since the Unix epoch.
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum TransferError {
+    BadFee { expected_fee: Nat },
+    BadBurn { min_burn_amount: Nat },
+    InsufficientFunds { balance: Nat },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    Duplicate { duplicate_of: Nat },
+    TemporarilyUnavailable,
+    GenericError { error_code: Nat, message: String },
+}
+
+impl From<crate::TransferError> for TransferError {
+    fn from(e: crate::TransferError) -> Self {
+        match e {
+            crate::TransferError::BadFee { expected_fee } => Self::BadFee {
+                expected_fee: tokens_to_nat(expected_fee),
+            },
+            crate::TransferError::InsufficientFunds { balance } => Self::InsufficientFunds {
+                balance: tokens_to_nat(balance),
+            },
+            crate::TransferError::TxTooOld { .. } => Self::TooOld,
+            crate::TransferError::TxCreatedInFuture => Self::CreatedInFuture {
+                ledger_time: dfn_core::api::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos() as u64,
+            },
+            crate::TransferError::TxDuplicate { duplicate_of } => Self::Duplicate {
+                duplicate_of: Nat::from(duplicate_of),
+            },
+        }
+    }
+}
+
+/// A value of the token metadata.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Nat(Nat),
+    Int(Int),
+    Text(String),
+    Blob(ByteBuf),
+}
+
+pub fn tokens_to_nat(tokens: Tokens) -> Nat {
+    Nat::from(tokens.get_e8s())
+}
+
+/// Converts an amount to `Tokens`, failing if it exceeds `u64::MAX` e8s.
+pub fn nat_to_tokens(amount: &Nat) -> Result<Tokens, String> {
+    amount
+        .0
+        .to_u64()
+        .map(Tokens::from_e8s)
+        .ok_or_else(|| format!("the amount {} doesn't fit into 64 bits", amount))
+}
+
+pub type TransferResult = Result<BlockHeight, TransferError>;
+
+/// The `error_code` of the [TransferError::GenericError] returned for a
+/// transfer from the minting account to itself, which is neither a mint nor a
+/// burn.
+pub const ERROR_CODE_MINTING_ACCOUNT_SELF_TRANSFER: u64 = 1;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn account_text_encoding_round_trip() {
+        let owner = PrincipalId::new_user_test_id(1);
+        let default = Account::new(owner, None);
+        assert_eq!(default.to_string(), owner.to_string());
+        assert_eq!(Account::from_str(&default.to_string()), Ok(default));
+        assert_eq!(default, Account::new(owner, Some(Subaccount([0; 32]))));
+
+        let mut subaccount = [0; 32];
+        subaccount[30] = 1;
+        subaccount[31] = 2;
+        let account = Account::new(owner, Some(Subaccount(subaccount)));
+        let text = account.to_string();
+        assert!(text.ends_with(".102"), "{}", text);
+        assert_eq!(Account::from_str(&text), Ok(account));
+        assert_eq!(
+            AccountIdentifier::from(account),
+            AccountIdentifier::new(owner, Some(Subaccount(subaccount)))
+        );
+
+        // A typo in the subaccount is detected by the checksum.
+        let typo = format!("{}3", &text[..text.len() - 1]);
+        assert_eq!(
+            Account::from_str(&typo),
+            Err(AccountParseError::BadChecksum)
+        );
+        let padded = text.replace(".102", ".0102");
+        assert_eq!(
+            Account::from_str(&padded),
+            Err(AccountParseError::NotCanonical)
+        );
+    }
+}
@@ -0,0 +1,618 @@
+//! The state of the index canister, which follows the ledger and its
+//! archive nodes and keeps, for every account, the indices of the blocks
+//! whose transactions involve the account.
+//!
+//! The synced blocks are appended to a log in stable memory. Each entry of
+//! the log holds a block and, for every account the block involves, the
+//! offset of the previous entry involving that account, so the history of an
+//! account is a linked list in stable memory. The heap only holds the offset
+//! of the latest entry of each account, the offsets of every
+//! `ENTRIES_PER_CHECKPOINT`-th entry of each account (so that pages of older
+//! transactions don't walk the account's whole newer history) and the account
+//! balances. On upgrades, only this heap state is written to stable memory,
+//! after the log.
+use crate::{
+    AccountIdBlob, AccountIdentifier, Block, BlockHeight, CandidBlock, EncodedBlock, HashOf,
+    LedgerBalances, Operation, Tokens,
+};
+use byteorder::{ByteOrder, LittleEndian};
+use candid::CandidType;
+use ic_types::CanisterId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// The maximum number of transactions returned by `get_account_transactions`.
+pub const MAX_RESULTS_PER_REQUEST: u64 = 1000;
+
+/// Argument taken by the index canister's init method
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct IndexCanisterInitArgs {
+    pub ledger_id: CanisterId,
+}
+
+/// Argument taken by the get_account_transactions endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct GetAccountTransactionsArgs {
+    pub account: AccountIdBlob,
+    /// Only transactions in blocks below this index are returned. If not
+    /// set, the most recent transactions are returned.
+    pub start: Option<BlockHeight>,
+    pub max_results: u64,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct AccountTransaction {
+    pub block_index: BlockHeight,
+    pub block: CandidBlock,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct GetAccountTransactionsResponse {
+    /// The balance of the account after the last synced block.
+    pub balance: Tokens,
+    /// The transactions involving the account, the most recent first.
+    pub transactions: Vec<AccountTransaction>,
+    /// The index of the oldest block involving the account, if any.
+    pub oldest_block_index: Option<BlockHeight>,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct IndexStatus {
+    /// The number of ledger blocks the index has synced.
+    pub num_blocks_synced: u64,
+    /// The number of synced blocks whose transaction could not be applied to
+    /// the balances of the index. These blocks are indexed nevertheless.
+    pub num_balance_errors: u64,
+    /// The error of the last attempt to sync blocks, if it failed.
+    pub last_sync_error: Option<String>,
+}
+
+/// The stable memory holding the log of synced blocks. Outside of a canister,
+/// it can be backed by a vector.
+pub trait Memory {
+    /// Returns the size of the memory in WebAssembly pages.
+    fn size(&self) -> u64;
+    /// Grows the memory by the given number of pages. Returns the previous
+    /// size, or -1 if the memory cannot grow.
+    fn grow(&mut self, pages: u64) -> i64;
+    fn read(&self, offset: u64, buf: &mut [u8]);
+    fn write(&mut self, offset: u64, bytes: &[u8]);
+}
+
+pub const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+// The stable memory starts with the offset and the length of the heap state
+// saved by `IndexState::save`, followed by the log.
+const HEADER_SIZE: u64 = 16;
+
+// Marks the end of the history of an account.
+const NO_ENTRY: u64 = u64::MAX;
+
+const ACCOUNT_HASH_SIZE: usize = 28;
+// The block index and the number of involved accounts.
+const ENTRY_HEADER_SIZE: usize = 8 + 1;
+// An involved account and the offset of its previous entry.
+const ENTRY_LINK_SIZE: usize = ACCOUNT_HASH_SIZE + 8;
+
+// The number of entries of an account between two entries whose offsets are
+// kept on the heap.
+const ENTRIES_PER_CHECKPOINT: u64 = 64;
+
+/// Where the history of an account is found in the log.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+struct AccountHistory {
+    /// The index of the oldest block involving the account.
+    oldest_block_index: BlockHeight,
+    /// The offset of the log entry of the latest block involving the account.
+    latest_entry: u64,
+    /// The number of log entries involving the account.
+    num_entries: u64,
+    /// The block indices and the offsets of every `ENTRIES_PER_CHECKPOINT`-th
+    /// log entry involving the account, the oldest first.
+    checkpoints: Vec<(BlockHeight, u64)>,
+}
+
+/// A log entry, without the block itself.
+struct Entry {
+    block_index: BlockHeight,
+    /// The involved accounts and the offsets of their previous entries.
+    links: Vec<(AccountIdentifier, u64)>,
+    /// The offset and the length of the encoded block.
+    block_offset: u64,
+    block_len: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IndexState {
+    pub ledger_id: CanisterId,
+    num_blocks_synced: u64,
+    last_hash: Option<HashOf<EncodedBlock>>,
+    /// The offset following the last entry of the log.
+    log_end: u64,
+    accounts: BTreeMap<AccountIdentifier, AccountHistory>,
+    balances: LedgerBalances,
+    num_balance_errors: u64,
+    pub last_sync_error: Option<String>,
+}
+
+impl IndexState {
+    pub fn new(ledger_id: CanisterId) -> Self {
+        Self {
+            ledger_id,
+            num_blocks_synced: 0,
+            last_hash: None,
+            log_end: HEADER_SIZE,
+            accounts: BTreeMap::new(),
+            balances: LedgerBalances::default(),
+            num_balance_errors: 0,
+            last_sync_error: None,
+        }
+    }
+
+    /// The index of the next block to sync from the ledger.
+    pub fn num_blocks_synced(&self) -> u64 {
+        self.num_blocks_synced
+    }
+
+    pub fn status(&self) -> IndexStatus {
+        IndexStatus {
+            num_blocks_synced: self.num_blocks_synced,
+            num_balance_errors: self.num_balance_errors,
+            last_sync_error: self.last_sync_error.clone(),
+        }
+    }
+
+    /// Appends the next block of the ledger's chain to the log. Fails, without
+    /// changing the state, if the block doesn't extend the synced chain or the
+    /// log cannot grow. The ledger is the authority on the validity of its
+    /// transactions, so a transaction the balances of the index cannot apply
+    /// is only counted as a balance error.
+    pub fn append_block(
+        &mut self,
+        memory: &mut dyn Memory,
+        block: CandidBlock,
+    ) -> Result<(), String> {
+        let block = Block::try_from(block)?;
+        let block_index = self.num_blocks_synced;
+        if block.parent_hash != self.last_hash {
+            return Err(format!(
+                "block {} doesn't extend the synced chain",
+                block_index
+            ));
+        }
+        let operation = block.transaction.operation.clone();
+        let encoded_block = block.encode()?;
+
+        let mut accounts = involved_accounts(&operation);
+        accounts.sort();
+        accounts.dedup();
+        let mut entry = vec![0; ENTRY_HEADER_SIZE];
+        LittleEndian::write_u64(&mut entry[..8], block_index);
+        entry[8] = accounts.len() as u8;
+        for account in &accounts {
+            let previous = self
+                .accounts
+                .get(account)
+                .map_or(NO_ENTRY, |history| history.latest_entry);
+            entry.extend_from_slice(&account.hash);
+            entry.extend_from_slice(&previous.to_le_bytes());
+        }
+        entry.extend_from_slice(&(encoded_block.0.len() as u32).to_le_bytes());
+        entry.extend_from_slice(&encoded_block.0);
+        let entry_offset = self.log_end;
+        write(memory, entry_offset, &entry)?;
+
+        if self.balances.add_payment(&operation).is_err() {
+            self.num_balance_errors += 1;
+        }
+        for account in accounts {
+            let history = self.accounts.entry(account).or_insert(AccountHistory {
+                oldest_block_index: block_index,
+                latest_entry: entry_offset,
+                num_entries: 0,
+                checkpoints: vec![],
+            });
+            if history.num_entries % ENTRIES_PER_CHECKPOINT == 0 {
+                history.checkpoints.push((block_index, entry_offset));
+            }
+            history.latest_entry = entry_offset;
+            history.num_entries += 1;
+        }
+        self.last_hash = Some(encoded_block.hash());
+        self.log_end += entry.len() as u64;
+        self.num_blocks_synced += 1;
+        Ok(())
+    }
+
+    pub fn get_account_transactions(
+        &self,
+        memory: &dyn Memory,
+        account: &AccountIdentifier,
+        start: Option<BlockHeight>,
+        max_results: u64,
+    ) -> GetAccountTransactionsResponse {
+        let history = self.accounts.get(account);
+        let max_results = max_results.min(MAX_RESULTS_PER_REQUEST) as usize;
+        let mut transactions = vec![];
+        // Start at the oldest checkpoint not below `start`, if any, from which
+        // fewer than `ENTRIES_PER_CHECKPOINT` entries are skipped.
+        let mut next = history.map_or(NO_ENTRY, |history| match start {
+            Some(start) => {
+                let first = history
+                    .checkpoints
+                    .partition_point(|(block_index, _)| *block_index < start);
+                history
+                    .checkpoints
+                    .get(first)
+                    .map_or(history.latest_entry, |(_, offset)| *offset)
+            }
+            None => history.latest_entry,
+        });
+        while next != NO_ENTRY && transactions.len() < max_results {
+            let entry = read_entry(memory, next);
+            if start.map_or(true, |start| entry.block_index < start) {
+                let mut bytes = vec![0; entry.block_len as usize];
+                memory.read(entry.block_offset, &mut bytes);
+                transactions.push(AccountTransaction {
+                    block_index: entry.block_index,
+                    block: CandidBlock::from(
+                        EncodedBlock::from(bytes.into_boxed_slice())
+                            .decode()
+                            .expect("failed to decode a block"),
+                    ),
+                });
+            }
+            next = entry
+                .links
+                .iter()
+                .find(|(linked, _)| linked == account)
+                .map(|(_, previous)| *previous)
+                .expect("the log entry doesn't link the account");
+        }
+        GetAccountTransactionsResponse {
+            balance: self.balances.account_balance(account),
+            transactions,
+            oldest_block_index: history.map(|history| history.oldest_block_index),
+        }
+    }
+
+    pub fn num_accounts(&self) -> usize {
+        self.accounts.len()
+    }
+
+    /// Writes the heap state to stable memory, after the log, so that it can
+    /// be restored with `load` after an upgrade.
+    pub fn save(&self, memory: &mut dyn Memory) -> Result<(), String> {
+        let bytes = serde_cbor::to_vec(self)
+            .map_err(|e| format!("failed to encode the index state: {}", e))?;
+        write(memory, self.log_end, &bytes)?;
+        let mut header = [0; HEADER_SIZE as usize];
+        LittleEndian::write_u64(&mut header[..8], self.log_end);
+        LittleEndian::write_u64(&mut header[8..], bytes.len() as u64);
+        write(memory, 0, &header)
+    }
+
+    /// Restores the state saved by `save`.
+    pub fn load(memory: &dyn Memory) -> Result<Self, String> {
+        let mut header = [0; HEADER_SIZE as usize];
+        memory.read(0, &mut header);
+        let offset = LittleEndian::read_u64(&header[..8]);
+        let len = LittleEndian::read_u64(&header[8..]);
+        if offset.saturating_add(len) > memory.size() * WASM_PAGE_SIZE {
+            return Err("the saved index state is out of bounds".to_string());
+        }
+        let mut bytes = vec![0; len as usize];
+        memory.read(offset, &mut bytes);
+        serde_cbor::from_slice(&bytes)
+            .map_err(|e| format!("failed to decode the index state: {}", e))
+    }
+}
+
+// Writes the bytes at the given offset, growing the memory if needed.
+fn write(memory: &mut dyn Memory, offset: u64, bytes: &[u8]) -> Result<(), String> {
+    let end = offset + bytes.len() as u64;
+    let size = memory.size() * WASM_PAGE_SIZE;
+    if end > size {
+        let pages = (end - size + WASM_PAGE_SIZE - 1) / WASM_PAGE_SIZE;
+        if memory.grow(pages) < 0 {
+            return Err(format!("failed to grow stable memory by {} pages", pages));
+        }
+    }
+    memory.write(offset, bytes);
+    Ok(())
+}
+
+fn read_entry(memory: &dyn Memory, offset: u64) -> Entry {
+    let mut header = [0; ENTRY_HEADER_SIZE];
+    memory.read(offset, &mut header);
+    let num_links = header[8] as usize;
+    let mut links = vec![0; num_links * ENTRY_LINK_SIZE + 4];
+    memory.read(offset + ENTRY_HEADER_SIZE as u64, &mut links);
+    let block_len = LittleEndian::read_u32(&links[num_links * ENTRY_LINK_SIZE..]);
+    Entry {
+        block_index: LittleEndian::read_u64(&header[..8]),
+        links: links[..num_links * ENTRY_LINK_SIZE]
+            .chunks(ENTRY_LINK_SIZE)
+            .map(|link| {
+                let mut hash = [0; ACCOUNT_HASH_SIZE];
+                hash.copy_from_slice(&link[..ACCOUNT_HASH_SIZE]);
+                (
+                    AccountIdentifier { hash },
+                    LittleEndian::read_u64(&link[ACCOUNT_HASH_SIZE..]),
+                )
+            })
+            .collect(),
+        block_offset: offset + (ENTRY_HEADER_SIZE + links.len()) as u64,
+        block_len,
+    }
+}
+
+fn involved_accounts(operation: &Operation) -> Vec<AccountIdentifier> {
+    match operation {
+        Operation::Burn { from, .. } => vec![*from],
+        Operation::Mint { to, .. } => vec![*to],
+        Operation::Transfer { from, to, .. } => vec![*from, *to],
+        Operation::Approve { from, spender, .. } => vec![*from, *spender],
+        Operation::TransferFrom {
+            from, to, spender, ..
+        } => vec![*from, *to, *spender],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memo, TimeStamp, Transaction};
+    use ic_types::PrincipalId;
+    use std::cell::Cell;
+
+    #[derive(Default)]
+    struct VecMemory(Vec<u8>);
+
+    impl Memory for VecMemory {
+        fn size(&self) -> u64 {
+            self.0.len() as u64 / WASM_PAGE_SIZE
+        }
+
+        fn grow(&mut self, pages: u64) -> i64 {
+            let size = self.size();
+            self.0.resize(((size + pages) * WASM_PAGE_SIZE) as usize, 0);
+            size as i64
+        }
+
+        fn read(&self, offset: u64, buf: &mut [u8]) {
+            let offset = offset as usize;
+            buf.copy_from_slice(&self.0[offset..offset + buf.len()]);
+        }
+
+        fn write(&mut self, offset: u64, bytes: &[u8]) {
+            let offset = offset as usize;
+            self.0[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+    }
+
+    /// Counts the reads from a memory.
+    struct CountingMemory<'a>(&'a VecMemory, Cell<u64>);
+
+    impl Memory for CountingMemory<'_> {
+        fn size(&self) -> u64 {
+            self.0.size()
+        }
+
+        fn grow(&mut self, _pages: u64) -> i64 {
+            unimplemented!()
+        }
+
+        fn read(&self, offset: u64, buf: &mut [u8]) {
+            self.1.set(self.1.get() + 1);
+            self.0.read(offset, buf)
+        }
+
+        fn write(&mut self, _offset: u64, _bytes: &[u8]) {
+            unimplemented!()
+        }
+    }
+
+    fn account(i: u64) -> AccountIdentifier {
+        PrincipalId::new_user_test_id(i).into()
+    }
+
+    fn block(parent_hash: Option<HashOf<EncodedBlock>>, operation: Operation) -> Block {
+        Block::new_from_transaction(
+            parent_hash,
+            Transaction {
+                operation,
+                memo: Memo(0),
+                created_at_time: TimeStamp::from_nanos_since_unix_epoch(1),
+            },
+            TimeStamp::from_nanos_since_unix_epoch(2),
+        )
+    }
+
+    fn append(state: &mut IndexState, memory: &mut VecMemory, operation: Operation) {
+        let block = block(state.last_hash, operation);
+        state
+            .append_block(memory, CandidBlock::from(block))
+            .unwrap();
+    }
+
+    fn block_indices(response: &GetAccountTransactionsResponse) -> Vec<BlockHeight> {
+        response
+            .transactions
+            .iter()
+            .map(|t| t.block_index)
+            .collect()
+    }
+
+    #[test]
+    fn account_transactions_are_indexed_and_paginated() {
+        let mut memory = VecMemory::default();
+        let mut state = IndexState::new(CanisterId::from_u64(1));
+        let operations = vec![
+            Operation::Mint {
+                to: account(1),
+                amount: Tokens::from_e8s(1_000_000),
+            },
+            Operation::Transfer {
+                from: account(1),
+                to: account(2),
+                amount: Tokens::from_e8s(1_000),
+                fee: Tokens::from_e8s(10),
+            },
+            Operation::Mint {
+                to: account(3),
+                amount: Tokens::from_e8s(1_000),
+            },
+            Operation::Burn {
+                from: account(1),
+                amount: Tokens::from_e8s(500),
+            },
+        ];
+        for operation in operations {
+            append(&mut state, &mut memory, operation);
+        }
+        assert_eq!(state.num_blocks_synced(), 4);
+        assert_eq!(state.num_accounts(), 3);
+
+        let response = state.get_account_transactions(&memory, &account(1), None, 10);
+        assert_eq!(response.balance, Tokens::from_e8s(1_000_000 - 1_010 - 500));
+        assert_eq!(block_indices(&response), vec![3, 1, 0]);
+        assert_eq!(response.oldest_block_index, Some(0));
+        assert_eq!(
+            Block::try_from(response.transactions[1].block.clone())
+                .unwrap()
+                .transaction
+                .operation,
+            Operation::Transfer {
+                from: account(1),
+                to: account(2),
+                amount: Tokens::from_e8s(1_000),
+                fee: Tokens::from_e8s(10),
+            }
+        );
+
+        let page = state.get_account_transactions(&memory, &account(1), Some(3), 1);
+        assert_eq!(block_indices(&page), vec![1]);
+        let page = state.get_account_transactions(&memory, &account(2), None, 10);
+        assert_eq!(block_indices(&page), vec![1]);
+
+        let unknown = state.get_account_transactions(&memory, &account(4), None, 10);
+        assert_eq!(unknown.balance, Tokens::ZERO);
+        assert!(unknown.transactions.is_empty());
+        assert_eq!(unknown.oldest_block_index, None);
+
+        // Blocks that don't extend the synced chain are rejected.
+        let orphan = block(
+            None,
+            Operation::Mint {
+                to: account(1),
+                amount: Tokens::from_e8s(1),
+            },
+        );
+        assert!(state
+            .append_block(&mut memory, CandidBlock::from(orphan))
+            .is_err());
+        assert_eq!(state.num_blocks_synced(), 4);
+    }
+
+    #[test]
+    fn pages_start_near_the_start_block() {
+        let mut memory = VecMemory::default();
+        let mut state = IndexState::new(CanisterId::from_u64(1));
+        for i in 0..1_000 {
+            append(
+                &mut state,
+                &mut memory,
+                Operation::Mint {
+                    to: account(i % 2),
+                    amount: Tokens::from_e8s(1),
+                },
+            );
+        }
+
+        for start in [0, 1, 2, 127, 128, 129, 500, 998, 999, 1_000, 2_000] {
+            let memory = CountingMemory(&memory, Cell::new(0));
+            let page = state.get_account_transactions(&memory, &account(1), Some(start), 3);
+            // The account is involved in the blocks with odd indices.
+            let expected: Vec<_> = (0..start.min(1_000))
+                .rev()
+                .filter(|block_index| block_index % 2 == 1)
+                .take(3)
+                .collect();
+            assert_eq!(block_indices(&page), expected, "start = {}", start);
+            // Reading an entry takes 2 reads, plus 1 for a returned block.
+            assert!(
+                memory.1.get() <= 2 * (ENTRIES_PER_CHECKPOINT + 3) + 3,
+                "start = {}: {} reads",
+                start,
+                memory.1.get()
+            );
+        }
+    }
+
+    #[test]
+    fn transactions_the_balances_cannot_apply_are_indexed() {
+        let mut memory = VecMemory::default();
+        let mut state = IndexState::new(CanisterId::from_u64(1));
+        append(
+            &mut state,
+            &mut memory,
+            Operation::Burn {
+                from: account(1),
+                amount: Tokens::from_e8s(500),
+            },
+        );
+        append(
+            &mut state,
+            &mut memory,
+            Operation::Mint {
+                to: account(1),
+                amount: Tokens::from_e8s(1_000),
+            },
+        );
+        assert_eq!(state.status().num_balance_errors, 1);
+        let response = state.get_account_transactions(&memory, &account(1), None, 10);
+        assert_eq!(block_indices(&response), vec![1, 0]);
+        assert_eq!(response.balance, Tokens::from_e8s(1_000));
+    }
+
+    #[test]
+    fn state_survives_save_and_load() {
+        let mut memory = VecMemory::default();
+        let mut state = IndexState::new(CanisterId::from_u64(1));
+        for i in 0..100 {
+            append(
+                &mut state,
+                &mut memory,
+                Operation::Mint {
+                    to: account(i % 3),
+                    amount: Tokens::from_e8s(i + 1),
+                },
+            );
+        }
+        let expected = state.get_account_transactions(&memory, &account(1), None, 100);
+        assert_eq!(expected.transactions.len(), 33);
+
+        state.save(&mut memory).unwrap();
+        let mut state = IndexState::load(&memory).unwrap();
+        assert_eq!(state.num_blocks_synced(), 100);
+        assert_eq!(
+            state.get_account_transactions(&memory, &account(1), None, 100),
+            expected
+        );
+
+        // The saved state is overwritten by the blocks synced after loading.
+        append(
+            &mut state,
+            &mut memory,
+            Operation::Mint {
+                to: account(1),
+                amount: Tokens::from_e8s(1),
+            },
+        );
+        let response = state.get_account_transactions(&memory, &account(1), None, 100);
+        assert_eq!(response.transactions.len(), 34);
+        assert_eq!(response.transactions[1..], expected.transactions[..]);
+    }
+}
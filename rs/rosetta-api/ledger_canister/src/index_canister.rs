@@ -0,0 +1,296 @@
+use candid::candid_method;
+use dfn_candid::{candid, candid_one, CandidOne};
+use dfn_core::api::{call_with_cleanup, trap_with};
+use dfn_core::{over, over_init, stable, BytesS};
+use ledger_canister::index::{
+    GetAccountTransactionsArgs, GetAccountTransactionsResponse, IndexCanisterInitArgs, IndexState,
+    IndexStatus, Memory,
+};
+use ledger_canister::{
+    metrics_encoder::MetricsEncoder, AccountIdentifier, GetBlocksArgs, GetBlocksResult,
+    QueryBlocksResponse, MAX_BLOCKS_PER_REQUEST,
+};
+use std::sync::RwLock;
+
+lazy_static::lazy_static! {
+    static ref INDEX_STATE: RwLock<IndexState> =
+        RwLock::new(IndexState::new(ic_nns_constants::LEDGER_CANISTER_ID));
+    // Set while blocks are being fetched, so that heartbeats don't start a
+    // concurrent sync.
+    static ref IS_SYNCING: RwLock<bool> = RwLock::new(false);
+}
+
+/// Clears a flag when dropped, which also happens when a callback of the
+/// future holding it traps.
+struct ClearOnDrop(&'static RwLock<bool>);
+
+impl Drop for ClearOnDrop {
+    fn drop(&mut self) {
+        *self.0.write().unwrap() = false;
+    }
+}
+
+/// Records the error of a sync when dropped: the one set when the sync
+/// finished, or that it trapped if a callback of the sync trapped.
+struct SyncErrorOnDrop(Option<Option<String>>);
+
+impl Drop for SyncErrorOnDrop {
+    fn drop(&mut self) {
+        INDEX_STATE.write().unwrap().last_sync_error = self
+            .0
+            .take()
+            .unwrap_or_else(|| Some("the sync trapped".to_string()));
+    }
+}
+
+/// The stable memory of the canister, which holds the log of synced blocks.
+struct StableMemory;
+
+impl Memory for StableMemory {
+    fn size(&self) -> u64 {
+        stable::stable64_size()
+    }
+
+    fn grow(&mut self, pages: u64) -> i64 {
+        stable::stable64_grow(pages)
+    }
+
+    fn read(&self, offset: u64, buf: &mut [u8]) {
+        stable::stable64_read(buf, offset, buf.len() as u64)
+    }
+
+    fn write(&mut self, offset: u64, bytes: &[u8]) {
+        stable::stable64_write(offset, bytes)
+    }
+}
+
+// Helper to print messages in blue
+fn print<S: std::convert::AsRef<str>>(s: S)
+where
+    yansi::Paint<S>: std::string::ToString,
+{
+    dfn_core::api::print(yansi::Paint::blue(s).to_string());
+}
+
+/// Fetches the blocks following the last synced block from the ledger, or
+/// from the archive node that holds them, and indexes them. Returns the
+/// number of indexed blocks.
+async fn sync_blocks() -> Result<u64, String> {
+    let (ledger_id, start) = {
+        let state = INDEX_STATE.read().unwrap();
+        (state.ledger_id, state.num_blocks_synced())
+    };
+    let response: QueryBlocksResponse = call_with_cleanup(
+        ledger_id,
+        "query_blocks",
+        candid_one,
+        GetBlocksArgs {
+            start,
+            length: MAX_BLOCKS_PER_REQUEST,
+        },
+    )
+    .await
+    .map_err(|(code, msg)| format!("query_blocks failed: {:?} {}", code, msg))?;
+
+    // Archived blocks precede the blocks the ledger still holds, so the next
+    // block is in the first archived range if there is one.
+    let blocks = match response.archived_blocks.first() {
+        Some(range) if range.start == start => {
+            let result: GetBlocksResult = call_with_cleanup(
+                range.callback.canister_id,
+                &range.callback.method,
+                candid_one,
+                GetBlocksArgs {
+                    start: range.start,
+                    length: range.length.min(MAX_BLOCKS_PER_REQUEST as u64) as usize,
+                },
+            )
+            .await
+            .map_err(|(code, msg)| {
+                format!("fetching archived blocks failed: {:?} {}", code, msg)
+            })?;
+            result
+                .map_err(|e| format!("fetching archived blocks failed: {:?}", e))?
+                .blocks
+        }
+        Some(range) => {
+            return Err(format!(
+                "expected archived blocks to start at {}, got {}",
+                start, range.start
+            ))
+        }
+        None if response.blocks.is_empty() || response.first_block_index == start => {
+            response.blocks
+        }
+        None => {
+            return Err(format!(
+                "expected blocks to start at {}, got {}",
+                start, response.first_block_index
+            ))
+        }
+    };
+
+    let mut state = INDEX_STATE.write().unwrap();
+    if state.num_blocks_synced() != start {
+        return Err("the index changed while fetching blocks".to_string());
+    }
+    let num_blocks = blocks.len() as u64;
+    for (i, block) in blocks.into_iter().enumerate() {
+        // The blocks appended so far are kept, so the next sync resumes
+        // from the failed block.
+        state
+            .append_block(&mut StableMemory, block)
+            .map_err(|e| format!("failed to index block {}: {}", start + i as u64, e))?;
+    }
+    Ok(num_blocks)
+}
+
+#[export_name = "canister_heartbeat"]
+fn canister_heartbeat() {
+    {
+        let mut is_syncing = IS_SYNCING.write().unwrap();
+        if *is_syncing {
+            return;
+        }
+        *is_syncing = true;
+    }
+    // canister_heartbeat must be synchronous, so we cannot .await the future
+    dfn_core::api::futures::spawn(async {
+        let _is_syncing = ClearOnDrop(&IS_SYNCING);
+        let mut sync_error = SyncErrorOnDrop(None);
+        let result = sync_blocks().await;
+        if let Err(e) = &result {
+            print(format!("[index] failed to sync blocks: {}", e));
+        }
+        sync_error.0 = Some(result.err());
+    });
+}
+
+#[candid_method(init)]
+fn init(args: IndexCanisterInitArgs) {
+    print(format!("[index] init(): ledger is {}", args.ledger_id));
+    *INDEX_STATE.write().unwrap() = IndexState::new(args.ledger_id);
+}
+
+#[export_name = "canister_init"]
+fn main() {
+    over_init(|CandidOne(args)| init(args))
+}
+
+#[candid_method(query, rename = "get_account_transactions")]
+fn get_account_transactions(args: GetAccountTransactionsArgs) -> GetAccountTransactionsResponse {
+    let account = AccountIdentifier::from_address(args.account).unwrap_or_else(|e| {
+        trap_with(&format!("Invalid account identifier: {}", e));
+        unreachable!()
+    });
+    INDEX_STATE.read().unwrap().get_account_transactions(
+        &StableMemory,
+        &account,
+        args.start,
+        args.max_results,
+    )
+}
+
+#[export_name = "canister_query get_account_transactions"]
+fn get_account_transactions_() {
+    over(candid_one, get_account_transactions)
+}
+
+#[candid_method(query, rename = "status")]
+fn status() -> IndexStatus {
+    INDEX_STATE.read().unwrap().status()
+}
+
+#[export_name = "canister_query status"]
+fn status_() {
+    over(candid, |()| status())
+}
+
+#[export_name = "canister_post_upgrade"]
+fn post_upgrade() {
+    over_init(|_: BytesS| {
+        let mut state = INDEX_STATE.write().unwrap();
+        *state = IndexState::load(&StableMemory).expect("Decoding stable memory failed");
+    });
+}
+
+// The synced blocks already are in stable memory, so only the heap state is
+// saved, after them.
+#[export_name = "canister_pre_upgrade"]
+fn pre_upgrade() {
+    dfn_core::setup::START.call_once(|| {
+        dfn_core::printer::hook();
+    });
+
+    let state = INDEX_STATE
+        .read()
+        // This should never happen, but it's better to be safe than sorry
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    state
+        .save(&mut StableMemory)
+        .unwrap_or_else(|e| trap_with(&format!("Saving the index state failed: {}", e)));
+}
+
+fn encode_metrics(w: &mut MetricsEncoder<Vec<u8>>) -> std::io::Result<()> {
+    let state = INDEX_STATE.read().unwrap();
+    w.encode_gauge(
+        "index_num_blocks_synced",
+        state.num_blocks_synced() as f64,
+        "Number of ledger blocks indexed by this canister.",
+    )?;
+    w.encode_gauge(
+        "index_num_accounts",
+        state.num_accounts() as f64,
+        "Number of accounts involved in the indexed blocks.",
+    )?;
+    let status = state.status();
+    w.encode_gauge(
+        "index_num_balance_errors",
+        status.num_balance_errors as f64,
+        "Number of indexed blocks whose transaction could not be applied to the balances.",
+    )?;
+    w.encode_gauge(
+        "index_sync_failing",
+        if status.last_sync_error.is_some() {
+            1.0
+        } else {
+            0.0
+        },
+        "Whether the last attempt to sync blocks from the ledger failed.",
+    )?;
+    w.encode_gauge(
+        "index_stable_memory_pages",
+        stable::stable64_size() as f64,
+        "Size of the stable memory allocated by this canister measured in 64K Wasm pages.",
+    )?;
+    Ok(())
+}
+
+#[export_name = "canister_query http_request"]
+fn http_request() {
+    ledger_canister::http_request::serve_metrics(encode_metrics);
+}
+
+#[export_name = "canister_query __get_candid_interface_tmp_hack"]
+fn get_candid_interface() {
+    over(candid_one, |()| -> &'static str {
+        include_str!("../ledger_index.did")
+    })
+}
+
+#[test]
+fn check_index_candid_interface_compatibility() {
+    use candid::utils::CandidSource;
+
+    candid::export_service!();
+
+    let actual_interface = __export_service();
+    let expected_interface_path =
+        std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("ledger_index.did");
+
+    candid::utils::service_compatible(
+        CandidSource::Text(&actual_interface),
+        CandidSource::File(&expected_interface_path),
+    )
+    .expect("ledger index canister interface is not compatible with the ledger_index.did file");
+}
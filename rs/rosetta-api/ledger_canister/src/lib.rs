@@ -24,7 +24,10 @@ use std::sync::{Arc, RwLock};
 use std::time::{Duration, SystemTime};
 
 pub mod account_identifier;
+pub mod approvals;
 pub mod http_request;
+pub mod icrc1;
+pub mod index;
 pub mod metrics_encoder;
 pub mod tokens;
 #[path = "../gen/ic_ledger.pb.v1.rs"]
@@ -42,6 +45,8 @@ use dfn_core::api::now;
 
 pub mod spawn;
 pub use account_identifier::{AccountIdentifier, Subaccount};
+pub use approvals::Allowance;
+use approvals::Approvals;
 pub use protobuf::TimeStamp;
 pub use tokens::{Tokens, DECIMAL_PLACES, DEFAULT_TRANSFER_FEE, TOKEN_SUBDIVIDABLE_BY};
 
@@ -287,6 +292,13 @@ impl<S: Default + BalancesStore> Balances<S> {
                 to,
                 amount,
                 fee,
+            }
+            | Operation::TransferFrom {
+                from,
+                to,
+                amount,
+                fee,
+                ..
             } => {
                 let debit_amount = (*amount + *fee).map_err(|_| {
                     // No account can hold more than u64::MAX.
@@ -308,6 +320,10 @@ impl<S: Default + BalancesStore> Balances<S> {
                 self.token_pool = (self.token_pool - *amount).expect("total token supply exceeded");
                 self.credit(to, *amount);
             }
+            Operation::Approve { from, fee, .. } => {
+                self.debit(from, *fee)?;
+                self.token_pool += *fee;
+            }
         }
         Ok(())
     }
@@ -419,6 +435,24 @@ pub enum Operation {
         amount: Tokens,
         fee: Tokens,
     },
+    /// Allows `spender` to transfer up to `allowance` from `from`, replacing
+    /// any previous allowance. The fee is paid by `from`.
+    Approve {
+        from: AccountIdentifier,
+        spender: AccountIdentifier,
+        allowance: Tokens,
+        expires_at: Option<TimeStamp>,
+        fee: Tokens,
+    },
+    /// A transfer from `from` to `to` made by `spender`. The amount and the
+    /// fee are debited from `from` and spent from the allowance of `spender`.
+    TransferFrom {
+        from: AccountIdentifier,
+        to: AccountIdentifier,
+        spender: AccountIdentifier,
+        amount: Tokens,
+        fee: Tokens,
+    },
 }
 
 /// An operation with the metadata the client generated attached to it
@@ -711,6 +745,14 @@ fn default_transfer_fee() -> Tokens {
     DEFAULT_TRANSFER_FEE
 }
 
+fn default_token_symbol() -> String {
+    "ICP".to_string()
+}
+
+fn default_token_name() -> String {
+    "Internet Computer".to_string()
+}
+
 #[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
 pub struct TransferFee {
     /// The fee to pay to perform a transfer
@@ -761,6 +803,19 @@ pub struct Ledger {
     /// The fee to pay to perform a transfer
     #[serde(default = "default_transfer_fee")]
     pub transfer_fee: Tokens,
+    /// The allowances granted through `Approve` operations
+    #[serde(default)]
+    pub approvals: Approvals,
+    /// The ticker symbol of the token, e.g. "ICP"
+    #[serde(default = "default_token_symbol")]
+    pub token_symbol: String,
+    /// The human-readable name of the token
+    #[serde(default = "default_token_name")]
+    pub token_name: String,
+    /// The minting account as an owner and a subaccount, if known. It maps
+    /// onto `minting_account_id`.
+    #[serde(default)]
+    pub icrc1_minting_account: Option<icrc1::Account>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -784,6 +839,10 @@ impl Default for Ledger {
             send_whitelist: HashSet::new(),
             max_transactions_in_window: Self::DEFAULT_MAX_TRANSACTIONS_IN_WINDOW,
             transfer_fee: DEFAULT_TRANSFER_FEE,
+            approvals: Approvals::default(),
+            token_symbol: default_token_symbol(),
+            token_name: default_token_name(),
+            icrc1_minting_account: None,
         }
     }
 }
@@ -846,6 +905,8 @@ impl Ledger {
         now: TimeStamp,
     ) -> Result<(BlockHeight, HashOf<EncodedBlock>), PaymentError> {
         let num_pruned = self.purge_old_transactions(now);
+        self.approvals
+            .purge_expired(now, Self::MAX_TRANSACTIONS_TO_PURGE);
 
         let created_at_time = created_at_time.unwrap_or(now);
 
@@ -881,6 +942,20 @@ impl Ledger {
             }));
         }
 
+        if let Operation::TransferFrom {
+            from,
+            spender,
+            amount,
+            fee,
+            ..
+        } = &payment
+        {
+            let allowance = self.approvals.allowance(from, spender, now).allowance;
+            if (*amount + *fee).map_or(true, |spent| spent > allowance) {
+                return Err(PaymentError::InsufficientAllowance { allowance });
+            }
+        }
+
         let block = Block::new_from_transaction(self.blockchain.last_hash, transaction, now);
         let block_timestamp = block.timestamp;
 
@@ -889,6 +964,7 @@ impl Ledger {
                 PaymentError::TransferError(TransferError::InsufficientFunds { balance })
             }
         })?;
+        self.approvals.apply(&payment, now);
 
         let height = self
             .blockchain
@@ -973,6 +1049,8 @@ impl Ledger {
         self.balances
             .add_payment(&block.transaction.operation)
             .map_err(|e| format!("failed to execute transfer {:?}: {:?}", block, e))?;
+        self.approvals
+            .apply(&block.transaction.operation, block.timestamp);
         self.blockchain.add_block(block)
     }
 
@@ -1099,6 +1177,13 @@ impl Ledger {
             transfer_fee: self.transfer_fee,
         }
     }
+
+    /// Returns the allowance that `spender` may currently transfer from
+    /// `owner`.
+    pub fn allowance(&self, owner: &AccountIdentifier, spender: &AccountIdentifier) -> Allowance {
+        self.approvals
+            .allowance(owner, spender, dfn_core::api::now().into())
+    }
 }
 
 lazy_static! {
@@ -1142,6 +1227,11 @@ pub struct LedgerCanisterInitPayload {
     pub archive_options: Option<ArchiveOptions>,
     pub send_whitelist: HashSet<CanisterId>,
     pub transfer_fee: Option<Tokens>,
+    pub token_symbol: Option<String>,
+    pub token_name: Option<String>,
+    /// The owner and subaccount of `minting_account`. Without it, the
+    /// minting account can't be reported through the icrc1 interface.
+    pub icrc1_minting_account: Option<icrc1::Account>,
 }
 
 impl LedgerCanisterInitPayload {
@@ -1158,6 +1248,9 @@ pub struct LedgerCanisterInitPayloadBuilder {
     archive_options: Option<ArchiveOptions>,
     send_whitelist: HashSet<CanisterId>,
     transfer_fee: Option<Tokens>,
+    token_symbol: Option<String>,
+    token_name: Option<String>,
+    icrc1_minting_account: Option<icrc1::Account>,
 }
 
 impl LedgerCanisterInitPayloadBuilder {
@@ -1170,6 +1263,9 @@ impl LedgerCanisterInitPayloadBuilder {
             archive_options: None,
             send_whitelist: Default::default(),
             transfer_fee: None,
+            token_symbol: None,
+            token_name: None,
+            icrc1_minting_account: None,
         }
     }
 
@@ -1178,6 +1274,14 @@ impl LedgerCanisterInitPayloadBuilder {
         self
     }
 
+    /// Sets the minting account from its owner and subaccount, which also
+    /// makes it available through the icrc1 interface.
+    pub fn icrc1_minting_account(mut self, minting_account: icrc1::Account) -> Self {
+        self.minting_account = Some(minting_account.into());
+        self.icrc1_minting_account = Some(minting_account);
+        self
+    }
+
     pub fn initial_values(mut self, initial_values: HashMap<AccountIdentifier, Tokens>) -> Self {
         self.initial_values = initial_values;
         self
@@ -1208,10 +1312,21 @@ impl LedgerCanisterInitPayloadBuilder {
         self
     }
 
+    pub fn token_symbol_and_name(mut self, token_symbol: &str, token_name: &str) -> Self {
+        self.token_symbol = Some(token_symbol.to_string());
+        self.token_name = Some(token_name.to_string());
+        self
+    }
+
     pub fn build(self) -> Result<LedgerCanisterInitPayload, String> {
         let minting_account = self
             .minting_account
             .ok_or("minting_account must be set in the payload")?;
+        if let Some(account) = self.icrc1_minting_account {
+            if AccountIdentifier::from(account) != minting_account {
+                return Err("icrc1_minting_account doesn't match the minting_account".to_string());
+            }
+        }
 
         // verify ledger's invariant about the maximum amount
         let mut sum = Tokens::ZERO;
@@ -1234,6 +1349,9 @@ impl LedgerCanisterInitPayloadBuilder {
             archive_options: self.archive_options,
             send_whitelist: self.send_whitelist,
             transfer_fee: self.transfer_fee,
+            token_symbol: self.token_symbol,
+            token_name: self.token_name,
+            icrc1_minting_account: self.icrc1_minting_account,
         })
     }
 }
@@ -1714,6 +1832,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn candid_blocks_verify_against_tip_hash() {
+        let mut state = Ledger::default();
+        state.from_init(
+            vec![(
+                PrincipalId::new_user_test_id(0).into(),
+                Tokens::new(1000000, 0).unwrap(),
+            )]
+            .into_iter()
+            .collect(),
+            PrincipalId::new_user_test_id(1000).into(),
+            SystemTime::UNIX_EPOCH.into(),
+            None,
+            HashSet::new(),
+            None,
+        );
+        for i in 0..5 {
+            state
+                .add_payment(
+                    Memo(i),
+                    Operation::Transfer {
+                        from: PrincipalId::new_user_test_id(0).into(),
+                        to: PrincipalId::new_user_test_id(1).into(),
+                        amount: Tokens::from_e8s(1),
+                        fee: state.transfer_fee,
+                    },
+                    None,
+                )
+                .unwrap();
+        }
+
+        // Blocks received through the Candid interface hash to the same values.
+        let blocks: Vec<Block> = state
+            .blockchain
+            .blocks
+            .iter()
+            .map(|b| Block::try_from(CandidBlock::from(b.decode().unwrap())).unwrap())
+            .collect();
+        let tip_hash = state.blockchain.last_hash.unwrap();
+
+        // The chain can be verified backwards in several ranges.
+        let parent_hash = verify_chain(&blocks[3..], tip_hash).unwrap();
+        assert_eq!(verify_chain(&blocks[..3], parent_hash.unwrap()), Ok(None));
+
+        let mut tampered = blocks.clone();
+        tampered[2].transaction.memo = Memo(42);
+        assert!(verify_chain(&tampered, tip_hash).is_err());
+        assert!(verify_chain(&blocks[..3], tip_hash).is_err());
+    }
+
     #[test]
     fn test_purge() {
         let mut ledger = Ledger::default();
@@ -1908,6 +2076,85 @@ mod tests {
             "Transaction hash must be stable."
         );
     }
+
+    #[test]
+    fn transfer_from_spends_allowance() {
+        let mut ledger = Ledger::default();
+        let owner: AccountIdentifier = PrincipalId::new_user_test_id(1).into();
+        let spender: AccountIdentifier = PrincipalId::new_user_test_id(2).into();
+        let receiver: AccountIdentifier = PrincipalId::new_user_test_id(3).into();
+        let fee = Tokens::from_e8s(10);
+        let now: TimeStamp = dfn_core::api::now().into();
+        let transfer_from = |amount| Operation::TransferFrom {
+            from: owner,
+            to: receiver,
+            spender,
+            amount: Tokens::from_e8s(amount),
+            fee,
+        };
+
+        apply_at(
+            &mut ledger,
+            &Operation::Mint {
+                to: owner,
+                amount: Tokens::from_e8s(10_000),
+            },
+            now,
+        );
+        assert_eq!(
+            ledger
+                .add_payment_with_timestamp(Memo(1), transfer_from(100), None, now)
+                .unwrap_err(),
+            PaymentError::InsufficientAllowance {
+                allowance: Tokens::ZERO
+            }
+        );
+
+        apply_at(
+            &mut ledger,
+            &Operation::Approve {
+                from: owner,
+                spender,
+                allowance: Tokens::from_e8s(1_000),
+                expires_at: Some(now + Duration::from_secs(60)),
+                fee,
+            },
+            now,
+        );
+        apply_at(&mut ledger, &transfer_from(490), now);
+        assert_eq!(
+            ledger.approvals.allowance(&owner, &spender, now).allowance,
+            Tokens::from_e8s(500)
+        );
+        assert_eq!(
+            ledger.balances.account_balance(&owner),
+            Tokens::from_e8s(10_000 - 10 - 500)
+        );
+        assert_eq!(
+            ledger.balances.account_balance(&receiver),
+            Tokens::from_e8s(490)
+        );
+        assert_eq!(
+            ledger
+                .add_payment_with_timestamp(Memo(2), transfer_from(491), None, now)
+                .unwrap_err(),
+            PaymentError::InsufficientAllowance {
+                allowance: Tokens::from_e8s(500)
+            }
+        );
+
+        // The allowance can't be used after it expired.
+        let later = now + Duration::from_secs(60);
+        assert_eq!(
+            ledger
+                .add_payment_with_timestamp(Memo(3), transfer_from(10), None, later)
+                .unwrap_err(),
+            PaymentError::InsufficientAllowance {
+                allowance: Tokens::ZERO
+            }
+        );
+        assert!(ledger.approvals.is_empty());
+    }
 }
 
 /// Argument taken by the send endpoint
@@ -1995,10 +2242,101 @@ impl fmt::Display for TransferError {
     }
 }
 
+/// Argument taken by the approve endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct ApproveArgs {
+    pub memo: Memo,
+    pub fee: Tokens,
+    pub from_subaccount: Option<Subaccount>,
+    pub spender: AccountIdBlob,
+    pub allowance: Tokens,
+    pub expires_at: Option<TimeStamp>,
+    pub created_at_time: Option<TimeStamp>,
+}
+
+/// Argument taken by the transfer_from endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct TransferFromArgs {
+    pub memo: Memo,
+    pub amount: Tokens,
+    pub fee: Tokens,
+    pub spender_subaccount: Option<Subaccount>,
+    pub from: AccountIdBlob,
+    pub to: AccountIdBlob,
+    pub created_at_time: Option<TimeStamp>,
+}
+
+/// Argument taken by the allowance endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct AllowanceArgs {
+    pub account: AccountIdBlob,
+    pub spender: AccountIdBlob,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum TransferFromError {
+    BadFee { expected_fee: Tokens },
+    InsufficientFunds { balance: Tokens },
+    InsufficientAllowance { allowance: Tokens },
+    TxTooOld { allowed_window_nanos: u64 },
+    TxCreatedInFuture,
+    TxDuplicate { duplicate_of: BlockHeight },
+}
+
+impl From<TransferError> for TransferFromError {
+    fn from(e: TransferError) -> Self {
+        match e {
+            TransferError::BadFee { expected_fee } => Self::BadFee { expected_fee },
+            TransferError::InsufficientFunds { balance } => Self::InsufficientFunds { balance },
+            TransferError::TxTooOld {
+                allowed_window_nanos,
+            } => Self::TxTooOld {
+                allowed_window_nanos,
+            },
+            TransferError::TxCreatedInFuture => Self::TxCreatedInFuture,
+            TransferError::TxDuplicate { duplicate_of } => Self::TxDuplicate { duplicate_of },
+        }
+    }
+}
+
+impl fmt::Display for TransferFromError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InsufficientAllowance { allowance } => write!(
+                f,
+                "the spender's allowance doesn't cover the amount and the fee, current allowance: {}",
+                allowance
+            ),
+            Self::BadFee { expected_fee } => TransferError::BadFee {
+                expected_fee: *expected_fee,
+            }
+            .fmt(f),
+            Self::InsufficientFunds { balance } => {
+                TransferError::InsufficientFunds { balance: *balance }.fmt(f)
+            }
+            Self::TxTooOld {
+                allowed_window_nanos,
+            } => TransferError::TxTooOld {
+                allowed_window_nanos: *allowed_window_nanos,
+            }
+            .fmt(f),
+            Self::TxCreatedInFuture => TransferError::TxCreatedInFuture.fmt(f),
+            Self::TxDuplicate { duplicate_of } => TransferError::TxDuplicate {
+                duplicate_of: *duplicate_of,
+            }
+            .fmt(f),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum PaymentError {
     Reject(String),
     TransferError(TransferError),
+    /// The allowance of the spender of a `TransferFrom` is too small.
+    InsufficientAllowance {
+        allowance: Tokens,
+    },
 }
 
 /// Struct sent by the ledger canister when it notifies a recipient of a payment
@@ -2084,6 +2422,20 @@ pub enum CandidOperation {
         amount: Tokens,
         fee: Tokens,
     },
+    Approve {
+        from: AccountIdBlob,
+        spender: AccountIdBlob,
+        allowance: Tokens,
+        expires_at: Option<TimeStamp>,
+        fee: Tokens,
+    },
+    TransferFrom {
+        from: AccountIdBlob,
+        to: AccountIdBlob,
+        spender: AccountIdBlob,
+        amount: Tokens,
+        fee: Tokens,
+    },
 }
 
 impl From<Operation> for CandidOperation {
@@ -2108,6 +2460,32 @@ impl From<Operation> for CandidOperation {
                 amount,
                 fee,
             },
+            Operation::Approve {
+                from,
+                spender,
+                allowance,
+                expires_at,
+                fee,
+            } => Self::Approve {
+                from: from.to_address(),
+                spender: spender.to_address(),
+                allowance,
+                expires_at,
+                fee,
+            },
+            Operation::TransferFrom {
+                from,
+                to,
+                spender,
+                amount,
+                fee,
+            } => Self::TransferFrom {
+                from: from.to_address(),
+                to: to.to_address(),
+                spender: spender.to_address(),
+                amount,
+                fee,
+            },
         }
     }
 }
@@ -2138,6 +2516,79 @@ impl From<Transaction> for CandidTransaction {
     }
 }
 
+impl TryFrom<CandidOperation> for Operation {
+    type Error = String;
+
+    fn try_from(op: CandidOperation) -> Result<Self, Self::Error> {
+        let address = |blob| AccountIdentifier::from_address(blob).map_err(|e| e.to_string());
+        Ok(match op {
+            CandidOperation::Burn { from, amount } => Self::Burn {
+                from: address(from)?,
+                amount,
+            },
+            CandidOperation::Mint { to, amount } => Self::Mint {
+                to: address(to)?,
+                amount,
+            },
+            CandidOperation::Transfer {
+                from,
+                to,
+                amount,
+                fee,
+            } => Self::Transfer {
+                from: address(from)?,
+                to: address(to)?,
+                amount,
+                fee,
+            },
+            CandidOperation::Approve {
+                from,
+                spender,
+                allowance,
+                expires_at,
+                fee,
+            } => Self::Approve {
+                from: address(from)?,
+                spender: address(spender)?,
+                allowance,
+                expires_at,
+                fee,
+            },
+            CandidOperation::TransferFrom {
+                from,
+                to,
+                spender,
+                amount,
+                fee,
+            } => Self::TransferFrom {
+                from: address(from)?,
+                to: address(to)?,
+                spender: address(spender)?,
+                amount,
+                fee,
+            },
+        })
+    }
+}
+
+impl TryFrom<CandidTransaction> for Transaction {
+    type Error = String;
+
+    fn try_from(
+        CandidTransaction {
+            operation,
+            memo,
+            created_at_time,
+        }: CandidTransaction,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: operation.try_into()?,
+            memo,
+            created_at_time,
+        })
+    }
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct CandidBlock {
     pub parent_hash: Option<[u8; HASH_LENGTH]>,
@@ -2161,6 +2612,26 @@ impl From<Block> for CandidBlock {
     }
 }
 
+/// Converts a block received through the Candid interface back to the block
+/// the ledger hashed, so that its hash can be recomputed.
+impl TryFrom<CandidBlock> for Block {
+    type Error = String;
+
+    fn try_from(
+        CandidBlock {
+            parent_hash,
+            transaction,
+            timestamp,
+        }: CandidBlock,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            parent_hash: parent_hash.map(HashOf::new),
+            transaction: transaction.try_into()?,
+            timestamp,
+        })
+    }
+}
+
 /// Argument taken by the transfer fee endpoint
 ///
 /// The reason it is a struct is so that it can be extended -- e.g., to be able
@@ -2198,6 +2669,14 @@ pub struct BlockRange {
 
 pub type GetBlocksResult = Result<BlockRange, GetBlocksError>;
 
+/// The decoded transactions of a range of blocks
+#[derive(Serialize, Deserialize, CandidType, Debug)]
+pub struct TransactionRange {
+    pub transactions: Vec<CandidTransaction>,
+}
+
+pub type GetTransactionsResult = Result<TransactionRange, GetBlocksError>;
+
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, CandidType)]
 pub enum GetBlocksError {
     BadFirstBlockIndex {
@@ -2212,6 +2691,68 @@ pub enum GetBlocksError {
 
 pub struct GetBlocksRes(pub Result<Vec<EncodedBlock>, String>);
 
+/// The method of an archive node that serves a range of archived blocks
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveCallback {
+    pub canister_id: CanisterId,
+    /// The method takes `GetBlocksArgs` and returns `GetBlocksResult`.
+    pub method: String,
+}
+
+/// A range of requested blocks that the ledger moved to an archive node
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct ArchivedBlocksRange {
+    pub start: BlockHeight,
+    pub length: u64,
+    pub callback: ArchiveCallback,
+}
+
+/// The response of the query_blocks endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct QueryBlocksResponse {
+    /// The number of blocks in the chain, including archived blocks.
+    pub chain_length: u64,
+    /// The certificate of the hash of the last block in the chain. It is only
+    /// available in non-replicated queries.
+    pub certificate: Option<serde_bytes::ByteBuf>,
+    /// The requested blocks that the ledger still holds.
+    pub blocks: Vec<CandidBlock>,
+    /// The height of the first block in `blocks`.
+    pub first_block_index: BlockHeight,
+    /// The requested blocks that have to be fetched from archive nodes, in
+    /// ascending order.
+    pub archived_blocks: Vec<ArchivedBlocksRange>,
+}
+
+/// Checks that `blocks` are consecutive blocks of a chain whose last block has
+/// the hash `last_hash`, and returns the parent hash of the first block.
+///
+/// Applied to the ledger blocks up to the tip of the chain and the certified
+/// tip hash, this verifies the blocks against the certificate. The returned
+/// parent hash then allows verifying the preceding blocks, e.g. those fetched
+/// from archive nodes, in the same way.
+pub fn verify_chain(
+    blocks: &[Block],
+    last_hash: HashOf<EncodedBlock>,
+) -> Result<Option<HashOf<EncodedBlock>>, String> {
+    let mut expected_hash = Some(last_hash);
+    for block in blocks.iter().rev() {
+        let hash = block.clone().encode()?.hash();
+        match expected_hash {
+            Some(expected_hash) if expected_hash != hash => {
+                return Err(format!(
+                    "block hash {} doesn't match the expected hash {}",
+                    hash, expected_hash
+                ))
+            }
+            None => return Err(format!("block {} precedes the first block", hash)),
+            _ => (),
+        }
+        expected_hash = block.parent_hash;
+    }
+    Ok(expected_hash)
+}
+
 pub struct IterBlocksArgs {
     pub start: usize,
     pub length: usize,
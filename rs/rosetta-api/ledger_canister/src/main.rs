@@ -38,6 +38,9 @@ where
 ///   old blocks.
 /// * `send_whitelist` - The [Ledger] canister whitelist.
 /// * `transfer_fee` - The fee to pay to perform a transaction.
+/// * `token_symbol` - The ticker symbol of the token. Defaults to "ICP".
+/// * `token_name` - The name of the token. Defaults to "Internet Computer".
+/// * `icrc1_minting_account` - The owner and subaccount of `minting_account`.
 #[allow(clippy::too_many_arguments)]
 fn init(
     minting_account: AccountIdentifier,
@@ -47,12 +50,16 @@ fn init(
     archive_options: Option<archive::ArchiveOptions>,
     send_whitelist: HashSet<CanisterId>,
     transfer_fee: Option<Tokens>,
+    token_symbol: Option<String>,
+    token_name: Option<String>,
+    icrc1_minting_account: Option<icrc1::Account>,
 ) {
     print(format!(
         "[ledger] init(): minting account is {}",
         minting_account
     ));
-    LEDGER.write().unwrap().from_init(
+    let mut ledger = LEDGER.write().unwrap();
+    ledger.from_init(
         initial_values,
         minting_account,
         dfn_core::api::now().into(),
@@ -60,6 +67,21 @@ fn init(
         send_whitelist,
         transfer_fee,
     );
+    if let Some(token_symbol) = token_symbol {
+        ledger.token_symbol = token_symbol;
+    }
+    if let Some(token_name) = token_name {
+        ledger.token_name = token_name;
+    }
+    if let Some(account) = icrc1_minting_account {
+        assert_eq!(
+            AccountIdentifier::from(account),
+            minting_account,
+            "icrc1_minting_account doesn't match the minting_account"
+        );
+        ledger.icrc1_minting_account = Some(account);
+    }
+    drop(ledger);
     match max_message_size_bytes {
         None => {
             print(format!(
@@ -176,6 +198,9 @@ async fn send(
         Ok((height, hash)) => (height, hash),
         Err(PaymentError::TransferError(transfer_error)) => return Err(transfer_error),
         Err(PaymentError::Reject(msg)) => panic!("{}", msg),
+        Err(PaymentError::InsufficientAllowance { .. }) => {
+            unreachable!("transfers don't spend allowances")
+        }
     };
     set_certified_data(&hash.into_bytes());
 
@@ -186,6 +211,157 @@ async fn send(
     Ok(height)
 }
 
+/// Allows `spender` to transfer up to `allowance` from the caller's account
+/// using `transfer_from`, replacing any allowance previously given to the
+/// same spender. An allowance of zero revokes the previous allowance. The
+/// fee is paid by the caller.
+///
+/// # Arguments
+///
+/// * `memo` -  A 8 byte "message" you can attach to the approval.
+/// * `fee` - The fee of the approval, which must be equal to the transfer fee.
+/// * `from_subaccount` - The subaccount the spender may draw funds from.
+/// * `spender` - The account allowed to transfer the funds.
+/// * `allowance` - The total amount the spender may transfer, including the
+///   fees of the transfers.
+/// * `expires_at` - When the allowance expires. If not set, the allowance
+///   doesn't expire.
+/// * `created_at_time`: When the transaction has been created. If not set then
+///   now is used.
+#[allow(clippy::too_many_arguments)]
+async fn approve(
+    memo: Memo,
+    fee: Tokens,
+    from_subaccount: Option<Subaccount>,
+    spender: AccountIdentifier,
+    allowance: Tokens,
+    expires_at: Option<TimeStamp>,
+    created_at_time: Option<TimeStamp>,
+) -> Result<BlockHeight, TransferError> {
+    let caller_principal_id = caller();
+
+    if !LEDGER.read().unwrap().can_send(&caller_principal_id) {
+        panic!("Approving from {} is not allowed", caller_principal_id);
+    }
+
+    let from = AccountIdentifier::new(caller_principal_id, from_subaccount);
+    let minting_acc = LEDGER
+        .read()
+        .unwrap()
+        .minting_account_id
+        .expect("Minting canister id not initialized");
+    if from == minting_acc || spender == minting_acc {
+        panic!("Approvals involving the minting account are not allowed");
+    }
+    if let Some(expires_at) = expires_at {
+        if expires_at <= dfn_core::api::now().into() {
+            panic!("The allowance expires in the past");
+        }
+    }
+
+    let transfer_fee = LEDGER.read().unwrap().transfer_fee;
+    if fee != transfer_fee {
+        return Err(TransferError::BadFee {
+            expected_fee: transfer_fee,
+        });
+    }
+    let operation = Operation::Approve {
+        from,
+        spender,
+        allowance,
+        expires_at,
+        fee,
+    };
+    let (height, hash) = match LEDGER
+        .write()
+        .unwrap()
+        .add_payment(memo, operation, created_at_time)
+    {
+        Ok((height, hash)) => (height, hash),
+        Err(PaymentError::TransferError(transfer_error)) => return Err(transfer_error),
+        Err(PaymentError::Reject(msg)) => panic!("{}", msg),
+        Err(PaymentError::InsufficientAllowance { .. }) => {
+            unreachable!("approvals don't spend allowances")
+        }
+    };
+    set_certified_data(&hash.into_bytes());
+
+    archive_blocks().await;
+    Ok(height)
+}
+
+/// Transfers funds from an account whose owner allowed the caller to spend
+/// them using `approve`. The amount and the fee are debited from `from` and
+/// spent from the caller's allowance.
+///
+/// # Arguments
+///
+/// * `memo` -  A 8 byte "message" you can attach to transactions to help the
+///   receiver disambiguate transactions.
+/// * `amount` - The number of Tokens the recipient gets.
+/// * `fee` - The fee of the transfer, which must be equal to the transfer fee.
+/// * `spender_subaccount` - The subaccount of the caller that was approved.
+/// * `from` - The account to draw funds from.
+/// * `to` - The account you want to send the funds to.
+/// * `created_at_time`: When the transaction has been created. If not set then
+///   now is used.
+#[allow(clippy::too_many_arguments)]
+async fn transfer_from(
+    memo: Memo,
+    amount: Tokens,
+    fee: Tokens,
+    spender_subaccount: Option<Subaccount>,
+    from: AccountIdentifier,
+    to: AccountIdentifier,
+    created_at_time: Option<TimeStamp>,
+) -> Result<BlockHeight, TransferFromError> {
+    let caller_principal_id = caller();
+
+    if !LEDGER.read().unwrap().can_send(&caller_principal_id) {
+        panic!("Sending from {} is not allowed", caller_principal_id);
+    }
+
+    let spender = AccountIdentifier::new(caller_principal_id, spender_subaccount);
+    let minting_acc = LEDGER
+        .read()
+        .unwrap()
+        .minting_account_id
+        .expect("Minting canister id not initialized");
+    if from == minting_acc || to == minting_acc {
+        panic!("Minting and burning through transfer_from is not allowed");
+    }
+
+    let transfer_fee = LEDGER.read().unwrap().transfer_fee;
+    if fee != transfer_fee {
+        return Err(TransferFromError::BadFee {
+            expected_fee: transfer_fee,
+        });
+    }
+    let operation = Operation::TransferFrom {
+        from,
+        to,
+        spender,
+        amount,
+        fee,
+    };
+    let (height, hash) = match LEDGER
+        .write()
+        .unwrap()
+        .add_payment(memo, operation, created_at_time)
+    {
+        Ok((height, hash)) => (height, hash),
+        Err(PaymentError::TransferError(transfer_error)) => return Err(transfer_error.into()),
+        Err(PaymentError::InsufficientAllowance { allowance }) => {
+            return Err(TransferFromError::InsufficientAllowance { allowance })
+        }
+        Err(PaymentError::Reject(msg)) => panic!("{}", msg),
+    };
+    set_certified_data(&hash.into_bytes());
+
+    archive_blocks().await;
+    Ok(height)
+}
+
 /// You can notify a canister that you have made a payment to it. The
 /// payment must have been made to the account of a canister and from the
 /// callers account. You cannot notify a canister about a transaction it has
@@ -422,6 +598,9 @@ fn canister_init(arg: LedgerCanisterInitPayload) {
         arg.archive_options,
         arg.send_whitelist,
         arg.transfer_fee,
+        arg.token_symbol,
+        arg.token_name,
+        arg.icrc1_minting_account,
     )
 }
 
@@ -605,6 +784,113 @@ fn transfer() {
     over_async(candid_one, transfer_candid)
 }
 
+#[candid_method(update, rename = "approve")]
+async fn approve_candid(arg: ApproveArgs) -> Result<BlockHeight, TransferError> {
+    let spender = AccountIdentifier::from_address(arg.spender).unwrap_or_else(|e| {
+        trap_with(&format!("Invalid account identifier: {}", e));
+        unreachable!()
+    });
+    approve(
+        arg.memo,
+        arg.fee,
+        arg.from_subaccount,
+        spender,
+        arg.allowance,
+        arg.expires_at,
+        arg.created_at_time,
+    )
+    .await
+}
+
+#[export_name = "canister_update approve"]
+fn approve_() {
+    over_async(candid_one, approve_candid)
+}
+
+#[candid_method(update, rename = "transfer_from")]
+async fn transfer_from_candid(arg: TransferFromArgs) -> Result<BlockHeight, TransferFromError> {
+    let parse_account = |address| {
+        AccountIdentifier::from_address(address).unwrap_or_else(|e| {
+            trap_with(&format!("Invalid account identifier: {}", e));
+            unreachable!()
+        })
+    };
+    transfer_from(
+        arg.memo,
+        arg.amount,
+        arg.fee,
+        arg.spender_subaccount,
+        parse_account(arg.from),
+        parse_account(arg.to),
+        arg.created_at_time,
+    )
+    .await
+}
+
+#[export_name = "canister_update transfer_from"]
+fn transfer_from_() {
+    over_async(candid_one, transfer_from_candid)
+}
+
+#[candid_method(update, rename = "icrc1_transfer")]
+async fn icrc1_transfer(arg: icrc1::TransferArg) -> icrc1::TransferResult {
+    let amount = icrc1::nat_to_tokens(&arg.amount).unwrap_or_else(|e| {
+        trap_with(&format!("Invalid amount: {}", e));
+        unreachable!()
+    });
+    let from = AccountIdentifier::new(caller(), arg.from_subaccount);
+    let to = AccountIdentifier::from(arg.to);
+    let (minting_acc, transfer_fee) = {
+        let ledger = LEDGER.read().unwrap();
+        (
+            ledger
+                .minting_account_id
+                .expect("Minting canister id not initialized"),
+            ledger.transfer_fee,
+        )
+    };
+    if from == minting_acc && to == minting_acc {
+        return Err(icrc1::TransferError::GenericError {
+            error_code: candid::Nat::from(icrc1::ERROR_CODE_MINTING_ACCOUNT_SELF_TRANSFER),
+            message: "The minting account can't transfer to itself".to_string(),
+        });
+    }
+    // Mints and burns are free, all other transfers pay the transfer fee.
+    let expected_fee = if from == minting_acc || to == minting_acc {
+        Tokens::ZERO
+    } else {
+        transfer_fee
+    };
+    if let Some(fee) = &arg.fee {
+        if icrc1::nat_to_tokens(fee) != Ok(expected_fee) {
+            return Err(icrc1::TransferError::BadFee {
+                expected_fee: icrc1::tokens_to_nat(expected_fee),
+            });
+        }
+    }
+    if to == minting_acc && amount < transfer_fee {
+        return Err(icrc1::TransferError::BadBurn {
+            min_burn_amount: icrc1::tokens_to_nat(transfer_fee),
+        });
+    }
+    send(
+        Memo(arg.memo.unwrap_or(0)),
+        amount,
+        expected_fee,
+        arg.from_subaccount,
+        to,
+        arg.created_at_time
+            .map(TimeStamp::from_nanos_since_unix_epoch),
+    )
+    .await
+    .map_err(icrc1::TransferError::from)
+}
+
+#[export_name = "canister_update icrc1_transfer"]
+fn icrc1_transfer_() {
+    over_async(candid_one, icrc1_transfer)
+}
+
 /// See caveats of use on send_dfx
 #[cfg(feature = "notify-method")]
 #[export_name = "canister_update notify_dfx"]
@@ -692,6 +978,25 @@ fn account_balance_candid() {
     over(candid_one, account_balance_candid_)
 }
 
+#[candid_method(query, rename = "allowance")]
+fn allowance_candid_(arg: AllowanceArgs) -> Allowance {
+    let parse_account = |address| {
+        AccountIdentifier::from_address(address).unwrap_or_else(|e| {
+            trap_with(&format!("Invalid account identifier: {}", e));
+            unreachable!()
+        })
+    };
+    LEDGER
+        .read()
+        .unwrap()
+        .allowance(&parse_account(arg.account), &parse_account(arg.spender))
+}
+
+#[export_name = "canister_query allowance"]
+fn allowance_candid() {
+    over(candid_one, allowance_candid_)
+}
+
 #[candid_method(query, rename = "account_balance_dfx")]
 fn account_balance_dfx_(args: AccountBalanceArgs) -> Tokens {
     account_balance(args.account)
@@ -713,6 +1018,97 @@ fn transfer_fee_() {
     over(protobuf, transfer_fee)
 }
 
+#[candid_method(query, rename = "icrc1_balance_of")]
+fn icrc1_balance_of(account: icrc1::Account) -> candid::Nat {
+    icrc1::tokens_to_nat(account_balance(account.into()))
+}
+
+#[export_name = "canister_query icrc1_balance_of"]
+fn icrc1_balance_of_() {
+    over(candid_one, icrc1_balance_of)
+}
+
+#[candid_method(query, rename = "icrc1_fee")]
+fn icrc1_fee() -> candid::Nat {
+    icrc1::tokens_to_nat(LEDGER.read().unwrap().transfer_fee)
+}
+
+#[export_name = "canister_query icrc1_fee"]
+fn icrc1_fee_() {
+    over(candid, |()| icrc1_fee())
+}
+
+#[candid_method(query, rename = "icrc1_decimals")]
+fn icrc1_decimals() -> u8 {
+    DECIMAL_PLACES as u8
+}
+
+#[export_name = "canister_query icrc1_decimals"]
+fn icrc1_decimals_() {
+    over(candid, |()| icrc1_decimals())
+}
+
+#[candid_method(query, rename = "icrc1_symbol")]
+fn icrc1_symbol() -> String {
+    LEDGER.read().unwrap().token_symbol.clone()
+}
+
+#[export_name = "canister_query icrc1_symbol"]
+fn icrc1_symbol_() {
+    over(candid, |()| icrc1_symbol())
+}
+
+#[candid_method(query, rename = "icrc1_name")]
+fn icrc1_name() -> String {
+    LEDGER.read().unwrap().token_name.clone()
+}
+
+#[export_name = "canister_query icrc1_name"]
+fn icrc1_name_() {
+    over(candid, |()| icrc1_name())
+}
+
+#[candid_method(query, rename = "icrc1_metadata")]
+fn icrc1_metadata() -> Vec<(String, icrc1::Value)> {
+    let ledger = LEDGER.read().unwrap();
+    vec![
+        (
+            "icrc1:symbol".to_string(),
+            icrc1::Value::Text(ledger.token_symbol.clone()),
+        ),
+        (
+            "icrc1:name".to_string(),
+            icrc1::Value::Text(ledger.token_name.clone()),
+        ),
+        (
+            "icrc1:decimals".to_string(),
+            icrc1::Value::Nat(candid::Nat::from(DECIMAL_PLACES)),
+        ),
+        (
+            "icrc1:fee".to_string(),
+            icrc1::Value::Nat(icrc1::tokens_to_nat(ledger.transfer_fee)),
+        ),
+    ]
+}
+
+#[export_name = "canister_query icrc1_metadata"]
+fn icrc1_metadata_() {
+    over(candid, |()| icrc1_metadata())
+}
+
+/// The account that mints tokens by transferring them and burns tokens by
+/// receiving them. It is `None` if the minting account was only set as an
+/// account identifier, which can't be converted back to its owner.
+#[candid_method(query, rename = "icrc1_minting_account")]
+fn icrc1_minting_account() -> Option<icrc1::Account> {
+    LEDGER.read().unwrap().icrc1_minting_account
+}
+
+#[export_name = "canister_query icrc1_minting_account"]
+fn icrc1_minting_account_() {
+    over(candid, |()| icrc1_minting_account())
+}
+
 #[export_name = "canister_query total_supply_pb"]
 fn total_supply_() {
     over(protobuf, |_: TotalSupplyArgs| total_supply())
@@ -742,6 +1138,78 @@ fn get_blocks_() {
     });
 }
 
+/// Returns the blocks in the requested range that the ledger still holds
+/// together with the certificate of the hash of the last block, and tells
+/// where to fetch the requested blocks that were moved to archive nodes.
+///
+/// The certified hash covers the blocks held by the ledger through their
+/// parent hashes, and the parent hash of the oldest of them covers the
+/// archived blocks, so a client can verify any block against the certificate
+/// (see `verify_chain`).
+#[candid_method(query, rename = "query_blocks")]
+fn query_blocks(GetBlocksArgs { start, length }: GetBlocksArgs) -> QueryBlocksResponse {
+    let ledger = LEDGER.read().unwrap();
+    let blockchain = &ledger.blockchain;
+    let requested_range = range_utils::make_range(start, length);
+    let local_range =
+        range_utils::make_range(blockchain.num_archived_blocks(), blockchain.blocks.len());
+    let effective_local_range = range_utils::head(
+        &range_utils::intersect(&requested_range, &local_range),
+        MAX_BLOCKS_PER_REQUEST,
+    );
+    let blocks = effective_local_range
+        .clone()
+        .map(|height| {
+            let encoded_block = &blockchain.blocks[(height - local_range.start) as usize];
+            CandidBlock::from(encoded_block.decode().expect("failed to decode a block"))
+        })
+        .collect();
+
+    // Blocks are only dropped from the ledger after they have been appended to
+    // an archive node, so the ranges of the archive nodes may overlap with the
+    // ranges of the local blocks.
+    let archived_range = range_utils::intersect(
+        &requested_range,
+        &range_utils::make_range(0, local_range.start as usize),
+    );
+    let archived_blocks = blockchain
+        .archive
+        .try_read()
+        .expect("Failed to get lock on archive")
+        .as_ref()
+        .map(|archive| archive.index())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|((height_from, height_to), canister_id)| {
+            let range = range_utils::intersect(&archived_range, &(height_from..height_to + 1));
+            if range_utils::range_len(&range) == 0 {
+                return None;
+            }
+            Some(ArchivedBlocksRange {
+                start: range.start,
+                length: range_utils::range_len(&range),
+                callback: ArchiveCallback {
+                    canister_id,
+                    method: "get_blocks".to_string(),
+                },
+            })
+        })
+        .collect();
+
+    QueryBlocksResponse {
+        chain_length: blockchain.chain_length(),
+        certificate: data_certificate().map(serde_bytes::ByteBuf::from),
+        blocks,
+        first_block_index: effective_local_range.start,
+        archived_blocks,
+    }
+}
+
+#[export_name = "canister_query query_blocks"]
+fn query_blocks_() {
+    over(candid_one, query_blocks)
+}
+
 #[export_name = "canister_query get_nodes"]
 fn get_nodes_() {
     over(candid, |()| -> Vec<CanisterId> {
@@ -813,6 +1281,11 @@ fn encode_metrics(w: &mut metrics_encoder::MetricsEncoder<Vec<u8>>) -> std::io::
         ledger.balances.store.len() as f64,
         "Total number of accounts in the balance store.",
     )?;
+    w.encode_gauge(
+        "ledger_allowances",
+        ledger.approvals.len() as f64,
+        "Total number of allowances granted through approvals.",
+    )?;
     w.encode_gauge(
         "ledger_most_recent_block_time_seconds",
         ledger.blockchain.last_timestamp.timestamp_nanos as f64 / 1_000_000_000.0,
@@ -835,9 +1308,12 @@ fn get_canidid_interface() {
 
 #[cfg(test)]
 mod tests {
+    use crate::icrc1;
     use crate::{
-        AccountBalanceArgs, BinaryAccountBalanceArgs, BlockHeight, LedgerCanisterInitPayload,
-        SendArgs, Tokens, TransferArgs, TransferError, TransferFee, TransferFeeArgs,
+        AccountBalanceArgs, Allowance, AllowanceArgs, ApproveArgs, BinaryAccountBalanceArgs,
+        BlockHeight, GetBlocksArgs, LedgerCanisterInitPayload, QueryBlocksResponse, SendArgs,
+        Tokens, TransferArgs, TransferError, TransferFee, TransferFeeArgs, TransferFromArgs,
+        TransferFromError,
     };
     use candid::utils::{service_compatible, CandidSource};
     use std::path::PathBuf;
@@ -601,6 +601,32 @@ impl ToProto for Transaction {
                     None => DEFAULT_TRANSFER_FEE,
                 },
             },
+            PTransfer::Approve(protobuf::Approve {
+                from: Some(from),
+                spender: Some(spender),
+                allowance: Some(allowance),
+                expires_at,
+                fee: Some(fee),
+            }) => Operation::Approve {
+                from: AccountIdentifier::from_proto(from)?,
+                spender: AccountIdentifier::from_proto(spender)?,
+                allowance: Tokens::from_proto(allowance)?,
+                expires_at,
+                fee: Tokens::from_proto(fee)?,
+            },
+            PTransfer::TransferFrom(protobuf::TransferFrom {
+                from: Some(from),
+                to: Some(to),
+                spender: Some(spender),
+                amount: Some(amount),
+                fee: Some(fee),
+            }) => Operation::TransferFrom {
+                from: AccountIdentifier::from_proto(from)?,
+                to: AccountIdentifier::from_proto(to)?,
+                spender: AccountIdentifier::from_proto(spender)?,
+                amount: Tokens::from_proto(amount)?,
+                fee: Tokens::from_proto(fee)?,
+            },
             t => return Err(format!("Transaction lacked a required field: {:?}", t)),
         };
         Ok(Transaction {
@@ -638,6 +664,34 @@ impl ToProto for Transaction {
                 from: Some(from.into_proto()),
                 max_fee: Some(fee.into_proto()),
             }),
+
+            Operation::Approve {
+                from,
+                spender,
+                allowance,
+                expires_at,
+                fee,
+            } => PTransfer::Approve(protobuf::Approve {
+                from: Some(from.into_proto()),
+                spender: Some(spender.into_proto()),
+                allowance: Some(allowance.into_proto()),
+                expires_at,
+                fee: Some(fee.into_proto()),
+            }),
+
+            Operation::TransferFrom {
+                from,
+                to,
+                spender,
+                amount,
+                fee,
+            } => PTransfer::TransferFrom(protobuf::TransferFrom {
+                from: Some(from.into_proto()),
+                to: Some(to.into_proto()),
+                spender: Some(spender.into_proto()),
+                amount: Some(amount.into_proto()),
+                fee: Some(fee.into_proto()),
+            }),
         };
         protobuf::Transaction {
             memo: Some(protobuf::Memo { memo: memo.0 }),
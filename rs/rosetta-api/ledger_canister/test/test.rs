@@ -1,15 +1,21 @@
+use candid::Nat;
 use canister_test::*;
 use dfn_candid::{candid, candid_one, CandidOne};
 use dfn_protobuf::protobuf;
 use ic_canister_client::Sender;
 use ic_types::{CanisterId, PrincipalId};
+use ledger_canister::index::{
+    GetAccountTransactionsArgs, GetAccountTransactionsResponse, IndexCanisterInitArgs, IndexStatus,
+};
 use ledger_canister::{
-    AccountBalanceArgs, AccountIdentifier, ArchiveOptions, BinaryAccountBalanceArgs, Block,
-    BlockArg, BlockHeight, BlockRange, BlockRes, CandidBlock, EncodedBlock, GetBlocksArgs,
-    GetBlocksError, GetBlocksRes, GetBlocksResult, IterBlocksArgs, IterBlocksRes,
-    LedgerCanisterInitPayload, Memo, NotifyCanisterArgs, Operation, SendArgs, Subaccount,
-    TimeStamp, Tokens, TotalSupplyArgs, Transaction, TransferArgs, TransferError, TransferFee,
-    TransferFeeArgs, DEFAULT_TRANSFER_FEE,
+    icrc1, verify_chain, AccountBalanceArgs, AccountIdentifier, Allowance, AllowanceArgs,
+    ApproveArgs, ArchiveOptions, BinaryAccountBalanceArgs, Block, BlockArg, BlockHeight,
+    BlockRange, BlockRes, CandidBlock, EncodedBlock, GetBlocksArgs, GetBlocksError, GetBlocksRes,
+    GetBlocksResult, GetTransactionsResult, IterBlocksArgs, IterBlocksRes,
+    LedgerCanisterInitPayload, Memo, NotifyCanisterArgs, Operation, QueryBlocksResponse, SendArgs,
+    Subaccount, TimeStamp, Tokens, TotalSupplyArgs, Transaction, TransactionRange, TransferArgs,
+    TransferError, TransferFee, TransferFeeArgs, TransferFromArgs, TransferFromError,
+    DEFAULT_TRANSFER_FEE, HASH_LENGTH, MAX_BLOCKS_PER_REQUEST,
 };
 use on_wire::IntoWire;
 use std::collections::{HashMap, HashSet};
@@ -305,6 +311,189 @@ fn archive_blocks_small_test() {
     })
 }
 
+async fn query_blocks(ledger: &Canister<'_>, range: std::ops::Range<u64>) -> QueryBlocksResponse {
+    ledger
+        .query_(
+            "query_blocks",
+            candid_one,
+            GetBlocksArgs {
+                start: range.start,
+                length: range.end.saturating_sub(range.start) as usize,
+            },
+        )
+        .await
+        .expect("query_blocks call trapped")
+}
+
+fn decode_candid_blocks(blocks: Vec<CandidBlock>) -> Vec<Block> {
+    blocks
+        .into_iter()
+        .map(|block| Block::try_from(block).expect("failed to decode a candid block"))
+        .collect()
+}
+
+#[test]
+fn query_blocks_spans_archive_nodes_test() {
+    local_test_e(|r| async move {
+        let proj = Project::new(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+
+        // More blocks than the ledger returns in a single response.
+        let accounts = make_accounts(201, 10);
+        let num_initial_blocks = accounts.len() as u64;
+        assert!(num_initial_blocks > MAX_BLOCKS_PER_REQUEST as u64);
+
+        // Archive the first few blocks into several tiny archive nodes.
+        let num_archived_blocks = 10;
+        let archive_options = ArchiveOptions {
+            trigger_threshold: num_initial_blocks as usize,
+            num_blocks_to_archive: num_archived_blocks as usize,
+            node_max_memory_size_bytes: Some(example_block().encode().unwrap().size_bytes() * 4),
+            max_message_size_bytes: None,
+            controller_id: CanisterId::from_u64(876),
+        };
+
+        let minting_account = create_sender(0);
+        let ledger: canister_test::Canister = {
+            let payload = LedgerCanisterInitPayload::builder()
+                .minting_account(
+                    CanisterId::try_from(minting_account.get_principal_id())
+                        .unwrap()
+                        .into(),
+                )
+                .initial_values(accounts)
+                .archive_options(archive_options)
+                .build()
+                .unwrap();
+            let mut install = proj.cargo_bin("ledger-canister", &[]).install(&r);
+            install.memory_allocation = Some(128 * 1024 * 1024);
+            install.bytes(CandidOne(payload).into_bytes()?).await?
+        };
+
+        // To trigger archiving we need a send
+        simple_send(&ledger, &create_sender(12345), &minting_account, 100, 0).await?;
+        let chain_length = num_initial_blocks + 1;
+
+        let response = query_blocks(&ledger, 0..chain_length).await;
+        assert_eq!(response.chain_length, chain_length);
+        assert_eq!(response.first_block_index, num_archived_blocks);
+        assert_eq!(response.blocks.len(), MAX_BLOCKS_PER_REQUEST);
+        let certificate = response
+            .certificate
+            .expect("query_blocks returned no certificate");
+
+        // The archived blocks are split into one range per archive node, in
+        // ascending order.
+        let nodes: Vec<CanisterId> = ledger.query_("get_nodes", dfn_candid::candid, ()).await?;
+        assert!(nodes.len() > 1, "expected several archive nodes");
+        assert_eq!(
+            response
+                .archived_blocks
+                .iter()
+                .map(|range| range.callback.canister_id)
+                .collect::<Vec<_>>(),
+            nodes
+        );
+        let mut next_start = 0;
+        for range in response.archived_blocks.iter() {
+            assert_eq!(range.start, next_start);
+            assert!(range.length > 0);
+            next_start += range.length;
+        }
+        assert_eq!(next_start, num_archived_blocks);
+
+        // Fetch the archived blocks through the callbacks.
+        let mut blocks = Vec::new();
+        for range in response.archived_blocks.iter() {
+            let archive = Canister::new(&r, range.callback.canister_id);
+            let args = || GetBlocksArgs {
+                start: range.start,
+                length: range.length as usize,
+            };
+            let archived: GetBlocksResult = archive
+                .query_(&range.callback.method, candid_one, args())
+                .await?;
+            let BlockRange {
+                blocks: archived_blocks,
+            } = archived.expect("failed to fetch archived blocks");
+            assert_eq!(archived_blocks.len() as u64, range.length);
+
+            let transactions: GetTransactionsResult = archive
+                .query_("get_transactions", candid_one, args())
+                .await?;
+            let TransactionRange { transactions } =
+                transactions.expect("failed to fetch archived transactions");
+            assert_eq!(
+                transactions,
+                archived_blocks
+                    .iter()
+                    .map(|block| block.transaction.clone())
+                    .collect::<Vec<_>>()
+            );
+
+            blocks.extend(decode_candid_blocks(archived_blocks));
+        }
+        blocks.extend(decode_candid_blocks(response.blocks));
+
+        // Fetch the local blocks that didn't fit into the first response.
+        while (blocks.len() as u64) < chain_length {
+            let response = query_blocks(&ledger, blocks.len() as u64..chain_length).await;
+            assert!(response.archived_blocks.is_empty());
+            assert_eq!(response.first_block_index, blocks.len() as u64);
+            assert!(!response.blocks.is_empty());
+            blocks.extend(decode_candid_blocks(response.blocks));
+        }
+        assert_eq!(blocks.len() as u64, chain_length);
+
+        // The certificate covers the hash of the tip, which in turn covers the
+        // whole chain.
+        let tip_hash = blocks.last().unwrap().clone().encode().unwrap().hash();
+        assert!(
+            certificate
+                .windows(HASH_LENGTH)
+                .any(|bytes| bytes == tip_hash.into_bytes()),
+            "the certificate doesn't contain the tip hash {}",
+            tip_hash
+        );
+        assert_eq!(verify_chain(&blocks, tip_hash), Ok(None));
+
+        // A range that overlaps both the archived and the local blocks.
+        let overlap = num_archived_blocks - 2..num_archived_blocks + 3;
+        let response = query_blocks(&ledger, overlap.clone()).await;
+        assert_eq!(response.first_block_index, num_archived_blocks);
+        assert_eq!(
+            decode_candid_blocks(response.blocks),
+            blocks[num_archived_blocks as usize..overlap.end as usize]
+        );
+        assert_eq!(
+            response.archived_blocks.first().unwrap().start,
+            overlap.start
+        );
+        assert_eq!(
+            response
+                .archived_blocks
+                .iter()
+                .map(|range| range.length)
+                .sum::<u64>(),
+            num_archived_blocks - overlap.start
+        );
+
+        // A range of archived blocks only.
+        let response = query_blocks(&ledger, 1..3).await;
+        assert!(response.blocks.is_empty());
+        assert_eq!(response.archived_blocks.first().unwrap().start, 1);
+        assert_eq!(
+            response
+                .archived_blocks
+                .iter()
+                .map(|range| range.length)
+                .sum::<u64>(),
+            2
+        );
+
+        Ok(())
+    })
+}
+
 #[test]
 fn archive_blocks_large_test() {
     local_test_e(|r| async move {
@@ -1866,6 +2055,369 @@ fn test_transfer_u64_overflow() {
         Ok(())
     });
 }
+
+async fn approve_candid(
+    ledger: &Canister<'_>,
+    owner: &Sender,
+    spender: &AccountIdentifier,
+    allowance: Tokens,
+    expires_at: Option<TimeStamp>,
+) -> Result<BlockHeight, TransferError> {
+    ledger
+        .update_from_sender(
+            "approve",
+            candid_one,
+            ApproveArgs {
+                memo: Memo(0),
+                fee: DEFAULT_TRANSFER_FEE,
+                from_subaccount: None,
+                spender: spender.to_address(),
+                allowance,
+                expires_at,
+                created_at_time: None,
+            },
+            owner,
+        )
+        .await
+        .expect("approve call trapped")
+}
+
+async fn transfer_from_candid(
+    ledger: &Canister<'_>,
+    spender: &Sender,
+    from: &AccountIdentifier,
+    to: &AccountIdentifier,
+    amount: Tokens,
+) -> Result<BlockHeight, TransferFromError> {
+    ledger
+        .update_from_sender(
+            "transfer_from",
+            candid_one,
+            TransferFromArgs {
+                memo: Memo(0),
+                amount,
+                fee: DEFAULT_TRANSFER_FEE,
+                spender_subaccount: None,
+                from: from.to_address(),
+                to: to.to_address(),
+                created_at_time: None,
+            },
+            spender,
+        )
+        .await
+        .expect("transfer_from call trapped")
+}
+
+async fn allowance_candid(
+    ledger: &Canister<'_>,
+    owner: &AccountIdentifier,
+    spender: &AccountIdentifier,
+) -> Allowance {
+    ledger
+        .query_(
+            "allowance",
+            candid_one,
+            AllowanceArgs {
+                account: owner.to_address(),
+                spender: spender.to_address(),
+            },
+        )
+        .await
+        .expect("failed to query the allowance")
+}
+
+#[test]
+fn allowances_survive_upgrades_test() {
+    local_test_e(|r| async move {
+        let proj = Project::new(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+
+        let minting_account = create_sender(0);
+        let owner = create_sender(1);
+        let spender = create_sender(2);
+        let short_lived_spender = create_sender(3);
+        let receiver = create_sender(4);
+
+        let owner_address: AccountIdentifier = owner.get_principal_id().into();
+        let spender_address: AccountIdentifier = spender.get_principal_id().into();
+        let short_lived_spender_address: AccountIdentifier =
+            short_lived_spender.get_principal_id().into();
+        let receiver_address: AccountIdentifier = receiver.get_principal_id().into();
+
+        let mut accounts = HashMap::new();
+        accounts.insert(owner_address, Tokens::from_e8s(1_000_000_000));
+
+        let mut ledger = proj
+            .cargo_bin("ledger-canister", &[])
+            .install_(
+                &r,
+                CandidOne(
+                    LedgerCanisterInitPayload::builder()
+                        .minting_account(
+                            CanisterId::try_from(minting_account.get_principal_id())
+                                .unwrap()
+                                .into(),
+                        )
+                        .initial_values(accounts)
+                        .build()
+                        .unwrap(),
+                ),
+            )
+            .await?;
+
+        let now = TimeStamp::from(SystemTime::now());
+        let expires_at = now + Duration::from_secs(24 * 3600);
+        let short_expiry = Duration::from_secs(30);
+        let short_expires_at = now + short_expiry;
+        approve_candid(
+            &ledger,
+            &owner,
+            &spender_address,
+            Tokens::from_e8s(100_000_000),
+            Some(expires_at),
+        )
+        .await
+        .expect("failed to approve");
+        approve_candid(
+            &ledger,
+            &owner,
+            &short_lived_spender_address,
+            Tokens::from_e8s(100_000_000),
+            Some(short_expires_at),
+        )
+        .await
+        .expect("failed to approve");
+
+        transfer_from_candid(
+            &ledger,
+            &spender,
+            &owner_address,
+            &receiver_address,
+            Tokens::from_e8s(10_000_000),
+        )
+        .await
+        .expect("failed to transfer from the owner");
+        let allowance = Allowance {
+            allowance: Tokens::from_e8s(100_000_000 - 10_000_000 - DEFAULT_TRANSFER_FEE.get_e8s()),
+            expires_at: Some(expires_at),
+        };
+        assert_eq!(
+            allowance_candid(&ledger, &owner_address, &spender_address).await,
+            allowance
+        );
+        let short_lived_allowance =
+            allowance_candid(&ledger, &owner_address, &short_lived_spender_address).await;
+        assert_eq!(short_lived_allowance.expires_at, Some(short_expires_at));
+
+        ledger.upgrade_to_self_binary(Vec::new()).await?;
+
+        assert_eq!(
+            allowance_candid(&ledger, &owner_address, &spender_address).await,
+            allowance
+        );
+        if SystemTime::now() < SystemTime::from(short_expires_at) {
+            assert_eq!(
+                allowance_candid(&ledger, &owner_address, &short_lived_spender_address).await,
+                short_lived_allowance
+            );
+        }
+
+        // The allowance can still be spent after the upgrade.
+        transfer_from_candid(
+            &ledger,
+            &spender,
+            &owner_address,
+            &receiver_address,
+            Tokens::from_e8s(10_000_000),
+        )
+        .await
+        .expect("failed to transfer from the owner after the upgrade");
+        assert_eq!(
+            allowance_candid(&ledger, &owner_address, &spender_address).await,
+            Allowance {
+                allowance: Tokens::from_e8s(
+                    100_000_000 - 2 * (10_000_000 + DEFAULT_TRANSFER_FEE.get_e8s())
+                ),
+                expires_at: Some(expires_at),
+            }
+        );
+        assert_eq!(
+            account_balance_candid(&ledger, &receiver_address).await,
+            Tokens::from_e8s(20_000_000)
+        );
+        assert_eq!(
+            account_balance_candid(&ledger, &owner_address).await,
+            Tokens::from_e8s(
+                1_000_000_000
+                    - 2 * (10_000_000 + DEFAULT_TRANSFER_FEE.get_e8s())
+                    - 2 * DEFAULT_TRANSFER_FEE.get_e8s()
+            )
+        );
+
+        // The expiry still applies after the upgrade.
+        let expired = SystemTime::from(short_expires_at) + Duration::from_secs(1);
+        if let Ok(remaining) = expired.duration_since(SystemTime::now()) {
+            std::thread::sleep(remaining);
+        }
+        assert_eq!(
+            allowance_candid(&ledger, &owner_address, &short_lived_spender_address).await,
+            Allowance::default()
+        );
+        assert_eq!(
+            transfer_from_candid(
+                &ledger,
+                &short_lived_spender,
+                &owner_address,
+                &receiver_address,
+                Tokens::from_e8s(10_000_000),
+            )
+            .await,
+            Err(TransferFromError::InsufficientAllowance {
+                allowance: Tokens::ZERO
+            })
+        );
+
+        Ok(())
+    });
+}
+
+async fn icrc1_transfer_candid(
+    ledger: &Canister<'_>,
+    from: &Sender,
+    to: icrc1::Account,
+    amount: u64,
+    fee: Option<u64>,
+) -> icrc1::TransferResult {
+    ledger
+        .update_from_sender(
+            "icrc1_transfer",
+            candid_one,
+            icrc1::TransferArg {
+                from_subaccount: None,
+                to,
+                amount: Nat::from(amount),
+                fee: fee.map(Nat::from),
+                memo: None,
+                created_at_time: None,
+            },
+            from,
+        )
+        .await
+        .expect("icrc1_transfer call trapped")
+}
+
+/// Checks that both the `account_balance` and the `icrc1_balance_of` endpoints
+/// report `expected_e8s` for `account`.
+async fn assert_balance(ledger: &Canister<'_>, account: icrc1::Account, expected_e8s: u64) {
+    assert_eq!(
+        account_balance_candid(ledger, &account.into()).await,
+        Tokens::from_e8s(expected_e8s)
+    );
+    let balance: Nat = ledger
+        .query_("icrc1_balance_of", candid_one, account)
+        .await
+        .expect("failed to query the icrc1 balance");
+    assert_eq!(balance, Nat::from(expected_e8s));
+}
+
+#[test]
+fn icrc1_transfer_test() {
+    local_test_e(|r| async move {
+        let proj = Project::new(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+
+        let minter = create_sender(0);
+        let alice = create_sender(1);
+        let bob = create_sender(2);
+        let minting_account = icrc1::Account::from(minter.get_principal_id());
+        let alice_account = icrc1::Account::from(alice.get_principal_id());
+        let bob_account = icrc1::Account::from(bob.get_principal_id());
+
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            AccountIdentifier::from(alice_account),
+            Tokens::from_e8s(1_000_000_000),
+        );
+
+        let ledger = proj
+            .cargo_bin("ledger-canister", &[])
+            .install_(
+                &r,
+                CandidOne(
+                    LedgerCanisterInitPayload::builder()
+                        .icrc1_minting_account(minting_account)
+                        .initial_values(accounts)
+                        .build()
+                        .unwrap(),
+                ),
+            )
+            .await?;
+
+        let fee = DEFAULT_TRANSFER_FEE.get_e8s();
+        let mut alice_balance = 1_000_000_000;
+        let mut bob_balance = 0;
+
+        // A regular transfer, paying the current fee implicitly or explicitly.
+        icrc1_transfer_candid(&ledger, &alice, bob_account, 100_000, None)
+            .await
+            .expect("failed to transfer");
+        icrc1_transfer_candid(&ledger, &alice, bob_account, 100_000, Some(fee))
+            .await
+            .expect("failed to transfer");
+        alice_balance -= 2 * (100_000 + fee);
+        bob_balance += 2 * 100_000;
+        assert_balance(&ledger, alice_account, alice_balance).await;
+        assert_balance(&ledger, bob_account, bob_balance).await;
+
+        assert_eq!(
+            icrc1_transfer_candid(&ledger, &alice, bob_account, 100_000, Some(fee + 1)).await,
+            Err(icrc1::TransferError::BadFee {
+                expected_fee: Nat::from(fee)
+            })
+        );
+
+        // Mints are free.
+        icrc1_transfer_candid(&ledger, &minter, bob_account, 500_000, None)
+            .await
+            .expect("failed to mint");
+        bob_balance += 500_000;
+        assert_balance(&ledger, bob_account, bob_balance).await;
+        assert_eq!(
+            icrc1_transfer_candid(&ledger, &minter, bob_account, 500_000, Some(fee)).await,
+            Err(icrc1::TransferError::BadFee {
+                expected_fee: Nat::from(0u64)
+            })
+        );
+
+        // Burns are free, but must burn at least the transfer fee.
+        icrc1_transfer_candid(&ledger, &bob, minting_account, 200_000, Some(0))
+            .await
+            .expect("failed to burn");
+        bob_balance -= 200_000;
+        assert_balance(&ledger, bob_account, bob_balance).await;
+        assert_eq!(
+            icrc1_transfer_candid(&ledger, &bob, minting_account, fee - 1, None).await,
+            Err(icrc1::TransferError::BadBurn {
+                min_burn_amount: Nat::from(fee)
+            })
+        );
+
+        // The minting account can't transfer to itself.
+        assert_eq!(
+            icrc1_transfer_candid(&ledger, &minter, minting_account, 500_000, None).await,
+            Err(icrc1::TransferError::GenericError {
+                error_code: Nat::from(icrc1::ERROR_CODE_MINTING_ACCOUNT_SELF_TRANSFER),
+                message: "The minting account can't transfer to itself".to_string(),
+            })
+        );
+
+        assert_balance(&ledger, alice_account, alice_balance).await;
+        assert_balance(&ledger, minting_account, 0).await;
+        // The initial mint, two transfers, a mint and a burn.
+        ledger_assert_num_blocks(&ledger, 5).await;
+
+        Ok(())
+    });
+}
+
 async fn ledger_assert_num_blocks(ledger: &Canister<'_>, num_expected: usize) {
     let IterBlocksRes(blocks) = ledger
         .query_(
@@ -1924,3 +2476,185 @@ fn call_with_cleanup() {
         Ok(())
     })
 }
+
+async fn index_status(index: &Canister<'_>) -> IndexStatus {
+    index
+        .query_("status", candid, ())
+        .await
+        .expect("status call trapped")
+}
+
+async fn get_account_transactions(
+    index: &Canister<'_>,
+    account: &AccountIdentifier,
+) -> GetAccountTransactionsResponse {
+    index
+        .query_(
+            "get_account_transactions",
+            candid_one,
+            GetAccountTransactionsArgs {
+                account: account.to_address(),
+                start: None,
+                max_results: 100,
+            },
+        )
+        .await
+        .expect("get_account_transactions call trapped")
+}
+
+#[test]
+fn index_canister_keeps_syncing_after_failed_syncs_test() {
+    local_test_e(|r| async move {
+        let proj = Project::new(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+
+        // The ledger is installed only after the index, so the first syncs
+        // fail.
+        let mut ledger = r.create_canister_max_cycles_with_retries().await?;
+        let index = proj
+            .cargo_bin("ledger-index-canister", &[])
+            .install_(
+                &r,
+                CandidOne(IndexCanisterInitArgs {
+                    ledger_id: ledger.canister_id(),
+                }),
+            )
+            .await?;
+
+        let mut attempts = 0;
+        while index_status(&index).await.last_sync_error.is_none() {
+            attempts += 1;
+            assert!(attempts < 100, "the index didn't try to sync");
+        }
+        assert_eq!(index_status(&index).await.num_blocks_synced, 0);
+
+        let accounts = make_accounts(3, 2);
+        proj.cargo_bin("ledger-canister", &[])
+            .install_onto_canister(
+                &mut ledger,
+                Some(
+                    CandidOne(
+                        LedgerCanisterInitPayload::builder()
+                            .minting_account(CanisterId::from_u64(0).into())
+                            .initial_values(accounts.clone())
+                            .build()
+                            .unwrap(),
+                    )
+                    .into_bytes()?,
+                ),
+            )
+            .await?;
+
+        let chain_length = accounts.len() as u64;
+        let mut attempts = 0;
+        while index_status(&index).await.num_blocks_synced < chain_length {
+            attempts += 1;
+            assert!(attempts < 100, "the index didn't sync after failing");
+        }
+        let status = index_status(&index).await;
+        assert_eq!(status.num_blocks_synced, chain_length);
+        assert_eq!(status.last_sync_error, None);
+        Ok(())
+    })
+}
+
+#[test]
+fn index_canister_upgrade_test() {
+    local_test_e(|r| async move {
+        let proj = Project::new(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+
+        let acc1 = create_sender(1);
+        let acc2 = create_sender(2);
+        let acc1_address: AccountIdentifier = acc1.get_principal_id().into();
+        let acc2_address: AccountIdentifier = acc2.get_principal_id().into();
+
+        let mut accounts = make_accounts(3, 2);
+        accounts.insert(acc1_address, Tokens::from_e8s(1_000_000_000));
+
+        let ledger = proj
+            .cargo_bin("ledger-canister", &[])
+            .install_(
+                &r,
+                CandidOne(
+                    LedgerCanisterInitPayload::builder()
+                        .minting_account(CanisterId::from_u64(0).into())
+                        .initial_values(accounts.clone())
+                        .build()
+                        .unwrap(),
+                ),
+            )
+            .await?;
+
+        for i in 1..=3 {
+            transfer_candid(
+                &ledger,
+                &acc1,
+                TransferArgs {
+                    memo: Memo(i),
+                    amount: Tokens::from_e8s(i * 1_000),
+                    fee: DEFAULT_TRANSFER_FEE,
+                    from_subaccount: None,
+                    to: acc2_address.to_address(),
+                    created_at_time: None,
+                },
+            )
+            .await
+            .expect("transfer failed");
+        }
+        let chain_length = accounts.len() as u64 + 3;
+
+        let mut index = proj
+            .cargo_bin("ledger-index-canister", &[])
+            .install_(
+                &r,
+                CandidOne(IndexCanisterInitArgs {
+                    ledger_id: ledger.canister_id(),
+                }),
+            )
+            .await?;
+
+        // The index syncs from the ledger on heartbeats.
+        let mut attempts = 0;
+        while index_status(&index).await.num_blocks_synced < chain_length {
+            attempts += 1;
+            assert!(attempts < 100, "the index didn't sync the ledger blocks");
+        }
+        let status = index_status(&index).await;
+        assert_eq!(status.num_balance_errors, 0);
+        assert_eq!(status.last_sync_error, None);
+
+        let acc1_history = get_account_transactions(&index, &acc1_address).await;
+        let acc2_history = get_account_transactions(&index, &acc2_address).await;
+        assert_eq!(
+            acc1_history.balance,
+            account_balance_candid(&ledger, &acc1_address).await
+        );
+        assert_eq!(
+            acc2_history.balance,
+            account_balance_candid(&ledger, &acc2_address).await
+        );
+        // The initial mint and three transfers, the most recent first.
+        assert_eq!(acc1_history.transactions.len(), 4);
+        assert_eq!(acc2_history.transactions.len(), 3);
+        assert_eq!(
+            acc2_history
+                .transactions
+                .iter()
+                .map(|t| t.block.transaction.memo)
+                .collect::<Vec<_>>(),
+            vec![Memo(3), Memo(2), Memo(1)]
+        );
+
+        index.upgrade_to_self_binary(Vec::new()).await?;
+
+        assert!(index_status(&index).await.num_blocks_synced >= chain_length);
+        assert_eq!(
+            get_account_transactions(&index, &acc1_address).await,
+            acc1_history
+        );
+        assert_eq!(
+            get_account_transactions(&index, &acc2_address).await,
+            acc2_history
+        );
+        Ok(())
+    })
+}
@@ -2,12 +2,15 @@ use crate::errors::ApiError;
 use crate::models;
 use crate::models::{AccountIdentifier, Amount, BlockIdentifier, Currency, Operation, Timestamp};
 use crate::request_types::{
-    AddHotKey, Disburse, DisburseMetadata, KeyMetadata, MergeMaturity, MergeMaturityMetadata,
-    NeuronIdentifierMetadata, PublicKeyOrPrincipal, Request, RequestResult, RequestResultMetadata,
-    SetDissolveTimestamp, SetDissolveTimestampMetadata, Spawn, SpawnMetadata, Stake, StartDissolve,
-    Status, StopDissolve, TransactionOperationResults, TransactionResults, ADD_HOT_KEY, DISBURSE,
-    FEE, MERGE_MATURITY, SET_DISSOLVE_TIMESTAMP, SPAWN, STAKE, START_DISSOLVE, STATUS_COMPLETED,
-    STOP_DISSOLVE, TRANSACTION,
+    AddHotKey, Disburse, DisburseMetadata, DisburseToNeuron, DisburseToNeuronMetadata, Follow,
+    FollowMetadata, KeyMetadata, Merge, MergeMaturity, MergeMaturityMetadata, MergeMetadata,
+    NeuronIdentifierMetadata, PublicKeyOrPrincipal, RegisterVote, RegisterVoteMetadata,
+    RemoveHotKey, Request, RequestResult, RequestResultMetadata, SetDissolveTimestamp,
+    SetDissolveTimestampMetadata, Spawn, SpawnMetadata, Split, Stake, StartDissolve, Status,
+    StopDissolve, TransactionOperationResults, TransactionResults, ADD_HOT_KEY, DISBURSE,
+    DISBURSE_TO_NEURON, FEE, FOLLOW, MERGE, MERGE_MATURITY, REGISTER_VOTE, REMOVE_HOT_KEY,
+    SET_DISSOLVE_TIMESTAMP, SPAWN, SPLIT, STAKE, START_DISSOLVE, STATUS_COMPLETED, STOP_DISSOLVE,
+    TRANSACTION,
 };
 use crate::store::HashedBlock;
 use crate::time::Seconds;
@@ -15,6 +18,7 @@ use crate::transaction_id::TransactionIdentifier;
 use crate::{convert, errors};
 use dfn_protobuf::ProtoBuf;
 use ic_crypto_tree_hash::Path;
+use ic_nns_governance::pb::v1::{Topic, Vote};
 use ic_types::messages::{HttpCanisterUpdate, HttpReadState};
 use ic_types::{CanisterId, PrincipalId};
 use ledger_canister::{
@@ -305,6 +309,132 @@ impl State {
         }));
         Ok(())
     }
+
+    fn remove_hot_key(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_index: u64,
+        key: PublicKeyOrPrincipal,
+    ) -> Result<(), ApiError> {
+        self.flush()?;
+
+        self.actions.push(Request::RemoveHotKey(RemoveHotKey {
+            account,
+            neuron_index,
+            key,
+        }));
+
+        Ok(())
+    }
+
+    fn follow(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_index: u64,
+        topic: i32,
+        followees: Vec<u64>,
+    ) -> Result<(), ApiError> {
+        if Topic::from_i32(topic).is_none() {
+            let msg = format!("Invalid topic: {}", topic);
+            return Err(ApiError::InvalidTransaction(false, msg.into()));
+        }
+        self.flush()?;
+
+        self.actions.push(Request::Follow(Follow {
+            account,
+            neuron_index,
+            topic,
+            followees,
+        }));
+
+        Ok(())
+    }
+
+    fn register_vote(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_index: u64,
+        proposal: u64,
+        vote: i32,
+    ) -> Result<(), ApiError> {
+        if !matches!(Vote::from_i32(vote), Some(Vote::Yes) | Some(Vote::No)) {
+            let msg = format!("Invalid vote: {}", vote);
+            return Err(ApiError::InvalidTransaction(false, msg.into()));
+        }
+        self.flush()?;
+
+        self.actions.push(Request::RegisterVote(RegisterVote {
+            account,
+            neuron_index,
+            proposal,
+            vote,
+        }));
+
+        Ok(())
+    }
+
+    fn split(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_index: u64,
+        amount: Tokens,
+    ) -> Result<(), ApiError> {
+        self.flush()?;
+
+        self.actions.push(Request::Split(Split {
+            account,
+            neuron_index,
+            amount,
+        }));
+
+        Ok(())
+    }
+
+    fn merge(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        neuron_index: u64,
+        source_neuron_id: u64,
+    ) -> Result<(), ApiError> {
+        self.flush()?;
+
+        self.actions.push(Request::Merge(Merge {
+            account,
+            neuron_index,
+            source_neuron_id,
+        }));
+
+        Ok(())
+    }
+
+    fn disburse_to_neuron(
+        &mut self,
+        account: ledger_canister::AccountIdentifier,
+        amount: Tokens,
+        metadata: DisburseToNeuronMetadata,
+    ) -> Result<(), ApiError> {
+        let DisburseToNeuronMetadata {
+            neuron_index,
+            controller,
+            dissolve_delay_seconds,
+            kyc_verified,
+            child_neuron_index,
+        } = metadata;
+        self.flush()?;
+
+        self.actions
+            .push(Request::DisburseToNeuron(DisburseToNeuron {
+                account,
+                neuron_index,
+                amount,
+                controller,
+                dissolve_delay_seconds,
+                kyc_verified,
+                child_neuron_index,
+            }));
+
+        Ok(())
+    }
 }
 
 pub fn from_operations(
@@ -336,7 +466,8 @@ pub fn from_operations(
             .map_err(|e| op_error(o, e))?;
 
         let validate_neuron_management_op = || {
-            if o.amount.is_some() && o._type != DISBURSE {
+            let may_have_amount = matches!(o._type.as_str(), DISBURSE | SPLIT | DISBURSE_TO_NEURON);
+            if o.amount.is_some() && !may_have_amount {
                 Err(op_error(
                     o,
                     format!(
@@ -437,6 +568,49 @@ pub fn from_operations(
                 validate_neuron_management_op()?;
                 state.merge_maturity(account, neuron_index, percentage_to_merge)?;
             }
+            REMOVE_HOT_KEY => {
+                let KeyMetadata { key, neuron_index } = o.metadata.clone().try_into()?;
+                validate_neuron_management_op()?;
+                state.remove_hot_key(account, neuron_index, key)?;
+            }
+            FOLLOW => {
+                let FollowMetadata {
+                    topic,
+                    followees,
+                    neuron_index,
+                } = o.metadata.clone().try_into()?;
+                validate_neuron_management_op()?;
+                state.follow(account, neuron_index, topic, followees)?;
+            }
+            REGISTER_VOTE => {
+                let RegisterVoteMetadata {
+                    proposal_id,
+                    vote,
+                    neuron_index,
+                } = o.metadata.clone().try_into()?;
+                validate_neuron_management_op()?;
+                state.register_vote(account, neuron_index, proposal_id, vote)?;
+            }
+            SPLIT => {
+                let NeuronIdentifierMetadata { neuron_index } = o.metadata.clone().try_into()?;
+                validate_neuron_management_op()?;
+                let amount = neuron_op_amount(o, token_name).map_err(|e| op_error(o, e))?;
+                state.split(account, neuron_index, amount)?;
+            }
+            MERGE => {
+                let MergeMetadata {
+                    source_neuron_id,
+                    neuron_index,
+                } = o.metadata.clone().try_into()?;
+                validate_neuron_management_op()?;
+                state.merge(account, neuron_index, source_neuron_id)?;
+            }
+            DISBURSE_TO_NEURON => {
+                let metadata: DisburseToNeuronMetadata = o.metadata.clone().try_into()?;
+                validate_neuron_management_op()?;
+                let amount = neuron_op_amount(o, token_name).map_err(|e| op_error(o, e))?;
+                state.disburse_to_neuron(account, amount, metadata)?;
+            }
             _ => {
                 let msg = format!("Unsupported operation type: {}", o._type);
                 return Err(op_error(o, msg));
@@ -456,6 +630,16 @@ pub fn from_operations(
     Ok(state.actions)
 }
 
+/// The amount of a neuron management operation that moves a part of the
+/// stake of a neuron, which must be populated.
+fn neuron_op_amount(o: &Operation, token_name: &str) -> Result<Tokens, String> {
+    let amount = o
+        .amount
+        .as_ref()
+        .ok_or_else(|| "Amount must be populated".to_string())?;
+    ledgeramount_from_amount(amount, token_name)
+}
+
 pub fn amount_(amount: Tokens, token_name: &str) -> Result<Amount, ApiError> {
     let amount = amount.get_e8s();
     Ok(Amount {
@@ -548,7 +732,16 @@ pub fn neuron_subaccount_bytes_from_public_key(
     neuron_index: u64,
 ) -> Result<[u8; 32], ApiError> {
     let controller = principal_id_from_public_key(pk)?;
+    Ok(neuron_subaccount_bytes_from_principal(
+        &controller,
+        neuron_index,
+    ))
+}
 
+pub fn neuron_subaccount_bytes_from_principal(
+    controller: &PrincipalId,
+    neuron_index: u64,
+) -> [u8; 32] {
     // FIXME: cut&paste from compute_neuron_staking_subaccount() in
     // rs/nns/governance/src/governance.rs.
     let mut state = ic_crypto_sha::Sha256::new();
@@ -556,7 +749,7 @@ pub fn neuron_subaccount_bytes_from_public_key(
     state.write(b"neuron-stake");
     state.write(controller.as_slice());
     state.write(&neuron_index.to_be_bytes());
-    Ok(state.finish())
+    state.finish()
 }
 
 /// `neuron_index` must also be the `nonce` of neuron management commands.
@@ -663,6 +856,7 @@ pub fn from_transaction_operation_results(
         let RequestResultMetadata {
             block_index,
             neuron_id,
+            created_neuron_id,
             transaction_identifier,
             response,
         } = RequestResultMetadata::try_from(o.metadata.clone())?;
@@ -675,6 +869,7 @@ pub fn from_transaction_operation_results(
             _type,
             block_index,
             neuron_id,
+            created_neuron_id,
             transaction_identifier,
             status,
         });
@@ -174,6 +174,98 @@ fn test_can_handle_multiple_transfers() {
     );
 }
 
+fn neuron_management_requests() -> Vec<Request> {
+    vec![
+        Request::RemoveHotKey(RemoveHotKey {
+            account: test_account(1),
+            neuron_index: 1,
+            key: PublicKeyOrPrincipal::Principal(PrincipalId::new_user_test_id(2)),
+        }),
+        Request::Follow(Follow {
+            account: test_account(1),
+            neuron_index: 1,
+            topic: Topic::Governance as i32,
+            followees: vec![10, 11],
+        }),
+        Request::RegisterVote(RegisterVote {
+            account: test_account(1),
+            neuron_index: 1,
+            proposal: 42,
+            vote: Vote::Yes as i32,
+        }),
+        Request::Split(Split {
+            account: test_account(1),
+            neuron_index: 1,
+            amount: Tokens::from_e8s(200_000_000),
+        }),
+        Request::Merge(Merge {
+            account: test_account(1),
+            neuron_index: 1,
+            source_neuron_id: 12,
+        }),
+        Request::DisburseToNeuron(DisburseToNeuron {
+            account: test_account(1),
+            neuron_index: 1,
+            amount: Tokens::from_e8s(300_000_000),
+            controller: Some(PrincipalId::new_user_test_id(3)),
+            dissolve_delay_seconds: 3600,
+            kyc_verified: true,
+            child_neuron_index: 4,
+        }),
+    ]
+}
+
+#[test]
+fn test_neuron_management_requests_round_trip() {
+    for request in neuron_management_requests() {
+        let ops = Request::requests_to_operations(&[request.clone()], DEFAULT_TOKEN_NAME).unwrap();
+        assert_eq!(ops.len(), 1, "{:?}", request);
+        assert_eq!(
+            from_operations(&ops, false, DEFAULT_TOKEN_NAME),
+            Ok(vec![request])
+        );
+    }
+
+    let requests = neuron_management_requests();
+    let ops = Request::requests_to_operations(&requests, DEFAULT_TOKEN_NAME).unwrap();
+    assert_eq!(
+        from_operations(&ops, false, DEFAULT_TOKEN_NAME),
+        Ok(requests)
+    );
+}
+
+#[test]
+fn test_created_neuron_id_round_trip() {
+    let results = TransactionResults {
+        operations: neuron_management_requests()
+            .into_iter()
+            .enumerate()
+            .map(|(i, _type)| {
+                let created_neuron_id = match _type {
+                    Request::Split(_) | Request::DisburseToNeuron(_) => Some(100 + i as u64),
+                    _ => None,
+                };
+                RequestResult {
+                    _type,
+                    block_index: None,
+                    neuron_id: None,
+                    created_neuron_id,
+                    transaction_identifier: None,
+                    status: Status::Completed,
+                }
+            })
+            .collect(),
+    };
+
+    let ops =
+        TransactionOperationResults::from_transaction_results(results.clone(), DEFAULT_TOKEN_NAME)
+            .unwrap();
+    assert_eq!(
+        from_transaction_operation_results(ops, DEFAULT_TOKEN_NAME),
+        Ok(results)
+    );
+}
+
 #[test]
 fn account_identifier_decode_test() {
     // a good address
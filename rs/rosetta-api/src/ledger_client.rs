@@ -16,7 +16,7 @@ use dfn_candid::CandidOne;
 use dfn_protobuf::{ProtoBuf, ToProto};
 use ic_canister_client::{Agent, HttpClient, Sender};
 use ic_nns_governance::pb::v1::manage_neuron_response::{
-    DisburseResponse, MergeMaturityResponse, SpawnResponse,
+    DisburseResponse, DisburseToNeuronResponse, MergeMaturityResponse, SpawnResponse, SplitResponse,
 };
 use ic_nns_governance::pb::v1::{
     claim_or_refresh_neuron_from_account_response::Result as ClaimOrRefreshResult,
@@ -29,12 +29,11 @@ use ic_types::{crypto::threshold_sig::ThresholdSigPublicKey, messages::SignedReq
 use ledger_canister::protobuf::{ArchiveIndexEntry, ArchiveIndexResponse};
 use ledger_canister::{
     protobuf::TipOfChainRequest, AccountIdentifier, BlockArg, BlockHeight, BlockRes, EncodedBlock,
-    GetBlocksArgs, GetBlocksRes, HashOf, TipOfChainRes, Tokens, Transaction, TransferFee,
-    TransferFeeArgs, DEFAULT_TRANSFER_FEE,
+    GetBlocksArgs, GetBlocksRes, HashOf, TipOfChainRes, Tokens, TransferFee, TransferFeeArgs,
+    DEFAULT_TRANSFER_FEE,
 };
 use on_wire::{FromWire, IntoWire};
 
-use crate::balance_book::BalanceBook;
 use crate::certification::verify_block_hash;
 use crate::convert;
 use crate::errors::{ApiError, Details, ICError};
@@ -42,7 +41,7 @@ use crate::models::{EnvelopePair, SignedTransaction};
 use crate::request_types::START_DISSOLVE;
 use crate::request_types::STOP_DISSOLVE;
 use crate::request_types::{Request, RequestResult, RequestType, Status, TransactionResults};
-use crate::store::{BlockStoreError, HashedBlock, SQLiteStore};
+use crate::store::{BlockStore, BlockStoreError, HashedBlock, SQLiteStore};
 use crate::transaction_id::TransactionIdentifier;
 
 // If pruning is enabled, instead of pruning after each new block
@@ -91,15 +90,12 @@ impl LedgerClient {
         canister_id: CanisterId,
         token_name: String,
         governance_canister_id: CanisterId,
-        store_location: Option<&std::path::Path>,
+        block_store: Box<dyn BlockStore>,
         store_max_blocks: Option<u64>,
         offline: bool,
         root_key: Option<ThresholdSigPublicKey>,
     ) -> Result<LedgerClient, ApiError> {
-        let mut blocks = match store_location {
-            Some(loc) => Blocks::new_persistent(loc),
-            None => Blocks::new_in_memory(),
-        };
+        let mut blocks = Blocks::new(block_store);
         let canister_access = if offline {
             None
         } else {
@@ -414,6 +410,7 @@ impl LedgerAccess for LedgerClient {
                     _type,
                     block_index: None,
                     neuron_id: None,
+                    created_neuron_id: None,
                     transaction_identifier: None,
                     status: crate::request_types::Status::NotAttempted,
                 })
@@ -909,6 +906,45 @@ impl LedgerClient {
                                                             ),
                                                         }
                                                     }
+                                                    RequestType::RemoveHotKey { .. }
+                                                    | RequestType::Follow { .. }
+                                                    | RequestType::RegisterVote { .. }
+                                                    | RequestType::Split { .. }
+                                                    | RequestType::Merge { .. }
+                                                    | RequestType::DisburseToNeuron { .. } => {
+                                                        let response: ManageNeuronResponse =
+                                                            candid::decode_one(bytes.as_ref())
+                                                                .map_err(|err| {
+                                                                    format!(
+                                                                        "Could not decode {} request: {}",
+                                                                        request_type.into_str(),
+                                                                        err
+                                                                    )
+                                                                })?;
+                                                        match (&request_type, &response.command) {
+                                                            (RequestType::RemoveHotKey { .. }, Some(manage_neuron_response::Command::Configure(_)))
+                                                            | (RequestType::Follow { .. }, Some(manage_neuron_response::Command::Follow(_)))
+                                                            | (RequestType::RegisterVote { .. }, Some(manage_neuron_response::Command::RegisterVote(_)))
+                                                            | (RequestType::Merge { .. }, Some(manage_neuron_response::Command::Merge(_))) => {
+                                                                return Ok(Ok(None));
+                                                            }
+                                                            (RequestType::Split { .. }, Some(manage_neuron_response::Command::Split(SplitResponse { created_neuron_id })))
+                                                            | (RequestType::DisburseToNeuron { .. }, Some(manage_neuron_response::Command::DisburseToNeuron(DisburseToNeuronResponse { created_neuron_id }))) => {
+                                                                return Ok(Ok(created_neuron_id.as_ref().map(|nid| nid.id)));
+                                                            }
+                                                            (_, Some(manage_neuron_response::Command::Error(err))) => {
+                                                                return Ok(Err(ApiError::TransactionRejected(
+                                                                    false,
+                                                                    format!("Could not {}: {}", request_type.into_str(), err).into()
+                                                                )));
+                                                            }
+                                                            _ => panic!(
+                                                                "unexpected {} result: {:?}",
+                                                                request_type.into_str(),
+                                                                response.command
+                                                            ),
+                                                        }
+                                                    }
                                                 }
                                             }
                                             None => {
@@ -974,10 +1010,12 @@ impl LedgerClient {
         match wait_for_result().await {
             // Success
             Ok(Ok(id)) => {
-                if let Request::Stake(_) = result._type {
-                    result.neuron_id = id;
-                } else {
-                    result.block_index = id;
+                match result._type {
+                    Request::Stake(_) => result.neuron_id = id,
+                    Request::Split(_) | Request::DisburseToNeuron(_) => {
+                        result.created_neuron_id = id
+                    }
+                    _ => result.block_index = id,
                 }
                 result.status = Status::Completed;
                 Ok(())
@@ -1234,48 +1272,37 @@ impl CanisterAccess {
 }
 
 pub struct Blocks {
-    pub balance_book: BalanceBook,
     hash_location: HashMap<HashOf<EncodedBlock>, BlockHeight>,
-    pub tx_hash_location: HashMap<HashOf<Transaction>, BlockHeight>,
-    pub block_store: SQLiteStore,
+    pub block_store: Box<dyn BlockStore>,
     last_hash: Option<HashOf<EncodedBlock>>,
 }
 
 impl Blocks {
     const LOAD_FROM_STORE_BLOCK_BATCH_LEN: u64 = 10000;
 
-    pub fn new_persistent(store_location: &std::path::Path) -> Self {
-        let block_store = SQLiteStore::new_on_disk(store_location)
-            .expect("Failed to initialize sql store for ledger");
+    pub fn new(block_store: Box<dyn BlockStore>) -> Self {
         Self {
-            balance_book: BalanceBook::default(),
             hash_location: HashMap::default(),
-            tx_hash_location: HashMap::default(),
             block_store,
             last_hash: None,
         }
     }
 
+    pub fn new_persistent(store_location: &std::path::Path) -> Self {
+        let block_store = SQLiteStore::new_on_disk(store_location)
+            .expect("Failed to initialize sql store for ledger");
+        Self::new(Box::new(block_store))
+    }
+
     pub fn new_in_memory() -> Self {
         let block_store =
             SQLiteStore::new_in_memory().expect("Failed to initialize sql store for ledger");
-        Self {
-            balance_book: BalanceBook::default(),
-            hash_location: HashMap::default(),
-            tx_hash_location: HashMap::default(),
-            block_store,
-            last_hash: None,
-        }
+        Self::new(Box::new(block_store))
     }
 
     pub fn load_from_store(&mut self) -> Result<u64, ApiError> {
         assert!(self.last()?.is_none(), "Blocks is not empty");
-        assert!(
-            self.balance_book.store.acc_to_hist.is_empty(),
-            "Blocks is not empty"
-        );
         assert!(self.hash_location.is_empty(), "Blocks is not empty");
-        assert!(self.tx_hash_location.is_empty(), "Blocks is not empty");
 
         if let Ok(genesis) = self.block_store.get_at(0) {
             self.process_block(genesis)?;
@@ -1283,17 +1310,12 @@ impl Blocks {
             return Ok(0);
         }
 
-        if let Some((first, balances_snapshot)) = self.block_store.first_snapshot() {
-            self.balance_book = balances_snapshot;
-
+        if let Some(first) = self.block_store.first()?.filter(|first| first.index > 0) {
             self.hash_location.insert(first.hash, first.index);
-
-            let tx = first.block.decode().unwrap().transaction;
-            self.tx_hash_location.insert(tx.hash(), first.index);
             self.last_hash = Some(first.hash);
         }
 
-        let mut n = 1; // one block loaded so far (genesis or first from snapshot)
+        let mut n = 1; // one block loaded so far (genesis or first after pruning)
         let mut next_idx = self.last()?.map(|hb| hb.index + 1).unwrap();
         loop {
             let batch = self
@@ -1359,7 +1381,10 @@ impl Blocks {
                 h
             )))
         } else {
-            self.balance_book.store.get_at(*acc, h)
+            Ok(self
+                .block_store
+                .get_account_balance(acc, h)?
+                .unwrap_or(Tokens::ZERO))
         }
     }
 
@@ -1397,10 +1422,10 @@ impl Blocks {
 
     pub fn process_block(&mut self, hb: HashedBlock) -> Result<(), ApiError> {
         let HashedBlock {
-            block,
             hash,
             parent_hash,
             index,
+            ..
         } = hb.clone();
         let last = self.last()?;
         let last_hash = last.clone().map(|hb| hb.hash);
@@ -1410,23 +1435,13 @@ impl Blocks {
             "When adding a block the parent_hash must match the last added block"
         );
 
-        let block = block.decode().unwrap();
-
         match last_index {
             Some(i) => assert_eq!(i + 1, index),
             None => assert_eq!(0, index),
         }
 
-        let mut bb = &mut self.balance_book;
-        bb.store.transaction_context = Some(index);
-        bb.add_payment(&block.transaction.operation).unwrap();
-        bb.store.transaction_context = None;
-
         self.hash_location.insert(hash, index);
 
-        let tx = block.transaction;
-        self.tx_hash_location.insert(tx.hash(), index);
-
         self.last_hash = Some(hb.hash);
 
         Ok(())
@@ -1437,18 +1452,7 @@ impl Blocks {
     }
 
     pub fn first_verified(&self) -> Result<Option<HashedBlock>, ApiError> {
-        let last_verified_idx = self
-            .block_store
-            .last_verified()
-            .map(|x| x as i128)
-            .unwrap_or(-1);
-        let first_block = self.block_store.first()?;
-        if let Some(fb) = first_block.as_ref() {
-            if fb.index as i128 > last_verified_idx {
-                return Ok(None);
-            }
-        }
-        Ok(first_block)
+        Ok(self.block_store.first_verified()?)
     }
 
     fn last(&self) -> Result<Option<HashedBlock>, ApiError> {
@@ -1488,22 +1492,10 @@ impl Blocks {
                     self.hash_location
                         .remove(&hb.hash)
                         .expect("failed to remove block by hash");
-                    let tx_hash = hb
-                        .block
-                        .decode()
-                        .expect("failed to decode block")
-                        .transaction
-                        .hash();
-                    self.tx_hash_location
-                        .remove(&tx_hash)
-                        .expect("failed to remove transaction by hash");
                 }
 
                 let hb = self.block_store.get_at(new_first_idx)?;
-                self.balance_book.store.prune_at(hb.index);
-                self.block_store
-                    .prune(&hb, &self.balance_book)
-                    .map_err(ApiError::internal_error)?
+                self.block_store.prune(&hb)?
             }
         }
         Ok(())
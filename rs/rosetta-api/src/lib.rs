@@ -1,4 +1,3 @@
-pub mod balance_book;
 pub mod certification;
 pub mod convert;
 pub mod errors;
@@ -18,8 +17,9 @@ use crate::convert::{
 };
 use crate::ledger_client::LedgerAccess;
 use crate::request_types::{
-    AddHotKey, Disburse, MergeMaturity, PublicKeyOrPrincipal, Request, RequestType,
-    SetDissolveTimestamp, Spawn, Stake, StartDissolve, StopDissolve, TransactionOperationResults,
+    AddHotKey, Disburse, DisburseToNeuron, Follow, Merge, MergeMaturity, PublicKeyOrPrincipal,
+    RegisterVote, RemoveHotKey, Request, RequestType, SetDissolveTimestamp, Spawn, Split, Stake,
+    StartDissolve, StopDissolve, TransactionOperationResults,
 };
 use crate::store::HashedBlock;
 use crate::time::Seconds;
@@ -28,7 +28,7 @@ use convert::to_arg;
 use dfn_candid::CandidOne;
 use errors::ApiError;
 use ic_interfaces::crypto::DOMAIN_IC_REQUEST;
-use ic_nns_common::pb::v1::NeuronId;
+use ic_nns_common::pb::v1::{NeuronId, ProposalId};
 use ic_nns_governance::pb::v1::{
     manage_neuron::{self, configure, Command, NeuronIdOrSubaccount},
     ClaimOrRefreshNeuronFromAccount, ManageNeuron,
@@ -58,6 +58,13 @@ pub const API_VERSION: &str = "1.4.10";
 pub const NODE_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const DEFAULT_TOKEN_NAME: &str = "ICP";
 
+/// The /call method that lists the neurons of a controller.
+pub const LIST_NEURONS: &str = "list_neurons";
+/// The number of neuron indices `list_neurons` looks at by default.
+const LIST_NEURONS_DEFAULT_LIMIT: u64 = 100;
+/// The maximum number of neuron indices `list_neurons` looks at.
+const LIST_NEURONS_MAX_LIMIT: u64 = 10_000;
+
 fn to_index(height: BlockHeight) -> Result<i128, ApiError> {
     i128::try_from(height).map_err(|e| ApiError::InternalError(true, e.to_string().into()))
 }
@@ -717,6 +724,158 @@ impl RosettaRequestHandler {
                         ));
                     }
                 }
+
+                RequestType::RemoveHotKey { neuron_index } => {
+                    let manage: ManageNeuron = candid::decode_one(arg.0.as_ref()).map_err(|e| {
+                        ApiError::internal_error(format!(
+                            "Could not decode ManageNeuron argument: {}",
+                            e
+                        ))
+                    })?;
+                    if let Some(Command::Configure(manage_neuron::Configure {
+                        operation:
+                            Some(manage_neuron::configure::Operation::RemoveHotKey(
+                                manage_neuron::RemoveHotKey {
+                                    hot_key_to_remove: Some(pid),
+                                },
+                            )),
+                    })) = manage.command
+                    {
+                        requests.push(Request::RemoveHotKey(RemoveHotKey {
+                            account: from,
+                            neuron_index,
+                            key: PublicKeyOrPrincipal::Principal(pid),
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    };
+                }
+
+                RequestType::Follow { neuron_index } => {
+                    let manage: ManageNeuron = candid::decode_one(arg.0.as_ref()).map_err(|e| {
+                        ApiError::internal_error(format!(
+                            "Could not decode ManageNeuron argument: {}",
+                            e
+                        ))
+                    })?;
+                    if let Some(Command::Follow(manage_neuron::Follow { topic, followees })) =
+                        manage.command
+                    {
+                        requests.push(Request::Follow(Follow {
+                            account: from,
+                            neuron_index,
+                            topic,
+                            followees: followees.iter().map(|n| n.id).collect(),
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    }
+                }
+
+                RequestType::RegisterVote { neuron_index } => {
+                    let manage: ManageNeuron = candid::decode_one(arg.0.as_ref()).map_err(|e| {
+                        ApiError::internal_error(format!(
+                            "Could not decode ManageNeuron argument: {}",
+                            e
+                        ))
+                    })?;
+                    if let Some(Command::RegisterVote(manage_neuron::RegisterVote {
+                        proposal: Some(proposal),
+                        vote,
+                    })) = manage.command
+                    {
+                        requests.push(Request::RegisterVote(RegisterVote {
+                            account: from,
+                            neuron_index,
+                            proposal: proposal.id,
+                            vote,
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    }
+                }
+
+                RequestType::Split { neuron_index } => {
+                    let manage: ManageNeuron = candid::decode_one(arg.0.as_ref()).map_err(|e| {
+                        ApiError::internal_error(format!(
+                            "Could not decode ManageNeuron argument: {}",
+                            e
+                        ))
+                    })?;
+                    if let Some(Command::Split(manage_neuron::Split { amount_e8s })) =
+                        manage.command
+                    {
+                        requests.push(Request::Split(Split {
+                            account: from,
+                            neuron_index,
+                            amount: ledger_canister::Tokens::from_e8s(amount_e8s),
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    }
+                }
+
+                RequestType::Merge { neuron_index } => {
+                    let manage: ManageNeuron = candid::decode_one(arg.0.as_ref()).map_err(|e| {
+                        ApiError::internal_error(format!(
+                            "Could not decode ManageNeuron argument: {}",
+                            e
+                        ))
+                    })?;
+                    if let Some(Command::Merge(manage_neuron::Merge {
+                        source_neuron_id: Some(source_neuron_id),
+                    })) = manage.command
+                    {
+                        requests.push(Request::Merge(Merge {
+                            account: from,
+                            neuron_index,
+                            source_neuron_id: source_neuron_id.id,
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    }
+                }
+
+                RequestType::DisburseToNeuron { neuron_index } => {
+                    let manage: ManageNeuron = candid::decode_one(arg.0.as_ref()).map_err(|e| {
+                        ApiError::internal_error(format!(
+                            "Could not decode ManageNeuron argument: {}",
+                            e
+                        ))
+                    })?;
+                    if let Some(Command::DisburseToNeuron(manage_neuron::DisburseToNeuron {
+                        new_controller,
+                        amount_e8s,
+                        dissolve_delay_seconds,
+                        kyc_verified,
+                        nonce,
+                    })) = manage.command
+                    {
+                        requests.push(Request::DisburseToNeuron(DisburseToNeuron {
+                            account: from,
+                            neuron_index,
+                            amount: ledger_canister::Tokens::from_e8s(amount_e8s),
+                            controller: new_controller,
+                            dissolve_delay_seconds,
+                            kyc_verified,
+                            child_neuron_index: nonce,
+                        }));
+                    } else {
+                        return Err(ApiError::internal_error(
+                            "Incompatible manage_neuron command".to_string(),
+                        ));
+                    }
+                }
             }
         }
 
@@ -1093,6 +1252,16 @@ impl RosettaRequestHandler {
                         "Mint operations are not supported through rosetta",
                     ))
                 }
+                Request::Transfer(Operation::Approve { .. }) => {
+                    return Err(ApiError::invalid_request(
+                        "Approve operations are not supported through rosetta",
+                    ))
+                }
+                Request::Transfer(Operation::TransferFrom { .. }) => {
+                    return Err(ApiError::invalid_request(
+                        "TransferFrom operations are not supported through rosetta",
+                    ))
+                }
                 Request::Spawn(Spawn {
                     account,
                     spawned_neuron_index,
@@ -1129,6 +1298,144 @@ impl RosettaRequestHandler {
                         &mut updates,
                     )?;
                 }
+                Request::RemoveHotKey(RemoveHotKey {
+                    account,
+                    key,
+                    neuron_index,
+                }) => {
+                    let pid = match key {
+                        PublicKeyOrPrincipal::Principal(p) => p,
+                        PublicKeyOrPrincipal::PublicKey(pk) => principal_id_from_public_key(&pk)?,
+                    };
+                    let command = Command::Configure(manage_neuron::Configure {
+                        operation: Some(configure::Operation::RemoveHotKey(
+                            manage_neuron::RemoveHotKey {
+                                hot_key_to_remove: Some(pid),
+                            },
+                        )),
+                    });
+                    add_neuron_management_payload(
+                        RequestType::RemoveHotKey { neuron_index },
+                        account,
+                        neuron_index,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::Follow(Follow {
+                    account,
+                    neuron_index,
+                    topic,
+                    followees,
+                }) => {
+                    let command = Command::Follow(manage_neuron::Follow {
+                        topic,
+                        followees: followees.into_iter().map(|id| NeuronId { id }).collect(),
+                    });
+                    add_neuron_management_payload(
+                        RequestType::Follow { neuron_index },
+                        account,
+                        neuron_index,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::RegisterVote(RegisterVote {
+                    account,
+                    neuron_index,
+                    proposal,
+                    vote,
+                }) => {
+                    let command = Command::RegisterVote(manage_neuron::RegisterVote {
+                        proposal: Some(ProposalId { id: proposal }),
+                        vote,
+                    });
+                    add_neuron_management_payload(
+                        RequestType::RegisterVote { neuron_index },
+                        account,
+                        neuron_index,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::Split(Split {
+                    account,
+                    neuron_index,
+                    amount,
+                }) => {
+                    let command = Command::Split(manage_neuron::Split {
+                        amount_e8s: amount.get_e8s(),
+                    });
+                    add_neuron_management_payload(
+                        RequestType::Split { neuron_index },
+                        account,
+                        neuron_index,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::Merge(Merge {
+                    account,
+                    neuron_index,
+                    source_neuron_id,
+                }) => {
+                    let command = Command::Merge(manage_neuron::Merge {
+                        source_neuron_id: Some(NeuronId {
+                            id: source_neuron_id,
+                        }),
+                    });
+                    add_neuron_management_payload(
+                        RequestType::Merge { neuron_index },
+                        account,
+                        neuron_index,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
+                Request::DisburseToNeuron(DisburseToNeuron {
+                    account,
+                    neuron_index,
+                    amount,
+                    controller,
+                    dissolve_delay_seconds,
+                    kyc_verified,
+                    child_neuron_index,
+                }) => {
+                    // Unlike SPAWN, governance requires the controller of the new
+                    // neuron to be set, so it defaults to the signer.
+                    let controller = match controller {
+                        Some(controller) => controller,
+                        None => {
+                            let pk = pks_map.get(&account).ok_or_else(|| {
+                                ApiError::internal_error(format!(
+                                    "Cannot find public key for account identifier {}",
+                                    account,
+                                ))
+                            })?;
+                            principal_id_from_public_key(pk)?
+                        }
+                    };
+                    let command = Command::DisburseToNeuron(manage_neuron::DisburseToNeuron {
+                        new_controller: Some(controller),
+                        amount_e8s: amount.get_e8s(),
+                        dissolve_delay_seconds,
+                        kyc_verified,
+                        nonce: child_neuron_index,
+                    });
+                    add_neuron_management_payload(
+                        RequestType::DisburseToNeuron { neuron_index },
+                        account,
+                        neuron_index,
+                        command,
+                        &mut payloads,
+                        &mut updates,
+                    )?;
+                }
             }
         }
 
@@ -1167,13 +1474,27 @@ impl RosettaRequestHandler {
                     | Request::Disburse(Disburse { account, .. })
                     | Request::AddHotKey(AddHotKey { account, .. })
                     | Request::Spawn(Spawn { account, .. })
-                    | Request::MergeMaturity(MergeMaturity { account, .. }) => Ok(account),
+                    | Request::MergeMaturity(MergeMaturity { account, .. })
+                    | Request::RemoveHotKey(RemoveHotKey { account, .. })
+                    | Request::Follow(Follow { account, .. })
+                    | Request::RegisterVote(RegisterVote { account, .. })
+                    | Request::Split(Split { account, .. })
+                    | Request::Merge(Merge { account, .. })
+                    | Request::DisburseToNeuron(DisburseToNeuron { account, .. }) => Ok(account),
                     Request::Transfer(Operation::Burn { .. }) => Err(ApiError::invalid_request(
                         "Burn operations are not supported through rosetta",
                     )),
                     Request::Transfer(Operation::Mint { .. }) => Err(ApiError::invalid_request(
                         "Mint operations are not supported through rosetta",
                     )),
+                    Request::Transfer(Operation::Approve { .. }) => Err(ApiError::invalid_request(
+                        "Approve operations are not supported through rosetta",
+                    )),
+                    Request::Transfer(Operation::TransferFrom { .. }) => {
+                        Err(ApiError::invalid_request(
+                            "TransferFrom operations are not supported through rosetta",
+                        ))
+                    }
                 })
                 .collect();
 
@@ -1333,6 +1654,13 @@ impl RosettaRequestHandler {
                     "STOP_DISSOLVING".to_string(),
                     "SPAWN".to_string(),
                     "MERGE_MATURITY".to_string(),
+                    "REMOVE_HOT_KEY".to_string(),
+                    "FOLLOW".to_string(),
+                    "REGISTER_VOTE".to_string(),
+                    "SPLIT".to_string(),
+                    "MERGE".to_string(),
+                    "DISBURSE_TO_NEURON".to_string(),
+                    "APPROVE".to_string(),
                 ],
                 {
                     let token_name = self.ledger.token_name();
@@ -1367,6 +1695,7 @@ impl RosettaRequestHandler {
                     errs
                 },
                 true,
+                vec![LIST_NEURONS.to_string()],
             ),
         ))
     }
@@ -1563,8 +1892,8 @@ impl RosettaRequestHandler {
             let tid = ledger_canister::HashOf::try_from(tid)
                 .map_err(|e| ApiError::InvalidTransactionId(false, e.into()))?;
 
-            if let Some(i) = blocks.tx_hash_location.get(&tid) {
-                heights.push(*i);
+            if let Some(i) = blocks.block_store.get_transaction_index(&tid)? {
+                heights.push(i);
                 total_count += 1;
             }
         }
@@ -1575,7 +1904,7 @@ impl RosettaRequestHandler {
             let acc = from_model_account_identifier(aid)
                 .map_err(|e| ApiError::InvalidAccountId(false, e.into()))?;
 
-            let hist = blocks.balance_book.store.get_history(&acc, max_block);
+            let hist = blocks.block_store.get_account_history(&acc, max_block)?;
             heights = hist
                 .iter()
                 .rev()
@@ -1619,6 +1948,81 @@ impl RosettaRequestHandler {
         ))
     }
 
+    /// Call a method that isn't covered by the other endpoints
+    pub async fn call(&self, msg: models::CallRequest) -> Result<CallResponse, ApiError> {
+        verify_network_id(self.ledger.ledger_canister_id(), &msg.network_identifier)?;
+        match msg.method.as_str() {
+            LIST_NEURONS => {
+                let params: ListNeuronsRequest = serde_json::from_value(serde_json::Value::Object(
+                    msg.parameters,
+                ))
+                .map_err(|e| {
+                    ApiError::invalid_request(format!(
+                        "Could not parse list_neurons parameters: {}",
+                        e
+                    ))
+                })?;
+                let result = self.list_neurons(params).await?;
+                match serde_json::to_value(result) {
+                    Ok(serde_json::Value::Object(o)) => Ok(CallResponse::new(o, false)),
+                    _ => Err(ApiError::internal_error(
+                        "Could not serialize list_neurons result",
+                    )),
+                }
+            }
+            _ => Err(ApiError::invalid_request(format!(
+                "Unsupported call method: {}",
+                msg.method
+            ))),
+        }
+    }
+
+    /// Lists the neurons of a controller whose staking accounts, derived from
+    /// the controller and a neuron index like for STAKE operations, appear on
+    /// the ledger. Neurons that were created with other subaccounts are not
+    /// listed.
+    async fn list_neurons(
+        &self,
+        params: ListNeuronsRequest,
+    ) -> Result<ListNeuronsResponse, ApiError> {
+        let controller = PrincipalId::try_from(&params.controller)?;
+        let limit = params.limit.unwrap_or(LIST_NEURONS_DEFAULT_LIMIT);
+        if limit > LIST_NEURONS_MAX_LIMIT {
+            return Err(ApiError::invalid_request(format!(
+                "The limit must not exceed {}",
+                LIST_NEURONS_MAX_LIMIT
+            )));
+        }
+        let end = params.start_index.checked_add(limit).ok_or_else(|| {
+            ApiError::invalid_request("start_index + limit overflows".to_string())
+        })?;
+        let governance_id = self.ledger.governance_canister_id().get();
+        let blocks = self.ledger.read_blocks().await;
+        let mut neurons = vec![];
+        for neuron_index in params.start_index..end {
+            let subaccount =
+                convert::neuron_subaccount_bytes_from_principal(&controller, neuron_index);
+            let account = ledger_canister::AccountIdentifier::new(
+                governance_id,
+                Some(ledger_canister::Subaccount(subaccount)),
+            );
+            // The latest balance, including blocks that are not verified yet.
+            let balance = match blocks
+                .block_store
+                .get_account_balance(&account, BlockHeight::MAX)?
+            {
+                Some(balance) => balance,
+                None => continue,
+            };
+            neurons.push(NeuronAccount {
+                neuron_index,
+                account_identifier: to_model_account_identifier(&account),
+                balance: convert::amount_(balance, self.ledger.token_name())?,
+            });
+        }
+        Ok(ListNeuronsResponse { neurons })
+    }
+
     pub async fn neuron_info(
         &self,
         neuron_id: NeuronIdOrSubaccount,
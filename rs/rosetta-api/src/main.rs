@@ -3,10 +3,11 @@ use structopt::StructOpt;
 use ic_crypto_internal_threshold_sig_bls12381 as bls12_381;
 use ic_crypto_utils_threshold_sig::parse_threshold_sig_key;
 use ic_rosetta_api::rosetta_server::{RosettaApiServer, RosettaApiServerOpt};
+use ic_rosetta_api::store::{BlockStore, SQLiteStore};
 use ic_rosetta_api::{ledger_client, RosettaRequestHandler, DEFAULT_TOKEN_NAME};
 use ic_types::crypto::threshold_sig::ThresholdSigPublicKey;
 use ic_types::{CanisterId, PrincipalId};
-use std::{path::Path, path::PathBuf, str::FromStr, sync::Arc};
+use std::{path::PathBuf, str::FromStr, sync::Arc};
 use url::Url;
 
 #[derive(Debug, StructOpt)]
@@ -32,11 +33,20 @@ struct Opt {
     log_config_file: PathBuf,
     #[structopt(long = "root-key")]
     root_key: Option<PathBuf>,
-    /// Supported options: sqlite, sqlite-in-memory
+    /// Supported options: sqlite, sqlite-in-memory, postgres (only when
+    /// built with the postgres_backend feature)
     #[structopt(long = "store-type", default_value = "sqlite")]
     store_type: String,
     #[structopt(long = "store-location", default_value = "./data")]
     store_location: PathBuf,
+    /// Connection string of the database used by the postgres store.
+    #[structopt(long = "postgres-url")]
+    postgres_url: Option<String>,
+    /// Schema holding the tables of the postgres store. Instances using the
+    /// same schema share the synced blocks, but only one of them should set
+    /// --store-max-blocks.
+    #[structopt(long = "postgres-schema", default_value = "rosetta")]
+    postgres_schema: String,
     #[structopt(long = "store-max-blocks")]
     store_max_blocks: Option<u64>,
     #[structopt(long = "exit-on-sync")]
@@ -132,14 +142,34 @@ async fn main() -> std::io::Result<()> {
         (root_key, canister_id, governance_canister_id, url)
     };
 
-    let store_location: Option<&Path> = match opt.store_type.as_ref() {
-        "sqlite" => Some(&opt.store_location),
+    let block_store: Box<dyn BlockStore> = match opt.store_type.as_ref() {
+        "sqlite" => Box::new(
+            SQLiteStore::new_on_disk(&opt.store_location)
+                .expect("Failed to initialize sql store for ledger"),
+        ),
         "sqlite-in-memory" | "in-memory" => {
             log::info!("Using in-memory block store");
-            None
+            Box::new(
+                SQLiteStore::new_in_memory().expect("Failed to initialize sql store for ledger"),
+            )
+        }
+        #[cfg(feature = "postgres_backend")]
+        "postgres" => {
+            let url = opt
+                .postgres_url
+                .as_ref()
+                .expect("The postgres store type requires --postgres-url");
+            log::info!(
+                "Using postgres block store (schema {})",
+                opt.postgres_schema
+            );
+            Box::new(
+                ic_rosetta_api::store::postgres::PostgresStore::connect(url, &opt.postgres_schema)
+                    .expect("Failed to initialize postgres store for ledger"),
+            )
         }
         _ => {
-            log::error!("Invalid store type. Expected sqlite or sqlite-in-memory.");
+            log::error!("Invalid store type. Expected sqlite, sqlite-in-memory or postgres.");
             panic!("Invalid store type");
         }
     };
@@ -158,7 +188,7 @@ async fn main() -> std::io::Result<()> {
         canister_id,
         opt.token_name.unwrap_or_else(|| DEFAULT_TOKEN_NAME.to_string()),
         governance_canister_id,
-        store_location,
+        block_store,
         store_max_blocks,
         offline,
         root_key,
@@ -1,4 +1,4 @@
-use crate::request_types::TransactionOperationResults;
+use crate::request_types::{PublicKeyOrPrincipal, TransactionOperationResults};
 use crate::{
     convert::from_hex, errors, errors::ApiError, request_types::RequestType,
     transaction_id::TransactionIdentifier,
@@ -167,6 +167,11 @@ pub struct Allow {
     /// account at any height in the past should set this to true.
     #[serde(rename = "historical_balance_lookup")]
     pub historical_balance_lookup: bool,
+
+    /// All methods that are supported by the /call endpoint.
+    #[serde(rename = "call_methods")]
+    #[serde(default)]
+    pub call_methods: Vec<String>,
 }
 
 impl Allow {
@@ -175,12 +180,14 @@ impl Allow {
         operation_types: Vec<String>,
         errors: Vec<Error>,
         historical_balance_lookup: bool,
+        call_methods: Vec<String>,
     ) -> Allow {
         Allow {
             operation_statuses,
             operation_types,
             errors,
             historical_balance_lookup,
+            call_methods,
         }
     }
 }
@@ -2307,3 +2314,81 @@ pub struct NeuronInfoResponse {
     #[serde(rename = "created_timestamp_seconds")]
     pub created_timestamp_seconds: u64,
 }
+
+/// CallRequest is the input to the /call endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "conversion", derive(LabelledGeneric))]
+pub struct CallRequest {
+    #[serde(rename = "network_identifier")]
+    pub network_identifier: NetworkIdentifier,
+
+    /// The method to call, one of `Allow.call_methods`.
+    #[serde(rename = "method")]
+    pub method: String,
+
+    /// The parameters of the method, specific to each method.
+    #[serde(rename = "parameters")]
+    #[serde(default)]
+    pub parameters: Object,
+}
+
+/// CallResponse contains the result of a /call invocation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "conversion", derive(LabelledGeneric))]
+pub struct CallResponse {
+    /// The result of the method, specific to each method.
+    #[serde(rename = "result")]
+    pub result: Object,
+
+    /// Whether the result is always the same for the same parameters, so it
+    /// can be cached.
+    #[serde(rename = "idempotent")]
+    pub idempotent: bool,
+}
+
+impl CallResponse {
+    pub fn new(result: Object, idempotent: bool) -> CallResponse {
+        CallResponse { result, idempotent }
+    }
+}
+
+/// The parameters of the `list_neurons` call method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListNeuronsRequest {
+    /// The controller of the neurons.
+    #[serde(flatten)]
+    pub controller: PublicKeyOrPrincipal,
+
+    /// The first neuron index to look at.
+    #[serde(rename = "start_index")]
+    #[serde(default)]
+    pub start_index: u64,
+
+    /// The number of neuron indices to look at, starting from `start_index`.
+    #[serde(rename = "limit")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub limit: Option<u64>,
+}
+
+/// The result of the `list_neurons` call method.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListNeuronsResponse {
+    #[serde(rename = "neurons")]
+    pub neurons: Vec<NeuronAccount>,
+}
+
+/// A neuron whose staking account appears on the ledger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NeuronAccount {
+    #[serde(rename = "neuron_index")]
+    pub neuron_index: u64,
+
+    /// The ledger account of the neuron's stake.
+    #[serde(rename = "account_identifier")]
+    pub account_identifier: AccountIdentifier,
+
+    /// The balance of the neuron's staking account at the tip of the chain.
+    #[serde(rename = "balance")]
+    pub balance: Amount,
+}
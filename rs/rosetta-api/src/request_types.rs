@@ -35,6 +35,15 @@ pub const DISSOLVE_TIME_UTC_SECONDS: &str = "dissolve_time_utc_seconds";
 pub const ADD_HOT_KEY: &str = "ADD_HOT_KEY";
 pub const SPAWN: &str = "SPAWN";
 pub const MERGE_MATURITY: &str = "MERGE_MATURITY";
+pub const REMOVE_HOT_KEY: &str = "REMOVE_HOT_KEY";
+pub const FOLLOW: &str = "FOLLOW";
+pub const REGISTER_VOTE: &str = "REGISTER_VOTE";
+pub const SPLIT: &str = "SPLIT";
+pub const MERGE: &str = "MERGE";
+pub const DISBURSE_TO_NEURON: &str = "DISBURSE_TO_NEURON";
+/// The operation associated with `LedgerOperation::Approve`, which is only
+/// reported for blocks and can't be submitted through rosetta.
+pub const APPROVE: &str = "APPROVE";
 
 /// `RequestType` contains all supported values of `Operation.type`.
 /// Extra information, such as `neuron_index` should only be included
@@ -69,6 +78,24 @@ pub enum RequestType {
     #[serde(rename = "MERGE_MATURITY")]
     #[serde(alias = "MergeMaturity")]
     MergeMaturity { neuron_index: u64 },
+    #[serde(rename = "REMOVE_HOT_KEY")]
+    #[serde(alias = "RemoveHotKey")]
+    RemoveHotKey { neuron_index: u64 },
+    #[serde(rename = "FOLLOW")]
+    #[serde(alias = "Follow")]
+    Follow { neuron_index: u64 },
+    #[serde(rename = "REGISTER_VOTE")]
+    #[serde(alias = "RegisterVote")]
+    RegisterVote { neuron_index: u64 },
+    #[serde(rename = "SPLIT")]
+    #[serde(alias = "Split")]
+    Split { neuron_index: u64 },
+    #[serde(rename = "MERGE")]
+    #[serde(alias = "Merge")]
+    Merge { neuron_index: u64 },
+    #[serde(rename = "DISBURSE_TO_NEURON")]
+    #[serde(alias = "DisburseToNeuron")]
+    DisburseToNeuron { neuron_index: u64 },
 }
 
 impl RequestType {
@@ -83,6 +110,12 @@ impl RequestType {
             RequestType::AddHotKey { .. } => ADD_HOT_KEY,
             RequestType::Spawn { .. } => SPAWN,
             RequestType::MergeMaturity { .. } => MERGE_MATURITY,
+            RequestType::RemoveHotKey { .. } => REMOVE_HOT_KEY,
+            RequestType::Follow { .. } => FOLLOW,
+            RequestType::RegisterVote { .. } => REGISTER_VOTE,
+            RequestType::Split { .. } => SPLIT,
+            RequestType::Merge { .. } => MERGE,
+            RequestType::DisburseToNeuron { .. } => DISBURSE_TO_NEURON,
         }
     }
 
@@ -101,6 +134,12 @@ impl RequestType {
                 | RequestType::AddHotKey { .. }
                 | RequestType::Spawn { .. }
                 | RequestType::MergeMaturity { .. }
+                | RequestType::RemoveHotKey { .. }
+                | RequestType::Follow { .. }
+                | RequestType::RegisterVote { .. }
+                | RequestType::Split { .. }
+                | RequestType::Merge { .. }
+                | RequestType::DisburseToNeuron { .. }
         )
     }
 }
@@ -286,6 +325,10 @@ pub struct RequestResultMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub neuron_id: Option<u64>,
+    /// The neuron created by a `SPLIT` or `DISBURSE_TO_NEURON` operation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub created_neuron_id: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub transaction_identifier: Option<TransactionIdentifier>,
@@ -310,6 +353,7 @@ pub fn convert_to_request_result_metadata(rr: &RequestResult) -> RequestResultMe
     RequestResultMetadata {
         block_index: rr.block_index,
         neuron_id: rr.neuron_id,
+        created_neuron_id: rr.created_neuron_id,
         transaction_identifier: rr.transaction_identifier.clone(),
         response: rr.status.failed().map(|e| errors::convert_to_error(e)),
     }
@@ -325,6 +369,10 @@ pub struct RequestResult {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub neuron_id: Option<u64>,
+    /// The neuron created by a `SPLIT` or `DISBURSE_TO_NEURON` operation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub created_neuron_id: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub transaction_identifier: Option<TransactionIdentifier>,
@@ -398,6 +446,18 @@ pub enum Request {
     Spawn(Spawn),
     #[serde(rename = "MERGE_MATURITY")]
     MergeMaturity(MergeMaturity),
+    #[serde(rename = "REMOVE_HOT_KEY")]
+    RemoveHotKey(RemoveHotKey),
+    #[serde(rename = "FOLLOW")]
+    Follow(Follow),
+    #[serde(rename = "REGISTER_VOTE")]
+    RegisterVote(RegisterVote),
+    #[serde(rename = "SPLIT")]
+    Split(Split),
+    #[serde(rename = "MERGE")]
+    Merge(Merge),
+    #[serde(rename = "DISBURSE_TO_NEURON")]
+    DisburseToNeuron(DisburseToNeuron),
 }
 
 impl Request {
@@ -434,6 +494,14 @@ impl Request {
             Request::Transfer(LedgerOperation::Mint { .. }) => Err(ApiError::invalid_request(
                 "Mint operations are not supported through rosetta",
             )),
+            Request::Transfer(LedgerOperation::Approve { .. }) => Err(ApiError::invalid_request(
+                "Approve operations are not supported through rosetta",
+            )),
+            Request::Transfer(LedgerOperation::TransferFrom { .. }) => {
+                Err(ApiError::invalid_request(
+                    "TransferFrom operations are not supported through rosetta",
+                ))
+            }
             Request::Spawn(Spawn { neuron_index, .. }) => Ok(RequestType::Spawn {
                 neuron_index: *neuron_index,
             }),
@@ -442,6 +510,30 @@ impl Request {
                     neuron_index: *neuron_index,
                 })
             }
+            Request::RemoveHotKey(RemoveHotKey { neuron_index, .. }) => {
+                Ok(RequestType::RemoveHotKey {
+                    neuron_index: *neuron_index,
+                })
+            }
+            Request::Follow(Follow { neuron_index, .. }) => Ok(RequestType::Follow {
+                neuron_index: *neuron_index,
+            }),
+            Request::RegisterVote(RegisterVote { neuron_index, .. }) => {
+                Ok(RequestType::RegisterVote {
+                    neuron_index: *neuron_index,
+                })
+            }
+            Request::Split(Split { neuron_index, .. }) => Ok(RequestType::Split {
+                neuron_index: *neuron_index,
+            }),
+            Request::Merge(Merge { neuron_index, .. }) => Ok(RequestType::Merge {
+                neuron_index: *neuron_index,
+            }),
+            Request::DisburseToNeuron(DisburseToNeuron { neuron_index, .. }) => {
+                Ok(RequestType::DisburseToNeuron {
+                    neuron_index: *neuron_index,
+                })
+            }
         }
     }
 
@@ -465,6 +557,12 @@ impl Request {
                 Request::AddHotKey(o) => builder.add_hot_key(o),
                 Request::Spawn(o) => builder.spawn(o),
                 Request::MergeMaturity(o) => builder.merge_maturity(o),
+                Request::RemoveHotKey(o) => builder.remove_hot_key(o),
+                Request::Follow(o) => builder.follow(o),
+                Request::RegisterVote(o) => builder.register_vote(o),
+                Request::Split(o) => builder.split(o, token_name),
+                Request::Merge(o) => builder.merge(o),
+                Request::DisburseToNeuron(o) => builder.disburse_to_neuron(o, token_name),
             };
         }
         Ok(builder.build())
@@ -485,6 +583,12 @@ impl Request {
                 | Request::AddHotKey(_)
                 | Request::Spawn(_)
                 | Request::MergeMaturity(_)
+                | Request::RemoveHotKey(_)
+                | Request::Follow(_)
+                | Request::RegisterVote(_)
+                | Request::Split(_)
+                | Request::Merge(_)
+                | Request::DisburseToNeuron(_)
         )
     }
 }
@@ -652,6 +756,105 @@ impl TryFrom<&models::Request> for Request {
                     Err(ApiError::invalid_request("Invalid merge maturity request."))
                 }
             }
+            RequestType::RemoveHotKey { neuron_index } => {
+                if let Some(Command::Configure(Configure {
+                    operation:
+                        Some(configure::Operation::RemoveHotKey(manage_neuron::RemoveHotKey {
+                            hot_key_to_remove: Some(pid),
+                        })),
+                })) = manage_neuron()?
+                {
+                    Ok(Request::RemoveHotKey(RemoveHotKey {
+                        account,
+                        neuron_index: *neuron_index,
+                        key: PublicKeyOrPrincipal::Principal(pid),
+                    }))
+                } else {
+                    Err(ApiError::invalid_request(
+                        "Request is missing hotkey to remove.",
+                    ))
+                }
+            }
+            RequestType::Follow { neuron_index } => {
+                if let Some(Command::Follow(manage_neuron::Follow { topic, followees })) =
+                    manage_neuron()?
+                {
+                    Ok(Request::Follow(Follow {
+                        account,
+                        neuron_index: *neuron_index,
+                        topic,
+                        followees: followees.iter().map(|n| n.id).collect(),
+                    }))
+                } else {
+                    Err(ApiError::invalid_request("Invalid follow request."))
+                }
+            }
+            RequestType::RegisterVote { neuron_index } => {
+                if let Some(Command::RegisterVote(manage_neuron::RegisterVote {
+                    proposal: Some(proposal),
+                    vote,
+                })) = manage_neuron()?
+                {
+                    Ok(Request::RegisterVote(RegisterVote {
+                        account,
+                        neuron_index: *neuron_index,
+                        proposal: proposal.id,
+                        vote,
+                    }))
+                } else {
+                    Err(ApiError::invalid_request("Invalid register vote request."))
+                }
+            }
+            RequestType::Split { neuron_index } => {
+                if let Some(Command::Split(manage_neuron::Split { amount_e8s })) = manage_neuron()?
+                {
+                    Ok(Request::Split(Split {
+                        account,
+                        neuron_index: *neuron_index,
+                        amount: Tokens::from_e8s(amount_e8s),
+                    }))
+                } else {
+                    Err(ApiError::invalid_request("Invalid split request."))
+                }
+            }
+            RequestType::Merge { neuron_index } => {
+                if let Some(Command::Merge(manage_neuron::Merge {
+                    source_neuron_id: Some(source_neuron_id),
+                })) = manage_neuron()?
+                {
+                    Ok(Request::Merge(Merge {
+                        account,
+                        neuron_index: *neuron_index,
+                        source_neuron_id: source_neuron_id.id,
+                    }))
+                } else {
+                    Err(ApiError::invalid_request("Invalid merge request."))
+                }
+            }
+            RequestType::DisburseToNeuron { neuron_index } => {
+                if let Some(Command::DisburseToNeuron(manage_neuron::DisburseToNeuron {
+                    new_controller,
+                    amount_e8s,
+                    dissolve_delay_seconds,
+                    kyc_verified,
+                    nonce,
+                })) = manage_neuron()?
+                {
+                    Ok(Request::DisburseToNeuron(DisburseToNeuron {
+                        account,
+                        neuron_index: *neuron_index,
+                        amount: Tokens::from_e8s(amount_e8s),
+                        controller: new_controller,
+                        dissolve_delay_seconds,
+                        kyc_verified,
+                        child_neuron_index: nonce,
+                    }))
+                } else {
+                    Err(ApiError::invalid_request(
+                        "Invalid disburse to neuron request.",
+                    ))
+                }
+            }
         }
     }
 }
@@ -702,6 +905,12 @@ mod send {
                 LedgerOperation::Mint { .. } => {
                     Err("Mint operations are not supported through rosetta".to_owned())
                 }
+                LedgerOperation::Approve { .. } => {
+                    Err("Approve operations are not supported through rosetta".to_owned())
+                }
+                LedgerOperation::TransferFrom { .. } => {
+                    Err("TransferFrom operations are not supported through rosetta".to_owned())
+                }
             }
         }
     }
@@ -786,6 +995,69 @@ pub struct MergeMaturity {
     pub neuron_index: u64,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct RemoveHotKey {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_index: u64,
+    pub key: PublicKeyOrPrincipal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Follow {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_index: u64,
+    /// The governance `Topic`, as its numeric value.
+    pub topic: i32,
+    /// The ids of the followed neurons. An empty list removes the followees
+    /// of the topic.
+    pub followees: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RegisterVote {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_index: u64,
+    pub proposal: u64,
+    /// The governance `Vote`, as its numeric value.
+    pub vote: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Split {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_index: u64,
+    /// The stake of the new neuron, including the transaction fee.
+    pub amount: Tokens,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Merge {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_index: u64,
+    /// The neuron that is merged into the neuron with `neuron_index`.
+    pub source_neuron_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DisburseToNeuron {
+    pub account: ledger_canister::AccountIdentifier,
+    #[serde(default)]
+    pub neuron_index: u64,
+    /// The stake of the new neuron, including the transaction fee.
+    pub amount: Tokens,
+    pub controller: Option<PrincipalId>,
+    pub dissolve_delay_seconds: u64,
+    pub kyc_verified: bool,
+    /// Differentiates between the neurons of the new controller, like
+    /// `neuron_index`.
+    pub child_neuron_index: u64,
+}
+
 #[derive(Debug, Clone, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PublicKeyOrPrincipal {
@@ -854,6 +1126,44 @@ impl TryFrom<Option<Object>> for SetDissolveTimestampMetadata {
     }
 }
 
+/// The metadata of an `APPROVE` operation.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ApproveMetadata {
+    /// The hex encoded account identifier of the spender.
+    pub spender: String,
+    pub allowance_e8s: u64,
+    /// The number of nanoseconds since Unix epoch after which the allowance
+    /// can't be used.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+}
+
+impl From<ApproveMetadata> for Object {
+    fn from(m: ApproveMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The metadata of the debit operation of a transfer made by a spender on
+/// behalf of the owner of the debited account.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TransferFromMetadata {
+    /// The hex encoded account identifier of the spender.
+    pub spender: String,
+}
+
+impl From<TransferFromMetadata> for Object {
+    fn from(m: TransferFromMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize, Serialize)]
 pub struct NeuronIdentifierMetadata {
     #[serde(default)]
@@ -1050,6 +1360,131 @@ impl From<MergeMaturityMetadata> for Object {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FollowMetadata {
+    pub topic: i32,
+    #[serde(default)]
+    pub followees: Vec<u64>,
+    #[serde(default)]
+    pub neuron_index: u64,
+}
+
+impl TryFrom<Option<Object>> for FollowMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse FOLLOW operation metadata from a JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<FollowMetadata> for Object {
+    fn from(m: FollowMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RegisterVoteMetadata {
+    pub proposal_id: u64,
+    pub vote: i32,
+    #[serde(default)]
+    pub neuron_index: u64,
+}
+
+impl TryFrom<Option<Object>> for RegisterVoteMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse REGISTER_VOTE operation metadata from a JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<RegisterVoteMetadata> for Object {
+    fn from(m: RegisterVoteMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MergeMetadata {
+    pub source_neuron_id: u64,
+    #[serde(default)]
+    pub neuron_index: u64,
+}
+
+impl TryFrom<Option<Object>> for MergeMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse MERGE operation metadata from a JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<MergeMetadata> for Object {
+    fn from(m: MergeMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DisburseToNeuronMetadata {
+    #[serde(default)]
+    pub neuron_index: u64,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub controller: Option<PrincipalId>,
+    pub dissolve_delay_seconds: u64,
+    #[serde(default)]
+    pub kyc_verified: bool,
+    pub child_neuron_index: u64,
+}
+
+impl TryFrom<Option<Object>> for DisburseToNeuronMetadata {
+    type Error = ApiError;
+
+    fn try_from(o: Option<Object>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse DISBURSE_TO_NEURON operation metadata from a JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<DisburseToNeuronMetadata> for Object {
+    fn from(m: DisburseToNeuronMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
 /// Transaction is a bit of a misnomer, since operations can succeed or fail
 /// independently from a Transaction.
 pub struct TransactionBuilder {
@@ -1084,7 +1519,7 @@ impl TransactionBuilder {
     }
 
     /// Add a `Request::Transfer` to the Transaction.
-    /// This handles `Send`, `Mint`, and `Burn`.
+    /// This handles `Send`, `Mint`, `Burn`, `Approve` and `TransferFrom`.
     pub fn transfer(
         &mut self,
         operation: &LedgerOperation,
@@ -1160,6 +1595,93 @@ impl TransactionBuilder {
                     metadata: None,
                 });
             }
+            LedgerOperation::Approve {
+                from,
+                spender,
+                allowance,
+                expires_at,
+                fee,
+            } => {
+                let from_account = Some(to_model_account_identifier(from));
+                let operation_identifier = self.allocate_op_id();
+                self.ops.push(Operation {
+                    operation_identifier,
+                    _type: APPROVE.to_string(),
+                    status: None,
+                    account: from_account.clone(),
+                    amount: None,
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        ApproveMetadata {
+                            spender: spender.to_hex(),
+                            allowance_e8s: allowance.get_e8s(),
+                            expires_at: expires_at.map(|t| t.as_nanos_since_unix_epoch()),
+                        }
+                        .into(),
+                    ),
+                });
+                let operation_identifier = self.allocate_op_id();
+                self.ops.push(Operation {
+                    operation_identifier,
+                    _type: FEE.to_string(),
+                    status: None,
+                    account: from_account,
+                    amount: Some(signed_amount(-(fee.get_e8s() as i128), token_name)),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: None,
+                });
+            }
+            LedgerOperation::TransferFrom {
+                from,
+                to,
+                spender,
+                amount,
+                fee,
+            } => {
+                let from_account = Some(to_model_account_identifier(from));
+                let amount = i128::from(amount.get_e8s());
+
+                let operation_identifier = self.allocate_op_id();
+                self.ops.push(Operation {
+                    operation_identifier,
+                    _type: TRANSACTION.to_string(),
+                    status: None,
+                    account: from_account.clone(),
+                    amount: Some(signed_amount(-amount, token_name)),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: Some(
+                        TransferFromMetadata {
+                            spender: spender.to_hex(),
+                        }
+                        .into(),
+                    ),
+                });
+                let operation_identifier = self.allocate_op_id();
+                self.ops.push(Operation {
+                    operation_identifier,
+                    _type: TRANSACTION.to_string(),
+                    status: None,
+                    account: Some(to_model_account_identifier(to)),
+                    amount: Some(signed_amount(amount, token_name)),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: None,
+                });
+                let operation_identifier = self.allocate_op_id();
+                self.ops.push(Operation {
+                    operation_identifier,
+                    _type: FEE.to_string(),
+                    status: None,
+                    account: from_account,
+                    amount: Some(signed_amount(-(fee.get_e8s() as i128), token_name)),
+                    related_operations: None,
+                    coin_change: None,
+                    metadata: None,
+                });
+            }
         };
         Ok(())
     }
@@ -1359,4 +1881,164 @@ impl TransactionBuilder {
             ),
         });
     }
+
+    pub fn remove_hot_key(&mut self, key: &RemoveHotKey) {
+        let RemoveHotKey {
+            account,
+            neuron_index,
+            key,
+        } = key;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: REMOVE_HOT_KEY.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: None,
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                KeyMetadata {
+                    key: key.clone(),
+                    neuron_index: *neuron_index,
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn follow(&mut self, follow: &Follow) {
+        let Follow {
+            account,
+            neuron_index,
+            topic,
+            followees,
+        } = follow;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: FOLLOW.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: None,
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                FollowMetadata {
+                    topic: *topic,
+                    followees: followees.clone(),
+                    neuron_index: *neuron_index,
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn register_vote(&mut self, vote: &RegisterVote) {
+        let RegisterVote {
+            account,
+            neuron_index,
+            proposal,
+            vote,
+        } = vote;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: REGISTER_VOTE.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: None,
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                RegisterVoteMetadata {
+                    proposal_id: *proposal,
+                    vote: *vote,
+                    neuron_index: *neuron_index,
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn split(&mut self, split: &Split, token_name: &str) {
+        let Split {
+            account,
+            neuron_index,
+            amount,
+        } = split;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: SPLIT.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: Some(amount_(*amount, token_name).expect("failed to convert amount")),
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                NeuronIdentifierMetadata {
+                    neuron_index: *neuron_index,
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn merge(&mut self, merge: &Merge) {
+        let Merge {
+            account,
+            neuron_index,
+            source_neuron_id,
+        } = merge;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: MERGE.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: None,
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                MergeMetadata {
+                    source_neuron_id: *source_neuron_id,
+                    neuron_index: *neuron_index,
+                }
+                .into(),
+            ),
+        });
+    }
+
+    pub fn disburse_to_neuron(&mut self, disburse: &DisburseToNeuron, token_name: &str) {
+        let DisburseToNeuron {
+            account,
+            neuron_index,
+            amount,
+            controller,
+            dissolve_delay_seconds,
+            kyc_verified,
+            child_neuron_index,
+        } = disburse;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: DISBURSE_TO_NEURON.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: Some(amount_(*amount, token_name).expect("failed to convert amount")),
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                DisburseToNeuronMetadata {
+                    neuron_index: *neuron_index,
+                    controller: *controller,
+                    dissolve_delay_seconds: *dissolve_delay_seconds,
+                    kyc_verified: *kyc_verified,
+                    child_neuron_index: *child_neuron_index,
+                }
+                .into(),
+            ),
+        });
+    }
 }
@@ -153,6 +153,19 @@ async fn block_transaction(
     to_rosetta_response(res)
 }
 
+#[post("/call")]
+async fn call(
+    msg: web::Json<CallRequest>,
+    req_handler: web::Data<RosettaRequestHandler>,
+) -> HttpResponse {
+    let _timer = ENDPOINTS_METRICS
+        .request_duration
+        .with_label_values(&["call"])
+        .start_timer();
+    let res = req_handler.call(msg.into_inner()).await;
+    to_rosetta_response(res)
+}
+
 #[post("/construction/combine")]
 async fn construction_combine(
     msg: web::Json<ConstructionCombineRequest>,
@@ -326,6 +339,7 @@ impl RosettaApiServer {
                 .service(account_balance)
                 .service(block)
                 .service(block_transaction)
+                .service(call)
                 .service(construction_combine)
                 .service(construction_derive)
                 .service(construction_hash)
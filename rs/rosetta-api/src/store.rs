@@ -1,16 +1,21 @@
 use log::debug;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::path::Path;
 use std::sync::Mutex;
 
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 
-use ledger_canister::{AccountIdentifier, BlockHeight, EncodedBlock, HashOf, Tokens};
+use ledger_canister::{
+    AccountIdentifier, Balances, BlockHeight, EncodedBlock, HashOf, Operation, Tokens, Transaction,
+};
 
-use crate::balance_book::BalanceBook;
 use crate::errors::ApiError;
 
+#[cfg(feature = "postgres_backend")]
+pub mod postgres;
+
 #[derive(candid::CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct HashedBlock {
     pub block: EncodedBlock,
@@ -55,6 +60,130 @@ impl From<BlockStoreError> for ApiError {
     }
 }
 
+/// Persistent storage of the blocks synced from the ledger.
+///
+/// Besides the blocks, a store keeps the index of the blocks by transaction
+/// hash and the balance history of every account the blocks involve. When the
+/// store is pruned, the balances at the oldest block that is kept are saved
+/// with that block, so that balances can still be queried from there on.
+pub trait BlockStore: Send + Sync {
+    fn get_at(&self, index: BlockHeight) -> Result<HashedBlock, BlockStoreError>;
+
+    fn get_range(
+        &self,
+        range: std::ops::Range<BlockHeight>,
+    ) -> Result<Vec<HashedBlock>, BlockStoreError>;
+
+    /// Returns the index of the block containing the transaction with the
+    /// given hash, if the store holds such a block.
+    fn get_transaction_index(
+        &self,
+        tx_hash: &HashOf<Transaction>,
+    ) -> Result<Option<BlockHeight>, BlockStoreError>;
+
+    /// Returns the balance of the account after the block at `index`, or
+    /// `None` if no block up to `index` involves the account.
+    fn get_account_balance(
+        &self,
+        account: &AccountIdentifier,
+        index: BlockHeight,
+    ) -> Result<Option<Tokens>, BlockStoreError>;
+
+    /// Returns the indices of the blocks up to `max_block` that involve the
+    /// account, each with the balance of the account after that block, in
+    /// ascending order. Once the store is pruned, the oldest block that is
+    /// kept is not included, as its entries hold the saved balances.
+    fn get_account_history(
+        &self,
+        account: &AccountIdentifier,
+        max_block: Option<BlockHeight>,
+    ) -> Result<Vec<(BlockHeight, Tokens)>, BlockStoreError>;
+
+    /// Pushes the block and updates the balances of the accounts it involves.
+    /// Fails if the block can't be applied to these balances.
+    fn push(&mut self, hb: HashedBlock) -> Result<(), BlockStoreError>;
+
+    /// Pushes all blocks of the batch or none of them.
+    fn push_batch(&mut self, batch: Vec<HashedBlock>) -> Result<(), BlockStoreError>;
+
+    /// Removes all blocks below `hb` except for the genesis block, and saves
+    /// the account balances at `hb`.
+    fn prune(&mut self, hb: &HashedBlock) -> Result<(), BlockStoreError>;
+
+    /// Returns the oldest block that is not pruned (the genesis block if the
+    /// store was never pruned).
+    fn first(&self) -> Result<Option<HashedBlock>, BlockStoreError>;
+
+    fn last_verified(&self) -> Option<BlockHeight>;
+
+    fn mark_last_verified(&mut self, block_height: BlockHeight) -> Result<(), BlockStoreError>;
+
+    /// Returns the oldest block that is not pruned, provided it is verified.
+    fn first_verified(&self) -> Result<Option<HashedBlock>, BlockStoreError> {
+        let last_verified_idx = self.last_verified().map(|x| x as i128).unwrap_or(-1);
+        let first_block = self.first()?;
+        if let Some(fb) = first_block.as_ref() {
+            if fb.index as i128 > last_verified_idx {
+                return Ok(None);
+            }
+        }
+        Ok(first_block)
+    }
+}
+
+fn decode_transaction(hb: &HashedBlock) -> Result<Transaction, BlockStoreError> {
+    hb.block
+        .decode()
+        .map(|block| block.transaction)
+        .map_err(|e| BlockStoreError::Other(format!("Failed to decode block {}: {}", hb.index, e)))
+}
+
+/// Applies the operation to the balances of the accounts it involves, which
+/// are read with `balance`, and returns the new balances of these accounts.
+fn apply_operation<F>(
+    operation: &Operation,
+    mut balance: F,
+) -> Result<Vec<(AccountIdentifier, Tokens)>, BlockStoreError>
+where
+    F: FnMut(&AccountIdentifier) -> Result<Tokens, BlockStoreError>,
+{
+    let mut accounts = match operation {
+        Operation::Burn { from, .. } | Operation::Approve { from, .. } => vec![*from],
+        Operation::Mint { to, .. } => vec![*to],
+        Operation::Transfer { from, to, .. } | Operation::TransferFrom { from, to, .. } => {
+            vec![*from, *to]
+        }
+    };
+    accounts.dedup();
+
+    let mut balances = Balances::<HashMap<AccountIdentifier, Tokens>>::default();
+    for account in &accounts {
+        balances.credit(account, balance(account)?);
+    }
+    // The tokens held by other accounts are not known, but they don't matter:
+    // the pool only needs to be large enough for minting and small enough to
+    // collect fees and burnt tokens without overflowing.
+    let mut held = Tokens::ZERO;
+    for account in &accounts {
+        held += balances.account_balance(account);
+    }
+    balances.token_pool = (Tokens::MAX - held).map_err(BlockStoreError::Other)?;
+
+    balances
+        .add_payment(operation)
+        .map_err(|e| BlockStoreError::Other(format!("Failed to apply {:?}: {:?}", operation, e)))?;
+    Ok(accounts
+        .into_iter()
+        .map(|account| (account, balances.account_balance(&account)))
+        .collect())
+}
+
+/// Converts a block index to a database integer. Indices beyond the range of
+/// the database are above any stored block.
+fn db_index(index: BlockHeight) -> i64 {
+    index.min(i64::MAX as u64) as i64
+}
+
 fn vec_into_array(v: Vec<u8>) -> [u8; 32] {
     let ba: Box<[u8; 32]> = match v.into_boxed_slice().try_into() {
         Ok(ba) => ba,
@@ -65,8 +194,6 @@ fn vec_into_array(v: Vec<u8>) -> [u8; 32] {
 
 pub struct SQLiteStore {
     connection: Mutex<rusqlite::Connection>,
-    base_idx: u64,
-    first_block: Option<HashedBlock>,
     last_verified_idx: Option<BlockHeight>,
 }
 
@@ -91,8 +218,6 @@ impl SQLiteStore {
     fn new(connection: rusqlite::Connection) -> Result<Self, BlockStoreError> {
         let mut store = Self {
             connection: Mutex::new(connection),
-            base_idx: 0,
-            first_block: None,
             last_verified_idx: None,
         };
         store
@@ -104,21 +229,8 @@ impl SQLiteStore {
         store.create_tables().map_err(|e| {
             BlockStoreError::Other(format!("Failed to initialize SQLite database: {}", e))
         })?;
-        store.first_block = store
-            .read_oldest_block_snapshot()
-            .map_err(BlockStoreError::Other)?
-            .map(|x| x.0);
-
-        if let Some(first_block) = &store.first_block {
-            store.base_idx = first_block.index;
-            store.get_at(first_block.index).and_then(|b| {
-                if *first_block != b {
-                    Err(BlockStoreError::Other("Corrupted snapshot".to_string()))
-                } else {
-                    Ok(())
-                }
-            })?;
-        }
+        store.index_transactions().map_err(BlockStoreError::Other)?;
+        store.index_balances()?;
 
         // Read last verified index (if any).
         {
@@ -151,7 +263,8 @@ impl SQLiteStore {
             "#,
             [],
         )?;
-        // Table of pool balance on each block, used for sanity checks.
+        // Table of pool balance on each block the store was pruned at, used
+        // for sanity checks.
         connection.execute(
             r#"
             CREATE TABLE IF NOT EXISTS balances (
@@ -162,7 +275,9 @@ impl SQLiteStore {
             "#,
             [],
         )?;
-        // Table of account book balances on each block.
+        // Table of account book balances on each block the store was pruned
+        // at. It is only read to fill the account_balances table of databases
+        // created before that table existed.
         connection.execute(
             r#"
             CREATE TABLE IF NOT EXISTS books (
@@ -176,10 +291,42 @@ impl SQLiteStore {
             "#,
             [],
         )?;
+        // Table of the balance of each account after each block involving
+        // it, and after the block the store was pruned at.
+        connection.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS account_balances (
+                account VARCHAR(64) NOT NULL,
+                block_idx INTEGER NOT NULL,
+                tokens INTEGER NOT NULL,
+                PRIMARY KEY(account,block_idx),
+                FOREIGN KEY(block_idx) REFERENCES blocks(idx)
+            )
+            "#,
+            [],
+        )?;
+        // Table of the block containing each transaction, used for searching
+        // transactions by hash.
+        connection.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS transactions (
+                tx_hash BLOB NOT NULL PRIMARY KEY,
+                block_idx INTEGER NOT NULL,
+                FOREIGN KEY(block_idx) REFERENCES blocks(idx)
+            )
+            "#,
+            [],
+        )?;
+        connection.execute(
+            "CREATE INDEX IF NOT EXISTS transactions_block_idx ON transactions(block_idx)",
+            [],
+        )?;
         Ok(())
     }
 
-    fn execute_push(connection: &mut Connection, hb: HashedBlock) -> Result<(), BlockStoreError> {
+    fn execute_push(connection: &Connection, hb: HashedBlock) -> Result<(), BlockStoreError> {
+        let transaction = decode_transaction(&hb)?;
+        let tx_hash = transaction.hash().into_bytes().to_vec();
         let hash = hb.hash.into_bytes().to_vec();
         let parent_hash = hb.parent_hash.map(|ph| ph.into_bytes().to_vec());
         connection
@@ -188,197 +335,204 @@ impl SQLiteStore {
                 params![hash, hb.block.0, parent_hash, hb.index],
             )
             .map_err(|e| BlockStoreError::Other(e.to_string()))?;
-        Ok(())
-    }
-
-    fn read_oldest_block_snapshot(&self) -> Result<Option<(HashedBlock, BalanceBook)>, String> {
-        let mut balance_book = BalanceBook::default();
-        let last_index;
-        {
-            let mut con = self.connection.lock().unwrap();
-            let tx = con.transaction().expect("Cannot open transaction");
-
-            // Read last balance.
-            {
-                let mut stmt = tx
-                    .prepare(
-                        "SELECT block_idx,icpt_pool FROM balances ORDER BY block_idx DESC LIMIT 1",
-                    )
-                    .map_err(|e| e.to_string())?;
-                let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
-                let next = rows.next().map_err(|e| e.to_string())?;
-                if next.is_none() {
-                    // No existing snapshot.
-                    return Ok(None);
-                }
-                let row = next.unwrap();
-                last_index = row.get(0).expect("Expected index.");
-
-                // Convert back pool from i64 to u64.
-                let pool: i64 = row.get(1).expect("Expected icpt pool.");
-                let pool = pool as u64;
-                balance_book.token_pool = Tokens::from_e8s(pool);
-            }
-
-            // Read last books.
-            {
-                let mut stmt = tx
-                    .prepare("SELECT block_idx,account,icpt,num_pruned_transactions FROM books WHERE block_idx=?")
-                    .map_err(|e| e.to_string())?;
-                let mut rows = stmt.query(params![last_index]).map_err(|e| e.to_string())?;
-                while let Some(row) = rows.next().unwrap() {
-                    let acc_hex: String = row.get(1).expect("Expected account identifier.");
-                    let acc_id = AccountIdentifier::from_hex(&acc_hex).unwrap();
-                    let icpt = row.get(2).expect("Expected tokens.");
-                    balance_book
-                        .store
-                        .insert(acc_id, last_index, Tokens::from_e8s(icpt));
-                    balance_book
-                        .store
-                        .acc_to_hist
-                        .get_mut(&acc_id)
-                        .expect("Expected history for account.")
-                        .num_pruned_transactions = row.get(3).unwrap();
-                }
-
-                SQLiteStore::sanity_check(&balance_book).map_err(|e| e)?;
-            }
-        }
-
-        let last_block = self.get_at(last_index);
-        Ok(Some((last_block.unwrap(), balance_book)))
+        // If a transaction occurs more than once, searching it returns the
+        // most recent block.
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO transactions (tx_hash, block_idx) VALUES (?1, ?2)",
+                params![tx_hash, hb.index],
+            )
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        Self::execute_apply(connection, &transaction.operation, hb.index)
     }
 
-    /// Sanity check (sum of tokens equal pool size).
-    fn sanity_check(balance_book: &BalanceBook) -> Result<(), String> {
-        let mut sum_icpt = Tokens::ZERO;
-        for acc in balance_book.store.acc_to_hist.keys() {
-            sum_icpt += balance_book.account_balance(acc);
-        }
-        let expected_icpt_pool = (Tokens::MAX - sum_icpt).unwrap();
-        if expected_icpt_pool != balance_book.token_pool {
-            return Err(format!(
-                "Incorrect ICPT pool value in the snapshot (expected: {}, got: {})",
-                expected_icpt_pool, balance_book.token_pool
-            ));
+    /// Records the balances of the accounts involved in the operation of the
+    /// block at `index`.
+    fn execute_apply(
+        connection: &Connection,
+        operation: &Operation,
+        index: BlockHeight,
+    ) -> Result<(), BlockStoreError> {
+        let balances = apply_operation(operation, |account| {
+            Ok(Self::read_balance(connection, account, index)?.unwrap_or(Tokens::ZERO))
+        })?;
+        for (account, tokens) in balances {
+            connection
+                .execute(
+                    "INSERT INTO account_balances (account, block_idx, tokens) VALUES (?1, ?2, ?3)",
+                    params![account.to_hex(), index, tokens.get_e8s() as i64],
+                )
+                .map_err(|e| BlockStoreError::Other(e.to_string()))?;
         }
         Ok(())
     }
 
-    fn write_oldest_block_snapshot(
-        &mut self,
-        hb: &HashedBlock,
-        balances: &BalanceBook,
-    ) -> Result<(), String> {
-        if let Ok(Some(b)) = self.first() {
-            // this check is made in upper levels, but for readability:
-            assert!(
-                b.index <= hb.index,
-                "Oldest: {}, new oldest: {}",
-                b.index,
-                hb.index
-            );
-        }
-
-        debug!("Writing oldest block snapshot ({})", hb.index);
+    fn read_balance(
+        connection: &Connection,
+        account: &AccountIdentifier,
+        index: BlockHeight,
+    ) -> Result<Option<Tokens>, BlockStoreError> {
+        connection
+            .query_row(
+                "SELECT tokens FROM account_balances WHERE account = ?1 AND block_idx <= ?2 ORDER BY block_idx DESC LIMIT 1",
+                params![account.to_hex(), db_index(index)],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .map(|tokens| tokens.map(|e8s| Tokens::from_e8s(e8s as u64)))
+            .map_err(|e| BlockStoreError::Other(e.to_string()))
+    }
 
-        // NB: storing the index of block should be enough, no need to store the
-        // hashedblock itself.
+    /// Returns the index of the block the store was last pruned at, if any.
+    fn read_pruned_at(connection: &Connection) -> Result<Option<BlockHeight>, BlockStoreError> {
+        connection
+            .query_row("SELECT MAX(block_idx) FROM balances", [], |row| row.get(0))
+            .map_err(|e| BlockStoreError::Other(e.to_string()))
+    }
 
+    /// Fills the transactions table if the database was created before the
+    /// table existed.
+    fn index_transactions(&self) -> Result<(), String> {
         let mut con = self.connection.lock().unwrap();
-        let tx = con.transaction().expect("Cannot open transaction");
-
-        // Build balances.
-        let mut balances_snapshot = BalanceBook::default();
-        for (acc, hist) in balances.store.acc_to_hist.iter() {
-            // It's safe to unwrap here: this can only fail if hb.index is below the index
-            // of the first block. We validate this invariant in the beginning
-            // of this function.
-            let amount = hist.get_at(hb.index).unwrap();
-            balances_snapshot.token_pool -= amount;
-            balances_snapshot.store.insert(*acc, hb.index, amount);
+        let num_transactions: i64 = con
+            .query_row("SELECT COUNT(*) FROM transactions", [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if num_transactions > 0 {
+            return Ok(());
         }
-
-        // We need to convert to i64 because SQLite only supports signed integers,
-        // and Rusqlite doesn't like overflows when converting u64 to i64.
-        let pool = balances_snapshot.token_pool.get_e8s() as i64;
-
-        tx.execute(
-            "INSERT INTO balances(block_idx,icpt_pool) VALUES (?1,?2)",
-            params![hb.index, pool],
-        )
-        .map_err(|e| e.to_string())?;
-
+        let tx = con.transaction().map_err(|e| e.to_string())?;
         {
-            let mut stmt = tx.prepare("INSERT INTO books(block_idx,account,icpt,num_pruned_transactions) VALUES (?1,?2,?3,?4)")
-                .expect("Couldn't prepare statement");
-            for (acc, history) in balances.store.acc_to_hist.iter() {
-                let icpts = history.get_at(hb.index).unwrap();
-                let num_pruned = history.num_pruned_transactions;
-                stmt.execute(params![hb.index, acc.to_hex(), icpts.get_e8s(), num_pruned])
+            let mut select = tx
+                .prepare("SELECT block, idx FROM blocks ORDER BY idx")
+                .map_err(|e| e.to_string())?;
+            let mut insert = tx
+                .prepare("INSERT OR REPLACE INTO transactions (tx_hash, block_idx) VALUES (?1, ?2)")
+                .map_err(|e| e.to_string())?;
+            let mut rows = select.query([]).map_err(|e| e.to_string())?;
+            while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+                let block: Vec<u8> = row.get(0).map_err(|e| e.to_string())?;
+                let index: BlockHeight = row.get(1).map_err(|e| e.to_string())?;
+                let tx_hash = EncodedBlock::from(block.into_boxed_slice())
+                    .decode()?
+                    .transaction
+                    .hash();
+                insert
+                    .execute(params![tx_hash.into_bytes().to_vec(), index])
                     .map_err(|e| e.to_string())?;
             }
         }
-
-        tx.commit().map_err(|e| e.to_string())?;
-
-        self.first_block = Option::Some(hb.clone());
-        Ok(())
+        tx.commit().map_err(|e| e.to_string())
     }
 
-    pub fn get_at(&self, index: BlockHeight) -> Result<HashedBlock, BlockStoreError> {
-        if 0 < index && index < self.base_idx {
-            return Err(BlockStoreError::NotAvailable(index));
+    /// Fills the account_balances table if the database was created before
+    /// the table existed, starting from the books saved by the last pruning.
+    fn index_balances(&self) -> Result<(), BlockStoreError> {
+        let mut con = self.connection.lock().unwrap();
+        let num_balances: i64 = con
+            .query_row("SELECT COUNT(*) FROM account_balances", [], |row| {
+                row.get(0)
+            })
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        if num_balances > 0 {
+            return Ok(());
+        }
+        let tx = con
+            .transaction()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        let pruned_at = Self::read_pruned_at(&tx)?;
+        if let Some(index) = pruned_at {
+            tx.execute(
+                "INSERT INTO account_balances (account, block_idx, tokens) SELECT account, block_idx, icpt FROM books WHERE block_idx = ?1",
+                params![index],
+            )
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
         }
+        {
+            let mut select = tx
+                .prepare("SELECT block, idx FROM blocks WHERE idx > ?1 ORDER BY idx")
+                .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+            let after = pruned_at.map(|index| index as i64).unwrap_or(-1);
+            let mut rows = select
+                .query(params![after])
+                .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+            while let Some(row) = rows
+                .next()
+                .map_err(|e| BlockStoreError::Other(e.to_string()))?
+            {
+                let block: Vec<u8> = row
+                    .get(0)
+                    .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+                let index: BlockHeight = row
+                    .get(1)
+                    .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+                let operation = EncodedBlock::from(block.into_boxed_slice())
+                    .decode()
+                    .map_err(BlockStoreError::Other)?
+                    .transaction
+                    .operation;
+                Self::execute_apply(&tx, &operation, index)?;
+            }
+        }
+        tx.commit()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))
+    }
+}
 
+fn hashed_block(row: &rusqlite::Row) -> Result<HashedBlock, rusqlite::Error> {
+    Ok(HashedBlock {
+        hash: row.get(0).map(|bytes| HashOf::new(vec_into_array(bytes)))?,
+        block: row
+            .get(1)
+            .map(|bytes: Vec<u8>| EncodedBlock::from(bytes.into_boxed_slice()))?,
+        parent_hash: row.get(2).map(|opt_bytes: Option<Vec<u8>>| {
+            opt_bytes.map(|bytes| HashOf::new(vec_into_array(bytes)))
+        })?,
+        index: row.get(3)?,
+    })
+}
+
+impl BlockStore for SQLiteStore {
+    fn get_at(&self, index: BlockHeight) -> Result<HashedBlock, BlockStoreError> {
         let connection = self.connection.lock().unwrap();
-        let mut stmt = connection
-            .prepare("SELECT hash, block, parent_hash, idx FROM blocks WHERE idx = ?")
-            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
-        let mut blocks = stmt
-            .query_map(params![index], |row| {
-                Ok(HashedBlock {
-                    hash: row.get(0).map(|bytes| HashOf::new(vec_into_array(bytes)))?,
-                    block: row
-                        .get(1)
-                        .map(|bytes: Vec<u8>| EncodedBlock::from(bytes.into_boxed_slice()))?,
-                    parent_hash: row.get(2).map(|opt_bytes: Option<Vec<u8>>| {
-                        opt_bytes.map(|bytes| HashOf::new(vec_into_array(bytes)))
-                    })?,
-                    index: row.get(3)?,
-                })
-            })
+        let block = connection
+            .query_row(
+                "SELECT hash, block, parent_hash, idx FROM blocks WHERE idx = ?",
+                params![index],
+                hashed_block,
+            )
+            .optional()
             .map_err(|e| BlockStoreError::Other(e.to_string()))?;
-        blocks
-            .next()
-            .ok_or(BlockStoreError::NotFound(index))
-            .map(|block| block.unwrap())
+        match block {
+            Some(hb) => Ok(hb),
+            // The pruning base is read from the database, as another process
+            // may have pruned it.
+            None if 0 < index
+                && Self::read_pruned_at(&connection)?.map_or(false, |base| index < base) =>
+            {
+                Err(BlockStoreError::NotAvailable(index))
+            }
+            None => Err(BlockStoreError::NotFound(index)),
+        }
     }
 
-    pub fn get_range(
+    fn get_range(
         &self,
         range: std::ops::Range<BlockHeight>,
     ) -> Result<Vec<HashedBlock>, BlockStoreError> {
-        if 0 < range.start && range.start < self.base_idx {
+        let connection = self.connection.lock().unwrap();
+        if 0 < range.start
+            && Self::read_pruned_at(&connection)?.map_or(false, |base| range.start < base)
+        {
             return Err(BlockStoreError::NotAvailable(range.start));
         }
-        let connection = self.connection.lock().unwrap();
         let mut stmt = connection
             .prepare("SELECT hash, block, parent_hash, idx FROM blocks WHERE idx >= ? AND idx < ?")
             .map_err(|e| BlockStoreError::Other(e.to_string()))?;
         let mut blocks = stmt
-            .query_map(params![range.start, range.end], |row| {
-                Ok(HashedBlock {
-                    hash: row.get(0).map(|bytes| HashOf::new(vec_into_array(bytes)))?,
-                    block: row
-                        .get(1)
-                        .map(|bytes: Vec<u8>| EncodedBlock::from(bytes.into_boxed_slice()))?,
-                    parent_hash: row.get(2).map(|opt_bytes: Option<Vec<u8>>| {
-                        opt_bytes.map(|bytes| HashOf::new(vec_into_array(bytes)))
-                    })?,
-                    index: row.get(3)?,
-                })
-            })
+            .query_map(
+                params![db_index(range.start), db_index(range.end)],
+                hashed_block,
+            )
             .map_err(|e| BlockStoreError::Other(e.to_string()))?;
         let mut res = Vec::new();
         while let Some(hb) = blocks.next().map(|block| block.unwrap()) {
@@ -387,20 +541,88 @@ impl SQLiteStore {
         Ok(res)
     }
 
-    pub fn push(&mut self, hb: HashedBlock) -> Result<(), BlockStoreError> {
-        let mut connection = self.connection.lock().unwrap();
-        Self::execute_push(&mut *connection, hb)
+    fn get_transaction_index(
+        &self,
+        tx_hash: &HashOf<Transaction>,
+    ) -> Result<Option<BlockHeight>, BlockStoreError> {
+        let connection = self.connection.lock().unwrap();
+        let mut stmt = connection
+            .prepare("SELECT block_idx FROM transactions WHERE tx_hash = ?")
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        let mut rows = stmt
+            .query(params![tx_hash.into_bytes().to_vec()])
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        rows.next()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?
+            .map(|row| row.get(0))
+            .transpose()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))
+    }
+
+    fn get_account_balance(
+        &self,
+        account: &AccountIdentifier,
+        index: BlockHeight,
+    ) -> Result<Option<Tokens>, BlockStoreError> {
+        let connection = self.connection.lock().unwrap();
+        if Self::read_pruned_at(&connection)?.map_or(false, |base| index < base) {
+            return Err(BlockStoreError::NotAvailable(index));
+        }
+        Self::read_balance(&connection, account, index)
     }
 
-    pub fn push_batch(&mut self, batch: Vec<HashedBlock>) -> Result<(), BlockStoreError> {
+    fn get_account_history(
+        &self,
+        account: &AccountIdentifier,
+        max_block: Option<BlockHeight>,
+    ) -> Result<Vec<(BlockHeight, Tokens)>, BlockStoreError> {
+        let connection = self.connection.lock().unwrap();
+        let after = Self::read_pruned_at(&connection)?
+            .map(|base| base as i64)
+            .unwrap_or(-1);
+        let mut stmt = connection
+            .prepare(
+                "SELECT block_idx, tokens FROM account_balances WHERE account = ?1 AND block_idx > ?2 AND block_idx <= ?3 ORDER BY block_idx",
+            )
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        let rows = stmt
+            .query_map(
+                params![
+                    account.to_hex(),
+                    after,
+                    max_block.map(db_index).unwrap_or(i64::MAX)
+                ],
+                |row| {
+                    Ok((
+                        row.get::<_, BlockHeight>(0)?,
+                        Tokens::from_e8s(row.get::<_, i64>(1)? as u64),
+                    ))
+                },
+            )
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))
+    }
+
+    fn push(&mut self, hb: HashedBlock) -> Result<(), BlockStoreError> {
         let mut connection = self.connection.lock().unwrap();
+        let tx = connection
+            .transaction()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        Self::execute_push(&tx, hb)?;
+        tx.commit()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))
+    }
+
+    fn push_batch(&mut self, batch: Vec<HashedBlock>) -> Result<(), BlockStoreError> {
+        let connection = self.connection.lock().unwrap();
 
         connection
             .execute_batch("BEGIN TRANSACTION;")
             .map_err(|e| BlockStoreError::Other(format!("{}", e)))?;
 
         for hb in batch {
-            match Self::execute_push(&mut *connection, hb) {
+            match Self::execute_push(&*connection, hb) {
                 Ok(_) => (),
                 Err(e) => {
                     connection
@@ -417,54 +639,81 @@ impl SQLiteStore {
         Ok(())
     }
 
-    // FIXME: Make `prune` return `BlockStoreError` on error
-    pub fn prune(&mut self, hb: &HashedBlock, balances: &BalanceBook) -> Result<(), String> {
-        self.write_oldest_block_snapshot(hb, balances)?;
+    fn prune(&mut self, hb: &HashedBlock) -> Result<(), BlockStoreError> {
+        if let Ok(Some(b)) = self.first() {
+            // this check is made in upper levels, but for readability:
+            assert!(
+                b.index <= hb.index,
+                "Oldest: {}, new oldest: {}",
+                b.index,
+                hb.index
+            );
+        }
+
+        debug!("Writing oldest block snapshot ({})", hb.index);
+
         let mut connection = self.connection.lock().unwrap();
-        let tx = connection.transaction().expect("Cannot open transaction");
-        // NB: An optimization would be to update only modified accounts.
+        let tx = connection
+            .transaction()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        // Every account gets an entry at the new oldest block, holding its
+        // balance there.
         tx.execute(
-            "DELETE FROM books WHERE block_idx > 0 AND block_idx < ?",
+            r#"
+            INSERT INTO account_balances (account, block_idx, tokens)
+            SELECT b.account, ?1, b.tokens FROM account_balances AS b
+            JOIN (
+                SELECT account, MAX(block_idx) AS block_idx FROM account_balances
+                WHERE block_idx <= ?1 GROUP BY account
+            ) AS latest ON b.account = latest.account AND b.block_idx = latest.block_idx
+            WHERE latest.block_idx < ?1
+            "#,
             params![hb.index],
         )
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        let held: i64 = tx
+            .query_row(
+                "SELECT COALESCE(SUM(tokens), 0) FROM account_balances WHERE block_idx = ?1",
+                params![hb.index],
+                |row| row.get(0),
+            )
+            .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        let pool = (Tokens::MAX - Tokens::from_e8s(held as u64)).map_err(BlockStoreError::Other)?;
+        // We need to convert to i64 because SQLite only supports signed integers,
+        // and Rusqlite doesn't like overflows when converting u64 to i64.
         tx.execute(
-            "DELETE FROM balances WHERE block_idx > 0 AND block_idx < ?",
-            params![hb.index],
+            "INSERT INTO balances(block_idx,icpt_pool) VALUES (?1,?2)",
+            params![hb.index, pool.get_e8s() as i64],
         )
-        .map_err(|e| e.to_string())?;
-        tx.execute(
+        .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        for sql in &[
+            "DELETE FROM account_balances WHERE block_idx < ?",
+            "DELETE FROM books WHERE block_idx > 0 AND block_idx < ?",
+            "DELETE FROM balances WHERE block_idx > 0 AND block_idx < ?",
+            "DELETE FROM transactions WHERE block_idx > 0 AND block_idx < ?",
             "DELETE FROM blocks WHERE idx > 0 AND idx < ?",
-            params![hb.index],
-        )
-        .map_err(|e| e.to_string())?;
-        self.base_idx = hb.index;
-        tx.commit().map_err(|e| e.to_string())?;
-        Ok(())
-    }
-
-    pub fn first_snapshot(&self) -> Option<(HashedBlock, BalanceBook)> {
-        self.read_oldest_block_snapshot()
-            .expect("Error while retrieving first snapshot.")
+        ] {
+            tx.execute(sql, params![hb.index])
+                .map_err(|e| BlockStoreError::Other(e.to_string()))?;
+        }
+        tx.commit()
+            .map_err(|e| BlockStoreError::Other(e.to_string()))
     }
 
-    pub fn first(&self) -> Result<Option<HashedBlock>, BlockStoreError> {
-        if let Some(first_block) = self.first_block.as_ref() {
-            Ok(Some(first_block.clone()))
-        } else {
-            match self.get_at(0) {
-                Ok(x) => Ok(Some(x)),
-                Err(BlockStoreError::NotFound(_)) => Ok(None),
-                Err(e) => Err(e),
-            }
+    fn first(&self) -> Result<Option<HashedBlock>, BlockStoreError> {
+        let pruned_at = Self::read_pruned_at(&self.connection.lock().unwrap())?;
+        match self.get_at(pruned_at.unwrap_or(0)) {
+            Ok(x) => Ok(Some(x)),
+            Err(BlockStoreError::NotFound(_)) if pruned_at.is_none() => Ok(None),
+            Err(e) => Err(e),
         }
     }
 
-    pub fn last_verified(&self) -> Option<BlockHeight> {
+    fn last_verified(&self) -> Option<BlockHeight> {
         self.last_verified_idx
     }
 
-    pub fn mark_last_verified(&mut self, block_height: BlockHeight) -> Result<(), BlockStoreError> {
+    fn mark_last_verified(&mut self, block_height: BlockHeight) -> Result<(), BlockStoreError> {
         if let Some(hh) = self.last_verified_idx {
             if block_height < hh {
                 panic!(
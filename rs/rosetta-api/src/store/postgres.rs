@@ -0,0 +1,495 @@
+//! A block store backed by a PostgreSQL database, which allows several Rosetta
+//! instances to share one durable index of the ledger.
+use std::sync::{mpsc, Mutex};
+
+use log::debug;
+
+use ::postgres::GenericClient;
+use ledger_canister::{
+    AccountIdentifier, BlockHeight, EncodedBlock, HashOf, Operation, Tokens, Transaction,
+};
+
+use super::{
+    apply_operation, db_index, decode_transaction, vec_into_array, BlockStore, BlockStoreError,
+    HashedBlock,
+};
+
+const CREATE_TABLES: &str = r#"
+    CREATE TABLE IF NOT EXISTS blocks (
+        hash BYTEA NOT NULL,
+        block BYTEA NOT NULL,
+        parent_hash BYTEA,
+        idx BIGINT NOT NULL PRIMARY KEY,
+        verified BOOLEAN NOT NULL DEFAULT FALSE
+    );
+    CREATE TABLE IF NOT EXISTS balances (
+        block_idx BIGINT NOT NULL PRIMARY KEY REFERENCES blocks(idx),
+        icpt_pool BIGINT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS account_balances (
+        account VARCHAR(64) NOT NULL,
+        block_idx BIGINT NOT NULL REFERENCES blocks(idx),
+        tokens BIGINT NOT NULL,
+        PRIMARY KEY(account, block_idx)
+    );
+    CREATE TABLE IF NOT EXISTS transactions (
+        tx_hash BYTEA NOT NULL PRIMARY KEY,
+        block_idx BIGINT NOT NULL REFERENCES blocks(idx)
+    );
+    CREATE INDEX IF NOT EXISTS transactions_block_idx ON transactions(block_idx);
+"#;
+
+type Job = Box<dyn FnOnce(&mut ::postgres::Client) + Send>;
+
+/// A connection to the database.
+///
+/// The synchronous Postgres client drives its own Tokio runtime, which cannot
+/// be started from within the runtime of the Rosetta server. The client
+/// therefore lives on a dedicated thread that runs the jobs it is sent.
+struct Connection {
+    jobs: Mutex<mpsc::Sender<Job>>,
+}
+
+impl Connection {
+    fn open(url: &str, schema: &str) -> Result<Self, BlockStoreError> {
+        let url = url.to_string();
+        let init = format!(
+            "CREATE SCHEMA IF NOT EXISTS {0}; SET search_path TO {0}; {1}",
+            quote_identifier(schema),
+            CREATE_TABLES
+        );
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let (ready_sender, ready) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("rosetta-postgres".to_string())
+            .spawn(move || {
+                let client = ::postgres::Client::connect(&url, ::postgres::NoTls)
+                    .and_then(|mut client| client.batch_execute(&init).map(|_| client));
+                let mut client = match client {
+                    Ok(client) => {
+                        let _ = ready_sender.send(Ok(()));
+                        client
+                    }
+                    Err(e) => {
+                        let _ = ready_sender.send(Err(e.to_string()));
+                        return;
+                    }
+                };
+                // Stops when the connection, and with it the sender, is dropped.
+                for job in receiver {
+                    job(&mut client);
+                }
+            })
+            .map_err(|e| {
+                BlockStoreError::Other(format!("Failed to start the Postgres thread: {}", e))
+            })?;
+        ready.recv().map_err(|_| Self::stopped())?.map_err(|e| {
+            BlockStoreError::Other(format!("Failed to initialize Postgres database: {}", e))
+        })?;
+        Ok(Self {
+            jobs: Mutex::new(jobs),
+        })
+    }
+
+    /// Runs `f` with the client and waits for its result.
+    fn run<T, E, F>(&self, f: F) -> Result<T, BlockStoreError>
+    where
+        T: Send + 'static,
+        E: Into<BlockStoreError>,
+        F: FnOnce(&mut ::postgres::Client) -> Result<T, E> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let job: Job = Box::new(move |client| {
+            let _ = sender.send(f(client).map_err(Into::into));
+        });
+        self.jobs
+            .lock()
+            .unwrap()
+            .send(job)
+            .map_err(|_| Self::stopped())?;
+        receiver.recv().map_err(|_| Self::stopped())?
+    }
+
+    fn stopped() -> BlockStoreError {
+        BlockStoreError::Other("The Postgres thread stopped".to_string())
+    }
+}
+
+impl From<::postgres::Error> for BlockStoreError {
+    fn from(e: ::postgres::Error) -> Self {
+        BlockStoreError::Other(e.to_string())
+    }
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn hashed_block(row: &::postgres::Row) -> HashedBlock {
+    HashedBlock {
+        hash: HashOf::new(vec_into_array(row.get(0))),
+        block: EncodedBlock::from(row.get::<_, Vec<u8>>(1).into_boxed_slice()),
+        parent_hash: row
+            .get::<_, Option<Vec<u8>>>(2)
+            .map(|bytes| HashOf::new(vec_into_array(bytes))),
+        index: row.get::<_, i64>(3) as u64,
+    }
+}
+
+/// Returns the index of the block the store was last pruned at, if any.
+fn read_pruned_at<C: GenericClient>(
+    client: &mut C,
+) -> Result<Option<BlockHeight>, ::postgres::Error> {
+    client
+        .query_one("SELECT MAX(block_idx) FROM balances", &[])
+        .map(|row| row.get::<_, Option<i64>>(0).map(|idx| idx as u64))
+}
+
+/// Returns true if the block at `index` was removed by pruning.
+fn is_pruned<C: GenericClient>(
+    client: &mut C,
+    index: BlockHeight,
+) -> Result<bool, ::postgres::Error> {
+    Ok(0 < index && read_pruned_at(client)?.map_or(false, |base| index < base))
+}
+
+fn read_balance<C: GenericClient>(
+    client: &mut C,
+    account: &str,
+    index: i64,
+) -> Result<Option<Tokens>, ::postgres::Error> {
+    client
+        .query_opt(
+            "SELECT tokens FROM account_balances WHERE account = $1 AND block_idx <= $2 ORDER BY block_idx DESC LIMIT 1",
+            &[&account, &index],
+        )
+        .map(|row| row.map(|row| Tokens::from_e8s(row.get::<_, i64>(0) as u64)))
+}
+
+/// The columns of a block and of its transaction, ready to be inserted.
+struct BlockRow {
+    hash: Vec<u8>,
+    block: Vec<u8>,
+    parent_hash: Option<Vec<u8>>,
+    idx: i64,
+    tx_hash: Vec<u8>,
+    operation: Operation,
+}
+
+impl BlockRow {
+    fn new(hb: HashedBlock) -> Result<Self, BlockStoreError> {
+        let transaction = decode_transaction(&hb)?;
+        Ok(Self {
+            tx_hash: transaction.hash().into_bytes().to_vec(),
+            operation: transaction.operation,
+            hash: hb.hash.into_bytes().to_vec(),
+            block: hb.block.0.into_vec(),
+            parent_hash: hb.parent_hash.map(|ph| ph.into_bytes().to_vec()),
+            idx: hb.index as i64,
+        })
+    }
+
+    /// Inserts the block and the balances of the accounts it involves unless
+    /// the store already holds it, which happens if another instance pushed
+    /// it. Returns false if the store holds a different block at the same
+    /// index.
+    fn insert(&self, tx: &mut ::postgres::Transaction) -> Result<bool, BlockStoreError> {
+        let inserted = tx.execute(
+            "INSERT INTO blocks (hash, block, parent_hash, idx, verified) VALUES ($1, $2, $3, $4, FALSE) \
+             ON CONFLICT (idx) DO NOTHING",
+            &[&self.hash, &self.block, &self.parent_hash, &self.idx],
+        )?;
+        if inserted == 0 {
+            let row = tx.query_one("SELECT hash FROM blocks WHERE idx = $1", &[&self.idx])?;
+            return Ok(row.get::<_, Vec<u8>>(0) == self.hash);
+        }
+        // If a transaction occurs more than once, searching it returns the
+        // most recent block.
+        tx.execute(
+            "INSERT INTO transactions (tx_hash, block_idx) VALUES ($1, $2) \
+             ON CONFLICT (tx_hash) DO UPDATE SET block_idx = EXCLUDED.block_idx",
+            &[&self.tx_hash, &self.idx],
+        )?;
+        let balances = apply_operation(&self.operation, |account| {
+            Ok(read_balance(tx, &account.to_hex(), self.idx)?.unwrap_or(Tokens::ZERO))
+        })?;
+        for (account, tokens) in balances {
+            tx.execute(
+                "INSERT INTO account_balances (account, block_idx, tokens) VALUES ($1, $2, $3)",
+                &[&account.to_hex(), &self.idx, &(tokens.get_e8s() as i64)],
+            )?;
+        }
+        Ok(true)
+    }
+
+    fn conflict(&self) -> BlockStoreError {
+        BlockStoreError::Other(format!(
+            "The store holds a different block at index {}",
+            self.idx
+        ))
+    }
+}
+
+pub struct PostgresStore {
+    connection: Connection,
+    last_verified_idx: Option<BlockHeight>,
+}
+
+impl PostgresStore {
+    /// Connects to the database at `url` and keeps the store's tables in
+    /// `schema`, creating them if they don't exist.
+    pub fn connect(url: &str, schema: &str) -> Result<Self, BlockStoreError> {
+        let mut store = Self {
+            connection: Connection::open(url, schema)?,
+            last_verified_idx: None,
+        };
+
+        store.last_verified_idx = store.connection.run(|client| {
+            client
+                .query_opt(
+                    "SELECT idx FROM blocks WHERE verified ORDER BY idx DESC LIMIT 1",
+                    &[],
+                )
+                .map(|row| row.map(|row| row.get::<_, i64>(0) as u64))
+        })?;
+
+        Ok(store)
+    }
+}
+
+// Unlike the blocks, the pruning base is never cached: other instances sharing
+// the database may prune it at any time.
+impl BlockStore for PostgresStore {
+    fn get_at(&self, index: BlockHeight) -> Result<HashedBlock, BlockStoreError> {
+        let idx = db_index(index);
+        self.connection.run(move |client| {
+            match client.query_opt(
+                "SELECT hash, block, parent_hash, idx FROM blocks WHERE idx = $1",
+                &[&idx],
+            )? {
+                Some(row) => Ok(hashed_block(&row)),
+                None if is_pruned(client, index)? => Err(BlockStoreError::NotAvailable(index)),
+                None => Err(BlockStoreError::NotFound(index)),
+            }
+        })
+    }
+
+    fn get_range(
+        &self,
+        range: std::ops::Range<BlockHeight>,
+    ) -> Result<Vec<HashedBlock>, BlockStoreError> {
+        let start = db_index(range.start);
+        let end = db_index(range.end);
+        self.connection.run(move |client| {
+            if is_pruned(client, range.start)? {
+                return Err(BlockStoreError::NotAvailable(range.start));
+            }
+            Ok(client
+                .query(
+                    "SELECT hash, block, parent_hash, idx FROM blocks WHERE idx >= $1 AND idx < $2 ORDER BY idx",
+                    &[&start, &end],
+                )?
+                .iter()
+                .map(hashed_block)
+                .collect())
+        })
+    }
+
+    fn get_transaction_index(
+        &self,
+        tx_hash: &HashOf<Transaction>,
+    ) -> Result<Option<BlockHeight>, BlockStoreError> {
+        let tx_hash = tx_hash.into_bytes().to_vec();
+        self.connection.run(move |client| {
+            client
+                .query_opt(
+                    "SELECT block_idx FROM transactions WHERE tx_hash = $1",
+                    &[&tx_hash],
+                )
+                .map(|row| row.map(|row| row.get::<_, i64>(0) as u64))
+        })
+    }
+
+    fn get_account_balance(
+        &self,
+        account: &AccountIdentifier,
+        index: BlockHeight,
+    ) -> Result<Option<Tokens>, BlockStoreError> {
+        let account = account.to_hex();
+        self.connection.run(move |client| {
+            // Both reads see the same snapshot of the database.
+            let mut tx = client.build_transaction().read_only(true).start()?;
+            if read_pruned_at(&mut tx)?.map_or(false, |base| index < base) {
+                return Err(BlockStoreError::NotAvailable(index));
+            }
+            Ok(read_balance(&mut tx, &account, db_index(index))?)
+        })
+    }
+
+    fn get_account_history(
+        &self,
+        account: &AccountIdentifier,
+        max_block: Option<BlockHeight>,
+    ) -> Result<Vec<(BlockHeight, Tokens)>, BlockStoreError> {
+        let account = account.to_hex();
+        let max_block = max_block.map(db_index).unwrap_or(i64::MAX);
+        self.connection.run(move |client| {
+            let mut tx = client.build_transaction().read_only(true).start()?;
+            let after = read_pruned_at(&mut tx)?
+                .map(|base| base as i64)
+                .unwrap_or(-1);
+            tx.query(
+                "SELECT block_idx, tokens FROM account_balances WHERE account = $1 AND block_idx > $2 AND block_idx <= $3 ORDER BY block_idx",
+                &[&account, &after, &max_block],
+            )
+            .map(|rows| {
+                rows.iter()
+                    .map(|row| {
+                        (
+                            row.get::<_, i64>(0) as u64,
+                            Tokens::from_e8s(row.get::<_, i64>(1) as u64),
+                        )
+                    })
+                    .collect()
+            })
+        })
+    }
+
+    fn push(&mut self, hb: HashedBlock) -> Result<(), BlockStoreError> {
+        let row = BlockRow::new(hb)?;
+        let (row, inserted) = self.connection.run(move |client| {
+            let mut tx = client.transaction()?;
+            let inserted = row.insert(&mut tx)?;
+            if inserted {
+                tx.commit()?;
+            }
+            Ok::<_, BlockStoreError>((row, inserted))
+        })?;
+        if !inserted {
+            return Err(row.conflict());
+        }
+        Ok(())
+    }
+
+    fn push_batch(&mut self, batch: Vec<HashedBlock>) -> Result<(), BlockStoreError> {
+        let rows = batch
+            .into_iter()
+            .map(BlockRow::new)
+            .collect::<Result<Vec<_>, _>>()?;
+        let conflict = self.connection.run(move |client| {
+            // The transaction is rolled back if it is dropped without commit.
+            let mut tx = client.transaction()?;
+            for row in rows {
+                if !row.insert(&mut tx)? {
+                    return Ok(Some(row));
+                }
+            }
+            tx.commit()?;
+            Ok::<_, BlockStoreError>(None)
+        })?;
+        match conflict {
+            Some(row) => Err(row.conflict()),
+            None => Ok(()),
+        }
+    }
+
+    fn prune(&mut self, hb: &HashedBlock) -> Result<(), BlockStoreError> {
+        if let Ok(Some(b)) = self.first() {
+            // this check is made in upper levels, but for readability:
+            assert!(
+                b.index <= hb.index,
+                "Oldest: {}, new oldest: {}",
+                b.index,
+                hb.index
+            );
+        }
+
+        debug!("Writing oldest block snapshot ({})", hb.index);
+
+        let idx = hb.index as i64;
+        self.connection.run(move |client| {
+            let mut tx = client.transaction()?;
+            // Every account gets an entry at the new oldest block, holding its
+            // balance there.
+            tx.execute(
+                r#"
+                INSERT INTO account_balances (account, block_idx, tokens)
+                SELECT b.account, $1, b.tokens FROM account_balances AS b
+                JOIN (
+                    SELECT account, MAX(block_idx) AS block_idx FROM account_balances
+                    WHERE block_idx <= $1 GROUP BY account
+                ) AS latest ON b.account = latest.account AND b.block_idx = latest.block_idx
+                WHERE latest.block_idx < $1
+                "#,
+                &[&idx],
+            )?;
+            let held: i64 = tx
+                .query_one(
+                    "SELECT COALESCE(SUM(tokens), 0)::BIGINT FROM account_balances WHERE block_idx = $1",
+                    &[&idx],
+                )?
+                .get(0);
+            let pool = (Tokens::MAX - Tokens::from_e8s(held as u64))
+                .map_err(BlockStoreError::Other)?;
+            tx.execute(
+                "INSERT INTO balances (block_idx, icpt_pool) VALUES ($1, $2)",
+                &[&idx, &(pool.get_e8s() as i64)],
+            )?;
+            tx.execute("DELETE FROM account_balances WHERE block_idx < $1", &[&idx])?;
+            tx.execute(
+                "DELETE FROM balances WHERE block_idx > 0 AND block_idx < $1",
+                &[&idx],
+            )?;
+            tx.execute(
+                "DELETE FROM transactions WHERE block_idx > 0 AND block_idx < $1",
+                &[&idx],
+            )?;
+            tx.execute("DELETE FROM blocks WHERE idx > 0 AND idx < $1", &[&idx])?;
+            Ok::<_, BlockStoreError>(tx.commit()?)
+        })
+    }
+
+    fn first(&self) -> Result<Option<HashedBlock>, BlockStoreError> {
+        self.connection.run(|client| {
+            let pruned_at = read_pruned_at(client)?;
+            let idx = pruned_at.unwrap_or(0) as i64;
+            match client.query_opt(
+                "SELECT hash, block, parent_hash, idx FROM blocks WHERE idx = $1",
+                &[&idx],
+            )? {
+                Some(row) => Ok(Some(hashed_block(&row))),
+                None if pruned_at.is_none() => Ok(None),
+                None => Err(BlockStoreError::NotFound(idx as u64)),
+            }
+        })
+    }
+
+    fn last_verified(&self) -> Option<BlockHeight> {
+        self.last_verified_idx
+    }
+
+    fn mark_last_verified(&mut self, block_height: BlockHeight) -> Result<(), BlockStoreError> {
+        if let Some(hh) = self.last_verified_idx {
+            if block_height < hh {
+                panic!(
+                    "New last verified index lower than the old one. New: {}, old: {}",
+                    block_height, hh
+                );
+            }
+            if block_height == hh {
+                return Ok(());
+            }
+        }
+
+        let start = self.last_verified_idx.map(|x| x + 1).unwrap_or(0) as i64;
+        let end = block_height as i64;
+        self.connection.run(move |client| {
+            client.execute(
+                "UPDATE blocks SET verified = TRUE WHERE idx >= $1 AND idx <= $2",
+                &[&start, &end],
+            )
+        })?;
+        self.last_verified_idx = Some(block_height);
+        Ok(())
+    }
+}
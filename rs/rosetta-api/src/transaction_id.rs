@@ -69,7 +69,13 @@ impl TransactionIdentifier {
             | RequestType::Disburse { .. }
             | RequestType::AddHotKey { .. }
             | RequestType::Spawn { .. }
-            | RequestType::MergeMaturity { .. } => {
+            | RequestType::MergeMaturity { .. }
+            | RequestType::RemoveHotKey { .. }
+            | RequestType::Follow { .. }
+            | RequestType::RegisterVote { .. }
+            | RequestType::Split { .. }
+            | RequestType::Merge { .. }
+            | RequestType::DisburseToNeuron { .. } => {
                 // Unfortunately, staking operations don't really have a transaction ID
                 Ok(TransactionIdentifier {
                     hash: NEURON_MANAGEMEN_PSEUDO_HASH.to_string(),
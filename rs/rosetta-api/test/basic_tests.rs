@@ -2,11 +2,18 @@ use super::*;
 
 use ic_rosetta_api::models::*;
 
-use ic_rosetta_api::convert::{amount_, block_id, from_hash, timestamp, to_hash};
+use ic_rosetta_api::convert::{
+    amount_, block_id, from_hash, from_operations, neuron_account_from_public_key, timestamp,
+    to_hash,
+};
 use ic_rosetta_api::ledger_client::LedgerAccess;
+use ic_rosetta_api::request_types::{
+    DisburseToNeuron, Follow, Merge, PublicKeyOrPrincipal, RegisterVote, RemoveHotKey, Split,
+};
 use ic_rosetta_api::transaction_id::TransactionIdentifier;
 use ic_rosetta_api::{RosettaRequestHandler, API_VERSION, NODE_VERSION};
 
+use ic_nns_governance::pb::v1::{Topic, Vote};
 use std::sync::Arc;
 
 #[actix_rt::test]
@@ -436,14 +443,33 @@ fn verify_balances(scribe: &Scribe, blocks: &Blocks, start_idx: usize) {
             assert_eq!(blocks.get_balance(account, hb.index).unwrap(), *amount);
         }
     }
-    let mut sum_icpt = Tokens::ZERO;
-    for amount in scribe.balance_history.back().unwrap().values() {
-        sum_icpt += *amount;
+
+    // The balances in the store add up to the tokens minted minus the tokens
+    // burned and the fees paid.
+    let last_idx = scribe.blockchain.back().unwrap().index;
+    let mut sum_scribe = Tokens::ZERO;
+    let mut sum_store = Tokens::ZERO;
+    for (account, amount) in scribe.balance_history.back().unwrap() {
+        sum_scribe += *amount;
+        sum_store += blocks
+            .block_store
+            .get_account_balance(account, last_idx)
+            .unwrap()
+            .unwrap_or(Tokens::ZERO);
     }
-    assert_eq!(
-        (Tokens::MAX - sum_icpt).unwrap(),
-        blocks.balance_book.token_pool
-    );
+    assert_eq!(sum_store, sum_scribe);
+
+    let mut total_supply = 0;
+    for hb in scribe.blockchain.iter() {
+        match hb.block.decode().unwrap().transaction.operation {
+            ledger_canister::Operation::Mint { amount, .. } => total_supply += amount.get_e8s(),
+            ledger_canister::Operation::Burn { amount, .. } => total_supply -= amount.get_e8s(),
+            ledger_canister::Operation::Transfer { fee, .. }
+            | ledger_canister::Operation::Approve { fee, .. }
+            | ledger_canister::Operation::TransferFrom { fee, .. } => total_supply -= fee.get_e8s(),
+        }
+    }
+    assert_eq!(sum_store, Tokens::from_e8s(total_supply));
 }
 
 async fn query_search_transactions(
@@ -479,12 +505,16 @@ async fn verify_account_search(
             ledger_canister::Operation::Mint { to, .. } => {
                 history.entry(to).or_insert_with(Vec::new).push(hb.index);
             }
-            ledger_canister::Operation::Transfer { from, to, .. } => {
+            ledger_canister::Operation::Transfer { from, to, .. }
+            | ledger_canister::Operation::TransferFrom { from, to, .. } => {
                 history.entry(from).or_insert_with(Vec::new).push(hb.index);
                 if from != to {
                     history.entry(to).or_insert_with(Vec::new).push(hb.index);
                 }
             }
+            ledger_canister::Operation::Approve { from, .. } => {
+                history.entry(from).or_insert_with(Vec::new).push(hb.index);
+            }
         }
     }
 
@@ -769,3 +799,136 @@ async fn store_batch_test() {
     blocks.block_store.mark_last_verified(last_idx).unwrap();
     verify_balances(&scribe, &blocks, 0);
 }
+
+#[actix_rt::test]
+async fn list_neurons_test() {
+    init_test_logger();
+
+    let (_, _, public_key, controller) = ic_rosetta_test_utils::make_user(1);
+    let mut scribe = Scribe::new();
+    for neuron_index in &[0, 2] {
+        let account = neuron_account_from_public_key(
+            &ic_nns_constants::GOVERNANCE_CANISTER_ID,
+            &public_key,
+            *neuron_index,
+        )
+        .unwrap();
+        scribe.add_account(&account.address, 1_000_000 + neuron_index);
+    }
+
+    let ledger = Arc::new(TestLedger::default());
+    let req_handler = RosettaRequestHandler::new(ledger.clone());
+    for b in &scribe.blockchain {
+        ledger.add_block(b.clone()).await.ok();
+    }
+
+    let list_neurons = |controller: PublicKeyOrPrincipal, start_index: u64| {
+        let params = ListNeuronsRequest {
+            controller,
+            start_index,
+            limit: Some(5),
+        };
+        let parameters = match serde_json::to_value(params).unwrap() {
+            serde_json::Value::Object(o) => o,
+            _ => unreachable!(),
+        };
+        CallRequest {
+            network_identifier: req_handler.network_id(),
+            method: "list_neurons".to_string(),
+            parameters,
+        }
+    };
+    let neuron_indices = |res: CallResponse| {
+        let res: ListNeuronsResponse =
+            serde_json::from_value(serde_json::Value::Object(res.result)).unwrap();
+        res.neurons
+            .iter()
+            .map(|n| (n.neuron_index, n.balance.value.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let res = req_handler
+        .call(list_neurons(PublicKeyOrPrincipal::PublicKey(public_key), 0))
+        .await
+        .unwrap();
+    assert_eq!(
+        neuron_indices(res),
+        vec![(0, "1000000".to_string()), (2, "1000002".to_string())]
+    );
+
+    let res = req_handler
+        .call(list_neurons(PublicKeyOrPrincipal::Principal(controller), 1))
+        .await
+        .unwrap();
+    assert_eq!(neuron_indices(res), vec![(2, "1000002".to_string())]);
+
+    let mut unsupported = list_neurons(PublicKeyOrPrincipal::Principal(controller), 0);
+    unsupported.method = "list_accounts".to_string();
+    assert!(req_handler.call(unsupported).await.is_err());
+}
+
+#[actix_rt::test]
+async fn neuron_management_construction_test() {
+    init_test_logger();
+
+    let (account, _, public_key, _) = ic_rosetta_test_utils::make_user(1);
+    let (_, _, _, other) = ic_rosetta_test_utils::make_user(2);
+
+    let ledger = Arc::new(TestLedger::default());
+    let req_handler = RosettaRequestHandler::new(ledger);
+
+    let requests = vec![
+        Request::RemoveHotKey(RemoveHotKey {
+            account,
+            neuron_index: 1,
+            key: PublicKeyOrPrincipal::Principal(other),
+        }),
+        Request::Follow(Follow {
+            account,
+            neuron_index: 1,
+            topic: Topic::Governance as i32,
+            followees: vec![10, 11],
+        }),
+        Request::RegisterVote(RegisterVote {
+            account,
+            neuron_index: 1,
+            proposal: 42,
+            vote: Vote::No as i32,
+        }),
+        Request::Split(Split {
+            account,
+            neuron_index: 1,
+            amount: Tokens::from_e8s(200_000_000),
+        }),
+        Request::Merge(Merge {
+            account,
+            neuron_index: 1,
+            source_neuron_id: 12,
+        }),
+        Request::DisburseToNeuron(DisburseToNeuron {
+            account,
+            neuron_index: 1,
+            amount: Tokens::from_e8s(300_000_000),
+            controller: Some(other),
+            dissolve_delay_seconds: 3600,
+            kyc_verified: true,
+            child_neuron_index: 4,
+        }),
+    ];
+
+    let operations = Request::requests_to_operations(&requests, DEFAULT_TOKEN_NAME).unwrap();
+    let mut msg = ConstructionPayloadsRequest::new(req_handler.network_id(), operations);
+    msg.public_keys = Some(vec![public_key]);
+    let payloads = req_handler.construction_payloads(msg).await.unwrap();
+
+    let msg = ConstructionParseRequest::new(
+        req_handler.network_id(),
+        false,
+        payloads.unsigned_transaction,
+    );
+    let parsed = req_handler.construction_parse(msg).await.unwrap();
+    assert_eq!(
+        from_operations(&parsed.operations, false, DEFAULT_TOKEN_NAME).unwrap(),
+        requests
+    );
+}
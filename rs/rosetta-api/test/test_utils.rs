@@ -18,7 +18,6 @@ use tokio::sync::RwLock;
 
 use async_trait::async_trait;
 use dfn_core::CanisterId;
-use ic_rosetta_api::balance_book::BalanceBook;
 
 use ic_rosetta_api::convert::{from_arg, to_model_account_identifier};
 use ic_rosetta_api::ledger_client::{Blocks, LedgerAccess};
@@ -221,6 +220,7 @@ impl LedgerAccess for TestLedger {
                 transaction_identifier: Some(From::from(&block.transaction().hash())),
                 block_index: None,
                 neuron_id: None,
+                created_neuron_id: None,
                 status: Status::Completed,
             });
         }
@@ -243,18 +243,6 @@ impl LedgerAccess for TestLedger {
     }
 }
 
-pub(crate) fn to_balances(
-    b: BTreeMap<AccountIdentifier, Tokens>,
-    index: BlockHeight,
-) -> BalanceBook {
-    let mut balance_book = BalanceBook::default();
-    for (acc, amount) in b {
-        balance_book.token_pool -= amount;
-        balance_book.store.insert(acc, index, amount);
-    }
-    balance_book
-}
-
 pub async fn get_balance(
     req_handler: &RosettaRequestHandler,
     height: Option<usize>,
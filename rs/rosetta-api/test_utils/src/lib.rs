@@ -9,8 +9,9 @@ use ic_rosetta_api::models::{
     CurveType, PublicKey, Signature, SignatureType,
 };
 use ic_rosetta_api::request_types::{
-    AddHotKey, Disburse, MergeMaturity, Request, RequestResult, SetDissolveTimestamp, Spawn, Stake,
-    StartDissolve, StopDissolve, TransactionResults,
+    AddHotKey, Disburse, DisburseToNeuron, Follow, Merge, MergeMaturity, RegisterVote,
+    RemoveHotKey, Request, RequestResult, SetDissolveTimestamp, Spawn, Split, Stake, StartDissolve,
+    StopDissolve, TransactionResults,
 };
 use ic_rosetta_api::transaction_id::TransactionIdentifier;
 use ic_rosetta_api::{convert, errors, errors::ApiError, DEFAULT_TOKEN_NAME};
@@ -114,7 +115,13 @@ pub async fn prepare_multiple_txn(
             | Request::AddHotKey(AddHotKey { account, .. })
             | Request::Disburse(Disburse { account, .. })
             | Request::Spawn(Spawn { account, .. })
-            | Request::MergeMaturity(MergeMaturity { account, .. }) => {
+            | Request::MergeMaturity(MergeMaturity { account, .. })
+            | Request::RemoveHotKey(RemoveHotKey { account, .. })
+            | Request::Follow(Follow { account, .. })
+            | Request::RegisterVote(RegisterVote { account, .. })
+            | Request::Split(Split { account, .. })
+            | Request::Merge(Merge { account, .. })
+            | Request::DisburseToNeuron(DisburseToNeuron { account, .. }) => {
                 all_sender_account_ids.push(to_model_account_identifier(&account));
             }
             Request::Transfer(Operation::Burn { .. }) => {
@@ -123,6 +130,12 @@ pub async fn prepare_multiple_txn(
             Request::Transfer(Operation::Mint { .. }) => {
                 panic!("Mint operations are supported here")
             }
+            Request::Transfer(Operation::Approve { .. }) => {
+                panic!("Approve operations are not supported here")
+            }
+            Request::Transfer(Operation::TransferFrom { .. }) => {
+                panic!("TransferFrom operations are not supported here")
+            }
         };
 
         all_sender_pks.push(to_public_key(&request.sender_keypair));
@@ -43,7 +43,7 @@ use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{
     registry::connection_endpoint::ConnectionEndpoint,
-    transport::{TransportConfig, TransportFlowConfig},
+    transport::{TransportBackend, TransportConfig, TransportFlowConfig},
     Height,
 };
 use serde::{Deserialize, Serialize};
@@ -282,9 +282,9 @@ struct CliArgs {
     #[structopt(long = "detect-consensus-starvation")]
     detect_consensus_starvation: Option<bool>,
 
-    /// The backend DB used by Consensus, can be rocksdb or lmdb.
+    /// The backend DB used by Consensus, can be rocksdb, lmdb or append_log.
     #[structopt(long = "consensus-pool-backend",
-                possible_values = &["lmdb", "rocksdb"])]
+                possible_values = &["lmdb", "rocksdb", "append_log"])]
     consensus_pool_backend: Option<String>,
 }
 
@@ -530,6 +530,7 @@ impl ValidatedConfig {
                 server_port: 0,
                 queue_size: 1024,
             }],
+            transport_backend: TransportBackend::Tcp,
         });
 
         let hypervisor_config = HypervisorConfig::default();
@@ -3,6 +3,7 @@ use ic_config::subnet_config::SubnetConfigs;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_execution_environment::setup_execution;
 use ic_interfaces::{
+    certified_stream_store::{CertifiedStreamStore, EncodeStreamError},
     execution_environment::{IngressHistoryReader, QueryHandler},
     messaging::MessageRouting,
     state_manager::{StateHashError, StateManager, StateReader},
@@ -12,59 +13,71 @@ use ic_messaging::MessageRoutingImpl;
 use ic_metrics::MetricsRegistry;
 use ic_protobuf::registry::{
     provisional_whitelist::v1::ProvisionalWhitelist as PbProvisionalWhitelist,
-    routing_table::v1::RoutingTable as PbRoutingTable,
+    routing_table::v1::RoutingTable as PbRoutingTable, subnet::v1::SubnetListRecord,
 };
 use ic_protobuf::types::v1::PrincipalId as PrincipalIdIdProto;
 use ic_protobuf::types::v1::SubnetId as SubnetIdProto;
 use ic_registry_client::client::RegistryClientImpl;
 use ic_registry_common::proto_registry_data_provider::ProtoRegistryDataProvider;
 use ic_registry_keys::{
-    make_provisional_whitelist_record_key, make_routing_table_record_key, ROOT_SUBNET_ID_KEY,
+    make_provisional_whitelist_record_key, make_routing_table_record_key,
+    make_subnet_list_record_key, make_subnet_record_key, ROOT_SUBNET_ID_KEY,
 };
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::{routing_table_insert_subnet, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::ReplicatedState;
+use ic_replicated_state::{canister_state::WASM_PAGE_SIZE_IN_BYTES, ReplicatedState};
 use ic_state_manager::StateManagerImpl;
 use ic_test_utilities::{
-    consensus::fake::FakeVerifier,
+    consensus::fake::{Fake, FakeVerifier},
     mock_time,
-    registry::{add_subnet_record, insert_initial_dkg_transcript, SubnetRecordBuilder},
+    registry::{insert_initial_dkg_transcript, SubnetRecordBuilder},
     types::messages::SignedIngressBuilder,
 };
 use ic_types::batch::SelfValidatingPayload;
 use ic_types::{
     batch::{Batch, BatchPayload, IngressPayload, XNetPayload},
+    consensus::{
+        certification::{Certification, CertificationContent},
+        ThresholdSignature,
+    },
+    crypto::Signed,
     ic00,
     ic00::{CanisterIdRecord, CanisterSettingsArgs, InstallCodeArgs, Method, Payload},
-    ingress::{IngressStatus, WasmResult},
+    ingress::{IngressStatus, WasmResult, MAX_INGRESS_TTL},
     messages::{CanisterInstallMode, MessageId, SignedIngress, UserQuery},
     time::Time,
     user_error::UserError,
-    CanisterId, CryptoHashOfState, NodeId, PrincipalId, Randomness, RegistryVersion, SubnetId,
-    UserId,
+    xnet::{CertifiedStreamSlice, StreamIndex},
+    CanisterId, CryptoHashOfState, Height, NodeId, PrincipalId, Randomness, RegistryVersion,
+    SubnetId, UserId,
 };
+use std::collections::BTreeMap;
 use std::fmt;
+use std::path::Path;
 use std::string::ToString;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use tempfile::TempDir;
 
-/// Constructs the initial version of the registry containing a subnet with the
-/// specified SUBNET_ID, with the node with the specified NODE_ID assigned to
-/// it.
-fn make_single_node_registry(
+/// Constructs the initial version of the registry containing the specified
+/// SUBNETS, each with a single node assigned to it. The first subnet in the
+/// list is the root subnet and all subnets share the same routing table, in
+/// which each subnet gets a canister ID range in the order of the list.
+fn make_registry(
     metrics_registry: &MetricsRegistry,
-    subnet_id: SubnetId,
-    subnet_type: SubnetType,
-    node_id: NodeId,
+    subnets: &[SubnetSetup],
 ) -> Arc<RegistryClientImpl> {
     let registry_version = RegistryVersion::from(1);
     let data_provider = Arc::new(ProtoRegistryDataProvider::new());
 
+    let root_subnet_id = subnets
+        .first()
+        .expect("at least one subnet is required")
+        .subnet_id;
     let root_subnet_id_proto = SubnetIdProto {
         principal_id: Some(PrincipalIdIdProto {
-            raw: subnet_id.get_ref().to_vec(),
+            raw: root_subnet_id.get_ref().to_vec(),
         }),
     };
     data_provider
@@ -76,7 +89,9 @@ fn make_single_node_registry(
         .unwrap();
 
     let mut routing_table = RoutingTable::new();
-    routing_table_insert_subnet(&mut routing_table, subnet_id).unwrap();
+    for subnet in subnets {
+        routing_table_insert_subnet(&mut routing_table, subnet.subnet_id).unwrap();
+    }
     let pb_routing_table = PbRoutingTable::from(routing_table);
     data_provider
         .add(
@@ -95,11 +110,37 @@ fn make_single_node_registry(
         .unwrap();
 
     // Set subnetwork list(needed for filling network_topology.nns_subnet_id)
-    let mut record = SubnetRecordBuilder::from(&[node_id]).build();
-    record.subnet_type = i32::from(subnet_type);
-
-    insert_initial_dkg_transcript(registry_version.get(), subnet_id, &record, &data_provider);
-    add_subnet_record(&data_provider, registry_version.get(), subnet_id, record);
+    for subnet in subnets {
+        let mut record = SubnetRecordBuilder::from(&[subnet.node_id]).build();
+        record.subnet_type = i32::from(subnet.subnet_type);
+
+        insert_initial_dkg_transcript(
+            registry_version.get(),
+            subnet.subnet_id,
+            &record,
+            &data_provider,
+        );
+        data_provider
+            .add(
+                &make_subnet_record_key(subnet.subnet_id),
+                registry_version,
+                Some(record),
+            )
+            .unwrap();
+    }
+    let subnet_list_record = SubnetListRecord {
+        subnets: subnets
+            .iter()
+            .map(|subnet| subnet.subnet_id.get().into_vec())
+            .collect(),
+    };
+    data_provider
+        .add(
+            &make_subnet_list_record_key(),
+            registry_version,
+            Some(subnet_list_record),
+        )
+        .unwrap();
 
     let registry_client = Arc::new(RegistryClientImpl::new(
         data_provider,
@@ -109,9 +150,31 @@ fn make_single_node_registry(
     registry_client
 }
 
+/// Describes one of the subnets known to a `StateMachine`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SubnetSetup {
+    subnet_id: SubnetId,
+    subnet_type: SubnetType,
+    node_id: NodeId,
+}
+
+impl SubnetSetup {
+    /// Returns the setup of the `index`-th (0-based) subnet of a topology.
+    fn nth(index: usize, subnet_type: SubnetType) -> Self {
+        let n = index as u64 + 1;
+        Self {
+            subnet_id: SubnetId::from(PrincipalId::new_subnet_test_id(n)),
+            subnet_type,
+            node_id: NodeId::from(PrincipalId::new_node_test_id(n)),
+        }
+    }
+}
+
 /// Represents a replicated state machine detached from the network layer that
 /// can be used to test this part of the stack in isolation.
 pub struct StateMachine {
+    subnets: Vec<SubnetSetup>,
+    own_subnet_index: usize,
     state_manager: Arc<StateManagerImpl>,
     message_routing: MessageRoutingImpl,
     ingress_history_reader: Box<dyn IngressHistoryReader>,
@@ -119,6 +182,7 @@ pub struct StateMachine {
     state_dir: TempDir,
     nonce: std::cell::Cell<u64>,
     time: std::cell::Cell<Time>,
+    checkpoints_enabled: std::cell::Cell<bool>,
 }
 
 impl Default for StateMachine {
@@ -130,6 +194,7 @@ impl Default for StateMachine {
 impl fmt::Debug for StateMachine {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("StateMachine")
+            .field("subnet_id", &self.get_subnet_id())
             .field("state_dir", &self.state_dir.path().display())
             .field("nonce", &self.nonce.get())
             .finish()
@@ -145,6 +210,8 @@ impl StateMachine {
             0,
             mock_time(),
             None,
+            vec![SubnetSetup::nth(0, SubnetType::System)],
+            0,
         )
     }
 
@@ -154,9 +221,39 @@ impl StateMachine {
             0,
             mock_time(),
             Some(config),
+            vec![SubnetSetup::nth(0, SubnetType::System)],
+            0,
         )
     }
 
+    /// Constructs one state machine per entry of `subnet_types`, each
+    /// simulating a different subnet of the same IC. All machines share a
+    /// routing table (the first subnet is the root subnet), so messages
+    /// addressed to canisters hosted by another machine end up in the
+    /// outgoing streams of the sender.
+    ///
+    /// Use [StateMachine::induct_stream_slices_from] or
+    /// [execute_xnet_rounds] to move messages between the machines.
+    pub fn new_multi_subnet(subnet_types: &[SubnetType]) -> Vec<Self> {
+        let subnets: Vec<_> = subnet_types
+            .iter()
+            .enumerate()
+            .map(|(index, subnet_type)| SubnetSetup::nth(index, *subnet_type))
+            .collect();
+        (0..subnets.len())
+            .map(|own_subnet_index| {
+                Self::setup_from_dir(
+                    TempDir::new().expect("failed to create a temporary directory"),
+                    0,
+                    mock_time(),
+                    None,
+                    subnets.clone(),
+                    own_subnet_index,
+                )
+            })
+            .collect()
+    }
+
     /// Constructs and initializes a new state machine that uses the specified
     /// directory for storing states.
     fn setup_from_dir(
@@ -164,21 +261,21 @@ impl StateMachine {
         nonce: u64,
         time: Time,
         subnet_config: Option<SubnetConfig>,
+        subnets: Vec<SubnetSetup>,
+        own_subnet_index: usize,
     ) -> Self {
         let logger = slog::Logger::root(slog::Discard, slog::o!());
         let replica_logger: ReplicaLogger = logger.into();
 
-        let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(1));
-        let node_id = NodeId::from(PrincipalId::new_node_test_id(1));
+        let subnet_id = subnets[own_subnet_index].subnet_id;
+        let subnet_type = subnets[own_subnet_index].subnet_type;
         let metrics_registry = MetricsRegistry::new();
-        let subnet_type = SubnetType::System;
         let subnet_config = match subnet_config {
             Some(subnet_config) => subnet_config,
             None => SubnetConfigs::default().own_subnet_config(subnet_type),
         };
 
-        let registry =
-            make_single_node_registry(&metrics_registry, subnet_id, subnet_type, node_id);
+        let registry = make_registry(&metrics_registry, &subnets);
 
         let sm_config = ic_config::state_manager::Config::new(state_dir.path().to_path_buf());
         let hypervisor_config = ic_config::execution_environment::Config::default();
@@ -224,6 +321,8 @@ impl StateMachine {
         );
 
         Self {
+            subnets,
+            own_subnet_index,
             state_manager,
             ingress_history_reader,
             message_routing,
@@ -231,12 +330,20 @@ impl StateMachine {
             state_dir,
             nonce: std::cell::Cell::new(nonce),
             time: std::cell::Cell::new(time),
+            checkpoints_enabled: std::cell::Cell::new(true),
         }
     }
 
     /// Emulates a node restart, including checkpoint recovery.
     pub fn restart_node(self) -> Self {
-        Self::setup_from_dir(self.state_dir, self.nonce.get(), self.time.get(), None)
+        Self::setup_from_dir(
+            self.state_dir,
+            self.nonce.get(),
+            self.time.get(),
+            None,
+            self.subnets,
+            self.own_subnet_index,
+        )
     }
 
     pub fn restart_node_with_config(self, config: SubnetConfig) -> Self {
@@ -245,23 +352,45 @@ impl StateMachine {
             self.nonce.get(),
             self.time.get(),
             Some(config),
+            self.subnets,
+            self.own_subnet_index,
         )
     }
 
+    /// Returns the ID of the subnet simulated by this state machine.
+    pub fn get_subnet_id(&self) -> SubnetId {
+        self.subnets[self.own_subnet_index].subnet_id
+    }
+
     /// Creates a new batch containing a single ingress message and sends it for
     /// processing to the replicated state machine.
     fn send_signed_ingress(&self, msg: SignedIngress) {
+        self.deliver_batch(
+            vec![msg],
+            Default::default(),
+            self.checkpoints_enabled.get(),
+        );
+    }
+
+    /// Sends a batch with the specified ingress messages and stream slices for
+    /// processing to the replicated state machine and returns its height. If
+    /// `checkpoint` is set, the resulting state is written to a checkpoint.
+    fn deliver_batch(
+        &self,
+        ingress: Vec<SignedIngress>,
+        stream_slices: BTreeMap<SubnetId, CertifiedStreamSlice>,
+        checkpoint: bool,
+    ) -> Height {
         // Move the block time forward by 1 second.
         self.time.set(self.time.get() + Duration::from_secs(1));
 
+        let batch_number = self.message_routing.expected_batch_height();
         let batch = Batch {
-            batch_number: self.message_routing.expected_batch_height(),
-            requires_full_state_hash: true,
+            batch_number,
+            requires_full_state_hash: checkpoint,
             payload: BatchPayload {
-                ingress: IngressPayload::from(vec![msg]),
-                xnet: XNetPayload {
-                    stream_slices: Default::default(),
-                },
+                ingress: IngressPayload::from(ingress),
+                xnet: XNetPayload { stream_slices },
                 self_validating: SelfValidatingPayload::default(),
             },
             randomness: Randomness::from([0; 32]),
@@ -271,7 +400,177 @@ impl StateMachine {
         };
         self.message_routing
             .deliver_batch(batch)
-            .expect("MR queue overflow")
+            .expect("MR queue overflow");
+        batch_number
+    }
+
+    /// Blocks until the state at the specified height is committed and then
+    /// certifies all states that are still waiting for a certification, so
+    /// that their streams can be sent to other subnets.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the state is not committed in a reasonable
+    /// amount of time (typically, a few seconds).
+    fn await_and_certify_height(&self, height: Height) {
+        let mut tries = 0;
+        while self.state_manager.latest_state_height() < height {
+            tries += 1;
+            if tries > 100 {
+                panic!("State at height {} was not committed in time", height);
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+
+        for (height, hash) in self.state_manager.list_state_hashes_to_certify() {
+            self.state_manager
+                .deliver_state_certification(Certification {
+                    height,
+                    signed: Signed {
+                        content: CertificationContent::new(hash),
+                        signature: ThresholdSignature::fake(),
+                    },
+                });
+        }
+    }
+
+    /// Blocks until all batches delivered so far are executed and certifies
+    /// the resulting states.
+    pub fn await_and_certify_latest_state(&self) {
+        let last_delivered = self.message_routing.expected_batch_height().decrement();
+        self.await_and_certify_height(last_delivered);
+    }
+
+    /// Executes a round with no ingress messages and no stream slices, and
+    /// blocks until the resulting state is committed and certified.
+    pub fn tick(&self) {
+        let height = self.deliver_batch(vec![], Default::default(), self.checkpoints_enabled.get());
+        self.await_and_certify_height(height);
+    }
+
+    /// Same as [StateMachine::tick], but the resulting state is always written
+    /// to a checkpoint, even if checkpoints are disabled.
+    pub fn checkpointed_tick(&self) {
+        let height = self.deliver_batch(vec![], Default::default(), true);
+        self.await_and_certify_height(height);
+    }
+
+    /// Enables or disables writing a checkpoint after every round (enabled by
+    /// default). Disabling checkpoints makes long-running tests considerably
+    /// faster, but a [StateMachine::restart_node] only recovers the state as
+    /// of the latest checkpoint.
+    pub fn set_checkpoints_enabled(&self, enabled: bool) {
+        self.checkpoints_enabled.set(enabled);
+    }
+
+    /// Returns the block time that was used for the latest round.
+    pub fn time(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_nanos(self.time.get().as_nanos_since_unix_epoch())
+    }
+
+    /// Sets the block time of the latest round. Every subsequent round moves the
+    /// block time forward by one second, so the next round is executed at
+    /// `time + 1s`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `time` is before the Unix epoch.
+    pub fn set_time(&self, time: SystemTime) {
+        let since_epoch = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("time must be after the Unix epoch");
+        self.time.set(Time::from_nanos_since_unix_epoch(
+            since_epoch.as_nanos() as u64
+        ));
+    }
+
+    /// Moves the block time forward by the specified amount. The effects of
+    /// the elapsed time (e.g. charges for memory and compute allocation) are
+    /// only applied in the next round, e.g. by a [StateMachine::tick].
+    pub fn advance_time(&self, amount: Duration) {
+        self.time.set(self.time.get() + amount);
+    }
+
+    /// Produces a slice of the certified stream from this subnet to the
+    /// `remote_subnet`, as an XNet endpoint would serve it. See
+    /// [CertifiedStreamStore::encode_certified_stream_slice] for the meaning of
+    /// the arguments.
+    pub fn generate_certified_stream_slice(
+        &self,
+        remote_subnet: SubnetId,
+        witness_begin: Option<StreamIndex>,
+        msg_begin: Option<StreamIndex>,
+        msg_limit: Option<usize>,
+        byte_limit: Option<usize>,
+    ) -> Result<CertifiedStreamSlice, EncodeStreamError> {
+        self.state_manager.encode_certified_stream_slice(
+            remote_subnet,
+            witness_begin,
+            msg_begin,
+            msg_limit,
+            byte_limit,
+        )
+    }
+
+    /// Returns the index of the next message this subnet expects in the stream
+    /// from `remote_subnet`, according to the latest state.
+    fn expected_stream_index(&self, remote_subnet: SubnetId) -> StreamIndex {
+        self.state_manager
+            .get_latest_state()
+            .get_ref()
+            .metadata
+            .streams
+            .get(&remote_subnet)
+            .map(|stream| stream.signals_end())
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if the latest state of this subnet contains
+    /// messages in its stream to `remote` that `remote` has not inducted yet.
+    pub fn has_messages_for(&self, remote: &StateMachine) -> bool {
+        let messages_end = self
+            .state_manager
+            .get_latest_state()
+            .get_ref()
+            .metadata
+            .streams
+            .get(&remote.get_subnet_id())
+            .map(|stream| stream.messages_end())
+            .unwrap_or_default();
+        messages_end > remote.expected_stream_index(self.get_subnet_id())
+    }
+
+    /// Takes the slice of the certified stream from `remote` to this subnet
+    /// starting at the next expected index, inducts it through the real
+    /// stream handler by executing a round containing only that slice and
+    /// blocks until the resulting state is committed and certified.
+    ///
+    /// Does nothing if `remote` has no stream to this subnet yet.
+    pub fn induct_stream_slices_from(&self, remote: &StateMachine) {
+        remote.await_and_certify_latest_state();
+
+        let begin = self.expected_stream_index(remote.get_subnet_id());
+        let slice = match remote.generate_certified_stream_slice(
+            self.get_subnet_id(),
+            Some(begin),
+            Some(begin),
+            None,
+            None,
+        ) {
+            Ok(slice) => slice,
+            Err(EncodeStreamError::NoStreamForSubnet(_)) => return,
+            Err(err) => panic!(
+                "Failed to encode stream slice from {} to {}: {}",
+                remote.get_subnet_id(),
+                self.get_subnet_id(),
+                err
+            ),
+        };
+
+        let mut stream_slices = BTreeMap::new();
+        stream_slices.insert(remote.get_subnet_id(), slice);
+        let height = self.deliver_batch(vec![], stream_slices, self.checkpoints_enabled.get());
+        self.await_and_certify_height(height);
     }
 
     /// Blocks until the hash of the latest state is computed.
@@ -322,28 +621,19 @@ impl StateMachine {
         panic!("didn't get answer to ingress {}", msg_id)
     }
 
-    /// Compiles specified WAT to Wasm and installs it for the canister using
-    /// the specified ID in the provided install mode.
+    /// Installs the specified Wasm module for the canister using the specified
+    /// ID in the provided install mode.
     fn install_in_mode(
         &self,
         canister_id: CanisterId,
         mode: CanisterInstallMode,
-        wat: &str,
+        wasm: Vec<u8>,
         payload: Vec<u8>,
     ) {
         self.execute_ingress(
             ic00::IC_00,
             Method::InstallCode,
-            InstallCodeArgs::new(
-                mode,
-                canister_id,
-                wabt::wat2wasm(wat).expect("invalid WAT"),
-                payload,
-                None,
-                None,
-                None,
-            )
-            .encode(),
+            InstallCodeArgs::new(mode, canister_id, wasm, payload, None, None, None).encode(),
         )
         .expect("failed to install canister code");
     }
@@ -361,6 +651,23 @@ impl StateMachine {
         wat: &str,
         payload: Vec<u8>,
         settings: Option<CanisterSettingsArgs>,
+    ) -> CanisterId {
+        self.install_canister_wasm(wabt::wat2wasm(wat).expect("invalid WAT"), payload, settings)
+    }
+
+    /// Creates a new canister and installs the specified Wasm module.
+    /// Returns the ID of the newly created canister.
+    ///
+    /// This function is synchronous.
+    ///
+    /// # Panics
+    ///
+    /// Panicks if canister creation or the code install failed.
+    pub fn install_canister_wasm(
+        &self,
+        wasm: Vec<u8>,
+        payload: Vec<u8>,
+        settings: Option<CanisterSettingsArgs>,
     ) -> CanisterId {
         let wasm_result = self
             .execute_ingress(
@@ -379,20 +686,30 @@ impl StateMachine {
                 .get_canister_id(),
             WasmResult::Reject(reason) => panic!("create_canister call rejected: {}", reason),
         };
-        self.install_in_mode(canister_id, CanisterInstallMode::Install, wat, payload);
+        self.install_in_mode(canister_id, CanisterInstallMode::Install, wasm, payload);
         canister_id
     }
 
     /// Erases the previous state and code of the canister with the specified ID
     /// and replaces the code with the compiled form of the provided WAT.
     pub fn reinstall_canister_wat(&self, canister_id: CanisterId, wat: &str, payload: Vec<u8>) {
-        self.install_in_mode(canister_id, CanisterInstallMode::Reinstall, wat, payload);
+        self.install_in_mode(
+            canister_id,
+            CanisterInstallMode::Reinstall,
+            wabt::wat2wasm(wat).expect("invalid WAT"),
+            payload,
+        );
     }
 
     /// Performs upgrade of the canister with the specified ID to the
     /// code obtained by compiling the provided WAT.
     pub fn upgrade_canister_wat(&self, canister_id: CanisterId, wat: &str, payload: Vec<u8>) {
-        self.install_in_mode(canister_id, CanisterInstallMode::Upgrade, wat, payload);
+        self.install_in_mode(
+            canister_id,
+            CanisterInstallMode::Upgrade,
+            wabt::wat2wasm(wat).expect("invalid WAT"),
+            payload,
+        );
     }
 
     /// Queries the canister with the specified ID.
@@ -451,6 +768,7 @@ impl StateMachine {
             .method_name(method.to_string())
             .method_payload(payload)
             .nonce(self.nonce.get())
+            .expiry_time(self.time.get() + MAX_INGRESS_TTL)
             .build();
         let msg_id = msg.id();
         self.send_signed_ingress(msg);
@@ -461,4 +779,205 @@ impl StateMachine {
     pub fn ingress_status(&self, msg_id: &MessageId) -> IngressStatus {
         (self.ingress_history_reader.get_latest_status())(msg_id)
     }
+
+    /// Returns the cycles balance of the specified canister in the latest
+    /// state.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the canister does not exist.
+    pub fn cycle_balance(&self, canister_id: CanisterId) -> u128 {
+        self.state_manager
+            .get_latest_state()
+            .get_ref()
+            .canister_state(&canister_id)
+            .unwrap_or_else(|| panic!("Canister {} not found", canister_id))
+            .system_state
+            .cycles_balance
+            .get()
+    }
+
+    /// Adds `amount` cycles to the balance of the specified canister and
+    /// returns the new balance.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the canister does not exist.
+    pub fn add_cycles(&self, canister_id: CanisterId, amount: u64) -> u128 {
+        let wasm_result = self
+            .execute_ingress(
+                ic00::IC_00,
+                ic00::Method::ProvisionalTopUpCanister,
+                ic00::ProvisionalTopUpCanisterArgs::new(canister_id, amount).encode(),
+            )
+            .unwrap_or_else(|err| panic!("failed to top up canister {}: {}", canister_id, err));
+        if let WasmResult::Reject(reason) = wasm_result {
+            panic!("provisional_top_up_canister call rejected: {}", reason);
+        }
+        self.cycle_balance(canister_id)
+    }
+
+    /// Returns the contents of the stable memory of the specified canister in
+    /// the latest state. The result is empty if the canister has no code
+    /// installed.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the canister does not exist.
+    pub fn stable_memory(&self, canister_id: CanisterId) -> Vec<u8> {
+        let state = self.state_manager.get_latest_state().take();
+        let canister = state
+            .canister_state(&canister_id)
+            .unwrap_or_else(|| panic!("Canister {} not found", canister_id));
+        match &canister.execution_state {
+            Some(execution_state) => {
+                let memory = &execution_state.stable_memory;
+                let mut buf = vec![0; memory.size.get() * WASM_PAGE_SIZE_IN_BYTES];
+                memory.page_map.read(&mut buf, 0);
+                buf
+            }
+            None => vec![],
+        }
+    }
+
+    /// Writes a checkpoint of the current state and copies it, together with
+    /// the harness bookkeeping (ingress nonce and block time), to `path`. The
+    /// copy can be loaded again with [StateMachine::import_state].
+    ///
+    /// Only the checkpoint is copied: checkpoints are immutable once written,
+    /// whereas the rest of the state directory (e.g. the tip) keeps being
+    /// modified by the state manager in the background.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `path` cannot be written.
+    pub fn export_state(&self, path: &Path) {
+        self.await_and_certify_latest_state();
+        self.checkpointed_tick();
+        self.await_state_hash();
+
+        let height = self.state_manager.latest_state_height();
+        let checkpoint = self
+            .state_manager
+            .state_layout()
+            .checkpoint(height)
+            .unwrap_or_else(|err| panic!("Failed to find checkpoint @{}: {:?}", height, err));
+        let checkpoint_path = checkpoint.raw_path();
+        let dst = path
+            .join(CHECKPOINTS_DIR)
+            .join(checkpoint_path.file_name().expect("checkpoint has no name"));
+        copy_dir_all(checkpoint_path, &dst).unwrap_or_else(|err| {
+            panic!(
+                "Failed to export checkpoint {} to {}: {}",
+                checkpoint_path.display(),
+                dst.display(),
+                err
+            )
+        });
+        std::fs::write(
+            path.join(STATE_MACHINE_METADATA_FILE),
+            format!(
+                "{}\n{}\n",
+                self.nonce.get(),
+                self.time.get().as_nanos_since_unix_epoch()
+            ),
+        )
+        .expect("failed to write state machine metadata");
+    }
+
+    /// Replaces the state of this machine with a state previously exported to
+    /// `path` by [StateMachine::export_state] and restarts the node from the
+    /// latest checkpoint found there. As with [StateMachine::restart_node], the
+    /// default subnet config is used. The subnet setup of this machine is
+    /// retained, so the exported state must belong to the same subnet.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `path` does not contain an exported state.
+    pub fn import_state(self, path: &Path) -> Self {
+        let metadata = std::fs::read_to_string(path.join(STATE_MACHINE_METADATA_FILE))
+            .unwrap_or_else(|err| panic!("No exported state found in {}: {}", path.display(), err));
+        let mut lines = metadata.lines().map(|line| {
+            line.parse::<u64>()
+                .expect("failed to parse state machine metadata")
+        });
+        let nonce = lines.next().expect("missing ingress nonce");
+        let time = Time::from_nanos_since_unix_epoch(lines.next().expect("missing block time"));
+
+        let state_dir = TempDir::new().expect("failed to create a temporary directory");
+        copy_dir_all(path, state_dir.path()).unwrap_or_else(|err| {
+            panic!("Failed to import state from {}: {}", path.display(), err)
+        });
+
+        let checkpoints_enabled = self.checkpoints_enabled.get();
+        let Self {
+            subnets,
+            own_subnet_index,
+            ..
+        } = self;
+        let env = Self::setup_from_dir(state_dir, nonce, time, None, subnets, own_subnet_index);
+        env.set_checkpoints_enabled(checkpoints_enabled);
+        env
+    }
+}
+
+/// The name of the file in an exported state directory that records the
+/// harness bookkeeping of the exporting `StateMachine`.
+const STATE_MACHINE_METADATA_FILE: &str = "state_machine_metadata";
+
+/// The directory of a state root that holds the checkpoints, as laid out by
+/// the state manager.
+const CHECKPOINTS_DIR: &str = "checkpoints";
+
+/// Recursively copies the contents of the `src` directory into `dst`,
+/// creating `dst` if it doesn't exist.
+fn copy_dir_all(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let dst_path = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &dst_path)?;
+        } else {
+            std::fs::copy(entry.path(), &dst_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Exchanges stream slices between the specified state machines until none of
+/// them has messages left that another machine has not inducted yet. Each
+/// induction executes one round on the receiving machine, so requests,
+/// responses and rejects travel between subnets exactly as they would through
+/// the XNet payload in production.
+///
+/// # Panics
+///
+/// This function panics if the machines don't reach quiescence within a
+/// reasonable number of rounds (e.g. because canisters keep calling each
+/// other).
+pub fn execute_xnet_rounds(machines: &[&StateMachine]) {
+    const MAX_ROUNDS: usize = 100;
+
+    for machine in machines {
+        machine.await_and_certify_latest_state();
+    }
+    for _ in 0..MAX_ROUNDS {
+        let mut inducted = false;
+        for src in machines {
+            for dst in machines {
+                if src.get_subnet_id() != dst.get_subnet_id() && src.has_messages_for(dst) {
+                    dst.induct_stream_slices_from(src);
+                    inducted = true;
+                }
+            }
+        }
+        if !inducted {
+            return;
+        }
+    }
+    panic!(
+        "XNet traffic did not quiesce within {} rounds per subnet",
+        MAX_ROUNDS
+    );
 }
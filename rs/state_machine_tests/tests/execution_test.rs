@@ -4,6 +4,7 @@ use ic_registry_subnet_type::SubnetType;
 use ic_state_machine_tests::StateMachine;
 use ic_types::ic00::CanisterSettingsArgs;
 use ic_types::Cycles;
+use std::time::Duration;
 
 /// This is a canister that keeps a counter on the heap and exposes various test
 /// methods. Exposed methods:
@@ -354,3 +355,94 @@ async fn test_manifest_computation_memory_expand() {
     let state_hash_3 = env.await_state_hash();
     assert_ne!(state_hash_2, state_hash_3);
 }
+
+/// Verifies that a state exported to disk can be imported again, and that the
+/// imported machine continues from the exported state.
+#[tokio::test]
+async fn test_export_import_state() {
+    let env = StateMachine::new();
+
+    let canister_id = env.install_canister_wat(TEST_CANISTER, vec![], None);
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+
+    let export_dir = tempfile::TempDir::new().unwrap();
+    env.export_state(export_dir.path());
+    let exported_time = env.time();
+
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 2);
+
+    let env = env.import_state(export_dir.path());
+    assert_eq!(env.time(), exported_time);
+
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 1);
+
+    // The ingress nonce is restored as well, so new messages don't clash with
+    // the ones in the imported ingress history.
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 2);
+}
+
+/// Verifies that only checkpointed rounds survive a restart when checkpoints
+/// are disabled.
+#[tokio::test]
+async fn test_checkpointed_tick() {
+    let env = StateMachine::new();
+    env.set_checkpoints_enabled(false);
+
+    let canister_id = env.install_canister_wat(TEST_CANISTER, vec![], None);
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+    env.checkpointed_tick();
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 2);
+
+    let env = env.restart_node();
+
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 1);
+}
+
+/// Verifies that the block time can be set and advanced, and that ingress
+/// messages sent after a large time jump are still accepted.
+#[tokio::test]
+async fn test_set_and_advance_time() {
+    let env = StateMachine::new();
+    let now = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_620_000_000);
+    env.set_time(now);
+    assert_eq!(env.time(), now);
+
+    let canister_id = env.install_canister_wat(TEST_CANISTER, vec![], None);
+    env.advance_time(Duration::from_secs(30 * 24 * 60 * 60));
+    env.tick();
+    assert!(env.time() > now + Duration::from_secs(30 * 24 * 60 * 60));
+
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+    let val = env.query(canister_id, "read", vec![]).unwrap().bytes();
+    assert_eq!(to_int(val), 1);
+}
+
+/// Verifies that the stable memory and cycles balance of a canister can be
+/// read directly from the state.
+#[tokio::test]
+async fn test_canister_state_accessors() {
+    let env = StateMachine::new();
+
+    let canister_id = env.install_canister_wat(TEST_CANISTER, vec![], None);
+    assert!(env.stable_memory(canister_id).is_empty());
+    assert_eq!(env.cycle_balance(canister_id), 0);
+    assert_eq!(env.add_cycles(canister_id, 1_000_000), 1_000_000);
+    assert_eq!(env.cycle_balance(canister_id), 1_000_000);
+
+    env.execute_ingress(canister_id, "inc", vec![]).unwrap();
+    env.execute_ingress(canister_id, "grow_page", vec![])
+        .unwrap();
+    env.execute_ingress(canister_id, "persist", vec![]).unwrap();
+
+    let stable_memory = env.stable_memory(canister_id);
+    assert_eq!(stable_memory.len(), 64 * 1024);
+    assert_eq!(&stable_memory[0..4], &from_int(1)[..]);
+}
@@ -0,0 +1,89 @@
+use ic_error_types::ErrorCode;
+use ic_registry_subnet_type::SubnetType;
+use ic_state_machine_tests::{execute_xnet_rounds, StateMachine};
+use ic_types::{ingress::WasmResult, CanisterId};
+use ic_universal_canister::{call_args, wasm, UNIVERSAL_CANISTER_WASM};
+
+fn install_universal_canister(env: &StateMachine) -> CanisterId {
+    env.install_canister_wasm(UNIVERSAL_CANISTER_WASM.to_vec(), vec![], None)
+}
+
+#[test]
+fn test_inter_subnet_call_gets_reply() {
+    let envs = StateMachine::new_multi_subnet(&[SubnetType::System, SubnetType::Application]);
+    let (env_a, env_b) = (&envs[0], &envs[1]);
+    let canister_a = install_universal_canister(env_a);
+    let canister_b = install_universal_canister(env_b);
+    assert_ne!(env_a.get_subnet_id(), env_b.get_subnet_id());
+
+    let msg_id = env_a.send_ingress(
+        canister_a,
+        "update",
+        wasm()
+            .inter_update(
+                canister_b,
+                call_args().other_side(wasm().reply_data(b"pong")),
+            )
+            .build(),
+    );
+    env_a.await_and_certify_latest_state();
+    assert!(env_a.has_messages_for(env_b));
+
+    execute_xnet_rounds(&[env_a, env_b]);
+
+    assert_eq!(
+        env_a.await_ingress(msg_id),
+        Ok(WasmResult::Reply(b"pong".to_vec()))
+    );
+    assert!(!env_a.has_messages_for(env_b));
+    assert!(!env_b.has_messages_for(env_a));
+}
+
+#[test]
+fn test_inter_subnet_call_to_missing_canister_is_rejected() {
+    let envs = StateMachine::new_multi_subnet(&[SubnetType::System, SubnetType::Application]);
+    let (env_a, env_b) = (&envs[0], &envs[1]);
+    let canister_a = install_universal_canister(env_a);
+    let canister_b = install_universal_canister(env_b);
+    // Subnet B owns the second range of 2^20 canister IDs in the routing table
+    // and only the first ID in that range has been allocated.
+    let missing_canister = CanisterId::from((1 << 20) + 1);
+    assert_ne!(missing_canister, canister_b);
+
+    let msg_id = env_a.send_ingress(
+        canister_a,
+        "update",
+        wasm().inter_update(missing_canister, call_args()).build(),
+    );
+
+    execute_xnet_rounds(&[env_a, env_b]);
+
+    let err = env_a
+        .await_ingress(msg_id)
+        .expect_err("call to a missing canister should be rejected");
+    assert_eq!(err.code(), ErrorCode::CanisterRejectedMessage);
+}
+
+#[test]
+fn test_inter_subnet_calls_survive_restart() {
+    let mut envs = StateMachine::new_multi_subnet(&[SubnetType::System, SubnetType::System]);
+    let canister_a = install_universal_canister(&envs[0]);
+    let canister_b = install_universal_canister(&envs[1]);
+
+    let env_b = envs.pop().unwrap().restart_node();
+    let env_a = envs.pop().unwrap();
+
+    let msg_id = env_a.send_ingress(
+        canister_a,
+        "update",
+        wasm()
+            .inter_update(canister_b, call_args().other_side(wasm().reply_data(b"ok")))
+            .build(),
+    );
+    execute_xnet_rounds(&[&env_a, &env_b]);
+
+    assert_eq!(
+        env_a.await_ingress(msg_id),
+        Ok(WasmResult::Reply(b"ok".to_vec()))
+    );
+}
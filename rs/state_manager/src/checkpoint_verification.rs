@@ -0,0 +1,245 @@
+//! Verification of checkpoints on disk against the manifests persisted in the
+//! states metadata, and repair of checkpoints that fail verification.
+//!
+//! A node that crashes while writing a checkpoint (or suffers from disk
+//! corruption) can end up with checkpoint files that no longer match the
+//! manifest computed when the checkpoint was created. Recomputing the manifest
+//! from the files on disk and comparing it with the persisted one detects
+//! missing, truncated and modified files without loading the state.
+
+use crate::{
+    manifest::{compare_manifests, compute_manifest, ManifestMismatch, DEFAULT_CHUNK_SIZE},
+    CheckpointError, ManifestMetrics, StateManagerImpl,
+};
+use ic_logger::{info, warn, ReplicaLogger};
+use ic_state_layout::StateLayout;
+use ic_types::{state_sync::Manifest, Height};
+use ic_utils::fs::{copy_file_sparse, get_tmp_for_path, sync_and_mark_files_readonly};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// The outcome of verifying a single checkpoint.
+#[derive(Debug, PartialEq)]
+pub enum CheckpointStatus {
+    /// The checkpoint on disk matches its persisted manifest.
+    Verified,
+    /// There is no persisted manifest for the checkpoint (e.g. because the node
+    /// crashed before the manifest computation finished), so it cannot be
+    /// verified.
+    NoManifest,
+    /// The checkpoint on disk does not match its persisted manifest.
+    Corrupted(Vec<ManifestMismatch>),
+}
+
+impl fmt::Display for CheckpointStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Verified => write!(f, "verified"),
+            Self::NoManifest => write!(f, "no manifest"),
+            Self::Corrupted(mismatches) => {
+                write!(f, "corrupted ({} mismatching file(s))", mismatches.len())
+            }
+        }
+    }
+}
+
+/// Returns the manifests persisted in the states metadata of `layout`, indexed
+/// by checkpoint height. Heights for which the manifest was not computed yet are
+/// omitted.
+pub fn persisted_manifests(
+    log: &ReplicaLogger,
+    layout: &StateLayout,
+) -> BTreeMap<Height, Manifest> {
+    StateManagerImpl::load_metadata(log, layout.states_metadata().as_path())
+        .by_height
+        .into_iter()
+        .filter_map(|(height, metadata)| metadata.manifest.map(|manifest| (height, manifest)))
+        .collect()
+}
+
+/// Recomputes the manifest of the checkpoint at `height` and compares it with
+/// the `expected` (persisted) manifest. Returns the list of discrepancies, which
+/// is empty if the checkpoint is intact.
+pub fn verify_checkpoint(
+    thread_pool: &mut scoped_threadpool::Pool,
+    metrics: &ManifestMetrics,
+    log: &ReplicaLogger,
+    layout: &StateLayout,
+    height: Height,
+    expected: &Manifest,
+) -> Result<Vec<ManifestMismatch>, CheckpointError> {
+    let cp_layout = layout.checkpoint(height)?;
+    let actual = compute_manifest(
+        thread_pool,
+        metrics,
+        log,
+        expected.version,
+        cp_layout.raw_path(),
+        DEFAULT_CHUNK_SIZE,
+        None,
+    )?;
+    Ok(compare_manifests(expected, &actual))
+}
+
+/// Verifies the checkpoint at `height` under `layout` against its manifest in
+/// `manifests` (as returned by [persisted_manifests]).
+pub fn checkpoint_status(
+    thread_pool: &mut scoped_threadpool::Pool,
+    metrics: &ManifestMetrics,
+    log: &ReplicaLogger,
+    layout: &StateLayout,
+    manifests: &BTreeMap<Height, Manifest>,
+    height: Height,
+) -> Result<CheckpointStatus, CheckpointError> {
+    let manifest = match manifests.get(&height) {
+        Some(manifest) => manifest,
+        None => return Ok(CheckpointStatus::NoManifest),
+    };
+    let mismatches = verify_checkpoint(thread_pool, metrics, log, layout, height, manifest)?;
+    if mismatches.is_empty() {
+        Ok(CheckpointStatus::Verified)
+    } else {
+        Ok(CheckpointStatus::Corrupted(mismatches))
+    }
+}
+
+/// Tries to repair the checkpoint at `height` by replacing every file listed in
+/// `mismatches` with the corresponding file of the checkpoint at the same
+/// height under `peer_layout`, typically a copy of the state root of another
+/// replica of the same subnet.
+///
+/// The files are not taken from the state sync cache: that cache only exists
+/// in the memory of a running replica, whereas repairs are done by the state
+/// tool while the replica is stopped, and it only holds the chunks of an
+/// aborted sync of some newer state rather than the checkpoint at `height`.
+/// A peer state root does contain that checkpoint, and since every peer file
+/// is checked against the `expected` manifest before it is used, the peer
+/// doesn't need to be trusted.
+///
+/// Files that are not listed in the expected manifest are removed. Returns the
+/// mismatches that could not be repaired.
+#[allow(clippy::too_many_arguments)]
+pub fn repair_checkpoint_from_peer(
+    thread_pool: &mut scoped_threadpool::Pool,
+    metrics: &ManifestMetrics,
+    log: &ReplicaLogger,
+    layout: &StateLayout,
+    peer_layout: &StateLayout,
+    height: Height,
+    expected: &Manifest,
+    mismatches: Vec<ManifestMismatch>,
+) -> Result<Vec<ManifestMismatch>, CheckpointError> {
+    let cp_root = layout.checkpoint(height)?.raw_path().to_path_buf();
+    let peer_cp_root = peer_layout.checkpoint(height)?.raw_path().to_path_buf();
+    let peer_manifest = compute_manifest(
+        thread_pool,
+        metrics,
+        log,
+        expected.version,
+        &peer_cp_root,
+        DEFAULT_CHUNK_SIZE,
+        None,
+    )?;
+
+    let mut unrepaired = Vec::new();
+    for mismatch in mismatches {
+        let relative_path = mismatch.relative_path().to_path_buf();
+        let dst = cp_root.join(&relative_path);
+
+        if let ManifestMismatch::UnexpectedFile { .. } = mismatch {
+            remove_file(&dst)?;
+            info!(log, "Removed unexpected file {}", dst.display());
+            continue;
+        }
+
+        let expected_hash = expected
+            .file_table
+            .iter()
+            .find(|f| f.relative_path == relative_path)
+            .map(|f| f.hash);
+        let peer_has_valid_copy = peer_manifest
+            .file_table
+            .iter()
+            .any(|f| f.relative_path == relative_path && Some(f.hash) == expected_hash);
+        if !peer_has_valid_copy {
+            warn!(
+                log,
+                "Peer checkpoint @{} has no valid copy of {}",
+                height,
+                relative_path.display()
+            );
+            unrepaired.push(mismatch);
+            continue;
+        }
+
+        replace_file(&peer_cp_root.join(&relative_path), &dst)?;
+        info!(
+            log,
+            "Replaced {} with a valid copy from {}",
+            dst.display(),
+            peer_cp_root.display()
+        );
+    }
+    Ok(unrepaired)
+}
+
+/// Repairs the checkpoint at `height` from the checkpoint at the same height
+/// under `peer_layout` (see [repair_checkpoint_from_peer]) and verifies it
+/// again against its manifest in `manifests`. Only a checkpoint whose status is
+/// [CheckpointStatus::Verified] afterwards can be trusted.
+#[allow(clippy::too_many_arguments)]
+pub fn repair_and_verify_checkpoint(
+    thread_pool: &mut scoped_threadpool::Pool,
+    metrics: &ManifestMetrics,
+    log: &ReplicaLogger,
+    layout: &StateLayout,
+    peer_layout: &StateLayout,
+    manifests: &BTreeMap<Height, Manifest>,
+    height: Height,
+    mismatches: Vec<ManifestMismatch>,
+) -> Result<CheckpointStatus, CheckpointError> {
+    let manifest = match manifests.get(&height) {
+        Some(manifest) => manifest,
+        None => return Ok(CheckpointStatus::NoManifest),
+    };
+    repair_checkpoint_from_peer(
+        thread_pool,
+        metrics,
+        log,
+        layout,
+        peer_layout,
+        height,
+        manifest,
+        mismatches,
+    )?;
+    checkpoint_status(thread_pool, metrics, log, layout, manifests, height)
+}
+
+/// Removes a (possibly read-only) checkpoint file.
+fn remove_file(path: &Path) -> Result<(), CheckpointError> {
+    std::fs::remove_file(path).map_err(|err| CheckpointError::IoError {
+        path: path.to_path_buf(),
+        message: "failed to remove file".to_string(),
+        io_err: err.to_string(),
+    })
+}
+
+/// Atomically replaces the (possibly missing or read-only) checkpoint file
+/// `dst` with a read-only copy of `src`.
+fn replace_file(src: &Path, dst: &Path) -> Result<(), CheckpointError> {
+    let io_error = |path: &Path, message: &str, err: std::io::Error| CheckpointError::IoError {
+        path: path.to_path_buf(),
+        message: message.to_string(),
+        io_err: err.to_string(),
+    };
+
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| io_error(parent, "failed to create directory", err))?;
+    }
+    let tmp = get_tmp_for_path(dst);
+    copy_file_sparse(src, &tmp).map_err(|err| io_error(src, "failed to copy file", err))?;
+    sync_and_mark_files_readonly(&tmp).map_err(|err| io_error(&tmp, "failed to sync file", err))?;
+    std::fs::rename(&tmp, dst).map_err(|err| io_error(dst, "failed to replace file", err))
+}
@@ -1,8 +1,10 @@
 // Needs to be `pub` so that the benchmarking code in `state_manager/benches`
 // can access it.
 pub mod checkpoint;
+pub mod checkpoint_verification;
 pub mod labeled_tree_visitor;
 pub mod manifest;
+pub mod state_change_feed;
 pub mod state_sync;
 pub mod stream_encoding;
 pub mod tree_diff;
@@ -81,6 +83,10 @@ const CRITICAL_ERROR_STATE_SYNC_CORRUPTED_CHUNKS: &str = "state_sync_corrupted_c
 /// Critical error tracking checkpoints expected to be on disk but not found.
 const CRITICAL_ERROR_MISSING_CHECKPOINTS: &str = "state_manager_missing_checkpoints";
 
+/// Critical error tracking checkpoints that don't match their persisted
+/// manifests on startup.
+const CRITICAL_ERROR_CORRUPTED_CHECKPOINTS: &str = "state_manager_corrupted_checkpoints";
+
 /// Labels for manifest metrics
 const LABEL_TYPE: &str = "type";
 const LABEL_VALUE_HASHED: &str = "hashed";
@@ -103,6 +109,7 @@ pub struct StateManagerMetrics {
     checkpoint_metrics: CheckpointMetrics,
     manifest_metrics: ManifestMetrics,
     missing_checkpoints: IntCounter,
+    corrupted_checkpoints: IntCounter,
 }
 
 #[derive(Clone)]
@@ -213,6 +220,9 @@ impl StateManagerMetrics {
         let missing_checkpoints =
             metrics_registry.error_counter(CRITICAL_ERROR_MISSING_CHECKPOINTS);
 
+        let corrupted_checkpoints =
+            metrics_registry.error_counter(CRITICAL_ERROR_CORRUPTED_CHECKPOINTS);
+
         Self {
             state_manager_error_count,
             checkpoint_op_duration,
@@ -228,6 +238,7 @@ impl StateManagerMetrics {
             checkpoint_metrics: CheckpointMetrics::new(metrics_registry),
             manifest_metrics: ManifestMetrics::new(metrics_registry),
             missing_checkpoints,
+            corrupted_checkpoints,
         }
     }
 }
@@ -855,6 +866,16 @@ impl StateManagerImpl {
 
         cleanup_diverged_states(&log, &state_layout);
 
+        if config.verify_checkpoints_on_startup() {
+            Self::mark_corrupted_checkpoints_diverged(
+                &log,
+                &metrics,
+                &state_layout,
+                &mut states_metadata,
+                &mut checkpoint_heights,
+            );
+        }
+
         let (certifications_metadata, compute_manifest_requests) = Self::populate_missing_metadata(
             &log,
             &metrics,
@@ -1313,6 +1334,70 @@ impl StateManagerImpl {
         }
     }
 
+    /// Recomputes the manifests of the checkpoints in `checkpoint_heights` and
+    /// marks every checkpoint that doesn't match its persisted manifest as
+    /// diverged, so that the node recovers from an older checkpoint (or via
+    /// state sync) instead of loading a corrupted state. Checkpoints without a
+    /// persisted manifest are left alone, their manifest is computed as part of
+    /// the regular recovery procedure.
+    fn mark_corrupted_checkpoints_diverged(
+        log: &ReplicaLogger,
+        metrics: &StateManagerMetrics,
+        layout: &StateLayout,
+        metadata: &mut StatesMetadata,
+        checkpoint_heights: &mut Vec<Height>,
+    ) {
+        let mut thread_pool = scoped_threadpool::Pool::new(NUMBER_OF_CHECKPOINT_THREADS);
+        let mut corrupted_heights = Vec::new();
+
+        for height in checkpoint_heights.iter() {
+            let manifest = match metadata.get(height).and_then(|m| m.manifest.as_ref()) {
+                Some(manifest) => manifest,
+                None => continue,
+            };
+            let problems = match checkpoint_verification::verify_checkpoint(
+                &mut thread_pool,
+                &metrics.manifest_metrics,
+                log,
+                layout,
+                *height,
+                manifest,
+            ) {
+                Ok(mismatches) => mismatches.iter().map(ToString::to_string).collect(),
+                Err(err) => vec![err.to_string()],
+            };
+            if problems.is_empty() {
+                info!(log, "Verified checkpoint @{}", height);
+                continue;
+            }
+
+            error!(
+                log,
+                "{}: Checkpoint @{} does not match its manifest: {}",
+                CRITICAL_ERROR_CORRUPTED_CHECKPOINTS,
+                height,
+                problems.join("; ")
+            );
+            metrics.corrupted_checkpoints.inc();
+            layout
+                .mark_checkpoint_diverged(*height)
+                .unwrap_or_else(|err| {
+                    fatal!(
+                        log,
+                        "Failed to mark corrupted checkpoint @{} diverged: {}",
+                        height,
+                        err
+                    )
+                });
+            corrupted_heights.push(*height);
+        }
+
+        for height in corrupted_heights.iter() {
+            metadata.remove(height);
+        }
+        checkpoint_heights.retain(|h| !corrupted_heights.contains(h));
+    }
+
     fn populate_missing_metadata(
         log: &ReplicaLogger,
         metrics: &StateManagerMetrics,
@@ -82,6 +82,75 @@ impl fmt::Display for ManifestValidationError {
 
 impl std::error::Error for ManifestValidationError {}
 
+/// A discrepancy between the manifest persisted for a checkpoint and the
+/// manifest recomputed from the files of that checkpoint on disk.
+#[derive(Debug, PartialEq)]
+pub enum ManifestMismatch {
+    /// A file listed in the persisted manifest does not exist on disk.
+    MissingFile { relative_path: PathBuf },
+    /// A file on disk is not listed in the persisted manifest.
+    UnexpectedFile { relative_path: PathBuf },
+    /// The size of a file on disk differs from the persisted size, e.g. because
+    /// the file was truncated.
+    InvalidFileSize {
+        relative_path: PathBuf,
+        expected_size: u64,
+        actual_size: u64,
+    },
+    /// The file on disk has the persisted size, but the contents of the listed
+    /// chunks differ.
+    InvalidFileContent {
+        relative_path: PathBuf,
+        chunk_offsets: Vec<u64>,
+    },
+}
+
+impl ManifestMismatch {
+    /// Returns the path of the affected file, relative to the checkpoint root.
+    pub fn relative_path(&self) -> &Path {
+        match self {
+            Self::MissingFile { relative_path }
+            | Self::UnexpectedFile { relative_path }
+            | Self::InvalidFileSize { relative_path, .. }
+            | Self::InvalidFileContent { relative_path, .. } => relative_path,
+        }
+    }
+}
+
+impl fmt::Display for ManifestMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingFile { relative_path } => {
+                write!(f, "file {} is missing", relative_path.display())
+            }
+            Self::UnexpectedFile { relative_path } => {
+                write!(f, "file {} is not in the manifest", relative_path.display())
+            }
+            Self::InvalidFileSize {
+                relative_path,
+                expected_size,
+                actual_size,
+            } => write!(
+                f,
+                "file {} size mismatch, expected {} bytes, got {} bytes",
+                relative_path.display(),
+                expected_size,
+                actual_size
+            ),
+            Self::InvalidFileContent {
+                relative_path,
+                chunk_offsets,
+            } => write!(
+                f,
+                "file {} content mismatch in {} chunk(s) at offsets {:?}",
+                relative_path.display(),
+                chunk_offsets.len(),
+                chunk_offsets
+            ),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ChunkValidationError {
     InvalidChunkHash {
@@ -895,6 +964,73 @@ pub fn validate_chunk(
     Ok(())
 }
 
+/// Compares the manifest persisted for a checkpoint (`expected`) with the
+/// manifest recomputed from the checkpoint files on disk (`actual`) and returns
+/// all the discrepancies, ordered by file path. An empty result means that the
+/// checkpoint on disk matches its persisted manifest.
+pub fn compare_manifests(expected: &Manifest, actual: &Manifest) -> Vec<ManifestMismatch> {
+    let actual_files: BTreeMap<&Path, usize> = actual
+        .file_table
+        .iter()
+        .enumerate()
+        .map(|(index, f)| (f.relative_path.as_path(), index))
+        .collect();
+    let expected_files: BTreeMap<&Path, usize> = expected
+        .file_table
+        .iter()
+        .enumerate()
+        .map(|(index, f)| (f.relative_path.as_path(), index))
+        .collect();
+
+    let mut mismatches = Vec::new();
+    for (relative_path, expected_index) in expected_files.iter() {
+        let expected_file = &expected.file_table[*expected_index];
+        let actual_index = match actual_files.get(relative_path) {
+            Some(index) => *index,
+            None => {
+                mismatches.push(ManifestMismatch::MissingFile {
+                    relative_path: relative_path.to_path_buf(),
+                });
+                continue;
+            }
+        };
+        let actual_file = &actual.file_table[actual_index];
+
+        if actual_file.size_bytes != expected_file.size_bytes {
+            mismatches.push(ManifestMismatch::InvalidFileSize {
+                relative_path: relative_path.to_path_buf(),
+                expected_size: expected_file.size_bytes,
+                actual_size: actual_file.size_bytes,
+            });
+        } else if actual_file.hash != expected_file.hash {
+            let expected_chunks =
+                &expected.chunk_table[file_chunk_range(&expected.chunk_table, *expected_index)];
+            let actual_chunks =
+                &actual.chunk_table[file_chunk_range(&actual.chunk_table, actual_index)];
+            let chunk_offsets = expected_chunks
+                .iter()
+                .zip(actual_chunks.iter())
+                .filter(|(e, a)| e.hash != a.hash || e.size_bytes != a.size_bytes)
+                .map(|(e, _)| e.offset)
+                .collect();
+            mismatches.push(ManifestMismatch::InvalidFileContent {
+                relative_path: relative_path.to_path_buf(),
+                chunk_offsets,
+            });
+        }
+    }
+
+    for relative_path in actual_files.keys() {
+        if !expected_files.contains_key(relative_path) {
+            mismatches.push(ManifestMismatch::UnexpectedFile {
+                relative_path: relative_path.to_path_buf(),
+            });
+        }
+    }
+    mismatches.sort_by(|l, r| l.relative_path().cmp(r.relative_path()));
+    mismatches
+}
+
 /// Computes root hash of the manifest.
 /// See note [Manifest Hash].
 pub fn manifest_hash(manifest: &Manifest) -> [u8; 32] {
@@ -1,7 +1,8 @@
 use super::{
-    compute_manifest, diff_manifest, file_chunk_range, filter_out_zero_chunks, hash::ManifestHash,
-    manifest_hash, validate_chunk, validate_manifest, ChunkValidationError, DiffScript,
-    ManifestValidationError, CURRENT_STATE_SYNC_VERSION, STATE_SYNC_V1,
+    compare_manifests, compute_manifest, diff_manifest, file_chunk_range, filter_out_zero_chunks,
+    hash::ManifestHash, manifest_hash, validate_chunk, validate_manifest, ChunkValidationError,
+    DiffScript, ManifestMismatch, ManifestValidationError, CURRENT_STATE_SYNC_VERSION,
+    STATE_SYNC_V1,
 };
 use crate::ManifestMetrics;
 
@@ -318,6 +319,65 @@ fn orphan_chunk_detected() {
     }
 }
 
+#[test]
+fn identical_manifests_have_no_mismatches() {
+    let (_, manifest) = simple_manifest();
+    assert_eq!(compare_manifests(&manifest, &manifest), vec![]);
+}
+
+#[test]
+fn compare_manifests_detects_missing_and_unexpected_files() {
+    let (_, expected) = simple_manifest();
+    let mut actual = expected.clone();
+    actual.file_table[3].relative_path = "subdir/queue.tmp".into();
+
+    assert_eq!(
+        compare_manifests(&expected, &actual),
+        vec![
+            ManifestMismatch::MissingFile {
+                relative_path: "subdir/queue".into()
+            },
+            ManifestMismatch::UnexpectedFile {
+                relative_path: "subdir/queue.tmp".into()
+            },
+        ]
+    );
+}
+
+#[test]
+fn compare_manifests_detects_truncated_file() {
+    let (_, expected) = simple_manifest();
+    let mut actual = expected.clone();
+    actual.file_table[2].size_bytes = 1024;
+    actual.file_table[2].hash = [0; 32];
+    actual.chunk_table.pop();
+
+    assert_eq!(
+        compare_manifests(&expected, &actual),
+        vec![ManifestMismatch::InvalidFileSize {
+            relative_path: "subdir/metadata".into(),
+            expected_size: 1050,
+            actual_size: 1024,
+        }]
+    );
+}
+
+#[test]
+fn compare_manifests_detects_modified_chunks() {
+    let (_, expected) = simple_manifest();
+    let mut actual = expected.clone();
+    actual.file_table[1].hash = [0; 32];
+    actual.chunk_table[2].hash = [0; 32];
+
+    assert_eq!(
+        compare_manifests(&expected, &actual),
+        vec![ManifestMismatch::InvalidFileContent {
+            relative_path: "subdir/memory".into(),
+            chunk_offsets: vec![1024],
+        }]
+    );
+}
+
 #[test]
 fn test_diff_simple_manifest() {
     let (_, manifest_old) = simple_manifest();
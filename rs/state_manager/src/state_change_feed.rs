@@ -0,0 +1,60 @@
+//! A node-local feed of typed state changes per certified height.
+//!
+//! The feed follows the certified states of a `StateReader` and reports, for
+//! every newly certified height, the semantic changes (see
+//! `ic_canonical_state::state_diff`) relative to the previously reported
+//! height. This allows local consumers (e.g. indexers) to follow the subnet
+//! without diffing canonical trees themselves.
+
+use ic_canonical_state::state_diff::{diff_states, StateChange};
+use ic_interfaces::state_manager::{Labeled, StateReader};
+use ic_replicated_state::ReplicatedState;
+use ic_types::Height;
+use std::sync::Arc;
+
+/// Follows the certified states of a `StateReader` and reports the typed
+/// changes between consecutive certified heights.
+pub struct StateChangeFeed {
+    state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+    /// The last state the changes were reported for.
+    last: Labeled<Arc<ReplicatedState>>,
+}
+
+impl StateChangeFeed {
+    /// Creates a feed that starts at the latest certified height of
+    /// `state_reader`, i.e. the first call to `poll` reports changes of the
+    /// heights certified after this call.
+    pub fn new(state_reader: Arc<dyn StateReader<State = ReplicatedState>>) -> Self {
+        let certified_height = state_reader.latest_certified_height();
+        let last = state_reader
+            .get_state_at(certified_height)
+            .unwrap_or_else(|_| state_reader.get_latest_state());
+        Self { state_reader, last }
+    }
+
+    /// Returns the height of the last state changes were reported for.
+    pub fn height(&self) -> Height {
+        self.last.height()
+    }
+
+    /// Returns the typed changes for every height that was certified since the
+    /// previous call, in increasing height order.
+    ///
+    /// Heights whose states are no longer available (e.g. because they were
+    /// already removed by the state manager) are skipped; their changes are
+    /// reported as part of the next available height.
+    pub fn poll(&mut self) -> Vec<(Height, Vec<StateChange>)> {
+        let certified_height = self.state_reader.latest_certified_height();
+        let mut result = Vec::new();
+
+        let mut h = self.last.height().increment();
+        while h <= certified_height {
+            if let Ok(state) = self.state_reader.get_state_at(h) {
+                result.push((h, diff_states(self.last.get_ref(), state.get_ref())));
+                self.last = state;
+            }
+            h = h.increment();
+        }
+        result
+    }
+}
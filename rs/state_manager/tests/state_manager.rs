@@ -12,7 +12,12 @@ use ic_replicated_state::{
     page_map::PageIndex, testing::ReplicatedStateTesting, NumWasmPages, PageMap, ReplicatedState,
     Stream,
 };
-use ic_state_manager::StateManagerImpl;
+use ic_state_layout::StateLayout;
+use ic_state_manager::{
+    checkpoint_verification::{self, CheckpointStatus},
+    manifest::ManifestMismatch,
+    ManifestMetrics, StateManagerImpl,
+};
 use ic_sys::PAGE_SIZE;
 use ic_test_utilities::{
     consensus::fake::FakeVerifier,
@@ -31,11 +36,12 @@ use ic_types::{
     crypto::CryptoHash,
     ingress::{IngressStatus, WasmResult},
     messages::{CallbackId, RequestOrResponse},
+    state_sync::Manifest,
     xnet::{StreamIndex, StreamIndexedQueue},
     CanisterId, CryptoHashOfPartialState, CryptoHashOfState, Height, PrincipalId,
 };
 use proptest::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::{
     collections::HashSet,
@@ -980,6 +986,52 @@ fn latest_certified_state_is_updated_on_state_removal() {
     });
 }
 
+#[test]
+fn state_change_feed_reports_changes_per_certified_height() {
+    use ic_canonical_state::state_diff::StateChange;
+    use ic_state_manager::state_change_feed::StateChangeFeed;
+
+    state_manager_test(|_metrics, state_manager| {
+        let state_manager = Arc::new(state_manager);
+        let mut feed = StateChangeFeed::new(state_manager.clone());
+        assert_eq!(height(0), feed.height());
+
+        let (_height, mut state) = state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(1));
+        state_manager.commit_and_certify(state, height(1), CertificationScope::Metadata);
+
+        // Height 1 is not certified yet.
+        assert_eq!(Vec::<(Height, Vec<StateChange>)>::new(), feed.poll());
+
+        let (_height, mut state) = state_manager.take_tip();
+        state.canister_states.remove(&canister_test_id(1));
+        state_manager.commit_and_certify(state, height(2), CertificationScope::Metadata);
+
+        certify_height(&*state_manager, height(1));
+        certify_height(&*state_manager, height(2));
+
+        assert_eq!(
+            vec![
+                (
+                    height(1),
+                    vec![StateChange::CanisterCreated {
+                        canister_id: canister_test_id(1)
+                    }]
+                ),
+                (
+                    height(2),
+                    vec![StateChange::CanisterDeleted {
+                        canister_id: canister_test_id(1)
+                    }]
+                ),
+            ],
+            feed.poll()
+        );
+        assert_eq!(height(2), feed.height());
+        assert!(feed.poll().is_empty());
+    });
+}
+
 #[test]
 fn can_return_and_remember_certifications() {
     state_manager_test(|_metrics, state_manager| {
@@ -2172,6 +2224,245 @@ fn deletes_diverged_states() {
     );
 }
 
+#[test]
+fn marks_corrupted_checkpoints_diverged_on_startup() {
+    let tmp = Builder::new().prefix("test").tempdir().unwrap();
+    let config = Config::new(tmp.path().into()).with_checkpoint_verification_on_startup(true);
+    with_test_replica_logger(|log| {
+        let make_state_manager = |metrics: &MetricsRegistry| {
+            StateManagerImpl::new(
+                Arc::new(FakeVerifier::new()),
+                subnet_test_id(42),
+                SubnetType::Application,
+                log.clone(),
+                metrics,
+                &config,
+                ic_types::malicious_flags::MaliciousFlags::default(),
+            )
+        };
+
+        let state_manager = make_state_manager(&MetricsRegistry::new());
+        let (_, state) = state_manager.take_tip();
+        state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+        wait_for_checkpoint(&state_manager, height(1));
+
+        let (_, mut state) = state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(100));
+        state_manager.commit_and_certify(state, height(2), CertificationScope::Full);
+        wait_for_checkpoint(&state_manager, height(2));
+        drop(state_manager);
+
+        // Restarting with intact checkpoints doesn't drop any of them.
+        let metrics = MetricsRegistry::new();
+        let state_manager = make_state_manager(&metrics);
+        assert_eq!(height(2), state_manager.latest_state_height());
+        assert_error_counters(&metrics);
+        drop(state_manager);
+
+        // Truncate a file of checkpoint @2, as a crash in the middle of writing
+        // the checkpoint would.
+        let layout = StateLayout::new(log.clone(), tmp.path().into());
+        let system_metadata = layout
+            .checkpoint(height(2))
+            .unwrap()
+            .system_metadata()
+            .raw_path()
+            .to_path_buf();
+        let mut permissions = std::fs::metadata(&system_metadata).unwrap().permissions();
+        permissions.set_readonly(false);
+        std::fs::set_permissions(&system_metadata, permissions).unwrap();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&system_metadata)
+            .unwrap()
+            .set_len(1)
+            .unwrap();
+
+        let metrics = MetricsRegistry::new();
+        let state_manager = make_state_manager(&metrics);
+        assert_eq!(height(1), state_manager.latest_state_height());
+        assert_eq!(
+            vec![height(2)],
+            layout.diverged_checkpoint_heights().unwrap()
+        );
+        assert_eq!(
+            1,
+            fetch_int_counter_vec(&metrics, "critical_errors")
+                .values()
+                .sum::<u64>()
+        );
+    });
+}
+
+/// Commits a state with a canister at height 1 to both `state_manager` and
+/// `peer_state_manager` and returns the manifest of the (identical)
+/// checkpoints.
+fn commit_same_checkpoint(
+    state_manager: &StateManagerImpl,
+    peer_state_manager: &StateManagerImpl,
+) -> Manifest {
+    let mut hashes = Vec::new();
+    for sm in [state_manager, peer_state_manager] {
+        let (_height, mut state) = sm.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(100));
+        sm.commit_and_certify(state, height(1), CertificationScope::Full);
+        hashes.push(wait_for_checkpoint(sm, height(1)));
+    }
+    assert_eq!(hashes[0], hashes[1]);
+
+    let id = StateSyncArtifactId {
+        height: height(1),
+        hash: hashes.pop().unwrap(),
+    };
+    state_manager
+        .get_validated_by_identifier(&id)
+        .expect("failed to get state sync messages")
+        .manifest
+}
+
+/// Flips the first byte of the file at `relative_path` in checkpoint @1.
+fn corrupt_checkpoint_file(layout: &StateLayout, relative_path: &Path) {
+    let path = layout
+        .checkpoint(height(1))
+        .unwrap()
+        .raw_path()
+        .join(relative_path);
+    let byte = std::fs::read(&path).unwrap()[0];
+    make_mutable(&path).unwrap();
+    write_at(&path, &[!byte], 0).unwrap();
+}
+
+#[test]
+fn corrupted_checkpoint_can_be_repaired_from_peer() {
+    state_manager_test(|_metrics, state_manager| {
+        state_manager_test(|_peer_metrics, peer_state_manager| {
+            let manifest = commit_same_checkpoint(&state_manager, &peer_state_manager);
+            let manifests = maplit::btreemap! { height(1) => manifest.clone() };
+            let layout = state_manager.state_layout();
+            let log = no_op_logger();
+            let manifest_metrics = ManifestMetrics::new(&MetricsRegistry::new());
+            let mut thread_pool = scoped_threadpool::Pool::new(NUM_THREADS);
+            let mut checkpoint_status = || {
+                checkpoint_verification::checkpoint_status(
+                    &mut thread_pool,
+                    &manifest_metrics,
+                    &log,
+                    layout,
+                    &manifests,
+                    height(1),
+                )
+                .unwrap()
+            };
+            assert_eq!(checkpoint_status(), CheckpointStatus::Verified);
+
+            // Modify one file, remove another and add one that doesn't belong to
+            // the checkpoint.
+            let mut non_empty_files = manifest
+                .file_table
+                .iter()
+                .filter(|f| f.size_bytes > 0)
+                .map(|f| f.relative_path.clone());
+            let modified = non_empty_files.next().unwrap();
+            let removed = non_empty_files.next().unwrap();
+            let unexpected = PathBuf::from("unexpected_file");
+            corrupt_checkpoint_file(layout, &modified);
+            let cp_root = layout
+                .checkpoint(height(1))
+                .unwrap()
+                .raw_path()
+                .to_path_buf();
+            std::fs::remove_file(cp_root.join(&removed)).unwrap();
+            std::fs::write(cp_root.join(&unexpected), b"garbage").unwrap();
+
+            let mismatches = match checkpoint_status() {
+                CheckpointStatus::Corrupted(mismatches) => mismatches,
+                status => panic!("Expected a corrupted checkpoint, got {}", status),
+            };
+            let mut paths: Vec<_> = mismatches
+                .iter()
+                .map(|m| m.relative_path().to_path_buf())
+                .collect();
+            paths.sort();
+            let mut expected_paths = vec![modified, removed, unexpected.clone()];
+            expected_paths.sort();
+            assert_eq!(paths, expected_paths);
+            assert!(mismatches.contains(&ManifestMismatch::UnexpectedFile {
+                relative_path: unexpected
+            }));
+
+            let status = checkpoint_verification::repair_and_verify_checkpoint(
+                &mut scoped_threadpool::Pool::new(NUM_THREADS),
+                &manifest_metrics,
+                &log,
+                layout,
+                peer_state_manager.state_layout(),
+                &manifests,
+                height(1),
+                mismatches,
+            )
+            .unwrap();
+            assert_eq!(status, CheckpointStatus::Verified);
+            assert_eq!(checkpoint_status(), CheckpointStatus::Verified);
+        });
+    });
+}
+
+#[test]
+fn checkpoint_is_not_repaired_from_corrupted_peer() {
+    state_manager_test(|_metrics, state_manager| {
+        state_manager_test(|_peer_metrics, peer_state_manager| {
+            let manifest = commit_same_checkpoint(&state_manager, &peer_state_manager);
+            let manifests = maplit::btreemap! { height(1) => manifest.clone() };
+            let log = no_op_logger();
+            let manifest_metrics = ManifestMetrics::new(&MetricsRegistry::new());
+            let mut thread_pool = scoped_threadpool::Pool::new(NUM_THREADS);
+
+            // The same file is corrupted on both replicas, so the peer copy must
+            // not be used.
+            let corrupted = manifest
+                .file_table
+                .iter()
+                .find(|f| f.size_bytes > 0)
+                .unwrap()
+                .relative_path
+                .clone();
+            corrupt_checkpoint_file(state_manager.state_layout(), &corrupted);
+            corrupt_checkpoint_file(peer_state_manager.state_layout(), &corrupted);
+
+            let mismatches = checkpoint_verification::verify_checkpoint(
+                &mut thread_pool,
+                &manifest_metrics,
+                &log,
+                state_manager.state_layout(),
+                height(1),
+                &manifest,
+            )
+            .unwrap();
+            assert_eq!(mismatches.len(), 1);
+            assert_eq!(mismatches[0].relative_path(), corrupted.as_path());
+
+            let status = checkpoint_verification::repair_and_verify_checkpoint(
+                &mut thread_pool,
+                &manifest_metrics,
+                &log,
+                state_manager.state_layout(),
+                peer_state_manager.state_layout(),
+                &manifests,
+                height(1),
+                mismatches,
+            )
+            .unwrap();
+            match status {
+                CheckpointStatus::Corrupted(mismatches) => {
+                    assert_eq!(mismatches.len(), 1);
+                    assert_eq!(mismatches[0].relative_path(), corrupted.as_path());
+                }
+                status => panic!("Expected a corrupted checkpoint, got {}", status),
+            }
+        });
+    });
+}
+
 proptest! {
     #[test]
     fn stream_store_encode_decode(stream in arb_stream(0, 10), size_limit in 0..20usize) {
@@ -1,5 +1,6 @@
 //! Computes diff of canonical trees between checkpoints.
 
+use ic_canonical_state::state_diff::{diff_states, StateChange};
 use ic_registry_subnet_type::SubnetType;
 use ic_state_layout::CompleteCheckpointLayout;
 use ic_state_manager::{
@@ -11,8 +12,12 @@ use ic_state_manager::{
 use ic_types::Height;
 use std::path::PathBuf;
 
-/// Loads the checkponts at `path_a` and `path_b` and diffs them.
-fn diff_checkpoints(path_a: PathBuf, path_b: PathBuf) -> Result<Changes, CheckpointError> {
+/// Loads the checkponts at `path_a` and `path_b` and diffs them, both at the
+/// canonical tree level and semantically.
+fn diff_checkpoints(
+    path_a: PathBuf,
+    path_b: PathBuf,
+) -> Result<(Changes, Vec<StateChange>), CheckpointError> {
     let unused_height = Height::from(0);
     let own_subnet_type = SubnetType::Application;
     let state_a = load_checkpoint(
@@ -28,15 +33,30 @@ fn diff_checkpoints(path_a: PathBuf, path_b: PathBuf) -> Result<Changes, Checkpo
 
     let tree_a = hash_state(&state_a);
     let tree_b = hash_state(&state_b);
-    Ok(diff(&tree_a, &tree_b))
+    Ok((diff(&tree_a, &tree_b), diff_states(&state_a, &state_b)))
 }
 
 /// `cdiff` command entry point.
-pub fn do_diff(path_a: PathBuf, path_b: PathBuf) -> Result<(), String> {
-    let d = diff_checkpoints(path_a, path_b).map_err(|err| format!("✗ Diff FAILED:\n\t{}", err))?;
-    if d.is_empty() {
+pub fn do_diff(path_a: PathBuf, path_b: PathBuf, json: bool) -> Result<(), String> {
+    let (d, changes) =
+        diff_checkpoints(path_a, path_b).map_err(|err| format!("✗ Diff FAILED:\n\t{}", err))?;
+
+    if json {
+        let output = serde_json::json!({
+            "identical": d.is_empty(),
+            "changes": changes,
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&output)
+                .map_err(|err| format!("✗ Failed to serialize diff: {}", err))?
+        );
+    } else if d.is_empty() {
         println!("✓ Snapshots are identical");
     } else {
+        for change in changes {
+            println!("{}", change);
+        }
         print!("{}", PrettyPrintedChanges(&d));
     }
 
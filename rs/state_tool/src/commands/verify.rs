@@ -0,0 +1,129 @@
+//! Verifies checkpoints against their persisted manifests.
+
+use crate::commands::utils;
+use ic_logger::replica_logger::no_op_logger;
+use ic_metrics::MetricsRegistry;
+use ic_state_layout::StateLayout;
+use ic_state_manager::{
+    checkpoint_verification::{
+        checkpoint_status, persisted_manifests, repair_and_verify_checkpoint, CheckpointStatus,
+    },
+    ManifestMetrics,
+};
+use ic_types::Height;
+use std::path::PathBuf;
+
+/// Recomputes the manifests of all checkpoints (or only the one at `height`)
+/// under the state root location indicated in the given configuration file,
+/// compares them with the manifests stored in the states metadata and reports
+/// missing, truncated and modified files.
+///
+/// Corrupted checkpoints are repaired with files from the checkpoint at the
+/// same height under the `repair_from` state root, if specified, and verified
+/// again. Checkpoints that are still corrupted afterwards are marked as
+/// diverged if `mark_diverged` is set.
+pub fn do_verify(
+    config: PathBuf,
+    height: Option<u64>,
+    repair_from: Option<PathBuf>,
+    mark_diverged: bool,
+) -> Result<(), String> {
+    let log = no_op_logger();
+    let state_layout = utils::locate_state_root(config)?;
+    let peer_layout = repair_from.map(|root| StateLayout::new(log.clone(), root));
+
+    let mut thread_pool =
+        scoped_threadpool::Pool::new(ic_state_manager::NUMBER_OF_CHECKPOINT_THREADS);
+    let metrics_registry = MetricsRegistry::new();
+    let manifest_metrics = ManifestMetrics::new(&metrics_registry);
+
+    let checkpoint_heights = state_layout
+        .checkpoint_heights()
+        .map_err(|e| format!("failed to list checkpoints: {}", e))?;
+    let heights = match height {
+        Some(h) => {
+            let h = Height::from(h);
+            if !checkpoint_heights.contains(&h) {
+                return Err(format!("checkpoint @{} not found", h));
+            }
+            vec![h]
+        }
+        None => checkpoint_heights,
+    };
+    if heights.is_empty() {
+        println!("No checkpoints to verify");
+        return Ok(());
+    }
+
+    let manifests = persisted_manifests(&log, &state_layout);
+    let mut num_corrupted = 0;
+
+    println!("{:>15}    {:<}", "HEIGHT", "STATUS");
+    for h in heights {
+        let status = checkpoint_status(
+            &mut thread_pool,
+            &manifest_metrics,
+            &log,
+            &state_layout,
+            &manifests,
+            h,
+        )
+        .map_err(|e| format!("failed to verify checkpoint @{}: {}", h, e))?;
+        println!("{:>15}    {}", h.get(), status);
+
+        let mut mismatches = match status {
+            CheckpointStatus::Corrupted(mismatches) => mismatches,
+            CheckpointStatus::Verified | CheckpointStatus::NoManifest => continue,
+        };
+        for mismatch in mismatches.iter() {
+            println!("{:>15}    - {}", "", mismatch);
+        }
+
+        if let Some(peer_layout) = &peer_layout {
+            // Only trust the repair if the checkpoint now matches its manifest.
+            mismatches = match repair_and_verify_checkpoint(
+                &mut thread_pool,
+                &manifest_metrics,
+                &log,
+                &state_layout,
+                peer_layout,
+                &manifests,
+                h,
+                mismatches,
+            )
+            .map_err(|e| format!("failed to repair checkpoint @{}: {}", h, e))?
+            {
+                CheckpointStatus::Corrupted(mismatches) => mismatches,
+                CheckpointStatus::Verified | CheckpointStatus::NoManifest => {
+                    println!("{:>15}    repaired", "");
+                    continue;
+                }
+            };
+            println!(
+                "{:>15}    {} file(s) could not be repaired",
+                "",
+                mismatches.len()
+            );
+            for mismatch in mismatches.iter() {
+                println!("{:>15}    - {}", "", mismatch);
+            }
+        }
+
+        num_corrupted += 1;
+        if mark_diverged {
+            mark_checkpoint_diverged(&state_layout, h)?;
+            println!("{:>15}    marked as diverged", "");
+        }
+    }
+
+    if num_corrupted > 0 && !mark_diverged {
+        return Err(format!("{} corrupted checkpoint(s) found", num_corrupted));
+    }
+    Ok(())
+}
+
+fn mark_checkpoint_diverged(state_layout: &StateLayout, h: Height) -> Result<(), String> {
+    state_layout
+        .mark_checkpoint_diverged(h)
+        .map_err(|e| format!("failed to mark checkpoint @{} as diverged: {}", h, e))
+}
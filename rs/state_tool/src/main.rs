@@ -2,7 +2,7 @@
 //!
 //! A command-line tool to manage Internet Computer replicated states (decode
 //! persisted state files, diff checkpoints, compute partial state hashes and
-//! checkpoint manifests, import state trees).
+//! checkpoint manifests, import state trees, verify checkpoints).
 
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -15,7 +15,14 @@ mod commands;
 enum Opt {
     /// Computes diff of canonical trees between checkpoints.
     #[structopt(name = "cdiff")]
-    CDiff { path_a: PathBuf, path_b: PathBuf },
+    CDiff {
+        path_a: PathBuf,
+        path_b: PathBuf,
+
+        /// Print the typed changes as JSON instead of human-readable text.
+        #[structopt(long = "json")]
+        json: bool,
+    },
 
     /// Computes partial state hash that is used for certification.
     #[structopt(name = "chash")]
@@ -57,6 +64,27 @@ enum Opt {
         config: PathBuf,
     },
 
+    /// Verifies checkpoints against the manifests stored in the states
+    /// metadata.
+    #[structopt(name = "verify")]
+    Verify {
+        /// Path to the replica configuration (ic.json).
+        #[structopt(long = "config")]
+        config: PathBuf,
+
+        /// Only verify the checkpoint at this height.
+        #[structopt(long = "height")]
+        height: Option<u64>,
+
+        /// Path to the state root of a peer to repair corrupted files from.
+        #[structopt(long = "repair-from")]
+        repair_from: Option<PathBuf>,
+
+        /// Mark checkpoints that are (still) corrupted as diverged.
+        #[structopt(long = "mark-diverged")]
+        mark_diverged: bool,
+    },
+
     /// Displays a pretty-printed debug view of a state file.
     #[structopt(name = "decode")]
     Decode {
@@ -69,7 +97,11 @@ enum Opt {
 fn main() {
     let opt = Opt::from_args();
     let result = match opt {
-        Opt::CDiff { path_a, path_b } => commands::cdiff::do_diff(path_a, path_b),
+        Opt::CDiff {
+            path_a,
+            path_b,
+            json,
+        } => commands::cdiff::do_diff(path_a, path_b, json),
         Opt::CHash { path } => commands::chash::do_hash(path),
         Opt::ImportState {
             state,
@@ -78,6 +110,12 @@ fn main() {
         } => commands::import_state::do_import(state, config, height),
         Opt::Manifest { path } => commands::manifest::do_compute_manifest(path),
         Opt::ListStates { config } => commands::list::do_list(config),
+        Opt::Verify {
+            config,
+            height,
+            repair_from,
+            mark_diverged,
+        } => commands::verify::do_verify(config, height, repair_from, mark_diverged),
         Opt::Decode { file } => commands::decode::do_decode(file),
     };
 
@@ -20,6 +20,7 @@ use ic_replicated_state::ReplicatedState;
 use ic_test_utilities::types::ids::{node_test_id, subnet_test_id};
 use ic_test_utilities::{consensus::fake::*, crypto::CryptoReturningOk, mock_time};
 use ic_types::batch::ValidationContext;
+use ic_types::consensus::equivocation::EquivocationEvidence;
 use ic_types::crypto::threshold_sig::ni_dkg::DkgId;
 use ic_types::{consensus::*, crypto::*, *};
 use std::sync::Arc;
@@ -685,6 +686,10 @@ impl ConsensusPool for TestConsensusPool {
     fn as_block_cache(&self) -> &dyn ConsensusBlockCache {
         self.pool.as_block_cache()
     }
+
+    fn equivocation_evidence(&self) -> Vec<EquivocationEvidence> {
+        self.pool.equivocation_evidence()
+    }
 }
 
 impl MutableConsensusPool for TestConsensusPool {
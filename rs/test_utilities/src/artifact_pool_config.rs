@@ -1,5 +1,6 @@
 use ic_config::artifact_pool::{
-    ArtifactPoolConfig, ArtifactPoolTomlConfig, LMDBConfig, PersistentPoolBackend, RocksDBConfig,
+    AppendLogConfig, ArtifactPoolConfig, ArtifactPoolTomlConfig, LMDBConfig, PersistentPoolBackend,
+    RocksDBConfig,
 };
 use tempfile::Builder;
 
@@ -36,6 +37,19 @@ pub fn with_test_lmdb_pool_config<T>(run: impl FnOnce(LMDBConfig) -> T) -> T {
     run(config)
 }
 
+/// Creates a new AppendLogConfig, based on the default, for tests.
+/// It removes the persistent pool directory afterwards.
+pub fn with_test_append_log_pool_config<T>(run: impl FnOnce(AppendLogConfig) -> T) -> T {
+    let tempdir = Builder::new().prefix("persistent-pool").tempdir().unwrap();
+    let mut toml_config = ArtifactPoolTomlConfig::new(tempdir.path().to_path_buf(), None);
+    toml_config.consensus_pool_backend = Some("append_log".to_string());
+    let config = match ArtifactPoolConfig::from(toml_config).persistent_pool_backend {
+        PersistentPoolBackend::AppendLog(config) => config,
+        _ => panic!("Missing append_log persistent pool config"),
+    };
+    run(config)
+}
+
 /// Creates a set of ArtifactPoolConfig(s), based on the default, for tests.
 /// It removes all persistent pool directories afterwards.
 pub fn with_test_pool_configs<T>(num: usize, run: impl FnOnce(Vec<ArtifactPoolConfig>) -> T) -> T {
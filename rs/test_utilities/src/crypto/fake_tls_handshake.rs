@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use ic_crypto_tls_interfaces::{
-    AllowedClients, AuthenticatedPeer, TlsClientHandshakeError, TlsHandshake,
-    TlsServerHandshakeError, TlsStream,
+    AllowedClients, AuthenticatedPeer, TlsClientConfig, TlsClientHandshakeError, TlsHandshake,
+    TlsServerConfig, TlsServerHandshakeError, TlsStream,
 };
 use ic_types::{NodeId, RegistryVersion};
 use tokio::net::TcpStream;
@@ -75,4 +75,20 @@ impl TlsHandshake for FakeTlsHandshake {
     ) -> Result<TlsStream, TlsClientHandshakeError> {
         unimplemented!()
     }
+
+    fn quic_server_config(
+        &self,
+        _allowed_clients: AllowedClients,
+        _registry_version: RegistryVersion,
+    ) -> Result<TlsServerConfig, TlsServerHandshakeError> {
+        unimplemented!()
+    }
+
+    fn quic_client_config(
+        &self,
+        _server: NodeId,
+        _registry_version: RegistryVersion,
+    ) -> Result<TlsClientConfig, TlsClientHandshakeError> {
+        unimplemented!()
+    }
 }
@@ -14,7 +14,7 @@ use ic_protobuf::registry::node::v1::{
 use ic_registry_common::proto_registry_data_provider::ProtoRegistryDataProvider;
 use ic_types::{
     replica_config::ReplicaConfig,
-    transport::{TransportConfig, TransportFlowConfig},
+    transport::{TransportBackend, TransportConfig, TransportFlowConfig},
     NodeId, RegistryVersion, SubnetId,
 };
 
@@ -331,6 +331,7 @@ pub fn get_replica_transport_config(
             server_port: port,
             queue_size: 8,
         }],
+        transport_backend: TransportBackend::Tcp,
     }
 }
 
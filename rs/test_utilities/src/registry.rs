@@ -5,7 +5,10 @@ use ic_interfaces::registry::{
     RegistryClientVersionedResult,
 };
 use ic_interfaces::time_source::TimeSource;
-use ic_protobuf::registry::subnet::v1::{CatchUpPackageContents, SubnetListRecord, SubnetRecord};
+use ic_protobuf::registry::subnet::v1::{
+    AdaptiveBlockRateConfig, CatchUpPackageContents, ConsensusParameters, SubnetListRecord,
+    SubnetRecord,
+};
 use ic_registry_client::fake::FakeRegistryClient;
 use ic_registry_common::proto_registry_data_provider::ProtoRegistryDataProvider;
 use ic_registry_keys::{
@@ -169,6 +172,8 @@ pub fn test_subnet_record() -> SubnetRecord {
         ssh_readonly_access: vec![],
         ssh_backup_access: vec![],
         ecdsa_config: None,
+        adaptive_block_rate_config: None,
+        consensus_parameters: None,
     }
 }
 
@@ -223,6 +228,16 @@ impl SubnetRecordBuilder {
         self
     }
 
+    pub fn with_adaptive_block_rate_config(mut self, config: AdaptiveBlockRateConfig) -> Self {
+        self.record.adaptive_block_rate_config = Some(config);
+        self
+    }
+
+    pub fn with_consensus_parameters(mut self, parameters: ConsensusParameters) -> Self {
+        self.record.consensus_parameters = Some(parameters);
+        self
+    }
+
     pub fn build(self) -> SubnetRecord {
         self.record
     }
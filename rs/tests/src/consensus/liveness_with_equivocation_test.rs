@@ -11,12 +11,14 @@ Runbook::
 . Pull the last sent message and compare with the expectation.
 
 Success:: Check the messages have really been written to memory
-by pulling the last one from a different node.
+by pulling the last one from a different node. Check that the honest
+nodes detected the equivocations and report them as a metric.
 The `ekg::finalized_height_progress_within` does not detect any stuck node. This is part of `ekg::basic_monitoring`, and
 hence, checked by default.
 
 Coverage::
 . Consensus doesn't break in the presence of simple malicious behavior
+. Equivocating block makers are detected
 
 
 end::catalog[] */
@@ -31,8 +33,14 @@ use ic_registry_subnet_type::SubnetType;
 use ic_types::malicious_behaviour::MaliciousBehaviour;
 use rand::Rng;
 use slog::{debug, info, Logger};
+use std::time::{Duration, Instant};
 use url::Url;
 
+/// The metric counting the equivocating block proposals a replica detected.
+const EQUIVOCATIONS_METRIC: &str = "consensus_equivocations{artifact=\"block_proposal\"}";
+/// How long to wait for an honest node to detect an equivocation.
+const EQUIVOCATION_TIMEOUT: Duration = Duration::from_secs(120);
+
 pub fn config() -> InternetComputer {
     let malicious_beh = MaliciousBehaviour::new(true).set_maliciously_propose_equivocating_blocks();
 
@@ -72,6 +80,40 @@ pub fn test(mut handle: IcHandle, ctx: &ic_fondue::pot::Context) {
     let (last_pulled_msg, last_pushed_msg) =
         rt.block_on(do_the_work(&ctx.logger, &mut rng, &n1.url, &n2.url));
     assert_eq!(last_pulled_msg, last_pushed_msg);
+
+    // The malicious node equivocates whenever it is the block maker of rank
+    // 0, so every honest node eventually holds evidence of it.
+    for url in &[&n1.url, &n2.url] {
+        let mut metrics_url = (*url).clone();
+        metrics_url.set_port(Some(9090)).unwrap();
+        let start = Instant::now();
+        loop {
+            let equivocations = get_equivocations(&metrics_url);
+            info!(ctx.logger, "Detected equivocations"; "url" => url.as_str(), "count" => equivocations);
+            if equivocations > 0 {
+                break;
+            }
+            if start.elapsed() > EQUIVOCATION_TIMEOUT {
+                panic!("{} did not detect any equivocation", url);
+            }
+            std::thread::sleep(Duration::from_secs(5));
+        }
+    }
+}
+
+/// Returns the number of equivocations reported on the given metrics
+/// endpoint, or 0 if the endpoint cannot be reached.
+fn get_equivocations(metrics_url: &Url) -> u64 {
+    reqwest::blocking::get(metrics_url.clone())
+        .and_then(|response| response.text())
+        .ok()
+        .and_then(|metrics| {
+            metrics.lines().find_map(|line| {
+                line.strip_prefix(EQUIVOCATIONS_METRIC)
+                    .and_then(|value| value.trim().parse::<f64>().ok())
+            })
+        })
+        .map_or(0, |value| value as u64)
 }
 
 const MSG_LEN: usize = 8;
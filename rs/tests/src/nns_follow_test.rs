@@ -30,6 +30,7 @@ use crate::util::{get_random_nns_node_endpoint, runtime_from_url};
 
 use ic_fondue::{ic_instance::InternetComputer, ic_manager::IcHandle};
 
+use ic_nns_governance::nns_function_payload::UpgradeRootProposalPayload;
 use ic_nns_governance::pb::v1::{
     governance_error::ErrorType,
     manage_neuron::{Command, Follow, NeuronIdOrSubaccount, RegisterVote, Split},
@@ -38,6 +39,7 @@ use ic_nns_governance::pb::v1::{
 };
 
 use crate::nns::NnsExt;
+use candid::Encode;
 use canister_test::Canister;
 use dfn_candid::{candid, candid_one};
 use ic_nns_test_utils::ids::{TEST_NEURON_1_ID, TEST_NEURON_2_ID, TEST_NEURON_3_ID};
@@ -219,8 +221,7 @@ pub fn test(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
         assert_no_followees(&ctx.logger, &governance, n2, valid_topic).await;
 
         // make a proposal via n2 before setting up followees
-        let proposal =
-            submit_proposal(&ctx.logger, &governance, n2, NnsFunction::NnsRootUpgrade).await;
+        let proposal = submit_proposal(&ctx.logger, &governance, n2).await;
 
         let votes = check_votes(&ctx.logger, &governance, proposal).await;
         assert_eq!(votes, 140_400_410);
@@ -294,8 +295,7 @@ pub fn test(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
         );
 
         // make another proposal via n2 now that followees are set up
-        let proposal =
-            submit_proposal(&ctx.logger, &governance, n2, NnsFunction::NnsRootUpgrade).await;
+        let proposal = submit_proposal(&ctx.logger, &governance, n2).await;
 
         // verify that all three neurons did vote
         let votes = check_votes(&ctx.logger, &governance, proposal).await;
@@ -383,8 +383,7 @@ pub fn test(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
 
         // fire off a new proposal by n1, and see all neurons voting
         // immediately along the chain
-        let proposal =
-            submit_proposal(&ctx.logger, &governance, n1, NnsFunction::NnsRootUpgrade).await;
+        let proposal = submit_proposal(&ctx.logger, &governance, n1).await;
 
         // verify that all four neurons did vote
         let votes = check_votes(&ctx.logger, &governance, proposal).await;
@@ -570,15 +569,20 @@ async fn submit_proposal(
     logger: &slog::Logger,
     gov: &Canister<'_>,
     neuron: (NeuronId, &Keypair),
-    update_type: NnsFunction,
 ) -> ProposalId {
+    // An empty wasm module: the proposal is voted on, but can't be executed.
+    let payload = UpgradeRootProposalPayload {
+        wasm_module: Vec::new(),
+        module_arg: Vec::new(),
+        stop_upgrade_start: false,
+    };
     let proposal = Proposal {
         title: Some("<proposal created from initialization>".to_string()),
         summary: "".to_string(),
         url: "".to_string(),
         action: Some(proposal::Action::ExecuteNnsFunction(ExecuteNnsFunction {
-            nns_function: update_type as i32,
-            payload: Vec::new(),
+            nns_function: NnsFunction::NnsRootUpgrade as i32,
+            payload: Encode!(&payload).unwrap(),
         })),
     };
 
@@ -422,6 +422,8 @@ pub fn get_updatesubnetpayload(
         max_instructions_per_install_code: None,
         features: None,
         ecdsa_config: None,
+        adaptive_block_rate_config: None,
+        consensus_parameters: None,
         max_number_of_canisters: None,
         ssh_readonly_access: readonly_keys,
         ssh_backup_access: backup_keys,
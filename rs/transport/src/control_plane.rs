@@ -19,7 +19,7 @@ use ic_interfaces::transport::AsyncTransportEventHandler;
 use ic_logger::{error, info, warn, ReplicaLogger};
 use ic_protobuf::registry::node::v1::NodeRecord;
 use ic_types::{
-    transport::{FlowId, FlowTag, TransportErrorCode},
+    transport::{FlowId, FlowTag, TransportBackend, TransportErrorCode},
     NodeId, RegistryVersion,
 };
 use std::collections::HashMap;
@@ -31,10 +31,10 @@ use tokio::net::{TcpListener, TcpSocket, TcpStream};
 use tokio::time::sleep;
 
 /// Time to wait before retrying an unsuccessful connection attempt
-const CONNECT_RETRY_SECONDS: u64 = 3;
+pub(crate) const CONNECT_RETRY_SECONDS: u64 = 3;
 
 /// Time to wait for the TLS handshake (for both client/server sides)
-const TLS_HANDSHAKE_TIMEOUT_SECONDS: u64 = 30;
+pub(crate) const TLS_HANDSHAKE_TIMEOUT_SECONDS: u64 = 30;
 
 /// Connection status values
 #[derive(Debug)]
@@ -63,6 +63,9 @@ impl TransportImpl {
             self.allowed_clients.write().unwrap().insert(*peer_id);
         }
         *self.registry_version.write().unwrap() = registry_version;
+        if let Some(endpoint) = &client_state.quic_endpoint {
+            self.refresh_quic_server_config(endpoint);
+        }
         info!(
             self.log,
             "ControlPlane::start_peer_connections(): node_id = {:?} peer_id = {:?}",
@@ -100,6 +103,10 @@ impl TransportImpl {
             }
         }
         client_state.peer_map.remove(peer_id);
+        if let Some(endpoint) = &client_state.quic_endpoint {
+            self.refresh_quic_server_config(endpoint);
+            self.close_quic_connection(peer_id);
+        }
 
         info!(
             self.log,
@@ -128,6 +135,7 @@ impl TransportImpl {
         // TODO: P2P-514
         let mut queue_size_map = HashMap::new();
         let flow_ips = get_flow_ips(peer_record)?;
+        let quic_peer_addr = Self::quic_peer_addr(peer_record);
         for flow_config in &self.config.p2p_flows {
             let flow_tag = FlowTag::from(flow_config.flow_tag);
             queue_size_map.insert(flow_tag, QueueSize::from(flow_config.queue_size));
@@ -190,15 +198,18 @@ impl TransportImpl {
             let peer_ip = IpAddr::from_str(endpoint.ip_addr.as_str())
                 .unwrap_or_else(|_| panic!("Invalid node IP: {}", endpoint.ip_addr));
             let flow_label = get_flow_label(endpoint.ip_addr.as_str(), peer_id);
-            let server_port = endpoint.port as u16;
-            let connecting_task = self.spawn_connect_task(
-                flow_endpoint.flow_tag.into(),
-                *peer_id,
-                peer_ip,
-                ServerPort::from(server_port),
-            );
+            let peer_addr = match self.config.transport_backend {
+                TransportBackend::Tcp => SocketAddr::new(peer_ip, endpoint.port as u16),
+                // All flows share the peer's QUIC endpoint.
+                TransportBackend::Quic => match quic_peer_addr {
+                    Some(peer_addr) => peer_addr,
+                    None => continue,
+                },
+            };
+            let connecting_task =
+                self.spawn_flow_connect_task(flow_endpoint.flow_tag.into(), *peer_id, peer_addr);
             let connecting_state = Connecting {
-                peer_addr: SocketAddr::new(peer_ip, server_port),
+                peer_addr,
                 connecting_task,
             };
             let flow_id = FlowId {
@@ -227,6 +238,25 @@ impl TransportImpl {
         Ok(())
     }
 
+    /// Spawns the task that establishes the connection of a flow with a peer
+    /// we are the client of, using the configured transport backend.
+    fn spawn_flow_connect_task(
+        &self,
+        flow_tag: FlowTag,
+        peer_id: NodeId,
+        peer_addr: SocketAddr,
+    ) -> AbortHandle {
+        match self.config.transport_backend {
+            TransportBackend::Tcp => self.spawn_connect_task(
+                flow_tag,
+                peer_id,
+                peer_addr.ip(),
+                ServerPort::from(peer_addr.port()),
+            ),
+            TransportBackend::Quic => self.spawn_quic_connect_task(flow_tag, peer_id, peer_addr),
+        }
+    }
+
     /// Starts the async task to accept the incoming TcpStreams in server mode.
     fn spawn_accept_task(&self, flow_tag: FlowTag, tcp_listener: TcpListener) -> AbortHandle {
         let weak_self = self.weak_self.read().unwrap().clone();
@@ -497,12 +527,8 @@ impl TransportImpl {
             // reconnect if we have a listener
             if client_state.accept_ports.contains_key(&flow_id.flow_tag) {
                 let socket_addr = sa.peer_addr;
-                let connecting_task = self.spawn_connect_task(
-                    flow_id.flow_tag,
-                    flow_id.peer_id,
-                    socket_addr.ip(),
-                    ServerPort::from(socket_addr.port()),
-                );
+                let connecting_task =
+                    self.spawn_flow_connect_task(flow_id.flow_tag, flow_id.peer_id, socket_addr);
                 let connecting_state = Connecting {
                     peer_addr: socket_addr,
                     connecting_task,
@@ -840,7 +866,7 @@ impl TransportImpl {
     }
 
     /// Returns our role wrt the peer connection
-    fn connection_role(my_id: &NodeId, peer: &NodeId) -> ConnectionRole {
+    pub(crate) fn connection_role(my_id: &NodeId, peer: &NodeId) -> ConnectionRole {
         assert!(*my_id != *peer);
         if *my_id > *peer {
             ConnectionRole::Server
@@ -879,6 +905,30 @@ impl TransportImpl {
             return Err(TransportErrorCode::TransportClientAlreadyRegistered);
         }
 
+        if self.config.transport_backend == TransportBackend::Quic {
+            // A single QUIC endpoint accepts the streams of all flows.
+            let (quic_endpoint, accept_task) = self.init_quic_endpoint()?;
+            let accept_ports = self
+                .config
+                .p2p_flows
+                .iter()
+                .map(|flow_config| {
+                    let accept_task = accept_task.clone();
+                    (
+                        FlowTag::from(flow_config.flow_tag),
+                        ServerPortState { accept_task },
+                    )
+                })
+                .collect();
+            client_map.replace(ClientState {
+                accept_ports,
+                peer_map: HashMap::new(),
+                event_handler,
+                quic_endpoint: Some(quic_endpoint),
+            });
+            return Ok(());
+        }
+
         // Bind to the server ports.
         let mut listeners = Vec::new();
         for flow_config in &self.config.p2p_flows {
@@ -900,6 +950,7 @@ impl TransportImpl {
             accept_ports,
             peer_map: HashMap::new(),
             event_handler,
+            quic_endpoint: None,
         });
 
         Ok(())
@@ -910,10 +961,10 @@ impl TransportImpl {
 mod tests {
     use crate::transport::create_transport;
     use async_trait::async_trait;
-    use crossbeam_channel::{bounded, Sender};
+    use crossbeam_channel::{bounded, unbounded, Sender};
     use ic_crypto::utils::TempCryptoComponent;
     use ic_interfaces::transport::{AsyncTransportEventHandler, SendError};
-    use ic_logger::warn;
+    use ic_logger::{warn, ReplicaLogger};
     use ic_metrics::MetricsRegistry;
     use ic_protobuf::registry::node::v1::{
         connection_endpoint::Protocol, ConnectionEndpoint, FlowEndpoint, NodeRecord,
@@ -921,16 +972,19 @@ mod tests {
     use ic_registry_client::fake::FakeRegistryClient;
     use ic_registry_common::proto_registry_data_provider::ProtoRegistryDataProvider;
     use ic_registry_keys::make_crypto_tls_cert_key;
-    use ic_test_utilities::types::ids::{NODE_1, NODE_2};
+    use ic_test_utilities::types::ids::{NODE_1, NODE_2, NODE_3};
     use ic_test_utilities::with_test_replica_logger;
     use ic_types::transport::TransportErrorCode;
     use ic_types::{
         transport::{
-            FlowId, TransportConfig, TransportFlowConfig, TransportPayload, TransportStateChange,
+            FlowId, FlowTag, TransportBackend, TransportConfig, TransportFlowConfig,
+            TransportPayload, TransportStateChange,
         },
         NodeId, RegistryVersion,
     };
+    use std::collections::BTreeSet;
     use std::sync::Arc;
+    use std::time::Duration;
 
     const NODE_ID_1: NodeId = NODE_1;
     const NODE_ID_2: NodeId = NODE_2;
@@ -941,6 +995,9 @@ mod tests {
     const PORT_1: u16 = 65001;
     const PORT_2: u16 = 65002;
 
+    /// Time to wait for flows to come up and messages to be delivered
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
     struct FakeEventHandler {
         connected: Sender<bool>,
     }
@@ -1000,6 +1057,7 @@ mod tests {
             let mut client_config_1 = TransportConfig {
                 node_ip: "0.0.0.0".to_string(),
                 p2p_flows: Vec::new(),
+                transport_backend: TransportBackend::Tcp,
             };
             let flow_internal_1 = TransportFlowConfig {
                 flow_tag: FLOW_TAG_1,
@@ -1020,6 +1078,7 @@ mod tests {
             let mut client_config_2 = TransportConfig {
                 node_ip: "0.0.0.0".to_string(),
                 p2p_flows: Vec::new(),
+                transport_backend: TransportBackend::Tcp,
             };
             let flow_internal_2 = TransportFlowConfig {
                 flow_tag: FLOW_TAG_2,
@@ -1080,6 +1139,269 @@ mod tests {
         });
     }
 
+    /// Records the flow state changes and the messages received by a node
+    struct RecordingEventHandler {
+        node_id: NodeId,
+        flow_up: Sender<(NodeId, FlowId)>,
+        flow_down: Sender<(NodeId, FlowId)>,
+        received: Sender<(NodeId, FlowId, TransportPayload)>,
+    }
+
+    #[async_trait]
+    impl AsyncTransportEventHandler for RecordingEventHandler {
+        async fn send_message(
+            &self,
+            flow: FlowId,
+            message: TransportPayload,
+        ) -> Result<(), SendError> {
+            let _ = self.received.send((self.node_id, flow, message));
+            Ok(())
+        }
+
+        async fn state_changed(&self, state_change: TransportStateChange) {
+            let _ = match state_change {
+                TransportStateChange::PeerFlowUp(flow) => self.flow_up.send((self.node_id, flow)),
+                TransportStateChange::PeerFlowDown(flow) => {
+                    self.flow_down.send((self.node_id, flow))
+                }
+            };
+        }
+
+        async fn error(&self, _flow: FlowId, _error: TransportErrorCode) {}
+    }
+
+    /// What the nodes observed in `run_flows()`, which must not depend on the
+    /// backend
+    #[derive(Debug, PartialEq)]
+    struct Observations {
+        /// The messages received while all nodes were connected, as
+        /// `(receiver, sender, flow tag, payload)`
+        delivered: Vec<(NodeId, NodeId, u32, Vec<u8>)>,
+        /// The flows that went down on the removed node, as `(node, peer,
+        /// flow tag)`
+        removed_flows_down: BTreeSet<(NodeId, NodeId, u32)>,
+        /// The result of sending to the removed peer
+        send_to_removed: Result<(), TransportErrorCode>,
+        /// The flows that came up again after re-adding the peer, as `(node,
+        /// peer, flow tag)`
+        readded_flows_up: BTreeSet<(NodeId, NodeId, u32)>,
+        /// The messages received after re-adding the peer
+        delivered_after_readding: Vec<(NodeId, NodeId, u32, Vec<u8>)>,
+    }
+
+    /// Starts 3 nodes using `backend` on the loopback interface (on ports
+    /// starting at `base_port`) and connects all of them with each other.
+    /// Then
+    /// * sends one message per ordered pair of nodes and flow,
+    /// * removes node 1 on node 3 (the server of both of its peers), and
+    /// * adds node 1 on node 3 again, which node 1 reconnects to, and sends
+    ///   one message per flow in both directions between them.
+    fn run_flows(
+        backend: TransportBackend,
+        base_port: u16,
+        logger: &ReplicaLogger,
+    ) -> Observations {
+        let node_ids = [NODE_1, NODE_2, NODE_3];
+        let flow_tags = [FLOW_TAG_1, FLOW_TAG_2];
+        let port = |node: usize, flow: usize| base_port + (node * flow_tags.len() + flow) as u16;
+
+        let registry_and_data = empty_registry();
+        let cryptos: Vec<_> = node_ids
+            .iter()
+            .map(|node_id| {
+                temp_crypto_component_with_tls_keys_in_registry(&registry_and_data, *node_id)
+            })
+            .collect();
+        registry_and_data.registry.update_to_latest_version();
+
+        let node_records: Vec<NodeRecord> = (0..node_ids.len())
+            .map(|node| {
+                let mut node_record: NodeRecord = Default::default();
+                for (flow, flow_tag) in flow_tags.iter().enumerate() {
+                    node_record.p2p_flow_endpoints.push(FlowEndpoint {
+                        flow_tag: *flow_tag,
+                        endpoint: Some(ConnectionEndpoint {
+                            ip_addr: "127.0.0.1".to_string(),
+                            port: port(node, flow) as u32,
+                            protocol: Protocol::P2p1Tls13 as i32,
+                        }),
+                    });
+                }
+                node_record
+            })
+            .collect();
+
+        let (flow_up_sender, flow_up) = unbounded();
+        let (flow_down_sender, flow_down) = unbounded();
+        let (received_sender, received) = unbounded();
+        let transports: Vec<_> = cryptos
+            .into_iter()
+            .enumerate()
+            .map(|(node, crypto)| {
+                let config = TransportConfig {
+                    node_ip: "127.0.0.1".to_string(),
+                    p2p_flows: flow_tags
+                        .iter()
+                        .enumerate()
+                        .map(|(flow, flow_tag)| TransportFlowConfig {
+                            flow_tag: *flow_tag,
+                            server_port: port(node, flow),
+                            queue_size: 10,
+                        })
+                        .collect(),
+                    transport_backend: backend,
+                };
+                let transport = create_transport(
+                    node_ids[node],
+                    config,
+                    REG_V1,
+                    MetricsRegistry::new(),
+                    Arc::new(crypto),
+                    tokio::runtime::Handle::current(),
+                    logger.clone(),
+                );
+                transport
+                    .register_client(Arc::new(RecordingEventHandler {
+                        node_id: node_ids[node],
+                        flow_up: flow_up_sender.clone(),
+                        flow_down: flow_down_sender.clone(),
+                        received: received_sender.clone(),
+                    }))
+                    .expect("register_client");
+                transport
+            })
+            .collect();
+        for (node, transport) in transports.iter().enumerate() {
+            for (peer, peer_record) in node_records.iter().enumerate() {
+                if peer != node {
+                    transport
+                        .start_connections(&node_ids[peer], peer_record, REG_V1)
+                        .expect("start_connections");
+                }
+            }
+        }
+
+        // Every node sees all flows with all of its peers come up.
+        let num_flows = node_ids.len() * (node_ids.len() - 1) * flow_tags.len();
+        for _ in 0..num_flows {
+            flow_up.recv_timeout(TIMEOUT).expect("flow did not come up");
+        }
+
+        let send = |node: usize, peer: usize, flow: usize| {
+            transports[node]
+                .send(
+                    &node_ids[peer],
+                    FlowTag::from(flow_tags[flow]),
+                    TransportPayload(vec![node as u8, peer as u8, flow as u8]),
+                )
+                .expect("send")
+        };
+        let receive = |num_messages: usize| {
+            let mut messages: Vec<_> = (0..num_messages)
+                .map(|_| {
+                    let (receiver, flow_id, message) = received
+                        .recv_timeout(TIMEOUT)
+                        .expect("message not received");
+                    (receiver, flow_id.peer_id, flow_id.flow_tag.get(), message.0)
+                })
+                .collect();
+            messages.sort();
+            messages
+        };
+
+        for node in 0..node_ids.len() {
+            for peer in 0..node_ids.len() {
+                for flow in 0..flow_tags.len() {
+                    if peer != node {
+                        send(node, peer, flow);
+                    }
+                }
+            }
+        }
+        let delivered = receive(num_flows);
+
+        // Node 3 removes node 1: node 1 sees both flows go down (and keeps
+        // trying to reconnect), node 3 can no longer send to node 1.
+        let (removed, remover) = (0, 2);
+        transports[remover]
+            .stop_connections(&node_ids[removed])
+            .expect("stop_connections");
+        let mut removed_flows_down = BTreeSet::new();
+        while removed_flows_down.len() < flow_tags.len() {
+            let (node, flow_id) = flow_down
+                .recv_timeout(TIMEOUT)
+                .expect("flow did not go down");
+            removed_flows_down.insert((node, flow_id.peer_id, flow_id.flow_tag.get()));
+        }
+        let send_to_removed = transports[remover].send(
+            &node_ids[removed],
+            FlowTag::from(flow_tags[0]),
+            TransportPayload(vec![]),
+        );
+
+        // Node 1 reconnects once node 3 adds it again. Node 1 may see its
+        // reconnection attempts come up and go down while it is removed, so
+        // only the flows coming up on node 3 are awaited.
+        while flow_up.try_recv().is_ok() {}
+        transports[remover]
+            .start_connections(&node_ids[removed], &node_records[removed], REG_V1)
+            .expect("start_connections");
+        let mut readded_flows_up = BTreeSet::new();
+        while readded_flows_up.len() < flow_tags.len() {
+            let (node, flow_id) = flow_up.recv_timeout(TIMEOUT).expect("flow did not come up");
+            if node == node_ids[remover] {
+                readded_flows_up.insert((node, flow_id.peer_id, flow_id.flow_tag.get()));
+            }
+        }
+        for flow in 0..flow_tags.len() {
+            send(removed, remover, flow);
+            send(remover, removed, flow);
+        }
+        let delivered_after_readding = receive(2 * flow_tags.len());
+
+        Observations {
+            delivered,
+            removed_flows_down,
+            send_to_removed,
+            readded_flows_up,
+            delivered_after_readding,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn tcp_and_quic_backends_behave_the_same() {
+        with_test_replica_logger(|logger| {
+            let tcp = run_flows(TransportBackend::Tcp, 65100, &logger);
+            let quic = run_flows(TransportBackend::Quic, 65200, &logger);
+
+            // 3 nodes, 2 peers each, 2 flows per peer.
+            assert_eq!(tcp.delivered.len(), 12);
+            assert!(tcp
+                .delivered
+                .iter()
+                .all(|(receiver, sender, flow_tag, payload)| {
+                    let flow = if *flow_tag == FLOW_TAG_1 { 0 } else { 1 };
+                    receiver != sender && payload[2] == flow
+                }));
+            let removed_flows_down: BTreeSet<_> = [FLOW_TAG_1, FLOW_TAG_2]
+                .iter()
+                .map(|flow_tag| (NODE_1, NODE_3, *flow_tag))
+                .collect();
+            assert_eq!(tcp.removed_flows_down, removed_flows_down);
+            assert_eq!(
+                tcp.send_to_removed,
+                Err(TransportErrorCode::TransportClientNotFound)
+            );
+            let readded_flows_up: BTreeSet<_> = [FLOW_TAG_1, FLOW_TAG_2]
+                .iter()
+                .map(|flow_tag| (NODE_3, NODE_1, *flow_tag))
+                .collect();
+            assert_eq!(tcp.readded_flows_up, readded_flows_up);
+            assert_eq!(tcp.delivered_after_readding.len(), 4);
+            assert_eq!(tcp, quic);
+        });
+    }
+
     struct RegistryAndDataProvider {
         data_provider: Arc<ProtoRegistryDataProvider>,
         registry: Arc<FakeRegistryClient>,
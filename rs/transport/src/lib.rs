@@ -31,10 +31,16 @@
 //! messages (artifact chunks), for ingress manager, consensus (incl DKG and
 //! certification) and state sync. Thus, Transport has to handle 3 x 3 flows per
 //! peer for Gossip.
+//!
+//! Flows are carried either by TLS over TCP (one connection per flow and peer)
+//! or by QUIC (one connection per peer, one stream per flow), as selected by
+//! `TransportConfig::transport_backend`. Both backends expose the same
+//! interface to transport clients.
 
 mod control_plane;
 mod data_plane;
 mod metrics;
+mod quic;
 pub mod transport;
 mod types;
 mod utils;
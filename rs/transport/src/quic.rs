@@ -0,0 +1,519 @@
+//! QUIC backend - Transport connection management over QUIC.
+//!
+//! With the TCP backend, every flow with a peer is a separate TLS/TCP
+//! connection. With the QUIC backend, a node runs a single QUIC endpoint (on
+//! the UDP port with the number of the server port of its first configured
+//! flow) and maintains a single QUIC connection with every peer. Each flow is
+//! mapped to its own bidirectional stream on that connection, so that a large
+//! message on one flow (e.g. a state sync chunk) does not delay the messages
+//! of the other flows (e.g. consensus artifacts).
+//!
+//! Connections are authenticated with the node TLS certificates, using the
+//! same TLS configuration as the TCP backend. As with TCP, the node with the
+//! smaller node ID is the client: it connects to the peer and opens one
+//! stream per flow, announcing the flow tag in the first bytes of the stream.
+//! The server identifies the peer by its certificate and the flow by the
+//! announced tag. When a peer is removed, its connection is closed on both
+//! sides, so that (as with TCP) it can only reconnect once it is added again.
+//!
+//! Established streams are handed over to the data plane via `on_connect()`,
+//! just like TLS streams, so the message framing, heartbeats, send queues and
+//! client callbacks are the same for both backends. When a stream fails, the
+//! data plane calls `on_disconnect()` as usual and the client reopens the
+//! stream, reconnecting to the peer if the connection itself is gone.
+//!
+//! The QUIC backend module implements the QUIC specific control plane
+//! functionality for [`TransportImpl`](../types/struct.TransportImpl.html).
+
+use crate::control_plane::{CONNECT_RETRY_SECONDS, TLS_HANDSHAKE_TIMEOUT_SECONDS};
+use crate::types::{ConnectionRole, TransportImpl};
+use futures::future::{AbortHandle, Abortable, Aborted};
+use futures::StreamExt;
+use ic_crypto::node_id_from_cert_subject_common_name;
+use ic_crypto_tls_interfaces::{AllowedClients, TlsPublicKeyCert};
+use ic_logger::{info, warn};
+use ic_protobuf::registry::node::v1::NodeRecord;
+use ic_types::{
+    transport::{FlowId, FlowTag, TransportErrorCode},
+    NodeId,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+/// The server name the client connects to. It is irrelevant, as the server is
+/// authenticated by its node ID and certificate in the registry.
+const QUIC_SERVER_NAME: &str = "domain.is-irrelevant-as-hostname-verification-is.disabled";
+
+/// The application error code used when closing connections.
+const QUIC_CLOSE_CODE: u32 = 0;
+
+/// Implementation for the QUIC backend of the transport control plane
+impl TransportImpl {
+    /// Binds the QUIC endpoint and starts the task accepting connections on
+    /// it. Connections are only accepted once there are allowed clients, see
+    /// `refresh_quic_server_config()`.
+    pub(crate) fn init_quic_endpoint(
+        &self,
+    ) -> Result<(quinn::Endpoint, AbortHandle), TransportErrorCode> {
+        let server_port = self
+            .config
+            .p2p_flows
+            .first()
+            .ok_or(TransportErrorCode::FlowNotFound)?
+            .server_port;
+        let local_addr = SocketAddr::new(self.node_ip, server_port);
+
+        // Binding registers the UDP socket with the runtime.
+        let _guard = self.tokio_runtime.enter();
+        let (endpoint, incoming) = quinn::Endpoint::builder().bind(&local_addr).map_err(|e| {
+            warn!(
+                self.log,
+                "QuicBackend::init_quic_endpoint(): Failed to bind: local_addr = {:?} {:?}",
+                local_addr,
+                e
+            );
+            TransportErrorCode::ServerSocketBindFailed
+        })?;
+        let accept_task = self.spawn_quic_accept_task(incoming);
+        Ok((endpoint, accept_task))
+    }
+
+    /// Updates the server configuration of the QUIC endpoint with the current
+    /// allowed clients and registry version. Stops accepting connections if
+    /// there are no allowed clients.
+    pub(crate) fn refresh_quic_server_config(&self, endpoint: &quinn::Endpoint) {
+        let allowed_clients = self.allowed_clients.read().unwrap().clone();
+        let registry_version = *self.registry_version.read().unwrap();
+        let server_config = match AllowedClients::new_with_nodes(allowed_clients) {
+            // We are the client of all our peers.
+            Err(_) => None,
+            Ok(allowed_clients) => match self
+                .crypto
+                .quic_server_config(allowed_clients, registry_version)
+            {
+                Ok(tls_config) => {
+                    let mut server_config = quinn::ServerConfig::default();
+                    server_config.crypto = Arc::new(tls_config.into_rustls());
+                    Some(server_config)
+                }
+                Err(e) => {
+                    warn!(
+                        self.log,
+                        "QuicBackend::refresh_quic_server_config(): node_id = {:?}, \
+                         registry_version = {:?}, error = {:?}",
+                        self.node_id,
+                        registry_version,
+                        e
+                    );
+                    None
+                }
+            },
+        };
+        endpoint.set_server_config(server_config);
+    }
+
+    /// Closes the QUIC connection to a peer, if any.
+    pub(crate) fn close_quic_connection(&self, peer_id: &NodeId) {
+        if let Some(connection) = self.quic_connections.lock().unwrap().remove(peer_id) {
+            connection.close(quinn::VarInt::from_u32(QUIC_CLOSE_CODE), b"peer removed");
+        }
+    }
+
+    /// Returns the address of the QUIC endpoint of a peer, i.e. the address of
+    /// its first flow endpoint.
+    pub(crate) fn quic_peer_addr(peer_record: &NodeRecord) -> Option<SocketAddr> {
+        peer_record
+            .p2p_flow_endpoints
+            .iter()
+            .find_map(|flow_endpoint| flow_endpoint.endpoint.as_ref())
+            .and_then(|endpoint| {
+                IpAddr::from_str(endpoint.ip_addr.as_str())
+                    .ok()
+                    .map(|ip| SocketAddr::new(ip, endpoint.port as u16))
+            })
+    }
+
+    /// Starts the async task to accept the incoming QUIC connections.
+    fn spawn_quic_accept_task(&self, mut incoming: quinn::Incoming) -> AbortHandle {
+        let weak_self = self.weak_self.read().unwrap().clone();
+        let tokio_runtime = self.tokio_runtime.clone();
+        let accept_task = async move {
+            while let Some(connecting) = incoming.next().await {
+                // If the TransportImpl has been deleted, abort.
+                let arc_self = match weak_self.upgrade() {
+                    Some(arc_self) => arc_self,
+                    _ => return,
+                };
+                tokio_runtime.spawn(async move {
+                    arc_self.accept_quic_connection(connecting).await;
+                });
+            }
+        };
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let log_cl = self.log.clone();
+        self.tokio_runtime.spawn(async move {
+            if let Err(Aborted) = Abortable::new(accept_task, abort_registration).await {
+                warn!(log_cl, "QuicBackend: accept task aborted");
+            }
+        });
+        abort_handle
+    }
+
+    /// Completes the handshake of an incoming QUIC connection and accepts the
+    /// flow streams opened by the peer, until the connection is closed.
+    async fn accept_quic_connection(&self, connecting: quinn::Connecting) {
+        let peer_addr = connecting.remote_address();
+        let handshake_timeout = Duration::from_secs(TLS_HANDSHAKE_TIMEOUT_SECONDS);
+        let quinn::NewConnection {
+            connection,
+            mut bi_streams,
+            ..
+        } = match timeout(handshake_timeout, connecting).await {
+            Ok(Ok(new_connection)) => new_connection,
+            Ok(Err(e)) => {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "QuicBackend::accept_quic_connection(): handshake failed: \
+                     node_id = {:?}, peer_addr = {:?}, error = {:?}",
+                    self.node_id,
+                    peer_addr,
+                    e
+                );
+                return;
+            }
+            Err(_) => {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "QuicBackend::accept_quic_connection(): handshake timed out: \
+                     node_id = {:?}, peer_addr = {:?}",
+                    self.node_id,
+                    peer_addr
+                );
+                return;
+            }
+        };
+
+        let peer_id = match self.quic_peer_id(&connection) {
+            Ok(peer_id) if self.allowed_clients.read().unwrap().contains(&peer_id) => peer_id,
+            _ => {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "QuicBackend::accept_quic_connection(): peer not allowed: \
+                     node_id = {:?}, peer_addr = {:?}",
+                    self.node_id,
+                    peer_addr
+                );
+                connection.close(quinn::VarInt::from_u32(QUIC_CLOSE_CODE), b"not allowed");
+                return;
+            }
+        };
+
+        // Keep the connection, so that it is closed when the peer is removed.
+        let stable_id = connection.stable_id();
+        self.quic_connections
+            .lock()
+            .unwrap()
+            .insert(peer_id, connection);
+
+        while let Some(stream) = bi_streams.next().await {
+            let (send_stream, mut recv_stream) = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    info!(
+                        self.log,
+                        "QuicBackend::accept_quic_connection(): connection closed: \
+                         node_id = {:?}, peer_id = {:?}, error = {:?}",
+                        self.node_id,
+                        peer_id,
+                        e
+                    );
+                    break;
+                }
+            };
+
+            let mut flow_tag = [0u8; 4];
+            match timeout(handshake_timeout, recv_stream.read_exact(&mut flow_tag)).await {
+                Ok(Ok(())) => (),
+                _ => {
+                    warn!(
+                        every_n_seconds => 30,
+                        self.log,
+                        "QuicBackend::accept_quic_connection(): failed to read flow tag: \
+                         node_id = {:?}, peer_id = {:?}",
+                        self.node_id,
+                        peer_id
+                    );
+                    continue;
+                }
+            }
+            let flow_id = FlowId {
+                peer_id,
+                flow_tag: FlowTag::from(u32::from_le_bytes(flow_tag)),
+            };
+            if let Err(e) = self
+                .on_connect(
+                    flow_id,
+                    ConnectionRole::Server,
+                    peer_addr,
+                    Box::new(recv_stream),
+                    Box::new(send_stream),
+                )
+                .await
+            {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "QuicBackend::accept_quic_connection(): failed to add flow: \
+                     node_id = {:?}, flow = {:?}, error = {:?}",
+                    self.node_id,
+                    flow_id,
+                    e
+                );
+            }
+        }
+        self.forget_quic_connection(&peer_id, stable_id);
+    }
+
+    /// Removes the connection with the given stable ID from the connections,
+    /// unless it has been replaced by a new connection to the peer already.
+    fn forget_quic_connection(&self, peer_id: &NodeId, stable_id: usize) {
+        let mut connections = self.quic_connections.lock().unwrap();
+        if let Some(connection) = connections.get(peer_id) {
+            if connection.stable_id() == stable_id {
+                connections.remove(peer_id);
+            }
+        }
+    }
+
+    /// Determines the node ID of the peer of an established connection from
+    /// the certificate it presented during the handshake.
+    fn quic_peer_id(&self, connection: &quinn::Connection) -> Result<NodeId, TransportErrorCode> {
+        let cert = connection
+            .authentication_data()
+            .peer_certificates
+            .and_then(|chain| chain.iter().next().cloned())
+            .ok_or(TransportErrorCode::PeerTlsInfoNotFound)?;
+        TlsPublicKeyCert::new_from_der(cert.0)
+            .map_err(|e| format!("{:?}", e))
+            .and_then(|cert| {
+                node_id_from_cert_subject_common_name(&cert).map_err(|e| format!("{:?}", e))
+            })
+            .map_err(|e| {
+                warn!(
+                    self.log,
+                    "QuicBackend::quic_peer_id(): malformed peer certificate: \
+                     node_id = {:?}, error = {}",
+                    self.node_id,
+                    e
+                );
+                TransportErrorCode::PeerTlsInfoNotFound
+            })
+    }
+
+    /// Spawn a task that tries to open the stream of a flow with a peer
+    /// (forever, or until the stream is established or peer is removed)
+    pub(crate) fn spawn_quic_connect_task(
+        &self,
+        flow_tag: FlowTag,
+        peer_id: NodeId,
+        peer_addr: SocketAddr,
+    ) -> AbortHandle {
+        let weak_self = self.weak_self.read().unwrap().clone();
+        let connect_task = async move {
+            let mut retries: u32 = 0;
+            loop {
+                retries += 1;
+                // If the TransportImpl has been deleted, abort.
+                let arc_self = match weak_self.upgrade() {
+                    Some(arc_self) => arc_self,
+                    _ => return,
+                };
+                match arc_self.open_quic_flow(flow_tag, peer_id, peer_addr).await {
+                    Ok(()) => {
+                        info!(
+                            arc_self.log,
+                            "QuicBackend::open_quic_flow(): Successful: peer = {:?}/{:?}, \
+                             flow = {:?}, retries = {}",
+                            peer_id,
+                            peer_addr,
+                            flow_tag,
+                            retries,
+                        );
+                        return;
+                    }
+                    Err(e) => {
+                        info!(
+                            every_n_seconds => 300,
+                            arc_self.log,
+                            "QuicBackend::open_quic_flow(): failed: peer = {:?}/{:?}, \
+                             flow = {:?}, err = {:?}, retries = {}",
+                            peer_id,
+                            peer_addr,
+                            flow_tag,
+                            e,
+                            retries
+                        );
+                    }
+                }
+                sleep(Duration::from_secs(CONNECT_RETRY_SECONDS)).await;
+            }
+        };
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let log_cl = self.log.clone();
+        self.tokio_runtime.spawn(async move {
+            if let Err(Aborted) = Abortable::new(connect_task, abort_registration).await {
+                warn!(log_cl, "QuicBackend: connect task aborted");
+            }
+        });
+        abort_handle
+    }
+
+    /// Opens the stream of a flow with a peer and passes it to the data plane.
+    async fn open_quic_flow(
+        &self,
+        flow_tag: FlowTag,
+        peer_id: NodeId,
+        peer_addr: SocketAddr,
+    ) -> Result<(), TransportErrorCode> {
+        let connection = self.quic_connection(peer_id, peer_addr).await?;
+        let (mut send_stream, recv_stream) = match connection.open_bi().await {
+            Ok(stream) => stream,
+            Err(_) => {
+                // The connection is gone, the next attempt reconnects.
+                self.forget_quic_connection(&peer_id, connection.stable_id());
+                return Err(TransportErrorCode::FlowConnectionDown);
+            }
+        };
+        send_stream
+            .write_all(&flow_tag.get().to_le_bytes())
+            .await
+            .map_err(|_| TransportErrorCode::FlowConnectionDown)?;
+
+        self.on_connect(
+            FlowId { peer_id, flow_tag },
+            ConnectionRole::Client,
+            peer_addr,
+            Box::new(recv_stream),
+            Box::new(send_stream),
+        )
+        .await
+    }
+
+    /// Returns the QUIC connection to a peer, connecting to the peer if there
+    /// is no connection yet.
+    async fn quic_connection(
+        &self,
+        peer_id: NodeId,
+        peer_addr: SocketAddr,
+    ) -> Result<quinn::Connection, TransportErrorCode> {
+        if let Some(connection) = self.quic_connections.lock().unwrap().get(&peer_id) {
+            return Ok(connection.clone());
+        }
+
+        let endpoint = self
+            .client_map
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|client_state| client_state.quic_endpoint.clone())
+            .ok_or(TransportErrorCode::TransportClientNotFound)?;
+        let registry_version = *self.registry_version.read().unwrap();
+        let tls_config = self
+            .crypto
+            .quic_client_config(peer_id, registry_version)
+            .map_err(|e| {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "QuicBackend::quic_connection(): node_id = {:?}, peer_id = {:?}, \
+                     error = {:?}",
+                    self.node_id,
+                    peer_id,
+                    e
+                );
+                TransportErrorCode::PeerTlsInfoNotFound
+            })?;
+        let client_config = quinn::ClientConfig {
+            transport: Default::default(),
+            crypto: Arc::new(tls_config.into_rustls()),
+        };
+        let connecting = endpoint
+            .connect_with(client_config, &peer_addr, QUIC_SERVER_NAME)
+            .map_err(|_| TransportErrorCode::ConnectOsError)?;
+        let quinn::NewConnection { connection, .. } = timeout(
+            Duration::from_secs(TLS_HANDSHAKE_TIMEOUT_SECONDS),
+            connecting,
+        )
+        .await
+        .map_err(|_| TransportErrorCode::TimeoutExpired)?
+        .map_err(|_| TransportErrorCode::ServerDown)?;
+
+        // Another flow may have connected in the meantime, in which case its
+        // connection is used (and ours is closed when dropped).
+        Ok(self
+            .quic_connections
+            .lock()
+            .unwrap()
+            .entry(peer_id)
+            .or_insert(connection)
+            .clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_protobuf::registry::node::v1::{
+        connection_endpoint::Protocol, ConnectionEndpoint, FlowEndpoint,
+    };
+
+    fn flow_endpoint(flow_tag: u32, ip_addr: &str, port: u32) -> FlowEndpoint {
+        FlowEndpoint {
+            flow_tag,
+            endpoint: Some(ConnectionEndpoint {
+                ip_addr: ip_addr.to_string(),
+                port,
+                protocol: Protocol::P2p1Tls13 as i32,
+            }),
+        }
+    }
+
+    #[test]
+    fn quic_peer_addr_is_the_address_of_the_first_flow_endpoint() {
+        let mut peer_record: NodeRecord = Default::default();
+        peer_record.p2p_flow_endpoints.push(FlowEndpoint {
+            flow_tag: 1,
+            endpoint: None,
+        });
+        peer_record
+            .p2p_flow_endpoints
+            .push(flow_endpoint(2, "10.0.0.1", 4100));
+        peer_record
+            .p2p_flow_endpoints
+            .push(flow_endpoint(3, "10.0.0.1", 4101));
+
+        assert_eq!(
+            TransportImpl::quic_peer_addr(&peer_record),
+            Some(SocketAddr::from_str("10.0.0.1:4100").unwrap())
+        );
+    }
+
+    #[test]
+    fn quic_peer_addr_is_none_without_valid_flow_endpoint() {
+        let mut peer_record: NodeRecord = Default::default();
+        assert_eq!(TransportImpl::quic_peer_addr(&peer_record), None);
+
+        peer_record
+            .p2p_flow_endpoints
+            .push(flow_endpoint(1, "not an ip", 4100));
+        assert_eq!(TransportImpl::quic_peer_addr(&peer_record), None);
+    }
+}
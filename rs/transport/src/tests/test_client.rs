@@ -44,7 +44,7 @@ use ic_transport::transport::create_transport;
 use ic_types::transport::TransportErrorCode;
 use ic_types::{
     transport::{
-        FlowId, FlowTag, TransportConfig, TransportFlowConfig, TransportPayload,
+        FlowId, FlowTag, TransportBackend, TransportConfig, TransportFlowConfig, TransportPayload,
         TransportStateChange,
     },
     NodeId, PrincipalId, RegistryVersion, SubnetId,
@@ -473,6 +473,7 @@ fn generate_config_and_registry(node_id: &NodeId) -> ConfigAndRecords {
                         queue_size: 1024,
                     },
                 ],
+                transport_backend: TransportBackend::Tcp,
             });
         }
 
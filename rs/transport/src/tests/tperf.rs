@@ -53,8 +53,8 @@ use ic_protobuf::registry::node::v1::{
 use ic_transport::transport::create_transport;
 use ic_types::{
     transport::{
-        FlowId, FlowTag, TransportConfig, TransportErrorCode, TransportFlowConfig,
-        TransportPayload, TransportStateChange,
+        FlowId, FlowTag, TransportBackend, TransportConfig, TransportErrorCode,
+        TransportFlowConfig, TransportPayload, TransportStateChange,
     },
     NodeId, RegistryVersion,
 };
@@ -634,6 +634,7 @@ fn generate_config_and_registry(
             server_port: FLOW_PORT as u16,
             queue_size: 8192,
         }],
+        transport_backend: TransportBackend::Tcp,
     };
 
     let mut node_records = Vec::new();
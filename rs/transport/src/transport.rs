@@ -75,10 +75,10 @@ use ic_metrics::MetricsRegistry;
 use ic_protobuf::registry::node::v1::NodeRecord;
 use ic_types::transport::{FlowTag, TransportConfig, TransportErrorCode, TransportPayload};
 use ic_types::{NodeId, RegistryVersion};
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::net::IpAddr;
 use std::str::FromStr;
-use std::sync::{Arc, RwLock, Weak};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use tokio::runtime::Handle;
 
 impl TransportImpl {
@@ -100,6 +100,7 @@ impl TransportImpl {
             config,
             allowed_clients: Arc::new(RwLock::new(BTreeSet::<NodeId>::new())),
             crypto,
+            quic_connections: Mutex::new(HashMap::new()),
             registry_version: Arc::new(RwLock::new(registry_version)),
             tokio_runtime,
             data_plane_metrics: DataPlaneMetrics::new(metrics_registry.clone()),
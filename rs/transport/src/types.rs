@@ -15,7 +15,8 @@ use std::collections::{BTreeSet, HashMap};
 use std::fmt::{self, Debug, Formatter};
 use std::net::IpAddr;
 use std::net::SocketAddr;
-use std::sync::{Arc, RwLock, Weak};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::runtime::Handle;
 use tokio::time::Duration;
 
@@ -75,6 +76,13 @@ pub(crate) struct TransportHeader {
     pub(crate) payload_length: u32, // Serialized little endian.
 }
 
+/// The read half of an established flow connection: the read half of a TLS
+/// stream (TCP backend) or a QUIC receive stream (QUIC backend).
+pub(crate) type FlowReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// The write half of an established flow connection.
+pub(crate) type FlowWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
 /// Transport implementation state struct. The control and data planes provide
 /// implementations for this struct.
 pub(crate) struct TransportImpl {
@@ -94,6 +102,9 @@ pub(crate) struct TransportImpl {
     pub registry_version: Arc<RwLock<RegistryVersion>>,
     /// Reference to the crypto component
     pub crypto: Arc<dyn TlsHandshake + Send + Sync>,
+    /// QUIC connections to the peers (QUIC backend only), shared by all flows
+    /// with the peer
+    pub quic_connections: Mutex<HashMap<NodeId, quinn::Connection>>,
 
     /// Data plane metrics
     pub data_plane_metrics: DataPlaneMetrics,
@@ -128,6 +139,8 @@ pub(crate) struct ClientState {
     pub peer_map: HashMap<NodeId, PeerState>,
     /// Event handler to report back to the transport client
     pub event_handler: Arc<dyn AsyncTransportEventHandler>,
+    /// The QUIC endpoint, if the QUIC backend is used
+    pub quic_endpoint: Option<quinn::Endpoint>,
 }
 
 /// State about the server ports we are listening on
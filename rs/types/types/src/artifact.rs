@@ -19,7 +19,7 @@ use crate::{
     filetree_sync::{FileTreeSyncArtifact, FileTreeSyncId},
     messages::{MessageId, SignedRequestBytes},
     p2p::GossipAdvert,
-    CryptoHashOfState, Height, Time,
+    CryptoHashOfState, Height, NodeId, Time,
 };
 use derive_more::{AsMut, AsRef, From, TryInto};
 use ic_protobuf::p2p::v1 as pb;
@@ -33,6 +33,7 @@ pub use crate::{
         certification::CertificationMessage,
         dkg::Message as DkgMessage,
         ecdsa::{EcdsaMessage, EcdsaMessageAttribute, EcdsaMessageHash},
+        equivocation::{EquivocationEvidence, EquivocationKind},
         ConsensusMessage, ConsensusMessageAttribute,
     },
     messages::SignedIngress,
@@ -50,6 +51,7 @@ pub enum Artifact {
     EcdsaMessage(EcdsaMessage),
     FileTreeSync(FileTreeSyncArtifact),
     StateSync(StateSyncMessage),
+    EquivocationEvidence(EquivocationEvidence),
 }
 
 /// Artifact attribute type.
@@ -63,6 +65,7 @@ pub enum ArtifactAttribute {
     EcdsaMessage(EcdsaMessageAttribute),
     FileTreeSync(FileTreeSyncAttribute),
     StateSync(StateSyncAttribute),
+    EquivocationEvidence(EquivocationEvidenceAttribute),
 }
 
 /// Artifact identifier type.
@@ -76,6 +79,7 @@ pub enum ArtifactId {
     EcdsaMessage(EcdsaMessageId),
     FileTreeSync(FileTreeSyncId),
     StateSync(StateSyncArtifactId),
+    EquivocationEvidence(EquivocationEvidenceId),
 }
 
 /// Artifact tags is used to select an artifact subtype when we do not have
@@ -90,6 +94,7 @@ pub enum ArtifactTag {
     EcdsaArtifact,
     FileTreeSyncArtifact,
     StateSyncArtifact,
+    EquivocationEvidenceArtifact,
 }
 
 impl std::fmt::Display for ArtifactTag {
@@ -105,6 +110,7 @@ impl std::fmt::Display for ArtifactTag {
                 ArtifactTag::EcdsaArtifact => "ECDSA",
                 ArtifactTag::FileTreeSyncArtifact => "FileTreeSync",
                 ArtifactTag::StateSyncArtifact => "StateSync",
+                ArtifactTag::EquivocationEvidenceArtifact => "EquivocationEvidence",
             }
         )
     }
@@ -120,6 +126,7 @@ impl From<&ArtifactId> for ArtifactTag {
             ArtifactId::EcdsaMessage(_) => ArtifactTag::EcdsaArtifact,
             ArtifactId::FileTreeSync(_) => ArtifactTag::FileTreeSyncArtifact,
             ArtifactId::StateSync(_) => ArtifactTag::StateSyncArtifact,
+            ArtifactId::EquivocationEvidence(_) => ArtifactTag::EquivocationEvidenceArtifact,
         }
     }
 }
@@ -136,6 +143,7 @@ impl From<&Artifact> for ArtifactTag {
             Artifact::EcdsaMessage(_) => ArtifactTag::EcdsaArtifact,
             Artifact::FileTreeSync(_) => ArtifactTag::FileTreeSyncArtifact,
             Artifact::StateSync(_) => ArtifactTag::StateSyncArtifact,
+            Artifact::EquivocationEvidence(_) => ArtifactTag::EquivocationEvidenceArtifact,
         }
     }
 }
@@ -462,6 +470,31 @@ pub struct DkgMessageAttribute {
 
 pub type EcdsaMessageId = EcdsaMessageHash;
 
+// -----------------------------------------------------------------------------
+// Equivocation evidence artifacts
+
+/// Identifier of a piece of evidence of equivocation.
+pub type EquivocationEvidenceId = CryptoHashOf<EquivocationEvidence>;
+
+/// The equivocation evidence attribute used by the priority function: only
+/// the first evidence per node and kind of equivocation is needed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EquivocationEvidenceAttribute {
+    pub signer: NodeId,
+    pub kind: EquivocationKind,
+    pub height: Height,
+}
+
+impl From<&EquivocationEvidence> for EquivocationEvidenceAttribute {
+    fn from(evidence: &EquivocationEvidence) -> Self {
+        Self {
+            signer: evidence.signer(),
+            kind: evidence.kind(),
+            height: evidence.height(),
+        }
+    }
+}
+
 // ------------------------------------------------------------------------------
 // StateSync artifacts.
 
@@ -19,7 +19,7 @@ use crate::{
     artifact::{Artifact, StateSyncMessage},
     consensus::{
         certification::CertificationMessage, dkg::Message as DkgMessage, ecdsa::EcdsaMessage,
-        ConsensusMessage,
+        equivocation::EquivocationEvidence, ConsensusMessage,
     },
     crypto::CryptoHash,
     messages::SignedIngress,
@@ -39,7 +39,8 @@ pub enum ArtifactErrorCode {
 
 /// The chunk type.
 pub type ChunkId = Id<ArtifactChunk, u32>;
-const CHUNKID_UNIT_CHUNK: u32 = 0;
+/// The ID of the only chunk of single-chunked artifacts.
+pub const CHUNKID_UNIT_CHUNK: u32 = 0;
 
 /// The data contained in an artifact chunk.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -92,6 +93,7 @@ pub enum SingleChunked {
     Certification,
     Dkg,
     Ecdsa,
+    EquivocationEvidence,
 }
 
 /// Interface providing access to artifact chunks.
@@ -133,6 +135,9 @@ chunkable_artifact_impl! {DkgMessage, |self|
 chunkable_artifact_impl! {EcdsaMessage, |self|
     ArtifactChunkData::UnitChunkData(Artifact::EcdsaMessage(*self))
 }
+chunkable_artifact_impl! {EquivocationEvidence, |self|
+    ArtifactChunkData::UnitChunkData(Artifact::EquivocationEvidence(*self))
+}
 
 impl ChunkableArtifact for StateSyncMessage {
     fn get_chunk(self: Box<Self>, chunk_id: ChunkId) -> Option<ArtifactChunk> {
@@ -18,6 +18,7 @@ pub mod certification;
 pub mod dkg;
 pub mod ecdsa;
 mod ecdsa_refs;
+pub mod equivocation;
 pub mod hashed;
 mod payload;
 pub mod thunk;
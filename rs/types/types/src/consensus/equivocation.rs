@@ -0,0 +1,217 @@
+//! Evidence of equivocation by block makers and notaries.
+use super::{
+    BlockProposal, FinalizationContent, FinalizationShare, HasHeight, HasRank, MultiSignatureShare,
+    NotarizationContent, NotarizationShare, Rank,
+};
+use crate::{
+    crypto::{CryptoHash, CryptoHashOf, IndividualMultiSig, IndividualMultiSigOf, Signed},
+    Height, NodeId, PrincipalId, ReplicaVersion,
+};
+use ic_protobuf::types::v1 as pb;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The role a node equivocated in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EquivocationKind {
+    BlockMaker,
+    Notary,
+}
+
+impl EquivocationKind {
+    /// Returns the name of the role, for labelling metrics and reports.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EquivocationKind::BlockMaker => "block_maker",
+            EquivocationKind::Notary => "notary",
+        }
+    }
+}
+
+impl fmt::Display for EquivocationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Two artifacts signed by the same node that an honest node never signs
+/// both of. Both artifacts carry the signature of the node, so the evidence
+/// can be verified by anyone against the node signing keys in the registry.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EquivocationEvidence {
+    /// Two different proposals of the same block maker at the same height and
+    /// rank.
+    BlockMaker {
+        first: BlockProposal,
+        second: BlockProposal,
+    },
+    /// A finalization share and a notarization share of the same notary for
+    /// different blocks at the same height. A notary only creates a
+    /// finalization share for a block if it did not notarize any other block
+    /// at that height.
+    Notary {
+        finalization_share: FinalizationShare,
+        notarization_share: NotarizationShare,
+    },
+}
+
+impl EquivocationEvidence {
+    /// Returns the equivocating node.
+    pub fn signer(&self) -> NodeId {
+        match self {
+            EquivocationEvidence::BlockMaker { first, .. } => first.signature.signer,
+            EquivocationEvidence::Notary {
+                finalization_share, ..
+            } => finalization_share.signature.signer,
+        }
+    }
+
+    /// Returns the height the node equivocated at.
+    pub fn height(&self) -> Height {
+        match self {
+            EquivocationEvidence::BlockMaker { first, .. } => first.height(),
+            EquivocationEvidence::Notary {
+                finalization_share, ..
+            } => finalization_share.height(),
+        }
+    }
+
+    /// Returns the rank of the equivocating proposals, if the node
+    /// equivocated as a block maker.
+    pub fn rank(&self) -> Option<Rank> {
+        match self {
+            EquivocationEvidence::BlockMaker { first, .. } => Some(first.rank()),
+            EquivocationEvidence::Notary { .. } => None,
+        }
+    }
+
+    /// Returns the role the node equivocated in.
+    pub fn kind(&self) -> EquivocationKind {
+        match self {
+            EquivocationEvidence::BlockMaker { .. } => EquivocationKind::BlockMaker,
+            EquivocationEvidence::Notary { .. } => EquivocationKind::Notary,
+        }
+    }
+}
+
+impl From<&EquivocationEvidence> for pb::EquivocationEvidence {
+    fn from(evidence: &EquivocationEvidence) -> Self {
+        let evidence = match evidence {
+            EquivocationEvidence::BlockMaker { first, second } => {
+                pb::equivocation_evidence::Evidence::BlockMaker(pb::BlockMakerEquivocation {
+                    first: Some(first.into()),
+                    second: Some(second.into()),
+                })
+            }
+            EquivocationEvidence::Notary {
+                finalization_share,
+                notarization_share,
+            } => pb::equivocation_evidence::Evidence::Notary(pb::NotaryEquivocation {
+                finalization_share: Some(finalization_share.into()),
+                notarization_share: Some(notarization_share.into()),
+            }),
+        };
+        Self {
+            evidence: Some(evidence),
+        }
+    }
+}
+
+impl TryFrom<pb::EquivocationEvidence> for EquivocationEvidence {
+    type Error = String;
+    fn try_from(evidence: pb::EquivocationEvidence) -> Result<Self, Self::Error> {
+        fn required<T>(value: Option<T>, field: &str) -> Result<T, String> {
+            value.ok_or_else(|| format!("Equivocation evidence without {}", field))
+        }
+        match required(evidence.evidence, "evidence")? {
+            pb::equivocation_evidence::Evidence::BlockMaker(block_maker) => {
+                Ok(EquivocationEvidence::BlockMaker {
+                    first: BlockProposal::try_from(required(block_maker.first, "first")?)?,
+                    second: BlockProposal::try_from(required(block_maker.second, "second")?)?,
+                })
+            }
+            pb::equivocation_evidence::Evidence::Notary(notary) => {
+                Ok(EquivocationEvidence::Notary {
+                    finalization_share: FinalizationShare::try_from(required(
+                        notary.finalization_share,
+                        "finalization_share",
+                    )?)?,
+                    notarization_share: NotarizationShare::try_from(required(
+                        notary.notarization_share,
+                        "notarization_share",
+                    )?)?,
+                })
+            }
+        }
+    }
+}
+
+impl From<&NotarizationShare> for pb::NotarizationShare {
+    fn from(share: &NotarizationShare) -> Self {
+        Self {
+            version: share.content.version.to_string(),
+            height: share.content.height.get(),
+            block: share.content.block.clone().get().0,
+            signature: share.signature.signature.clone().get().0,
+            signer: share.signature.signer.get().into_vec(),
+        }
+    }
+}
+
+impl TryFrom<pb::NotarizationShare> for NotarizationShare {
+    type Error = String;
+    fn try_from(share: pb::NotarizationShare) -> Result<Self, Self::Error> {
+        Ok(Signed {
+            content: NotarizationContent {
+                version: ReplicaVersion::try_from(share.version.as_str()).map_err(|e| {
+                    format!("NotarizationShare replica version failed to parse {:?}", e)
+                })?,
+                height: Height::from(share.height),
+                block: CryptoHashOf::from(CryptoHash(share.block)),
+            },
+            signature: multi_signature_share(share.signature, share.signer)?,
+        })
+    }
+}
+
+impl From<&FinalizationShare> for pb::FinalizationShare {
+    fn from(share: &FinalizationShare) -> Self {
+        Self {
+            version: share.content.version.to_string(),
+            height: share.content.height.get(),
+            block: share.content.block.clone().get().0,
+            signature: share.signature.signature.clone().get().0,
+            signer: share.signature.signer.get().into_vec(),
+        }
+    }
+}
+
+impl TryFrom<pb::FinalizationShare> for FinalizationShare {
+    type Error = String;
+    fn try_from(share: pb::FinalizationShare) -> Result<Self, Self::Error> {
+        Ok(Signed {
+            content: FinalizationContent {
+                version: ReplicaVersion::try_from(share.version.as_str()).map_err(|e| {
+                    format!("FinalizationShare replica version failed to parse {:?}", e)
+                })?,
+                height: Height::from(share.height),
+                block: CryptoHashOf::from(CryptoHash(share.block)),
+            },
+            signature: multi_signature_share(share.signature, share.signer)?,
+        })
+    }
+}
+
+fn multi_signature_share<T>(
+    signature: Vec<u8>,
+    signer: Vec<u8>,
+) -> Result<MultiSignatureShare<T>, String> {
+    Ok(MultiSignatureShare {
+        signature: IndividualMultiSigOf::from(IndividualMultiSig(signature)),
+        signer: NodeId::from(
+            PrincipalId::try_from(signer)
+                .map_err(|e| format!("Couldn't parse the signer principal id: {:?}", e))?,
+        ),
+    })
+}
@@ -38,6 +38,29 @@ pub struct TransportConfig {
 
     /// P2P specific config. In future, this will be made more generic.
     pub p2p_flows: Vec<TransportFlowConfig>,
+
+    /// The protocol used to exchange messages with peers.
+    #[serde(default)]
+    pub transport_backend: TransportBackend,
+}
+
+/// The protocol the transport uses to exchange messages with peers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportBackend {
+    /// TLS over TCP, with one TCP connection per flow and peer.
+    Tcp,
+
+    /// QUIC, with one connection per peer and one stream per flow. The QUIC
+    /// endpoint listens on the UDP port with the number of the server port of
+    /// the first configured flow.
+    Quic,
+}
+
+impl Default for TransportBackend {
+    fn default() -> Self {
+        TransportBackend::Tcp
+    }
 }
 
 /// Per-flow config
@@ -218,11 +218,14 @@ step 3.B Upgrade replica version to a working version || time (
 )
 
 step 3.C Calculate recovery height and state hash. || time (
-    echo ic-cup-explorer "$NNS_URL" "$TARGET_SUBNET"
-    OUTPUT=$(ic-cup-explorer "$NNS_URL" "$TARGET_SUBNET")
+    echo ic-cup-explorer --subnet-id "$TARGET_SUBNET" fetch "$NNS_URL"
+    # The explorer fails if the nodes disagree, e.g. when some of them are
+    # behind, but still reports the latest CUP, which is all we need here.
+    OUTPUT=$(ic-cup-explorer --subnet-id "$TARGET_SUBNET" fetch "$NNS_URL" || true)
     echo "ic-cup-explorer output: $OUTPUT"
-    HEIGHT=$(echo "$OUTPUT" | grep 'HEIGHT:' | grep -oE "[0-9]*")
-    STATE_HASH=$(echo "$OUTPUT" | grep 'HASH:' | sed 's/HASH://' | xargs)
+    LATEST=$(echo "$OUTPUT" | sed -n '/^Latest state:/,$p')
+    HEIGHT=$(echo "$LATEST" | grep 'HEIGHT:' | grep -oE "[0-9]*")
+    STATE_HASH=$(echo "$LATEST" | grep 'HASH:' | sed 's/HASH://' | xargs)
     RECOVERY_HEIGHT=$((HEIGHT + 100))
     setvar STATE_HASH "$STATE_HASH"
     setvar LAST_HEIGHT_IN_HEX "$(printf "%016x" "$HEIGHT")"